@@ -0,0 +1,79 @@
+use classfile::jar::{JarEntry, JarReader};
+use classfile::{
+    ClassFileResult, ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags, JImageReader,
+};
+use std::io::{Cursor, Write};
+use std::time::{Duration, Instant};
+use test_helpers::java_home;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const ITERATIONS: u32 = 5;
+
+/// Builds a jar of the classes of `java.base` from the JDK used to compile the test data.
+fn java_base_jar() -> Vec<u8> {
+    let mut jimage = JImageReader::open_jdk(java_home!()).unwrap();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for resource in jimage.resources().unwrap() {
+        if resource.module == "java.base" && resource.is_class() {
+            let data = jimage.read(&resource).unwrap();
+            writer
+                .start_file(resource.name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(&data).unwrap();
+        }
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn rewrite(entry: &JarEntry) -> ClassFileResult<Vec<u8>> {
+    let reader = ClassReader::new(&entry.data, ClassReaderFlags::None)?;
+    ClassWriter::new(ClassWriterFlags::None).write(&reader)
+}
+
+/// The number of classes rewritten successfully, out of all classes.
+type Counts = (usize, usize);
+
+fn bench(
+    name: &str,
+    jar: &[u8],
+    run: impl Fn(&mut JarReader<Cursor<&[u8]>>) -> Counts,
+) -> Duration {
+    let mut best = Duration::MAX;
+    let mut counts = (0, 0);
+    for _ in 0..ITERATIONS {
+        let mut reader = JarReader::new(Cursor::new(jar)).unwrap();
+        let start = Instant::now();
+        counts = run(&mut reader);
+        best = best.min(start.elapsed());
+    }
+    println!("{name}: {} of {} classes in {best:?}", counts.0, counts.1);
+    best
+}
+
+fn main() {
+    let jar = java_base_jar();
+
+    let sequential = bench("sequential", &jar, |reader| {
+        let mut counts = (0, 0);
+        for entry in reader.entries() {
+            let entry = entry.unwrap();
+            if entry.is_class() {
+                counts.0 += rewrite(&entry).is_ok() as usize;
+                counts.1 += 1;
+            }
+        }
+        counts
+    });
+    let parallel = bench("parallel", &jar, |reader| {
+        let results = reader
+            .par_map_classes(|entry| Ok(rewrite(&entry).is_ok()))
+            .unwrap();
+        (results.iter().filter(|&&ok| ok).count(), results.len())
+    });
+    println!(
+        "speedup on {} threads: {:.2}x",
+        rayon::current_num_threads(),
+        sequential.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -2,10 +2,16 @@ use bitflags::bitflags;
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct ClassAccess: u16 {
         const Public = 0x0001;
         const Final = 0x0010;
         const Super = 0x0020;
+        /// The Valhalla name for [`Super`](ClassAccess::Super). Classes without it are value
+        /// classes.
+        #[cfg(feature = "preview")]
+        const Identity = 0x0020;
         const Interface = 0x0200;
         const Abstract = 0x0400;
         const Synthetic = 0x1000;
@@ -17,6 +23,8 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct FieldAccess: u16 {
         const Public = 0x0001;
         const Private = 0x0002;
@@ -25,6 +33,10 @@ bitflags! {
         const Final = 0x0010;
         const Volatile = 0x0040;
         const Transient = 0x0080;
+        /// A field that must be initialized before the `super` constructor call, as in value
+        /// classes.
+        #[cfg(feature = "preview")]
+        const StrictInit = 0x0800;
         const Synthetic = 0x1000;
         const Enum = 0x4000;
     }
@@ -32,6 +44,8 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct MethodAccess: u16 {
         const Public = 0x0001;
         const Private = 0x0002;
@@ -50,6 +64,8 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct ParameterAccess: u16 {
         const Final = 0x0010;
         const Synthetic = 0x1000;
@@ -59,6 +75,8 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct InnerClassAccess : u16 {
         const Public = 0x0001;
         const Private = 0x0002;
@@ -75,6 +93,8 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct ModuleAccess: u16 {
         const Open = 0x0020;
         const Synthetic = 0x1000;
@@ -84,6 +104,8 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct ModuleRequireAccess: u16 {
         const Transitive = 0x0020;
         const StaticPhase = 0x0040;
@@ -94,8 +116,23 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct ModuleRelationAccess: u16 {
         const Synthetic = 0x1000;
         const Mandated = 0x8000;
     }
 }
+
+bitflags! {
+    /// The flags of the JDK's `ModuleResolution` attribute.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
+    pub struct ModuleResolution: u16 {
+        const DoNotResolveByDefault = 0x0001;
+        const WarnDeprecated = 0x0002;
+        const WarnDeprecatedForRemoval = 0x0004;
+        const WarnIncubating = 0x0008;
+    }
+}
@@ -0,0 +1,513 @@
+use crate::{
+    buffer_class_events, BufferedClassEvents, ClassAccess, ClassEvent, ClassEventSource,
+    ClassFileError, ClassFileResult, FieldAccess, InnerClassAccess, MethodAccess, MethodEvent,
+    Opcode,
+};
+use java_string::{JavaStr, JavaString};
+use std::collections::{HashMap, HashSet};
+
+/// The visibility of a class or member, from the least to the most visible.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Visibility {
+    Private,
+    Default,
+    Protected,
+    Public,
+}
+
+impl Visibility {
+    fn from_access(access: u16) -> Visibility {
+        if access & 0x0001 != 0 {
+            Visibility::Public
+        } else if access & 0x0004 != 0 {
+            Visibility::Protected
+        } else if access & 0x0002 != 0 {
+            Visibility::Private
+        } else {
+            Visibility::Default
+        }
+    }
+
+    fn access_bits(self) -> u16 {
+        match self {
+            Visibility::Private => 0x0002,
+            Visibility::Default => 0,
+            Visibility::Protected => 0x0004,
+            Visibility::Public => 0x0001,
+        }
+    }
+}
+
+/// A change to the access flags of a class or member, made by an [`AccessTransformer`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct AccessChange {
+    pub visibility: Option<Visibility>,
+    /// Only changes the visibility if it makes the class or member more visible.
+    pub widen_only: bool,
+    /// Adds the final modifier if `Some(true)`, or removes it if `Some(false)`.
+    pub make_final: Option<bool>,
+}
+
+impl AccessChange {
+    /// Combines two changes to the same class or member, keeping the most visible access and
+    /// preferring to remove the final modifier.
+    pub fn merge(self, other: AccessChange) -> AccessChange {
+        AccessChange {
+            visibility: self.visibility.max(other.visibility),
+            widen_only: self.widen_only && other.widen_only,
+            make_final: match (self.make_final, other.make_final) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (make_final, None) | (None, make_final) => make_final,
+                (Some(true), Some(true)) => Some(true),
+            },
+        }
+    }
+
+    fn apply(self, access: u16) -> u16 {
+        let mut access = access;
+        if let Some(visibility) = self.visibility {
+            if !self.widen_only || visibility > Visibility::from_access(access) {
+                access = (access & !0x0007) | visibility.access_bits();
+            }
+        }
+        match self.make_final {
+            Some(true) => access | 0x0010,
+            Some(false) => access & !0x0010,
+            None => access,
+        }
+    }
+}
+
+/// Changes the access of classes, fields and methods, such as to make them public or non-final for
+/// modding or testing. See [`transform_access`].
+///
+/// Rules can be read from Forge access transformer files with [`read_access_transformer`] and from
+/// Fabric access widener files with [`read_access_widener`].
+#[derive(Debug, Clone, Default)]
+pub struct AccessTransformer {
+    classes: HashMap<JavaString, AccessChange>,
+    fields: HashMap<JavaString, HashMap<JavaString, AccessChange>>,
+    /// The methods of each class, by their name followed by their descriptor.
+    methods: HashMap<JavaString, HashMap<JavaString, AccessChange>>,
+    all_fields: HashMap<JavaString, AccessChange>,
+    all_methods: HashMap<JavaString, AccessChange>,
+}
+
+impl AccessTransformer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Changes the access of a class, including its entries in the `InnerClasses` attributes of
+    /// all classes. Protected classes are public and private classes are package-private outside
+    /// of the `InnerClasses` attribute.
+    pub fn add_class(&mut self, name: impl Into<JavaString>, change: AccessChange) {
+        merge_change(self.classes.entry(name.into()).or_default(), change);
+    }
+
+    pub fn add_field(
+        &mut self,
+        owner: impl Into<JavaString>,
+        name: impl Into<JavaString>,
+        change: AccessChange,
+    ) {
+        let fields = self.fields.entry(owner.into()).or_default();
+        merge_change(fields.entry(name.into()).or_default(), change);
+    }
+
+    pub fn add_method(
+        &mut self,
+        owner: impl Into<JavaString>,
+        name: &JavaStr,
+        desc: &JavaStr,
+        change: AccessChange,
+    ) {
+        let mut key = name.to_owned();
+        key.push_java_str(desc);
+        let methods = self.methods.entry(owner.into()).or_default();
+        merge_change(methods.entry(key).or_default(), change);
+    }
+
+    pub fn add_all_fields(&mut self, owner: impl Into<JavaString>, change: AccessChange) {
+        merge_change(self.all_fields.entry(owner.into()).or_default(), change);
+    }
+
+    /// Changes the access of all methods of a class, other than its static initializer.
+    pub fn add_all_methods(&mut self, owner: impl Into<JavaString>, change: AccessChange) {
+        merge_change(self.all_methods.entry(owner.into()).or_default(), change);
+    }
+
+    fn field_change(&self, owner: &JavaStr, name: &JavaStr) -> Option<AccessChange> {
+        let field = self
+            .fields
+            .get(owner)
+            .and_then(|fields| fields.get(name))
+            .copied();
+        merge_options(field, self.all_fields.get(owner).copied())
+    }
+
+    fn method_change(
+        &self,
+        owner: &JavaStr,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> Option<AccessChange> {
+        let mut key = name.to_owned();
+        key.push_java_str(desc);
+        let method = self
+            .methods
+            .get(owner)
+            .and_then(|methods| methods.get(&key))
+            .copied();
+        let all_methods = self
+            .all_methods
+            .get(owner)
+            .copied()
+            .filter(|_| name != "<clinit>");
+        merge_options(method, all_methods)
+    }
+}
+
+fn merge_change(existing: &mut AccessChange, change: AccessChange) {
+    *existing = if *existing == AccessChange::default() {
+        change
+    } else {
+        existing.merge(change)
+    };
+}
+
+fn merge_options(a: Option<AccessChange>, b: Option<AccessChange>) -> Option<AccessChange> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.merge(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Reads a class, changing the access of its classes and members as given by the transformer.
+/// Calls to private methods of the class which are no longer private are changed from
+/// `invokespecial` to `invokevirtual`, or to `invokeinterface` in interfaces.
+pub fn transform_access<'class, S>(
+    source: S,
+    transformer: &AccessTransformer,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    let mut events = buffer_class_events(source)?;
+    let mut this_class = None;
+    let mut is_interface = false;
+
+    for event in &mut events.0 {
+        match event {
+            ClassEvent::Class(class) => {
+                is_interface = class.access.contains(ClassAccess::Interface);
+                if let Some(change) = transformer.classes.get(class.name.as_ref()) {
+                    let access = change.apply(class.access.bits());
+                    // a class file can only be public or package-private
+                    let access = if access & 0x0005 != 0 {
+                        (access & !0x0007) | 0x0001
+                    } else {
+                        access & !0x0007
+                    };
+                    class.access = ClassAccess::from_bits_retain(access);
+                }
+                this_class = Some(class.name.clone());
+            }
+            ClassEvent::InnerClasses(inner_classes) => {
+                for inner_class in &mut inner_classes.0 {
+                    if let Some(change) = transformer.classes.get(inner_class.name.as_ref()) {
+                        inner_class.access = InnerClassAccess::from_bits_retain(
+                            change.apply(inner_class.access.bits()),
+                        );
+                    }
+                }
+            }
+            ClassEvent::Fields(fields) => {
+                let Some(this_class) = &this_class else {
+                    continue;
+                };
+                for field in &mut fields.0 {
+                    if let Some(change) = transformer.field_change(this_class, &field.name) {
+                        field.access =
+                            FieldAccess::from_bits_retain(change.apply(field.access.bits()));
+                    }
+                }
+            }
+            ClassEvent::Methods(methods) => {
+                let Some(this_class) = &this_class else {
+                    continue;
+                };
+                let mut now_virtual = HashSet::new();
+                for method in &mut methods.0 {
+                    let Some(change) =
+                        transformer.method_change(this_class, &method.name, &method.desc)
+                    else {
+                        continue;
+                    };
+                    let was_private = method.access.contains(MethodAccess::Private);
+                    method.access =
+                        MethodAccess::from_bits_retain(change.apply(method.access.bits()));
+                    if was_private
+                        && !method.access.contains(MethodAccess::Private)
+                        && !method.access.contains(MethodAccess::Static)
+                        && !method.name.starts_with('<')
+                    {
+                        now_virtual.insert((method.name.clone(), method.desc.clone()));
+                    }
+                }
+                if now_virtual.is_empty() {
+                    continue;
+                }
+                let virtual_opcode = if is_interface {
+                    Opcode::InvokeInterface
+                } else {
+                    Opcode::InvokeVirtual
+                };
+                for method in &mut methods.0 {
+                    for event in &mut method.events.0 {
+                        if let MethodEvent::MethodInsn {
+                            opcode: opcode @ Opcode::InvokeSpecial,
+                            owner,
+                            name,
+                            desc,
+                            ..
+                        } = event
+                        {
+                            if owner == this_class
+                                && now_virtual.contains(&(name.clone(), desc.clone()))
+                            {
+                                *opcode = virtual_opcode;
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// Reads a Forge access transformer file, with lines such as `public-f net.example.Foo field`.
+///
+/// Each line has a visibility of `public`, `protected`, `default` or `private`, optionally
+/// followed by `-f` to remove or `+f` to add the final modifier, then the binary name of a class,
+/// then optionally a field name, a method name and descriptor, `*` for all fields or `*()` for all
+/// methods. Text after a `#` is a comment.
+pub fn read_access_transformer(input: &str) -> ClassFileResult<AccessTransformer> {
+    let mut transformer = AccessTransformer::new();
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let parts: Vec<_> = line.split('#').next().unwrap().split_whitespace().collect();
+        let (modifier, class, member) = match parts.as_slice() {
+            [] => continue,
+            [modifier, class] => (*modifier, *class, None),
+            [modifier, class, member] => (*modifier, *class, Some(*member)),
+            _ => return Err(bad_access_rules(line_number, "bad access transformer line")),
+        };
+
+        let (visibility, make_final) = match modifier.len().checked_sub(2) {
+            Some(split) if modifier.ends_with("-f") => (&modifier[..split], Some(false)),
+            Some(split) if modifier.ends_with("+f") => (&modifier[..split], Some(true)),
+            _ => (modifier, None),
+        };
+        let visibility = match visibility {
+            "public" => Visibility::Public,
+            "protected" => Visibility::Protected,
+            "default" => Visibility::Default,
+            "private" => Visibility::Private,
+            _ => return Err(bad_access_rules(line_number, "unknown visibility")),
+        };
+        let change = AccessChange {
+            visibility: Some(visibility),
+            widen_only: false,
+            make_final,
+        };
+
+        let class = JavaString::from(class.replace('.', "/"));
+        match member {
+            None => transformer.add_class(class, change),
+            Some("*") => transformer.add_all_fields(class, change),
+            Some("*()") => transformer.add_all_methods(class, change),
+            Some(member) => match member.find('(') {
+                Some(desc_start) => transformer.add_method(
+                    class,
+                    JavaStr::from_str(&member[..desc_start]),
+                    JavaStr::from_str(&member[desc_start..]),
+                    change,
+                ),
+                None => transformer.add_field(class, member, change),
+            },
+        }
+    }
+    Ok(transformer)
+}
+
+/// Reads a Fabric access widener file, starting with an `accessWidener v1 <namespace>` or
+/// `accessWidener v2 <namespace>` header. Access wideners only make classes and members more
+/// visible: `accessible` makes them public, `extendable` makes classes public and methods
+/// protected and removes the final modifier, and `mutable` removes the final modifier from
+/// fields. The `transitive-` prefix is accepted and ignored.
+pub fn read_access_widener(input: &str) -> ClassFileResult<AccessTransformer> {
+    let mut transformer = AccessTransformer::new();
+    let mut has_header = false;
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let parts: Vec<_> = line.split('#').next().unwrap().split_whitespace().collect();
+        if parts.is_empty() {
+            continue;
+        }
+        if !has_header {
+            match parts.as_slice() {
+                ["accessWidener", "v1" | "v2", _] => {
+                    has_header = true;
+                    continue;
+                }
+                _ => {
+                    return Err(bad_access_rules(
+                        line_number,
+                        "expected access widener header",
+                    ))
+                }
+            }
+        }
+
+        let access = parts[0].strip_prefix("transitive-").unwrap_or(parts[0]);
+        let widen = |visibility, make_final| AccessChange {
+            visibility,
+            widen_only: true,
+            make_final,
+        };
+        match (access, &parts[1..]) {
+            ("accessible", ["class", class]) => {
+                transformer.add_class(*class, widen(Some(Visibility::Public), None))
+            }
+            ("extendable", ["class", class]) => {
+                transformer.add_class(*class, widen(Some(Visibility::Public), Some(false)))
+            }
+            ("accessible", ["method", owner, name, desc]) => transformer.add_method(
+                *owner,
+                JavaStr::from_str(name),
+                JavaStr::from_str(desc),
+                widen(Some(Visibility::Public), None),
+            ),
+            ("extendable", ["method", owner, name, desc]) => transformer.add_method(
+                *owner,
+                JavaStr::from_str(name),
+                JavaStr::from_str(desc),
+                widen(Some(Visibility::Protected), Some(false)),
+            ),
+            ("accessible", ["field", owner, name, _]) => {
+                transformer.add_field(*owner, *name, widen(Some(Visibility::Public), None))
+            }
+            ("mutable", ["field", owner, name, _]) => {
+                transformer.add_field(*owner, *name, widen(None, Some(false)))
+            }
+            _ => return Err(bad_access_rules(line_number, "bad access widener line")),
+        }
+    }
+    Ok(transformer)
+}
+
+fn bad_access_rules(line: usize, reason: &'static str) -> ClassFileError {
+    ClassFileError::BadAccessRules { line, reason }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ClassNode;
+    use crate::{
+        read_access_transformer, read_access_widener, transform_access, AccessChange, ClassAccess,
+        ClassFileError, ClassReader, ClassReaderFlags, InnerClassAccess, MethodAccess, Visibility,
+    };
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_access_transformer() {
+        let transformer = read_access_transformer(
+            "# make the inner class visible\n\
+             public TestInnerClass$Inner\n\
+             private+f HelloWorld main([Ljava/lang/String;)V\n\
+             public-f HelloWorld *()\n",
+        )
+        .unwrap();
+
+        let reader =
+            ClassReader::new(include_class!("TestInnerClass"), ClassReaderFlags::None).unwrap();
+        let class =
+            ClassNode::from_events(transform_access(&reader, &transformer).unwrap()).unwrap();
+        let inner = class
+            .inner_classes
+            .iter()
+            .find(|inner| inner.name.as_ref() == "TestInnerClass$Inner")
+            .unwrap();
+        assert!(inner.access.contains(InnerClassAccess::Public));
+        assert!(!inner.access.contains(InnerClassAccess::Private));
+
+        let reader = ClassReader::new(
+            include_class!("TestInnerClass$Inner"),
+            ClassReaderFlags::None,
+        )
+        .unwrap();
+        let class =
+            ClassNode::from_events(transform_access(&reader, &transformer).unwrap()).unwrap();
+        assert!(class.access.contains(ClassAccess::Public));
+
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let class =
+            ClassNode::from_events(transform_access(&reader, &transformer).unwrap()).unwrap();
+        for method in &class.methods {
+            // the specific rule is merged with the wildcard, keeping the most visible access
+            assert_eq!(
+                MethodAccess::Public,
+                method.access
+                    & (MethodAccess::Public | MethodAccess::Private | MethodAccess::Final),
+                "{}",
+                method.name
+            );
+        }
+
+        assert_eq!(
+            Err(ClassFileError::BadAccessRules {
+                line: 1,
+                reason: "unknown visibility"
+            }),
+            read_access_transformer("visible Foo").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_access_widener() {
+        let transformer = read_access_widener(
+            "accessWidener v2 named\n\
+             extendable class TestInnerClass$Inner\n\
+             transitive-accessible method HelloWorld main ([Ljava/lang/String;)V\n",
+        )
+        .unwrap();
+        let reader =
+            ClassReader::new(include_class!("TestInnerClass"), ClassReaderFlags::None).unwrap();
+        let class =
+            ClassNode::from_events(transform_access(&reader, &transformer).unwrap()).unwrap();
+        let inner = class
+            .inner_classes
+            .iter()
+            .find(|inner| inner.name.as_ref() == "TestInnerClass$Inner")
+            .unwrap();
+        assert!(inner
+            .access
+            .contains(InnerClassAccess::Public | InnerClassAccess::Static));
+        assert!(!inner.access.contains(InnerClassAccess::Private));
+
+        // widening never makes a member less visible
+        let change = AccessChange {
+            visibility: Some(Visibility::Protected),
+            widen_only: true,
+            make_final: None,
+        };
+        assert_eq!(0x0001, change.apply(0x0001));
+        assert_eq!(0x0004, change.apply(0x0002));
+
+        assert!(read_access_widener("accessible class Foo").is_err());
+    }
+}
@@ -0,0 +1,706 @@
+use crate::analysis::{Interpreter, Value};
+use crate::frame_computer::type_end;
+use crate::tree::{InsnHandle, InsnNode, MethodNode};
+use crate::{ClassFileError, ClassFileResult, Label, MethodAccess, Opcode};
+use java_string::{JavaStr, JavaString};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// The locals and operand stack before an instruction. Longs and doubles take up one element of
+/// the stack, but two locals, the second of which is an empty value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyzerFrame<V> {
+    pub locals: Vec<V>,
+    pub stack: Vec<V>,
+}
+
+impl<V: Value> AnalyzerFrame<V> {
+    fn pop(&mut self) -> ClassFileResult<V> {
+        self.stack.pop().ok_or(ClassFileError::OperandStackEmpty)
+    }
+
+    /// Pops a value which must take up one slot.
+    fn pop1(&mut self, opcode: Opcode) -> ClassFileResult<V> {
+        let value = self.pop()?;
+        if value.size() != 1 {
+            return Err(ClassFileError::BadOperandSize(opcode));
+        }
+        Ok(value)
+    }
+
+    fn pop_n(&mut self, n: usize) -> ClassFileResult<Vec<V>> {
+        if self.stack.len() < n {
+            return Err(ClassFileError::OperandStackEmpty);
+        }
+        Ok(self.stack.split_off(self.stack.len() - n))
+    }
+
+    fn push_all(&mut self, values: impl IntoIterator<Item = V>) {
+        self.stack.extend(values);
+    }
+
+    fn merge<'class, I>(&mut self, other: &Self, interpreter: &mut I) -> ClassFileResult<bool>
+    where
+        I: Interpreter<'class, Value = V>,
+    {
+        if self.stack.len() != other.stack.len() {
+            return Err(ClassFileError::OperandStackHeightMismatch {
+                expected: self.stack.len(),
+                actual: other.stack.len(),
+            });
+        }
+        let mut changed = false;
+        for (value, other) in self
+            .locals
+            .iter_mut()
+            .zip(&other.locals)
+            .chain(self.stack.iter_mut().zip(&other.stack))
+        {
+            let merged = interpreter.merge(value, other)?;
+            if merged != *value {
+                *value = merged;
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// Computes the [`AnalyzerFrame`] before each instruction of a method, by following the control
+/// flow from the start of the method and from each exception handler, and asking an
+/// [`Interpreter`] for the values.
+///
+/// Subroutines (`jsr` and `ret`) aren't supported, but can be inlined beforehand with
+/// [`MethodNode::inline_subroutines`].
+#[derive(Debug)]
+pub struct Analyzer<'class, I: Interpreter<'class>> {
+    interpreter: I,
+    handles: Vec<InsnHandle>,
+    indexes: HashMap<InsnHandle, usize>,
+    frames: Vec<Option<AnalyzerFrame<I::Value>>>,
+    successors: Vec<Vec<usize>>,
+    exception_successors: Vec<Vec<usize>>,
+}
+
+impl<'class, I: Interpreter<'class>> Analyzer<'class, I> {
+    pub fn new(interpreter: I) -> Self {
+        Analyzer {
+            interpreter,
+            handles: Vec::new(),
+            indexes: HashMap::new(),
+            frames: Vec::new(),
+            successors: Vec::new(),
+            exception_successors: Vec::new(),
+        }
+    }
+
+    pub fn interpreter(&self) -> &I {
+        &self.interpreter
+    }
+
+    pub fn interpreter_mut(&mut self) -> &mut I {
+        &mut self.interpreter
+    }
+
+    pub fn into_interpreter(self) -> I {
+        self.interpreter
+    }
+
+    /// Analyzes a method of the class `owner`, returning the frame before each instruction in the
+    /// order of the instruction list, or `None` for unreachable instructions. Errors are reported
+    /// as [`ClassFileError::AnalysisFailed`].
+    pub fn analyze(
+        &mut self,
+        owner: &JavaStr,
+        method: &MethodNode<'class>,
+    ) -> ClassFileResult<&[Option<AnalyzerFrame<I::Value>>]> {
+        let insns = &method.instructions;
+        self.handles = insns.handles().collect();
+        self.indexes = self
+            .handles
+            .iter()
+            .enumerate()
+            .map(|(index, handle)| (*handle, index))
+            .collect();
+        self.frames = vec![None; self.handles.len()];
+        self.successors = vec![Vec::new(); self.handles.len()];
+        self.exception_successors = vec![Vec::new(); self.handles.len()];
+        if method.label_creator.is_none() || self.handles.is_empty() {
+            return Ok(&self.frames);
+        }
+
+        let label_indexes: HashMap<Label, usize> = self
+            .handles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &handle)| match insns[handle] {
+                InsnNode::Label(label) => Some((label, index)),
+                _ => None,
+            })
+            .collect();
+        let label_index = |label: Label| {
+            label_indexes
+                .get(&label)
+                .copied()
+                .ok_or(ClassFileError::UnknownLabel(label))
+        };
+
+        // the try-catch blocks covering each instruction, with the index of their handler
+        let mut handlers = vec![Vec::new(); self.handles.len()];
+        for try_catch_block in &method.try_catch_blocks {
+            let handler = label_index(try_catch_block.handler)?;
+            let range = label_index(try_catch_block.start)?..label_index(try_catch_block.end)?;
+            for covered in &mut handlers[range] {
+                covered.push((try_catch_block, handler));
+            }
+        }
+
+        let is_instance_method = !method.access.contains(MethodAccess::Static);
+        let (arguments, return_type) = method_desc_types(&method.desc);
+        let expected_return = (return_type != "V").then(|| self.interpreter.new_value(return_type));
+        let initial_frame = self.initial_frame(owner, method, is_instance_method, &arguments);
+
+        self.frames[0] = Some(initial_frame);
+        let mut worklist = vec![0];
+        while let Some(index) = worklist.pop() {
+            let handle = self.handles[index];
+            let insn = &insns[handle];
+            let input = self.frames[index]
+                .clone()
+                .expect("instruction in worklist has no frame");
+
+            let mut frame = input.clone();
+            let result = self
+                .execute(&mut frame, handle, insn, expected_return.as_ref())
+                .and_then(|()| self.successors_of(index, insn, label_index));
+            let successors = result.map_err(|err| analysis_failed(index, &input, err))?;
+
+            for &(try_catch_block, handler) in &handlers[index] {
+                let exception = match &try_catch_block.ty {
+                    Some(ty) => ty.as_ref(),
+                    None => JavaStr::from_str("java/lang/Throwable"),
+                };
+                let exception = self
+                    .interpreter
+                    .new_exception_value(try_catch_block, exception);
+                for locals in [&input.locals, &frame.locals] {
+                    let handler_frame = AnalyzerFrame {
+                        locals: locals.clone(),
+                        stack: vec![exception.clone()],
+                    };
+                    self.merge_into(&mut worklist, handler, &handler_frame)?;
+                }
+                if !self.exception_successors[index].contains(&handler) {
+                    self.exception_successors[index].push(handler);
+                }
+            }
+
+            for &successor in &successors {
+                self.merge_into(&mut worklist, successor, &frame)?;
+            }
+            self.successors[index] = successors;
+        }
+
+        Ok(&self.frames)
+    }
+
+    fn initial_frame(
+        &mut self,
+        owner: &JavaStr,
+        method: &MethodNode<'class>,
+        is_instance_method: bool,
+        arguments: &[&JavaStr],
+    ) -> AnalyzerFrame<I::Value> {
+        let mut locals = Vec::new();
+        if is_instance_method {
+            let mut this = JavaString::from("L");
+            this.push_java_str(owner);
+            this.push(';');
+            locals.push(self.interpreter.new_parameter_value(true, 0, &this));
+        }
+        for argument in arguments {
+            let value =
+                self.interpreter
+                    .new_parameter_value(is_instance_method, locals.len(), argument);
+            let size = value.size();
+            locals.push(value);
+            if size == 2 {
+                locals.push(self.interpreter.new_empty_value(locals.len()));
+            }
+        }
+
+        let max_locals = method
+            .instructions
+            .iter()
+            .filter_map(|insn| match insn {
+                InsnNode::VarInsn { opcode, var_index } => Some(
+                    *var_index as usize
+                        + match opcode {
+                            Opcode::LLoad | Opcode::DLoad | Opcode::LStore | Opcode::DStore => 2,
+                            _ => 1,
+                        },
+                ),
+                InsnNode::IIncInsn { var_index, .. } => Some(*var_index as usize + 1),
+                _ => None,
+            })
+            .chain(method.maxs.map(|maxs| maxs.max_locals as usize))
+            .max()
+            .unwrap_or(0);
+        while locals.len() < max_locals {
+            locals.push(self.interpreter.new_empty_value(locals.len()));
+        }
+
+        AnalyzerFrame {
+            locals,
+            stack: Vec::new(),
+        }
+    }
+
+    /// The frames computed by the last call to [`analyze`](Analyzer::analyze).
+    pub fn frames(&self) -> &[Option<AnalyzerFrame<I::Value>>] {
+        &self.frames
+    }
+
+    /// The frame before the given instruction, or `None` if it's unreachable.
+    pub fn frame(&self, handle: InsnHandle) -> Option<&AnalyzerFrame<I::Value>> {
+        self.frames[self.index_of(handle)?].as_ref()
+    }
+
+    /// The index of the given instruction in the analyzed instruction list.
+    pub fn index_of(&self, handle: InsnHandle) -> Option<usize> {
+        self.indexes.get(&handle).copied()
+    }
+
+    /// The instructions of the analyzed method, in order.
+    pub fn handles(&self) -> &[InsnHandle] {
+        &self.handles
+    }
+
+    /// The indexes of the instructions which can be executed directly after the instruction at the
+    /// given index, not counting exception handlers.
+    pub fn successors(&self, index: usize) -> &[usize] {
+        &self.successors[index]
+    }
+
+    /// The indexes of the exception handlers which cover the instruction at the given index.
+    pub fn exception_successors(&self, index: usize) -> &[usize] {
+        &self.exception_successors[index]
+    }
+
+    fn merge_into(
+        &mut self,
+        worklist: &mut Vec<usize>,
+        index: usize,
+        frame: &AnalyzerFrame<I::Value>,
+    ) -> ClassFileResult<()> {
+        let changed = match &mut self.frames[index] {
+            Some(existing) => existing
+                .merge(frame, &mut self.interpreter)
+                .map_err(|err| analysis_failed(index, frame, err))?,
+            None => {
+                self.frames[index] = Some(frame.clone());
+                true
+            }
+        };
+        if changed && !worklist.contains(&index) {
+            worklist.push(index);
+        }
+        Ok(())
+    }
+
+    fn successors_of(
+        &self,
+        index: usize,
+        insn: &InsnNode<'class>,
+        label_index: impl Fn(Label) -> ClassFileResult<usize>,
+    ) -> ClassFileResult<Vec<usize>> {
+        let mut successors = Vec::new();
+        let falls_through = match insn {
+            InsnNode::Insn(
+                Opcode::IReturn
+                | Opcode::LReturn
+                | Opcode::FReturn
+                | Opcode::DReturn
+                | Opcode::AReturn
+                | Opcode::Return
+                | Opcode::AThrow,
+            ) => false,
+            InsnNode::JumpInsn { opcode, label } => {
+                successors.push(label_index(*label)?);
+                *opcode != Opcode::Goto
+            }
+            InsnNode::TableSwitchInsn { dflt, labels, .. } => {
+                successors.push(label_index(*dflt)?);
+                for label in labels {
+                    successors.push(label_index(*label)?);
+                }
+                false
+            }
+            InsnNode::LookupSwitchInsn { dflt, values } => {
+                successors.push(label_index(*dflt)?);
+                for (_, label) in values {
+                    successors.push(label_index(*label)?);
+                }
+                false
+            }
+            _ => true,
+        };
+        if falls_through {
+            if index + 1 == self.handles.len() {
+                return Err(ClassFileError::FallOffEndOfCode);
+            }
+            successors.insert(0, index + 1);
+        }
+        let mut unique = Vec::with_capacity(successors.len());
+        for successor in successors {
+            if !unique.contains(&successor) {
+                unique.push(successor);
+            }
+        }
+        Ok(unique)
+    }
+
+    fn execute(
+        &mut self,
+        frame: &mut AnalyzerFrame<I::Value>,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        expected_return: Option<&I::Value>,
+    ) -> ClassFileResult<()> {
+        let interpreter = &mut self.interpreter;
+        match insn {
+            InsnNode::Insn(opcode) => match opcode {
+                Opcode::Nop => {}
+                Opcode::AConstNull
+                | Opcode::IConstM1
+                | Opcode::IConst0
+                | Opcode::IConst1
+                | Opcode::IConst2
+                | Opcode::IConst3
+                | Opcode::IConst4
+                | Opcode::IConst5
+                | Opcode::LConst0
+                | Opcode::LConst1
+                | Opcode::FConst0
+                | Opcode::FConst1
+                | Opcode::FConst2
+                | Opcode::DConst0
+                | Opcode::DConst1 => {
+                    let value = interpreter.new_operation(handle, insn)?;
+                    frame.stack.push(value);
+                }
+                Opcode::IAStore
+                | Opcode::LAStore
+                | Opcode::FAStore
+                | Opcode::DAStore
+                | Opcode::AAStore
+                | Opcode::BAStore
+                | Opcode::CAStore
+                | Opcode::SAStore => {
+                    let value3 = frame.pop()?;
+                    let value2 = frame.pop()?;
+                    let value1 = frame.pop()?;
+                    interpreter.ternary_operation(handle, insn, &value1, &value2, &value3)?;
+                }
+                Opcode::Pop => {
+                    frame.pop1(*opcode)?;
+                }
+                Opcode::Pop2 => {
+                    if frame.pop()?.size() == 1 {
+                        frame.pop1(*opcode)?;
+                    }
+                }
+                Opcode::Dup => {
+                    let value1 = frame.pop1(*opcode)?;
+                    let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                    frame.push_all([value1, copy1]);
+                }
+                Opcode::DupX1 => {
+                    let value1 = frame.pop1(*opcode)?;
+                    let value2 = frame.pop1(*opcode)?;
+                    let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                    frame.push_all([copy1, value2, value1]);
+                }
+                Opcode::DupX2 => {
+                    let value1 = frame.pop1(*opcode)?;
+                    let value2 = frame.pop()?;
+                    let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                    if value2.size() == 1 {
+                        let value3 = frame.pop1(*opcode)?;
+                        frame.push_all([copy1, value3, value2, value1]);
+                    } else {
+                        frame.push_all([copy1, value2, value1]);
+                    }
+                }
+                Opcode::Dup2 => {
+                    let value1 = frame.pop()?;
+                    if value1.size() == 1 {
+                        let value2 = frame.pop1(*opcode)?;
+                        let copy2 = interpreter.copy_operation(handle, insn, &value2)?;
+                        let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                        frame.push_all([value2, value1, copy2, copy1]);
+                    } else {
+                        let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                        frame.push_all([value1, copy1]);
+                    }
+                }
+                Opcode::Dup2X1 => {
+                    let value1 = frame.pop()?;
+                    if value1.size() == 1 {
+                        let value2 = frame.pop1(*opcode)?;
+                        let value3 = frame.pop1(*opcode)?;
+                        let copy2 = interpreter.copy_operation(handle, insn, &value2)?;
+                        let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                        frame.push_all([copy2, copy1, value3, value2, value1]);
+                    } else {
+                        let value2 = frame.pop1(*opcode)?;
+                        let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                        frame.push_all([copy1, value2, value1]);
+                    }
+                }
+                Opcode::Dup2X2 => {
+                    let value1 = frame.pop()?;
+                    if value1.size() == 1 {
+                        let value2 = frame.pop1(*opcode)?;
+                        let value3 = frame.pop()?;
+                        let copy2 = interpreter.copy_operation(handle, insn, &value2)?;
+                        let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                        if value3.size() == 1 {
+                            let value4 = frame.pop1(*opcode)?;
+                            frame.push_all([copy2, copy1, value4, value3, value2, value1]);
+                        } else {
+                            frame.push_all([copy2, copy1, value3, value2, value1]);
+                        }
+                    } else {
+                        let value2 = frame.pop()?;
+                        let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                        if value2.size() == 1 {
+                            let value3 = frame.pop1(*opcode)?;
+                            frame.push_all([copy1, value3, value2, value1]);
+                        } else {
+                            frame.push_all([copy1, value2, value1]);
+                        }
+                    }
+                }
+                Opcode::Swap => {
+                    let value2 = frame.pop1(*opcode)?;
+                    let value1 = frame.pop1(*opcode)?;
+                    let copy2 = interpreter.copy_operation(handle, insn, &value2)?;
+                    let copy1 = interpreter.copy_operation(handle, insn, &value1)?;
+                    frame.push_all([copy2, copy1]);
+                }
+                Opcode::IReturn
+                | Opcode::LReturn
+                | Opcode::FReturn
+                | Opcode::DReturn
+                | Opcode::AReturn => {
+                    let value = frame.pop()?;
+                    interpreter.unary_operation(handle, insn, &value)?;
+                    if let Some(expected) = expected_return {
+                        interpreter.return_operation(handle, insn, &value, expected)?;
+                    }
+                }
+                Opcode::Return => {}
+                Opcode::IALoad
+                | Opcode::LALoad
+                | Opcode::FALoad
+                | Opcode::DALoad
+                | Opcode::AALoad
+                | Opcode::BALoad
+                | Opcode::CALoad
+                | Opcode::SALoad
+                | Opcode::IAdd
+                | Opcode::LAdd
+                | Opcode::FAdd
+                | Opcode::DAdd
+                | Opcode::ISub
+                | Opcode::LSub
+                | Opcode::FSub
+                | Opcode::DSub
+                | Opcode::IMul
+                | Opcode::LMul
+                | Opcode::FMul
+                | Opcode::DMul
+                | Opcode::IDiv
+                | Opcode::LDiv
+                | Opcode::FDiv
+                | Opcode::DDiv
+                | Opcode::IRem
+                | Opcode::LRem
+                | Opcode::FRem
+                | Opcode::DRem
+                | Opcode::IShl
+                | Opcode::LShl
+                | Opcode::IShr
+                | Opcode::LShr
+                | Opcode::IUShr
+                | Opcode::LUShr
+                | Opcode::IAnd
+                | Opcode::LAnd
+                | Opcode::IOr
+                | Opcode::LOr
+                | Opcode::IXor
+                | Opcode::LXor
+                | Opcode::LCmp
+                | Opcode::FCmpL
+                | Opcode::FCmpG
+                | Opcode::DCmpL
+                | Opcode::DCmpG => {
+                    let value2 = frame.pop()?;
+                    let value1 = frame.pop()?;
+                    let result = interpreter.binary_operation(handle, insn, &value1, &value2)?;
+                    frame.push_all(result);
+                }
+                _ => {
+                    // negations, conversions, arraylength, athrow and monitors
+                    let value = frame.pop()?;
+                    let result = interpreter.unary_operation(handle, insn, &value)?;
+                    frame.push_all(result);
+                }
+            },
+            InsnNode::BIPushInsn(_)
+            | InsnNode::SIPushInsn(_)
+            | InsnNode::LdcInsn(_)
+            | InsnNode::TypeInsn {
+                opcode: Opcode::New,
+                ..
+            }
+            | InsnNode::FieldInsn {
+                opcode: Opcode::GetStatic,
+                ..
+            } => {
+                let value = interpreter.new_operation(handle, insn)?;
+                frame.stack.push(value);
+            }
+            InsnNode::VarInsn { opcode, var_index } => {
+                let var_index = *var_index as usize;
+                match opcode {
+                    Opcode::ILoad
+                    | Opcode::LLoad
+                    | Opcode::FLoad
+                    | Opcode::DLoad
+                    | Opcode::ALoad => {
+                        let value =
+                            interpreter.copy_operation(handle, insn, &frame.locals[var_index])?;
+                        frame.stack.push(value);
+                    }
+                    Opcode::IStore
+                    | Opcode::LStore
+                    | Opcode::FStore
+                    | Opcode::DStore
+                    | Opcode::AStore => {
+                        let value = frame.pop()?;
+                        let value = interpreter.copy_operation(handle, insn, &value)?;
+                        let size = value.size();
+                        frame.locals[var_index] = value;
+                        if size == 2 {
+                            frame.locals[var_index + 1] =
+                                interpreter.new_empty_value(var_index + 1);
+                        }
+                        if var_index > 0 && frame.locals[var_index - 1].size() == 2 {
+                            frame.locals[var_index - 1] =
+                                interpreter.new_empty_value(var_index - 1);
+                        }
+                    }
+                    _ => return Err(ClassFileError::AnalyzerUnsupported("subroutines")),
+                }
+            }
+            InsnNode::IIncInsn { var_index, .. } => {
+                let var_index = *var_index as usize;
+                if let Some(value) =
+                    interpreter.unary_operation(handle, insn, &frame.locals[var_index])?
+                {
+                    frame.locals[var_index] = value;
+                }
+            }
+            InsnNode::FieldInsn {
+                opcode: Opcode::PutField,
+                ..
+            } => {
+                let value2 = frame.pop()?;
+                let value1 = frame.pop()?;
+                interpreter.binary_operation(handle, insn, &value1, &value2)?;
+            }
+            InsnNode::JumpInsn { opcode, .. } => match opcode {
+                Opcode::Goto => {}
+                Opcode::Jsr => return Err(ClassFileError::AnalyzerUnsupported("subroutines")),
+                Opcode::IfICmpEq
+                | Opcode::IfICmpNe
+                | Opcode::IfICmpLt
+                | Opcode::IfICmpGe
+                | Opcode::IfICmpGt
+                | Opcode::IfICmpLe
+                | Opcode::IfACmpEq
+                | Opcode::IfACmpNe => {
+                    let value2 = frame.pop()?;
+                    let value1 = frame.pop()?;
+                    interpreter.binary_operation(handle, insn, &value1, &value2)?;
+                }
+                _ => {
+                    let value = frame.pop()?;
+                    interpreter.unary_operation(handle, insn, &value)?;
+                }
+            },
+            InsnNode::MethodInsn { opcode, desc, .. } => {
+                let (arguments, _) = method_desc_types(desc);
+                let receiver = usize::from(*opcode != Opcode::InvokeStatic);
+                let values = frame.pop_n(receiver + arguments.len())?;
+                let result = interpreter.nary_operation(handle, insn, &values)?;
+                frame.push_all(result);
+            }
+            InsnNode::InvokeDynamicInsn { desc, .. } => {
+                let (arguments, _) = method_desc_types(desc);
+                let values = frame.pop_n(arguments.len())?;
+                let result = interpreter.nary_operation(handle, insn, &values)?;
+                frame.push_all(result);
+            }
+            InsnNode::MultiANewArrayInsn { dimensions, .. } => {
+                let values = frame.pop_n(*dimensions as usize)?;
+                let result = interpreter.nary_operation(handle, insn, &values)?;
+                frame.push_all(result);
+            }
+            InsnNode::NewArrayInsn(_)
+            | InsnNode::TypeInsn { .. }
+            | InsnNode::FieldInsn { .. }
+            | InsnNode::TableSwitchInsn { .. }
+            | InsnNode::LookupSwitchInsn { .. } => {
+                let value = frame.pop()?;
+                let result = interpreter.unary_operation(handle, insn, &value)?;
+                frame.push_all(result);
+            }
+            InsnNode::Label(_)
+            | InsnNode::LineNumber { .. }
+            | InsnNode::Frame(_)
+            | InsnNode::InsnAnnotations(_) => {}
+        }
+        Ok(())
+    }
+}
+
+fn analysis_failed<V: Debug>(
+    index: usize,
+    frame: &AnalyzerFrame<V>,
+    err: ClassFileError,
+) -> ClassFileError {
+    match err {
+        ClassFileError::AnalysisFailed { .. } => err,
+        _ => ClassFileError::AnalysisFailed {
+            index,
+            stack: format!("{:?}", frame.stack),
+            source: Box::new(err),
+        },
+    }
+}
+
+/// Splits a method descriptor into its argument types and return type.
+pub(crate) fn method_desc_types(desc: &JavaStr) -> (Vec<&JavaStr>, &JavaStr) {
+    let bytes = desc.as_bytes();
+    let mut arguments = Vec::new();
+    let mut index = 1;
+    while index < bytes.len() && bytes[index] != b')' {
+        let end = type_end(bytes, index);
+        arguments.push(&desc[index..end]);
+        index = end;
+    }
+    (arguments, &desc[(index + 1).min(desc.len())..])
+}
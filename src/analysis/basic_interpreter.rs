@@ -0,0 +1,326 @@
+use crate::analysis::{method_desc_types, Interpreter, Value};
+use crate::tree::{InsnHandle, InsnNode};
+use crate::{ClassFileResult, LdcConstant, Opcode};
+use java_string::JavaStr;
+
+/// The basic type of a value, as needed to know which instructions can operate on it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BasicValue {
+    /// An unset local, the second slot of a long or double, or the merge of incompatible values.
+    Uninitialized,
+    Int,
+    Float,
+    Long,
+    Double,
+    Reference,
+}
+
+impl BasicValue {
+    /// The value of a field descriptor, where `boolean`, `byte`, `char` and `short` are ints.
+    pub fn from_desc(desc: &JavaStr) -> BasicValue {
+        match desc.as_bytes().first() {
+            Some(b'Z' | b'B' | b'C' | b'S' | b'I') => BasicValue::Int,
+            Some(b'F') => BasicValue::Float,
+            Some(b'J') => BasicValue::Long,
+            Some(b'D') => BasicValue::Double,
+            Some(b'L' | b'[') => BasicValue::Reference,
+            _ => BasicValue::Uninitialized,
+        }
+    }
+}
+
+impl Value for BasicValue {
+    fn size(&self) -> usize {
+        match self {
+            BasicValue::Long | BasicValue::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// An [`Interpreter`] which computes the [`BasicValue`] of each value, without checking that the
+/// operands of each instruction are correct.
+#[derive(Debug, Default, Clone)]
+pub struct BasicInterpreter;
+
+impl BasicInterpreter {
+    pub fn new() -> Self {
+        BasicInterpreter
+    }
+}
+
+impl<'class> Interpreter<'class> for BasicInterpreter {
+    type Value = BasicValue;
+
+    fn new_value(&mut self, desc: &JavaStr) -> BasicValue {
+        BasicValue::from_desc(desc)
+    }
+
+    fn new_empty_value(&mut self, _local: usize) -> BasicValue {
+        BasicValue::Uninitialized
+    }
+
+    fn new_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+    ) -> ClassFileResult<BasicValue> {
+        Ok(match insn {
+            InsnNode::LdcInsn(constant) => match constant {
+                LdcConstant::Integer(_) => BasicValue::Int,
+                LdcConstant::Float(_) => BasicValue::Float,
+                LdcConstant::Long(_) => BasicValue::Long,
+                LdcConstant::Double(_) => BasicValue::Double,
+                LdcConstant::ConstantDynamic(constant) => BasicValue::from_desc(&constant.desc),
+                _ => BasicValue::Reference,
+            },
+            InsnNode::FieldInsn { desc, .. } => BasicValue::from_desc(desc),
+            _ => insn
+                .opcode()
+                .and_then(basic_result)
+                .unwrap_or(BasicValue::Reference),
+        })
+    }
+
+    fn copy_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        value: &BasicValue,
+    ) -> ClassFileResult<BasicValue> {
+        Ok(*value)
+    }
+
+    fn unary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        _value: &BasicValue,
+    ) -> ClassFileResult<Option<BasicValue>> {
+        Ok(match insn {
+            InsnNode::FieldInsn {
+                opcode: Opcode::GetField,
+                desc,
+                ..
+            } => Some(BasicValue::from_desc(desc)),
+            InsnNode::NewArrayInsn(_)
+            | InsnNode::TypeInsn {
+                opcode: Opcode::ANewArray | Opcode::CheckCast,
+                ..
+            } => Some(BasicValue::Reference),
+            _ => insn.opcode().and_then(basic_result),
+        })
+    }
+
+    fn binary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        _value1: &BasicValue,
+        _value2: &BasicValue,
+    ) -> ClassFileResult<Option<BasicValue>> {
+        Ok(insn.opcode().and_then(basic_result))
+    }
+
+    fn ternary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        _value1: &BasicValue,
+        _value2: &BasicValue,
+        _value3: &BasicValue,
+    ) -> ClassFileResult<()> {
+        Ok(())
+    }
+
+    fn nary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        _values: &[BasicValue],
+    ) -> ClassFileResult<Option<BasicValue>> {
+        Ok(match insn {
+            InsnNode::MethodInsn { desc, .. } | InsnNode::InvokeDynamicInsn { desc, .. } => {
+                let (_, return_type) = method_desc_types(desc);
+                (return_type != "V").then(|| BasicValue::from_desc(return_type))
+            }
+            _ => Some(BasicValue::Reference),
+        })
+    }
+
+    fn return_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        _value: &BasicValue,
+        _expected: &BasicValue,
+    ) -> ClassFileResult<()> {
+        Ok(())
+    }
+
+    fn merge(&mut self, value1: &BasicValue, value2: &BasicValue) -> ClassFileResult<BasicValue> {
+        Ok(if value1 == value2 {
+            *value1
+        } else {
+            BasicValue::Uninitialized
+        })
+    }
+}
+
+/// The value produced by an opcode whose result doesn't depend on its operands, or `None` if it
+/// produces no value.
+pub(crate) fn basic_result(opcode: Opcode) -> Option<BasicValue> {
+    Some(match opcode {
+        Opcode::IConstM1
+        | Opcode::IConst0
+        | Opcode::IConst1
+        | Opcode::IConst2
+        | Opcode::IConst3
+        | Opcode::IConst4
+        | Opcode::IConst5
+        | Opcode::BIPush
+        | Opcode::SIPush
+        | Opcode::IALoad
+        | Opcode::BALoad
+        | Opcode::CALoad
+        | Opcode::SALoad
+        | Opcode::IAdd
+        | Opcode::ISub
+        | Opcode::IMul
+        | Opcode::IDiv
+        | Opcode::IRem
+        | Opcode::INeg
+        | Opcode::IShl
+        | Opcode::IShr
+        | Opcode::IUShr
+        | Opcode::IAnd
+        | Opcode::IOr
+        | Opcode::IXor
+        | Opcode::IInc
+        | Opcode::L2i
+        | Opcode::F2i
+        | Opcode::D2i
+        | Opcode::I2b
+        | Opcode::I2c
+        | Opcode::I2s
+        | Opcode::LCmp
+        | Opcode::FCmpL
+        | Opcode::FCmpG
+        | Opcode::DCmpL
+        | Opcode::DCmpG
+        | Opcode::ArrayLength
+        | Opcode::Instanceof => BasicValue::Int,
+        Opcode::FConst0
+        | Opcode::FConst1
+        | Opcode::FConst2
+        | Opcode::FALoad
+        | Opcode::FAdd
+        | Opcode::FSub
+        | Opcode::FMul
+        | Opcode::FDiv
+        | Opcode::FRem
+        | Opcode::FNeg
+        | Opcode::I2f
+        | Opcode::L2f
+        | Opcode::D2f => BasicValue::Float,
+        Opcode::LConst0
+        | Opcode::LConst1
+        | Opcode::LALoad
+        | Opcode::LAdd
+        | Opcode::LSub
+        | Opcode::LMul
+        | Opcode::LDiv
+        | Opcode::LRem
+        | Opcode::LNeg
+        | Opcode::LShl
+        | Opcode::LShr
+        | Opcode::LUShr
+        | Opcode::LAnd
+        | Opcode::LOr
+        | Opcode::LXor
+        | Opcode::I2l
+        | Opcode::F2l
+        | Opcode::D2l => BasicValue::Long,
+        Opcode::DConst0
+        | Opcode::DConst1
+        | Opcode::DALoad
+        | Opcode::DAdd
+        | Opcode::DSub
+        | Opcode::DMul
+        | Opcode::DDiv
+        | Opcode::DRem
+        | Opcode::DNeg
+        | Opcode::I2d
+        | Opcode::L2d
+        | Opcode::F2d => BasicValue::Double,
+        Opcode::AConstNull
+        | Opcode::AALoad
+        | Opcode::New
+        | Opcode::NewArray
+        | Opcode::ANewArray
+        | Opcode::CheckCast
+        | Opcode::MultiANewArray => BasicValue::Reference,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{Analyzer, BasicInterpreter, BasicValue};
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{ClassFileError, ClassReader, ClassReaderFlags, Opcode};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_analyze_test_code() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let mut analyzer = Analyzer::new(BasicInterpreter::new());
+        for method in &class.methods {
+            let frames = analyzer.analyze(&class.name, method).unwrap();
+            assert!(frames.iter().all(Option::is_some), "{}", method.name);
+
+            for (index, &handle) in analyzer.handles().iter().enumerate() {
+                let frame = analyzer.frame(handle).unwrap();
+                match &method.instructions[handle] {
+                    InsnNode::Insn(Opcode::DReturn) => {
+                        assert_eq!(vec![BasicValue::Double], frame.stack);
+                    }
+                    InsnNode::Insn(Opcode::IReturn) => {
+                        assert_eq!(vec![BasicValue::Int], frame.stack);
+                    }
+                    InsnNode::Insn(Opcode::AReturn) => {
+                        assert_eq!(vec![BasicValue::Reference], frame.stack);
+                    }
+                    _ => {}
+                }
+                if !analyzer.exception_successors(index).is_empty() {
+                    for &handler in analyzer.exception_successors(index) {
+                        let handler = analyzer.frames()[handler].as_ref().unwrap();
+                        assert_eq!(vec![BasicValue::Reference], handler.stack);
+                    }
+                }
+            }
+        }
+
+        let loops = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "loops")
+            .unwrap();
+        let frames = analyzer.analyze(&class.name, loops).unwrap();
+        let first = frames[0].as_ref().unwrap();
+        assert_eq!(
+            vec![BasicValue::Reference, BasicValue::Reference],
+            first.locals[..2]
+        );
+        assert!(first.stack.is_empty());
+
+        let mut broken = loops.clone();
+        broken.instructions.push_front(InsnNode::Insn(Opcode::Pop));
+        assert!(matches!(
+            analyzer.analyze(&class.name, &broken),
+            Err(ClassFileError::AnalysisFailed { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+use crate::analysis::{method_desc_types, BasicInterpreter, BasicValue, Interpreter};
+use crate::tree::{InsnHandle, InsnNode};
+use crate::{ClassFileError, ClassFileResult, Opcode};
+use java_string::JavaStr;
+
+/// An [`Interpreter`] which computes the same values as a [`BasicInterpreter`], but fails with
+/// [`ClassFileError::BadOperand`] when an instruction is given an operand of the wrong basic type,
+/// such as `iadd` on a reference. The [`Analyzer`](crate::analysis::Analyzer) reports the index of
+/// the instruction and the stack before it.
+#[derive(Debug, Default, Clone)]
+pub struct BasicVerifier {
+    interpreter: BasicInterpreter,
+}
+
+impl BasicVerifier {
+    pub fn new() -> Self {
+        BasicVerifier::default()
+    }
+}
+
+fn check(expected: BasicValue, actual: &BasicValue) -> ClassFileResult<()> {
+    if expected != *actual {
+        return Err(ClassFileError::BadOperand {
+            expected,
+            actual: *actual,
+        });
+    }
+    Ok(())
+}
+
+/// The type of the operands of a load, store, arithmetic, comparison, conversion or return
+/// instruction, or of the value of an array store.
+fn operand_type(opcode: Opcode) -> BasicValue {
+    match opcode {
+        Opcode::LLoad
+        | Opcode::LStore
+        | Opcode::LAStore
+        | Opcode::LAdd
+        | Opcode::LSub
+        | Opcode::LMul
+        | Opcode::LDiv
+        | Opcode::LRem
+        | Opcode::LNeg
+        | Opcode::LAnd
+        | Opcode::LOr
+        | Opcode::LXor
+        | Opcode::L2i
+        | Opcode::L2f
+        | Opcode::L2d
+        | Opcode::LCmp
+        | Opcode::LReturn => BasicValue::Long,
+        Opcode::FLoad
+        | Opcode::FStore
+        | Opcode::FAStore
+        | Opcode::FAdd
+        | Opcode::FSub
+        | Opcode::FMul
+        | Opcode::FDiv
+        | Opcode::FRem
+        | Opcode::FNeg
+        | Opcode::F2i
+        | Opcode::F2l
+        | Opcode::F2d
+        | Opcode::FCmpL
+        | Opcode::FCmpG
+        | Opcode::FReturn => BasicValue::Float,
+        Opcode::DLoad
+        | Opcode::DStore
+        | Opcode::DAStore
+        | Opcode::DAdd
+        | Opcode::DSub
+        | Opcode::DMul
+        | Opcode::DDiv
+        | Opcode::DRem
+        | Opcode::DNeg
+        | Opcode::D2i
+        | Opcode::D2l
+        | Opcode::D2f
+        | Opcode::DCmpL
+        | Opcode::DCmpG
+        | Opcode::DReturn => BasicValue::Double,
+        Opcode::ALoad | Opcode::AStore | Opcode::AAStore | Opcode::AReturn => BasicValue::Reference,
+        _ => BasicValue::Int,
+    }
+}
+
+impl<'class> Interpreter<'class> for BasicVerifier {
+    type Value = BasicValue;
+
+    fn new_value(&mut self, desc: &JavaStr) -> BasicValue {
+        <BasicInterpreter as Interpreter<'class>>::new_value(&mut self.interpreter, desc)
+    }
+
+    fn new_empty_value(&mut self, local: usize) -> BasicValue {
+        <BasicInterpreter as Interpreter<'class>>::new_empty_value(&mut self.interpreter, local)
+    }
+
+    fn new_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+    ) -> ClassFileResult<BasicValue> {
+        self.interpreter.new_operation(handle, insn)
+    }
+
+    fn copy_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &BasicValue,
+    ) -> ClassFileResult<BasicValue> {
+        if let InsnNode::VarInsn { opcode, .. } = insn {
+            check(operand_type(*opcode), value)?;
+        }
+        self.interpreter.copy_operation(handle, insn, value)
+    }
+
+    fn unary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &BasicValue,
+    ) -> ClassFileResult<Option<BasicValue>> {
+        let expected = match insn {
+            InsnNode::FieldInsn {
+                opcode: Opcode::PutStatic,
+                desc,
+                ..
+            } => BasicValue::from_desc(desc),
+            InsnNode::FieldInsn { .. }
+            | InsnNode::JumpInsn {
+                opcode: Opcode::IfNull | Opcode::IfNonNull,
+                ..
+            }
+            | InsnNode::TypeInsn {
+                opcode: Opcode::CheckCast | Opcode::Instanceof,
+                ..
+            } => BasicValue::Reference,
+            InsnNode::Insn(
+                Opcode::ArrayLength | Opcode::AThrow | Opcode::MonitorEnter | Opcode::MonitorExit,
+            ) => BasicValue::Reference,
+            // negations, conversions and returns are named after their operand type
+            InsnNode::Insn(opcode) => operand_type(*opcode),
+            _ => BasicValue::Int,
+        };
+        check(expected, value)?;
+        self.interpreter.unary_operation(handle, insn, value)
+    }
+
+    fn binary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &BasicValue,
+        value2: &BasicValue,
+    ) -> ClassFileResult<Option<BasicValue>> {
+        let (expected1, expected2) = match insn {
+            InsnNode::FieldInsn { desc, .. } => {
+                (BasicValue::Reference, BasicValue::from_desc(desc))
+            }
+            InsnNode::JumpInsn {
+                opcode: Opcode::IfACmpEq | Opcode::IfACmpNe,
+                ..
+            } => (BasicValue::Reference, BasicValue::Reference),
+            InsnNode::Insn(
+                Opcode::IALoad
+                | Opcode::LALoad
+                | Opcode::FALoad
+                | Opcode::DALoad
+                | Opcode::AALoad
+                | Opcode::BALoad
+                | Opcode::CALoad
+                | Opcode::SALoad,
+            ) => (BasicValue::Reference, BasicValue::Int),
+            InsnNode::Insn(Opcode::LShl | Opcode::LShr | Opcode::LUShr) => {
+                (BasicValue::Long, BasicValue::Int)
+            }
+            InsnNode::Insn(opcode) => (operand_type(*opcode), operand_type(*opcode)),
+            _ => (BasicValue::Int, BasicValue::Int),
+        };
+        check(expected1, value1)?;
+        check(expected2, value2)?;
+        self.interpreter
+            .binary_operation(handle, insn, value1, value2)
+    }
+
+    fn ternary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &BasicValue,
+        value2: &BasicValue,
+        value3: &BasicValue,
+    ) -> ClassFileResult<()> {
+        check(BasicValue::Reference, value1)?;
+        check(BasicValue::Int, value2)?;
+        if let InsnNode::Insn(opcode) = insn {
+            check(operand_type(*opcode), value3)?;
+        }
+        self.interpreter
+            .ternary_operation(handle, insn, value1, value2, value3)
+    }
+
+    fn nary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        values: &[BasicValue],
+    ) -> ClassFileResult<Option<BasicValue>> {
+        let mut expected = Vec::with_capacity(values.len());
+        match insn {
+            InsnNode::MethodInsn { opcode, desc, .. } => {
+                if *opcode != Opcode::InvokeStatic {
+                    expected.push(BasicValue::Reference);
+                }
+                let (arguments, _) = method_desc_types(desc);
+                expected.extend(arguments.into_iter().map(BasicValue::from_desc));
+            }
+            InsnNode::InvokeDynamicInsn { desc, .. } => {
+                let (arguments, _) = method_desc_types(desc);
+                expected.extend(arguments.into_iter().map(BasicValue::from_desc));
+            }
+            _ => expected.resize(values.len(), BasicValue::Int),
+        }
+        for (expected, value) in expected.into_iter().zip(values) {
+            check(expected, value)?;
+        }
+        self.interpreter.nary_operation(handle, insn, values)
+    }
+
+    fn return_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        value: &BasicValue,
+        expected: &BasicValue,
+    ) -> ClassFileResult<()> {
+        check(*expected, value)
+    }
+
+    fn merge(&mut self, value1: &BasicValue, value2: &BasicValue) -> ClassFileResult<BasicValue> {
+        self.interpreter.merge(value1, value2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{Analyzer, BasicValue, BasicVerifier};
+    use crate::tree::{ClassNode, InsnNode, MethodNode};
+    use crate::{
+        ClassFileError, ClassReader, ClassReaderFlags, LabelCreator, MethodAccess, Opcode,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_basic_verifier() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let mut analyzer = Analyzer::new(BasicVerifier::new());
+        for method in &class.methods {
+            analyzer.analyze(&class.name, method).unwrap();
+        }
+
+        let mut method = MethodNode::new(
+            MethodAccess::Static,
+            JavaStr::from_str("add"),
+            JavaStr::from_str("()I"),
+        );
+        method.label_creator = Some(LabelCreator::default());
+        method.instructions.extend([
+            InsnNode::Insn(Opcode::IConst0),
+            InsnNode::Insn(Opcode::AConstNull),
+            InsnNode::Insn(Opcode::IAdd),
+            InsnNode::Insn(Opcode::IReturn),
+        ]);
+        let err = analyzer
+            .analyze(JavaStr::from_str("Test"), &method)
+            .unwrap_err();
+        assert_eq!(
+            ClassFileError::AnalysisFailed {
+                index: 2,
+                stack: "[Int, Reference]".to_owned(),
+                source: Box::new(ClassFileError::BadOperand {
+                    expected: BasicValue::Int,
+                    actual: BasicValue::Reference,
+                }),
+            },
+            err
+        );
+
+        method.instructions.clear();
+        method.instructions.extend([
+            InsnNode::Insn(Opcode::LConst0),
+            InsnNode::Insn(Opcode::IReturn),
+        ]);
+        assert!(analyzer
+            .analyze(JavaStr::from_str("Test"), &method)
+            .is_err());
+    }
+}
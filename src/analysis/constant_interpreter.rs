@@ -0,0 +1,622 @@
+use crate::analysis::{Analyzer, BasicInterpreter, BasicValue, Interpreter, Value};
+use crate::tree::{InsnHandle, InsnNode, MethodNode};
+use crate::{ClassFileResult, LdcConstant, Opcode};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+/// A value which may be known to be a constant, as computed by a [`ConstantInterpreter`].
+#[derive(Debug, Clone)]
+pub enum ConstantValue<'class> {
+    /// A value which isn't a known constant, with its basic type.
+    Unknown(BasicValue),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(Cow<'class, JavaStr>),
+    Null,
+}
+
+impl ConstantValue<'_> {
+    pub fn basic_value(&self) -> BasicValue {
+        match self {
+            ConstantValue::Unknown(value) => *value,
+            ConstantValue::Int(_) => BasicValue::Int,
+            ConstantValue::Long(_) => BasicValue::Long,
+            ConstantValue::Float(_) => BasicValue::Float,
+            ConstantValue::Double(_) => BasicValue::Double,
+            ConstantValue::String(_) | ConstantValue::Null => BasicValue::Reference,
+        }
+    }
+
+    pub fn is_constant(&self) -> bool {
+        !matches!(self, ConstantValue::Unknown(_))
+    }
+}
+
+/// Floats are compared by their bits, so that NaN constants are equal to themselves.
+impl PartialEq for ConstantValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ConstantValue::Unknown(a), ConstantValue::Unknown(b)) => a == b,
+            (ConstantValue::Int(a), ConstantValue::Int(b)) => a == b,
+            (ConstantValue::Long(a), ConstantValue::Long(b)) => a == b,
+            (ConstantValue::Float(a), ConstantValue::Float(b)) => a.to_bits() == b.to_bits(),
+            (ConstantValue::Double(a), ConstantValue::Double(b)) => a.to_bits() == b.to_bits(),
+            (ConstantValue::String(a), ConstantValue::String(b)) => a == b,
+            (ConstantValue::Null, ConstantValue::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Value for ConstantValue<'_> {
+    fn size(&self) -> usize {
+        self.basic_value().size()
+    }
+}
+
+/// An [`Interpreter`] which tracks the values known to be constants: pushed by constant
+/// instructions, or computed from constants by arithmetic, conversions and comparisons, following
+/// the semantics of the JVM. Division by a constant zero isn't folded, as it throws.
+#[derive(Debug, Default, Clone)]
+pub struct ConstantInterpreter {
+    basic: BasicInterpreter,
+}
+
+impl ConstantInterpreter {
+    pub fn new() -> Self {
+        ConstantInterpreter::default()
+    }
+}
+
+/// The result type of an instruction comes from a [`BasicInterpreter`], which only looks at the
+/// instruction and not at its operands.
+const ANY: BasicValue = BasicValue::Uninitialized;
+
+impl<'class> Interpreter<'class> for ConstantInterpreter {
+    type Value = ConstantValue<'class>;
+
+    fn new_value(&mut self, desc: &JavaStr) -> ConstantValue<'class> {
+        ConstantValue::Unknown(BasicValue::from_desc(desc))
+    }
+
+    fn new_empty_value(&mut self, _local: usize) -> ConstantValue<'class> {
+        ConstantValue::Unknown(BasicValue::Uninitialized)
+    }
+
+    fn new_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+    ) -> ClassFileResult<ConstantValue<'class>> {
+        Ok(match insn {
+            InsnNode::Insn(Opcode::AConstNull) => ConstantValue::Null,
+            InsnNode::Insn(Opcode::IConstM1) => ConstantValue::Int(-1),
+            InsnNode::Insn(Opcode::IConst0) => ConstantValue::Int(0),
+            InsnNode::Insn(Opcode::IConst1) => ConstantValue::Int(1),
+            InsnNode::Insn(Opcode::IConst2) => ConstantValue::Int(2),
+            InsnNode::Insn(Opcode::IConst3) => ConstantValue::Int(3),
+            InsnNode::Insn(Opcode::IConst4) => ConstantValue::Int(4),
+            InsnNode::Insn(Opcode::IConst5) => ConstantValue::Int(5),
+            InsnNode::Insn(Opcode::LConst0) => ConstantValue::Long(0),
+            InsnNode::Insn(Opcode::LConst1) => ConstantValue::Long(1),
+            InsnNode::Insn(Opcode::FConst0) => ConstantValue::Float(0.0),
+            InsnNode::Insn(Opcode::FConst1) => ConstantValue::Float(1.0),
+            InsnNode::Insn(Opcode::FConst2) => ConstantValue::Float(2.0),
+            InsnNode::Insn(Opcode::DConst0) => ConstantValue::Double(0.0),
+            InsnNode::Insn(Opcode::DConst1) => ConstantValue::Double(1.0),
+            InsnNode::BIPushInsn(value) => ConstantValue::Int(*value as i32),
+            InsnNode::SIPushInsn(value) => ConstantValue::Int(*value as i32),
+            InsnNode::LdcInsn(LdcConstant::Integer(value)) => ConstantValue::Int(*value),
+            InsnNode::LdcInsn(LdcConstant::Long(value)) => ConstantValue::Long(*value),
+            InsnNode::LdcInsn(LdcConstant::Float(value)) => ConstantValue::Float(*value),
+            InsnNode::LdcInsn(LdcConstant::Double(value)) => ConstantValue::Double(*value),
+            InsnNode::LdcInsn(LdcConstant::String(value)) => ConstantValue::String(value.clone()),
+            _ => ConstantValue::Unknown(self.basic.new_operation(handle, insn)?),
+        })
+    }
+
+    fn copy_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        value: &ConstantValue<'class>,
+    ) -> ClassFileResult<ConstantValue<'class>> {
+        Ok(value.clone())
+    }
+
+    fn unary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &ConstantValue<'class>,
+    ) -> ClassFileResult<Option<ConstantValue<'class>>> {
+        let folded = match (insn, value) {
+            (InsnNode::IIncInsn { increment, .. }, ConstantValue::Int(value)) => {
+                Some(ConstantValue::Int(value.wrapping_add(*increment as i32)))
+            }
+            (InsnNode::Insn(opcode), value) => fold_unary(*opcode, value),
+            _ => None,
+        };
+        if let Some(folded) = folded {
+            return Ok(Some(folded));
+        }
+        let result = self.basic.unary_operation(handle, insn, &ANY)?;
+        Ok(result.map(ConstantValue::Unknown))
+    }
+
+    fn binary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &ConstantValue<'class>,
+        value2: &ConstantValue<'class>,
+    ) -> ClassFileResult<Option<ConstantValue<'class>>> {
+        if let InsnNode::Insn(opcode) = insn {
+            if let Some(folded) = fold_binary(*opcode, value1, value2) {
+                return Ok(Some(folded));
+            }
+        }
+        let result = self.basic.binary_operation(handle, insn, &ANY, &ANY)?;
+        Ok(result.map(ConstantValue::Unknown))
+    }
+
+    fn ternary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        _value1: &ConstantValue<'class>,
+        _value2: &ConstantValue<'class>,
+        _value3: &ConstantValue<'class>,
+    ) -> ClassFileResult<()> {
+        Ok(())
+    }
+
+    fn nary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        _values: &[ConstantValue<'class>],
+    ) -> ClassFileResult<Option<ConstantValue<'class>>> {
+        let result = self.basic.nary_operation(handle, insn, &[])?;
+        Ok(result.map(ConstantValue::Unknown))
+    }
+
+    fn return_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        _value: &ConstantValue<'class>,
+        _expected: &ConstantValue<'class>,
+    ) -> ClassFileResult<()> {
+        Ok(())
+    }
+
+    fn merge(
+        &mut self,
+        value1: &ConstantValue<'class>,
+        value2: &ConstantValue<'class>,
+    ) -> ClassFileResult<ConstantValue<'class>> {
+        if value1 == value2 {
+            return Ok(value1.clone());
+        }
+        let merged = <BasicInterpreter as Interpreter<'class>>::merge(
+            &mut self.basic,
+            &value1.basic_value(),
+            &value2.basic_value(),
+        )?;
+        Ok(ConstantValue::Unknown(merged))
+    }
+}
+
+fn fold_unary<'class>(
+    opcode: Opcode,
+    value: &ConstantValue<'class>,
+) -> Option<ConstantValue<'class>> {
+    use ConstantValue::{Double, Float, Int, Long};
+    Some(match (opcode, value) {
+        (Opcode::INeg, Int(value)) => Int(value.wrapping_neg()),
+        (Opcode::LNeg, Long(value)) => Long(value.wrapping_neg()),
+        (Opcode::FNeg, Float(value)) => Float(-value),
+        (Opcode::DNeg, Double(value)) => Double(-value),
+        (Opcode::I2l, Int(value)) => Long(*value as i64),
+        (Opcode::I2f, Int(value)) => Float(*value as f32),
+        (Opcode::I2d, Int(value)) => Double(*value as f64),
+        (Opcode::L2i, Long(value)) => Int(*value as i32),
+        (Opcode::L2f, Long(value)) => Float(*value as f32),
+        (Opcode::L2d, Long(value)) => Double(*value as f64),
+        // float to integer casts saturate and turn NaN into 0, as in the JVM
+        (Opcode::F2i, Float(value)) => Int(*value as i32),
+        (Opcode::F2l, Float(value)) => Long(*value as i64),
+        (Opcode::F2d, Float(value)) => Double(*value as f64),
+        (Opcode::D2i, Double(value)) => Int(*value as i32),
+        (Opcode::D2l, Double(value)) => Long(*value as i64),
+        (Opcode::D2f, Double(value)) => Float(*value as f32),
+        (Opcode::I2b, Int(value)) => Int(*value as i8 as i32),
+        (Opcode::I2c, Int(value)) => Int(*value as u16 as i32),
+        (Opcode::I2s, Int(value)) => Int(*value as i16 as i32),
+        _ => return None,
+    })
+}
+
+fn fold_binary<'class>(
+    opcode: Opcode,
+    value1: &ConstantValue<'class>,
+    value2: &ConstantValue<'class>,
+) -> Option<ConstantValue<'class>> {
+    use ConstantValue::{Double, Float, Int, Long};
+    Some(match (opcode, value1, value2) {
+        (Opcode::IAdd, Int(a), Int(b)) => Int(a.wrapping_add(*b)),
+        (Opcode::ISub, Int(a), Int(b)) => Int(a.wrapping_sub(*b)),
+        (Opcode::IMul, Int(a), Int(b)) => Int(a.wrapping_mul(*b)),
+        (Opcode::IDiv, Int(a), Int(b)) if *b != 0 => Int(a.wrapping_div(*b)),
+        (Opcode::IRem, Int(a), Int(b)) if *b != 0 => Int(a.wrapping_rem(*b)),
+        (Opcode::IShl, Int(a), Int(b)) => Int(a.wrapping_shl(*b as u32)),
+        (Opcode::IShr, Int(a), Int(b)) => Int(a.wrapping_shr(*b as u32)),
+        (Opcode::IUShr, Int(a), Int(b)) => Int((*a as u32).wrapping_shr(*b as u32) as i32),
+        (Opcode::IAnd, Int(a), Int(b)) => Int(a & b),
+        (Opcode::IOr, Int(a), Int(b)) => Int(a | b),
+        (Opcode::IXor, Int(a), Int(b)) => Int(a ^ b),
+        (Opcode::LAdd, Long(a), Long(b)) => Long(a.wrapping_add(*b)),
+        (Opcode::LSub, Long(a), Long(b)) => Long(a.wrapping_sub(*b)),
+        (Opcode::LMul, Long(a), Long(b)) => Long(a.wrapping_mul(*b)),
+        (Opcode::LDiv, Long(a), Long(b)) if *b != 0 => Long(a.wrapping_div(*b)),
+        (Opcode::LRem, Long(a), Long(b)) if *b != 0 => Long(a.wrapping_rem(*b)),
+        (Opcode::LShl, Long(a), Int(b)) => Long(a.wrapping_shl(*b as u32)),
+        (Opcode::LShr, Long(a), Int(b)) => Long(a.wrapping_shr(*b as u32)),
+        (Opcode::LUShr, Long(a), Int(b)) => Long((*a as u64).wrapping_shr(*b as u32) as i64),
+        (Opcode::LAnd, Long(a), Long(b)) => Long(a & b),
+        (Opcode::LOr, Long(a), Long(b)) => Long(a | b),
+        (Opcode::LXor, Long(a), Long(b)) => Long(a ^ b),
+        (Opcode::FAdd, Float(a), Float(b)) => Float(a + b),
+        (Opcode::FSub, Float(a), Float(b)) => Float(a - b),
+        (Opcode::FMul, Float(a), Float(b)) => Float(a * b),
+        (Opcode::FDiv, Float(a), Float(b)) => Float(a / b),
+        (Opcode::FRem, Float(a), Float(b)) => Float(a % b),
+        (Opcode::DAdd, Double(a), Double(b)) => Double(a + b),
+        (Opcode::DSub, Double(a), Double(b)) => Double(a - b),
+        (Opcode::DMul, Double(a), Double(b)) => Double(a * b),
+        (Opcode::DDiv, Double(a), Double(b)) => Double(a / b),
+        (Opcode::DRem, Double(a), Double(b)) => Double(a % b),
+        (Opcode::LCmp, Long(a), Long(b)) => Int(a.cmp(b) as i32),
+        (Opcode::FCmpL, Float(a), Float(b)) => Int(a.partial_cmp(b).map_or(-1, |ord| ord as i32)),
+        (Opcode::FCmpG, Float(a), Float(b)) => Int(a.partial_cmp(b).map_or(1, |ord| ord as i32)),
+        (Opcode::DCmpL, Double(a), Double(b)) => Int(a.partial_cmp(b).map_or(-1, |ord| ord as i32)),
+        (Opcode::DCmpG, Double(a), Double(b)) => Int(a.partial_cmp(b).map_or(1, |ord| ord as i32)),
+        _ => return None,
+    })
+}
+
+/// Whether a conditional jump with the given operands is known to be taken.
+fn jump_taken(opcode: Opcode, operands: &[ConstantValue<'_>]) -> Option<bool> {
+    use ConstantValue::{Int, Null};
+    Some(match (opcode, operands) {
+        (Opcode::IfEq, [Int(a)]) => *a == 0,
+        (Opcode::IfNe, [Int(a)]) => *a != 0,
+        (Opcode::IfLt, [Int(a)]) => *a < 0,
+        (Opcode::IfGe, [Int(a)]) => *a >= 0,
+        (Opcode::IfGt, [Int(a)]) => *a > 0,
+        (Opcode::IfLe, [Int(a)]) => *a <= 0,
+        (Opcode::IfICmpEq, [Int(a), Int(b)]) => a == b,
+        (Opcode::IfICmpNe, [Int(a), Int(b)]) => a != b,
+        (Opcode::IfICmpLt, [Int(a), Int(b)]) => a < b,
+        (Opcode::IfICmpGe, [Int(a), Int(b)]) => a >= b,
+        (Opcode::IfICmpGt, [Int(a), Int(b)]) => a > b,
+        (Opcode::IfICmpLe, [Int(a), Int(b)]) => a <= b,
+        // string constants are interned, so equal strings are the same object
+        (Opcode::IfACmpEq, [a, b]) if a.is_constant() && b.is_constant() => a == b,
+        (Opcode::IfACmpNe, [a, b]) if a.is_constant() && b.is_constant() => a != b,
+        (Opcode::IfNull, [a]) if a.is_constant() => *a == Null,
+        (Opcode::IfNonNull, [a]) if a.is_constant() => *a != Null,
+        _ => return None,
+    })
+}
+
+/// The instruction which pushes the given constant.
+fn push_constant<'class>(value: &ConstantValue<'class>) -> Option<InsnNode<'class>> {
+    Some(match value {
+        ConstantValue::Unknown(_) => return None,
+        ConstantValue::Int(value @ -1..=5) => InsnNode::Insn(match value {
+            -1 => Opcode::IConstM1,
+            0 => Opcode::IConst0,
+            1 => Opcode::IConst1,
+            2 => Opcode::IConst2,
+            3 => Opcode::IConst3,
+            4 => Opcode::IConst4,
+            _ => Opcode::IConst5,
+        }),
+        ConstantValue::Int(value) => match i8::try_from(*value) {
+            Ok(value) => InsnNode::BIPushInsn(value),
+            Err(_) => match i16::try_from(*value) {
+                Ok(value) => InsnNode::SIPushInsn(value),
+                Err(_) => InsnNode::LdcInsn(LdcConstant::Integer(*value)),
+            },
+        },
+        ConstantValue::Long(0) => InsnNode::Insn(Opcode::LConst0),
+        ConstantValue::Long(1) => InsnNode::Insn(Opcode::LConst1),
+        ConstantValue::Long(value) => InsnNode::LdcInsn(LdcConstant::Long(*value)),
+        ConstantValue::Float(value) if value.to_bits() == 0.0f32.to_bits() => {
+            InsnNode::Insn(Opcode::FConst0)
+        }
+        ConstantValue::Float(1.0) => InsnNode::Insn(Opcode::FConst1),
+        ConstantValue::Float(2.0) => InsnNode::Insn(Opcode::FConst2),
+        ConstantValue::Float(value) => InsnNode::LdcInsn(LdcConstant::Float(*value)),
+        ConstantValue::Double(value) if value.to_bits() == 0.0f64.to_bits() => {
+            InsnNode::Insn(Opcode::DConst0)
+        }
+        ConstantValue::Double(1.0) => InsnNode::Insn(Opcode::DConst1),
+        ConstantValue::Double(value) => InsnNode::LdcInsn(LdcConstant::Double(*value)),
+        ConstantValue::String(value) => InsnNode::LdcInsn(LdcConstant::String(value.clone())),
+        ConstantValue::Null => InsnNode::Insn(Opcode::AConstNull),
+    })
+}
+
+fn pop(value: &ConstantValue<'_>) -> InsnNode<'static> {
+    InsnNode::Insn(if value.size() == 2 {
+        Opcode::Pop2
+    } else {
+        Opcode::Pop
+    })
+}
+
+/// Folds the constants of a method of the class `owner`, as found by a [`ConstantInterpreter`]:
+/// loads of locals holding a constant and arithmetic on constants are replaced with the constant,
+/// and conditional jumps whose outcome is known are replaced with a `goto` or removed. The
+/// operands of the replaced instructions are then removed where they are pushed right before.
+///
+/// Returns the number of instructions folded. Code after a jump which is now always taken may have
+/// become unreachable, which [`remove_dead_code`](crate::analysis::remove_dead_code) removes.
+pub fn fold_constants<'class>(
+    owner: &JavaStr,
+    method: &mut MethodNode<'class>,
+) -> ClassFileResult<usize> {
+    if method.label_creator.is_none() {
+        return Ok(0);
+    }
+    let mut analyzer = Analyzer::new(ConstantInterpreter::new());
+    analyzer.analyze(owner, method)?;
+
+    let mut folded = 0;
+    for (index, &handle) in analyzer.handles().iter().enumerate() {
+        let Some(frame) = &analyzer.frames()[index] else {
+            continue;
+        };
+        let stack = &frame.stack;
+        let insn = &method.instructions[handle];
+        let (operands, result) = match insn {
+            InsnNode::VarInsn {
+                opcode:
+                    Opcode::ILoad | Opcode::LLoad | Opcode::FLoad | Opcode::DLoad | Opcode::ALoad,
+                var_index,
+            } => (0, Some(frame.locals[*var_index as usize].clone())),
+            InsnNode::Insn(opcode) => match stack.as_slice() {
+                [.., value] if fold_unary(*opcode, value).is_some() => {
+                    (1, fold_unary(*opcode, value))
+                }
+                [.., value1, value2] => (2, fold_binary(*opcode, value1, value2)),
+                _ => continue,
+            },
+            InsnNode::JumpInsn { opcode, label } if *opcode != Opcode::Goto => {
+                let operands = match opcode {
+                    Opcode::IfICmpEq
+                    | Opcode::IfICmpNe
+                    | Opcode::IfICmpLt
+                    | Opcode::IfICmpGe
+                    | Opcode::IfICmpGt
+                    | Opcode::IfICmpLe
+                    | Opcode::IfACmpEq
+                    | Opcode::IfACmpNe => 2,
+                    _ => 1,
+                };
+                let Some(operands) = stack.get(stack.len().saturating_sub(operands)..) else {
+                    continue;
+                };
+                let Some(taken) = jump_taken(*opcode, operands) else {
+                    continue;
+                };
+                let label = *label;
+                for operand in operands {
+                    method.instructions.insert_before(handle, pop(operand));
+                }
+                if taken {
+                    method.instructions[handle] = InsnNode::JumpInsn {
+                        opcode: Opcode::Goto,
+                        label,
+                    };
+                } else {
+                    method.instructions.remove(handle);
+                }
+                folded += 1;
+                continue;
+            }
+            _ => continue,
+        };
+        let operands = &stack[stack.len() - operands..];
+        if !operands.iter().all(ConstantValue::is_constant) {
+            continue;
+        }
+        let Some(push) = result.as_ref().and_then(push_constant) else {
+            continue;
+        };
+        for operand in operands {
+            method.instructions.insert_before(handle, pop(operand));
+        }
+        method.instructions[handle] = push;
+        folded += 1;
+    }
+
+    remove_popped_constants(method);
+    Ok(folded)
+}
+
+/// Removes constants and loads which are immediately popped.
+fn remove_popped_constants(method: &mut MethodNode<'_>) {
+    fn pushed_size(insn: &InsnNode<'_>) -> Option<usize> {
+        match insn {
+            InsnNode::Insn(
+                Opcode::LConst0 | Opcode::LConst1 | Opcode::DConst0 | Opcode::DConst1,
+            )
+            | InsnNode::LdcInsn(LdcConstant::Long(_) | LdcConstant::Double(_))
+            | InsnNode::VarInsn {
+                opcode: Opcode::LLoad | Opcode::DLoad,
+                ..
+            } => Some(2),
+            InsnNode::Insn(
+                Opcode::AConstNull
+                | Opcode::IConstM1
+                | Opcode::IConst0
+                | Opcode::IConst1
+                | Opcode::IConst2
+                | Opcode::IConst3
+                | Opcode::IConst4
+                | Opcode::IConst5
+                | Opcode::FConst0
+                | Opcode::FConst1
+                | Opcode::FConst2,
+            )
+            | InsnNode::BIPushInsn(_)
+            | InsnNode::SIPushInsn(_)
+            | InsnNode::LdcInsn(
+                LdcConstant::Integer(_) | LdcConstant::Float(_) | LdcConstant::String(_),
+            )
+            | InsnNode::VarInsn {
+                opcode: Opcode::ILoad | Opcode::FLoad | Opcode::ALoad,
+                ..
+            } => Some(1),
+            _ => None,
+        }
+    }
+
+    let insns = &mut method.instructions;
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for handle in insns.handles().collect::<Vec<_>>() {
+            let popped = match insns[handle] {
+                InsnNode::Insn(Opcode::Pop) => 1,
+                InsnNode::Insn(Opcode::Pop2) => 2,
+                _ => continue,
+            };
+            let Some(prev) = insns.prev(handle) else {
+                continue;
+            };
+            match pushed_size(&insns[prev]) {
+                Some(size) if size == popped => {
+                    insns.remove(prev);
+                    insns.remove(handle);
+                }
+                Some(1) => {
+                    insns.remove(prev);
+                    insns[handle] = InsnNode::Insn(Opcode::Pop);
+                }
+                _ => continue,
+            }
+            changed = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{
+        fold_constants, BasicValue, ConstantInterpreter, ConstantValue, Interpreter,
+    };
+    use crate::tree::{InsnList, InsnNode, MethodNode};
+    use crate::{LabelCreator, MethodAccess, Opcode};
+    use java_string::JavaStr;
+
+    #[test]
+    fn test_constant_interpreter() {
+        let mut interpreter = ConstantInterpreter::new();
+        let mut list = InsnList::new();
+        let mut binary = |opcode, value1, value2| {
+            let insn = InsnNode::Insn(opcode);
+            let handle = list.push_back(insn.clone());
+            interpreter
+                .binary_operation(handle, &insn, &value1, &value2)
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(
+            ConstantValue::Int(i32::MIN),
+            binary(
+                Opcode::IAdd,
+                ConstantValue::Int(i32::MAX),
+                ConstantValue::Int(1)
+            )
+        );
+        assert_eq!(
+            ConstantValue::Unknown(BasicValue::Int),
+            binary(Opcode::IDiv, ConstantValue::Int(1), ConstantValue::Int(0))
+        );
+        assert_eq!(
+            ConstantValue::Int(1),
+            binary(
+                Opcode::FCmpG,
+                ConstantValue::Float(f32::NAN),
+                ConstantValue::Float(0.0)
+            )
+        );
+        assert_eq!(
+            ConstantValue::Long(1 << 1),
+            binary(Opcode::LShl, ConstantValue::Long(1), ConstantValue::Int(65))
+        );
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let label_creator = LabelCreator::default();
+        let label = label_creator.create_label();
+        let mut method = MethodNode::new(
+            MethodAccess::Static,
+            JavaStr::from_str("test"),
+            JavaStr::from_str("()I"),
+        );
+        method.label_creator = Some(label_creator);
+        let load = InsnNode::VarInsn {
+            opcode: Opcode::ILoad,
+            var_index: 0,
+        };
+        method.instructions.extend([
+            InsnNode::Insn(Opcode::IConst2),
+            InsnNode::Insn(Opcode::IConst3),
+            InsnNode::Insn(Opcode::IAdd),
+            InsnNode::VarInsn {
+                opcode: Opcode::IStore,
+                var_index: 0,
+            },
+            load.clone(),
+            InsnNode::Insn(Opcode::IConst5),
+            InsnNode::JumpInsn {
+                opcode: Opcode::IfICmpNe,
+                label,
+            },
+            load,
+            InsnNode::Insn(Opcode::IReturn),
+            InsnNode::Label(label),
+            InsnNode::Insn(Opcode::IConstM1),
+            InsnNode::Insn(Opcode::IReturn),
+        ]);
+
+        assert_eq!(
+            4,
+            fold_constants(JavaStr::from_str("Test"), &mut method).unwrap()
+        );
+        assert_eq!(
+            vec![
+                InsnNode::Insn(Opcode::IConst5),
+                InsnNode::VarInsn {
+                    opcode: Opcode::IStore,
+                    var_index: 0,
+                },
+                InsnNode::Insn(Opcode::IConst5),
+                InsnNode::Insn(Opcode::IReturn),
+                InsnNode::Label(label),
+                InsnNode::Insn(Opcode::IConstM1),
+                InsnNode::Insn(Opcode::IReturn),
+            ],
+            method.instructions.iter().cloned().collect::<Vec<_>>()
+        );
+    }
+}
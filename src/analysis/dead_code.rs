@@ -0,0 +1,180 @@
+use crate::analysis::{Analyzer, BasicInterpreter};
+use crate::tree::{InsnNode, MethodNode};
+use crate::{ClassFileResult, Label};
+use java_string::JavaStr;
+use std::collections::HashMap;
+
+/// Removes the instructions of a method of the class `owner` which can't be reached from the start
+/// of the method, nor from an exception handler covering reachable code. Frames and instruction
+/// annotations in the removed code are removed with it, as are the try-catch blocks which no
+/// longer cover any instructions, along with their annotations. Labels and line numbers are kept,
+/// as local variables may still refer to them.
+///
+/// Returns the number of bytes by which the code shrank.
+pub fn remove_dead_code(owner: &JavaStr, method: &mut MethodNode<'_>) -> ClassFileResult<usize> {
+    if method.label_creator.is_none() {
+        return Ok(0);
+    }
+    let old_size = method.instructions.code_size();
+
+    let mut analyzer = Analyzer::new(BasicInterpreter::new());
+    analyzer.analyze(owner, method)?;
+    let mut removed_insn = false;
+    for (index, &handle) in analyzer.handles().iter().enumerate() {
+        let unreachable = analyzer.frames()[index].is_none();
+        let remove = match &method.instructions[handle] {
+            InsnNode::Label(_) | InsnNode::LineNumber { .. } => false,
+            // the annotations of an instruction follow it, and are unreachable after a jump
+            InsnNode::InsnAnnotations(_) => removed_insn,
+            InsnNode::Frame(_) => unreachable,
+            _ => {
+                removed_insn = unreachable;
+                unreachable
+            }
+        };
+        if remove {
+            method.instructions.remove(handle);
+        }
+    }
+
+    // the number of real instructions before each label
+    let mut real_insns = 0;
+    let mut label_positions: HashMap<Label, usize> = HashMap::new();
+    for insn in &method.instructions {
+        match insn {
+            InsnNode::Label(label) => {
+                label_positions.insert(*label, real_insns);
+            }
+            insn if !insn.is_pseudo_insn() => real_insns += 1,
+            _ => {}
+        }
+    }
+    let mut new_indexes = Vec::with_capacity(method.try_catch_blocks.len());
+    let mut next_index = 0;
+    method.try_catch_blocks.retain(|try_catch_block| {
+        let covers_code = label_positions.get(&try_catch_block.start)
+            != label_positions.get(&try_catch_block.end);
+        new_indexes.push(covers_code.then_some(next_index));
+        next_index += covers_code as u16;
+        covers_code
+    });
+    method.try_catch_block_annotations.retain_mut(|annotation| {
+        match new_indexes
+            .get(annotation.try_catch_block_index as usize)
+            .copied()
+            .flatten()
+        {
+            Some(index) => {
+                annotation.try_catch_block_index = index;
+                true
+            }
+            None => false,
+        }
+    });
+
+    Ok(old_size - method.instructions.code_size())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::remove_dead_code;
+    use crate::tree::{ClassNode, InsnNode, MethodNode, TypeAnnotationNode};
+    use crate::{
+        ClassReader, ClassReaderFlags, LabelCreator, MethodAccess,
+        MethodTryCatchBlockAnnotationEvent, MethodTryCatchBlockEvent, Opcode, TypeReference,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_remove_dead_code() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let mut class = ClassNode::from_events(&reader).unwrap();
+        for method in &mut class.methods {
+            let insns = method.instructions.len();
+            assert_eq!(0, remove_dead_code(&class.name, method).unwrap());
+            assert_eq!(insns, method.instructions.len());
+        }
+
+        let mut label_creator = LabelCreator::default();
+        let [start, end, handler, used, last] = [(); 5].map(|()| label_creator.create_label());
+        let mut method = MethodNode::new(
+            MethodAccess::Static,
+            JavaStr::from_str("test"),
+            JavaStr::from_str("()V"),
+        );
+        method.label_creator = Some(label_creator);
+        method.instructions.extend([
+            InsnNode::JumpInsn {
+                opcode: Opcode::Goto,
+                label: used,
+            },
+            InsnNode::Label(start),
+            InsnNode::Insn(Opcode::IConst0),
+            InsnNode::Insn(Opcode::Pop),
+            InsnNode::Label(end),
+            InsnNode::Label(handler),
+            InsnNode::Insn(Opcode::AThrow),
+            InsnNode::Label(used),
+            InsnNode::Insn(Opcode::Return),
+            InsnNode::Label(last),
+        ]);
+        method.try_catch_blocks.extend([
+            MethodTryCatchBlockEvent {
+                start,
+                end,
+                handler,
+                ty: None,
+            },
+            MethodTryCatchBlockEvent {
+                start: used,
+                end: last,
+                handler,
+                ty: None,
+            },
+        ]);
+        let annotation = TypeAnnotationNode {
+            type_ref: TypeReference::ExceptionParameter,
+            type_path: "".parse().unwrap(),
+            desc: JavaStr::from_str("LAnnotation;").into(),
+            values: Vec::new(),
+        };
+        method
+            .try_catch_block_annotations
+            .extend(
+                [0, 1].map(|try_catch_block_index| MethodTryCatchBlockAnnotationEvent {
+                    try_catch_block_index,
+                    visible: true,
+                    annotation: annotation.clone(),
+                }),
+            );
+
+        assert_eq!(
+            2,
+            remove_dead_code(JavaStr::from_str("Test"), &mut method).unwrap()
+        );
+        assert_eq!(
+            vec![
+                InsnNode::JumpInsn {
+                    opcode: Opcode::Goto,
+                    label: used,
+                },
+                InsnNode::Label(start),
+                InsnNode::Label(end),
+                InsnNode::Label(handler),
+                InsnNode::Insn(Opcode::AThrow),
+                InsnNode::Label(used),
+                InsnNode::Insn(Opcode::Return),
+                InsnNode::Label(last),
+            ],
+            method.instructions.iter().cloned().collect::<Vec<_>>()
+        );
+        assert_eq!(1, method.try_catch_blocks.len());
+        assert_eq!(used, method.try_catch_blocks[0].start);
+        assert_eq!(1, method.try_catch_block_annotations.len());
+        assert_eq!(
+            0,
+            method.try_catch_block_annotations[0].try_catch_block_index
+        );
+    }
+}
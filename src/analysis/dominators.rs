@@ -0,0 +1,309 @@
+use crate::analysis::{Analyzer, Interpreter};
+use std::collections::BTreeSet;
+
+/// The dominator tree of a control flow graph whose nodes are the indexes `0..len`. A node `a`
+/// dominates `b` if every path from a root to `b` goes through `a`. Nodes which can't be reached
+/// from a root have no dominators.
+///
+/// The tree is computed with the algorithm of Cooper, Harvey and Kennedy. When built from the
+/// reversed graph, as by [`post_dominators`](DominatorTree::post_dominators), it is the
+/// post-dominator tree.
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    /// The immediate dominator of each node, where `len` stands for a virtual node above all the
+    /// roots, and `None` means unreachable.
+    idoms: Vec<Option<usize>>,
+    /// The position of each reachable node in a postorder of the graph.
+    postorder: Vec<usize>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl DominatorTree {
+    /// Computes the dominator tree of the graph with the given successors of each node, starting
+    /// from the given roots.
+    pub fn new(roots: &[usize], successors: &[Vec<usize>]) -> Self {
+        let len = successors.len();
+        let root = len;
+        let mut predecessors = vec![Vec::new(); len + 1];
+        for (node, successors) in successors.iter().enumerate() {
+            for &successor in successors {
+                predecessors[successor].push(node);
+            }
+        }
+        for &node in roots {
+            predecessors[node].push(root);
+        }
+
+        // depth first search from the virtual root for the postorder
+        let mut postorder = vec![usize::MAX; len + 1];
+        let mut order = Vec::with_capacity(len + 1);
+        let mut visited = vec![false; len + 1];
+        visited[root] = true;
+        let mut stack = vec![(root, 0)];
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+            let node_successors = if node == root {
+                roots
+            } else {
+                &successors[node]
+            };
+            if let Some(&successor) = node_successors.get(*next) {
+                *next += 1;
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, 0));
+                }
+            } else {
+                stack.pop();
+                postorder[node] = order.len();
+                order.push(node);
+            }
+        }
+
+        let mut idoms = vec![None; len + 1];
+        idoms[root] = Some(root);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in order.iter().rev().skip(1) {
+                let mut new_idom = None;
+                for &predecessor in &predecessors[node] {
+                    if idoms[predecessor].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        Some(idom) => intersect(&idoms, &postorder, predecessor, idom),
+                        None => predecessor,
+                    });
+                }
+                if new_idom != idoms[node] {
+                    idoms[node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        idoms.pop();
+        DominatorTree {
+            idoms,
+            postorder,
+            predecessors,
+        }
+    }
+
+    /// The dominator tree of the instructions analyzed by the last call to
+    /// [`Analyzer::analyze`], including the edges to exception handlers.
+    pub fn dominators<'class, I: Interpreter<'class>>(analyzer: &Analyzer<'class, I>) -> Self {
+        let roots: &[usize] = if analyzer.handles().is_empty() {
+            &[]
+        } else {
+            &[0]
+        };
+        DominatorTree::new(roots, &edges(analyzer))
+    }
+
+    /// The post-dominator tree of the instructions analyzed by the last call to
+    /// [`Analyzer::analyze`], whose roots are the returns and throws. Instructions which can't
+    /// reach a return or throw, such as those in an infinite loop, have no post-dominators.
+    pub fn post_dominators<'class, I: Interpreter<'class>>(analyzer: &Analyzer<'class, I>) -> Self {
+        let edges = edges(analyzer);
+        let mut reversed = vec![Vec::new(); edges.len()];
+        for (node, successors) in edges.iter().enumerate() {
+            for &successor in successors {
+                reversed[successor].push(node);
+            }
+        }
+        let exits = (0..edges.len())
+            .filter(|&index| {
+                analyzer.frames()[index].is_some() && analyzer.successors(index).is_empty()
+            })
+            .collect::<Vec<_>>();
+        DominatorTree::new(&exits, &reversed)
+    }
+
+    /// The number of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.idoms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.idoms.is_empty()
+    }
+
+    pub fn is_reachable(&self, node: usize) -> bool {
+        self.idoms[node].is_some()
+    }
+
+    /// The closest strict dominator of the given node, or `None` for roots and unreachable nodes.
+    pub fn immediate_dominator(&self, node: usize) -> Option<usize> {
+        self.idoms[node].filter(|&idom| idom != self.len())
+    }
+
+    /// The nodes immediately dominated by the given node.
+    pub fn children(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).filter(move |&child| self.idoms[child] == Some(node))
+    }
+
+    /// Whether `a` dominates `b`. Every reachable node dominates itself.
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        let mut node = b;
+        loop {
+            if node == a {
+                return true;
+            }
+            match self.immediate_dominator(node) {
+                Some(idom) => node = idom,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn strictly_dominates(&self, a: usize, b: usize) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// The dominance frontier of each node: the nodes where the region dominated by the node ends,
+    /// which are the nodes it doesn't strictly dominate, but which have a predecessor it does
+    /// dominate.
+    pub fn dominance_frontiers(&self) -> Vec<BTreeSet<usize>> {
+        let mut frontiers = vec![BTreeSet::new(); self.len()];
+        for node in 0..self.len() {
+            let Some(idom) = self.idoms[node] else {
+                continue;
+            };
+            let predecessors = &self.predecessors[node];
+            if predecessors.len() < 2 {
+                continue;
+            }
+            for &predecessor in predecessors {
+                let mut runner = predecessor;
+                while runner != idom {
+                    let Some(runner_idom) = self.idoms.get(runner).copied().flatten() else {
+                        break;
+                    };
+                    frontiers[runner].insert(node);
+                    runner = runner_idom;
+                }
+            }
+        }
+        frontiers
+    }
+
+    /// The iterated dominance frontier of the given nodes, which for the definitions of a
+    /// variable are the nodes where φ-functions are needed to put it into SSA form.
+    pub fn iterated_dominance_frontier(
+        &self,
+        nodes: impl IntoIterator<Item = usize>,
+    ) -> BTreeSet<usize> {
+        let frontiers = self.dominance_frontiers();
+        let mut result = BTreeSet::new();
+        let mut worklist = nodes.into_iter().collect::<Vec<_>>();
+        while let Some(node) = worklist.pop() {
+            for &frontier in &frontiers[node] {
+                if result.insert(frontier) {
+                    worklist.push(frontier);
+                }
+            }
+        }
+        result
+    }
+}
+
+fn intersect(idoms: &[Option<usize>], postorder: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while postorder[a] < postorder[b] {
+            a = idoms[a].expect("processed node has an immediate dominator");
+        }
+        while postorder[b] < postorder[a] {
+            b = idoms[b].expect("processed node has an immediate dominator");
+        }
+    }
+    a
+}
+
+fn edges<'class, I: Interpreter<'class>>(analyzer: &Analyzer<'class, I>) -> Vec<Vec<usize>> {
+    (0..analyzer.handles().len())
+        .map(|index| {
+            let mut successors = analyzer.successors(index).to_vec();
+            successors.extend_from_slice(analyzer.exception_successors(index));
+            successors
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{Analyzer, BasicInterpreter, DominatorTree};
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{ClassReader, ClassReaderFlags, Opcode};
+    use std::collections::BTreeSet;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_graph() {
+        // 0 -> 1 -> 2 -> 1, 0 -> 3, 2 -> 4, 3 -> 4, and 5 is unreachable
+        let tree = DominatorTree::new(
+            &[0],
+            &[vec![1, 3], vec![2], vec![1, 4], vec![4], vec![], vec![4]],
+        );
+        assert_eq!(None, tree.immediate_dominator(0));
+        assert_eq!(Some(0), tree.immediate_dominator(1));
+        assert_eq!(Some(1), tree.immediate_dominator(2));
+        assert_eq!(Some(0), tree.immediate_dominator(4));
+        assert!(!tree.is_reachable(5));
+        assert!(tree.dominates(1, 2));
+        assert!(tree.dominates(2, 2));
+        assert!(!tree.strictly_dominates(2, 2));
+        assert!(!tree.dominates(3, 4));
+        assert!(!tree.dominates(5, 4));
+        assert_eq!(vec![1, 3, 4], tree.children(0).collect::<Vec<_>>());
+
+        let frontiers = tree.dominance_frontiers();
+        assert_eq!(BTreeSet::from([1, 4]), frontiers[2]);
+        assert_eq!(BTreeSet::from([1, 4]), frontiers[1]);
+        assert_eq!(BTreeSet::from([4]), frontiers[3]);
+        assert!(frontiers[0].is_empty());
+        assert_eq!(
+            BTreeSet::from([1, 4]),
+            tree.iterated_dominance_frontier([2])
+        );
+    }
+
+    #[test]
+    fn test_method() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "loops")
+            .unwrap();
+        let mut analyzer = Analyzer::new(BasicInterpreter::new());
+        analyzer.analyze(&class.name, method).unwrap();
+
+        let dominators = DominatorTree::dominators(&analyzer);
+        let post_dominators = DominatorTree::post_dominators(&analyzer);
+        let ret = analyzer
+            .handles()
+            .iter()
+            .position(|&handle| method.instructions[handle] == InsnNode::Insn(Opcode::DReturn))
+            .unwrap();
+        let add = analyzer
+            .handles()
+            .iter()
+            .position(|&handle| method.instructions[handle] == InsnNode::Insn(Opcode::DAdd))
+            .unwrap();
+        for index in 0..analyzer.handles().len() {
+            if analyzer.frames()[index].is_some() {
+                assert!(dominators.dominates(0, index));
+                assert!(post_dominators.dominates(ret, index));
+            }
+        }
+        // the body of the loop doesn't always run
+        assert!(!dominators.dominates(add, ret));
+        assert!(!post_dominators.dominates(add, 0));
+    }
+}
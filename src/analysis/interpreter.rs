@@ -0,0 +1,120 @@
+use crate::tree::{InsnHandle, InsnNode};
+use crate::{ClassFileResult, MethodTryCatchBlockEvent};
+use java_string::{JavaStr, JavaString};
+use std::fmt::Debug;
+
+/// A value in the locals or on the operand stack, as modelled by an [`Interpreter`].
+pub trait Value: Clone + PartialEq + Debug {
+    /// The number of slots the value takes up: 2 for longs and doubles, and 1 otherwise.
+    fn size(&self) -> usize;
+}
+
+/// The semantics of the values computed by an [`Analyzer`](crate::analysis::Analyzer). The
+/// analyzer takes care of moving values between the locals and the stack, and asks the interpreter
+/// for the result of each instruction.
+///
+/// Each operation is passed the handle of the instruction and the instruction itself. Operations
+/// which produce no value for the instruction, such as conditional jumps, return `None`.
+pub trait Interpreter<'class> {
+    type Value: Value;
+
+    /// A value of the given field descriptor.
+    fn new_value(&mut self, desc: &JavaStr) -> Self::Value;
+
+    /// The value of a local which hasn't been set, or the second slot of a long or double.
+    fn new_empty_value(&mut self, local: usize) -> Self::Value;
+
+    /// The value of a parameter, including `this` at local 0 of an instance method.
+    fn new_parameter_value(
+        &mut self,
+        is_instance_method: bool,
+        local: usize,
+        desc: &JavaStr,
+    ) -> Self::Value {
+        let _ = (is_instance_method, local);
+        self.new_value(desc)
+    }
+
+    /// The value on the stack at the start of an exception handler, where `exception` is the
+    /// internal name of the caught exception.
+    fn new_exception_value(
+        &mut self,
+        try_catch_block: &MethodTryCatchBlockEvent<'class>,
+        exception: &JavaStr,
+    ) -> Self::Value {
+        let _ = try_catch_block;
+        let mut desc = JavaString::from("L");
+        desc.push_java_str(exception);
+        desc.push(';');
+        self.new_value(&desc)
+    }
+
+    /// Instructions with no operands which push a value: constants, `getstatic` and `new`.
+    fn new_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+    ) -> ClassFileResult<Self::Value>;
+
+    /// Instructions which move a value between the locals and the stack: loads, stores, `dup`s and
+    /// `swap`.
+    fn copy_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &Self::Value,
+    ) -> ClassFileResult<Self::Value>;
+
+    /// Instructions with one operand, including `iinc`, whose operand is the local.
+    fn unary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &Self::Value,
+    ) -> ClassFileResult<Option<Self::Value>>;
+
+    /// Instructions with two operands, such as arithmetic, array loads and `putfield`.
+    fn binary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &Self::Value,
+        value2: &Self::Value,
+    ) -> ClassFileResult<Option<Self::Value>>;
+
+    /// Array stores, whose operands are the array, the index and the value.
+    fn ternary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &Self::Value,
+        value2: &Self::Value,
+        value3: &Self::Value,
+    ) -> ClassFileResult<()>;
+
+    /// Method invocations, whose operands are the receiver if any followed by the arguments, and
+    /// `multianewarray`.
+    fn nary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        values: &[Self::Value],
+    ) -> ClassFileResult<Option<Self::Value>>;
+
+    /// Called for return instructions with a value, after [`unary_operation`], with the value of
+    /// the method's return type.
+    ///
+    /// [`unary_operation`]: Interpreter::unary_operation
+    fn return_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &Self::Value,
+        expected: &Self::Value,
+    ) -> ClassFileResult<()>;
+
+    /// Merges two values where control flow joins. If the result equals `value1`, the analyzer
+    /// considers the values already merged.
+    fn merge(&mut self, value1: &Self::Value, value2: &Self::Value)
+        -> ClassFileResult<Self::Value>;
+}
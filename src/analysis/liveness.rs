@@ -0,0 +1,242 @@
+use crate::analysis::{Analyzer, Interpreter};
+use crate::tree::{InsnNode, MethodNode};
+use crate::Opcode;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// The locals which are live before and after each instruction of a method, meaning that their
+/// value may be read later before being overwritten. Longs and doubles make both of their slots
+/// live.
+///
+/// Computed from the control flow found by an [`Analyzer`], including the edges to exception
+/// handlers: a local read by a handler is live throughout the code it covers. Unreachable
+/// instructions have no live locals.
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    live_in: Vec<BTreeSet<u16>>,
+    live_out: Vec<BTreeSet<u16>>,
+    blocks: Vec<Range<usize>>,
+    block_indexes: Vec<usize>,
+}
+
+impl Liveness {
+    /// Computes the liveness of the locals of the method last analyzed by the given analyzer.
+    pub fn new<'class, I: Interpreter<'class>>(
+        analyzer: &Analyzer<'class, I>,
+        method: &MethodNode<'class>,
+    ) -> Self {
+        let handles = analyzer.handles();
+        let len = handles.len();
+        let (uses, defs): (Vec<_>, Vec<_>) = handles
+            .iter()
+            .map(|&handle| uses_and_defs(&method.instructions[handle]))
+            .unzip();
+
+        let mut live_in = vec![BTreeSet::new(); len];
+        let mut live_out = vec![BTreeSet::new(); len];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in (0..len).rev() {
+                if analyzer.frames()[index].is_none() {
+                    continue;
+                }
+                let mut after = BTreeSet::new();
+                for &successor in analyzer.successors(index) {
+                    after.extend(live_in[successor].iter().copied());
+                }
+                let mut before = after.clone();
+                for def in defs[index].clone() {
+                    before.remove(&def);
+                }
+                before.extend(uses[index].clone());
+                // an exception can be thrown before the instruction writes to its local
+                for &handler in analyzer.exception_successors(index) {
+                    after.extend(live_in[handler].iter().copied());
+                    before.extend(live_in[handler].iter().copied());
+                }
+                if before != live_in[index] || after != live_out[index] {
+                    live_in[index] = before;
+                    live_out[index] = after;
+                    changed = true;
+                }
+            }
+        }
+
+        let (blocks, block_indexes) = basic_blocks(analyzer);
+        Liveness {
+            live_in,
+            live_out,
+            blocks,
+            block_indexes,
+        }
+    }
+
+    /// The locals live before the instruction at the given index.
+    pub fn live_in(&self, index: usize) -> &BTreeSet<u16> {
+        &self.live_in[index]
+    }
+
+    /// The locals live after the instruction at the given index.
+    pub fn live_out(&self, index: usize) -> &BTreeSet<u16> {
+        &self.live_out[index]
+    }
+
+    pub fn is_live_in(&self, index: usize, local: u16) -> bool {
+        self.live_in[index].contains(&local)
+    }
+
+    pub fn is_live_out(&self, index: usize, local: u16) -> bool {
+        self.live_out[index].contains(&local)
+    }
+
+    /// The ranges of instruction indexes of the basic blocks: the sequences of instructions which
+    /// can only be entered at the start, and only branch at the end. Instructions covered by an
+    /// exception handler may also leave the block to the handler.
+    pub fn blocks(&self) -> &[Range<usize>] {
+        &self.blocks
+    }
+
+    /// The index of the basic block containing the instruction at the given index.
+    pub fn block_of(&self, index: usize) -> usize {
+        self.block_indexes[index]
+    }
+
+    /// The locals live at the start of the given basic block.
+    pub fn block_live_in(&self, block: usize) -> &BTreeSet<u16> {
+        &self.live_in[self.blocks[block].start]
+    }
+
+    /// The locals live at the end of the given basic block.
+    pub fn block_live_out(&self, block: usize) -> &BTreeSet<u16> {
+        &self.live_out[self.blocks[block].end - 1]
+    }
+}
+
+/// The locals read and written by an instruction.
+fn uses_and_defs(insn: &InsnNode<'_>) -> (Range<u16>, Range<u16>) {
+    match insn {
+        InsnNode::VarInsn { opcode, var_index } => {
+            let var_index = *var_index;
+            let size = match opcode {
+                Opcode::LLoad | Opcode::DLoad | Opcode::LStore | Opcode::DStore => 2,
+                _ => 1,
+            };
+            let slots = var_index..var_index.saturating_add(size);
+            match opcode {
+                Opcode::IStore
+                | Opcode::LStore
+                | Opcode::FStore
+                | Opcode::DStore
+                | Opcode::AStore => (0..0, slots),
+                _ => (slots, 0..0),
+            }
+        }
+        InsnNode::IIncInsn { var_index, .. } => (*var_index..var_index.saturating_add(1), 0..0),
+        _ => (0..0, 0..0),
+    }
+}
+
+/// Splits the instructions into basic blocks, returning the blocks and the block of each
+/// instruction.
+fn basic_blocks<'class, I: Interpreter<'class>>(
+    analyzer: &Analyzer<'class, I>,
+) -> (Vec<Range<usize>>, Vec<usize>) {
+    let len = analyzer.handles().len();
+    let mut leaders = vec![false; len];
+    if len != 0 {
+        leaders[0] = true;
+    }
+    for index in 0..len {
+        let successors = analyzer.successors(index);
+        if successors != [index + 1] && index + 1 < len {
+            leaders[index + 1] = true;
+        }
+        for &successor in successors
+            .iter()
+            .chain(analyzer.exception_successors(index))
+        {
+            if successor != index + 1 {
+                leaders[successor] = true;
+            }
+        }
+    }
+
+    let mut blocks: Vec<Range<usize>> = Vec::new();
+    let mut block_indexes = Vec::with_capacity(len);
+    for (index, leader) in leaders.into_iter().enumerate() {
+        if leader {
+            blocks.push(index..index + 1);
+        } else {
+            blocks.last_mut().unwrap().end = index + 1;
+        }
+        block_indexes.push(blocks.len() - 1);
+    }
+    (blocks, block_indexes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{Analyzer, BasicInterpreter, Liveness};
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{ClassReader, ClassReaderFlags, Opcode};
+    use std::collections::BTreeSet;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_liveness() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "loops")
+            .unwrap();
+        let mut analyzer = Analyzer::new(BasicInterpreter::new());
+        analyzer.analyze(&class.name, method).unwrap();
+        let liveness = Liveness::new(&analyzer, method);
+
+        // only the parameter is read before being written
+        assert_eq!(&BTreeSet::from([1]), liveness.live_in(0));
+        assert_eq!(&BTreeSet::from([1]), liveness.block_live_in(0));
+        let ret = analyzer
+            .handles()
+            .iter()
+            .position(|&handle| method.instructions[handle] == InsnNode::Insn(Opcode::DReturn))
+            .unwrap();
+        assert!(liveness.live_in(ret).is_empty());
+        let load_sum = analyzer.handles()[..ret]
+            .iter()
+            .rposition(|&handle| {
+                matches!(
+                    method.instructions[handle],
+                    InsnNode::VarInsn {
+                        opcode: Opcode::DLoad,
+                        ..
+                    }
+                )
+            })
+            .unwrap();
+        assert_eq!(&BTreeSet::from([2, 3]), liveness.live_in(load_sum));
+        assert!(!liveness.is_live_in(load_sum, 1));
+
+        let blocks = liveness.blocks();
+        assert!(blocks.len() > 2);
+        assert_eq!(0, blocks[0].start);
+        assert_eq!(analyzer.handles().len(), blocks.last().unwrap().end);
+        for (index, block) in blocks.iter().enumerate() {
+            assert!(block.clone().all(|insn| liveness.block_of(insn) == index));
+        }
+        // the loop condition reads the array and the counter
+        let loop_header = liveness.block_of(
+            analyzer.handles()[..ret]
+                .iter()
+                .rposition(|&handle| {
+                    method.instructions[handle] == InsnNode::Insn(Opcode::ArrayLength)
+                })
+                .unwrap(),
+        );
+        assert!(liveness.block_live_in(loop_header).contains(&1));
+        assert!(liveness.block_live_out(loop_header).contains(&1));
+    }
+}
@@ -0,0 +1,21 @@
+pub mod analyzer;
+pub mod basic_interpreter;
+pub mod basic_verifier;
+pub mod constant_interpreter;
+pub mod dead_code;
+pub mod dominators;
+pub mod interpreter;
+pub mod liveness;
+pub mod simple_verifier;
+pub mod source_interpreter;
+
+pub use analyzer::*;
+pub use basic_interpreter::*;
+pub use basic_verifier::*;
+pub use constant_interpreter::*;
+pub use dead_code::*;
+pub use dominators::*;
+pub use interpreter::*;
+pub use liveness::*;
+pub use simple_verifier::*;
+pub use source_interpreter::*;
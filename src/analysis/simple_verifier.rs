@@ -0,0 +1,664 @@
+use crate::analysis::{method_desc_types, Interpreter, Value};
+use crate::frame_computer::ldc_value;
+use crate::tree::{InsnHandle, InsnNode};
+use crate::{
+    merge_frame_values, ClassFileError, ClassFileResult, ClassHierarchy, FrameValue, NewArrayType,
+    Opcode,
+};
+use derive_more::Debug;
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+impl Value for FrameValue<'_> {
+    fn size(&self) -> usize {
+        match self {
+            FrameValue::Long | FrameValue::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// An [`Interpreter`] which computes the verification type of each value, and checks that each
+/// operand is assignable to the type the instruction expects: the receivers and values of fields
+/// and methods, the element types of arrays, `athrow` and return values. Subtyping is decided by
+/// a [`ClassHierarchy`], in the same way as when the [`ClassWriter`](crate::ClassWriter) computes
+/// frames, and failures are reported as [`ClassFileError::NotAssignable`].
+///
+/// Like the JVM's verifier, interfaces are treated as `java/lang/Object`. Objects are considered
+/// initialized as soon as they are created.
+#[derive(Debug, Clone)]
+pub struct SimpleVerifier {
+    #[debug(skip)]
+    class_hierarchy: Arc<dyn ClassHierarchy>,
+}
+
+impl SimpleVerifier {
+    pub fn new(class_hierarchy: Arc<dyn ClassHierarchy>) -> Self {
+        SimpleVerifier { class_hierarchy }
+    }
+
+    /// Whether a value of type `value` can be used where `expected` is expected.
+    pub fn is_assignable(
+        &self,
+        expected: &FrameValue<'_>,
+        value: &FrameValue<'_>,
+    ) -> ClassFileResult<bool> {
+        Ok(match (expected, value) {
+            _ if expected == value => true,
+            (FrameValue::Top, _) => true,
+            (FrameValue::Class(_), FrameValue::Null) => true,
+            (FrameValue::Class(expected), FrameValue::Class(value)) => {
+                self.is_class_assignable(expected, value)?
+            }
+            _ => false,
+        })
+    }
+
+    fn is_class_assignable(&self, expected: &JavaStr, value: &JavaStr) -> ClassFileResult<bool> {
+        if expected == value || expected == "java/lang/Object" {
+            return Ok(true);
+        }
+        if let Some(expected_element) = expected.strip_prefix('[') {
+            let Some(value_element) = value.strip_prefix('[') else {
+                return Ok(false);
+            };
+            return match (
+                reference_name(expected_element),
+                reference_name(value_element),
+            ) {
+                (Some(expected), Some(value)) => self.is_class_assignable(expected, value),
+                _ => Ok(expected_element == value_element),
+            };
+        }
+        if value.starts_with('[') {
+            return Ok(expected == "java/lang/Cloneable" || expected == "java/io/Serializable");
+        }
+        if self.class_hierarchy.is_interface(expected)? {
+            return Ok(true);
+        }
+        let mut current = self.class_hierarchy.super_class(value)?;
+        while let Some(class) = current {
+            if class == *expected {
+                return Ok(true);
+            }
+            current = self.class_hierarchy.super_class(&class)?;
+        }
+        Ok(false)
+    }
+
+    fn check(&self, expected: &FrameValue<'_>, value: &FrameValue<'_>) -> ClassFileResult<()> {
+        if !self.is_assignable(expected, value)? {
+            return Err(ClassFileError::NotAssignable {
+                expected: to_static(expected),
+                actual: to_static(value),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_reference(&self, value: &FrameValue<'_>) -> ClassFileResult<()> {
+        self.check(&class("java/lang/Object"), value)
+    }
+}
+
+fn class(name: &'static str) -> FrameValue<'static> {
+    FrameValue::Class(Cow::Borrowed(JavaStr::from_str(name)))
+}
+
+fn to_static(value: &FrameValue<'_>) -> FrameValue<'static> {
+    match value {
+        FrameValue::Top => FrameValue::Top,
+        FrameValue::Integer => FrameValue::Integer,
+        FrameValue::Float => FrameValue::Float,
+        FrameValue::Long => FrameValue::Long,
+        FrameValue::Double => FrameValue::Double,
+        FrameValue::Null => FrameValue::Null,
+        FrameValue::UninitializedThis => FrameValue::UninitializedThis,
+        FrameValue::Class(name) => FrameValue::Class(Cow::Owned(name.as_ref().to_owned())),
+        FrameValue::Uninitialized(label) => FrameValue::Uninitialized(*label),
+    }
+}
+
+/// The internal name of an object or array descriptor.
+fn reference_name(desc: &JavaStr) -> Option<&JavaStr> {
+    if desc.starts_with('[') {
+        Some(desc)
+    } else {
+        desc.strip_prefix('L')?.strip_suffix(';')
+    }
+}
+
+/// The verification type of a field descriptor.
+fn desc_value<'class>(desc: &JavaStr) -> FrameValue<'class> {
+    match desc.as_bytes().first() {
+        Some(b'Z' | b'B' | b'C' | b'S' | b'I') => FrameValue::Integer,
+        Some(b'F') => FrameValue::Float,
+        Some(b'J') => FrameValue::Long,
+        Some(b'D') => FrameValue::Double,
+        _ => match reference_name(desc) {
+            Some(name) => FrameValue::Class(Cow::Owned(name.to_owned())),
+            None => FrameValue::Top,
+        },
+    }
+}
+
+/// The type of an array of the given element descriptor.
+fn array_of<'class>(element: &JavaStr) -> FrameValue<'class> {
+    let mut array = JavaString::from("[");
+    array.push_java_str(element);
+    FrameValue::Class(Cow::Owned(array))
+}
+
+/// The types of the operands of an instruction whose opcode determines them, and its result.
+fn typed_operation<'class>(
+    opcode: Opcode,
+) -> Option<(Vec<FrameValue<'class>>, FrameValue<'class>)> {
+    use FrameValue::{Double as D, Float as F, Integer as I, Long as J};
+    Some(match opcode {
+        Opcode::IAdd
+        | Opcode::ISub
+        | Opcode::IMul
+        | Opcode::IDiv
+        | Opcode::IRem
+        | Opcode::IShl
+        | Opcode::IShr
+        | Opcode::IUShr
+        | Opcode::IAnd
+        | Opcode::IOr
+        | Opcode::IXor => (vec![I, I], I),
+        Opcode::LAdd
+        | Opcode::LSub
+        | Opcode::LMul
+        | Opcode::LDiv
+        | Opcode::LRem
+        | Opcode::LAnd
+        | Opcode::LOr
+        | Opcode::LXor => (vec![J, J], J),
+        Opcode::LShl | Opcode::LShr | Opcode::LUShr => (vec![J, I], J),
+        Opcode::FAdd | Opcode::FSub | Opcode::FMul | Opcode::FDiv | Opcode::FRem => (vec![F, F], F),
+        Opcode::DAdd | Opcode::DSub | Opcode::DMul | Opcode::DDiv | Opcode::DRem => (vec![D, D], D),
+        Opcode::INeg | Opcode::I2b | Opcode::I2c | Opcode::I2s => (vec![I], I),
+        Opcode::LNeg => (vec![J], J),
+        Opcode::FNeg => (vec![F], F),
+        Opcode::DNeg => (vec![D], D),
+        Opcode::I2l => (vec![I], J),
+        Opcode::I2f => (vec![I], F),
+        Opcode::I2d => (vec![I], D),
+        Opcode::L2i => (vec![J], I),
+        Opcode::L2f => (vec![J], F),
+        Opcode::L2d => (vec![J], D),
+        Opcode::F2i => (vec![F], I),
+        Opcode::F2l => (vec![F], J),
+        Opcode::F2d => (vec![F], D),
+        Opcode::D2i => (vec![D], I),
+        Opcode::D2l => (vec![D], J),
+        Opcode::D2f => (vec![D], F),
+        Opcode::LCmp => (vec![J, J], I),
+        Opcode::FCmpL | Opcode::FCmpG => (vec![F, F], I),
+        Opcode::DCmpL | Opcode::DCmpG => (vec![D, D], I),
+        _ => return None,
+    })
+}
+
+/// The array type read by an array load or written by an array store, and the type of its
+/// elements on the stack. Reference arrays are handled separately.
+fn primitive_array<'class>(
+    opcode: Opcode,
+) -> Option<(&'static [&'static str], FrameValue<'class>)> {
+    Some(match opcode {
+        Opcode::IALoad | Opcode::IAStore => (&["[I"], FrameValue::Integer),
+        Opcode::LALoad | Opcode::LAStore => (&["[J"], FrameValue::Long),
+        Opcode::FALoad | Opcode::FAStore => (&["[F"], FrameValue::Float),
+        Opcode::DALoad | Opcode::DAStore => (&["[D"], FrameValue::Double),
+        Opcode::BALoad | Opcode::BAStore => (&["[B", "[Z"], FrameValue::Integer),
+        Opcode::CALoad | Opcode::CAStore => (&["[C"], FrameValue::Integer),
+        Opcode::SALoad | Opcode::SAStore => (&["[S"], FrameValue::Integer),
+        _ => return None,
+    })
+}
+
+impl SimpleVerifier {
+    fn check_primitive_array(
+        &self,
+        arrays: &[&'static str],
+        value: &FrameValue<'_>,
+    ) -> ClassFileResult<()> {
+        let matches = match value {
+            FrameValue::Null => true,
+            FrameValue::Class(name) => arrays.iter().any(|array| name.as_ref() == *array),
+            _ => false,
+        };
+        if !matches {
+            return Err(ClassFileError::NotAssignable {
+                expected: class(arrays[0]),
+                actual: to_static(value),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that a value is an array of references, returning the type of its elements.
+    fn check_reference_array<'class>(
+        &self,
+        value: &FrameValue<'class>,
+    ) -> ClassFileResult<FrameValue<'class>> {
+        if let FrameValue::Null = value {
+            return Ok(FrameValue::Null);
+        }
+        if let FrameValue::Class(name) = value {
+            if let Some(element) = name.strip_prefix('[').and_then(reference_name) {
+                return Ok(FrameValue::Class(Cow::Owned(element.to_owned())));
+            }
+        }
+        Err(ClassFileError::NotAssignable {
+            expected: class("[Ljava/lang/Object;"),
+            actual: to_static(value),
+        })
+    }
+}
+
+impl<'class> Interpreter<'class> for SimpleVerifier {
+    type Value = FrameValue<'class>;
+
+    fn new_value(&mut self, desc: &JavaStr) -> FrameValue<'class> {
+        desc_value(desc)
+    }
+
+    fn new_empty_value(&mut self, _local: usize) -> FrameValue<'class> {
+        FrameValue::Top
+    }
+
+    fn new_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+    ) -> ClassFileResult<FrameValue<'class>> {
+        Ok(match insn {
+            InsnNode::Insn(Opcode::AConstNull) => FrameValue::Null,
+            InsnNode::Insn(Opcode::LConst0 | Opcode::LConst1) => FrameValue::Long,
+            InsnNode::Insn(Opcode::FConst0 | Opcode::FConst1 | Opcode::FConst2) => {
+                FrameValue::Float
+            }
+            InsnNode::Insn(Opcode::DConst0 | Opcode::DConst1) => FrameValue::Double,
+            InsnNode::LdcInsn(constant) => ldc_value(constant),
+            InsnNode::FieldInsn { desc, .. } => desc_value(desc),
+            InsnNode::TypeInsn { ty, .. } => FrameValue::Class(ty.clone()),
+            _ => FrameValue::Integer,
+        })
+    }
+
+    fn copy_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &FrameValue<'class>,
+    ) -> ClassFileResult<FrameValue<'class>> {
+        let expected = match insn {
+            InsnNode::VarInsn { opcode, .. } => match opcode {
+                Opcode::ILoad | Opcode::IStore => FrameValue::Integer,
+                Opcode::LLoad | Opcode::LStore => FrameValue::Long,
+                Opcode::FLoad | Opcode::FStore => FrameValue::Float,
+                Opcode::DLoad | Opcode::DStore => FrameValue::Double,
+                _ => {
+                    // uninitialized objects may be loaded and stored too
+                    if !matches!(
+                        value,
+                        FrameValue::UninitializedThis | FrameValue::Uninitialized(_)
+                    ) {
+                        self.check_reference(value)?;
+                    }
+                    return Ok(value.clone());
+                }
+            },
+            _ => return Ok(value.clone()),
+        };
+        self.check(&expected, value)?;
+        Ok(value.clone())
+    }
+
+    fn unary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &FrameValue<'class>,
+    ) -> ClassFileResult<Option<FrameValue<'class>>> {
+        Ok(match insn {
+            InsnNode::Insn(opcode) => {
+                if let Some((operands, result)) = typed_operation(*opcode) {
+                    self.check(&operands[0], value)?;
+                    return Ok(Some(result));
+                }
+                match opcode {
+                    Opcode::IReturn => self.check(&FrameValue::Integer, value)?,
+                    Opcode::LReturn => self.check(&FrameValue::Long, value)?,
+                    Opcode::FReturn => self.check(&FrameValue::Float, value)?,
+                    Opcode::DReturn => self.check(&FrameValue::Double, value)?,
+                    Opcode::AReturn | Opcode::MonitorEnter | Opcode::MonitorExit => {
+                        self.check_reference(value)?
+                    }
+                    Opcode::AThrow => self.check(&class("java/lang/Throwable"), value)?,
+                    Opcode::ArrayLength => {
+                        let is_array = match value {
+                            FrameValue::Null => true,
+                            FrameValue::Class(name) => name.starts_with('['),
+                            _ => false,
+                        };
+                        if !is_array {
+                            return Err(ClassFileError::NotAssignable {
+                                expected: class("[Ljava/lang/Object;"),
+                                actual: to_static(value),
+                            });
+                        }
+                        return Ok(Some(FrameValue::Integer));
+                    }
+                    _ => {}
+                }
+                None
+            }
+            InsnNode::IIncInsn { .. } => {
+                self.check(&FrameValue::Integer, value)?;
+                Some(FrameValue::Integer)
+            }
+            InsnNode::NewArrayInsn(ty) => {
+                self.check(&FrameValue::Integer, value)?;
+                let element = match ty {
+                    NewArrayType::Boolean => "Z",
+                    NewArrayType::Char => "C",
+                    NewArrayType::Float => "F",
+                    NewArrayType::Double => "D",
+                    NewArrayType::Byte => "B",
+                    NewArrayType::Short => "S",
+                    NewArrayType::Int => "I",
+                    NewArrayType::Long => "J",
+                };
+                Some(array_of(JavaStr::from_str(element)))
+            }
+            InsnNode::TypeInsn { opcode, ty } => match opcode {
+                Opcode::ANewArray => {
+                    self.check(&FrameValue::Integer, value)?;
+                    if ty.starts_with('[') {
+                        Some(array_of(ty))
+                    } else {
+                        let mut element = JavaString::from("L");
+                        element.push_java_str(ty);
+                        element.push(';');
+                        Some(array_of(&element))
+                    }
+                }
+                Opcode::CheckCast => {
+                    self.check_reference(value)?;
+                    Some(FrameValue::Class(ty.clone()))
+                }
+                _ => {
+                    self.check_reference(value)?;
+                    Some(FrameValue::Integer)
+                }
+            },
+            InsnNode::FieldInsn {
+                opcode,
+                owner,
+                desc,
+                ..
+            } => {
+                if *opcode == Opcode::PutStatic {
+                    self.check(&desc_value(desc), value)?;
+                    None
+                } else {
+                    self.check(&FrameValue::Class(owner.clone()), value)?;
+                    Some(desc_value(desc))
+                }
+            }
+            InsnNode::JumpInsn {
+                opcode: Opcode::IfNull | Opcode::IfNonNull,
+                ..
+            } => {
+                self.check_reference(value)?;
+                None
+            }
+            _ => {
+                self.check(&FrameValue::Integer, value)?;
+                None
+            }
+        })
+    }
+
+    fn binary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &FrameValue<'class>,
+        value2: &FrameValue<'class>,
+    ) -> ClassFileResult<Option<FrameValue<'class>>> {
+        Ok(match insn {
+            InsnNode::Insn(Opcode::AALoad) => {
+                let element = self.check_reference_array(value1)?;
+                self.check(&FrameValue::Integer, value2)?;
+                Some(element)
+            }
+            InsnNode::Insn(opcode) => {
+                if let Some((arrays, element)) = primitive_array(*opcode) {
+                    self.check_primitive_array(arrays, value1)?;
+                    self.check(&FrameValue::Integer, value2)?;
+                    return Ok(Some(element));
+                }
+                let (operands, result) =
+                    typed_operation(*opcode).expect("binary operation has typed operands");
+                self.check(&operands[0], value1)?;
+                self.check(&operands[1], value2)?;
+                Some(result)
+            }
+            InsnNode::FieldInsn { owner, desc, .. } => {
+                self.check(&FrameValue::Class(owner.clone()), value1)?;
+                self.check(&desc_value(desc), value2)?;
+                None
+            }
+            InsnNode::JumpInsn {
+                opcode: Opcode::IfACmpEq | Opcode::IfACmpNe,
+                ..
+            } => {
+                self.check_reference(value1)?;
+                self.check_reference(value2)?;
+                None
+            }
+            _ => {
+                self.check(&FrameValue::Integer, value1)?;
+                self.check(&FrameValue::Integer, value2)?;
+                None
+            }
+        })
+    }
+
+    fn ternary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &FrameValue<'class>,
+        value2: &FrameValue<'class>,
+        value3: &FrameValue<'class>,
+    ) -> ClassFileResult<()> {
+        let InsnNode::Insn(opcode) = insn else {
+            return Ok(());
+        };
+        match primitive_array(*opcode) {
+            Some((arrays, element)) => {
+                self.check_primitive_array(arrays, value1)?;
+                self.check(&element, value3)?;
+            }
+            None => {
+                // the element type of a covariant array is only checked at runtime
+                self.check_reference_array(value1)?;
+                self.check_reference(value3)?;
+            }
+        }
+        self.check(&FrameValue::Integer, value2)
+    }
+
+    fn nary_operation(
+        &mut self,
+        _handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        values: &[FrameValue<'class>],
+    ) -> ClassFileResult<Option<FrameValue<'class>>> {
+        let (arguments, desc) = match insn {
+            InsnNode::MethodInsn {
+                opcode,
+                owner,
+                desc,
+                ..
+            } => {
+                let arguments = if *opcode == Opcode::InvokeStatic {
+                    values
+                } else {
+                    self.check(&FrameValue::Class(owner.clone()), &values[0])?;
+                    &values[1..]
+                };
+                (arguments, desc)
+            }
+            InsnNode::InvokeDynamicInsn { desc, .. } => (values, desc),
+            InsnNode::MultiANewArrayInsn { desc, .. } => {
+                for value in values {
+                    self.check(&FrameValue::Integer, value)?;
+                }
+                return Ok(Some(FrameValue::Class(desc.clone())));
+            }
+            _ => return Ok(None),
+        };
+
+        let (argument_types, return_type) = method_desc_types(desc);
+        for (argument_type, value) in argument_types.into_iter().zip(arguments) {
+            self.check(&desc_value(argument_type), value)?;
+        }
+        Ok((return_type != "V").then(|| desc_value(return_type)))
+    }
+
+    fn return_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        value: &FrameValue<'class>,
+        expected: &FrameValue<'class>,
+    ) -> ClassFileResult<()> {
+        self.check(expected, value)
+    }
+
+    fn merge(
+        &mut self,
+        value1: &FrameValue<'class>,
+        value2: &FrameValue<'class>,
+    ) -> ClassFileResult<FrameValue<'class>> {
+        merge_frame_values(&*self.class_hierarchy, value1, value2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{Analyzer, SimpleVerifier};
+    use crate::tree::{ClassNode, InsnNode, MethodNode};
+    use crate::{
+        ClassFileError, ClassReader, ClassReaderFlags, FrameValue, LabelCreator, MethodAccess,
+        Opcode, SimpleClassHierarchy,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+    use std::sync::Arc;
+    use test_helpers::include_class;
+
+    fn str(value: &str) -> Cow<'_, JavaStr> {
+        Cow::Borrowed(JavaStr::from_str(value))
+    }
+
+    #[test]
+    fn test_simple_verifier() {
+        let mut hierarchy = SimpleClassHierarchy::new();
+        for (name, super_class) in [
+            ("java/lang/String", "java/lang/Object"),
+            ("java/lang/Throwable", "java/lang/Object"),
+            ("java/lang/Exception", "java/lang/Throwable"),
+            ("java/lang/RuntimeException", "java/lang/Exception"),
+            ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+            (
+                "java/lang/NullPointerException",
+                "java/lang/RuntimeException",
+            ),
+            ("java/lang/StringBuilder", "java/lang/Object"),
+            ("TestCode", "java/lang/Object"),
+        ] {
+            hierarchy.insert(name, Some(super_class.into()), false);
+        }
+        hierarchy.insert("java/lang/Runnable", None, true);
+        let hierarchy = Arc::new(hierarchy);
+
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let mut analyzer = Analyzer::new(SimpleVerifier::new(hierarchy.clone()));
+        for method in &class.methods {
+            analyzer.analyze(&class.name, method).unwrap();
+        }
+
+        let mut method = MethodNode::new(
+            MethodAccess::Static,
+            str("length"),
+            str("(Ljava/lang/Object;)I"),
+        );
+        method.label_creator = Some(LabelCreator::default());
+        method.instructions.extend([
+            InsnNode::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 0,
+            },
+            InsnNode::MethodInsn {
+                opcode: Opcode::InvokeVirtual,
+                owner: str("java/lang/String"),
+                name: str("length"),
+                desc: str("()I"),
+                is_interface: false,
+            },
+            InsnNode::Insn(Opcode::IReturn),
+        ]);
+        let Err(ClassFileError::AnalysisFailed { index, source, .. }) =
+            analyzer.analyze(JavaStr::from_str("Test"), &method)
+        else {
+            panic!("expected the receiver to be rejected");
+        };
+        assert_eq!(1, index);
+        assert_eq!(
+            ClassFileError::NotAssignable {
+                expected: FrameValue::Class(str("java/lang/String")),
+                actual: FrameValue::Class(str("java/lang/Object")),
+            },
+            *source
+        );
+
+        method.instructions.insert_after(
+            method.instructions.first().unwrap(),
+            InsnNode::TypeInsn {
+                opcode: Opcode::CheckCast,
+                ty: str("java/lang/String"),
+            },
+        );
+        analyzer
+            .analyze(JavaStr::from_str("Test"), &method)
+            .unwrap();
+
+        let verifier = SimpleVerifier::new(hierarchy);
+        let assignable = |expected: &str, value: &str| {
+            verifier
+                .is_assignable(
+                    &FrameValue::Class(str(expected)),
+                    &FrameValue::Class(str(value)),
+                )
+                .unwrap()
+        };
+        assert!(assignable(
+            "java/lang/Throwable",
+            "java/lang/ClassCastException"
+        ));
+        assert!(!assignable("java/lang/String", "java/lang/Throwable"));
+        assert!(assignable("java/lang/Runnable", "java/lang/String"));
+        assert!(assignable("[Ljava/lang/Object;", "[Ljava/lang/String;"));
+        assert!(assignable("java/io/Serializable", "[I"));
+        assert!(!assignable("[Ljava/lang/Object;", "[I"));
+        assert!(!assignable("[J", "[I"));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::analysis::{BasicInterpreter, BasicValue, Interpreter, Value};
+use crate::tree::{InsnHandle, InsnNode};
+use crate::ClassFileResult;
+use java_string::JavaStr;
+use std::collections::{BTreeSet, HashMap};
+
+/// A value along with the instructions which may have produced it. Parameters, unset locals and
+/// caught exceptions have no instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceValue {
+    pub size: usize,
+    pub insns: BTreeSet<InsnHandle>,
+}
+
+impl SourceValue {
+    pub fn new(size: usize) -> Self {
+        SourceValue {
+            size,
+            insns: BTreeSet::new(),
+        }
+    }
+
+    pub fn from_insn(size: usize, insn: InsnHandle) -> Self {
+        SourceValue {
+            size,
+            insns: BTreeSet::from([insn]),
+        }
+    }
+}
+
+impl Value for SourceValue {
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// An [`Interpreter`] which tracks the instructions that may have produced each value, giving the
+/// use-def chains of a method: the frame before an instruction tells which instructions produced
+/// each of its operands. It also records the def-use chains, see [`sources`] and [`users`].
+///
+/// Loads and stores count as producing the value they copy, so the source of an operand loaded
+/// from a local is the load, whose own source is the stores to that local.
+///
+/// [`sources`]: SourceInterpreter::sources
+/// [`users`]: SourceInterpreter::users
+#[derive(Debug, Default, Clone)]
+pub struct SourceInterpreter {
+    basic: BasicInterpreter,
+    sources: HashMap<InsnHandle, BTreeSet<InsnHandle>>,
+}
+
+impl SourceInterpreter {
+    pub fn new() -> Self {
+        SourceInterpreter::default()
+    }
+
+    /// The instructions which may have produced any of the operands of the given instruction.
+    pub fn sources(&self, insn: InsnHandle) -> impl Iterator<Item = InsnHandle> + '_ {
+        self.sources.get(&insn).into_iter().flatten().copied()
+    }
+
+    /// The instructions which may use a value produced by the given instruction as an operand.
+    pub fn users(&self, insn: InsnHandle) -> BTreeSet<InsnHandle> {
+        self.sources
+            .iter()
+            .filter(|(_, sources)| sources.contains(&insn))
+            .map(|(user, _)| *user)
+            .collect()
+    }
+
+    /// Records the sources of an operand. Operands only gain sources as the analysis goes on, so
+    /// the union over all executions of an instruction is its final set of sources.
+    fn record<'a>(
+        &mut self,
+        insn: InsnHandle,
+        operands: impl IntoIterator<Item = &'a SourceValue>,
+    ) {
+        let sources = self.sources.entry(insn).or_default();
+        for operand in operands {
+            sources.extend(operand.insns.iter().copied());
+        }
+    }
+}
+
+/// The result sizes come from a [`BasicInterpreter`], which only looks at the instruction and not
+/// at its operands.
+const ANY: BasicValue = BasicValue::Uninitialized;
+
+impl<'class> Interpreter<'class> for SourceInterpreter {
+    type Value = SourceValue;
+
+    fn new_value(&mut self, desc: &JavaStr) -> SourceValue {
+        SourceValue::new(
+            <BasicInterpreter as Interpreter<'class>>::new_value(&mut self.basic, desc).size(),
+        )
+    }
+
+    fn new_empty_value(&mut self, _local: usize) -> SourceValue {
+        SourceValue::new(1)
+    }
+
+    fn new_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+    ) -> ClassFileResult<SourceValue> {
+        let size = self.basic.new_operation(handle, insn)?.size();
+        Ok(SourceValue::from_insn(size, handle))
+    }
+
+    fn copy_operation(
+        &mut self,
+        handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        value: &SourceValue,
+    ) -> ClassFileResult<SourceValue> {
+        self.record(handle, [value]);
+        Ok(SourceValue::from_insn(value.size, handle))
+    }
+
+    fn unary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value: &SourceValue,
+    ) -> ClassFileResult<Option<SourceValue>> {
+        self.record(handle, [value]);
+        let result = self.basic.unary_operation(handle, insn, &ANY)?;
+        Ok(result.map(|result| SourceValue::from_insn(result.size(), handle)))
+    }
+
+    fn binary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        value1: &SourceValue,
+        value2: &SourceValue,
+    ) -> ClassFileResult<Option<SourceValue>> {
+        self.record(handle, [value1, value2]);
+        let result = self.basic.binary_operation(handle, insn, &ANY, &ANY)?;
+        Ok(result.map(|result| SourceValue::from_insn(result.size(), handle)))
+    }
+
+    fn ternary_operation(
+        &mut self,
+        handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        value1: &SourceValue,
+        value2: &SourceValue,
+        value3: &SourceValue,
+    ) -> ClassFileResult<()> {
+        self.record(handle, [value1, value2, value3]);
+        Ok(())
+    }
+
+    fn nary_operation(
+        &mut self,
+        handle: InsnHandle,
+        insn: &InsnNode<'class>,
+        values: &[SourceValue],
+    ) -> ClassFileResult<Option<SourceValue>> {
+        self.record(handle, values);
+        let result = self.basic.nary_operation(handle, insn, &[])?;
+        Ok(result.map(|result| SourceValue::from_insn(result.size(), handle)))
+    }
+
+    fn return_operation(
+        &mut self,
+        _handle: InsnHandle,
+        _insn: &InsnNode<'class>,
+        _value: &SourceValue,
+        _expected: &SourceValue,
+    ) -> ClassFileResult<()> {
+        Ok(())
+    }
+
+    fn merge(
+        &mut self,
+        value1: &SourceValue,
+        value2: &SourceValue,
+    ) -> ClassFileResult<SourceValue> {
+        if value2.size == value1.size && value2.insns.is_subset(&value1.insns) {
+            return Ok(value1.clone());
+        }
+        Ok(SourceValue {
+            size: value1.size.min(value2.size),
+            insns: value1.insns.union(&value2.insns).copied().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{Analyzer, SourceInterpreter};
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{ClassReader, ClassReaderFlags, Opcode};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_source_interpreter() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "switches")
+            .unwrap();
+        let mut analyzer = Analyzer::new(SourceInterpreter::new());
+        analyzer.analyze(&class.name, method).unwrap();
+
+        let length = method
+            .instructions
+            .handles()
+            .find(|&handle| {
+                matches!(&method.instructions[handle], InsnNode::MethodInsn { name, .. } if name.as_ref() == "length")
+            })
+            .unwrap();
+        let receiver = analyzer.frame(length).unwrap().stack.last().unwrap();
+        assert_eq!(1, receiver.insns.len());
+        let load = *receiver.insns.first().unwrap();
+        assert_eq!(
+            InsnNode::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 2
+            },
+            method.instructions[load]
+        );
+
+        let interpreter = analyzer.interpreter();
+        assert_eq!(vec![load], interpreter.sources(length).collect::<Vec<_>>());
+        assert!(interpreter.users(load).contains(&length));
+        // the parameter isn't produced by an instruction
+        assert_eq!(0, interpreter.sources(load).count());
+
+        // the result is the sum of the stores in each branch of the switches
+        let add = method
+            .instructions
+            .handles()
+            .filter(|&handle| method.instructions[handle] == InsnNode::Insn(Opcode::IAdd))
+            .last()
+            .unwrap();
+        let result = &analyzer.frame(add).unwrap().stack[0];
+        let load = *result.insns.first().unwrap();
+        let stores = interpreter.sources(load).collect::<Vec<_>>();
+        assert!(stores.len() > 1);
+        for store in stores {
+            assert!(matches!(
+                method.instructions[store],
+                InsnNode::VarInsn {
+                    opcode: Opcode::IStore,
+                    ..
+                } | InsnNode::IIncInsn { .. }
+            ));
+        }
+    }
+}
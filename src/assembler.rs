@@ -0,0 +1,794 @@
+use crate::tree::{BufferedFieldEvent, BufferedMethodEvent};
+use crate::validation::has_no_operands;
+use crate::{
+    BufferedClassEvents, BufferedEventProviders, ClassAccess, ClassClassEvent, ClassEvent,
+    ClassFileError, ClassFileResult, ClassSourceEvent, EventBuffer, FieldAccess, FieldValue, Label,
+    LabelCreator, LdcConstant, MethodAccess, MethodEvent, MethodLocalVariableEvent,
+    MethodMaxsEvent, MethodTryCatchBlockEvent, NewArrayType, Opcode,
+};
+use bitflags::Flags;
+use java_string::{JavaCodePoint, JavaStr, JavaString};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Assembles a class from text in a Jasmin-style syntax, so that test classes, including
+/// malformed ones, can be written by hand without a Java compiler. Nothing is checked beyond what
+/// is needed to parse the text; pass the result to [`check_class`](crate::check_class) to
+/// validate it.
+///
+/// Each line holds one directive, label or instruction. Tokens are separated by whitespace, and a
+/// token starting with `;` starts a comment, so descriptors can still contain `;`. Strings are
+/// quoted with `"`, supporting the escapes `\\`, `\"`, `\n`, `\r`, `\t` and `\uXXXX`.
+///
+/// ```text
+/// .version 52 0
+/// .class public super Example
+/// .super java/lang/Object
+/// .implements java/lang/Runnable
+/// .source "Example.java"
+/// .field private static final COUNT I = 3
+///
+/// .method public run ()V
+///     .limit stack 2
+///     .limit locals 1
+///     .line 7
+///     getstatic java/lang/System out Ljava/io/PrintStream;
+///     ldc "Hello"
+///     invokevirtual java/io/PrintStream println (Ljava/lang/String;)V
+///     return
+/// .end method
+/// ```
+///
+/// The class directives are:
+/// - `.version <major> [<minor>]`, defaulting to Java 8.
+/// - `.class <access>* <name>`, which must come before any members.
+/// - `.super <name>`, and `.implements <name>` for each interface.
+/// - `.source <file>`.
+/// - `.signature <signature>`, applying to the last class, field or method declared.
+/// - `.field <access>* <name> <desc> [= <constant>]`.
+/// - `.method <access>* <name> <desc>`, ended by `.end method`.
+///
+/// Inside a method, the directives are:
+/// - `.throws <name>`.
+/// - `.limit stack <n>` and `.limit locals <n>`. Without them the method has no
+///   [`MethodEvent::Maxs`], so it should be written with
+///   [`ClassWriterFlags::ComputeMaxs`](crate::ClassWriterFlags::ComputeMaxs).
+/// - `.line <n>`, starting a line number at the next instruction.
+/// - `.catch <type> from <label> to <label> using <label>`, where the type `all` catches
+///   everything.
+/// - `.var <index> is <name> <desc> from <label> to <label>`.
+/// - `<label>:`, placing a label.
+///
+/// Instructions use the mnemonics of [`Opcode`], followed by their operands:
+/// - Local variable indexes for loads, stores and `ret`, and `iinc <index> <increment>`.
+/// - `<owner> <name> <desc>` for field and method instructions. Non-virtual calls of interface
+///   methods are followed by `itf`.
+/// - A label for jumps, and `tableswitch <low> <label>* default <label>` and
+///   `lookupswitch (<value> <label>)* default <label>` for switches.
+/// - A type for `newarray`, such as `int`, and `multianewarray <desc> <dimensions>`.
+/// - `ldc <constant>`, `ldc class <name>` or `ldc methodtype <desc>`.
+///
+/// Constants are integers, numbers suffixed with `L`, `F` or `D` for longs, floats and doubles,
+/// numbers with a decimal point for doubles, and strings. `invokedynamic`, frames, annotations and
+/// attributes are not supported.
+pub fn assemble(input: &str) -> ClassFileResult<BufferedClassEvents<'static>> {
+    let mut assembler = Assembler::default();
+    let mut line_number = 0;
+    for (index, line) in input.lines().enumerate() {
+        line_number = index + 1;
+        let tokens = tokenize(line).map_err(|reason| bad_assembly(line_number, reason))?;
+        if !tokens.is_empty() {
+            assembler
+                .line(&tokens)
+                .map_err(|reason| bad_assembly(line_number, reason))?;
+        }
+    }
+    assembler
+        .finish()
+        .map_err(|reason| bad_assembly(line_number, reason))
+}
+
+fn bad_assembly(line: usize, reason: &'static str) -> ClassFileError {
+    ClassFileError::BadAssembly { line, reason }
+}
+
+type AssembleResult<T> = Result<T, &'static str>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    String(JavaString),
+}
+
+impl Token {
+    fn word(&self) -> AssembleResult<&str> {
+        match self {
+            Token::Word(word) => Ok(word),
+            Token::String(_) => Err("unexpected string"),
+        }
+    }
+
+    /// A name or descriptor, which may be quoted if it contains whitespace.
+    fn string(&self) -> Cow<'static, JavaStr> {
+        match self {
+            Token::Word(word) => Cow::Owned(JavaString::from(word.as_str())),
+            Token::String(string) => Cow::Owned(string.clone()),
+        }
+    }
+}
+
+fn tokenize(line: &str) -> AssembleResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == ';' {
+            break;
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut string = JavaString::new();
+            loop {
+                match chars.next().ok_or("unterminated string")? {
+                    '"' => break,
+                    '\\' => {
+                        let code_point = match chars.next().ok_or("unterminated string")? {
+                            'n' => '\n'.into(),
+                            'r' => '\r'.into(),
+                            't' => '\t'.into(),
+                            '\\' => '\\'.into(),
+                            '"' => '"'.into(),
+                            'u' => unicode_escape(&mut chars)?,
+                            _ => return Err("bad escape"),
+                        };
+                        string.push_java(code_point);
+                    }
+                    c => string.push(c),
+                }
+            }
+            tokens.push(Token::String(string));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses the digits of a `\u` escape, combining surrogate pairs into a single code point.
+fn unicode_escape(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+) -> AssembleResult<JavaCodePoint> {
+    let hex = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        let digits: String = chars.take(4).collect();
+        if digits.len() != 4 {
+            return Err("bad escape");
+        }
+        u32::from_str_radix(&digits, 16).map_err(|_| "bad escape")
+    };
+    let high = hex(chars)?;
+    if (0xd800..0xdc00).contains(&high) {
+        let mut lookahead = chars.clone();
+        if lookahead.next() == Some('\\') && lookahead.next() == Some('u') {
+            if let Ok(low) = hex(&mut lookahead) {
+                if (0xdc00..0xe000).contains(&low) {
+                    *chars = lookahead;
+                    let code_point = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                    return JavaCodePoint::from_u32(code_point).ok_or("bad escape");
+                }
+            }
+        }
+    }
+    JavaCodePoint::from_u32(high).ok_or("bad escape")
+}
+
+/// Which declaration a `.signature` directive applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Declaration {
+    None,
+    Class,
+    Field,
+    Method,
+}
+
+struct Assembler {
+    major_version: u16,
+    minor_version: u16,
+    class: Option<ClassClassEvent<'static>>,
+    source: Option<Cow<'static, JavaStr>>,
+    fields: Vec<BufferedFieldEvent<'static>>,
+    methods: Vec<BufferedMethodEvent<'static>>,
+    method: Option<MethodAssembler>,
+    last_declaration: Declaration,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Assembler {
+            major_version: 52,
+            minor_version: 0,
+            class: None,
+            source: None,
+            fields: Vec::new(),
+            methods: Vec::new(),
+            method: None,
+            last_declaration: Declaration::None,
+        }
+    }
+}
+
+impl Assembler {
+    fn line(&mut self, tokens: &[Token]) -> AssembleResult<()> {
+        let first = tokens[0].word()?;
+        if let Some(method) = &mut self.method {
+            if first == ".end" {
+                if tokens.len() != 2 || tokens[1].word()? != "method" {
+                    return Err("expected `.end method`");
+                }
+                let method = self.method.take().unwrap().finish()?;
+                self.methods.push(method);
+                return Ok(());
+            }
+            if first == ".signature" {
+                method.method.signature = Some(single_operand(tokens)?.string());
+                return Ok(());
+            }
+            return method.line(first, &tokens[1..]);
+        }
+
+        let operands = &tokens[1..];
+        match first {
+            ".version" => {
+                let (major, minor) = match operands {
+                    [major] => (major, None),
+                    [major, minor] => (major, Some(minor)),
+                    _ => return Err("expected `.version <major> [<minor>]`"),
+                };
+                self.major_version = parse_int(major.word()?)?;
+                if let Some(minor) = minor {
+                    self.minor_version = parse_int(minor.word()?)?;
+                }
+            }
+            ".class" => {
+                if self.class.is_some() {
+                    return Err("duplicate `.class`");
+                }
+                let (name, access) = operands.split_last().ok_or("expected class name")?;
+                self.class = Some(ClassClassEvent {
+                    major_version: self.major_version,
+                    minor_version: self.minor_version,
+                    access: parse_access(access)?,
+                    name: name.string(),
+                    signature: None,
+                    super_name: None,
+                    interfaces: Vec::new(),
+                });
+                self.last_declaration = Declaration::Class;
+            }
+            ".super" => {
+                self.class_mut()?.super_name = Some(single_operand(tokens)?.string());
+            }
+            ".implements" => {
+                let interface = single_operand(tokens)?.string();
+                self.class_mut()?.interfaces.push(interface);
+            }
+            ".source" => self.source = Some(single_operand(tokens)?.string()),
+            ".signature" => {
+                let signature = Some(single_operand(tokens)?.string());
+                match self.last_declaration {
+                    Declaration::None => return Err("`.signature` before a declaration"),
+                    Declaration::Class => self.class_mut()?.signature = signature,
+                    Declaration::Field => self.fields.last_mut().unwrap().signature = signature,
+                    Declaration::Method => self.methods.last_mut().unwrap().signature = signature,
+                }
+            }
+            ".field" => {
+                self.class_mut()?;
+                let (declaration, value) =
+                    match operands.iter().position(|token| *token == word("=")) {
+                        Some(index) => match &operands[index + 1..] {
+                            [value] => (&operands[..index], Some(parse_field_value(value)?)),
+                            _ => return Err("expected one constant after `=`"),
+                        },
+                        None => (operands, None),
+                    };
+                let [access @ .., name, desc] = declaration else {
+                    return Err("expected `.field <access>* <name> <desc>`");
+                };
+                self.fields.push(BufferedFieldEvent {
+                    access: parse_access(access)?,
+                    name: name.string(),
+                    desc: desc.string(),
+                    signature: None,
+                    value,
+                    events: EventBuffer(Vec::new()),
+                });
+                self.last_declaration = Declaration::Field;
+            }
+            ".method" => {
+                self.class_mut()?;
+                let [access @ .., name, desc] = operands else {
+                    return Err("expected `.method <access>* <name> <desc>`");
+                };
+                self.method = Some(MethodAssembler::new(BufferedMethodEvent {
+                    access: parse_access(access)?,
+                    name: name.string(),
+                    desc: desc.string(),
+                    signature: None,
+                    exceptions: Vec::new(),
+                    events: EventBuffer(Vec::new()),
+                }));
+                self.last_declaration = Declaration::Method;
+            }
+            _ => return Err("unknown directive"),
+        }
+        Ok(())
+    }
+
+    fn class_mut(&mut self) -> AssembleResult<&mut ClassClassEvent<'static>> {
+        self.class.as_mut().ok_or("expected `.class` first")
+    }
+
+    fn finish(self) -> AssembleResult<BufferedClassEvents<'static>> {
+        if self.method.is_some() {
+            return Err("missing `.end method`");
+        }
+        let class = self.class.ok_or("missing `.class`")?;
+        let mut events = vec![ClassEvent::Class(class)];
+        if self.source.is_some() {
+            events.push(ClassEvent::Source(ClassSourceEvent {
+                source: self.source,
+                debug: None,
+            }));
+        }
+        events.push(ClassEvent::Fields(EventBuffer(self.fields)));
+        events.push(ClassEvent::Methods(EventBuffer(self.methods)));
+        Ok(EventBuffer(events))
+    }
+}
+
+struct MethodAssembler {
+    method: BufferedMethodEvent<'static>,
+    label_creator: LabelCreator,
+    labels: HashMap<String, Label>,
+    placed_labels: HashSet<String>,
+    local_variables: Vec<MethodLocalVariableEvent<'static>>,
+    try_catch_blocks: Vec<MethodTryCatchBlockEvent<'static>>,
+    maxs: Option<MethodMaxsEvent>,
+    has_code: bool,
+}
+
+impl MethodAssembler {
+    fn new(method: BufferedMethodEvent<'static>) -> MethodAssembler {
+        MethodAssembler {
+            method,
+            label_creator: LabelCreator::default(),
+            labels: HashMap::new(),
+            placed_labels: HashSet::new(),
+            local_variables: Vec::new(),
+            try_catch_blocks: Vec::new(),
+            maxs: None,
+            has_code: false,
+        }
+    }
+
+    fn push(&mut self, event: MethodEvent<'static, BufferedEventProviders>) {
+        if !self.has_code {
+            self.has_code = true;
+            self.method.events.0.push(MethodEvent::Code {
+                label_creator: self.label_creator.clone(),
+            });
+        }
+        self.method.events.0.push(event);
+    }
+
+    fn label(&mut self, token: &Token) -> AssembleResult<Label> {
+        let name = token.word()?;
+        if let Some(label) = self.labels.get(name) {
+            return Ok(*label);
+        }
+        let label = self.label_creator.create_label();
+        self.labels.insert(name.to_owned(), label);
+        Ok(label)
+    }
+
+    fn line(&mut self, first: &str, operands: &[Token]) -> AssembleResult<()> {
+        if let Some(name) = first.strip_suffix(':') {
+            if !operands.is_empty() {
+                return Err("expected a label on its own line");
+            }
+            if !self.placed_labels.insert(name.to_owned()) {
+                return Err("duplicate label");
+            }
+            let label = self.label(&word(name))?;
+            self.push(MethodEvent::Label(label));
+            return Ok(());
+        }
+
+        match first {
+            ".throws" => {
+                let [exception] = operands else {
+                    return Err("expected `.throws <name>`");
+                };
+                self.method.exceptions.push(exception.string());
+            }
+            ".limit" => {
+                let [kind, value] = operands else {
+                    return Err("expected `.limit stack|locals <n>`");
+                };
+                let value = parse_int(value.word()?)?;
+                let maxs = self.maxs.get_or_insert(MethodMaxsEvent {
+                    max_stack: 0,
+                    max_locals: 0,
+                });
+                match kind.word()? {
+                    "stack" => maxs.max_stack = value,
+                    "locals" => maxs.max_locals = value,
+                    _ => return Err("expected `.limit stack|locals <n>`"),
+                }
+            }
+            ".line" => {
+                let [line] = operands else {
+                    return Err("expected `.line <n>`");
+                };
+                let line = parse_int(line.word()?)?;
+                let start = self.label_creator.create_label();
+                self.push(MethodEvent::Label(start));
+                self.push(MethodEvent::LineNumber { line, start });
+            }
+            ".catch" => {
+                let [ty, from, start, to, end, using, handler] = operands else {
+                    return Err("expected `.catch <type> from <label> to <label> using <label>`");
+                };
+                if [from, to, using] != [&word("from"), &word("to"), &word("using")] {
+                    return Err("expected `.catch <type> from <label> to <label> using <label>`");
+                }
+                let ty = match ty.word()? {
+                    "all" => None,
+                    _ => Some(ty.string()),
+                };
+                let block = MethodTryCatchBlockEvent {
+                    start: self.label(start)?,
+                    end: self.label(end)?,
+                    handler: self.label(handler)?,
+                    ty,
+                };
+                self.try_catch_blocks.push(block);
+            }
+            ".var" => {
+                let [index, is, name, desc, from, start, to, end] = operands else {
+                    return Err("expected `.var <index> is <name> <desc> from <label> to <label>`");
+                };
+                if [is, from, to] != [&word("is"), &word("from"), &word("to")] {
+                    return Err("expected `.var <index> is <name> <desc> from <label> to <label>`");
+                }
+                let local = MethodLocalVariableEvent {
+                    name: name.string(),
+                    desc: desc.string(),
+                    signature: None,
+                    start: self.label(start)?,
+                    end: self.label(end)?,
+                    index: parse_int(index.word()?)?,
+                };
+                self.local_variables.push(local);
+            }
+            _ if first.starts_with('.') => return Err("unknown directive"),
+            _ => {
+                let opcode = parse_opcode(first)?;
+                let insn = self.insn(opcode, operands)?;
+                self.push(insn);
+            }
+        }
+        Ok(())
+    }
+
+    fn insn(
+        &mut self,
+        opcode: Opcode,
+        operands: &[Token],
+    ) -> AssembleResult<MethodEvent<'static, BufferedEventProviders>> {
+        let insn = match (opcode, operands) {
+            (_, []) if has_no_operands(opcode) => MethodEvent::Insn(opcode),
+            (Opcode::BIPush, [value]) => MethodEvent::BIPushInsn(parse_int(value.word()?)?),
+            (Opcode::SIPush, [value]) => MethodEvent::SIPushInsn(parse_int(value.word()?)?),
+            (Opcode::NewArray, [ty]) => MethodEvent::NewArrayInsn(parse_new_array_type(ty)?),
+            (Opcode::Ldc, [constant]) => MethodEvent::LdcInsn(parse_constant(constant)?),
+            (Opcode::Ldc, [kind, value]) => match kind.word()? {
+                "class" => MethodEvent::LdcInsn(LdcConstant::Class(value.string())),
+                "methodtype" => MethodEvent::LdcInsn(LdcConstant::MethodType(value.string())),
+                _ => return Err("expected `ldc class|methodtype <value>`"),
+            },
+            (Opcode::IInc, [var_index, increment]) => MethodEvent::IIncInsn {
+                var_index: parse_int(var_index.word()?)?,
+                increment: parse_int(increment.word()?)?,
+            },
+            (_, [var_index]) if matches!(opcode as u8, 21..=25 | 54..=58 | 169) => {
+                MethodEvent::VarInsn {
+                    opcode,
+                    var_index: parse_int(var_index.word()?)?,
+                }
+            }
+            (Opcode::New | Opcode::ANewArray | Opcode::CheckCast | Opcode::Instanceof, [ty]) => {
+                MethodEvent::TypeInsn {
+                    opcode,
+                    ty: ty.string(),
+                }
+            }
+            (
+                Opcode::GetStatic | Opcode::PutStatic | Opcode::GetField | Opcode::PutField,
+                [owner, name, desc],
+            ) => MethodEvent::FieldInsn {
+                opcode,
+                owner: owner.string(),
+                name: name.string(),
+                desc: desc.string(),
+            },
+            (
+                Opcode::InvokeVirtual
+                | Opcode::InvokeSpecial
+                | Opcode::InvokeStatic
+                | Opcode::InvokeInterface,
+                [owner, name, desc, itf @ ..],
+            ) => {
+                let is_interface = match itf {
+                    [] => opcode == Opcode::InvokeInterface,
+                    [itf] if *itf == word("itf") => true,
+                    _ => return Err("expected `itf` after method instruction"),
+                };
+                MethodEvent::MethodInsn {
+                    opcode,
+                    owner: owner.string(),
+                    name: name.string(),
+                    desc: desc.string(),
+                    is_interface,
+                }
+            }
+            (Opcode::InvokeDynamic, _) => return Err("invokedynamic is not supported"),
+            (_, [label]) if matches!(opcode as u8, 153..=168 | 198 | 199) => {
+                MethodEvent::JumpInsn {
+                    opcode,
+                    label: self.label(label)?,
+                }
+            }
+            (Opcode::TableSwitch, [low, targets @ .., default, dflt])
+                if *default == word("default") =>
+            {
+                let low: i32 = parse_int(low.word()?)?;
+                let high = i32::try_from(i64::from(low) + targets.len() as i64 - 1)
+                    .map_err(|_| "too many tableswitch labels")?;
+                MethodEvent::TableSwitchInsn {
+                    low,
+                    high,
+                    dflt: self.label(dflt)?,
+                    labels: targets
+                        .iter()
+                        .map(|target| self.label(target))
+                        .collect::<AssembleResult<_>>()?,
+                }
+            }
+            (Opcode::LookupSwitch, [cases @ .., default, dflt]) if *default == word("default") => {
+                if cases.len() % 2 != 0 {
+                    return Err("expected `lookupswitch (<value> <label>)* default <label>`");
+                }
+                MethodEvent::LookupSwitchInsn {
+                    dflt: self.label(dflt)?,
+                    values: cases
+                        .chunks(2)
+                        .map(|case| Ok((parse_int(case[0].word()?)?, self.label(&case[1])?)))
+                        .collect::<AssembleResult<_>>()?,
+                }
+            }
+            (Opcode::MultiANewArray, [desc, dimensions]) => MethodEvent::MultiANewArrayInsn {
+                desc: desc.string(),
+                dimensions: parse_int(dimensions.word()?)?,
+            },
+            _ => return Err("wrong operands for instruction"),
+        };
+        Ok(insn)
+    }
+
+    fn finish(mut self) -> AssembleResult<BufferedMethodEvent<'static>> {
+        if self
+            .labels
+            .keys()
+            .any(|name| !self.placed_labels.contains(name))
+        {
+            return Err("label is used but never placed");
+        }
+        if !self.local_variables.is_empty() {
+            let locals = std::mem::take(&mut self.local_variables);
+            self.push(MethodEvent::LocalVariables(EventBuffer(locals)));
+        }
+        if !self.try_catch_blocks.is_empty() {
+            let blocks = std::mem::take(&mut self.try_catch_blocks);
+            self.push(MethodEvent::TryCatchBlocks(EventBuffer(blocks)));
+        }
+        if let Some(maxs) = self.maxs {
+            self.push(MethodEvent::Maxs(maxs));
+        }
+        Ok(self.method)
+    }
+}
+
+fn word(word: &str) -> Token {
+    Token::Word(word.to_owned())
+}
+
+fn single_operand(tokens: &[Token]) -> AssembleResult<&Token> {
+    match tokens {
+        [_, operand] => Ok(operand),
+        _ => Err("expected one operand"),
+    }
+}
+
+fn parse_access<F>(tokens: &[Token]) -> AssembleResult<F>
+where
+    F: Flags<Bits = u16>,
+{
+    let mut bits = 0;
+    for token in tokens {
+        let name = token.word()?;
+        if !name.bytes().all(|b| b.is_ascii_lowercase()) {
+            return Err("unknown access flag");
+        }
+        // flags are named in upper camel case, such as `Public`
+        let name = name[..1].to_ascii_uppercase() + &name[1..];
+        bits |= F::from_name(&name).ok_or("unknown access flag")?.bits();
+    }
+    Ok(F::from_bits_retain(bits))
+}
+
+fn parse_int<T>(word: &str) -> AssembleResult<T>
+where
+    T: TryFrom<i64>,
+{
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .map_err(|_| "bad integer")?;
+    let value = if negative { -value } else { value };
+    T::try_from(value).map_err(|_| "integer out of range")
+}
+
+fn parse_constant(token: &Token) -> AssembleResult<LdcConstant<'static>> {
+    let word = match token {
+        Token::Word(word) => word,
+        Token::String(string) => return Ok(LdcConstant::String(Cow::Owned(string.clone()))),
+    };
+    let constant = if let Some(value) = word.strip_suffix('L') {
+        LdcConstant::Long(parse_int(value)?)
+    } else if let Some(value) = word.strip_suffix(['F', 'f']) {
+        LdcConstant::Float(value.parse().map_err(|_| "bad float")?)
+    } else if let Some(value) = word.strip_suffix(['D', 'd']) {
+        LdcConstant::Double(value.parse().map_err(|_| "bad double")?)
+    } else if word.contains('.') {
+        LdcConstant::Double(word.parse().map_err(|_| "bad double")?)
+    } else {
+        LdcConstant::Integer(parse_int(word)?)
+    };
+    Ok(constant)
+}
+
+fn parse_field_value(token: &Token) -> AssembleResult<FieldValue<'static>> {
+    match parse_constant(token)? {
+        LdcConstant::Integer(value) => Ok(FieldValue::Integer(value)),
+        LdcConstant::Float(value) => Ok(FieldValue::Float(value)),
+        LdcConstant::Long(value) => Ok(FieldValue::Long(value)),
+        LdcConstant::Double(value) => Ok(FieldValue::Double(value)),
+        LdcConstant::String(value) => Ok(FieldValue::String(value)),
+        _ => Err("bad field constant"),
+    }
+}
+
+fn parse_opcode(word: &str) -> AssembleResult<Opcode> {
+    (0..=u8::MAX)
+        .filter_map(|opcode| Opcode::try_from(opcode).ok())
+        .find(|opcode| opcode.to_string() == word)
+        .ok_or("unknown instruction")
+}
+
+fn parse_new_array_type(token: &Token) -> AssembleResult<NewArrayType> {
+    let word = token.word()?;
+    (0..=u8::MAX)
+        .filter_map(|ty| NewArrayType::try_from(ty).ok())
+        .find(|ty| ty.to_string() == word)
+        .ok_or("unknown newarray type")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ClassNode;
+    use crate::{
+        assemble, check_class, ClassFileError, ClassReader, ClassReaderFlags, ClassWriter,
+        ClassWriterFlags, FieldValue, MethodMaxsEvent,
+    };
+    use java_string::JavaStr;
+
+    const LOOP_CLASS: &str = r#"
+        .version 49
+        .class public super Loop
+        .super java/lang/Object
+        .field private static final GREETING Ljava/lang/String; = "hi!"
+
+        .method public static count (I)I
+            .limit stack 2
+            .limit locals 2
+            .var 0 is limit I from start to end
+        start:
+            .line 3
+            iconst_0
+            istore 1
+        loop:
+            iload 1
+            iload 0
+            if_icmpge end
+            iinc 1 1
+            goto loop
+        end:
+            iload 1
+            lookupswitch 0 zero default other ; switch on the result
+        zero:
+            iconst_m1
+            ireturn
+        other:
+            iload 1
+            ireturn
+        .end method
+    "#;
+
+    #[test]
+    fn test_assemble() {
+        let events = assemble(LOOP_CLASS).unwrap();
+        let events = check_class(events).unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(events)
+            .unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        assert_eq!("Loop", class.name.as_ref());
+        assert_eq!(49, class.major_version);
+        assert_eq!(
+            Some(FieldValue::String(JavaStr::from_str("hi!").into())),
+            class.fields[0].value
+        );
+        let method = &class.methods[0];
+        assert_eq!("count", method.name.as_ref());
+        assert_eq!(1, method.local_variables.len());
+        assert_eq!(
+            Some(MethodMaxsEvent {
+                max_stack: 2,
+                max_locals: 2
+            }),
+            method.maxs
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let error = |input| match assemble(input) {
+            Err(ClassFileError::BadAssembly { line, reason }) => (line, reason),
+            result => panic!("expected an error, got {result:?}"),
+        };
+        assert_eq!((1, "expected `.class` first"), error(".field public x I"));
+        assert_eq!(
+            (3, "unknown instruction"),
+            error(".class Foo\n.method m ()V\nfoo\n.end method")
+        );
+        assert_eq!(
+            (4, "label is used but never placed"),
+            error(".class Foo\n.method m ()V\ngoto nowhere\n.end method")
+        );
+    }
+}
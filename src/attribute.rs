@@ -1,4 +1,4 @@
-use crate::{ClassBuffer, ClassFileResult, ClassReader};
+use crate::{ClassBuffer, ClassFileError, ClassFileResult, ClassReader, ConstantPoolBuilder};
 use derive_more::Debug;
 use java_string::{JavaStr, JavaString};
 use std::any::Any;
@@ -7,6 +7,13 @@ pub trait Attribute: Any + std::fmt::Debug {
     fn name(&self) -> &JavaStr;
 
     fn copy(&self) -> Box<dyn Attribute>;
+
+    /// Returns the payload of the attribute, excluding its name and length, adding the constants
+    /// it refers to to the given constant pool. Attributes which don't implement this can't be
+    /// written.
+    fn write(&self, constant_pool: &mut ConstantPoolBuilder) -> ClassFileResult<Vec<u8>> {
+        Err(ClassFileError::UnwritableAttribute(self.name().to_owned()))
+    }
 }
 
 impl Clone for Box<dyn Attribute> {
@@ -32,6 +39,9 @@ impl Clone for Box<dyn AttributeReader> {
     }
 }
 
+/// An attribute without an [`AttributeReader`]. Its data is written unchanged, so any constant pool
+/// indices in it are only valid if the constant pool of the original class is copied, and it can't
+/// be written with [`ClassWriterFlags::Deterministic`](crate::ClassWriterFlags::Deterministic).
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct UnknownAttribute {
     pub name: JavaString,
@@ -47,4 +57,8 @@ impl Attribute for UnknownAttribute {
     fn copy(&self) -> Box<dyn Attribute> {
         Box::new(self.clone())
     }
+
+    fn write(&self, _constant_pool: &mut ConstantPoolBuilder) -> ClassFileResult<Vec<u8>> {
+        Ok(self.data.clone())
+    }
 }
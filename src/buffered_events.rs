@@ -0,0 +1,378 @@
+use crate::tree::{AnnotationNode, TypeAnnotationNode};
+use crate::{
+    AnnotationEvent, Attribute, ClassEvent, ClassEventProviders, ClassEventSource, ClassFieldEvent,
+    ClassFileResult, ClassInnerClassEvent, ClassMethodEvent, ClassModuleEvent,
+    ClassRecordComponentEvent, FieldEvent, FieldEventProviders, MethodEvent, MethodEventProviders,
+    MethodLocalVariableAnnotationEvent, MethodLocalVariableEvent, MethodParameterAnnotationEvent,
+    MethodParameterEvent, MethodTryCatchBlockAnnotationEvent, MethodTryCatchBlockEvent,
+    ModuleEvent, ModuleEventProviders, ModuleProvidesEvent, ModuleRelationEvent,
+    ModuleRequireEvent, RecordComponentEvent, RecordComponentEventProviders,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+/// Events which have been read into memory, so that they can be inspected or modified before
+/// being passed on. Iterating yields each event wrapped in `Ok`.
+#[derive(Debug, Clone)]
+pub struct EventBuffer<T>(pub Vec<T>);
+
+impl<T> IntoIterator for EventBuffer<T> {
+    type Item = ClassFileResult<T>;
+    type IntoIter = std::iter::Map<std::vec::IntoIter<T>, fn(T) -> ClassFileResult<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter().map(Ok)
+    }
+}
+
+impl<T> FromIterator<T> for EventBuffer<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        EventBuffer(iter.into_iter().collect())
+    }
+}
+
+fn collect<T>(
+    events: impl IntoIterator<Item = ClassFileResult<T>>,
+) -> ClassFileResult<EventBuffer<T>> {
+    events
+        .into_iter()
+        .collect::<ClassFileResult<Vec<_>>>()
+        .map(EventBuffer)
+}
+
+/// The providers of buffered events, where every nested provider is an [`EventBuffer`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BufferedEventProviders;
+
+pub type BufferedClassEvents<'class> = EventBuffer<ClassEvent<'class, BufferedEventProviders>>;
+
+impl<'class> ClassEventProviders<'class> for BufferedEventProviders {
+    type ModuleSubProviders = BufferedEventProviders;
+    type ModuleEvents = EventBuffer<ModuleEvent<'class, BufferedEventProviders>>;
+    type Annotations = EventBuffer<AnnotationEvent<AnnotationNode<'class>>>;
+    type TypeAnnotations = EventBuffer<AnnotationEvent<TypeAnnotationNode<'class>>>;
+    type Attributes = EventBuffer<Box<dyn Attribute>>;
+    type NestMembers = EventBuffer<Cow<'class, JavaStr>>;
+    type PermittedSubclasses = EventBuffer<Cow<'class, JavaStr>>;
+    type InnerClasses = EventBuffer<ClassInnerClassEvent<'class>>;
+    type RecordComponentSubProviders = BufferedEventProviders;
+    type RecordComponentEvents = EventBuffer<RecordComponentEvent<'class, BufferedEventProviders>>;
+    type RecordComponents =
+        EventBuffer<ClassRecordComponentEvent<'class, Self::RecordComponentEvents>>;
+    type FieldSubProviders = BufferedEventProviders;
+    type FieldEvents = EventBuffer<FieldEvent<'class, BufferedEventProviders>>;
+    type Fields = EventBuffer<ClassFieldEvent<'class, Self::FieldEvents>>;
+    type MethodSubProviders = BufferedEventProviders;
+    type MethodEvents = EventBuffer<MethodEvent<'class, BufferedEventProviders>>;
+    type Methods = EventBuffer<ClassMethodEvent<'class, Self::MethodEvents>>;
+
+    fn buffer_event(
+        event: ClassEvent<'class, Self>,
+    ) -> ClassFileResult<ClassEvent<'class, BufferedEventProviders>> {
+        Ok(event)
+    }
+}
+
+impl<'class> ModuleEventProviders<'class> for BufferedEventProviders {
+    type Packages = EventBuffer<Cow<'class, JavaStr>>;
+    type Requires = EventBuffer<ModuleRequireEvent<'class>>;
+    type Exports = EventBuffer<ModuleRelationEvent<'class>>;
+    type Opens = EventBuffer<ModuleRelationEvent<'class>>;
+    type Uses = EventBuffer<Cow<'class, JavaStr>>;
+    type Provides = EventBuffer<ModuleProvidesEvent<'class>>;
+}
+
+impl<'class> RecordComponentEventProviders<'class> for BufferedEventProviders {
+    type Annotations = EventBuffer<AnnotationEvent<AnnotationNode<'class>>>;
+    type TypeAnnotations = EventBuffer<AnnotationEvent<TypeAnnotationNode<'class>>>;
+    type Attributes = EventBuffer<Box<dyn Attribute>>;
+}
+
+impl<'class> FieldEventProviders<'class> for BufferedEventProviders {
+    type Annotations = EventBuffer<AnnotationEvent<AnnotationNode<'class>>>;
+    type TypeAnnotations = EventBuffer<AnnotationEvent<TypeAnnotationNode<'class>>>;
+    type Attributes = EventBuffer<Box<dyn Attribute>>;
+}
+
+impl<'class> MethodEventProviders<'class> for BufferedEventProviders {
+    type Parameters = EventBuffer<MethodParameterEvent<'class>>;
+    type Annotations = EventBuffer<AnnotationEvent<AnnotationNode<'class>>>;
+    type TypeAnnotations = EventBuffer<AnnotationEvent<TypeAnnotationNode<'class>>>;
+    type ParameterAnnotations = EventBuffer<MethodParameterAnnotationEvent<'class>>;
+    type Attributes = EventBuffer<Box<dyn Attribute>>;
+    type InsnAnnotations = EventBuffer<AnnotationEvent<TypeAnnotationNode<'class>>>;
+    type LocalVariables = EventBuffer<MethodLocalVariableEvent<'class>>;
+    type LocalVariableAnnotations = EventBuffer<MethodLocalVariableAnnotationEvent<'class>>;
+    type TryCatchBlocks = EventBuffer<MethodTryCatchBlockEvent<'class>>;
+    type TryCatchBlockAnnotations = EventBuffer<MethodTryCatchBlockAnnotationEvent<'class>>;
+    type CodeAttributes = EventBuffer<Box<dyn Attribute>>;
+}
+
+/// Reads all the events of a class into memory. Events that are already buffered, such as those of
+/// a [`BufferedClassEvents`], are passed through without being copied.
+pub fn buffer_class_events<'class, S>(source: S) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    source
+        .events()?
+        .map(|event| S::Providers::buffer_event(event?))
+        .collect::<ClassFileResult<_>>()
+        .map(EventBuffer)
+}
+
+/// Reads a single class event into memory, see [`ClassEventProviders::buffer_event`].
+pub(crate) fn buffer_class_event<'class, P>(
+    event: ClassEvent<'class, P>,
+) -> ClassFileResult<ClassEvent<'class, BufferedEventProviders>>
+where
+    P: ClassEventProviders<'class>,
+{
+    Ok(match event {
+        ClassEvent::Class(class) => ClassEvent::Class(class),
+        ClassEvent::Synthetic => ClassEvent::Synthetic,
+        ClassEvent::Deprecated => ClassEvent::Deprecated,
+        ClassEvent::Source(source) => ClassEvent::Source(source),
+        ClassEvent::Module(module) => ClassEvent::Module(ClassModuleEvent {
+            name: module.name,
+            access: module.access,
+            version: module.version,
+            events: buffer_module_events(module.events)?,
+        }),
+        ClassEvent::NestHost(nest_host) => ClassEvent::NestHost(nest_host),
+        ClassEvent::OuterClass(outer_class) => ClassEvent::OuterClass(outer_class),
+        ClassEvent::Annotations(events) => ClassEvent::Annotations(collect(events)?),
+        ClassEvent::TypeAnnotations(events) => ClassEvent::TypeAnnotations(collect(events)?),
+        ClassEvent::Attributes(events) => ClassEvent::Attributes(collect(events)?),
+        ClassEvent::NestMembers(events) => ClassEvent::NestMembers(collect(events)?),
+        ClassEvent::PermittedSubclasses(events) => {
+            ClassEvent::PermittedSubclasses(collect(events)?)
+        }
+        #[cfg(feature = "preview")]
+        ClassEvent::LoadableDescriptors(descs) => ClassEvent::LoadableDescriptors(descs),
+        ClassEvent::InnerClasses(events) => ClassEvent::InnerClasses(collect(events)?),
+        ClassEvent::Record(components) => {
+            let mut buffered = Vec::new();
+            for component in components {
+                let component = component?;
+                buffered.push(ClassRecordComponentEvent {
+                    name: component.name,
+                    desc: component.desc,
+                    signature: component.signature,
+                    events: buffer_record_component_events(component.events)?,
+                });
+            }
+            ClassEvent::Record(EventBuffer(buffered))
+        }
+        ClassEvent::Fields(fields) => {
+            let mut buffered = Vec::new();
+            for field in fields {
+                let field = field?;
+                buffered.push(ClassFieldEvent {
+                    access: field.access,
+                    name: field.name,
+                    desc: field.desc,
+                    signature: field.signature,
+                    value: field.value,
+                    events: buffer_field_events(field.events)?,
+                });
+            }
+            ClassEvent::Fields(EventBuffer(buffered))
+        }
+        ClassEvent::Methods(methods) => {
+            let mut buffered = Vec::new();
+            for method in methods {
+                let method = method?;
+                buffered.push(ClassMethodEvent {
+                    access: method.access,
+                    name: method.name,
+                    desc: method.desc,
+                    signature: method.signature,
+                    exceptions: method.exceptions,
+                    events: buffer_method_events(method.events)?,
+                });
+            }
+            ClassEvent::Methods(EventBuffer(buffered))
+        }
+    })
+}
+
+pub(crate) fn buffer_module_events<'class, E, P>(
+    events: E,
+) -> ClassFileResult<EventBuffer<ModuleEvent<'class, BufferedEventProviders>>>
+where
+    E: IntoIterator<Item = ClassFileResult<ModuleEvent<'class, P>>>,
+    P: ModuleEventProviders<'class>,
+{
+    let mut result = Vec::new();
+    for event in events {
+        result.push(match event? {
+            ModuleEvent::MainClass(main_class) => ModuleEvent::MainClass(main_class),
+            ModuleEvent::Packages(events) => ModuleEvent::Packages(collect(events)?),
+            ModuleEvent::Target(target) => ModuleEvent::Target(target),
+            ModuleEvent::Resolution(resolution) => ModuleEvent::Resolution(resolution),
+            ModuleEvent::Hashes(hashes) => ModuleEvent::Hashes(hashes),
+            ModuleEvent::Requires(events) => ModuleEvent::Requires(collect(events)?),
+            ModuleEvent::Exports(events) => ModuleEvent::Exports(collect(events)?),
+            ModuleEvent::Opens(events) => ModuleEvent::Opens(collect(events)?),
+            ModuleEvent::Uses(events) => ModuleEvent::Uses(collect(events)?),
+            ModuleEvent::Provides(events) => ModuleEvent::Provides(collect(events)?),
+        });
+    }
+    Ok(EventBuffer(result))
+}
+
+pub(crate) fn buffer_record_component_events<'class, E, P>(
+    events: E,
+) -> ClassFileResult<EventBuffer<RecordComponentEvent<'class, BufferedEventProviders>>>
+where
+    E: IntoIterator<Item = ClassFileResult<RecordComponentEvent<'class, P>>>,
+    P: RecordComponentEventProviders<'class>,
+{
+    let mut result = Vec::new();
+    for event in events {
+        result.push(match event? {
+            RecordComponentEvent::Annotations(events) => {
+                RecordComponentEvent::Annotations(collect(events)?)
+            }
+            RecordComponentEvent::TypeAnnotations(events) => {
+                RecordComponentEvent::TypeAnnotations(collect(events)?)
+            }
+            RecordComponentEvent::Attributes(events) => {
+                RecordComponentEvent::Attributes(collect(events)?)
+            }
+        });
+    }
+    Ok(EventBuffer(result))
+}
+
+pub(crate) fn buffer_field_events<'class, E, P>(
+    events: E,
+) -> ClassFileResult<EventBuffer<FieldEvent<'class, BufferedEventProviders>>>
+where
+    E: IntoIterator<Item = ClassFileResult<FieldEvent<'class, P>>>,
+    P: FieldEventProviders<'class>,
+{
+    let mut result = Vec::new();
+    for event in events {
+        result.push(match event? {
+            FieldEvent::Deprecated => FieldEvent::Deprecated,
+            FieldEvent::Annotations(events) => FieldEvent::Annotations(collect(events)?),
+            FieldEvent::TypeAnnotations(events) => FieldEvent::TypeAnnotations(collect(events)?),
+            FieldEvent::Attributes(events) => FieldEvent::Attributes(collect(events)?),
+        });
+    }
+    Ok(EventBuffer(result))
+}
+
+pub(crate) fn buffer_method_events<'class, E, P>(
+    events: E,
+) -> ClassFileResult<EventBuffer<MethodEvent<'class, BufferedEventProviders>>>
+where
+    E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+    P: MethodEventProviders<'class>,
+{
+    let mut result = Vec::new();
+    for event in events {
+        result.push(match event? {
+            MethodEvent::Deprecated => MethodEvent::Deprecated,
+            MethodEvent::Parameters(events) => MethodEvent::Parameters(collect(events)?),
+            MethodEvent::AnnotationDefault(value) => MethodEvent::AnnotationDefault(value),
+            MethodEvent::Annotations(events) => MethodEvent::Annotations(collect(events)?),
+            MethodEvent::TypeAnnotations(events) => MethodEvent::TypeAnnotations(collect(events)?),
+            MethodEvent::AnnotableParameterCount(event) => {
+                MethodEvent::AnnotableParameterCount(event)
+            }
+            MethodEvent::ParameterAnnotations(events) => {
+                MethodEvent::ParameterAnnotations(collect(events)?)
+            }
+            MethodEvent::Attributes(events) => MethodEvent::Attributes(collect(events)?),
+            MethodEvent::Code { label_creator } => MethodEvent::Code { label_creator },
+            MethodEvent::Frame(frame) => MethodEvent::Frame(frame),
+            MethodEvent::Insn(opcode) => MethodEvent::Insn(opcode),
+            MethodEvent::BIPushInsn(value) => MethodEvent::BIPushInsn(value),
+            MethodEvent::SIPushInsn(value) => MethodEvent::SIPushInsn(value),
+            MethodEvent::NewArrayInsn(ty) => MethodEvent::NewArrayInsn(ty),
+            MethodEvent::VarInsn { opcode, var_index } => {
+                MethodEvent::VarInsn { opcode, var_index }
+            }
+            MethodEvent::TypeInsn { opcode, ty } => MethodEvent::TypeInsn { opcode, ty },
+            MethodEvent::FieldInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+            } => MethodEvent::FieldInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+            },
+            MethodEvent::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                is_interface,
+            } => MethodEvent::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                is_interface,
+            },
+            MethodEvent::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            } => MethodEvent::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            },
+            MethodEvent::JumpInsn { opcode, label } => MethodEvent::JumpInsn { opcode, label },
+            MethodEvent::Label(label) => MethodEvent::Label(label),
+            MethodEvent::LdcInsn(constant) => MethodEvent::LdcInsn(constant),
+            MethodEvent::IIncInsn {
+                var_index,
+                increment,
+            } => MethodEvent::IIncInsn {
+                var_index,
+                increment,
+            },
+            MethodEvent::TableSwitchInsn {
+                low,
+                high,
+                dflt,
+                labels,
+            } => MethodEvent::TableSwitchInsn {
+                low,
+                high,
+                dflt,
+                labels,
+            },
+            MethodEvent::LookupSwitchInsn { dflt, values } => {
+                MethodEvent::LookupSwitchInsn { dflt, values }
+            }
+            MethodEvent::MultiANewArrayInsn { desc, dimensions } => {
+                MethodEvent::MultiANewArrayInsn { desc, dimensions }
+            }
+            MethodEvent::InsnAnnotations(events) => MethodEvent::InsnAnnotations(collect(events)?),
+            MethodEvent::LineNumber { line, start } => MethodEvent::LineNumber { line, start },
+            MethodEvent::LocalVariables(events) => MethodEvent::LocalVariables(collect(events)?),
+            MethodEvent::LocalVariableAnnotations(events) => {
+                MethodEvent::LocalVariableAnnotations(collect(events)?)
+            }
+            MethodEvent::TryCatchBlocks(events) => MethodEvent::TryCatchBlocks(collect(events)?),
+            MethodEvent::TryCatchBlockAnnotations(events) => {
+                MethodEvent::TryCatchBlockAnnotations(collect(events)?)
+            }
+            MethodEvent::CodeAttributes(events) => MethodEvent::CodeAttributes(collect(events)?),
+            MethodEvent::Maxs(maxs) => MethodEvent::Maxs(maxs),
+            MethodEvent::Unchanged(event) => MethodEvent::Unchanged(event),
+        });
+    }
+    Ok(EventBuffer(result))
+}
@@ -0,0 +1,371 @@
+#[cfg(feature = "jar")]
+use crate::jar::JarReader;
+use crate::{
+    ClassAccess, ClassEvent, ClassEventSource, ClassFileError, ClassFileResult, ClassReader,
+    ClassReaderFlags, JImageReader,
+};
+use derive_more::Debug;
+use java_string::{JavaStr, JavaString};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Answers questions about the class hierarchy for the [`ClassWriter`](crate::ClassWriter) when it
+/// computes frames. Implementations may look classes up on a classpath, use a fixed mapping, etc.
+/// Unknown classes should be reported with [`ClassFileError::UnknownClass`].
+pub trait ClassHierarchy {
+    /// Returns the internal name of the superclass of the given class, or `None` for
+    /// `java/lang/Object`.
+    fn super_class(&self, name: &JavaStr) -> ClassFileResult<Option<JavaString>>;
+
+    fn is_interface(&self, name: &JavaStr) -> ClassFileResult<bool>;
+
+    /// Returns the internal names of the interfaces directly implemented by the given class. The
+    /// default implementation knows no interfaces.
+    fn interfaces(&self, name: &JavaStr) -> ClassFileResult<Vec<JavaString>> {
+        let _ = name;
+        Ok(Vec::new())
+    }
+
+    /// Whether a value of the class `from` can be assigned to the class `to`, which is when `to`
+    /// is `from`, or one of its superclasses or superinterfaces.
+    fn is_assignable(&self, from: &JavaStr, to: &JavaStr) -> ClassFileResult<bool> {
+        if from == to || to == "java/lang/Object" {
+            return Ok(true);
+        }
+        let mut visited = HashSet::new();
+        let mut queue = vec![from.to_owned()];
+        while let Some(name) = queue.pop() {
+            if name == to {
+                return Ok(true);
+            }
+            if visited.insert(name.clone()) {
+                queue.extend(self.super_class(&name)?);
+                queue.extend(self.interfaces(&name)?);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the most specific common superclass of two classes, which is `java/lang/Object` if
+    /// either of them is an interface.
+    fn common_super_class(&self, a: &JavaStr, b: &JavaStr) -> ClassFileResult<JavaString> {
+        if a == b {
+            return Ok(a.to_owned());
+        }
+        if self.is_interface(a)? || self.is_interface(b)? {
+            return Ok(JavaString::from("java/lang/Object"));
+        }
+
+        let mut a_supers = vec![a.to_owned()];
+        while let Some(super_class) = self.super_class(a_supers.last().unwrap())? {
+            if a_supers.contains(&super_class) {
+                return Err(ClassFileError::CircularSuperClass(super_class));
+            }
+            a_supers.push(super_class);
+        }
+
+        let mut visited = HashSet::new();
+        let mut b_super = Some(b.to_owned());
+        while let Some(current) = b_super {
+            if a_supers.contains(&current) {
+                return Ok(current);
+            }
+            if !visited.insert(current.clone()) {
+                return Err(ClassFileError::CircularSuperClass(current));
+            }
+            b_super = self.super_class(&current)?;
+        }
+        Ok(JavaString::from("java/lang/Object"))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ClassInfo {
+    super_class: Option<JavaString>,
+    interfaces: Vec<JavaString>,
+    is_interface: bool,
+}
+
+impl ClassInfo {
+    fn read<'class, S>(source: S) -> ClassFileResult<Option<(JavaString, ClassInfo)>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let Some(ClassEvent::Class(class)) = source.events()?.next().transpose()? else {
+            return Ok(None);
+        };
+        Ok(Some((
+            class.name.into_owned(),
+            ClassInfo {
+                super_class: class.super_name.map(|super_name| super_name.into_owned()),
+                interfaces: class
+                    .interfaces
+                    .into_iter()
+                    .map(|interface| interface.into_owned())
+                    .collect(),
+                is_interface: class.access.contains(ClassAccess::Interface),
+            },
+        )))
+    }
+}
+
+/// A [`ClassHierarchy`] backed by a fixed mapping of classes. `java/lang/Object` is always known.
+#[derive(Debug, Clone, Default)]
+pub struct SimpleClassHierarchy {
+    classes: HashMap<JavaString, ClassInfo>,
+}
+
+impl SimpleClassHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_class<'class, S>(&mut self, source: S) -> ClassFileResult<()>
+    where
+        S: ClassEventSource<'class>,
+    {
+        if let Some((name, info)) = ClassInfo::read(source)? {
+            self.classes.insert(name, info);
+        }
+        Ok(())
+    }
+
+    pub fn insert(
+        &mut self,
+        name: impl Into<JavaString>,
+        super_class: Option<JavaString>,
+        is_interface: bool,
+    ) {
+        self.classes.insert(
+            name.into(),
+            ClassInfo {
+                super_class,
+                interfaces: Vec::new(),
+                is_interface,
+            },
+        );
+    }
+
+    fn get(&self, name: &JavaStr) -> ClassFileResult<&ClassInfo> {
+        self.classes
+            .get(name)
+            .ok_or_else(|| ClassFileError::UnknownClass(name.to_owned()))
+    }
+}
+
+impl ClassHierarchy for SimpleClassHierarchy {
+    fn super_class(&self, name: &JavaStr) -> ClassFileResult<Option<JavaString>> {
+        if name == "java/lang/Object" {
+            return Ok(None);
+        }
+        Ok(self.get(name)?.super_class.clone())
+    }
+
+    fn is_interface(&self, name: &JavaStr) -> ClassFileResult<bool> {
+        if name == "java/lang/Object" {
+            return Ok(false);
+        }
+        Ok(self.get(name)?.is_interface)
+    }
+
+    fn interfaces(&self, name: &JavaStr) -> ClassFileResult<Vec<JavaString>> {
+        if name == "java/lang/Object" {
+            return Ok(Vec::new());
+        }
+        Ok(self.get(name)?.interfaces.clone())
+    }
+}
+
+/// A [`ClassHierarchy`] which looks classes up on a classpath of directories, jar files with the
+/// `jar` feature, and the platform modules of JDKs. Classes are read when they're first needed,
+/// from the first root which has them, and then cached.
+#[derive(Debug, Default)]
+pub struct ClasspathClassHierarchy {
+    roots: Mutex<Vec<ClasspathHierarchyRoot>>,
+    #[debug("{} classes", classes.lock().map_or(0, |classes| classes.len()))]
+    classes: Mutex<HashMap<JavaString, Option<Arc<ClassInfo>>>>,
+}
+
+#[derive(Debug)]
+enum ClasspathHierarchyRoot {
+    Directory(PathBuf),
+    #[cfg(feature = "jar")]
+    Jar(JarReader),
+    Jdk {
+        jimage: JImageReader,
+        /// The module of each package, with `/` as the separator.
+        #[debug(skip)]
+        packages: HashMap<String, String>,
+    },
+}
+
+impl ClasspathClassHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a directory, or a jar file with the `jar` feature, to the end of the classpath.
+    pub fn add_root(&mut self, path: impl AsRef<Path>) -> ClassFileResult<()> {
+        let path = path.as_ref();
+        let root = if path.is_dir() {
+            ClasspathHierarchyRoot::Directory(path.to_owned())
+        } else {
+            #[cfg(not(feature = "jar"))]
+            return Err(ClassFileError::Io {
+                kind: std::io::ErrorKind::Unsupported,
+                message: format!("unsupported classpath entry: {}", path.display()),
+            });
+            #[cfg(feature = "jar")]
+            ClasspathHierarchyRoot::Jar(JarReader::open(path)?)
+        };
+        self.roots.get_mut().unwrap().push(root);
+        Ok(())
+    }
+
+    /// Adds the platform modules of the JDK installed in the given directory to the end of the
+    /// classpath.
+    pub fn add_jdk(&mut self, java_home: impl AsRef<Path>) -> ClassFileResult<()> {
+        let jimage = JImageReader::open_jdk(java_home)?;
+        let mut packages = HashMap::new();
+        for resource in jimage.resources()? {
+            if let Some((package, _)) = resource.class_name().and_then(|name| name.rsplit_once('/'))
+            {
+                packages
+                    .entry(package.to_owned())
+                    .or_insert_with(|| resource.module.clone());
+            }
+        }
+        self.roots
+            .get_mut()
+            .unwrap()
+            .push(ClasspathHierarchyRoot::Jdk { jimage, packages });
+        Ok(())
+    }
+
+    fn get(&self, name: &JavaStr) -> ClassFileResult<Arc<ClassInfo>> {
+        if let Some(info) = self.classes.lock().unwrap().get(name) {
+            return info
+                .clone()
+                .ok_or_else(|| ClassFileError::UnknownClass(name.to_owned()));
+        }
+        let info = self.find(name)?.map(Arc::new);
+        self.classes
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), info.clone());
+        info.ok_or_else(|| ClassFileError::UnknownClass(name.to_owned()))
+    }
+
+    fn find(&self, name: &JavaStr) -> ClassFileResult<Option<ClassInfo>> {
+        let internal_name = name.as_str_lossy();
+        let mut roots = self.roots.lock().unwrap();
+        for root in roots.iter_mut() {
+            let data = match root {
+                ClasspathHierarchyRoot::Directory(dir) => {
+                    let path = dir.join(format!("{internal_name}.class"));
+                    if path.is_file() {
+                        Some(std::fs::read(path)?)
+                    } else {
+                        None
+                    }
+                }
+                #[cfg(feature = "jar")]
+                ClasspathHierarchyRoot::Jar(jar) => {
+                    jar.read_class(&internal_name)?.map(|entry| entry.data)
+                }
+                ClasspathHierarchyRoot::Jdk { jimage, packages } => {
+                    let module = internal_name
+                        .rsplit_once('/')
+                        .and_then(|(package, _)| packages.get(package));
+                    match module {
+                        Some(module) => jimage.read_class(module, &internal_name)?,
+                        None => None,
+                    }
+                }
+            };
+            if let Some(data) = data {
+                let reader = ClassReader::new(&data, ClassReaderFlags::None)?;
+                return Ok(ClassInfo::read(&reader)?.map(|(_, info)| info));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl ClassHierarchy for ClasspathClassHierarchy {
+    fn super_class(&self, name: &JavaStr) -> ClassFileResult<Option<JavaString>> {
+        Ok(self.get(name)?.super_class.clone())
+    }
+
+    fn is_interface(&self, name: &JavaStr) -> ClassFileResult<bool> {
+        Ok(self.get(name)?.is_interface)
+    }
+
+    fn interfaces(&self, name: &JavaStr) -> ClassFileResult<Vec<JavaString>> {
+        Ok(self.get(name)?.interfaces.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassFileError, ClassHierarchy, ClasspathClassHierarchy, SimpleClassHierarchy};
+    use java_string::{JavaStr, JavaString};
+    use test_helpers::java_home;
+
+    #[test]
+    fn test_common_super_class() {
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy.insert("A", Some("java/lang/Object".into()), false);
+        hierarchy.insert("B", Some("A".into()), false);
+        hierarchy.insert("C", Some("A".into()), false);
+        hierarchy.insert("I", Some("java/lang/Object".into()), true);
+        hierarchy.insert("X", Some("Y".into()), false);
+        hierarchy.insert("Y", Some("X".into()), false);
+
+        let common = |a: &str, b: &str| {
+            hierarchy.common_super_class(JavaStr::from_str(a), JavaStr::from_str(b))
+        };
+        assert_eq!(Ok(JavaString::from("A")), common("B", "C"));
+        assert_eq!(Ok(JavaString::from("A")), common("A", "C"));
+        assert_eq!(Ok(JavaString::from("java/lang/Object")), common("B", "I"));
+        assert_eq!(
+            Err(ClassFileError::UnknownClass(JavaString::from("D"))),
+            common("B", "D")
+        );
+
+        assert_eq!(
+            Err(ClassFileError::CircularSuperClass(JavaString::from("X"))),
+            common("X", "B")
+        );
+        assert_eq!(
+            Err(ClassFileError::CircularSuperClass(JavaString::from("X"))),
+            common("B", "X")
+        );
+    }
+
+    #[test]
+    fn test_classpath_hierarchy() {
+        let mut hierarchy = ClasspathClassHierarchy::new();
+        hierarchy.add_jdk(java_home!()).unwrap();
+        let name = JavaStr::from_str;
+
+        assert_eq!(
+            Ok(JavaString::from("java/util/AbstractList")),
+            hierarchy.common_super_class(name("java/util/ArrayList"), name("java/util/Vector"))
+        );
+        assert_eq!(
+            Ok(true),
+            hierarchy.is_assignable(name("java/util/ArrayList"), name("java/util/Collection"))
+        );
+        assert_eq!(
+            Ok(false),
+            hierarchy.is_assignable(name("java/util/ArrayList"), name("java/util/Set"))
+        );
+        assert_eq!(Ok(true), hierarchy.is_interface(name("java/util/List")));
+        assert_eq!(
+            Err(ClassFileError::UnknownClass(JavaString::from("Missing"))),
+            hierarchy.super_class(name("Missing"))
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::frame::expand_frame;
+use crate::frame_computer::initial_frame_locals;
 use crate::opcodes::InternalOpcodes;
 use crate::tree::{AnnotationNode, AnnotationValue, TypeAnnotationNode};
 use crate::{
@@ -5,16 +7,18 @@ use crate::{
     ClassClassEvent, ClassEvent, ClassEventProviders, ClassEventSource, ClassFieldEvent,
     ClassFileError, ClassFileResult, ClassInnerClassEvent, ClassMethodEvent, ClassModuleEvent,
     ClassOuterClassEvent, ClassRecordComponentEvent, ClassSourceEvent, ConstantDynamic,
-    ConstantPool, ConstantPoolEntry, ConstantPoolTag, DynamicEntry, FieldAccess, FieldEvent,
-    FieldEventProviders, FieldValue, Frame, FrameValue, Handle, HandleKind, InnerClassAccess,
-    Label, LabelCreator, LdcConstant, MethodAccess, MethodAnnotableParameterCountEvent,
-    MethodEvent, MethodEventProviders, MethodLocalVariableAnnotationEvent,
-    MethodLocalVariableEvent, MethodMaxsEvent, MethodParameterAnnotationEvent,
-    MethodParameterEvent, MethodTryCatchBlockAnnotationEvent, MethodTryCatchBlockEvent,
-    ModuleAccess, ModuleEvent, ModuleEventProviders, ModuleProvidesEvent, ModuleRelationAccess,
-    ModuleRelationEvent, ModuleRequireAccess, ModuleRequireEvent, NewArrayType, Opcode,
-    ParameterAccess, RecordComponentEvent, RecordComponentEventProviders, TypePath, TypeReference,
-    TypeReferenceTargetType, UnknownAttribute, LATEST_MAJOR_VERSION, MAX_ANNOTATION_NESTING,
+    ConstantPool, ConstantPoolEntry, ConstantPoolReferences, ConstantPoolTag, DynamicEntry,
+    FieldAccess, FieldEvent, FieldEventProviders, FieldValue, Frame, FrameValue, Handle,
+    HandleKind, InnerClassAccess, InsnReference, Label, LabelCreator, LdcConstant, MethodAccess,
+    MethodAnnotableParameterCountEvent, MethodEvent, MethodEventProviders,
+    MethodLocalVariableAnnotationEvent, MethodLocalVariableEvent, MethodMaxsEvent,
+    MethodParameterAnnotationEvent, MethodParameterEvent, MethodTryCatchBlockAnnotationEvent,
+    MethodTryCatchBlockEvent, MethodUnchangedEvent, ModuleAccess, ModuleEvent,
+    ModuleEventProviders, ModuleHashEvent, ModuleHashesEvent, ModuleProvidesEvent,
+    ModuleRelationAccess, ModuleRelationEvent, ModuleRequireAccess, ModuleRequireEvent,
+    ModuleResolution, NewArrayType, Opcode, ParameterAccess, RecordComponentEvent,
+    RecordComponentEventProviders, TypePath, TypeReference, TypeReferenceTargetType,
+    UnknownAttribute, LATEST_MAJOR_VERSION, MAX_ANNOTATION_NESTING, PREVIEW_MINOR_VERSION,
 };
 use bitflags::{bitflags, Flags};
 use derive_more::Debug;
@@ -79,6 +83,14 @@ bitflags! {
         const SkipDebug = 2;
         const SkipFrames = 4;
         const ExpandFrames = 8;
+        /// Enforce constraints of the JVMS which are otherwise ignored: unknown access flags,
+        /// predefined attributes which are repeated or in the wrong structure, a missing superclass
+        /// and constant values which don't match their field.
+        const Strict = 16;
+        /// Recover from an error in a field, method or annotation by continuing with the next one.
+        /// The error is still returned by the iterator, but no longer leaves it reading from the
+        /// middle of the broken structure.
+        const Lenient = 32;
     }
 }
 
@@ -133,18 +145,26 @@ impl<'class> ClassReader<'class> {
 
     pub fn minor_version(&self) -> u16 {
         self.buffer
-            .read_u16(8)
+            .read_u16(4)
             .expect("couldn't read value before constant pool")
     }
 
+    /// Whether the class depends on the preview features of its Java version.
+    pub fn is_preview(&self) -> bool {
+        self.minor_version() == PREVIEW_MINOR_VERSION
+    }
+
     /// Returns the access flags of the class. For classes before Java 1.5, this value won't reflect
     /// the [`ClassAccess::Synthetic`] flag. If you need to support parsing these old classes and
     /// need to check for synthetic classes, use [`ClassReaderEvents::is_synthetic`] or check for
     /// [`ClassEvent::Synthetic`].
     pub fn access(&self) -> ClassFileResult<ClassAccess> {
-        Ok(ClassAccess::from_bits_retain(
-            self.buffer.read_u16(self.metadata_start)?,
-        ))
+        let access = self.buffer.read_u16(self.metadata_start)?;
+        if self.reader_flags.contains(ClassReaderFlags::Strict) {
+            return ClassAccess::from_bits(access)
+                .ok_or(ClassFileError::UnknownAccessFlags(access));
+        }
+        Ok(ClassAccess::from_bits_retain(access))
     }
 
     pub fn name(&self) -> ClassFileResult<Cow<'class, JavaStr>> {
@@ -153,8 +173,38 @@ impl<'class> ClassReader<'class> {
     }
 
     pub fn super_name(&self) -> ClassFileResult<Option<Cow<'class, JavaStr>>> {
-        self.constant_pool
-            .get_optional_class(self.buffer.read_u16(self.metadata_start + 4)?)
+        let super_index = self.buffer.read_u16(self.metadata_start + 4)?;
+        if super_index == 0
+            && self.reader_flags.contains(ClassReaderFlags::Strict)
+            && !self.access()?.contains(ClassAccess::Module)
+            && self.name()? != JavaStr::from_str("java/lang/Object")
+        {
+            return Err(ClassFileError::BadConstantPoolIndexNoEntry(0));
+        }
+        self.constant_pool.get_optional_class(super_index)
+    }
+
+    /// The whole class file.
+    pub(crate) fn bytes(&self) -> &'class [u8] {
+        self.buffer.data
+    }
+
+    /// The name index and payload of each class attribute, in their order in the class file.
+    pub(crate) fn raw_attributes(&self) -> ClassFileResult<Vec<(u16, ClassBuffer<'class>)>> {
+        let mut pos = self.events()?.attributes_offset;
+        let attributes_count = self.buffer.read_u16(pos)?;
+        pos += 2;
+        let mut result = Vec::with_capacity(attributes_count as usize);
+        for _ in 0..attributes_count {
+            let name_index = self.buffer.read_u16(pos)?;
+            let attribute_length = self.buffer.read_u32(pos + 2)? as usize;
+            result.push((
+                name_index,
+                self.buffer.slice(pos + 6..pos + 6 + attribute_length)?,
+            ));
+            pos += 6 + attribute_length;
+        }
+        Ok(result)
     }
 
     pub fn interfaces(&self) -> ClassFileResult<InterfacesIterator<'_, 'class>> {
@@ -165,6 +215,281 @@ impl<'class> ClassReader<'class> {
             index: 0,
         })
     }
+
+    /// Finds a field by its name and descriptor. Only the name and descriptor of the fields before
+    /// it are read.
+    pub fn find_field<'reader>(
+        &'reader self,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> ClassFileResult<Option<ClassFieldEvent<'class, FieldReaderEvents<'reader, 'class>>>> {
+        let mut fields = self.events()?.fields();
+        while fields.remaining != 0 {
+            if self.member_matches(fields.offset, name, desc)? {
+                return fields.next().transpose();
+            }
+            fields.remaining -= 1;
+            fields.offset = self.member_end(fields.offset)?;
+        }
+        Ok(None)
+    }
+
+    /// Finds a method by its name and descriptor. Only the name and descriptor of the methods
+    /// before it are read.
+    pub fn find_method<'reader>(
+        &'reader self,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> ClassFileResult<Option<ClassMethodEvent<'class, MethodReaderEvents<'reader, 'class>>>>
+    {
+        let mut methods = self.events()?.methods();
+        while methods.remaining != 0 {
+            if self.member_matches(methods.offset, name, desc)? {
+                return methods.next().transpose();
+            }
+            methods.remaining -= 1;
+            methods.offset = self.member_end(methods.offset)?;
+        }
+        Ok(None)
+    }
+
+    /// Decodes the method of an unchanged method event read from this class, without reading the
+    /// other fields and methods.
+    pub(crate) fn unchanged_method<'reader>(
+        &'reader self,
+        method: &MethodUnchangedEvent<'class>,
+    ) -> ClassFileResult<ClassMethodEvent<'class, MethodReaderEvents<'reader, 'class>>> {
+        let mut methods = ClassMethodsIterator::new(
+            self,
+            method.index + 1,
+            method.start,
+            self.events()?.bootstrap_methods,
+        );
+        methods.remaining = 0;
+        methods.event().map_err(|err| {
+            err.with_context(|| self.member_path("method", method.start), method.start)
+        })
+    }
+
+    /// Reads the header of the class and the access, name and descriptor of each field and method,
+    /// without reading any attributes. Like [`access`](Self::access), the access flags don't
+    /// reflect `Synthetic` attributes.
+    pub fn outline(&self) -> ClassFileResult<ClassOutline<'class>> {
+        let interfaces = self.interfaces()?.collect::<ClassFileResult<Vec<_>>>()?;
+        let mut pos = self.metadata_start + 8 + interfaces.len() * 2;
+        let fields_count = self.buffer.read_u16(pos)?;
+        pos += 2;
+        let mut fields = Vec::with_capacity(fields_count as usize);
+        for _ in 0..fields_count {
+            fields.push(self.member_outline(pos, FieldAccess::from_bits_retain)?);
+            pos = self.member_end(pos)?;
+        }
+        let methods_count = self.buffer.read_u16(pos)?;
+        pos += 2;
+        let mut methods = Vec::with_capacity(methods_count as usize);
+        for _ in 0..methods_count {
+            methods.push(self.member_outline(pos, MethodAccess::from_bits_retain)?);
+            pos = self.member_end(pos)?;
+        }
+        Ok(ClassOutline {
+            access: self.access()?,
+            name: self.name()?,
+            super_name: self.super_name()?,
+            interfaces,
+            fields,
+            methods,
+        })
+    }
+
+    fn member_outline<A>(
+        &self,
+        offset: usize,
+        access: impl FnOnce(u16) -> A,
+    ) -> ClassFileResult<MemberOutline<'class, A>> {
+        Ok(MemberOutline {
+            access: access(self.buffer.read_u16(offset)?),
+            name: self
+                .constant_pool
+                .get_utf8(self.buffer.read_u16(offset + 2)?)?,
+            desc: self
+                .constant_pool
+                .get_utf8(self.buffer.read_u16(offset + 4)?)?,
+        })
+    }
+
+    /// Whether the field or method at the given offset has the given name and descriptor.
+    fn member_matches(
+        &self,
+        offset: usize,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> ClassFileResult<bool> {
+        Ok(self
+            .constant_pool
+            .get_utf8(self.buffer.read_u16(offset + 2)?)?
+            .as_ref()
+            == name
+            && self
+                .constant_pool
+                .get_utf8(self.buffer.read_u16(offset + 4)?)?
+                .as_ref()
+                == desc)
+    }
+
+    /// Describes the field or method at the given offset for the context of errors, by its name and
+    /// descriptor if they can be read.
+    fn member_path(&self, kind: &str, offset: usize) -> String {
+        let name_and_desc = |offset| -> ClassFileResult<_> {
+            let name = self
+                .constant_pool
+                .get_utf8(self.buffer.read_u16(offset + 2)?)?;
+            let desc = self
+                .constant_pool
+                .get_utf8(self.buffer.read_u16(offset + 4)?)?;
+            Ok((name, desc))
+        };
+        match name_and_desc(offset) {
+            Ok((name, desc)) if kind == "method" => format!("{kind} \"{name}{desc}\""),
+            Ok((name, desc)) => format!("{kind} \"{name}:{desc}\""),
+            Err(_) => kind.to_owned(),
+        }
+    }
+
+    /// The offset following the field or method at the given offset.
+    fn member_end(&self, mut offset: usize) -> ClassFileResult<usize> {
+        let attributes_count = self.buffer.read_u16(offset + 6)?;
+        offset += 8;
+        for _ in 0..attributes_count {
+            offset += 6 + self.buffer.read_u32(offset + 2)? as usize;
+        }
+        Ok(offset)
+    }
+
+    /// Like [`ConstantPool::references`], but also finds the instructions referring to each
+    /// constant pool entry.
+    pub fn constant_pool_references(&self) -> ClassFileResult<ConstantPoolReferences> {
+        let mut references = self.constant_pool.references()?;
+        let events = self.events()?;
+        let mut pos = events.methods_offset;
+        for method_index in 0..events.methods_count {
+            let attribute_count = self.buffer.read_u16(pos + 6)?;
+            pos += 8;
+            for _ in 0..attribute_count {
+                let name_index = self.buffer.read_u16(pos)?;
+                let attribute_length = self.buffer.read_u32(pos + 2)? as usize;
+                if self.constant_pool.get_utf8_as_bytes(name_index)? == b"Code" {
+                    let code_length = self.buffer.read_u32(pos + 10)? as usize;
+                    let code = self.buffer.slice(pos + 14..pos + 14 + code_length)?;
+                    find_insn_references(code, method_index, &mut references)?;
+                }
+                pos += 6 + attribute_length;
+            }
+        }
+        Ok(references)
+    }
+
+    /// Reads the whole class in [`Lenient`](ClassReaderFlags::Lenient) mode and returns every
+    /// error, rather than stopping at the first one. An error which leaves no way to find the next
+    /// item, such as a bad instruction or an entry of a list attribute, skips the rest of the
+    /// structure it's in.
+    pub fn collect_errors(&self) -> Vec<ClassFileError> {
+        let mut reader = self.clone();
+        reader.reader_flags |= ClassReaderFlags::Lenient;
+        let mut errors = Vec::new();
+        match reader.events() {
+            Ok(events) => collect_class_errors(events, &mut errors),
+            Err(err) => errors.push(err),
+        }
+        errors
+    }
+}
+
+/// The header and members of a class, see [`ClassReader::outline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassOutline<'class> {
+    pub access: ClassAccess,
+    pub name: Cow<'class, JavaStr>,
+    pub super_name: Option<Cow<'class, JavaStr>>,
+    pub interfaces: Vec<Cow<'class, JavaStr>>,
+    pub fields: Vec<MemberOutline<'class, FieldAccess>>,
+    pub methods: Vec<MemberOutline<'class, MethodAccess>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemberOutline<'class, A> {
+    pub access: A,
+    pub name: Cow<'class, JavaStr>,
+    pub desc: Cow<'class, JavaStr>,
+}
+
+/// A class file which owns its data, for storing in long-lived structures or sending to other
+/// threads. The data is checked once on creation, and [`reader`](Self::reader) borrows a
+/// [`ClassReader`] for it without checking it again. Cloning is cheap, as the data is shared.
+///
+/// The events of the borrowed reader, and anything built from them such as a
+/// [`ClassNode`](crate::tree::ClassNode), still borrow from the `OwnedClassReader`, so it has to be
+/// kept alive for as long as they are used.
+#[derive(Debug, Clone)]
+pub struct OwnedClassReader {
+    #[debug("{} bytes", data.len())]
+    data: Arc<[u8]>,
+    #[debug(skip)]
+    constant_pool_offsets: Arc<[usize]>,
+    #[debug(skip)]
+    metadata_start: usize,
+    reader_flags: ClassReaderFlags,
+}
+
+impl OwnedClassReader {
+    pub fn new(
+        data: impl Into<Arc<[u8]>>,
+        reader_flags: ClassReaderFlags,
+    ) -> ClassFileResult<OwnedClassReader> {
+        let data = data.into();
+        let (constant_pool_offsets, metadata_start) = ClassReader::new(&data, reader_flags)?
+            .constant_pool
+            .offsets();
+        Ok(OwnedClassReader {
+            data,
+            constant_pool_offsets,
+            metadata_start,
+            reader_flags,
+        })
+    }
+
+    /// Borrows a reader for the class. Attribute readers aren't kept, so any that are needed have
+    /// to be added to each returned reader.
+    pub fn reader(&self) -> ClassReader<'_> {
+        let buffer = ClassBuffer { data: &self.data };
+        ClassReader {
+            buffer,
+            constant_pool: ConstantPool::from_offsets(
+                buffer,
+                self.constant_pool_offsets.clone(),
+                self.metadata_start,
+            ),
+            metadata_start: self.metadata_start,
+            reader_flags: self.reader_flags,
+            attribute_readers: HashMap::new(),
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Arc<[u8]> {
+        self.data
+    }
+}
+
+/// Reads the class with [`ClassReaderFlags::None`].
+impl TryFrom<Vec<u8>> for OwnedClassReader {
+    type Error = ClassFileError;
+
+    fn try_from(data: Vec<u8>) -> ClassFileResult<OwnedClassReader> {
+        OwnedClassReader::new(data, ClassReaderFlags::None)
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -306,9 +631,14 @@ impl<'reader, 'class> ClassEventSource<'class> for &'reader ClassReader<'class>
         let mut invisible_type_annotations_count = 0;
         let mut invisible_type_annotations_offset = 0;
         let mut is_deprecated = false;
+        #[cfg(feature = "preview")]
+        let mut loadable_descriptors_offset = 0;
+        let mut module_hashes_offset = 0;
         let mut module_main_offset = 0;
         let mut module_offset = 0;
         let mut module_packages_offset = 0;
+        let mut module_resolution_offset = 0;
+        let mut module_target_offset = 0;
         let mut nest_host_offset = 0;
         let mut nest_members_count = 0;
         let mut nest_members_offset = 0;
@@ -331,14 +661,7 @@ impl<'reader, 'class> ClassEventSource<'class> for &'reader ClassReader<'class>
         let fields_offset = pos;
 
         for _ in 0..fields_count {
-            pos += 6;
-            let attributes_count = self.buffer.read_u16(pos)?;
-            pos += 2;
-            for _ in 0..attributes_count {
-                pos += 2;
-                let attribute_length = self.buffer.read_u32(pos)?;
-                pos += 4 + attribute_length as usize;
-            }
+            pos = self.member_end(pos)?;
         }
 
         let methods_count = self.buffer.read_u16(pos)?;
@@ -346,23 +669,19 @@ impl<'reader, 'class> ClassEventSource<'class> for &'reader ClassReader<'class>
         let methods_offset = pos;
 
         for _ in 0..methods_count {
-            pos += 6;
-            let attributes_count = self.buffer.read_u16(pos)?;
-            pos += 2;
-            for _ in 0..attributes_count {
-                pos += 2;
-                let attribute_length = self.buffer.read_u32(pos)?;
-                pos += 4 + attribute_length as usize;
-            }
+            pos = self.member_end(pos)?;
         }
 
+        let attributes_offset = pos;
         let attributes_count = self.buffer.read_u16(pos)?;
         pos += 2;
 
+        let mut seen_attributes = Vec::new();
         for _ in 0..attributes_count {
             let attribute_name = self
                 .constant_pool
                 .get_utf8_as_bytes(self.buffer.read_u16(pos)?)?;
+            check_attribute(self, attribute_name, CLASS_ATTRIBUTES, &mut seen_attributes)?;
             pos += 2;
             let attribute_length = self.buffer.read_u32(pos)?;
             pos += 4;
@@ -375,9 +694,14 @@ impl<'reader, 'class> ClassEventSource<'class> for &'reader ClassReader<'class>
                     inner_classes_count = self.buffer.read_u16(pos)?;
                     inner_classes_offset = pos + 2;
                 }
+                #[cfg(feature = "preview")]
+                b"LoadableDescriptors" => loadable_descriptors_offset = pos,
                 b"Module" => module_offset = pos,
+                b"ModuleHashes" => module_hashes_offset = pos,
                 b"ModuleMainClass" => module_main_offset = pos,
                 b"ModulePackages" => module_packages_offset = pos,
+                b"ModuleResolution" => module_resolution_offset = pos,
+                b"ModuleTarget" => module_target_offset = pos,
                 b"NestHost" => nest_host_offset = pos,
                 b"NestMembers" => {
                     nest_members_count = self.buffer.read_u16(pos)?;
@@ -434,9 +758,14 @@ impl<'reader, 'class> ClassEventSource<'class> for &'reader ClassReader<'class>
             invisible_type_annotations_count,
             invisible_type_annotations_offset,
             is_deprecated,
+            #[cfg(feature = "preview")]
+            loadable_descriptors_offset,
+            module_hashes_offset,
             module_main_offset,
             module_offset,
             module_packages_offset,
+            module_resolution_offset,
+            module_target_offset,
             nest_host_offset,
             nest_members_count,
             nest_members_offset,
@@ -452,6 +781,7 @@ impl<'reader, 'class> ClassEventSource<'class> for &'reader ClassReader<'class>
             visible_type_annotations_count,
             visible_type_annotations_offset,
             custom_attributes_offsets,
+            attributes_offset,
             bootstrap_methods: BootstrapMethods {
                 reader: self,
                 bootstrap_methods_offset,
@@ -480,9 +810,14 @@ pub struct ClassReaderEvents<'reader, 'class> {
     invisible_type_annotations_count: u16,
     invisible_type_annotations_offset: usize,
     is_deprecated: bool,
+    #[cfg(feature = "preview")]
+    loadable_descriptors_offset: usize,
+    module_hashes_offset: usize,
     module_main_offset: usize,
     module_offset: usize,
     module_packages_offset: usize,
+    module_resolution_offset: usize,
+    module_target_offset: usize,
     nest_host_offset: usize,
     nest_members_count: u16,
     nest_members_offset: usize,
@@ -498,6 +833,7 @@ pub struct ClassReaderEvents<'reader, 'class> {
     visible_type_annotations_count: u16,
     visible_type_annotations_offset: usize,
     custom_attributes_offsets: Vec<usize>,
+    attributes_offset: usize,
     bootstrap_methods: BootstrapMethods<'reader, 'class>,
     state: u8,
 }
@@ -596,6 +932,9 @@ impl<'reader, 'class> ClassReaderEvents<'reader, 'class> {
                 offset: self.module_offset + 6,
                 packages_offset: self.module_packages_offset,
                 main_offset: self.module_main_offset,
+                target_offset: self.module_target_offset,
+                resolution_offset: self.module_resolution_offset,
+                hashes_offset: self.module_hashes_offset,
                 state: 0,
             },
         }))
@@ -680,6 +1019,22 @@ impl<'reader, 'class> ClassReaderEvents<'reader, 'class> {
         )
     }
 
+    #[cfg(feature = "preview")]
+    fn loadable_descriptors(&self) -> ClassFileResult<Vec<Cow<'class, JavaStr>>> {
+        let count = self
+            .reader
+            .buffer
+            .read_u16(self.loadable_descriptors_offset)?;
+        (0..count as usize)
+            .map(|index| {
+                let offset = self.loadable_descriptors_offset + 2 + index * 2;
+                self.reader
+                    .constant_pool
+                    .get_utf8(self.reader.buffer.read_u16(offset)?)
+            })
+            .collect()
+    }
+
     fn inner_classes(&self) -> ClassInnerClassesReaderIterator<'reader, 'class> {
         ClassInnerClassesReaderIterator::new(
             self.reader,
@@ -782,22 +1137,33 @@ impl<'reader, 'class> Iterator for ClassReaderEvents<'reader, 'class> {
                         )));
                     }
                 }
+                #[cfg(feature = "preview")]
                 12 => {
+                    if self.loadable_descriptors_offset != 0 {
+                        return Some(
+                            self.loadable_descriptors()
+                                .map(ClassEvent::LoadableDescriptors),
+                        );
+                    }
+                }
+                #[cfg(not(feature = "preview"))]
+                12 => {}
+                13 => {
                     if self.inner_classes_offset != 0 {
                         return Some(Ok(ClassEvent::InnerClasses(self.inner_classes())));
                     }
                 }
-                13 => {
+                14 => {
                     if self.record_components_offset != 0 {
                         return Some(Ok(ClassEvent::Record(self.record_components())));
                     }
                 }
-                14 => {
+                15 => {
                     if self.fields_count != 0 {
                         return Some(Ok(ClassEvent::Fields(self.fields())));
                     }
                 }
-                15 => {
+                16 => {
                     if self.methods_count != 0 {
                         return Some(Ok(ClassEvent::Methods(self.methods())));
                     }
@@ -1095,11 +1461,18 @@ define_simple_iterator!(
         let mut visible_type_annotations_count = 0;
         let mut visible_type_annotations_offset = 0;
         let mut custom_attributes_offsets = Vec::new();
+        let mut seen_attributes = Vec::new();
 
         for _ in 0..attribute_count {
             let attribute_name = reader
                 .constant_pool
                 .get_utf8_as_bytes(reader.buffer.read_u16(*offset)?)?;
+            check_attribute(
+                reader,
+                attribute_name,
+                RECORD_COMPONENT_ATTRIBUTES,
+                &mut seen_attributes,
+            )?;
             *offset += 2;
             let attribute_length = reader.buffer.read_u32(*offset)?;
             *offset += 4;
@@ -1158,115 +1531,151 @@ define_simple_iterator!(
 define_simple_iterator!(
     ClassFieldsIterator,
     ClassFieldEvent<'class, FieldReaderEvents<'reader, 'class>>,
-    |reader: &'reader ClassReader<'class>, offset: &mut usize| -> ClassFileResult<_> {
-        let mut access = FieldAccess::from_bits_retain(reader.buffer.read_u16(*offset)?);
-        *offset += 2;
-        let name = reader
-            .constant_pool
-            .get_utf8(reader.buffer.read_u16(*offset)?)?;
-        *offset += 2;
-        let desc = reader
-            .constant_pool
-            .get_utf8(reader.buffer.read_u16(*offset)?)?;
-        *offset += 2;
+    |reader: &'reader ClassReader<'class>, offset: &mut usize| {
+        let start = *offset;
+        let field = read_field(reader, offset)
+            .map_err(|err| err.with_context(|| reader.member_path("field", start), start));
+        if field.is_err() && reader.reader_flags.contains(ClassReaderFlags::Lenient) {
+            *offset = reader.member_end(start).unwrap_or(reader.buffer.len());
+        }
+        field
+    }
+);
 
-        let attribute_count = reader.buffer.read_u16(*offset)?;
-        *offset += 2;
+fn read_field<'reader, 'class>(
+    reader: &'reader ClassReader<'class>,
+    offset: &mut usize,
+) -> ClassFileResult<ClassFieldEvent<'class, FieldReaderEvents<'reader, 'class>>> {
+    let access = reader.buffer.read_u16(*offset)?;
+    if reader.reader_flags.contains(ClassReaderFlags::Strict)
+        && FieldAccess::from_bits(access).is_none()
+    {
+        return Err(ClassFileError::UnknownAccessFlags(access));
+    }
+    let mut access = FieldAccess::from_bits_retain(access);
+    *offset += 2;
+    let name = reader
+        .constant_pool
+        .get_utf8(reader.buffer.read_u16(*offset)?)?;
+    *offset += 2;
+    let desc = reader
+        .constant_pool
+        .get_utf8(reader.buffer.read_u16(*offset)?)?;
+    *offset += 2;
 
-        let mut constant_value = None;
-        let mut invisible_annotations_count = 0;
-        let mut invisible_annotations_offset = 0;
-        let mut invisible_type_annotations_count = 0;
-        let mut invisible_type_annotations_offset = 0;
-        let mut is_deprecated = false;
-        let mut signature = None;
-        let mut visible_annotations_count = 0;
-        let mut visible_annotations_offset = 0;
-        let mut visible_type_annotations_count = 0;
-        let mut visible_type_annotations_offset = 0;
-        let mut custom_attributes_offsets = Vec::new();
+    let attribute_count = reader.buffer.read_u16(*offset)?;
+    *offset += 2;
 
-        for _ in 0..attribute_count {
-            let attribute_name = reader
-                .constant_pool
-                .get_utf8_as_bytes(reader.buffer.read_u16(*offset)?)?;
-            *offset += 2;
-            let attribute_length = reader.buffer.read_u32(*offset)?;
-            *offset += 4;
+    let mut constant_value = None;
+    let mut invisible_annotations_count = 0;
+    let mut invisible_annotations_offset = 0;
+    let mut invisible_type_annotations_count = 0;
+    let mut invisible_type_annotations_offset = 0;
+    let mut is_deprecated = false;
+    let mut signature = None;
+    let mut visible_annotations_count = 0;
+    let mut visible_annotations_offset = 0;
+    let mut visible_type_annotations_count = 0;
+    let mut visible_type_annotations_offset = 0;
+    let mut custom_attributes_offsets = Vec::new();
+    let mut seen_attributes = Vec::new();
+
+    for _ in 0..attribute_count {
+        let attribute_name = reader
+            .constant_pool
+            .get_utf8_as_bytes(reader.buffer.read_u16(*offset)?)?;
+        check_attribute(
+            reader,
+            attribute_name,
+            FIELD_ATTRIBUTES,
+            &mut seen_attributes,
+        )?;
+        *offset += 2;
+        let attribute_length = reader.buffer.read_u32(*offset)?;
+        *offset += 4;
 
-            match attribute_name {
-                b"ConstantValue" => {
-                    let cp_index = reader.buffer.read_u16(*offset)?;
-                    let constant = match reader.constant_pool.get(cp_index)? {
-                        ConstantPoolEntry::Integer(i) => FieldValue::Integer(i),
-                        ConstantPoolEntry::Float(f) => FieldValue::Float(f),
-                        ConstantPoolEntry::Long(l) => FieldValue::Long(l),
-                        ConstantPoolEntry::Double(d) => FieldValue::Double(d),
-                        ConstantPoolEntry::String(s) => FieldValue::String(s),
-                        _ => {
-                            return Err(
-                                ClassFileError::BadConstantPoolTypeExpectedFieldConstantValue(
-                                    reader.constant_pool.get_type(cp_index)?,
-                                ),
-                            )
-                        }
-                    };
-                    constant_value = Some(constant);
-                }
-                b"Deprecated" => is_deprecated = true,
-                b"RuntimeInvisibleAnnotations" => {
-                    invisible_annotations_count = reader.buffer.read_u16(*offset)?;
-                    invisible_annotations_offset = *offset + 2;
-                }
-                b"RuntimeInvisibleTypeAnnotations" => {
-                    invisible_type_annotations_count = reader.buffer.read_u16(*offset)?;
-                    invisible_type_annotations_offset = *offset + 2;
-                }
-                b"RuntimeVisibleAnnotations" => {
-                    visible_annotations_count = reader.buffer.read_u16(*offset)?;
-                    visible_annotations_offset = *offset + 2;
-                }
-                b"RuntimeVisibleTypeAnnotations" => {
-                    visible_type_annotations_count = reader.buffer.read_u16(*offset)?;
-                    visible_type_annotations_offset = *offset + 2;
-                }
-                b"Signature" => {
-                    signature = Some(
-                        reader
-                            .constant_pool
-                            .get_utf8(reader.buffer.read_u16(*offset)?)?,
-                    )
+        match attribute_name {
+            b"ConstantValue" => {
+                let cp_index = reader.buffer.read_u16(*offset)?;
+                let constant = match reader.constant_pool.get(cp_index)? {
+                    ConstantPoolEntry::Integer(i) => FieldValue::Integer(i),
+                    ConstantPoolEntry::Float(f) => FieldValue::Float(f),
+                    ConstantPoolEntry::Long(l) => FieldValue::Long(l),
+                    ConstantPoolEntry::Double(d) => FieldValue::Double(d),
+                    ConstantPoolEntry::String(s) => FieldValue::String(s),
+                    _ => {
+                        return Err(
+                            ClassFileError::BadConstantPoolTypeExpectedFieldConstantValue(
+                                reader.constant_pool.get_type(cp_index)?,
+                            ),
+                        )
+                    }
+                };
+                // the JVM ignores the attribute on instance fields
+                if reader.reader_flags.contains(ClassReaderFlags::Strict)
+                    && access.contains(FieldAccess::Static)
+                    && !constant.matches_desc(&desc)
+                {
+                    return Err(ClassFileError::ConstantValueMismatch {
+                        desc: desc.into_owned(),
+                        actual: constant.tag(),
+                    });
                 }
-                b"Synthetic" => access.insert(FieldAccess::Synthetic),
-                _ => custom_attributes_offsets.push(*offset - 6),
+                constant_value = Some(constant);
             }
-
-            *offset += attribute_length as usize;
+            b"Deprecated" => is_deprecated = true,
+            b"RuntimeInvisibleAnnotations" => {
+                invisible_annotations_count = reader.buffer.read_u16(*offset)?;
+                invisible_annotations_offset = *offset + 2;
+            }
+            b"RuntimeInvisibleTypeAnnotations" => {
+                invisible_type_annotations_count = reader.buffer.read_u16(*offset)?;
+                invisible_type_annotations_offset = *offset + 2;
+            }
+            b"RuntimeVisibleAnnotations" => {
+                visible_annotations_count = reader.buffer.read_u16(*offset)?;
+                visible_annotations_offset = *offset + 2;
+            }
+            b"RuntimeVisibleTypeAnnotations" => {
+                visible_type_annotations_count = reader.buffer.read_u16(*offset)?;
+                visible_type_annotations_offset = *offset + 2;
+            }
+            b"Signature" => {
+                signature = Some(
+                    reader
+                        .constant_pool
+                        .get_utf8(reader.buffer.read_u16(*offset)?)?,
+                )
+            }
+            b"Synthetic" => access.insert(FieldAccess::Synthetic),
+            _ => custom_attributes_offsets.push(*offset - 6),
         }
 
-        Ok(ClassFieldEvent {
-            access,
-            name,
-            desc,
-            signature,
-            value: constant_value,
-            events: FieldReaderEvents {
-                reader,
-                invisible_annotations_count,
-                invisible_annotations_offset,
-                invisible_type_annotations_count,
-                invisible_type_annotations_offset,
-                is_deprecated,
-                visible_annotations_count,
-                visible_annotations_offset,
-                visible_type_annotations_count,
-                visible_type_annotations_offset,
-                custom_attributes_offsets,
-                state: 0,
-            },
-        })
+        *offset += attribute_length as usize;
     }
-);
+
+    Ok(ClassFieldEvent {
+        access,
+        name,
+        desc,
+        signature,
+        value: constant_value,
+        events: FieldReaderEvents {
+            reader,
+            invisible_annotations_count,
+            invisible_annotations_offset,
+            invisible_type_annotations_count,
+            invisible_type_annotations_offset,
+            is_deprecated,
+            visible_annotations_count,
+            visible_annotations_offset,
+            visible_type_annotations_count,
+            visible_type_annotations_offset,
+            custom_attributes_offsets,
+            state: 0,
+        },
+    })
+}
 
 #[derive(Debug)]
 pub struct ClassMethodsIterator<'reader, 'class> {
@@ -1295,7 +1704,14 @@ impl<'reader, 'class> ClassMethodsIterator<'reader, 'class> {
     fn event(
         &mut self,
     ) -> ClassFileResult<ClassMethodEvent<'class, MethodReaderEvents<'reader, 'class>>> {
-        let mut access = MethodAccess::from_bits_retain(self.reader.buffer.read_u16(self.offset)?);
+        let start = self.offset;
+        let access = self.reader.buffer.read_u16(self.offset)?;
+        if self.reader.reader_flags.contains(ClassReaderFlags::Strict)
+            && MethodAccess::from_bits(access).is_none()
+        {
+            return Err(ClassFileError::UnknownAccessFlags(access));
+        }
+        let mut access = MethodAccess::from_bits_retain(access);
         self.offset += 2;
         let name = self
             .reader
@@ -1327,11 +1743,18 @@ impl<'reader, 'class> ClassMethodsIterator<'reader, 'class> {
         let mut visible_type_annotations_count = 0;
         let mut visible_type_annotations_offset = 0;
         let mut custom_attribute_offsets = Vec::new();
+        let mut seen_attributes = Vec::new();
         for _ in 0..attribute_count {
             let attribute_name = self
                 .reader
                 .constant_pool
                 .get_utf8_as_bytes(self.reader.buffer.read_u16(self.offset)?)?;
+            check_attribute(
+                self.reader,
+                attribute_name,
+                METHOD_ATTRIBUTES,
+                &mut seen_attributes,
+            )?;
             self.offset += 2;
             let attribute_length = self.reader.buffer.read_u32(self.offset)?;
             self.offset += 4;
@@ -1366,8 +1789,8 @@ impl<'reader, 'class> ClassMethodsIterator<'reader, 'class> {
                         .reader_flags
                         .contains(ClassReaderFlags::SkipDebug)
                     {
-                        parameters_count = self.reader.buffer.read_u16(self.offset)?;
-                        parameters_offset = self.offset + 2;
+                        parameters_count = self.reader.buffer.read_u8(self.offset)? as u16;
+                        parameters_offset = self.offset + 1;
                     }
                 }
                 b"RuntimeInvisibleAnnotations" => {
@@ -1406,12 +1829,15 @@ impl<'reader, 'class> ClassMethodsIterator<'reader, 'class> {
         }
         Ok(ClassMethodEvent {
             access,
-            name,
-            desc,
+            name: name.clone(),
+            desc: desc.clone(),
             signature,
             exceptions,
             events: MethodReaderEvents {
                 reader: self.reader,
+                access,
+                name,
+                desc,
                 annotation_default_offset,
                 code_offset,
                 invisible_annotations_count,
@@ -1428,10 +1854,17 @@ impl<'reader, 'class> ClassMethodsIterator<'reader, 'class> {
                 visible_type_annotations_count,
                 visible_type_annotations_offset,
                 custom_attribute_offsets,
+                label_creator: LabelCreator::default(),
                 code_data: None,
                 bootstrap_methods: self.bootstrap_methods.clone(),
                 state: 0,
                 code_index: 0,
+                unchanged: MethodUnchangedEvent {
+                    class: self.reader.buffer.data,
+                    index: self.count - self.remaining - 1,
+                    start,
+                    end: self.offset,
+                },
             },
         })
     }
@@ -1444,7 +1877,17 @@ impl<'reader, 'class> Iterator for ClassMethodsIterator<'reader, 'class> {
             return None;
         }
         self.remaining -= 1;
-        Some(self.event())
+        let start = self.offset;
+        let method = self
+            .event()
+            .map_err(|err| err.with_context(|| self.reader.member_path("method", start), start));
+        if method.is_err() && self.reader.reader_flags.contains(ClassReaderFlags::Lenient) {
+            self.offset = self
+                .reader
+                .member_end(start)
+                .unwrap_or(self.reader.buffer.len());
+        }
+        Some(method)
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.count as usize, Some(self.count as usize))
@@ -1516,14 +1959,14 @@ impl<'reader, 'class> Iterator for FieldReaderEvents<'reader, 'class> {
                 }
                 1 => {
                     if self.visible_annotations_offset != 0
-                        && self.invisible_annotations_offset != 0
+                        || self.invisible_annotations_offset != 0
                     {
                         return Some(Ok(FieldEvent::Annotations(self.annotations())));
                     }
                 }
                 2 => {
                     if self.visible_type_annotations_offset != 0
-                        && self.invisible_type_annotations_offset != 0
+                        || self.invisible_type_annotations_offset != 0
                     {
                         return Some(Ok(FieldEvent::TypeAnnotations(self.type_annotations())));
                     }
@@ -1559,6 +2002,9 @@ where
 #[derive(Debug)]
 pub struct MethodReaderEvents<'reader, 'class> {
     reader: &'reader ClassReader<'class>,
+    access: MethodAccess,
+    name: Cow<'class, JavaStr>,
+    desc: Cow<'class, JavaStr>,
     annotation_default_offset: usize,
     code_offset: usize,
     invisible_annotations_count: u16,
@@ -1575,13 +2021,20 @@ pub struct MethodReaderEvents<'reader, 'class> {
     visible_type_annotations_count: u16,
     visible_type_annotations_offset: usize,
     custom_attribute_offsets: Vec<usize>,
+    label_creator: LabelCreator,
     code_data: Option<CodeData<'reader, 'class>>,
     bootstrap_methods: BootstrapMethods<'reader, 'class>,
     state: u8,
     code_index: u16,
+    unchanged: MethodUnchangedEvent<'class>,
 }
 
 impl<'reader, 'class> MethodReaderEvents<'reader, 'class> {
+    const START_INSNS_STATE: u8 = 10;
+    const END_INSNS_STATE: u8 = 16;
+    const MAXS_STATE: u8 = 21;
+    const MAX_STATE: u8 = 22;
+
     pub fn is_deprecated(&self) -> bool {
         self.is_deprecated
     }
@@ -1640,16 +2093,28 @@ impl<'reader, 'class> MethodReaderEvents<'reader, 'class> {
     pub fn has_code(&self) -> bool {
         self.code_offset != 0
     }
-}
 
-impl<'reader, 'class> Iterator for MethodReaderEvents<'reader, 'class> {
-    type Item = ClassFileResult<MethodEvent<'class, MethodReaderEventProviders<'reader, 'class>>>;
+    /// An event to emit instead of the events of this method, if the method is unchanged.
+    pub fn unchanged(&self) -> MethodUnchangedEvent<'class> {
+        self.unchanged
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        const START_INSNS_STATE: u8 = 10;
-        const END_INSNS_STATE: u8 = 16;
-        const MAX_STATE: u8 = 22;
+    /// Skips the rest of the code without reading it. If the code event has already been returned,
+    /// the next event is the maxs event, otherwise no more events are returned.
+    pub fn skip_code(&mut self) {
+        self.state = if self.state <= 9 {
+            Self::MAX_STATE
+        } else {
+            self.state.max(Self::MAXS_STATE)
+        };
+    }
+}
 
+impl<'reader, 'class> MethodReaderEvents<'reader, 'class> {
+    fn next_event(
+        &mut self,
+    ) -> Option<ClassFileResult<MethodEvent<'class, MethodReaderEventProviders<'reader, 'class>>>>
+    {
         loop {
             let state = self.state;
             self.state += 1;
@@ -1672,7 +2137,7 @@ impl<'reader, 'class> Iterator for MethodReaderEvents<'reader, 'class> {
                 }
                 3 => {
                     if self.visible_annotations_offset != 0
-                        || self.invisible_type_annotations_offset != 0
+                        || self.invisible_annotations_offset != 0
                     {
                         return Some(Ok(MethodEvent::Annotations(self.annotations())));
                     }
@@ -1734,31 +2199,45 @@ impl<'reader, 'class> Iterator for MethodReaderEvents<'reader, 'class> {
                 }
                 9 => {
                     if self.code_offset == 0 {
-                        self.state = MAX_STATE;
+                        self.state = Self::MAX_STATE;
                         return None;
                     }
-
-                    let code_data = match CodeData::read(
-                        self.reader,
-                        self.code_offset,
-                        &self.bootstrap_methods,
-                    ) {
-                        Ok(code_data) => code_data,
-                        Err(err) => return Some(Err(err)),
-                    };
-
-                    let label_creator = code_data.label_creator.clone();
-                    self.code_data = Some(code_data);
-                    return Some(Ok(MethodEvent::Code { label_creator }));
+                    return Some(Ok(MethodEvent::Code {
+                        label_creator: self.label_creator.clone(),
+                    }));
                 }
-                START_INSNS_STATE => {
+                Self::START_INSNS_STATE => {
+                    // the code is only read once the first event after the code event is needed,
+                    // so that it's not read at all if skipped
+                    if self.code_data.is_none() {
+                        match CodeData::read(
+                            self.reader,
+                            self.code_offset,
+                            &self.bootstrap_methods,
+                            &self.label_creator,
+                            |this_class| {
+                                initial_frame_locals(
+                                    this_class,
+                                    self.access,
+                                    &self.name,
+                                    &self.desc,
+                                )
+                            },
+                        ) {
+                            Ok(code_data) => self.code_data = Some(code_data),
+                            Err(err) => {
+                                self.state = Self::MAX_STATE;
+                                return Some(Err(err));
+                            }
+                        }
+                    }
                     let code_data = self
                         .code_data
                         .as_ref()
                         .expect("should not reach this state with no code data");
 
                     if self.code_index as usize >= code_data.insn_metadata.len() {
-                        self.state = END_INSNS_STATE;
+                        self.state = Self::END_INSNS_STATE;
                         continue;
                     }
 
@@ -1825,10 +2304,10 @@ impl<'reader, 'class> Iterator for MethodReaderEvents<'reader, 'class> {
                 }
                 15 => {
                     self.code_index += 1;
-                    self.state = START_INSNS_STATE;
+                    self.state = Self::START_INSNS_STATE;
                     continue;
                 }
-                END_INSNS_STATE => {
+                Self::END_INSNS_STATE => {
                     let code_data = self
                         .code_data
                         .as_mut()
@@ -1894,28 +2373,49 @@ impl<'reader, 'class> Iterator for MethodReaderEvents<'reader, 'class> {
                         )));
                     }
                 }
-                21 => {
-                    let code_data = self
-                        .code_data
-                        .as_ref()
-                        .expect("should not reach this state with no code data");
-                    return Some(Ok(MethodEvent::Maxs(MethodMaxsEvent {
-                        max_locals: code_data.max_locals,
-                        max_stack: code_data.max_stack,
-                    })));
+                Self::MAXS_STATE => {
+                    let maxs =
+                        self.reader
+                            .buffer
+                            .read_u16(self.code_offset)
+                            .and_then(|max_stack| {
+                                Ok(MethodEvent::Maxs(MethodMaxsEvent {
+                                    max_stack,
+                                    max_locals: self
+                                        .reader
+                                        .buffer
+                                        .read_u16(self.code_offset + 2)?,
+                                }))
+                            });
+                    return Some(maxs);
                 }
-                MAX_STATE => return None,
+                Self::MAX_STATE => return None,
                 _ => return None,
             }
         }
     }
 }
 
+impl<'reader, 'class> Iterator for MethodReaderEvents<'reader, 'class> {
+    type Item = ClassFileResult<MethodEvent<'class, MethodReaderEventProviders<'reader, 'class>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let in_code = self.state >= Self::START_INSNS_STATE;
+        let event = self.next_event()?;
+        Some(event.map_err(|mut err| {
+            if in_code {
+                err = err.with_context(|| "Code".to_owned(), self.code_offset - 6);
+            }
+            let start = self.unchanged.start;
+            err.with_context(|| format!("method \"{}{}\"", self.name, self.desc), start)
+        }))
+    }
+}
+
 #[derive(Debug)]
 struct CodeData<'reader, 'class> {
     max_stack: u16,
     max_locals: u16,
-    label_creator: LabelCreator,
     insn_metadata: Box<[InstructionMetadata<'reader, 'class>]>,
     try_catch_blocks: Vec<MethodTryCatchBlockEvent<'class>>,
     try_catch_block_annotations: Vec<MethodTryCatchBlockAnnotationEvent<'class>>,
@@ -1929,14 +2429,14 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
         reader: &'reader ClassReader<'class>,
         mut offset: usize,
         bootstrap_methods: &BootstrapMethods<'reader, 'class>,
+        label_creator: &LabelCreator,
+        initial_locals: impl FnOnce(&Cow<'class, JavaStr>) -> Vec<FrameValue<'class>>,
     ) -> ClassFileResult<CodeData<'reader, 'class>> {
         let max_stack = reader.buffer.read_u16(offset)?;
         offset += 2;
         let max_locals = reader.buffer.read_u16(offset)?;
         offset += 2;
 
-        let label_creator = LabelCreator::default();
-
         let code_length = reader.buffer.read_u32(offset)?;
         offset += 4;
         if code_length == 0 || code_length > 65535 {
@@ -1956,7 +2456,7 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
             code,
             bootstrap_methods,
             &mut insn_metadata,
-            &label_creator,
+            label_creator,
         )?;
 
         let try_catch_block_count = reader.buffer.read_u16(offset)?;
@@ -1977,13 +2477,13 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
 
             let start = insn_metadata
                 .get_code_mut(start_pc as usize)?
-                .get_or_create_label(&label_creator);
+                .get_or_create_label(label_creator);
             let end = insn_metadata
                 .get_code_mut(end_pc as usize)?
-                .get_or_create_label(&label_creator);
+                .get_or_create_label(label_creator);
             let handler = insn_metadata
                 .get_code_mut(handler_pc as usize)?
-                .get_or_create_label(&label_creator);
+                .get_or_create_label(label_creator);
 
             try_catch_blocks.push(MethodTryCatchBlockEvent {
                 start,
@@ -2003,11 +2503,18 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
         let mut stack_map_table_offset = 0;
         let mut try_catch_block_annotations = Vec::new();
         let mut custom_attribute_offsets = Vec::new();
+        let mut seen_attributes = Vec::new();
 
         for _ in 0..attribute_count {
             let attribute_name = reader
                 .constant_pool
                 .get_utf8_as_bytes(reader.buffer.read_u16(offset)?)?;
+            check_attribute(
+                reader,
+                attribute_name,
+                CODE_ATTRIBUTES,
+                &mut seen_attributes,
+            )?;
             offset += 2;
             let attribute_length = reader.buffer.read_u32(offset)?;
             offset += 4;
@@ -2021,7 +2528,7 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
                             let line_number =
                                 reader.buffer.read_u16(offset + 4 + 4 * i as usize)?;
                             let metadata = insn_metadata.get_code_mut(start_pc as usize)?;
-                            metadata.get_or_create_label(&label_creator);
+                            metadata.get_or_create_label(label_creator);
                             metadata.line_number = Some(line_number);
                         }
                     }
@@ -2043,10 +2550,10 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
 
                             let start = insn_metadata
                                 .get_code_mut(start_pc as usize)?
-                                .get_or_create_label(&label_creator);
+                                .get_or_create_label(label_creator);
                             let end = insn_metadata
                                 .get_code_mut(start_pc as usize + length as usize)?
-                                .get_or_create_label(&label_creator);
+                                .get_or_create_label(label_creator);
 
                             lvt.push(MethodLocalVariableEvent {
                                 start,
@@ -2073,7 +2580,7 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
                         &mut local_variable_annotations,
                         &mut try_catch_block_annotations,
                         &mut insn_metadata,
-                        &label_creator,
+                        label_creator,
                     )?;
                 }
                 b"RuntimeVisibleTypeAnnotations" => {
@@ -2084,7 +2591,7 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
                         &mut local_variable_annotations,
                         &mut try_catch_block_annotations,
                         &mut insn_metadata,
-                        &label_creator,
+                        label_creator,
                     )?;
                 }
                 _ => custom_attribute_offsets.push(offset - 6),
@@ -2118,19 +2625,24 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
         if !reader.reader_flags.contains(ClassReaderFlags::SkipFrames)
             && stack_map_table_offset != 0
         {
+            let initial_locals = if reader.reader_flags.contains(ClassReaderFlags::ExpandFrames) {
+                Some(initial_locals(&reader.name()?))
+            } else {
+                None
+            };
             Self::read_frames(
                 reader,
                 stack_map_table_offset,
                 stack_map_compressed,
                 &mut insn_metadata,
-                &label_creator,
+                label_creator,
+                initial_locals,
             )?;
         }
 
         Ok(CodeData {
             max_stack,
             max_locals,
-            label_creator,
             insn_metadata,
             try_catch_blocks,
             try_catch_block_annotations,
@@ -2580,7 +3092,7 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
                                     ))
                                 })
                                 .collect::<ClassFileResult<Vec<_>>>()?;
-                            i += 4 + 8 * npairs as usize;
+                            i += 8 + 8 * npairs as usize;
                             MethodEvent::LookupSwitchInsn { dflt, values }
                         }
                         Opcode::GetStatic
@@ -2758,6 +3270,7 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
                 TypeAnnotationCodeLocation::TryCatchBlock(index) => {
                     try_catch_block_annotations.push(MethodTryCatchBlockAnnotationEvent {
                         try_catch_block_index: index,
+                        visible,
                         annotation,
                     });
                 }
@@ -2773,103 +3286,127 @@ impl<'reader, 'class> CodeData<'reader, 'class> {
         compressed: bool,
         insn_metadata: &mut [InstructionMetadata<'reader, 'class>],
         label_creator: &LabelCreator,
+        mut expanded_locals: Option<Vec<FrameValue<'class>>>,
     ) -> ClassFileResult<()> {
         let frame_count = reader.buffer.read_u16(offset)?;
         offset += 2;
 
         let mut last_code_offset = None;
 
-        for _ in 0..frame_count {
-            let frame_type = if compressed {
-                let frame_type = reader.buffer.read_u8(offset)?;
-                offset += 1;
-                frame_type
-            } else {
-                255 // full
-            };
+        for index in 0..frame_count {
+            let frame_start = offset;
+            let mut read_frame = || -> ClassFileResult<()> {
+                let frame_type = if compressed {
+                    let frame_type = reader.buffer.read_u8(offset)?;
+                    offset += 1;
+                    frame_type
+                } else {
+                    255 // full
+                };
 
-            let (offset_delta, frame) = match frame_type {
-                0..=63 => (frame_type as u16, Frame::Same),
-                64..=127 => {
-                    let stack_value =
-                        Self::read_frame_value(reader, &mut offset, insn_metadata, label_creator)?;
-                    ((frame_type - 64) as u16, Frame::Same1 { stack_value })
-                }
-                247 => {
-                    let offset_delta = reader.buffer.read_u16(offset)?;
-                    offset += 2;
-                    let stack_value =
-                        Self::read_frame_value(reader, &mut offset, insn_metadata, label_creator)?;
-                    (offset_delta, Frame::Same1 { stack_value })
-                }
-                248..=250 => {
-                    let offset_delta = reader.buffer.read_u16(offset)?;
-                    offset += 2;
-                    (
-                        offset_delta,
-                        Frame::Chop {
-                            num_locals: 251 - frame_type,
-                        },
-                    )
-                }
-                251 => {
-                    let offset_delta = reader.buffer.read_u16(offset)?;
-                    offset += 2;
-                    (offset_delta, Frame::Same)
-                }
-                252..=254 => {
-                    let offset_delta = reader.buffer.read_u16(offset)?;
-                    offset += 2;
-                    let locals = (0..frame_type - 251)
-                        .map(|_| {
-                            Self::read_frame_value(
-                                reader,
-                                &mut offset,
-                                insn_metadata,
-                                label_creator,
-                            )
-                        })
-                        .collect::<ClassFileResult<Vec<_>>>()?;
-                    (offset_delta, Frame::Append { locals })
-                }
-                255 => {
-                    let offset_delta = reader.buffer.read_u16(offset)?;
-                    offset += 2;
-                    let local_count = reader.buffer.read_u16(offset)?;
-                    offset += 2;
-                    let locals = (0..local_count)
-                        .map(|_| {
-                            Self::read_frame_value(
-                                reader,
-                                &mut offset,
-                                insn_metadata,
-                                label_creator,
-                            )
-                        })
-                        .collect::<ClassFileResult<Vec<_>>>()?;
-                    let stack_count = reader.buffer.read_u16(offset)?;
-                    offset += 2;
-                    let stack = (0..stack_count)
-                        .map(|_| {
-                            Self::read_frame_value(
-                                reader,
-                                &mut offset,
-                                insn_metadata,
-                                label_creator,
-                            )
-                        })
-                        .collect::<ClassFileResult<Vec<_>>>()?;
-                    (offset_delta, Frame::Full { locals, stack })
-                }
-                _ => return Err(ClassFileError::BadFrameType(frame_type)),
-            };
+                let (offset_delta, mut frame) = match frame_type {
+                    0..=63 => (frame_type as u16, Frame::Same),
+                    64..=127 => {
+                        let stack_value = Self::read_frame_value(
+                            reader,
+                            &mut offset,
+                            insn_metadata,
+                            label_creator,
+                        )?;
+                        ((frame_type - 64) as u16, Frame::Same1 { stack_value })
+                    }
+                    247 => {
+                        let offset_delta = reader.buffer.read_u16(offset)?;
+                        offset += 2;
+                        let stack_value = Self::read_frame_value(
+                            reader,
+                            &mut offset,
+                            insn_metadata,
+                            label_creator,
+                        )?;
+                        (offset_delta, Frame::Same1 { stack_value })
+                    }
+                    248..=250 => {
+                        let offset_delta = reader.buffer.read_u16(offset)?;
+                        offset += 2;
+                        (
+                            offset_delta,
+                            Frame::Chop {
+                                num_locals: 251 - frame_type,
+                            },
+                        )
+                    }
+                    251 => {
+                        let offset_delta = reader.buffer.read_u16(offset)?;
+                        offset += 2;
+                        (offset_delta, Frame::Same)
+                    }
+                    252..=254 => {
+                        let offset_delta = reader.buffer.read_u16(offset)?;
+                        offset += 2;
+                        let locals = (0..frame_type - 251)
+                            .map(|_| {
+                                Self::read_frame_value(
+                                    reader,
+                                    &mut offset,
+                                    insn_metadata,
+                                    label_creator,
+                                )
+                            })
+                            .collect::<ClassFileResult<Vec<_>>>()?;
+                        (offset_delta, Frame::Append { locals })
+                    }
+                    255 => {
+                        let offset_delta = reader.buffer.read_u16(offset)?;
+                        offset += 2;
+                        let local_count = reader.buffer.read_u16(offset)?;
+                        offset += 2;
+                        let locals = (0..local_count)
+                            .map(|_| {
+                                Self::read_frame_value(
+                                    reader,
+                                    &mut offset,
+                                    insn_metadata,
+                                    label_creator,
+                                )
+                            })
+                            .collect::<ClassFileResult<Vec<_>>>()?;
+                        let stack_count = reader.buffer.read_u16(offset)?;
+                        offset += 2;
+                        let stack = (0..stack_count)
+                            .map(|_| {
+                                Self::read_frame_value(
+                                    reader,
+                                    &mut offset,
+                                    insn_metadata,
+                                    label_creator,
+                                )
+                            })
+                            .collect::<ClassFileResult<Vec<_>>>()?;
+                        (offset_delta, Frame::Full { locals, stack })
+                    }
+                    _ => return Err(ClassFileError::BadFrameType(frame_type)),
+                };
 
-            let code_offset = match last_code_offset {
-                None => offset_delta as usize,
-                Some(last_code_offset) => last_code_offset + offset_delta as usize + 1,
+                let code_offset = match last_code_offset {
+                    None => offset_delta as usize,
+                    Some(last_code_offset) => last_code_offset + offset_delta as usize + 1,
+                };
+                last_code_offset = Some(code_offset);
+                if let Some(locals) = &mut expanded_locals {
+                    frame = expand_frame(locals, frame);
+                }
+                insn_metadata.get_code_mut(code_offset)?.frame = Some(frame);
+                Ok(())
             };
-            last_code_offset = Some(code_offset);
-            insn_metadata.get_code_mut(code_offset)?.frame = Some(frame);
+            read_frame().map_err(|err| {
+                let attribute_name = if compressed {
+                    "StackMapTable"
+                } else {
+                    "StackMap"
+                };
+                err.with_context(|| format!("{attribute_name} entry {index}"), frame_start)
+            })?;
         }
 
         Ok(())
@@ -3686,12 +4223,22 @@ impl<'reader, 'class> Iterator for AnnotationReaderIterator<'reader, 'class> {
     type Item = ClassFileResult<AnnotationEvent<AnnotationNode<'class>>>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let lenient = self.reader.reader_flags.contains(ClassReaderFlags::Lenient);
         if self.visible_remaining != 0 {
             self.visible_remaining -= 1;
-            Some(Self::event(self.reader, true, &mut self.visible_offset))
+            let event = Self::event(self.reader, true, &mut self.visible_offset);
+            if event.is_err() && lenient {
+                // the end of the broken annotation is unknown, so skip the rest of the attribute
+                self.visible_remaining = 0;
+            }
+            Some(event)
         } else if self.invisible_remaining != 0 {
             self.invisible_remaining -= 1;
-            Some(Self::event(self.reader, false, &mut self.invisible_offset))
+            let event = Self::event(self.reader, false, &mut self.invisible_offset);
+            if event.is_err() && lenient {
+                self.invisible_remaining = 0;
+            }
+            Some(event)
         } else {
             None
         }
@@ -3751,12 +4298,22 @@ impl<'reader, 'class> Iterator for TypeAnnotationReaderIterator<'reader, 'class>
     type Item = ClassFileResult<AnnotationEvent<TypeAnnotationNode<'class>>>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let lenient = self.reader.reader_flags.contains(ClassReaderFlags::Lenient);
         if self.visible_remaining != 0 {
             self.visible_remaining -= 1;
-            Some(Self::event(self.reader, true, &mut self.visible_offset))
+            let event = Self::event(self.reader, true, &mut self.visible_offset);
+            if event.is_err() && lenient {
+                // the end of the broken annotation is unknown, so skip the rest of the attribute
+                self.visible_remaining = 0;
+            }
+            Some(event)
         } else if self.invisible_remaining != 0 {
             self.invisible_remaining -= 1;
-            Some(Self::event(self.reader, false, &mut self.invisible_offset))
+            let event = Self::event(self.reader, false, &mut self.invisible_offset);
+            if event.is_err() && lenient {
+                self.invisible_remaining = 0;
+            }
+            Some(event)
         } else {
             None
         }
@@ -3777,6 +4334,9 @@ pub struct ModuleReaderEvents<'reader, 'class> {
     offset: usize,
     packages_offset: usize,
     main_offset: usize,
+    target_offset: usize,
+    resolution_offset: usize,
+    hashes_offset: usize,
     state: u8,
 }
 
@@ -3786,7 +4346,7 @@ impl<'reader, 'class> ModuleReaderEvents<'reader, 'class> {
             return Ok(None);
         }
 
-        Ok(Some(self.reader.constant_pool.get_utf8(
+        Ok(Some(self.reader.constant_pool.get_class(
             self.reader.buffer.read_u16(self.main_offset)?,
         )?))
     }
@@ -3804,6 +4364,55 @@ impl<'reader, 'class> ModuleReaderEvents<'reader, 'class> {
         ))
     }
 
+    pub fn target(&self) -> ClassFileResult<Option<Cow<'class, JavaStr>>> {
+        if self.target_offset == 0 {
+            return Ok(None);
+        }
+
+        self.reader
+            .constant_pool
+            .get_optional_utf8(self.reader.buffer.read_u16(self.target_offset)?)
+    }
+
+    pub fn resolution(&self) -> ClassFileResult<Option<ModuleResolution>> {
+        if self.resolution_offset == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(ModuleResolution::from_bits_retain(
+            self.reader.buffer.read_u16(self.resolution_offset)?,
+        )))
+    }
+
+    pub fn hashes(&self) -> ClassFileResult<Option<ModuleHashesEvent<'class>>> {
+        if self.hashes_offset == 0 {
+            return Ok(None);
+        }
+
+        let mut offset = self.hashes_offset;
+        let algorithm = self
+            .reader
+            .constant_pool
+            .get_utf8(self.reader.buffer.read_u16(offset)?)?;
+        let hashes_count = self.reader.buffer.read_u16(offset + 2)?;
+        offset += 4;
+        let mut hashes = Vec::with_capacity(hashes_count as usize);
+        for _ in 0..hashes_count {
+            let module = self
+                .reader
+                .constant_pool
+                .get_module(self.reader.buffer.read_u16(offset)?)?;
+            let hash_len = self.reader.buffer.read_u16(offset + 2)? as usize;
+            let hash = self.reader.buffer.read_bytes(offset + 4, hash_len)?;
+            offset += 4 + hash_len;
+            hashes.push(ModuleHashEvent {
+                module,
+                hash: Cow::Borrowed(hash),
+            });
+        }
+        Ok(Some(ModuleHashesEvent { algorithm, hashes }))
+    }
+
     fn requires_internal(
         &mut self,
     ) -> ClassFileResult<Option<ModuleRequireReaderIterator<'reader, 'class>>> {
@@ -3885,26 +4494,41 @@ impl<'reader, 'class> Iterator for ModuleReaderEvents<'reader, 'class> {
                     }
                 }
                 2 => {
+                    if let Some(target) = self.target().transpose() {
+                        return Some(target.map(ModuleEvent::Target));
+                    }
+                }
+                3 => {
+                    if let Some(resolution) = self.resolution().transpose() {
+                        return Some(resolution.map(ModuleEvent::Resolution));
+                    }
+                }
+                4 => {
+                    if let Some(hashes) = self.hashes().transpose() {
+                        return Some(hashes.map(ModuleEvent::Hashes));
+                    }
+                }
+                5 => {
                     if let Some(requires) = self.requires_internal().transpose() {
                         return Some(requires.map(ModuleEvent::Requires));
                     }
                 }
-                3 => {
+                6 => {
                     if let Some(exports) = self.relations_internal().transpose() {
                         return Some(exports.map(ModuleEvent::Exports));
                     }
                 }
-                4 => {
+                7 => {
                     if let Some(opens) = self.relations_internal().transpose() {
                         return Some(opens.map(ModuleEvent::Opens));
                     }
                 }
-                5 => {
+                8 => {
                     if let Some(uses) = self.uses_internal().transpose() {
                         return Some(uses.map(ModuleEvent::Uses));
                     }
                 }
-                6 => {
+                9 => {
                     // no need to increment the offset here as this is the last thing visited
                     let provides_count = match self.reader.buffer.read_u16(self.offset) {
                         Ok(count) => count,
@@ -4135,13 +4759,15 @@ impl<'reader, 'class> CustomAttributeReaderIterator<'reader, 'class> {
             .reader
             .constant_pool
             .get_utf8(self.reader.buffer.read_u16(offset)?)?;
-        let len = self.reader.buffer.read_u32(offset)?;
+        let len = self.reader.buffer.read_u32(offset + 2)?;
         let buffer = self
             .reader
             .buffer
             .slice(offset + 6..offset + 6 + len as usize)?;
         match self.reader.attribute_readers.get(name.as_ref()) {
-            Some(reader) => reader.read(&name, self.reader, buffer),
+            Some(reader) => reader
+                .read(&name, self.reader, buffer)
+                .map_err(|err| err.with_context(|| format!("attribute \"{name}\""), offset)),
             None => Ok(Box::new(UnknownAttribute {
                 name: name.into_owned(),
                 data: buffer.data.to_vec(),
@@ -4204,18 +4830,361 @@ define_simple_iterator!(
     }
 );
 
+/// The attributes predefined by JVMS 4.7, the module attributes of the JDK and, with the
+/// `preview` feature, the Valhalla attributes, which may appear in each kind of structure.
+const CLASS_ATTRIBUTES: &[&[u8]] = &[
+    b"BootstrapMethods",
+    b"Deprecated",
+    b"EnclosingMethod",
+    b"InnerClasses",
+    #[cfg(feature = "preview")]
+    b"LoadableDescriptors",
+    b"Module",
+    b"ModuleHashes",
+    b"ModuleMainClass",
+    b"ModulePackages",
+    b"ModuleResolution",
+    b"ModuleTarget",
+    b"NestHost",
+    b"NestMembers",
+    b"PermittedSubclasses",
+    b"Record",
+    b"RuntimeInvisibleAnnotations",
+    b"RuntimeInvisibleTypeAnnotations",
+    b"RuntimeVisibleAnnotations",
+    b"RuntimeVisibleTypeAnnotations",
+    b"Signature",
+    b"SourceDebugExtension",
+    b"SourceFile",
+    b"Synthetic",
+];
+const FIELD_ATTRIBUTES: &[&[u8]] = &[
+    b"ConstantValue",
+    b"Deprecated",
+    b"RuntimeInvisibleAnnotations",
+    b"RuntimeInvisibleTypeAnnotations",
+    b"RuntimeVisibleAnnotations",
+    b"RuntimeVisibleTypeAnnotations",
+    b"Signature",
+    b"Synthetic",
+];
+const METHOD_ATTRIBUTES: &[&[u8]] = &[
+    b"AnnotationDefault",
+    b"Code",
+    b"Deprecated",
+    b"Exceptions",
+    b"MethodParameters",
+    b"RuntimeInvisibleAnnotations",
+    b"RuntimeInvisibleParameterAnnotations",
+    b"RuntimeInvisibleTypeAnnotations",
+    b"RuntimeVisibleAnnotations",
+    b"RuntimeVisibleParameterAnnotations",
+    b"RuntimeVisibleTypeAnnotations",
+    b"Signature",
+    b"Synthetic",
+];
+const CODE_ATTRIBUTES: &[&[u8]] = &[
+    b"LineNumberTable",
+    b"LocalVariableTable",
+    b"LocalVariableTypeTable",
+    b"RuntimeInvisibleTypeAnnotations",
+    b"RuntimeVisibleTypeAnnotations",
+    b"StackMap",
+    b"StackMapTable",
+];
+const RECORD_COMPONENT_ATTRIBUTES: &[&[u8]] = &[
+    b"RuntimeInvisibleAnnotations",
+    b"RuntimeInvisibleTypeAnnotations",
+    b"RuntimeVisibleAnnotations",
+    b"RuntimeVisibleTypeAnnotations",
+    b"Signature",
+];
+/// The predefined attributes which may appear more than once in the same structure.
+const REPEATABLE_ATTRIBUTES: &[&[u8]] = &[
+    b"LineNumberTable",
+    b"LocalVariableTable",
+    b"LocalVariableTypeTable",
+];
+
+/// In strict mode, checks that a predefined attribute is allowed in the structure it appears in,
+/// and that it appears at most once unless it's repeatable. Other attributes are always allowed.
+fn check_attribute<'a>(
+    reader: &ClassReader,
+    name: &'a [u8],
+    allowed: &[&[u8]],
+    seen: &mut Vec<&'a [u8]>,
+) -> ClassFileResult<()> {
+    if !reader.reader_flags.contains(ClassReaderFlags::Strict) {
+        return Ok(());
+    }
+    let name_string =
+        || -> ClassFileResult<JavaString> { Ok(JavaStr::from_modified_utf8(name)?.into_owned()) };
+    if allowed.contains(&name) {
+        if seen.contains(&name) && !REPEATABLE_ATTRIBUTES.contains(&name) {
+            return Err(ClassFileError::DuplicateAttribute(name_string()?));
+        }
+        seen.push(name);
+    } else if [
+        CLASS_ATTRIBUTES,
+        FIELD_ATTRIBUTES,
+        METHOD_ATTRIBUTES,
+        CODE_ATTRIBUTES,
+        RECORD_COMPONENT_ATTRIBUTES,
+    ]
+    .iter()
+    .any(|attributes| attributes.contains(&name))
+    {
+        return Err(ClassFileError::MisplacedAttribute(name_string()?));
+    }
+    Ok(())
+}
+
+fn find_insn_references(
+    code: ClassBuffer,
+    method_index: u16,
+    references: &mut ConstantPoolReferences,
+) -> ClassFileResult<()> {
+    let mut i = 0;
+    while i < code.len() {
+        let (size, cst_index) = match code.read_u8(i)? {
+            InternalOpcodes::LDC_W | InternalOpcodes::LDC2_W => (3, Some(code.read_u16(i + 1)?)),
+            InternalOpcodes::ILOAD_0..=InternalOpcodes::ALOAD_3
+            | InternalOpcodes::ISTORE_0..=InternalOpcodes::ASTORE_3 => (1, None),
+            InternalOpcodes::WIDE => {
+                if code.read_u8(i + 1)? == Opcode::IInc as u8 {
+                    (6, None)
+                } else {
+                    (4, None)
+                }
+            }
+            InternalOpcodes::GOTO_W | InternalOpcodes::JSR_W => (5, None),
+            opcode => {
+                let opcode =
+                    Opcode::try_from(opcode).map_err(|_| ClassFileError::BadOpcode(opcode))?;
+                match opcode {
+                    Opcode::Ldc => (2, Some(code.read_u8(i + 1)? as u16)),
+                    Opcode::GetStatic
+                    | Opcode::PutStatic
+                    | Opcode::GetField
+                    | Opcode::PutField
+                    | Opcode::InvokeVirtual
+                    | Opcode::InvokeSpecial
+                    | Opcode::InvokeStatic
+                    | Opcode::New
+                    | Opcode::ANewArray
+                    | Opcode::CheckCast
+                    | Opcode::Instanceof => (3, Some(code.read_u16(i + 1)?)),
+                    Opcode::MultiANewArray => (4, Some(code.read_u16(i + 1)?)),
+                    Opcode::InvokeInterface | Opcode::InvokeDynamic => {
+                        (5, Some(code.read_u16(i + 1)?))
+                    }
+                    Opcode::BIPush
+                    | Opcode::NewArray
+                    | Opcode::ILoad
+                    | Opcode::LLoad
+                    | Opcode::FLoad
+                    | Opcode::DLoad
+                    | Opcode::ALoad
+                    | Opcode::IStore
+                    | Opcode::LStore
+                    | Opcode::FStore
+                    | Opcode::DStore
+                    | Opcode::AStore
+                    | Opcode::Ret => (2, None),
+                    Opcode::SIPush
+                    | Opcode::IInc
+                    | Opcode::IfEq
+                    | Opcode::IfNe
+                    | Opcode::IfLt
+                    | Opcode::IfGe
+                    | Opcode::IfGt
+                    | Opcode::IfLe
+                    | Opcode::IfICmpEq
+                    | Opcode::IfICmpNe
+                    | Opcode::IfICmpLt
+                    | Opcode::IfICmpGe
+                    | Opcode::IfICmpGt
+                    | Opcode::IfICmpLe
+                    | Opcode::IfACmpEq
+                    | Opcode::IfACmpNe
+                    | Opcode::Goto
+                    | Opcode::Jsr
+                    | Opcode::IfNull
+                    | Opcode::IfNonNull => (3, None),
+                    Opcode::TableSwitch => {
+                        let start = (i + 1).next_multiple_of(4);
+                        let low = code.read_i32(start + 4)?;
+                        let high = code.read_i32(start + 8)?;
+                        if low > high {
+                            return Err(ClassFileError::TableSwitchBoundsWrongOrder { low, high });
+                        }
+                        let label_count = high.wrapping_sub(low) as u32 as usize + 1;
+                        (start - i + 12 + 4 * label_count, None)
+                    }
+                    Opcode::LookupSwitch => {
+                        let start = (i + 1).next_multiple_of(4);
+                        let npairs = code.read_u32(start + 4)? as usize;
+                        (start - i + 8 + 8 * npairs, None)
+                    }
+                    _ => (1, None),
+                }
+            }
+        };
+        if let Some(cst_index) = cst_index {
+            references.add_insn(
+                cst_index,
+                InsnReference {
+                    method_index,
+                    offset: i as u16,
+                },
+            )?;
+        }
+        i += size;
+    }
+    Ok(())
+}
+
+fn collect_errors_from<I, T>(
+    items: I,
+    recoverable: bool,
+    errors: &mut Vec<ClassFileError>,
+    mut visit: impl FnMut(T, &mut Vec<ClassFileError>),
+) where
+    I: IntoIterator<Item = ClassFileResult<T>>,
+{
+    for item in items {
+        match item {
+            Ok(item) => visit(item, errors),
+            Err(err) => {
+                errors.push(err);
+                if !recoverable {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn collect_item_errors<I, T>(items: I, recoverable: bool, errors: &mut Vec<ClassFileError>)
+where
+    I: IntoIterator<Item = ClassFileResult<T>>,
+{
+    collect_errors_from(items, recoverable, errors, |_, _| {});
+}
+
+fn collect_class_errors<'class, I, P>(events: I, errors: &mut Vec<ClassFileError>)
+where
+    I: IntoIterator<Item = ClassFileResult<ClassEvent<'class, P>>>,
+    P: ClassEventProviders<'class>,
+{
+    collect_errors_from(events, true, errors, |event, errors| match event {
+        ClassEvent::Module(module) => {
+            collect_errors_from(module.events, true, errors, |event, errors| match event {
+                ModuleEvent::Packages(packages) => collect_item_errors(packages, false, errors),
+                ModuleEvent::Requires(requires) => collect_item_errors(requires, false, errors),
+                ModuleEvent::Exports(exports) => collect_item_errors(exports, false, errors),
+                ModuleEvent::Opens(opens) => collect_item_errors(opens, false, errors),
+                ModuleEvent::Uses(uses) => collect_item_errors(uses, false, errors),
+                ModuleEvent::Provides(provides) => collect_item_errors(provides, false, errors),
+                _ => {}
+            })
+        }
+        ClassEvent::Annotations(annotations) => collect_item_errors(annotations, true, errors),
+        ClassEvent::TypeAnnotations(annotations) => collect_item_errors(annotations, true, errors),
+        ClassEvent::Attributes(attributes) => collect_item_errors(attributes, true, errors),
+        ClassEvent::NestMembers(classes) => collect_item_errors(classes, false, errors),
+        ClassEvent::PermittedSubclasses(classes) => collect_item_errors(classes, false, errors),
+        ClassEvent::InnerClasses(inner_classes) => {
+            collect_item_errors(inner_classes, false, errors)
+        }
+        ClassEvent::Record(components) => {
+            collect_errors_from(components, false, errors, |component, errors| {
+                collect_errors_from(
+                    component.events,
+                    true,
+                    errors,
+                    |event, errors| match event {
+                        RecordComponentEvent::Annotations(annotations) => {
+                            collect_item_errors(annotations, true, errors)
+                        }
+                        RecordComponentEvent::TypeAnnotations(annotations) => {
+                            collect_item_errors(annotations, true, errors)
+                        }
+                        RecordComponentEvent::Attributes(attributes) => {
+                            collect_item_errors(attributes, true, errors)
+                        }
+                    },
+                )
+            })
+        }
+        ClassEvent::Fields(fields) => collect_errors_from(fields, true, errors, |field, errors| {
+            collect_errors_from(field.events, true, errors, |event, errors| match event {
+                FieldEvent::Annotations(annotations) => {
+                    collect_item_errors(annotations, true, errors)
+                }
+                FieldEvent::TypeAnnotations(annotations) => {
+                    collect_item_errors(annotations, true, errors)
+                }
+                FieldEvent::Attributes(attributes) => collect_item_errors(attributes, true, errors),
+                FieldEvent::Deprecated => {}
+            })
+        }),
+        ClassEvent::Methods(methods) => {
+            collect_errors_from(methods, true, errors, |method, errors| {
+                collect_method_errors(method.events, errors)
+            })
+        }
+        _ => {}
+    })
+}
+
+fn collect_method_errors<'class, I, P>(events: I, errors: &mut Vec<ClassFileError>)
+where
+    I: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+    P: MethodEventProviders<'class>,
+{
+    collect_errors_from(events, true, errors, |event, errors| match event {
+        MethodEvent::Parameters(parameters) => collect_item_errors(parameters, false, errors),
+        MethodEvent::Annotations(annotations) => collect_item_errors(annotations, true, errors),
+        MethodEvent::TypeAnnotations(annotations) => collect_item_errors(annotations, true, errors),
+        MethodEvent::InsnAnnotations(annotations) => collect_item_errors(annotations, true, errors),
+        MethodEvent::ParameterAnnotations(annotations) => {
+            collect_item_errors(annotations, false, errors)
+        }
+        MethodEvent::Attributes(attributes) => collect_item_errors(attributes, true, errors),
+        MethodEvent::CodeAttributes(attributes) => collect_item_errors(attributes, true, errors),
+        MethodEvent::LocalVariables(local_variables) => {
+            collect_item_errors(local_variables, false, errors)
+        }
+        MethodEvent::LocalVariableAnnotations(annotations) => {
+            collect_item_errors(annotations, false, errors)
+        }
+        MethodEvent::TryCatchBlocks(try_catch_blocks) => {
+            collect_item_errors(try_catch_blocks, false, errors)
+        }
+        MethodEvent::TryCatchBlockAnnotations(annotations) => {
+            collect_item_errors(annotations, false, errors)
+        }
+        _ => {}
+    })
+}
+
 #[cfg(test)]
 mod test {
-    use crate::tree::{AnnotationNode, AnnotationValue, TypeAnnotationNode};
+    #[cfg(feature = "preview")]
+    use crate::tree::FieldNode;
+    use crate::tree::{AnnotationNode, AnnotationValue, ClassNode, ModuleNode, TypeAnnotationNode};
     use crate::{
-        AnnotationEvent, ClassAccess, ClassEventSource, ClassFileResult, ClassInnerClassEvent,
-        ClassOuterClassEvent, ClassReader, ClassReaderFlags, InnerClassAccess, ModuleProvidesEvent,
+        buffer_class_events, AnnotationEvent, ClassAccess, ClassEvent, ClassEventSource,
+        ClassFileError, ClassFileResult, ClassInnerClassEvent, ClassOuterClassEvent, ClassReader,
+        ClassReaderFlags, ClassWriter, ClassWriterFlags, ConstantPoolTag, FieldAccess, FieldValue,
+        Frame, FrameValue, InnerClassAccess, JImageReader, MethodEvent, ModuleProvidesEvent,
         ModuleRelationAccess, ModuleRelationEvent, ModuleRequireAccess, ModuleRequireEvent,
-        TypePath, TypeReference,
+        ModuleResolution, OwnedClassReader, TypePath, TypeReference,
     };
     use java_string::JavaStr;
     use std::borrow::Cow;
-    use test_helpers::{include_class, java_version};
+    use test_helpers::{include_class, java_home, java_version};
 
     #[test]
     fn test_hello_world() {
@@ -4232,6 +5201,154 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_owned_reader() {
+        let data = include_class!("HelloWorld").to_vec();
+        let reader = OwnedClassReader::try_from(data).unwrap();
+        let name = std::thread::spawn(move || reader.reader().name().unwrap().into_owned())
+            .join()
+            .unwrap();
+        assert_eq!(JavaStr::from_str("HelloWorld"), name);
+        assert!(OwnedClassReader::try_from(vec![0; 10]).is_err());
+
+        let data: &[u8] = include_class!("TestCode");
+        let owned_reader = OwnedClassReader::new(data, ClassReaderFlags::None).unwrap();
+        let reader = ClassReader::new(data, ClassReaderFlags::None).unwrap();
+        assert_eq!(
+            format!("{:?}", buffer_class_events(&reader).unwrap()),
+            format!("{:?}", buffer_class_events(&owned_reader.reader()).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_find_member() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let field = reader
+            .find_field(JavaStr::from_str("BIG"), JavaStr::from_str("J"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(FieldValue::Long(1 << 40)), field.value);
+        assert!(reader
+            .find_field(JavaStr::from_str("BIG"), JavaStr::from_str("I"))
+            .unwrap()
+            .is_none());
+
+        let expected = reader
+            .events()
+            .unwrap()
+            .find_map(|event| match event.unwrap() {
+                ClassEvent::Methods(methods) => methods.map(Result::unwrap).last(),
+                _ => None,
+            })
+            .unwrap();
+        let method = reader
+            .find_method(&expected.name, &expected.desc)
+            .unwrap()
+            .unwrap();
+        assert_eq!(expected.access, method.access);
+        assert_eq!(
+            expected.events.map(Result::unwrap).count(),
+            method.events.map(Result::unwrap).count()
+        );
+        assert!(reader
+            .find_method(JavaStr::from_str("missing"), JavaStr::from_str("()V"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_outline() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let outline = reader.outline().unwrap();
+        assert_eq!(JavaStr::from_str("TestCode"), outline.name);
+        assert_eq!(reader.access().unwrap(), outline.access);
+        assert!(outline.interfaces.is_empty());
+        let big = outline
+            .fields
+            .iter()
+            .find(|field| field.name.as_ref() == "BIG")
+            .unwrap();
+        assert_eq!(JavaStr::from_str("J"), big.desc);
+        assert!(big
+            .access
+            .contains(FieldAccess::Static | FieldAccess::Final));
+        for method in &outline.methods {
+            let found = reader
+                .find_method(&method.name, &method.desc)
+                .unwrap()
+                .unwrap();
+            assert_eq!(found.access, method.access);
+        }
+        assert!(outline
+            .methods
+            .iter()
+            .any(|method| method.name.as_ref() == "<init>"));
+    }
+
+    #[test]
+    fn test_lenient() {
+        let mut data = include_class!("HelloWorld").to_vec();
+        let methods_offset = ClassReader::new(&data, ClassReaderFlags::None)
+            .unwrap()
+            .events()
+            .unwrap()
+            .methods_offset;
+        // break the name of the constructor
+        data[methods_offset + 2..methods_offset + 4].copy_from_slice(&u16::MAX.to_be_bytes());
+
+        let reader = ClassReader::new(&data, ClassReaderFlags::Lenient).unwrap();
+        let methods: Vec<_> = reader.events().unwrap().methods().collect();
+        assert_eq!(2, methods.len());
+        assert!(methods[0].is_err());
+        assert_eq!(JavaStr::from_str("main"), methods[1].as_ref().unwrap().name);
+    }
+
+    #[test]
+    fn test_strict() {
+        let classes: [&[u8]; 4] = [
+            include_class!("TestCode"),
+            include_class!("TestRecord"),
+            include_class!("TestAnnotations"),
+            include_class!("module-info"),
+        ];
+        for class in classes {
+            let reader = ClassReader::new(class, ClassReaderFlags::Strict).unwrap();
+            ClassNode::from_events(&reader).unwrap();
+        }
+
+        let data = include_class!("HelloWorld");
+        let reader = ClassReader::new(data, ClassReaderFlags::None).unwrap();
+        let metadata_start = reader.metadata_start;
+        let attributes_offset = reader.events().unwrap().attributes_offset;
+        let code_index = (1..reader.constant_pool.len())
+            .find(|&index| reader.constant_pool.get_utf8_as_bytes(index) == Ok(b"Code"))
+            .unwrap();
+        let strict = |change: &dyn Fn(&mut [u8])| {
+            let mut data = data.to_vec();
+            change(&mut data);
+            assert!(ClassNode::from_events(
+                &ClassReader::new(&data, ClassReaderFlags::None).unwrap()
+            )
+            .is_ok());
+            ClassNode::from_events(&ClassReader::new(&data, ClassReaderFlags::Strict).unwrap())
+                .unwrap_err()
+        };
+        assert_eq!(
+            ClassFileError::UnknownAccessFlags(0x0023),
+            strict(&|data| data[metadata_start + 1] |= 0x02)
+        );
+        assert_eq!(
+            ClassFileError::BadConstantPoolIndexNoEntry(0),
+            strict(&|data| data[metadata_start + 4..metadata_start + 6].fill(0))
+        );
+        // rename the SourceFile attribute to Code
+        assert_eq!(
+            ClassFileError::MisplacedAttribute(JavaStr::from_str("Code").to_owned()),
+            strict(&|data| data[attributes_offset + 2..attributes_offset + 4]
+                .copy_from_slice(&code_index.to_be_bytes()))
+        );
+    }
+
     #[test]
     fn test_interfaces() {
         const BYTECODE: &[u8] = include_class!("TestInterfaces");
@@ -4399,6 +5516,45 @@ mod test {
         assert!(events.next().is_none());
     }
 
+    #[test]
+    fn test_jdk_module_attributes() {
+        fn read_module(data: &[u8]) -> ModuleNode<'_> {
+            let reader = ClassReader::new(data, ClassReaderFlags::None).unwrap();
+            let module = ClassNode::from_events(&reader).unwrap().module.unwrap();
+            let rewritten = ClassWriter::new(ClassWriterFlags::None)
+                .write(&reader)
+                .unwrap();
+            let reader = ClassReader::new(&rewritten, ClassReaderFlags::None).unwrap();
+            let rewritten_module = ClassNode::from_events(&reader).unwrap().module.unwrap();
+            assert_eq!(format!("{module:?}"), format!("{rewritten_module:?}"));
+            module
+        }
+
+        let mut jimage = JImageReader::open_jdk(java_home!()).unwrap();
+        let mut module_info = |name| jimage.read_class(name, "module-info").unwrap().unwrap();
+        let base_data = module_info("java.base");
+        let vector_data = module_info("jdk.incubator.vector");
+
+        let base = read_module(&base_data);
+        assert!(base.target.is_some());
+        assert_eq!(None, base.resolution);
+        let hashes = base.hashes.unwrap();
+        assert_eq!("SHA-256", hashes.algorithm.as_ref());
+        let sql_hash = hashes
+            .hashes
+            .iter()
+            .find(|hash| hash.module == JavaStr::from_str("java.sql"))
+            .unwrap();
+        assert_eq!(32, sql_hash.hash.len());
+
+        let vector = read_module(&vector_data);
+        assert_eq!(
+            Some(ModuleResolution::DoNotResolveByDefault | ModuleResolution::WarnIncubating),
+            vector.resolution
+        );
+        assert_eq!(None, vector.hashes);
+    }
+
     #[test]
     fn test_nest_host() {
         const BYTECODE: &[u8] = include_class!("TestInnerClass$Inner");
@@ -4409,6 +5565,33 @@ mod test {
         );
     }
 
+    #[cfg(feature = "preview")]
+    #[test]
+    fn test_value_class() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let mut node = ClassNode::from_events(&reader).unwrap();
+        node.access = ClassAccess::Public | ClassAccess::Final;
+        node.loadable_descriptors = vec![Cow::Borrowed(JavaStr::from_str("LPoint;"))];
+        node.fields.push(FieldNode::new(
+            FieldAccess::Private | FieldAccess::Final | FieldAccess::StrictInit,
+            JavaStr::from_str("point"),
+            JavaStr::from_str("LPoint;"),
+        ));
+        let bytecode = ClassWriter::new(ClassWriterFlags::None)
+            .write(&node)
+            .unwrap();
+
+        let reader = ClassReader::new(&bytecode, ClassReaderFlags::Strict).unwrap();
+        assert!(!reader.access().unwrap().contains(ClassAccess::Identity));
+        let node = ClassNode::from_events(&reader).unwrap();
+        assert_eq!(
+            vec![JavaStr::from_str("LPoint;")],
+            node.loadable_descriptors
+        );
+        assert!(node.fields[0].access.contains(FieldAccess::StrictInit));
+    }
+
     #[test]
     fn test_nest_members() {
         const BYTECODE: &[u8] = include_class!("TestInnerClass");
@@ -4769,4 +5952,198 @@ mod test {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn test_constant_value_mismatch() {
+        const BYTECODE: &[u8] = include_class!("TestCode");
+        // change the descriptor of the long constant field BIG to int
+        let mut bytecode = BYTECODE.to_vec();
+        let desc_offset = bytecode
+            .windows(4)
+            .position(|window| window == b"\x01\x00\x01J")
+            .unwrap();
+        bytecode[desc_offset + 3] = b'I';
+
+        let fields = |flags| -> ClassFileResult<Vec<_>> {
+            let reader = ClassReader::new(&bytecode, flags)?;
+            let mut values = Vec::new();
+            for event in reader.events()? {
+                if let ClassEvent::Fields(fields) = event? {
+                    for field in fields {
+                        values.push(field?.value);
+                    }
+                }
+            }
+            Ok(values)
+        };
+        assert_eq!(
+            Ok(vec![Some(FieldValue::Long(1 << 40)), None]),
+            fields(ClassReaderFlags::None)
+        );
+        let err = fields(ClassReaderFlags::Strict).unwrap_err();
+        assert_eq!(
+            &ClassFileError::ConstantValueMismatch {
+                desc: JavaStr::from_str("I").to_owned(),
+                actual: ConstantPoolTag::Long,
+            },
+            err.root()
+        );
+        assert!(
+            matches!(&err, ClassFileError::WithContext { path, .. } if path == "field \"BIG:I\"")
+        );
+    }
+
+    #[test]
+    fn test_error_context() {
+        let mut data = include_class!("HelloWorld").to_vec();
+        let reader = ClassReader::new(&data, ClassReaderFlags::None).unwrap();
+        let main = reader
+            .find_method(
+                JavaStr::from_str("main"),
+                JavaStr::from_str("([Ljava/lang/String;)V"),
+            )
+            .unwrap()
+            .unwrap();
+        let code_offset = main.events.code_offset + 8;
+        data[code_offset] = 0xff;
+
+        let reader = ClassReader::new(&data, ClassReaderFlags::None).unwrap();
+        let err = reader
+            .find_method(
+                JavaStr::from_str("main"),
+                JavaStr::from_str("([Ljava/lang/String;)V"),
+            )
+            .unwrap()
+            .unwrap()
+            .events
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(&ClassFileError::BadOpcode(0xff), err.root());
+        assert_eq!(
+            format!(
+                "method \"main([Ljava/lang/String;)V\" > Code at byte {}: bad opcode: 255",
+                code_offset - 14
+            ),
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_collect_errors() {
+        let data = include_class!("HelloWorld");
+        let reader = ClassReader::new(data, ClassReaderFlags::None).unwrap();
+        assert!(reader.collect_errors().is_empty());
+
+        let mut data = data.to_vec();
+        let main = reader
+            .find_method(
+                JavaStr::from_str("main"),
+                JavaStr::from_str("([Ljava/lang/String;)V"),
+            )
+            .unwrap()
+            .unwrap();
+        data[main.events.code_offset + 8] = 0xff;
+        // break the name of the constructor
+        let methods_offset = reader.events().unwrap().methods_offset;
+        data[methods_offset + 2..methods_offset + 4].copy_from_slice(&u16::MAX.to_be_bytes());
+
+        let reader = ClassReader::new(&data, ClassReaderFlags::None).unwrap();
+        let errors = reader.collect_errors();
+        assert_eq!(2, errors.len());
+        assert!(matches!(
+            errors[0].root(),
+            ClassFileError::BadConstantPoolIndex {
+                index: u16::MAX,
+                ..
+            }
+        ));
+        assert_eq!(&ClassFileError::BadOpcode(0xff), errors[1].root());
+    }
+
+    #[test]
+    fn test_skip_code() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let methods = || {
+            reader
+                .events()
+                .unwrap()
+                .find_map(|event| event.unwrap().try_unwrap_methods().ok())
+                .unwrap()
+        };
+        let expected_maxs: Vec<_> = methods()
+            .map(|method| {
+                method
+                    .unwrap()
+                    .events
+                    .find_map(|event| event.unwrap().try_unwrap_maxs().ok())
+                    .unwrap()
+            })
+            .collect();
+
+        for (index, method) in methods().enumerate() {
+            let mut events = method.unwrap().events;
+            if index % 2 == 0 {
+                assert!(events.any(|event| matches!(event, Ok(MethodEvent::Code { .. }))));
+                events.next().unwrap().unwrap();
+                events.skip_code();
+                let remaining: Vec<_> = events
+                    .map(|event| event.unwrap().try_unwrap_maxs().ok())
+                    .collect();
+                assert_eq!(vec![Some(expected_maxs[index])], remaining);
+            } else {
+                events.skip_code();
+                assert!(events.next().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_expand_frames() {
+        let reader =
+            ClassReader::new(include_class!("TestCode"), ClassReaderFlags::ExpandFrames).unwrap();
+        let methods = reader
+            .events()
+            .unwrap()
+            .find_map(|event| event.unwrap().try_unwrap_methods().ok())
+            .unwrap();
+        let mut loops_frames = Vec::new();
+        for method in methods {
+            let method = method.unwrap();
+            let frames: Vec<_> = method
+                .events
+                .filter_map(|event| event.unwrap().try_unwrap_frame().ok())
+                .collect();
+            assert!(frames
+                .iter()
+                .all(|frame| matches!(frame, Frame::Full { .. })));
+            if method.name.as_ref() == "loops" {
+                loops_frames = frames;
+            }
+        }
+
+        assert_eq!(
+            Frame::Full {
+                locals: vec![
+                    FrameValue::Class(Cow::Borrowed(JavaStr::from_str("TestCode"))),
+                    FrameValue::Class(Cow::Borrowed(JavaStr::from_str("[I"))),
+                    FrameValue::Double,
+                    FrameValue::Integer,
+                ],
+                stack: Vec::new(),
+            },
+            loops_frames[0]
+        );
+        // the frame after the loop chops the counter
+        assert_eq!(
+            Frame::Full {
+                locals: vec![
+                    FrameValue::Class(Cow::Borrowed(JavaStr::from_str("TestCode"))),
+                    FrameValue::Class(Cow::Borrowed(JavaStr::from_str("[I"))),
+                    FrameValue::Double,
+                ],
+                stack: Vec::new(),
+            },
+            loops_frames[1]
+        );
+    }
 }
@@ -0,0 +1,304 @@
+use crate::{
+    read_classpath, scan_in_parallel, ClassAccess, ClassEvent, ClassEventSource, ClassFileResult,
+    ClassReader, ClassReaderFlags, FieldEvent, LdcConstant, MethodAccess, MethodEvent,
+    JAVA_11_VERSION, JAVA_16_VERSION, JAVA_17_VERSION, JAVA_7_VERSION, JAVA_8_VERSION,
+    JAVA_9_VERSION, PREVIEW_MINOR_VERSION,
+};
+use java_string::JavaString;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::thread;
+
+/// A class file feature which needs a minimum class file version.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum VersionedConstruct {
+    InvokeDynamic,
+    /// A `MethodHandle` or `MethodType` constant.
+    MethodHandleConstant,
+    /// A non-abstract method in an interface.
+    InterfaceMethodCode,
+    TypeAnnotations,
+    Module,
+    NestMates,
+    ConstantDynamic,
+    Record,
+    PermittedSubclasses,
+}
+
+impl VersionedConstruct {
+    pub fn required_version(self) -> u16 {
+        match self {
+            VersionedConstruct::InvokeDynamic | VersionedConstruct::MethodHandleConstant => {
+                JAVA_7_VERSION
+            }
+            VersionedConstruct::InterfaceMethodCode | VersionedConstruct::TypeAnnotations => {
+                JAVA_8_VERSION
+            }
+            VersionedConstruct::Module => JAVA_9_VERSION,
+            VersionedConstruct::NestMates | VersionedConstruct::ConstantDynamic => JAVA_11_VERSION,
+            VersionedConstruct::Record => JAVA_16_VERSION,
+            VersionedConstruct::PermittedSubclasses => JAVA_17_VERSION,
+        }
+    }
+}
+
+/// The version of a class, and the versioned constructs it uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassVersionInfo {
+    pub name: JavaString,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub constructs: BTreeSet<VersionedConstruct>,
+}
+
+impl ClassVersionInfo {
+    pub fn is_preview(&self) -> bool {
+        self.minor_version == PREVIEW_MINOR_VERSION
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassVersionIssue {
+    pub class: JavaString,
+    pub kind: ClassVersionIssueKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ClassVersionIssueKind {
+    /// The class version is newer than the target version.
+    VersionTooNew(u16),
+    /// The class depends on the preview features of a different version than the target version,
+    /// so can't be loaded by the target.
+    PreviewVersionMismatch(u16),
+    /// The class uses a construct which is unsupported by the target version, so can't just be
+    /// downgraded.
+    UnsupportedConstruct(VersionedConstruct),
+}
+
+/// The distribution of class versions across many classes, and the classes which can't run on a
+/// target version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassVersionReport {
+    pub target_version: u16,
+    /// The number of classes with each major version.
+    pub version_counts: BTreeMap<u16, u32>,
+    /// The number of classes which depend on preview features.
+    pub preview_count: u32,
+    pub issues: Vec<ClassVersionIssue>,
+}
+
+/// Scans many classes for their versions, relative to a target major version.
+#[derive(Debug, Clone)]
+pub struct ClassVersionScanner {
+    target_version: u16,
+    threads: usize,
+}
+
+impl ClassVersionScanner {
+    pub fn new(target_version: u16) -> Self {
+        ClassVersionScanner {
+            target_version,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+
+    /// Sets the maximum number of threads used to scan classes. Defaults to the available
+    /// parallelism.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn scan_class(&self, bytecode: &[u8]) -> ClassFileResult<ClassVersionInfo> {
+        let reader = ClassReader::new(
+            bytecode,
+            ClassReaderFlags::SkipDebug | ClassReaderFlags::SkipFrames,
+        )?;
+        let mut info = ClassVersionInfo {
+            name: reader.name()?.into_owned(),
+            major_version: 0,
+            minor_version: 0,
+            constructs: BTreeSet::new(),
+        };
+        find_constructs(&reader, &mut info)?;
+        Ok(info)
+    }
+
+    pub fn scan_classes<'a>(
+        &self,
+        classes: impl IntoIterator<Item = &'a [u8]>,
+    ) -> ClassFileResult<ClassVersionReport> {
+        let infos = scan_in_parallel(classes, self.threads, |bytecode| self.scan_class(bytecode))?;
+        Ok(self.report(&infos))
+    }
+
+    /// Scans the class files in the given classpath entries, which are either class files,
+    /// directories searched recursively, or jar files with the `jar` feature.
+    pub fn scan_classpath<P>(
+        &self,
+        classpath: impl IntoIterator<Item = P>,
+    ) -> ClassFileResult<ClassVersionReport>
+    where
+        P: AsRef<Path>,
+    {
+        let classes = read_classpath(classpath)?;
+        self.scan_classes(classes.iter().map(Vec::as_slice))
+    }
+
+    pub fn report(&self, infos: &[ClassVersionInfo]) -> ClassVersionReport {
+        let mut report = ClassVersionReport {
+            target_version: self.target_version,
+            version_counts: BTreeMap::new(),
+            preview_count: 0,
+            issues: Vec::new(),
+        };
+        for info in infos {
+            *report.version_counts.entry(info.major_version).or_default() += 1;
+            let mut add_issue = |kind| {
+                report.issues.push(ClassVersionIssue {
+                    class: info.name.clone(),
+                    kind,
+                })
+            };
+            if info.major_version > self.target_version {
+                add_issue(ClassVersionIssueKind::VersionTooNew(info.major_version));
+            }
+            if info.is_preview() && info.major_version != self.target_version {
+                add_issue(ClassVersionIssueKind::PreviewVersionMismatch(
+                    info.major_version,
+                ));
+            }
+            for &construct in &info.constructs {
+                if construct.required_version() > self.target_version {
+                    add_issue(ClassVersionIssueKind::UnsupportedConstruct(construct));
+                }
+            }
+            if info.is_preview() {
+                report.preview_count += 1;
+            }
+        }
+        report
+    }
+}
+
+pub(crate) fn find_constructs<'class>(
+    source: impl ClassEventSource<'class>,
+    info: &mut ClassVersionInfo,
+) -> ClassFileResult<()> {
+    let mut is_interface = false;
+    for event in source.events()? {
+        let construct = match event? {
+            ClassEvent::Class(class) => {
+                info.major_version = class.major_version;
+                info.minor_version = class.minor_version;
+                is_interface = class.access.contains(ClassAccess::Interface);
+                continue;
+            }
+            ClassEvent::Module(_) => VersionedConstruct::Module,
+            ClassEvent::NestHost(_) | ClassEvent::NestMembers(_) => VersionedConstruct::NestMates,
+            ClassEvent::Record(_) => VersionedConstruct::Record,
+            ClassEvent::PermittedSubclasses(_) => VersionedConstruct::PermittedSubclasses,
+            ClassEvent::TypeAnnotations(_) => VersionedConstruct::TypeAnnotations,
+            ClassEvent::Fields(fields) => {
+                for field in fields {
+                    for event in field?.events {
+                        if let FieldEvent::TypeAnnotations(_) = event? {
+                            info.constructs.insert(VersionedConstruct::TypeAnnotations);
+                        }
+                    }
+                }
+                continue;
+            }
+            ClassEvent::Methods(methods) => {
+                for method in methods {
+                    let method = method?;
+                    if is_interface
+                        && !method.access.contains(MethodAccess::Abstract)
+                        && method.name.as_ref() != "<clinit>"
+                    {
+                        info.constructs
+                            .insert(VersionedConstruct::InterfaceMethodCode);
+                    }
+                    for event in method.events {
+                        let construct = match event? {
+                            MethodEvent::InvokeDynamicInsn { .. } => {
+                                VersionedConstruct::InvokeDynamic
+                            }
+                            MethodEvent::LdcInsn(
+                                LdcConstant::Handle(_) | LdcConstant::MethodType(_),
+                            ) => VersionedConstruct::MethodHandleConstant,
+                            MethodEvent::LdcInsn(LdcConstant::ConstantDynamic(_)) => {
+                                VersionedConstruct::ConstantDynamic
+                            }
+                            MethodEvent::TypeAnnotations(_)
+                            | MethodEvent::InsnAnnotations(_)
+                            | MethodEvent::LocalVariableAnnotations(_)
+                            | MethodEvent::TryCatchBlockAnnotations(_) => {
+                                VersionedConstruct::TypeAnnotations
+                            }
+                            _ => continue,
+                        };
+                        info.constructs.insert(construct);
+                    }
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        info.constructs.insert(construct);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassVersionIssue, ClassVersionIssueKind, ClassVersionScanner, VersionedConstruct,
+        JAVA_8_VERSION,
+    };
+    use java_string::JavaString;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_class_versions() {
+        let mut scanner = ClassVersionScanner::new(JAVA_8_VERSION);
+        scanner.set_threads(2);
+        let classes: [&[u8]; 4] = [
+            include_class!("TestCode"),
+            include_class!("TestRecord"),
+            include_class!("TestSealedClass"),
+            include_class!("module-info"),
+        ];
+        let major_version = scanner.scan_class(classes[0]).unwrap().major_version;
+        let report = scanner.scan_classes(classes).unwrap();
+
+        assert_eq!(Some(&4), report.version_counts.get(&major_version));
+        assert_eq!(0, report.preview_count);
+        let issue = |class: &str, kind| ClassVersionIssue {
+            class: JavaString::from(class),
+            kind,
+        };
+        for class in ["TestCode", "TestRecord", "TestSealedClass", "module-info"] {
+            assert!(report.issues.contains(&issue(
+                class,
+                ClassVersionIssueKind::VersionTooNew(major_version)
+            )));
+        }
+        for (class, construct) in [
+            ("TestRecord", VersionedConstruct::Record),
+            ("TestSealedClass", VersionedConstruct::PermittedSubclasses),
+            ("TestSealedClass", VersionedConstruct::NestMates),
+            ("module-info", VersionedConstruct::Module),
+        ] {
+            assert!(report.issues.contains(&issue(
+                class,
+                ClassVersionIssueKind::UnsupportedConstruct(construct)
+            )));
+        }
+        // invokedynamic is supported by the target
+        assert!(!report.issues.contains(&issue(
+            "TestCode",
+            ClassVersionIssueKind::UnsupportedConstruct(VersionedConstruct::InvokeDynamic)
+        )));
+    }
+}
@@ -0,0 +1,3358 @@
+use crate::constant_pool::constant_references;
+use crate::frame_computer::{ldc_value, FrameComputer, FrameInsn};
+use crate::maxs_calculator::{compute_max_stack, insn_flow, local_use, InsnFlow};
+use crate::opcodes::InternalOpcodes;
+use crate::tree::{AnnotationNode, AnnotationValue, TypeAnnotationNode};
+use crate::{
+    Attribute, AttributeReader, BootstrapMethodArgument, ClassAccess, ClassBuffer, ClassClassEvent,
+    ClassEvent, ClassEventSource, ClassFieldEvent, ClassFileError, ClassFileResult, ClassHierarchy,
+    ClassMethodEvent, ClassModuleEvent, ClassReader, ClassReaderFlags, ClassRecordComponentEvent,
+    ConstantDynamic, ConstantPool, ConstantPoolBuilder, ConstantPoolTag, FieldAccess, FieldEvent,
+    FieldEventProviders, FieldValue, Frame, FrameValue, Handle, HandleKind, Label, LdcConstant,
+    MethodAccess, MethodEvent, MethodEventProviders, MethodLocalVariableAnnotationEvent,
+    MethodLocalVariableEvent, MethodTryCatchBlockAnnotationEvent, MethodTryCatchBlockEvent,
+    MethodUnchangedEvent, ModuleEvent, ModuleEventProviders, ModuleRelationEvent, Opcode,
+    RecordComponentEvent, RecordComponentEventProviders, SimpleClassHierarchy, TypeReference,
+    UnknownAttribute, VersionedConstruct, JAVA_5_VERSION, LATEST_MAJOR_VERSION,
+    PREVIEW_MINOR_VERSION,
+};
+use bitflags::bitflags;
+use derive_more::Debug;
+use java_string::{JavaStr, JavaString};
+use std::any::Any;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::sync::Arc;
+
+bitflags! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    pub struct ClassWriterFlags: u8 {
+        const None = 0;
+        /// Compute the max stack and max locals of each method, ignoring the values from
+        /// [`MethodEvent::Maxs`].
+        const ComputeMaxs = 1;
+        /// Compute the StackMapTable frames of each method, ignoring [`MethodEvent::Frame`]
+        /// events. Implies [`ClassWriterFlags::ComputeMaxs`]. Merging reference types requires a
+        /// [`ClassHierarchy`], see [`ClassWriter::set_class_hierarchy`].
+        const ComputeFrames = 2 | Self::ComputeMaxs.bits();
+        /// Sort the constant pool, bootstrap methods and class attributes by their contents, so
+        /// that the same class always produces the same bytes, regardless of the order things
+        /// were added in. This writes the class twice, the second time from the bytes of the
+        /// first. Custom attributes are written again from the original [`Attribute`]s by the
+        /// second pass, so an [`UnknownAttribute`], whose constant pool indices can't be updated,
+        /// fails with [`ClassFileError::UnwritableAttribute`].
+        const Deterministic = 4;
+    }
+}
+
+/// Serializes a stream of class events into the bytes of a class file.
+///
+/// Unless asked to by the [`ClassWriterFlags`], the writer doesn't compute anything on its own:
+/// frames and max stack/locals are written exactly as they appear in the events.
+#[derive(Debug, Clone, Default)]
+pub struct ClassWriter {
+    flags: ClassWriterFlags,
+    #[debug(skip)]
+    class_hierarchy: Option<Arc<dyn ClassHierarchy>>,
+    #[debug(skip)]
+    initial_symbols: Option<SymbolTable>,
+    /// The name indices of the class attributes of the copied class, in their original order.
+    attribute_order: Vec<u16>,
+    preview: bool,
+}
+
+impl ClassWriter {
+    pub fn new(flags: ClassWriterFlags) -> Self {
+        ClassWriter {
+            flags,
+            class_hierarchy: None,
+            initial_symbols: None,
+            attribute_order: Vec::new(),
+            preview: false,
+        }
+    }
+
+    /// Starts each written class with the constant pool and bootstrap methods of the given class,
+    /// keeping their original indices and order. Writing the unmodified events of that class then
+    /// reproduces its bytes exactly, as long as no flags are set that recompute code attributes.
+    /// Entries which are no longer used are kept, and class attributes are written in the order of
+    /// the original class where possible.
+    ///
+    /// Methods of that class signalled with [`MethodEvent::Unchanged`] are then copied verbatim
+    /// without being decoded, regardless of the flags.
+    pub fn copy_constant_pool(&mut self, reader: &ClassReader) -> ClassFileResult<()> {
+        self.initial_symbols = Some(SymbolTable::from_reader(reader)?);
+        self.attribute_order = reader
+            .raw_attributes()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        Ok(())
+    }
+
+    /// Marks written classes as depending on preview features, by setting their minor version to
+    /// [`PREVIEW_MINOR_VERSION`]. Otherwise, the minor version of the class event is kept. Preview
+    /// classes must have the [`LATEST_MAJOR_VERSION`].
+    pub fn set_preview(&mut self, preview: bool) {
+        self.preview = preview;
+    }
+
+    /// Sets the class hierarchy used to compute frames. Without one, only `java/lang/Object` is
+    /// known, and merging any other two classes fails.
+    pub fn set_class_hierarchy<H>(&mut self, class_hierarchy: H)
+    where
+        H: ClassHierarchy + 'static,
+    {
+        self.class_hierarchy = Some(Arc::new(class_hierarchy));
+    }
+
+    pub fn write<'class, S>(&self, source: S) -> ClassFileResult<Vec<u8>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let sections = self.write_sections(source)?;
+        let mut output = Vec::with_capacity(sections.len());
+        sections.write_to(&mut output)?;
+        Ok(output)
+    }
+
+    /// Writes the class file to the given output. The constant pool is only complete once
+    /// everything else has been written, so the other sections are buffered separately and
+    /// streamed out after it, without first being copied into a single buffer.
+    pub fn write_to<'class, S, W>(&self, source: S, output: &mut W) -> ClassFileResult<()>
+    where
+        S: ClassEventSource<'class>,
+        W: Write,
+    {
+        self.write_sections(source)?.write_to(output)
+    }
+
+    fn write_sections<'class, S>(&self, source: S) -> ClassFileResult<ClassSections>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let sections = self.write_unsorted_sections(source)?;
+        if !self.flags.contains(ClassWriterFlags::Deterministic) {
+            return Ok(sections);
+        }
+
+        let mut bytes = Vec::with_capacity(sections.len());
+        sections.write_to(&mut bytes)?;
+        let mut reader = ClassReader::new(&bytes, ClassReaderFlags::None)?;
+        // custom attributes are read back as the originals, to write them against the new indices
+        let written_attributes = Rc::new(sections.written_attributes);
+        for (name, _) in written_attributes.keys() {
+            reader.add_attribute_reader(
+                name.clone(),
+                WrittenAttributeReader(written_attributes.clone()),
+            );
+        }
+        // frames and maxs were already computed by the first pass
+        let writer = ClassWriter {
+            flags: ClassWriterFlags::None,
+            class_hierarchy: None,
+            initial_symbols: Some(SymbolTable::sorted_from_reader(&reader)?),
+            attribute_order: Vec::new(),
+            preview: false,
+        };
+        let mut sections = writer.write_unsorted_sections(&reader)?;
+        // the names are in sorted order in the constant pool
+        sections.attributes.sort_by_key(|name| name);
+        Ok(sections)
+    }
+
+    fn write_unsorted_sections<'class, S>(&self, source: S) -> ClassFileResult<ClassSections>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut events = source.events()?;
+        let mut class = match events.next().transpose()? {
+            Some(ClassEvent::Class(class)) => class,
+            _ => return Err(ClassFileError::MissingClassEvent),
+        };
+        if self.preview {
+            class.minor_version = PREVIEW_MINOR_VERSION;
+        }
+        if class.is_preview() && class.major_version != LATEST_MAJOR_VERSION {
+            return Err(ClassFileError::PreviewNotLatestVersion(class.major_version));
+        }
+
+        let mut symbols = self.initial_symbols.clone().unwrap_or_default();
+        symbols.major_version = class.major_version;
+        if self.flags.contains(ClassWriterFlags::Deterministic) {
+            symbols.written_attributes = Some(HashMap::new());
+        }
+        let mut access = class.access;
+        let mut attributes = AttributesWriter::default();
+        let mut annotations = AnnotationsWriter::default();
+        let mut type_annotations = AnnotationsWriter::default();
+        let mut nest_members = None;
+        let mut permitted_subclasses = None;
+        let mut inner_classes = None;
+        let mut record = None;
+        let mut custom_attributes = Vec::new();
+        let mut field_count = 0u16;
+        let mut fields = Vec::new();
+        let mut method_count = 0u16;
+        let mut methods = Vec::new();
+
+        if let Some(signature) = &class.signature {
+            let signature = symbols.utf8(signature)?;
+            attributes.add(&mut symbols, "Signature", &signature.to_be_bytes())?;
+        }
+
+        for event in events {
+            match event? {
+                ClassEvent::Class(_) => return Err(ClassFileError::DuplicateClassEvent),
+                ClassEvent::Synthetic => {
+                    if class.major_version < JAVA_5_VERSION {
+                        access.remove(ClassAccess::Synthetic);
+                        attributes.add(&mut symbols, "Synthetic", &[])?;
+                    } else {
+                        access.insert(ClassAccess::Synthetic);
+                    }
+                }
+                ClassEvent::Deprecated => attributes.add(&mut symbols, "Deprecated", &[])?,
+                ClassEvent::Source(source) => {
+                    if let Some(source) = &source.source {
+                        let source = symbols.utf8(source)?;
+                        attributes.add(&mut symbols, "SourceFile", &source.to_be_bytes())?;
+                    }
+                    if let Some(debug) = &source.debug {
+                        attributes.add(
+                            &mut symbols,
+                            "SourceDebugExtension",
+                            &debug.to_modified_utf8(),
+                        )?;
+                    }
+                }
+                ClassEvent::Module(module) => {
+                    symbols.require(VersionedConstruct::Module)?;
+                    write_module(&mut symbols, &mut attributes, module)?;
+                }
+                ClassEvent::NestHost(nest_host) => {
+                    symbols.require(VersionedConstruct::NestMates)?;
+                    let nest_host = symbols.class(&nest_host)?;
+                    attributes.add(&mut symbols, "NestHost", &nest_host.to_be_bytes())?;
+                }
+                ClassEvent::OuterClass(outer_class) => {
+                    let mut data = Vec::with_capacity(4);
+                    data.put_u16(symbols.class(&outer_class.owner)?);
+                    match (&outer_class.method_name, &outer_class.method_desc) {
+                        (Some(name), Some(desc)) => {
+                            data.put_u16(symbols.name_and_type(name, desc)?)
+                        }
+                        _ => data.put_u16(0),
+                    }
+                    attributes.add(&mut symbols, "EnclosingMethod", &data)?;
+                }
+                ClassEvent::Annotations(events) => {
+                    for event in events {
+                        let event = event?;
+                        annotations.add_annotation(
+                            &mut symbols,
+                            event.visible,
+                            &event.annotation,
+                        )?;
+                    }
+                }
+                ClassEvent::TypeAnnotations(events) => {
+                    for event in events {
+                        let event = event?;
+                        type_annotations.add_type_annotation(
+                            &mut symbols,
+                            event.visible,
+                            &event.annotation,
+                            TypeAnnotationLocation::None,
+                        )?;
+                    }
+                }
+                ClassEvent::Attributes(events) => {
+                    for attribute in events {
+                        custom_attributes.push(attribute?);
+                    }
+                }
+                ClassEvent::NestMembers(events) => {
+                    symbols.require(VersionedConstruct::NestMates)?;
+                    let (count, data) = nest_members.get_or_insert_with(|| (0u16, Vec::new()));
+                    for nest_member in events {
+                        increment_count("nest members", count)?;
+                        data.put_u16(symbols.class(&nest_member?)?);
+                    }
+                }
+                ClassEvent::PermittedSubclasses(events) => {
+                    symbols.require(VersionedConstruct::PermittedSubclasses)?;
+                    let (count, data) =
+                        permitted_subclasses.get_or_insert_with(|| (0u16, Vec::new()));
+                    for permitted_subclass in events {
+                        increment_count("permitted subclasses", count)?;
+                        data.put_u16(symbols.class(&permitted_subclass?)?);
+                    }
+                }
+                #[cfg(feature = "preview")]
+                ClassEvent::LoadableDescriptors(descs) => {
+                    let mut data = Vec::with_capacity(2 + descs.len() * 2);
+                    data.put_u16(table_len("loadable descriptors", descs.len())?);
+                    for desc in &descs {
+                        data.put_u16(symbols.utf8(desc)?);
+                    }
+                    attributes.add(&mut symbols, "LoadableDescriptors", &data)?;
+                }
+                ClassEvent::InnerClasses(events) => {
+                    let (count, data) = inner_classes.get_or_insert_with(|| (0u16, Vec::new()));
+                    for inner_class in events {
+                        let inner_class = inner_class?;
+                        increment_count("inner classes", count)?;
+                        data.put_u16(symbols.class(&inner_class.name)?);
+                        data.put_u16(
+                            inner_class
+                                .outer_name
+                                .as_deref()
+                                .map_or(Ok(0), |outer_name| symbols.class(outer_name))?,
+                        );
+                        data.put_u16(
+                            inner_class
+                                .inner_name
+                                .as_deref()
+                                .map_or(Ok(0), |inner_name| symbols.utf8(inner_name))?,
+                        );
+                        data.put_u16(inner_class.access.bits());
+                    }
+                }
+                ClassEvent::Record(events) => {
+                    symbols.require(VersionedConstruct::Record)?;
+                    let (count, data) = record.get_or_insert_with(|| (0u16, Vec::new()));
+                    for component in events {
+                        write_record_component(&mut symbols, data, component?)?;
+                        increment_count("record components", count)?;
+                    }
+                }
+                ClassEvent::Fields(events) => {
+                    for field in events {
+                        write_field(&mut symbols, class.major_version, &mut fields, field?)?;
+                        increment_count("fields", &mut field_count)?;
+                    }
+                }
+                ClassEvent::Methods(events) => {
+                    for method in events {
+                        write_method(&mut symbols, self, &class, &mut methods, method?)?;
+                        increment_count("methods", &mut method_count)?;
+                    }
+                }
+            }
+        }
+
+        annotations.finish(
+            &mut symbols,
+            &mut attributes,
+            "RuntimeVisibleAnnotations",
+            "RuntimeInvisibleAnnotations",
+        )?;
+        type_annotations.finish(
+            &mut symbols,
+            &mut attributes,
+            "RuntimeVisibleTypeAnnotations",
+            "RuntimeInvisibleTypeAnnotations",
+        )?;
+        for (name, list) in [
+            ("NestMembers", nest_members),
+            ("PermittedSubclasses", permitted_subclasses),
+            ("InnerClasses", inner_classes),
+            ("Record", record),
+        ] {
+            if let Some((count, data)) = list {
+                let mut payload = Vec::with_capacity(2 + data.len());
+                payload.put_u16(count);
+                payload.extend_from_slice(&data);
+                attributes.add(&mut symbols, name, &payload)?;
+            }
+        }
+        for attribute in &custom_attributes {
+            attributes.add_custom(&mut symbols, attribute.as_ref())?;
+        }
+
+        let this_class = symbols.class(&class.name)?;
+        let super_class = class
+            .super_name
+            .as_deref()
+            .map_or(Ok(0), |super_name| symbols.class(super_name))?;
+        let interfaces = class
+            .interfaces
+            .iter()
+            .map(|interface| symbols.class(interface))
+            .collect::<ClassFileResult<Vec<_>>>()?;
+
+        // must be last, as writing the other attributes can add bootstrap methods
+        if symbols.bootstrap_method_count != 0 {
+            let mut payload = Vec::with_capacity(2 + symbols.bootstrap_methods.len());
+            payload.put_u16(symbols.bootstrap_method_count);
+            payload.extend_from_slice(&symbols.bootstrap_methods);
+            attributes.add(&mut symbols, "BootstrapMethods", &payload)?;
+        }
+        if !self.attribute_order.is_empty() {
+            attributes.sort_by_name_order(&self.attribute_order);
+        }
+
+        Ok(ClassSections {
+            minor_version: class.minor_version,
+            major_version: class.major_version,
+            constant_pool: symbols.constant_pool,
+            access,
+            this_class,
+            super_class,
+            interfaces,
+            field_count,
+            fields,
+            method_count,
+            methods,
+            attributes,
+            written_attributes: symbols.written_attributes.unwrap_or_default(),
+        })
+    }
+}
+
+/// The custom attributes written to a class, by their name and payload.
+type WrittenAttributes = HashMap<(JavaString, Vec<u8>), Box<dyn Attribute>>;
+
+/// Reads custom attributes back as the [`Attribute`]s they were written from.
+#[derive(Debug, Clone)]
+struct WrittenAttributeReader(Rc<WrittenAttributes>);
+
+impl AttributeReader for WrittenAttributeReader {
+    fn read<'class>(
+        &self,
+        name: &JavaStr,
+        _reader: &ClassReader<'class>,
+        data: ClassBuffer<'class>,
+    ) -> ClassFileResult<Box<dyn Attribute>> {
+        let key = (name.to_owned(), data.read_bytes(0, data.len())?.to_vec());
+        self.0
+            .get(&key)
+            .map(|attribute| attribute.copy())
+            .ok_or_else(|| ClassFileError::UnwritableAttribute(name.to_owned()))
+    }
+
+    fn copy(&self) -> Box<dyn AttributeReader> {
+        Box::new(self.clone())
+    }
+}
+
+/// The sections of a class file, in the order they're written.
+struct ClassSections {
+    minor_version: u16,
+    major_version: u16,
+    constant_pool: ConstantPoolBuilder,
+    access: ClassAccess,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    field_count: u16,
+    fields: Vec<u8>,
+    method_count: u16,
+    methods: Vec<u8>,
+    attributes: AttributesWriter,
+    written_attributes: WrittenAttributes,
+}
+
+impl ClassSections {
+    fn len(&self) -> usize {
+        24 + self.constant_pool.as_bytes().len()
+            + self.interfaces.len() * 2
+            + self.fields.len()
+            + self.methods.len()
+            + self.attributes.data.len()
+    }
+
+    fn write_to<W: Write>(&self, output: &mut W) -> ClassFileResult<()> {
+        let mut header = Vec::with_capacity(10);
+        header.put_u32(0xcafebabe);
+        header.put_u16(self.minor_version);
+        header.put_u16(self.major_version);
+        header.put_u16(self.constant_pool.len());
+        output.write_all(&header)?;
+        output.write_all(self.constant_pool.as_bytes())?;
+
+        let mut class_info = Vec::with_capacity(10 + self.interfaces.len() * 2);
+        class_info.put_u16(self.access.bits());
+        class_info.put_u16(self.this_class);
+        class_info.put_u16(self.super_class);
+        class_info.put_u16(table_len("interfaces", self.interfaces.len())?);
+        for &interface in &self.interfaces {
+            class_info.put_u16(interface);
+        }
+        class_info.put_u16(self.field_count);
+        output.write_all(&class_info)?;
+        output.write_all(&self.fields)?;
+        output.write_all(&self.method_count.to_be_bytes())?;
+        output.write_all(&self.methods)?;
+        output.write_all(&self.attributes.count.to_be_bytes())?;
+        output.write_all(&self.attributes.data)?;
+        Ok(())
+    }
+}
+
+fn write_module<'class, E, P>(
+    symbols: &mut SymbolTable,
+    attributes: &mut AttributesWriter,
+    module: ClassModuleEvent<'class, E>,
+) -> ClassFileResult<()>
+where
+    E: IntoIterator<Item = ClassFileResult<ModuleEvent<'class, P>>>,
+    P: ModuleEventProviders<'class>,
+{
+    let mut main_class = None;
+    let mut packages = None;
+    let mut target = None;
+    let mut resolution = None;
+    let mut hashes = None;
+    let mut requires = (0u16, Vec::new());
+    let mut exports = (0u16, Vec::new());
+    let mut opens = (0u16, Vec::new());
+    let mut uses = (0u16, Vec::new());
+    let mut provides = (0u16, Vec::new());
+
+    for event in module.events {
+        match event? {
+            ModuleEvent::MainClass(name) => main_class = Some(symbols.class(&name)?),
+            ModuleEvent::Packages(events) => {
+                let (count, data) = packages.get_or_insert_with(|| (0u16, Vec::new()));
+                for package in events {
+                    increment_count("module packages", count)?;
+                    data.put_u16(symbols.package(&package?)?);
+                }
+            }
+            ModuleEvent::Target(platform) => target = Some(symbols.utf8(&platform)?),
+            ModuleEvent::Resolution(flags) => resolution = Some(flags),
+            ModuleEvent::Hashes(event) => {
+                let mut payload = Vec::new();
+                payload.put_u16(symbols.utf8(&event.algorithm)?);
+                payload.put_u16(table_len("module hashes", event.hashes.len())?);
+                for hash in &event.hashes {
+                    payload.put_u16(symbols.module(&hash.module)?);
+                    payload.put_u16(table_len("bytes in a module hash", hash.hash.len())?);
+                    payload.extend_from_slice(&hash.hash);
+                }
+                hashes = Some(payload);
+            }
+            ModuleEvent::Requires(events) => {
+                for require in events {
+                    let require = require?;
+                    increment_count("module requires", &mut requires.0)?;
+                    requires.1.put_u16(symbols.module(&require.module)?);
+                    requires.1.put_u16(require.access.bits());
+                    requires.1.put_u16(
+                        require
+                            .version
+                            .as_deref()
+                            .map_or(Ok(0), |version| symbols.utf8(version))?,
+                    );
+                }
+            }
+            ModuleEvent::Exports(events) => {
+                for export in events {
+                    write_module_relation(symbols, &mut exports, export?)?;
+                }
+            }
+            ModuleEvent::Opens(events) => {
+                for open in events {
+                    write_module_relation(symbols, &mut opens, open?)?;
+                }
+            }
+            ModuleEvent::Uses(events) => {
+                for service in events {
+                    increment_count("module uses", &mut uses.0)?;
+                    uses.1.put_u16(symbols.class(&service?)?);
+                }
+            }
+            ModuleEvent::Provides(events) => {
+                for provide in events {
+                    let provide = provide?;
+                    increment_count("module provides", &mut provides.0)?;
+                    provides.1.put_u16(symbols.class(&provide.service)?);
+                    provides
+                        .1
+                        .put_u16(table_len("service providers", provide.providers.len())?);
+                    for provider in &provide.providers {
+                        provides.1.put_u16(symbols.class(provider)?);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut payload = Vec::new();
+    payload.put_u16(symbols.module(&module.name)?);
+    payload.put_u16(module.access.bits());
+    payload.put_u16(
+        module
+            .version
+            .as_deref()
+            .map_or(Ok(0), |version| symbols.utf8(version))?,
+    );
+    for (count, data) in [requires, exports, opens, uses, provides] {
+        payload.put_u16(count);
+        payload.extend_from_slice(&data);
+    }
+    attributes.add(symbols, "Module", &payload)?;
+
+    if let Some((count, data)) = packages {
+        let mut payload = Vec::with_capacity(2 + data.len());
+        payload.put_u16(count);
+        payload.extend_from_slice(&data);
+        attributes.add(symbols, "ModulePackages", &payload)?;
+    }
+    if let Some(main_class) = main_class {
+        attributes.add(symbols, "ModuleMainClass", &main_class.to_be_bytes())?;
+    }
+    if let Some(target) = target {
+        attributes.add(symbols, "ModuleTarget", &target.to_be_bytes())?;
+    }
+    if let Some(resolution) = resolution {
+        attributes.add(
+            symbols,
+            "ModuleResolution",
+            &resolution.bits().to_be_bytes(),
+        )?;
+    }
+    if let Some(hashes) = hashes {
+        attributes.add(symbols, "ModuleHashes", &hashes)?;
+    }
+    Ok(())
+}
+
+fn write_module_relation(
+    symbols: &mut SymbolTable,
+    (count, data): &mut (u16, Vec<u8>),
+    relation: ModuleRelationEvent,
+) -> ClassFileResult<()> {
+    increment_count("module exports or opens", count)?;
+    data.put_u16(symbols.package(&relation.package)?);
+    data.put_u16(relation.access.bits());
+    data.put_u16(table_len("target modules", relation.modules.len())?);
+    for module in &relation.modules {
+        data.put_u16(symbols.module(module)?);
+    }
+    Ok(())
+}
+
+fn write_record_component<'class, E, P>(
+    symbols: &mut SymbolTable,
+    output: &mut Vec<u8>,
+    component: ClassRecordComponentEvent<'class, E>,
+) -> ClassFileResult<()>
+where
+    E: IntoIterator<Item = ClassFileResult<RecordComponentEvent<'class, P>>>,
+    P: RecordComponentEventProviders<'class>,
+{
+    let mut attributes = AttributesWriter::default();
+    let mut annotations = AnnotationsWriter::default();
+    let mut type_annotations = AnnotationsWriter::default();
+    let mut custom_attributes = Vec::new();
+
+    if let Some(signature) = &component.signature {
+        let signature = symbols.utf8(signature)?;
+        attributes.add(symbols, "Signature", &signature.to_be_bytes())?;
+    }
+
+    for event in component.events {
+        match event? {
+            RecordComponentEvent::Annotations(events) => {
+                for event in events {
+                    let event = event?;
+                    annotations.add_annotation(symbols, event.visible, &event.annotation)?;
+                }
+            }
+            RecordComponentEvent::TypeAnnotations(events) => {
+                for event in events {
+                    let event = event?;
+                    type_annotations.add_type_annotation(
+                        symbols,
+                        event.visible,
+                        &event.annotation,
+                        TypeAnnotationLocation::None,
+                    )?;
+                }
+            }
+            RecordComponentEvent::Attributes(events) => {
+                for attribute in events {
+                    custom_attributes.push(attribute?);
+                }
+            }
+        }
+    }
+
+    annotations.finish(
+        symbols,
+        &mut attributes,
+        "RuntimeVisibleAnnotations",
+        "RuntimeInvisibleAnnotations",
+    )?;
+    type_annotations.finish(
+        symbols,
+        &mut attributes,
+        "RuntimeVisibleTypeAnnotations",
+        "RuntimeInvisibleTypeAnnotations",
+    )?;
+    for attribute in &custom_attributes {
+        attributes.add_custom(symbols, attribute.as_ref())?;
+    }
+
+    output.put_u16(symbols.utf8(&component.name)?);
+    output.put_u16(symbols.utf8(&component.desc)?);
+    attributes.write_to(output);
+    Ok(())
+}
+
+fn write_field<'class, E, P>(
+    symbols: &mut SymbolTable,
+    major_version: u16,
+    output: &mut Vec<u8>,
+    field: ClassFieldEvent<'class, E>,
+) -> ClassFileResult<()>
+where
+    E: IntoIterator<Item = ClassFileResult<FieldEvent<'class, P>>>,
+    P: FieldEventProviders<'class>,
+{
+    let mut access = field.access;
+    let mut attributes = AttributesWriter::default();
+    let mut annotations = AnnotationsWriter::default();
+    let mut type_annotations = AnnotationsWriter::default();
+    let mut custom_attributes = Vec::new();
+
+    if let Some(value) = &field.value {
+        // the JVM ignores the attribute on instance fields
+        if access.contains(FieldAccess::Static) && !value.matches_desc(&field.desc) {
+            return Err(ClassFileError::ConstantValueMismatch {
+                desc: field.desc.into_owned(),
+                actual: value.tag(),
+            });
+        }
+        let value = symbols.field_value(value)?;
+        attributes.add(symbols, "ConstantValue", &value.to_be_bytes())?;
+    }
+    if let Some(signature) = &field.signature {
+        let signature = symbols.utf8(signature)?;
+        attributes.add(symbols, "Signature", &signature.to_be_bytes())?;
+    }
+    if major_version < JAVA_5_VERSION && access.contains(FieldAccess::Synthetic) {
+        access.remove(FieldAccess::Synthetic);
+        attributes.add(symbols, "Synthetic", &[])?;
+    }
+
+    for event in field.events {
+        match event? {
+            FieldEvent::Deprecated => attributes.add(symbols, "Deprecated", &[])?,
+            FieldEvent::Annotations(events) => {
+                for event in events {
+                    let event = event?;
+                    annotations.add_annotation(symbols, event.visible, &event.annotation)?;
+                }
+            }
+            FieldEvent::TypeAnnotations(events) => {
+                for event in events {
+                    let event = event?;
+                    type_annotations.add_type_annotation(
+                        symbols,
+                        event.visible,
+                        &event.annotation,
+                        TypeAnnotationLocation::None,
+                    )?;
+                }
+            }
+            FieldEvent::Attributes(events) => {
+                for attribute in events {
+                    custom_attributes.push(attribute?);
+                }
+            }
+        }
+    }
+
+    annotations.finish(
+        symbols,
+        &mut attributes,
+        "RuntimeVisibleAnnotations",
+        "RuntimeInvisibleAnnotations",
+    )?;
+    type_annotations.finish(
+        symbols,
+        &mut attributes,
+        "RuntimeVisibleTypeAnnotations",
+        "RuntimeInvisibleTypeAnnotations",
+    )?;
+    for attribute in &custom_attributes {
+        attributes.add_custom(symbols, attribute.as_ref())?;
+    }
+
+    output.put_u16(access.bits());
+    output.put_u16(symbols.utf8(&field.name)?);
+    output.put_u16(symbols.utf8(&field.desc)?);
+    attributes.write_to(output);
+    Ok(())
+}
+
+fn write_method<'class, E, P>(
+    symbols: &mut SymbolTable,
+    writer: &ClassWriter,
+    class: &ClassClassEvent<'class>,
+    output: &mut Vec<u8>,
+    method: ClassMethodEvent<'class, E>,
+) -> ClassFileResult<()>
+where
+    E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+    P: MethodEventProviders<'class>,
+{
+    if class.access.contains(ClassAccess::Interface)
+        && !method.access.contains(MethodAccess::Abstract)
+        && method.name.as_ref() != "<clinit>"
+    {
+        symbols.require(VersionedConstruct::InterfaceMethodCode)?;
+    }
+
+    let mut access = method.access;
+    let mut is_deprecated = false;
+    let mut parameters = None;
+    let mut annotation_default = None;
+    let mut annotations = AnnotationsWriter::default();
+    let mut type_annotations = AnnotationsWriter::default();
+    let mut visible_parameter_annotations = ParameterAnnotationsWriter::default();
+    let mut invisible_parameter_annotations = ParameterAnnotationsWriter::default();
+    let mut custom_attributes = Vec::new();
+    let mut code: Option<CodeWriter<'class>> = None;
+    let new_code_writer = || {
+        let mut code = CodeWriter::default();
+        if writer.flags.contains(ClassWriterFlags::ComputeMaxs) {
+            let (argument_slots, _) = method_desc_slots(&method.desc);
+            let this_slots = !method.access.contains(MethodAccess::Static) as u16;
+            code.compute_maxs = true;
+            code.max_locals = argument_slots + this_slots;
+        }
+        if writer.flags.contains(ClassWriterFlags::ComputeFrames) {
+            let class_hierarchy = writer
+                .class_hierarchy
+                .clone()
+                .unwrap_or_else(|| Arc::new(SimpleClassHierarchy::new()));
+            code.frame_computer = Some(FrameComputer::new(
+                class_hierarchy,
+                class.name.clone(),
+                method.access,
+                &method.name,
+                &method.desc,
+            ));
+        }
+        code
+    };
+
+    let mut events = method.events.into_iter();
+    let mut unchanged = None;
+    let mut event_count = 0;
+    for event in events.by_ref() {
+        event_count += 1;
+        match event? {
+            MethodEvent::Unchanged(_) if event_count != 1 => {
+                return Err(ClassFileError::UnchangedMethodNotAlone)
+            }
+            MethodEvent::Unchanged(event) => {
+                unchanged = Some(event);
+                break;
+            }
+            MethodEvent::Deprecated => is_deprecated = true,
+            MethodEvent::Parameters(events) => {
+                let (count, data) = parameters.get_or_insert_with(|| (0u8, Vec::new()));
+                for parameter in events {
+                    let parameter = parameter?;
+                    *count = count.checked_add(1).ok_or(ClassFileError::TooMany {
+                        what: "method parameters",
+                        len: u8::MAX as usize + 1,
+                        max: u8::MAX as usize,
+                    })?;
+                    data.put_u16(
+                        parameter
+                            .name
+                            .as_deref()
+                            .map_or(Ok(0), |name| symbols.utf8(name))?,
+                    );
+                    data.put_u16(parameter.access.bits());
+                }
+            }
+            MethodEvent::AnnotationDefault(value) => {
+                let mut data = Vec::new();
+                write_annotation_value(symbols, &mut data, &value)?;
+                annotation_default = Some(data);
+            }
+            MethodEvent::Annotations(events) => {
+                for event in events {
+                    let event = event?;
+                    annotations.add_annotation(symbols, event.visible, &event.annotation)?;
+                }
+            }
+            MethodEvent::TypeAnnotations(events) => {
+                for event in events {
+                    let event = event?;
+                    type_annotations.add_type_annotation(
+                        symbols,
+                        event.visible,
+                        &event.annotation,
+                        TypeAnnotationLocation::None,
+                    )?;
+                }
+            }
+            MethodEvent::AnnotableParameterCount(event) => {
+                if event.visible {
+                    visible_parameter_annotations.count = Some(event.count);
+                } else {
+                    invisible_parameter_annotations.count = Some(event.count);
+                }
+            }
+            MethodEvent::ParameterAnnotations(events) => {
+                for event in events {
+                    let event = event?;
+                    let parameter_annotations = if event.visible {
+                        &mut visible_parameter_annotations
+                    } else {
+                        &mut invisible_parameter_annotations
+                    };
+                    parameter_annotations.add(symbols, event.parameter, &event.annotation)?;
+                }
+            }
+            MethodEvent::Attributes(events) => {
+                for attribute in events {
+                    custom_attributes.push(attribute?);
+                }
+            }
+            MethodEvent::Code { .. } => {
+                code.get_or_insert_with(new_code_writer);
+            }
+            event => {
+                code.get_or_insert_with(new_code_writer)
+                    .visit(symbols, event)?;
+            }
+        }
+    }
+    if let Some(unchanged) = unchanged {
+        if events.next().is_some() {
+            return Err(ClassFileError::UnchangedMethodNotAlone);
+        }
+        return write_unchanged_method(symbols, writer, class, output, unchanged);
+    }
+
+    let mut attributes = AttributesWriter::default();
+    if let Some(code) = code {
+        let code = code.finish(symbols)?;
+        attributes.add(symbols, "Code", &code)?;
+    }
+    if !method.exceptions.is_empty() {
+        let mut data = Vec::with_capacity(2 + method.exceptions.len() * 2);
+        data.put_u16(table_len("exceptions", method.exceptions.len())?);
+        for exception in &method.exceptions {
+            data.put_u16(symbols.class(exception)?);
+        }
+        attributes.add(symbols, "Exceptions", &data)?;
+    }
+    if let Some(signature) = &method.signature {
+        let signature = symbols.utf8(signature)?;
+        attributes.add(symbols, "Signature", &signature.to_be_bytes())?;
+    }
+    if class.major_version < JAVA_5_VERSION && access.contains(MethodAccess::Synthetic) {
+        access.remove(MethodAccess::Synthetic);
+        attributes.add(symbols, "Synthetic", &[])?;
+    }
+    if is_deprecated {
+        attributes.add(symbols, "Deprecated", &[])?;
+    }
+    if let Some((count, data)) = parameters {
+        let mut payload = Vec::with_capacity(1 + data.len());
+        payload.put_u8(count);
+        payload.extend_from_slice(&data);
+        attributes.add(symbols, "MethodParameters", &payload)?;
+    }
+    if let Some(annotation_default) = annotation_default {
+        attributes.add(symbols, "AnnotationDefault", &annotation_default)?;
+    }
+    annotations.finish(
+        symbols,
+        &mut attributes,
+        "RuntimeVisibleAnnotations",
+        "RuntimeInvisibleAnnotations",
+    )?;
+    type_annotations.finish(
+        symbols,
+        &mut attributes,
+        "RuntimeVisibleTypeAnnotations",
+        "RuntimeInvisibleTypeAnnotations",
+    )?;
+    if let Some(data) = visible_parameter_annotations.to_bytes() {
+        attributes.add(symbols, "RuntimeVisibleParameterAnnotations", &data)?;
+    }
+    if let Some(data) = invisible_parameter_annotations.to_bytes() {
+        attributes.add(symbols, "RuntimeInvisibleParameterAnnotations", &data)?;
+    }
+    for attribute in &custom_attributes {
+        attributes.add_custom(symbols, attribute.as_ref())?;
+    }
+
+    output.put_u16(access.bits());
+    output.put_u16(symbols.utf8(&method.name)?);
+    output.put_u16(symbols.utf8(&method.desc)?);
+    attributes.write_to(output);
+    Ok(())
+}
+
+/// Copies an unchanged method verbatim if it was read from the class whose constant pool was
+/// copied, so its constant pool indices are still valid, and otherwise decodes it again.
+fn write_unchanged_method<'class>(
+    symbols: &mut SymbolTable,
+    writer: &ClassWriter,
+    class: &ClassClassEvent<'class>,
+    output: &mut Vec<u8>,
+    method: MethodUnchangedEvent<'class>,
+) -> ClassFileResult<()> {
+    if symbols.is_copied_from(method.class) {
+        output.extend_from_slice(method.bytes());
+        return Ok(());
+    }
+
+    let reader = ClassReader::new(method.class, ClassReaderFlags::None)?;
+    let method = reader.unchanged_method(&method)?;
+    write_method(symbols, writer, class, output, method)
+}
+
+#[derive(Debug, Default)]
+struct CodeWriter<'class> {
+    code: Vec<u8>,
+    last_insn_offset: usize,
+    labels: HashMap<Label, usize>,
+    jumps: Vec<Jump>,
+    frames: Vec<(usize, Frame<'class>)>,
+    line_numbers: Vec<(Label, u16)>,
+    insn_annotations: Vec<(usize, bool, TypeAnnotationNode<'class>)>,
+    local_variables: Vec<MethodLocalVariableEvent<'class>>,
+    local_variable_annotations: Vec<MethodLocalVariableAnnotationEvent<'class>>,
+    try_catch_blocks: Vec<MethodTryCatchBlockEvent<'class>>,
+    try_catch_block_annotations: Vec<MethodTryCatchBlockAnnotationEvent<'class>>,
+    custom_attributes: Vec<Box<dyn Attribute>>,
+    max_stack: u16,
+    max_locals: u16,
+    compute_maxs: bool,
+    insn_flows: Vec<InsnFlow>,
+    frame_computer: Option<FrameComputer<'class>>,
+    synthetic_label_count: u32,
+}
+
+/// How far the code after each widened jump and each switch moves when jumps are widened.
+#[derive(Debug, Default)]
+struct Relocation {
+    /// The original offsets of the instructions which change size, and the total distance the code
+    /// after each of them moves.
+    shifts: Vec<(usize, usize)>,
+}
+
+impl Relocation {
+    fn new(
+        code: &[u8],
+        switches: &BTreeSet<usize>,
+        widened_jumps: impl Iterator<Item = usize>,
+    ) -> Self {
+        let mut offsets: Vec<_> = switches.iter().copied().chain(widened_jumps).collect();
+        offsets.sort_unstable();
+
+        let mut shifts = Vec::with_capacity(offsets.len());
+        let mut shift = 0;
+        for offset in offsets {
+            if switches.contains(&offset) {
+                // the padding of a switch depends on where it ends up, and can shrink
+                let old_padding = 3 - offset % 4;
+                let new_padding = 3 - (offset + shift) % 4;
+                shift = shift + new_padding - old_padding;
+            } else if matches!(code[offset], GOTO | JSR) {
+                shift += 2;
+            } else {
+                shift += 5;
+            }
+            shifts.push((offset, shift));
+        }
+        Relocation { shifts }
+    }
+
+    fn new_offset(&self, offset: usize) -> usize {
+        let index = self.shifts.partition_point(|&(start, _)| start < offset);
+        match index {
+            0 => offset,
+            _ => offset + self.shifts[index - 1].1,
+        }
+    }
+}
+
+const GOTO: u8 = Opcode::Goto as u8;
+const JSR: u8 = Opcode::Jsr as u8;
+
+/// The opposite of a conditional jump opcode. The conditional jumps come in opposite pairs, starting
+/// at the odd `ifeq`, apart from `ifnull` and `ifnonnull` which start at an even opcode.
+fn inverted_branch(opcode: u8) -> u8 {
+    if opcode >= Opcode::IfNull as u8 {
+        opcode ^ 1
+    } else if opcode % 2 == 1 {
+        opcode + 1
+    } else {
+        opcode - 1
+    }
+}
+
+/// A reference to a label from the code, which is patched once all labels are known.
+#[derive(Debug)]
+struct Jump {
+    label: Label,
+    insn_offset: usize,
+    patch_offset: usize,
+    wide: bool,
+}
+
+impl<'class> CodeWriter<'class> {
+    fn visit<P>(
+        &mut self,
+        symbols: &mut SymbolTable,
+        event: MethodEvent<'class, P>,
+    ) -> ClassFileResult<()>
+    where
+        P: MethodEventProviders<'class>,
+    {
+        let flow = if self.compute_maxs {
+            if let Some((var_index, size)) = local_use(&event) {
+                self.max_locals = self.max_locals.max(var_index.saturating_add(size));
+            }
+            insn_flow(&event)
+        } else {
+            None
+        };
+        match event {
+            MethodEvent::Deprecated
+            | MethodEvent::Parameters(_)
+            | MethodEvent::AnnotationDefault(_)
+            | MethodEvent::Annotations(_)
+            | MethodEvent::TypeAnnotations(_)
+            | MethodEvent::AnnotableParameterCount(_)
+            | MethodEvent::ParameterAnnotations(_)
+            | MethodEvent::Attributes(_)
+            | MethodEvent::Code { .. }
+            | MethodEvent::Unchanged(_) => unreachable!("handled by write_method"),
+            MethodEvent::Frame(_) if self.frame_computer.is_some() => {}
+            MethodEvent::Frame(frame) => {
+                let offset = self.code.len();
+                match self.frames.last_mut() {
+                    Some((last_offset, last_frame)) if *last_offset == offset => {
+                        *last_frame = frame
+                    }
+                    _ => self.frames.push((offset, frame)),
+                }
+            }
+            MethodEvent::Insn(opcode) => {
+                self.start_insn();
+                self.code.put_u8(opcode as u8);
+                self.frame_insn(|| FrameInsn::Insn(opcode));
+            }
+            MethodEvent::BIPushInsn(value) => {
+                self.start_insn();
+                self.code.put_u8(Opcode::BIPush as u8);
+                self.code.put_u8(value as u8);
+                self.frame_insn(|| FrameInsn::Push(FrameValue::Integer));
+            }
+            MethodEvent::SIPushInsn(value) => {
+                self.start_insn();
+                self.code.put_u8(Opcode::SIPush as u8);
+                self.code.put_u16(value as u16);
+                self.frame_insn(|| FrameInsn::Push(FrameValue::Integer));
+            }
+            MethodEvent::NewArrayInsn(ty) => {
+                self.start_insn();
+                self.code.put_u8(Opcode::NewArray as u8);
+                self.code.put_u8(ty as u8);
+                self.frame_insn(|| FrameInsn::NewArray(ty));
+            }
+            MethodEvent::VarInsn { opcode, var_index } => {
+                self.start_insn();
+                let shortcut_base = match opcode {
+                    Opcode::ILoad
+                    | Opcode::LLoad
+                    | Opcode::FLoad
+                    | Opcode::DLoad
+                    | Opcode::ALoad => {
+                        Some(InternalOpcodes::ILOAD_0 + (opcode as u8 - Opcode::ILoad as u8) * 4)
+                    }
+                    Opcode::IStore
+                    | Opcode::LStore
+                    | Opcode::FStore
+                    | Opcode::DStore
+                    | Opcode::AStore => {
+                        Some(InternalOpcodes::ISTORE_0 + (opcode as u8 - Opcode::IStore as u8) * 4)
+                    }
+                    _ => None,
+                };
+                match shortcut_base {
+                    Some(base) if var_index < 4 => self.code.put_u8(base + var_index as u8),
+                    _ if var_index > u8::MAX as u16 => {
+                        self.code.put_u8(InternalOpcodes::WIDE);
+                        self.code.put_u8(opcode as u8);
+                        self.code.put_u16(var_index);
+                    }
+                    _ => {
+                        self.code.put_u8(opcode as u8);
+                        self.code.put_u8(var_index as u8);
+                    }
+                }
+                self.frame_insn(|| FrameInsn::Var { opcode, var_index });
+            }
+            MethodEvent::TypeInsn { opcode, ty } => {
+                self.start_insn();
+                self.code.put_u8(opcode as u8);
+                self.code.put_u16(symbols.class(&ty)?);
+                if opcode == Opcode::New && self.frame_computer.is_some() {
+                    // uninitialized values refer to the offset of their new instruction by label
+                    let label = Label::synthetic(self.synthetic_label_count);
+                    self.synthetic_label_count += 1;
+                    self.labels.insert(label, self.last_insn_offset);
+                    self.frame_insn(|| FrameInsn::Push(FrameValue::Uninitialized(label)));
+                } else {
+                    self.frame_insn(|| FrameInsn::Type { opcode, ty });
+                }
+            }
+            MethodEvent::FieldInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+            } => {
+                self.start_insn();
+                self.code.put_u8(opcode as u8);
+                self.code.put_u16(symbols.field_ref(&owner, &name, &desc)?);
+                self.frame_insn(|| FrameInsn::Field { opcode, desc });
+            }
+            MethodEvent::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                is_interface,
+            } => {
+                self.start_insn();
+                self.code.put_u8(opcode as u8);
+                self.code
+                    .put_u16(symbols.method_ref(&owner, &name, &desc, is_interface)?);
+                if opcode == Opcode::InvokeInterface {
+                    let (argument_slots, _) = method_desc_slots(&desc);
+                    self.code.put_u8(argument_slots as u8 + 1);
+                    self.code.put_u8(0);
+                }
+                self.frame_insn(|| FrameInsn::Method {
+                    opcode,
+                    owner,
+                    name,
+                    desc,
+                });
+            }
+            MethodEvent::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            } => {
+                symbols.require(VersionedConstruct::InvokeDynamic)?;
+                self.start_insn();
+                let bootstrap_method = symbols
+                    .bootstrap_method(&bootstrap_method_handle, &bootstrap_method_arguments)?;
+                self.code.put_u8(Opcode::InvokeDynamic as u8);
+                self.code
+                    .put_u16(symbols.invoke_dynamic(bootstrap_method, &name, &desc)?);
+                self.code.put_u16(0);
+                self.frame_insn(|| FrameInsn::InvokeDynamic { desc });
+            }
+            MethodEvent::JumpInsn { opcode, label } => {
+                self.start_insn();
+                self.code.put_u8(opcode as u8);
+                self.jump(label, false);
+                self.frame_insn(|| FrameInsn::Jump { opcode, label });
+            }
+            MethodEvent::Label(label) => {
+                self.labels.insert(label, self.code.len());
+            }
+            MethodEvent::LdcInsn(constant) => {
+                self.start_insn();
+                let index = symbols.ldc_constant(&constant)?;
+                if matches!(constant, LdcConstant::Long(_) | LdcConstant::Double(_)) {
+                    self.code.put_u8(InternalOpcodes::LDC2_W);
+                    self.code.put_u16(index);
+                } else if index > u8::MAX as u16 {
+                    self.code.put_u8(InternalOpcodes::LDC_W);
+                    self.code.put_u16(index);
+                } else {
+                    self.code.put_u8(Opcode::Ldc as u8);
+                    self.code.put_u8(index as u8);
+                }
+                self.frame_insn(|| FrameInsn::Push(ldc_value(&constant)));
+            }
+            MethodEvent::IIncInsn {
+                var_index,
+                increment,
+            } => {
+                self.start_insn();
+                if var_index > u8::MAX as u16 || i8::try_from(increment).is_err() {
+                    self.code.put_u8(InternalOpcodes::WIDE);
+                    self.code.put_u8(Opcode::IInc as u8);
+                    self.code.put_u16(var_index);
+                    self.code.put_u16(increment as u16);
+                } else {
+                    self.code.put_u8(Opcode::IInc as u8);
+                    self.code.put_u8(var_index as u8);
+                    self.code.put_u8(increment as u8);
+                }
+                self.frame_insn(|| FrameInsn::Insn(Opcode::IInc));
+            }
+            MethodEvent::TableSwitchInsn {
+                low,
+                high,
+                dflt,
+                labels,
+            } => {
+                self.start_insn();
+                self.code.put_u8(Opcode::TableSwitch as u8);
+                self.align_switch();
+                self.jump(dflt, true);
+                self.code.put_u32(low as u32);
+                self.code.put_u32(high as u32);
+                for &label in &labels {
+                    self.jump(label, true);
+                }
+                let mut targets = labels;
+                targets.push(dflt);
+                self.frame_insn(|| FrameInsn::Switch { labels: targets });
+            }
+            MethodEvent::LookupSwitchInsn { dflt, mut values } => {
+                self.start_insn();
+                self.code.put_u8(Opcode::LookupSwitch as u8);
+                self.align_switch();
+                self.jump(dflt, true);
+                values.sort_by_key(|&(value, _)| value);
+                self.code.put_u32(values.len() as u32);
+                for &(value, label) in &values {
+                    self.code.put_u32(value as u32);
+                    self.jump(label, true);
+                }
+                let mut targets: Vec<_> = values.into_iter().map(|(_, label)| label).collect();
+                targets.push(dflt);
+                self.frame_insn(|| FrameInsn::Switch { labels: targets });
+            }
+            MethodEvent::MultiANewArrayInsn { desc, dimensions } => {
+                self.start_insn();
+                self.code.put_u8(Opcode::MultiANewArray as u8);
+                self.code.put_u16(symbols.class(&desc)?);
+                self.code.put_u8(dimensions);
+                self.frame_insn(|| FrameInsn::MultiANewArray { desc, dimensions });
+            }
+            MethodEvent::InsnAnnotations(events) => {
+                for event in events {
+                    let event = event?;
+                    self.insn_annotations.push((
+                        self.last_insn_offset,
+                        event.visible,
+                        event.annotation,
+                    ));
+                }
+            }
+            MethodEvent::LineNumber { line, start } => self.line_numbers.push((start, line)),
+            MethodEvent::LocalVariables(events) => {
+                for local_variable in events {
+                    self.local_variables.push(local_variable?);
+                }
+            }
+            MethodEvent::LocalVariableAnnotations(events) => {
+                for event in events {
+                    self.local_variable_annotations.push(event?);
+                }
+            }
+            MethodEvent::TryCatchBlocks(events) => {
+                for try_catch_block in events {
+                    self.try_catch_blocks.push(try_catch_block?);
+                }
+            }
+            MethodEvent::TryCatchBlockAnnotations(events) => {
+                for event in events {
+                    self.try_catch_block_annotations.push(event?);
+                }
+            }
+            MethodEvent::CodeAttributes(events) => {
+                for attribute in events {
+                    self.custom_attributes.push(attribute?);
+                }
+            }
+            MethodEvent::Maxs(maxs) => {
+                if !self.compute_maxs {
+                    self.max_stack = maxs.max_stack;
+                    self.max_locals = maxs.max_locals;
+                }
+            }
+        }
+
+        if let Some((stack_delta, kind)) = flow {
+            self.insn_flows.push(InsnFlow {
+                offset: self.last_insn_offset,
+                stack_delta,
+                kind,
+            });
+        }
+        Ok(())
+    }
+
+    fn start_insn(&mut self) {
+        self.last_insn_offset = self.code.len();
+    }
+
+    fn frame_insn(&mut self, insn: impl FnOnce() -> FrameInsn<'class>) {
+        if let Some(frame_computer) = &mut self.frame_computer {
+            frame_computer.add_insn(self.last_insn_offset, insn());
+        }
+    }
+
+    fn jump(&mut self, label: Label, wide: bool) {
+        self.jumps.push(Jump {
+            label,
+            insn_offset: self.last_insn_offset,
+            patch_offset: self.code.len(),
+            wide,
+        });
+        if wide {
+            self.code.put_u32(0);
+        } else {
+            self.code.put_u16(0);
+        }
+    }
+
+    fn align_switch(&mut self) {
+        while !self.code.len().is_multiple_of(4) {
+            self.code.put_u8(0);
+        }
+    }
+
+    fn label_offset(&self, label: Label) -> ClassFileResult<usize> {
+        self.labels
+            .get(&label)
+            .copied()
+            .ok_or(ClassFileError::UnknownLabel(label))
+    }
+
+    /// Widens the jumps whose offsets don't fit in 16 bits. `goto` and `jsr` become `goto_w` and
+    /// `jsr_w`, and conditional jumps become the opposite jump over a `goto_w`. The code after a
+    /// widened jump moves, so everything else which refers to code offsets is moved with it.
+    fn widen_jumps(&mut self) -> ClassFileResult<()> {
+        let switches: BTreeSet<usize> = self
+            .jumps
+            .iter()
+            .filter(|jump| jump.wide)
+            .map(|jump| jump.insn_offset)
+            .collect();
+        let mut widened = vec![false; self.jumps.len()];
+        let mut any_widened = false;
+        let mut relocation = Relocation::default();
+
+        // widening a jump can push other jumps out of range, so repeat until nothing changes
+        loop {
+            let mut changed = false;
+            for (jump, widened) in self.jumps.iter().zip(&mut widened) {
+                if jump.wide || *widened {
+                    continue;
+                }
+                let target = relocation.new_offset(self.label_offset(jump.label)?);
+                let offset = target as i64 - relocation.new_offset(jump.insn_offset) as i64;
+                if i16::try_from(offset).is_err() {
+                    *widened = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+            any_widened = true;
+            relocation = Relocation::new(
+                &self.code,
+                &switches,
+                self.jumps
+                    .iter()
+                    .zip(&widened)
+                    .filter(|(_, widened)| **widened)
+                    .map(|(jump, _)| jump.insn_offset),
+            );
+        }
+        if !any_widened {
+            return Ok(());
+        }
+
+        let is_conditional = |offset: usize| !matches!(self.code[offset], GOTO | JSR);
+        let mut new_frame_targets = Vec::new();
+        let mut code = Vec::with_capacity(relocation.new_offset(self.code.len()));
+        let mut copied = 0;
+        for &(offset, _) in &relocation.shifts {
+            code.extend_from_slice(&self.code[copied..offset]);
+            let opcode = self.code[offset];
+            if switches.contains(&offset) {
+                code.put_u8(opcode);
+                while !code.len().is_multiple_of(4) {
+                    code.put_u8(0);
+                }
+                copied = offset + 1 + (3 - offset % 4);
+            } else if is_conditional(offset) {
+                // the frames at the instruction after the goto_w can only be computed
+                if !self.frames.is_empty() && self.frame_computer.is_none() {
+                    let jump = self
+                        .jumps
+                        .iter()
+                        .find(|jump| jump.insn_offset == offset)
+                        .expect("widened jump exists");
+                    let target = self.label_offset(jump.label)? as i32;
+                    return Err(ClassFileError::JumpOffsetTooLarge(target - offset as i32));
+                }
+                code.put_u8(inverted_branch(opcode));
+                code.put_u16(8);
+                code.put_u8(InternalOpcodes::GOTO_W);
+                code.put_u32(0);
+                new_frame_targets.push(code.len());
+                copied = offset + 3;
+            } else {
+                code.put_u8(if opcode == GOTO {
+                    InternalOpcodes::GOTO_W
+                } else {
+                    InternalOpcodes::JSR_W
+                });
+                code.put_u32(0);
+                copied = offset + 3;
+            }
+        }
+        code.extend_from_slice(&self.code[copied..]);
+
+        for (jump, widened) in self.jumps.iter_mut().zip(widened) {
+            let insn_offset = relocation.new_offset(jump.insn_offset);
+            if !widened {
+                jump.patch_offset = relocation.new_offset(jump.patch_offset);
+                jump.insn_offset = insn_offset;
+            } else if is_conditional(jump.insn_offset) {
+                jump.insn_offset = insn_offset + 3;
+                jump.patch_offset = insn_offset + 4;
+                jump.wide = true;
+            } else {
+                jump.insn_offset = insn_offset;
+                jump.patch_offset = insn_offset + 1;
+                jump.wide = true;
+            }
+        }
+        self.code = code;
+        for offset in self.labels.values_mut() {
+            *offset = relocation.new_offset(*offset);
+        }
+        for (offset, _) in &mut self.frames {
+            *offset = relocation.new_offset(*offset);
+        }
+        for (offset, _, _) in &mut self.insn_annotations {
+            *offset = relocation.new_offset(*offset);
+        }
+        for flow in &mut self.insn_flows {
+            flow.offset = relocation.new_offset(flow.offset);
+        }
+        if let Some(frame_computer) = &mut self.frame_computer {
+            frame_computer.relocate(|offset| relocation.new_offset(offset));
+            for offset in new_frame_targets {
+                frame_computer.add_frame_target(offset);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, symbols: &mut SymbolTable) -> ClassFileResult<Vec<u8>> {
+        self.widen_jumps()?;
+        if self.code.is_empty() || self.code.len() > u16::MAX as usize {
+            return Err(ClassFileError::BadCodeSize(self.code.len() as u32));
+        }
+
+        if self.compute_maxs {
+            self.max_stack = self.compute_max_stack()?;
+        }
+        if let Some(frame_computer) = &self.frame_computer {
+            self.frames =
+                frame_computer.compute(|label| self.label_offset(label), &self.try_catch_blocks)?;
+        }
+
+        for jump in &self.jumps {
+            let offset = self.label_offset(jump.label)? as i32 - jump.insn_offset as i32;
+            if jump.wide {
+                self.code[jump.patch_offset..jump.patch_offset + 4]
+                    .copy_from_slice(&offset.to_be_bytes());
+            } else {
+                let offset = i16::try_from(offset)
+                    .map_err(|_| ClassFileError::JumpOffsetTooLarge(offset))?;
+                self.code[jump.patch_offset..jump.patch_offset + 2]
+                    .copy_from_slice(&offset.to_be_bytes());
+            }
+        }
+
+        let mut attributes = AttributesWriter::default();
+
+        if !self.line_numbers.is_empty() {
+            let mut data = Vec::with_capacity(2 + self.line_numbers.len() * 4);
+            data.put_u16(table_len("line numbers", self.line_numbers.len())?);
+            for &(start, line) in &self.line_numbers {
+                data.put_u16(self.label_offset(start)? as u16);
+                data.put_u16(line);
+            }
+            attributes.add(symbols, "LineNumberTable", &data)?;
+        }
+
+        if !self.local_variables.is_empty() {
+            let mut lvt = Vec::with_capacity(2 + self.local_variables.len() * 10);
+            let mut lvtt_count = 0u16;
+            let mut lvtt = Vec::new();
+            lvt.put_u16(table_len("local variables", self.local_variables.len())?);
+            for local_variable in &self.local_variables {
+                let start = self.label_offset(local_variable.start)?;
+                let length = self.label_offset(local_variable.end)? - start;
+                let name = symbols.utf8(&local_variable.name)?;
+                lvt.put_u16(start as u16);
+                lvt.put_u16(length as u16);
+                lvt.put_u16(name);
+                lvt.put_u16(symbols.utf8(&local_variable.desc)?);
+                lvt.put_u16(local_variable.index);
+                if let Some(signature) = &local_variable.signature {
+                    increment_count("local variable types", &mut lvtt_count)?;
+                    lvtt.put_u16(start as u16);
+                    lvtt.put_u16(length as u16);
+                    lvtt.put_u16(name);
+                    lvtt.put_u16(symbols.utf8(signature)?);
+                    lvtt.put_u16(local_variable.index);
+                }
+            }
+            attributes.add(symbols, "LocalVariableTable", &lvt)?;
+            if lvtt_count != 0 {
+                let mut payload = Vec::with_capacity(2 + lvtt.len());
+                payload.put_u16(lvtt_count);
+                payload.extend_from_slice(&lvtt);
+                attributes.add(symbols, "LocalVariableTypeTable", &payload)?;
+            }
+        }
+
+        if !self.frames.is_empty() {
+            let frames = self.write_frames(symbols)?;
+            attributes.add(symbols, "StackMapTable", &frames)?;
+        }
+
+        let mut type_annotations = AnnotationsWriter::default();
+        for (offset, visible, annotation) in &self.insn_annotations {
+            type_annotations.add_type_annotation(
+                symbols,
+                *visible,
+                annotation,
+                TypeAnnotationLocation::Insn(*offset as u16),
+            )?;
+        }
+        for event in &self.local_variable_annotations {
+            let ranges = event
+                .ranges
+                .iter()
+                .map(|&(start, end, index)| -> ClassFileResult<_> {
+                    let start = self.label_offset(start)?;
+                    let length = self.label_offset(end)? - start;
+                    Ok((start as u16, length as u16, index))
+                })
+                .collect::<ClassFileResult<Vec<_>>>()?;
+            type_annotations.add_type_annotation(
+                symbols,
+                event.visible,
+                &event.annotation,
+                TypeAnnotationLocation::LocalVariable(ranges),
+            )?;
+        }
+        for event in &self.try_catch_block_annotations {
+            type_annotations.add_type_annotation(
+                symbols,
+                event.visible,
+                &event.annotation,
+                TypeAnnotationLocation::TryCatchBlock(event.try_catch_block_index),
+            )?;
+        }
+        type_annotations.finish(
+            symbols,
+            &mut attributes,
+            "RuntimeVisibleTypeAnnotations",
+            "RuntimeInvisibleTypeAnnotations",
+        )?;
+
+        for attribute in &self.custom_attributes {
+            attributes.add_custom(symbols, attribute.as_ref())?;
+        }
+
+        let mut output = Vec::with_capacity(
+            12 + self.code.len() + self.try_catch_blocks.len() * 8 + attributes.data.len(),
+        );
+        output.put_u16(self.max_stack);
+        output.put_u16(self.max_locals);
+        output.put_u32(self.code.len() as u32);
+        output.extend_from_slice(&self.code);
+        output.put_u16(table_len("try-catch blocks", self.try_catch_blocks.len())?);
+        for try_catch_block in &self.try_catch_blocks {
+            output.put_u16(self.label_offset(try_catch_block.start)? as u16);
+            output.put_u16(self.label_offset(try_catch_block.end)? as u16);
+            output.put_u16(self.label_offset(try_catch_block.handler)? as u16);
+            output.put_u16(
+                try_catch_block
+                    .ty
+                    .as_deref()
+                    .map_or(Ok(0), |ty| symbols.class(ty))?,
+            );
+        }
+        attributes.write_to(&mut output);
+
+        Ok(output)
+    }
+
+    /// Computes the max stack by following the control flow from the start of the code and from each
+    /// exception handler, assuming each instruction is always reached with the same stack height.
+    fn compute_max_stack(&self) -> ClassFileResult<u16> {
+        let insn_indexes: HashMap<usize, usize> = self
+            .insn_flows
+            .iter()
+            .enumerate()
+            .map(|(index, insn)| (insn.offset, index))
+            .collect();
+        let handlers: Vec<_> = self
+            .try_catch_blocks
+            .iter()
+            .map(|try_catch_block| try_catch_block.handler)
+            .collect();
+        compute_max_stack(&self.insn_flows, &handlers, |label| {
+            Ok(insn_indexes.get(&self.label_offset(label)?).copied())
+        })
+    }
+
+    fn write_frames(&self, symbols: &mut SymbolTable) -> ClassFileResult<Vec<u8>> {
+        let mut output = Vec::new();
+        output.put_u16(table_len("frames", self.frames.len())?);
+
+        let mut last_offset = None;
+        for (offset, frame) in &self.frames {
+            let offset_delta = match last_offset {
+                None => *offset,
+                Some(last_offset) => offset - last_offset - 1,
+            } as u16;
+            last_offset = Some(*offset);
+
+            match frame {
+                Frame::Same => {
+                    if offset_delta < 64 {
+                        output.put_u8(offset_delta as u8);
+                    } else {
+                        output.put_u8(251);
+                        output.put_u16(offset_delta);
+                    }
+                }
+                Frame::Same1 { stack_value } => {
+                    if offset_delta < 64 {
+                        output.put_u8(64 + offset_delta as u8);
+                    } else {
+                        output.put_u8(247);
+                        output.put_u16(offset_delta);
+                    }
+                    self.write_frame_value(symbols, &mut output, stack_value)?;
+                }
+                Frame::Chop { num_locals } => {
+                    let frame_type = 251u8.wrapping_sub(*num_locals);
+                    if !(248..=250).contains(&frame_type) {
+                        return Err(ClassFileError::BadFrameType(frame_type));
+                    }
+                    output.put_u8(frame_type);
+                    output.put_u16(offset_delta);
+                }
+                Frame::Append { locals } => {
+                    let frame_type = 251u8.wrapping_add(locals.len().min(u8::MAX as usize) as u8);
+                    if !(252..=254).contains(&frame_type) {
+                        return Err(ClassFileError::BadFrameType(frame_type));
+                    }
+                    output.put_u8(frame_type);
+                    output.put_u16(offset_delta);
+                    for local in locals {
+                        self.write_frame_value(symbols, &mut output, local)?;
+                    }
+                }
+                Frame::Full { locals, stack } | Frame::New { locals, stack } => {
+                    output.put_u8(255);
+                    output.put_u16(offset_delta);
+                    output.put_u16(table_len("frame locals", locals.len())?);
+                    for local in locals {
+                        self.write_frame_value(symbols, &mut output, local)?;
+                    }
+                    output.put_u16(table_len("frame stack values", stack.len())?);
+                    for value in stack {
+                        self.write_frame_value(symbols, &mut output, value)?;
+                    }
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn write_frame_value(
+        &self,
+        symbols: &mut SymbolTable,
+        output: &mut Vec<u8>,
+        value: &FrameValue,
+    ) -> ClassFileResult<()> {
+        match value {
+            FrameValue::Top => output.put_u8(0),
+            FrameValue::Integer => output.put_u8(1),
+            FrameValue::Float => output.put_u8(2),
+            FrameValue::Double => output.put_u8(3),
+            FrameValue::Long => output.put_u8(4),
+            FrameValue::Null => output.put_u8(5),
+            FrameValue::UninitializedThis => output.put_u8(6),
+            FrameValue::Class(ty) => {
+                output.put_u8(7);
+                output.put_u16(symbols.class(ty)?);
+            }
+            FrameValue::Uninitialized(label) => {
+                output.put_u8(8);
+                output.put_u16(self.label_offset(*label)? as u16);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of slots taken up by the arguments and the return value of a method descriptor.
+pub(crate) fn method_desc_slots(desc: &JavaStr) -> (u16, u16) {
+    let mut bytes = desc.bytes().skip(1);
+    let mut slots = 0u16;
+    while let Some(b) = bytes.next() {
+        match b {
+            b')' => break,
+            b'J' | b'D' => slots = slots.wrapping_add(2),
+            b'L' => {
+                bytes.by_ref().take_while(|&b| b != b';').for_each(drop);
+                slots = slots.wrapping_add(1);
+            }
+            b'[' => {
+                let element = bytes.by_ref().find(|&b| b != b'[');
+                if element == Some(b'L') {
+                    bytes.by_ref().take_while(|&b| b != b';').for_each(drop);
+                }
+                slots = slots.wrapping_add(1);
+            }
+            _ => slots = slots.wrapping_add(1),
+        }
+    }
+    let return_slots = match bytes.next() {
+        Some(b'V') => 0,
+        Some(b'J' | b'D') => 2,
+        _ => 1,
+    };
+    (slots, return_slots)
+}
+
+pub(crate) fn field_desc_slots(desc: &JavaStr) -> u16 {
+    match desc.as_bytes().first() {
+        Some(b'J' | b'D') => 2,
+        _ => 1,
+    }
+}
+
+#[derive(Debug, Default)]
+struct AttributesWriter {
+    count: u16,
+    data: Vec<u8>,
+}
+
+impl AttributesWriter {
+    fn add(
+        &mut self,
+        symbols: &mut SymbolTable,
+        name: &str,
+        payload: &[u8],
+    ) -> ClassFileResult<()> {
+        increment_count("attributes", &mut self.count)?;
+        self.data.put_u16(symbols.utf8(JavaStr::from_str(name))?);
+        self.data.put_u32(payload.len() as u32);
+        self.data.extend_from_slice(payload);
+        Ok(())
+    }
+
+    fn add_custom(
+        &mut self,
+        symbols: &mut SymbolTable,
+        attribute: &dyn Attribute,
+    ) -> ClassFileResult<()> {
+        let payload = attribute.write(symbols)?;
+        if let Some(written_attributes) = &mut symbols.written_attributes {
+            let any: &dyn Any = attribute;
+            if any.is::<UnknownAttribute>() {
+                return Err(ClassFileError::UnwritableAttribute(
+                    attribute.name().to_owned(),
+                ));
+            }
+            written_attributes
+                .entry((attribute.name().to_owned(), payload.clone()))
+                .or_insert_with(|| attribute.copy());
+        }
+        increment_count("attributes", &mut self.count)?;
+        self.data.put_u16(symbols.utf8(attribute.name())?);
+        self.data.put_u32(payload.len() as u32);
+        self.data.extend_from_slice(&payload);
+        Ok(())
+    }
+
+    /// Reorders the attributes to match the given order of attribute name indices. Attributes with
+    /// other names are moved to the end.
+    fn sort_by_name_order(&mut self, order: &[u16]) {
+        self.sort_by_key(|name| {
+            order
+                .iter()
+                .position(|&index| index == name)
+                .unwrap_or(order.len())
+        });
+    }
+
+    /// Stably reorders the attributes by a key of their name index.
+    fn sort_by_key<K: Ord>(&mut self, key: impl Fn(u16) -> K) {
+        let mut attributes = Vec::with_capacity(self.count as usize);
+        let mut pos = 0;
+        while pos < self.data.len() {
+            let name = u16::from_be_bytes([self.data[pos], self.data[pos + 1]]);
+            let len = u32::from_be_bytes(self.data[pos + 2..pos + 6].try_into().unwrap());
+            let end = pos + 6 + len as usize;
+            attributes.push((name, &self.data[pos..end]));
+            pos = end;
+        }
+        attributes.sort_by_key(|&(name, _)| key(name));
+        self.data = attributes
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .copied()
+            .collect();
+    }
+
+    fn write_to(&self, output: &mut Vec<u8>) {
+        output.put_u16(self.count);
+        output.extend_from_slice(&self.data);
+    }
+}
+
+#[derive(Debug, Default)]
+struct AnnotationsWriter {
+    visible_count: u16,
+    visible: Vec<u8>,
+    invisible_count: u16,
+    invisible: Vec<u8>,
+}
+
+impl AnnotationsWriter {
+    fn output(&mut self, visible: bool) -> ClassFileResult<&mut Vec<u8>> {
+        if visible {
+            increment_count("annotations", &mut self.visible_count)?;
+            Ok(&mut self.visible)
+        } else {
+            increment_count("annotations", &mut self.invisible_count)?;
+            Ok(&mut self.invisible)
+        }
+    }
+
+    fn add_annotation(
+        &mut self,
+        symbols: &mut SymbolTable,
+        visible: bool,
+        annotation: &AnnotationNode,
+    ) -> ClassFileResult<()> {
+        write_annotation(symbols, self.output(visible)?, annotation)
+    }
+
+    fn add_type_annotation(
+        &mut self,
+        symbols: &mut SymbolTable,
+        visible: bool,
+        annotation: &TypeAnnotationNode,
+        location: TypeAnnotationLocation,
+    ) -> ClassFileResult<()> {
+        write_type_annotation(symbols, self.output(visible)?, annotation, location)
+    }
+
+    fn finish(
+        self,
+        symbols: &mut SymbolTable,
+        attributes: &mut AttributesWriter,
+        visible_name: &str,
+        invisible_name: &str,
+    ) -> ClassFileResult<()> {
+        for (name, count, data) in [
+            (visible_name, self.visible_count, self.visible),
+            (invisible_name, self.invisible_count, self.invisible),
+        ] {
+            if count != 0 {
+                let mut payload = Vec::with_capacity(2 + data.len());
+                payload.put_u16(count);
+                payload.extend_from_slice(&data);
+                attributes.add(symbols, name, &payload)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct ParameterAnnotationsWriter {
+    count: Option<u8>,
+    parameters: Vec<(u16, Vec<u8>)>,
+}
+
+impl ParameterAnnotationsWriter {
+    fn add(
+        &mut self,
+        symbols: &mut SymbolTable,
+        parameter: u8,
+        annotation: &AnnotationNode,
+    ) -> ClassFileResult<()> {
+        if self.parameters.len() <= parameter as usize {
+            self.parameters
+                .resize_with(parameter as usize + 1, Default::default);
+        }
+        let (count, data) = &mut self.parameters[parameter as usize];
+        increment_count("parameter annotations", count)?;
+        write_annotation(symbols, data, annotation)
+    }
+
+    fn to_bytes(&self) -> Option<Vec<u8>> {
+        if self.count.is_none() && self.parameters.is_empty() {
+            return None;
+        }
+
+        let num_parameters = self
+            .count
+            .unwrap_or_default()
+            .max(self.parameters.len() as u8);
+        let mut output = Vec::new();
+        output.put_u8(num_parameters);
+        for parameter in 0..num_parameters as usize {
+            match self.parameters.get(parameter) {
+                Some((count, data)) => {
+                    output.put_u16(*count);
+                    output.extend_from_slice(data);
+                }
+                None => output.put_u16(0),
+            }
+        }
+        Some(output)
+    }
+}
+
+enum TypeAnnotationLocation {
+    None,
+    LocalVariable(Vec<(u16, u16, u16)>),
+    Insn(u16),
+    TryCatchBlock(u16),
+}
+
+fn write_annotation(
+    symbols: &mut SymbolTable,
+    output: &mut Vec<u8>,
+    annotation: &AnnotationNode,
+) -> ClassFileResult<()> {
+    output.put_u16(symbols.utf8(&annotation.desc)?);
+    write_annotation_values(symbols, output, &annotation.values)
+}
+
+fn write_type_annotation(
+    symbols: &mut SymbolTable,
+    output: &mut Vec<u8>,
+    annotation: &TypeAnnotationNode,
+    location: TypeAnnotationLocation,
+) -> ClassFileResult<()> {
+    let target_type = annotation.type_ref.target_type() as u8;
+    output.put_u8(target_type);
+
+    match (annotation.type_ref, location) {
+        (
+            TypeReference::ClassTypeParameter { param_index }
+            | TypeReference::MethodTypeParameter { param_index }
+            | TypeReference::MethodFormalParameter { param_index },
+            _,
+        ) => output.put_u8(param_index),
+        (TypeReference::ClassExtends { interface_index }, _) => {
+            output.put_u16(interface_index.unwrap_or(u16::MAX))
+        }
+        (
+            TypeReference::ClassTypeParameterBound {
+                param_index,
+                bound_index,
+            }
+            | TypeReference::MethodTypeParameterBound {
+                param_index,
+                bound_index,
+            },
+            _,
+        ) => {
+            output.put_u8(param_index);
+            output.put_u8(bound_index);
+        }
+        (TypeReference::Field | TypeReference::MethodReturn | TypeReference::MethodReceiver, _) => {
+        }
+        (TypeReference::Throws { exception_index }, _) => output.put_u16(exception_index),
+        (
+            TypeReference::LocalVariable | TypeReference::ResourceVariable,
+            TypeAnnotationLocation::LocalVariable(ranges),
+        ) => {
+            output.put_u16(table_len("local variable ranges", ranges.len())?);
+            for (start, length, index) in ranges {
+                output.put_u16(start);
+                output.put_u16(length);
+                output.put_u16(index);
+            }
+        }
+        (TypeReference::ExceptionParameter, TypeAnnotationLocation::TryCatchBlock(index)) => {
+            output.put_u16(index)
+        }
+        (
+            TypeReference::Instanceof
+            | TypeReference::New
+            | TypeReference::ConstructorReference
+            | TypeReference::MethodReference,
+            TypeAnnotationLocation::Insn(offset),
+        ) => output.put_u16(offset),
+        (
+            TypeReference::Cast { arg_index }
+            | TypeReference::ConstructorInvocationTypeArgument { arg_index }
+            | TypeReference::MethodInvocationTypeArgument { arg_index }
+            | TypeReference::ConstructorReferenceTypeArgument { arg_index }
+            | TypeReference::MethodReferenceTypeArgument { arg_index },
+            TypeAnnotationLocation::Insn(offset),
+        ) => {
+            output.put_u16(offset);
+            output.put_u8(arg_index);
+        }
+        _ => return Err(ClassFileError::BadTypeAnnotationTarget(target_type)),
+    }
+
+    output.put_u8(annotation.type_path.len() as u8);
+    output.extend_from_slice(annotation.type_path.as_bytes());
+    output.put_u16(symbols.utf8(&annotation.desc)?);
+    write_annotation_values(symbols, output, &annotation.values)
+}
+
+fn write_annotation_values(
+    symbols: &mut SymbolTable,
+    output: &mut Vec<u8>,
+    values: &[(impl AsRef<JavaStr>, AnnotationValue)],
+) -> ClassFileResult<()> {
+    output.put_u16(table_len("annotation values", values.len())?);
+    for (name, value) in values {
+        output.put_u16(symbols.utf8(name.as_ref())?);
+        write_annotation_value(symbols, output, value)?;
+    }
+    Ok(())
+}
+
+fn write_annotation_value(
+    symbols: &mut SymbolTable,
+    output: &mut Vec<u8>,
+    value: &AnnotationValue,
+) -> ClassFileResult<()> {
+    match value {
+        AnnotationValue::Byte(value) => {
+            output.put_u8(b'B');
+            output.put_u16(symbols.integer(*value as i32)?);
+        }
+        AnnotationValue::Char(value) => {
+            output.put_u8(b'C');
+            output.put_u16(symbols.integer(*value as i32)?);
+        }
+        AnnotationValue::Double(value) => {
+            output.put_u8(b'D');
+            output.put_u16(symbols.double(*value)?);
+        }
+        AnnotationValue::Float(value) => {
+            output.put_u8(b'F');
+            output.put_u16(symbols.float(*value)?);
+        }
+        AnnotationValue::Int(value) => {
+            output.put_u8(b'I');
+            output.put_u16(symbols.integer(*value)?);
+        }
+        AnnotationValue::Long(value) => {
+            output.put_u8(b'J');
+            output.put_u16(symbols.long(*value)?);
+        }
+        AnnotationValue::Short(value) => {
+            output.put_u8(b'S');
+            output.put_u16(symbols.integer(*value as i32)?);
+        }
+        AnnotationValue::Boolean(value) => {
+            output.put_u8(b'Z');
+            output.put_u16(symbols.integer(*value as i32)?);
+        }
+        AnnotationValue::String(value) => {
+            output.put_u8(b's');
+            output.put_u16(symbols.utf8(value)?);
+        }
+        AnnotationValue::Enum { desc, name } => {
+            output.put_u8(b'e');
+            output.put_u16(symbols.utf8(desc)?);
+            output.put_u16(symbols.utf8(name)?);
+        }
+        AnnotationValue::Class(value) => {
+            output.put_u8(b'c');
+            output.put_u16(symbols.utf8(value)?);
+        }
+        AnnotationValue::Annotation(annotation) => {
+            output.put_u8(b'@');
+            write_annotation(symbols, output, annotation)?;
+        }
+        AnnotationValue::Array(values) => {
+            output.put_u8(b'[');
+            output.put_u16(table_len("annotation array values", values.len())?);
+            for value in values {
+                write_annotation_value(symbols, output, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The constant pool and bootstrap methods of the class being written.
+#[derive(Debug, Clone, Default)]
+struct SymbolTable {
+    constant_pool: ConstantPoolBuilder,
+    bootstrap_methods: Vec<u8>,
+    bootstrap_method_count: u16,
+    /// The indices of the bootstrap methods by their encoding, so identical bootstrap methods are
+    /// only written once.
+    bootstrap_method_indices: HashMap<Vec<u8>, u16>,
+    /// The version of the class being written, which limits the constants that can be added.
+    major_version: u16,
+    /// The class the constant pool and bootstrap methods were copied from.
+    #[debug(skip)]
+    copied_class: Option<Arc<[u8]>>,
+    /// The address of a class already found to be equal to the copied class.
+    copied_class_address: Option<usize>,
+    /// The custom attributes written, when they're needed to write the class again.
+    written_attributes: Option<WrittenAttributes>,
+}
+
+impl Deref for SymbolTable {
+    type Target = ConstantPoolBuilder;
+
+    fn deref(&self) -> &ConstantPoolBuilder {
+        &self.constant_pool
+    }
+}
+
+impl DerefMut for SymbolTable {
+    fn deref_mut(&mut self) -> &mut ConstantPoolBuilder {
+        &mut self.constant_pool
+    }
+}
+
+impl SymbolTable {
+    fn from_reader(reader: &ClassReader) -> ClassFileResult<SymbolTable> {
+        let mut symbols = SymbolTable {
+            constant_pool: ConstantPoolBuilder::from_constant_pool(&reader.constant_pool)?,
+            copied_class: Some(reader.bytes().into()),
+            ..SymbolTable::default()
+        };
+        let bootstrap_methods = reader.raw_attributes()?.into_iter().find(|&(name, _)| {
+            reader.constant_pool.get_utf8_as_bytes(name) == Ok(b"BootstrapMethods")
+        });
+        if let Some((_, data)) = bootstrap_methods {
+            let count = data.read_u16(0)?;
+            let mut pos = 2;
+            for index in 0..count {
+                let len = 4 + data.read_u16(pos + 2)? as usize * 2;
+                symbols
+                    .bootstrap_method_indices
+                    .entry(data.read_bytes(pos, len)?.to_vec())
+                    .or_insert(index);
+                pos += len;
+            }
+            symbols.bootstrap_methods = data.read_bytes(2, pos - 2)?.to_vec();
+            symbols.bootstrap_method_count = count;
+        }
+        Ok(symbols)
+    }
+
+    /// Creates a symbol table with the constant pool entries and bootstrap methods of a class,
+    /// sorted by their contents instead of the order they were added in. Loadable constants come
+    /// first, so that as many as possible can be loaded with `ldc`.
+    fn sorted_from_reader(reader: &ClassReader) -> ClassFileResult<SymbolTable> {
+        let constant_pool = &reader.constant_pool;
+        let mut bootstrap_methods = Vec::new();
+        let bootstrap_methods_attribute = reader
+            .raw_attributes()?
+            .into_iter()
+            .find(|&(name, _)| constant_pool.get_utf8_as_bytes(name) == Ok(b"BootstrapMethods"));
+        if let Some((_, data)) = bootstrap_methods_attribute {
+            let mut pos = 2;
+            for _ in 0..data.read_u16(0)? {
+                let handle = data.read_u16(pos)?;
+                let argument_count = data.read_u16(pos + 2)?;
+                pos += 4;
+                let mut arguments = Vec::with_capacity(argument_count as usize);
+                for _ in 0..argument_count {
+                    arguments.push(data.read_u16(pos)?);
+                    pos += 2;
+                }
+                bootstrap_methods.push((handle, arguments));
+            }
+        }
+
+        let mut sort_keys = SortKeys {
+            constant_pool,
+            bootstrap_methods: &bootstrap_methods,
+            entry_keys: vec![None; constant_pool.len() as usize],
+            bootstrap_method_keys: vec![None; bootstrap_methods.len()],
+            depth: 0,
+        };
+        let mut entries = Vec::new();
+        for index in 1..constant_pool.len() {
+            if let Some(entry) = constant_pool.raw_entry(index)? {
+                let tag = ConstantPoolTag::from_u8(entry[0])?;
+                let is_loadable = matches!(
+                    tag,
+                    ConstantPoolTag::Integer
+                        | ConstantPoolTag::Float
+                        | ConstantPoolTag::String
+                        | ConstantPoolTag::Class
+                        | ConstantPoolTag::MethodHandle
+                        | ConstantPoolTag::MethodType
+                        | ConstantPoolTag::Dynamic
+                );
+                entries.push((!is_loadable, sort_keys.entry(index)?, index, entry));
+            }
+        }
+        entries.sort();
+        let mut bootstrap_method_order = (0..bootstrap_methods.len())
+            .map(|index| Ok((sort_keys.bootstrap_method(index as u16)?, index)))
+            .collect::<ClassFileResult<Vec<_>>>()?;
+        bootstrap_method_order.sort();
+
+        // entries with the same contents are merged
+        let mut new_indices = vec![0u16; constant_pool.len() as usize];
+        let mut next_index = 1;
+        for (i, (_, key, index, entry)) in entries.iter().enumerate() {
+            if i != 0 && entries[i - 1].1 == *key {
+                new_indices[*index as usize] = new_indices[entries[i - 1].2 as usize];
+                continue;
+            }
+            new_indices[*index as usize] = next_index;
+            next_index += match ConstantPoolTag::from_u8(entry[0])? {
+                ConstantPoolTag::Long | ConstantPoolTag::Double => 2,
+                _ => 1,
+            };
+        }
+        entries.dedup_by(|entry, previous| entry.1 == previous.1);
+        let mut new_bootstrap_methods = vec![0u16; bootstrap_methods.len()];
+        let mut next_index = 0;
+        for (i, (key, index)) in bootstrap_method_order.iter().enumerate() {
+            if i != 0 && bootstrap_method_order[i - 1].0 == *key {
+                new_bootstrap_methods[*index] =
+                    new_bootstrap_methods[bootstrap_method_order[i - 1].1];
+                continue;
+            }
+            new_bootstrap_methods[*index] = next_index;
+            next_index += 1;
+        }
+        bootstrap_method_order.dedup_by(|method, previous| method.0 == previous.0);
+
+        let mut symbols = SymbolTable::default();
+        for (_, _, _, entry) in entries {
+            let mut entry = entry.to_vec();
+            let tag = ConstantPoolTag::from_u8(entry[0])?;
+            for &offset in constant_references(tag) {
+                let index = u16::from_be_bytes([entry[offset], entry[offset + 1]]);
+                entry[offset..offset + 2]
+                    .copy_from_slice(&new_indices[index as usize].to_be_bytes());
+            }
+            if matches!(
+                tag,
+                ConstantPoolTag::Dynamic | ConstantPoolTag::InvokeDynamic
+            ) {
+                let index = u16::from_be_bytes([entry[1], entry[2]]);
+                entry[1..3].copy_from_slice(&new_bootstrap_methods[index as usize].to_be_bytes());
+            }
+            symbols.constant_pool.add_raw(&entry)?;
+        }
+        for (_, index) in bootstrap_method_order {
+            let (handle, arguments) = &bootstrap_methods[index];
+            let mut data = Vec::with_capacity(4 + arguments.len() * 2);
+            data.put_u16(new_indices[*handle as usize]);
+            data.put_u16(table_len("bootstrap method arguments", arguments.len())?);
+            for &argument in arguments {
+                data.put_u16(new_indices[argument as usize]);
+            }
+            symbols.bootstrap_methods.extend_from_slice(&data);
+            symbols
+                .bootstrap_method_indices
+                .insert(data, symbols.bootstrap_method_count);
+            increment_count("bootstrap methods", &mut symbols.bootstrap_method_count)?;
+        }
+        Ok(symbols)
+    }
+
+    fn is_copied_from(&mut self, class: &[u8]) -> bool {
+        let address = class.as_ptr() as usize;
+        if self.copied_class_address == Some(address) {
+            return true;
+        }
+        if self.copied_class.as_deref() != Some(class) {
+            return false;
+        }
+        self.copied_class_address = Some(address);
+        true
+    }
+
+    fn require(&self, construct: VersionedConstruct) -> ClassFileResult<()> {
+        if self.major_version < construct.required_version() {
+            return Err(ClassFileError::VersionTooOld {
+                construct,
+                major_version: self.major_version,
+            });
+        }
+        Ok(())
+    }
+
+    fn constant_dynamic(&mut self, constant: &ConstantDynamic) -> ClassFileResult<u16> {
+        self.require(VersionedConstruct::ConstantDynamic)?;
+        let bootstrap_method = self.bootstrap_method(
+            &constant.bootstrap_method,
+            &constant.bootstrap_method_arguments,
+        )?;
+        self.constant_pool
+            .dynamic(bootstrap_method, &constant.name, &constant.desc)
+    }
+
+    fn ldc_constant(&mut self, constant: &LdcConstant) -> ClassFileResult<u16> {
+        match constant {
+            LdcConstant::Integer(value) => self.integer(*value),
+            LdcConstant::Float(value) => self.float(*value),
+            LdcConstant::Long(value) => self.long(*value),
+            LdcConstant::Double(value) => self.double(*value),
+            LdcConstant::String(value) => self.string(value),
+            LdcConstant::Class(name) => self.class(name),
+            LdcConstant::MethodType(desc) => {
+                self.require(VersionedConstruct::MethodHandleConstant)?;
+                self.method_type(desc)
+            }
+            LdcConstant::Handle(handle) => {
+                self.require(VersionedConstruct::MethodHandleConstant)?;
+                self.method_handle(handle)
+            }
+            LdcConstant::ConstantDynamic(constant) => self.constant_dynamic(constant),
+        }
+    }
+
+    fn field_value(&mut self, value: &FieldValue) -> ClassFileResult<u16> {
+        match value {
+            FieldValue::Integer(value) => self.integer(*value),
+            FieldValue::Float(value) => self.float(*value),
+            FieldValue::Long(value) => self.long(*value),
+            FieldValue::Double(value) => self.double(*value),
+            FieldValue::String(value) => self.string(value),
+        }
+    }
+
+    fn bootstrap_method_argument(
+        &mut self,
+        argument: &BootstrapMethodArgument,
+    ) -> ClassFileResult<u16> {
+        match argument {
+            BootstrapMethodArgument::Integer(value) => self.integer(*value),
+            BootstrapMethodArgument::Float(value) => self.float(*value),
+            BootstrapMethodArgument::Long(value) => self.long(*value),
+            BootstrapMethodArgument::Double(value) => self.double(*value),
+            BootstrapMethodArgument::String(value) => self.string(value),
+            BootstrapMethodArgument::Class(name) => self.class(name),
+            BootstrapMethodArgument::Handle(handle) => self.method_handle(handle),
+            BootstrapMethodArgument::ConstantDynamic(constant) => self.constant_dynamic(constant),
+        }
+    }
+
+    fn bootstrap_method(
+        &mut self,
+        handle: &Handle,
+        arguments: &[BootstrapMethodArgument],
+    ) -> ClassFileResult<u16> {
+        let handle = self.method_handle(handle)?;
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.bootstrap_method_argument(argument))
+            .collect::<ClassFileResult<Vec<_>>>()?;
+
+        let mut data = Vec::with_capacity(4 + arguments.len() * 2);
+        data.put_u16(handle);
+        data.put_u16(table_len("bootstrap method arguments", arguments.len())?);
+        for argument in arguments {
+            data.put_u16(argument);
+        }
+        if let Some(&index) = self.bootstrap_method_indices.get(&data) {
+            return Ok(index);
+        }
+        self.bootstrap_methods.extend_from_slice(&data);
+
+        let index = self.bootstrap_method_count;
+        increment_count("bootstrap methods", &mut self.bootstrap_method_count)?;
+        self.bootstrap_method_indices.insert(data, index);
+        Ok(index)
+    }
+}
+
+/// Computes keys which sort constant pool entries and bootstrap methods by their contents,
+/// independently of their indices.
+struct SortKeys<'a, 'class> {
+    constant_pool: &'a ConstantPool<'class>,
+    bootstrap_methods: &'a [(u16, Vec<u16>)],
+    entry_keys: Vec<Option<Vec<u8>>>,
+    bootstrap_method_keys: Vec<Option<Vec<u8>>>,
+    /// Guards against dynamic constants which depend on themselves.
+    depth: usize,
+}
+
+impl SortKeys<'_, '_> {
+    fn entry(&mut self, index: u16) -> ClassFileResult<Vec<u8>> {
+        if let Some(Some(key)) = self.entry_keys.get(index as usize) {
+            return Ok(key.clone());
+        }
+        let entry = self
+            .constant_pool
+            .raw_entry(index)?
+            .ok_or(ClassFileError::BadConstantPoolIndexNoEntry(index))?;
+        let tag = ConstantPoolTag::from_u8(entry[0])?;
+        self.enter()?;
+        let mut key = vec![entry[0]];
+        match tag {
+            ConstantPoolTag::Utf8
+            | ConstantPoolTag::Integer
+            | ConstantPoolTag::Float
+            | ConstantPoolTag::Long
+            | ConstantPoolTag::Double => key.extend_from_slice(&entry[1..]),
+            ConstantPoolTag::MethodHandle => key.push(entry[1]),
+            ConstantPoolTag::Dynamic | ConstantPoolTag::InvokeDynamic => {
+                let bootstrap_method =
+                    self.bootstrap_method(u16::from_be_bytes([entry[1], entry[2]]))?;
+                put_sort_key(&mut key, &bootstrap_method);
+            }
+            _ => {}
+        }
+        for &offset in constant_references(tag) {
+            let reference = self.entry(u16::from_be_bytes([entry[offset], entry[offset + 1]]))?;
+            put_sort_key(&mut key, &reference);
+        }
+        self.depth -= 1;
+        self.entry_keys[index as usize] = Some(key.clone());
+        Ok(key)
+    }
+
+    fn bootstrap_method(&mut self, index: u16) -> ClassFileResult<Vec<u8>> {
+        let bootstrap_methods = self.bootstrap_methods;
+        let Some((handle, arguments)) = bootstrap_methods.get(index as usize) else {
+            return Err(ClassFileError::BootstrapMethodOutOfBounds {
+                index,
+                len: bootstrap_methods.len() as u16,
+            });
+        };
+        if let Some(key) = &self.bootstrap_method_keys[index as usize] {
+            return Ok(key.clone());
+        }
+        self.enter()?;
+        let mut key = Vec::new();
+        put_sort_key(&mut key, &self.entry(*handle)?);
+        for &argument in arguments {
+            put_sort_key(&mut key, &self.entry(argument)?);
+        }
+        self.depth -= 1;
+        self.bootstrap_method_keys[index as usize] = Some(key.clone());
+        Ok(key)
+    }
+
+    fn enter(&mut self) -> ClassFileResult<()> {
+        self.depth += 1;
+        if self.depth > self.entry_keys.len() + self.bootstrap_method_keys.len() {
+            return Err(ClassFileError::BootstrapMethodCircularDependency);
+        }
+        Ok(())
+    }
+}
+
+/// Appends a nested key, prefixed with its length so that keys of different structure can't be
+/// confused.
+fn put_sort_key(key: &mut Vec<u8>, nested: &[u8]) {
+    key.put_u32(nested.len() as u32);
+    key.extend_from_slice(nested);
+}
+
+/// Converts the length of a table to the `u16` count that's written before it.
+fn table_len(what: &'static str, len: usize) -> ClassFileResult<u16> {
+    u16::try_from(len).map_err(|_| ClassFileError::TooMany {
+        what,
+        len,
+        max: u16::MAX as usize,
+    })
+}
+
+/// Adds an entry to the `u16` count of a table.
+fn increment_count(what: &'static str, count: &mut u16) -> ClassFileResult<()> {
+    *count = table_len(what, *count as usize + 1)?;
+    Ok(())
+}
+
+trait ByteVecExt {
+    fn put_u8(&mut self, value: u8);
+    fn put_u16(&mut self, value: u16);
+    fn put_u32(&mut self, value: u32);
+    fn put_u64(&mut self, value: u64);
+}
+
+impl ByteVecExt for Vec<u8> {
+    fn put_u8(&mut self, value: u8) {
+        self.push(value);
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_u64(&mut self, value: u64) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{ClassNode, FieldNode, TypeAnnotationNode};
+    use crate::{
+        buffer_class_events, Attribute, AttributeReader, BootstrapMethodArgument,
+        BufferedClassEvents, BufferedEventProviders, ClassAccess, ClassBuffer, ClassClassEvent,
+        ClassEvent, ClassEventSource, ClassFileError, ClassFileResult, ClassMethodEvent,
+        ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags, ConstantPoolBuilder,
+        EventBuffer, FieldAccess, FieldValue, Handle, HandleKind, LabelCreator, MethodAccess,
+        MethodEvent, MethodTryCatchBlockAnnotationEvent, ModuleEvent, ModuleHashEvent,
+        ModuleHashesEvent, ModuleRequireAccess, ModuleRequireEvent, ModuleResolution, Opcode,
+        SimpleClassHierarchy, TypeReference, VersionedConstruct, LATEST_MAJOR_VERSION,
+    };
+    use java_string::{JavaStr, JavaString};
+    use std::any::Any;
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    fn method_events(bytecode: &[u8]) -> Vec<String> {
+        let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+        let mut result = Vec::new();
+        for event in reader.events().unwrap() {
+            let ClassEvent::Methods(methods) = event.unwrap() else {
+                continue;
+            };
+            for method in methods {
+                let method = method.unwrap();
+                result.push(format!("{} {}", method.name, method.desc));
+                for event in method.events {
+                    result.push(match event.unwrap() {
+                        MethodEvent::LocalVariables(local_variables) => format!(
+                            "{:?}",
+                            local_variables.into_iter().collect::<Result<Vec<_>, _>>()
+                        ),
+                        MethodEvent::TryCatchBlocks(try_catch_blocks) => format!(
+                            "{:?}",
+                            try_catch_blocks.into_iter().collect::<Result<Vec<_>, _>>()
+                        ),
+                        MethodEvent::Code { .. } => "code".to_owned(),
+                        event @ (MethodEvent::Frame(_)
+                        | MethodEvent::Insn(_)
+                        | MethodEvent::BIPushInsn(_)
+                        | MethodEvent::SIPushInsn(_)
+                        | MethodEvent::NewArrayInsn(_)
+                        | MethodEvent::VarInsn { .. }
+                        | MethodEvent::TypeInsn { .. }
+                        | MethodEvent::FieldInsn { .. }
+                        | MethodEvent::MethodInsn { .. }
+                        | MethodEvent::InvokeDynamicInsn { .. }
+                        | MethodEvent::JumpInsn { .. }
+                        | MethodEvent::Label(_)
+                        | MethodEvent::LdcInsn(_)
+                        | MethodEvent::IIncInsn { .. }
+                        | MethodEvent::TableSwitchInsn { .. }
+                        | MethodEvent::LookupSwitchInsn { .. }
+                        | MethodEvent::MultiANewArrayInsn { .. }
+                        | MethodEvent::LineNumber { .. }
+                        | MethodEvent::Maxs(_)) => format!("{event:?}"),
+                        _ => continue,
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    fn test_round_trip(bytecode: &[u8], flags: ClassWriterFlags) {
+        let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+        let written = ClassWriter::new(flags).write(&reader).unwrap();
+        assert_eq!(method_events(bytecode), method_events(&written));
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        assert_eq!(written, ClassWriter::new(flags).write(&reader).unwrap());
+    }
+
+    #[test]
+    fn test_write_hello_world() {
+        test_round_trip(include_class!("HelloWorld"), ClassWriterFlags::None);
+    }
+
+    #[test]
+    fn test_write_code() {
+        test_round_trip(include_class!("TestCode"), ClassWriterFlags::None);
+    }
+
+    #[test]
+    fn test_write_annotations() {
+        test_round_trip(include_class!("TestAnnotations"), ClassWriterFlags::None);
+    }
+
+    #[test]
+    fn test_write_try_catch_block_annotations() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let mut node = ClassNode::from_events(&reader).unwrap();
+        let method = node
+            .methods
+            .iter_mut()
+            .find(|method| !method.try_catch_blocks.is_empty())
+            .unwrap();
+        let annotation = TypeAnnotationNode {
+            type_ref: TypeReference::ExceptionParameter,
+            type_path: "".parse().unwrap(),
+            desc: JavaStr::from_str("LInvisibleTypeAnnotation;").into(),
+            values: Vec::new(),
+        };
+        method
+            .try_catch_block_annotations
+            .push(MethodTryCatchBlockAnnotationEvent {
+                try_catch_block_index: 0,
+                visible: false,
+                annotation,
+            });
+        let expected = method.try_catch_block_annotations.clone();
+        let name = method.name.clone();
+
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(&node)
+            .unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let node = ClassNode::from_events(&reader).unwrap();
+        let method = node
+            .methods
+            .iter()
+            .find(|method| method.name == name)
+            .unwrap();
+        assert_eq!(expected, method.try_catch_block_annotations);
+    }
+
+    #[test]
+    fn test_write_record_and_nest() {
+        let bytecodes: [&[u8]; 3] = [
+            include_class!("TestRecord"),
+            include_class!("TestSealedClass"),
+            include_class!("TestSealedClass$Foo"),
+        ];
+        for bytecode in bytecodes {
+            let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+            let expected = format!("{:?}", buffer_class_events(&reader).unwrap());
+            let written = ClassWriter::new(ClassWriterFlags::None)
+                .write(&reader)
+                .unwrap();
+            let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+            assert_eq!(
+                expected,
+                format!("{:?}", buffer_class_events(&reader).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let bytecode = include_class!("TestCode");
+        let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+        // moving the source file to the end adds its constants and attribute last
+        let reordered = |flags| {
+            let mut events = buffer_class_events(&reader).unwrap();
+            let source = events
+                .0
+                .iter()
+                .position(|event| matches!(event, ClassEvent::Source(_)))
+                .unwrap();
+            let source = events.0.remove(source);
+            events.0.push(source);
+            ClassWriter::new(flags).write(events).unwrap()
+        };
+
+        assert_ne!(
+            ClassWriter::new(ClassWriterFlags::None)
+                .write(&reader)
+                .unwrap(),
+            reordered(ClassWriterFlags::None)
+        );
+        let written = ClassWriter::new(ClassWriterFlags::Deterministic)
+            .write(&reader)
+            .unwrap();
+        assert_eq!(written, reordered(ClassWriterFlags::Deterministic));
+        assert_eq!(method_events(bytecode), method_events(&written));
+    }
+
+    #[test]
+    fn test_version_too_old() {
+        let bytecodes: [(&[u8], _); 3] = [
+            (
+                include_class!("TestCode"),
+                VersionedConstruct::InvokeDynamic,
+            ),
+            (include_class!("TestRecord"), VersionedConstruct::Record),
+            (
+                include_class!("TestSealedClass"),
+                VersionedConstruct::PermittedSubclasses,
+            ),
+        ];
+        for (bytecode, construct) in bytecodes {
+            let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+            let mut events = buffer_class_events(&reader).unwrap();
+            let major_version = construct.required_version() - 1;
+            let Some(ClassEvent::Class(class)) = events.0.first_mut() else {
+                panic!("expected class event");
+            };
+            class.major_version = major_version;
+            assert_eq!(
+                Err(ClassFileError::VersionTooOld {
+                    construct,
+                    major_version
+                }),
+                ClassWriter::new(ClassWriterFlags::None).write(events)
+            );
+        }
+    }
+
+    #[test]
+    fn test_preview() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        assert!(!reader.is_preview());
+        let mut writer = ClassWriter::new(ClassWriterFlags::None);
+        writer.set_preview(true);
+        assert_eq!(
+            Err(ClassFileError::PreviewNotLatestVersion(
+                reader.major_version()
+            )),
+            writer.write(&reader)
+        );
+
+        let mut events = buffer_class_events(&reader).unwrap();
+        let Some(ClassEvent::Class(class)) = events.0.first_mut() else {
+            panic!("expected class event");
+        };
+        class.major_version = LATEST_MAJOR_VERSION;
+        let written = writer.write(events).unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        assert!(reader.is_preview());
+        let Some(Ok(ClassEvent::Class(class))) = reader.events().unwrap().next() else {
+            panic!("expected class event");
+        };
+        assert!(class.is_preview());
+    }
+
+    #[test]
+    fn test_compute_maxs() {
+        const CLASSES: [&[u8]; 3] = [
+            include_class!("HelloWorld"),
+            include_class!("TestCode"),
+            include_class!("TestAnnotations"),
+        ];
+        for bytecode in CLASSES {
+            test_round_trip(bytecode, ClassWriterFlags::ComputeMaxs);
+        }
+    }
+
+    #[test]
+    fn test_write_to() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class_writer = ClassWriter::new(ClassWriterFlags::None);
+        let mut output = Vec::new();
+        class_writer.write_to(&reader, &mut output).unwrap();
+        assert_eq!(class_writer.write(&reader).unwrap(), output);
+
+        let mut output = [0; 16];
+        assert!(matches!(
+            class_writer.write_to(&reader, &mut &mut output[..]),
+            Err(ClassFileError::Io {
+                kind: std::io::ErrorKind::WriteZero,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_too_many_table_entries() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let mut node = ClassNode::from_events(&reader).unwrap();
+        node.interfaces = vec![Cow::Borrowed(JavaStr::from_str("Foo")); 65536];
+        assert!(matches!(
+            ClassWriter::new(ClassWriterFlags::None).write(&node),
+            Err(ClassFileError::TooMany {
+                what: "interfaces",
+                len: 65536,
+                max: 65535,
+            })
+        ));
+
+        node.interfaces.clear();
+        node.nest_members = vec![Cow::Borrowed(JavaStr::from_str("Foo")); 65536];
+        assert!(matches!(
+            ClassWriter::new(ClassWriterFlags::None).write(&node),
+            Err(ClassFileError::TooMany {
+                what: "nest members",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_constant_value_mismatch() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let mut node = ClassNode::from_events(&reader).unwrap();
+        let mut field = FieldNode::new(
+            FieldAccess::Private | FieldAccess::Final,
+            JavaStr::from_str("x"),
+            JavaStr::from_str("I"),
+        );
+        field.value = Some(FieldValue::Long(1));
+        node.fields.push(field);
+
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(&node)
+            .unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::Strict).unwrap();
+        assert_eq!(
+            Some(FieldValue::Long(1)),
+            ClassNode::from_events(&reader).unwrap().fields[0].value
+        );
+
+        node.fields[0].access |= FieldAccess::Static;
+        assert!(matches!(
+            ClassWriter::new(ClassWriterFlags::None).write(&node),
+            Err(ClassFileError::ConstantValueMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_copy_constant_pool() {
+        const CLASSES: [&[u8]; 4] = [
+            include_class!("HelloWorld"),
+            include_class!("TestCode"),
+            include_class!("TestAnnotations"),
+            include_class!("TestDeprecated"),
+        ];
+        for bytecode in CLASSES {
+            let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+            let mut class_writer = ClassWriter::new(ClassWriterFlags::None);
+            class_writer.copy_constant_pool(&reader).unwrap();
+            assert_eq!(bytecode, class_writer.write(&reader).unwrap());
+        }
+    }
+
+    /// Renames the `loops` method, signalling the other methods as unchanged if `unchanged` is set.
+    fn rename_loops<'class>(
+        reader: &ClassReader<'class>,
+        unchanged: bool,
+    ) -> BufferedClassEvents<'class> {
+        let mut unchanged_events = Vec::new();
+        for event in reader.events().unwrap() {
+            if let ClassEvent::Methods(methods) = event.unwrap() {
+                for method in methods {
+                    unchanged_events.push(method.unwrap().events.unchanged());
+                }
+            }
+        }
+
+        let mut events = buffer_class_events(reader).unwrap();
+        for event in &mut events.0 {
+            let ClassEvent::Methods(methods) = event else {
+                continue;
+            };
+            for (method, unchanged_event) in methods.0.iter_mut().zip(&unchanged_events) {
+                if method.name.as_ref() == "loops" {
+                    method.name = Cow::Owned(JavaString::from("renamedLoops"));
+                } else if unchanged {
+                    method.events = EventBuffer(vec![MethodEvent::Unchanged(*unchanged_event)]);
+                }
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_write_unchanged_methods() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+
+        let mut copying_writer = ClassWriter::new(ClassWriterFlags::None);
+        copying_writer.copy_constant_pool(&reader).unwrap();
+        let expected = copying_writer.write(rename_loops(&reader, false)).unwrap();
+        let written = copying_writer.write(rename_loops(&reader, true)).unwrap();
+        assert_eq!(expected, written);
+        assert!(method_events(&written).contains(&"renamedLoops ([I)D".to_owned()));
+
+        // without the copied constant pool, unchanged methods are read again
+        let class_writer = ClassWriter::new(ClassWriterFlags::None);
+        let expected = class_writer.write(rename_loops(&reader, false)).unwrap();
+        let written = class_writer.write(rename_loops(&reader, true)).unwrap();
+        assert_eq!(expected, written);
+    }
+
+    #[test]
+    fn test_widen_jumps() {
+        let label_creator = LabelCreator::default();
+        let far = label_creator.create_label();
+        let mut events = vec![
+            MethodEvent::Code {
+                label_creator: label_creator.clone(),
+            },
+            MethodEvent::VarInsn {
+                opcode: Opcode::ILoad,
+                var_index: 0,
+            },
+            MethodEvent::JumpInsn {
+                opcode: Opcode::IfEq,
+                label: far,
+            },
+        ];
+        events.extend((0..40000).map(|_| MethodEvent::Insn(Opcode::Nop)));
+        events.extend([
+            MethodEvent::Insn(Opcode::IConst1),
+            MethodEvent::Insn(Opcode::IReturn),
+            MethodEvent::Label(far),
+            MethodEvent::Insn(Opcode::IConst2),
+            MethodEvent::Insn(Opcode::IReturn),
+        ]);
+        let events: Vec<ClassFileResult<ClassEvent<'_, BufferedEventProviders>>> = vec![
+            Ok(ClassEvent::Class(ClassClassEvent {
+                major_version: 52,
+                minor_version: 0,
+                access: ClassAccess::Public | ClassAccess::Super,
+                name: Cow::Borrowed(JavaStr::from_str("Widen")),
+                signature: None,
+                super_name: Some(Cow::Borrowed(JavaStr::from_str("java/lang/Object"))),
+                interfaces: Vec::new(),
+            })),
+            Ok(ClassEvent::Methods(EventBuffer(vec![ClassMethodEvent {
+                access: MethodAccess::Public | MethodAccess::Static,
+                name: Cow::Borrowed(JavaStr::from_str("widen")),
+                desc: Cow::Borrowed(JavaStr::from_str("(I)I")),
+                signature: None,
+                exceptions: Vec::new(),
+                events: EventBuffer(events),
+            }]))),
+        ];
+        let written = ClassWriter::new(ClassWriterFlags::ComputeFrames)
+            .write(events)
+            .unwrap();
+
+        let events = method_events(&written);
+        assert_eq!("JumpInsn { opcode: IfNe, label: Label(0) }", events[3]);
+        assert!(events[4].starts_with("JumpInsn { opcode: Goto,"));
+        // the instruction after the goto_w is now a jump target, so needs a frame
+        assert_eq!("Label(Label(0))", events[5]);
+        assert!(events[6].starts_with("Frame("));
+    }
+
+    #[test]
+    fn test_dedup_bootstrap_methods() {
+        let concat = |recipe: &'static str| {
+            MethodEvent::InvokeDynamicInsn {
+            name: Cow::Borrowed(JavaStr::from_str("makeConcatWithConstants")),
+            desc: Cow::Borrowed(JavaStr::from_str(
+                "(Ljava/lang/String;)Ljava/lang/String;",
+            )),
+            bootstrap_method_handle: Handle {
+                kind: HandleKind::InvokeStatic,
+                owner: Cow::Borrowed(JavaStr::from_str("java/lang/invoke/StringConcatFactory")),
+                name: Cow::Borrowed(JavaStr::from_str("makeConcatWithConstants")),
+                desc: Cow::Borrowed(JavaStr::from_str("(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/String;[Ljava/lang/Object;)Ljava/lang/invoke/CallSite;")),
+                is_interface: false,
+            },
+            bootstrap_method_arguments: vec![BootstrapMethodArgument::String(Cow::Borrowed(
+                JavaStr::from_str(recipe),
+            ))],
+        }
+        };
+        let events: Vec<ClassFileResult<ClassEvent<'_, BufferedEventProviders>>> = vec![
+            Ok(ClassEvent::Class(ClassClassEvent {
+                major_version: 55,
+                minor_version: 0,
+                access: ClassAccess::Public | ClassAccess::Super,
+                name: Cow::Borrowed(JavaStr::from_str("Concat")),
+                signature: None,
+                super_name: Some(Cow::Borrowed(JavaStr::from_str("java/lang/Object"))),
+                interfaces: Vec::new(),
+            })),
+            Ok(ClassEvent::Methods(EventBuffer(vec![ClassMethodEvent {
+                access: MethodAccess::Public | MethodAccess::Static,
+                name: Cow::Borrowed(JavaStr::from_str("concat")),
+                desc: Cow::Borrowed(JavaStr::from_str("(Ljava/lang/String;)Ljava/lang/String;")),
+                signature: None,
+                exceptions: Vec::new(),
+                events: EventBuffer(vec![
+                    MethodEvent::Code {
+                        label_creator: LabelCreator::default(),
+                    },
+                    MethodEvent::VarInsn {
+                        opcode: Opcode::ALoad,
+                        var_index: 0,
+                    },
+                    concat("\u{1}!"),
+                    concat("\u{1}!"),
+                    concat("\u{1}?"),
+                    concat("\u{1}!"),
+                    MethodEvent::Insn(Opcode::AReturn),
+                ]),
+            }]))),
+        ];
+        let written = ClassWriter::new(ClassWriterFlags::ComputeMaxs)
+            .write(events)
+            .unwrap();
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let (_, bootstrap_methods) = reader
+            .raw_attributes()
+            .unwrap()
+            .into_iter()
+            .find(|&(name, _)| {
+                reader.constant_pool.get_utf8_as_bytes(name) == Ok(b"BootstrapMethods")
+            })
+            .unwrap();
+        assert_eq!(2, bootstrap_methods.read_u16(0).unwrap());
+        let recipes: Vec<_> = method_events(&written)
+            .into_iter()
+            .filter(|event| event.starts_with("InvokeDynamicInsn"))
+            .map(|event| event.contains(r#"String("\u{1}!")"#))
+            .collect();
+        assert_eq!(vec![true, true, false, true], recipes);
+    }
+
+    #[test]
+    fn test_write_module() {
+        let reader =
+            ClassReader::new(include_class!("module-info"), ClassReaderFlags::None).unwrap();
+        let mut events = buffer_class_events(&reader).unwrap();
+        for event in &mut events.0 {
+            if let ClassEvent::Module(module) = event {
+                module.events.0.splice(
+                    0..0,
+                    [
+                        ModuleEvent::MainClass(Cow::Borrowed(JavaStr::from_str(
+                            "pkg/ClassInPackage",
+                        ))),
+                        ModuleEvent::Packages(EventBuffer(vec![
+                            Cow::Borrowed(JavaStr::from_str("pkg")),
+                            Cow::Borrowed(JavaStr::from_str("pkg2")),
+                        ])),
+                        ModuleEvent::Target(Cow::Borrowed(JavaStr::from_str("linux-amd64"))),
+                        ModuleEvent::Resolution(
+                            ModuleResolution::DoNotResolveByDefault
+                                | ModuleResolution::WarnIncubating,
+                        ),
+                        ModuleEvent::Hashes(ModuleHashesEvent {
+                            algorithm: Cow::Borrowed(JavaStr::from_str("SHA-256")),
+                            hashes: vec![ModuleHashEvent {
+                                module: Cow::Borrowed(JavaStr::from_str("java.base")),
+                                hash: Cow::Owned((0..32).collect()),
+                            }],
+                        }),
+                    ],
+                );
+            }
+        }
+        let expected = format!("{events:?}");
+
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(events.clone())
+            .unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        assert_eq!(
+            expected,
+            format!("{:?}", buffer_class_events(&reader).unwrap())
+        );
+
+        for event in &mut events.0 {
+            if let ClassEvent::Module(module) = event {
+                for event in &mut module.events.0 {
+                    if let ModuleEvent::Hashes(hashes) = event {
+                        hashes.hashes[0].hash = Cow::Owned(vec![0; 65536]);
+                    }
+                }
+            }
+        }
+        assert!(matches!(
+            ClassWriter::new(ClassWriterFlags::None).write(events.clone()),
+            Err(ClassFileError::TooMany { len: 65536, .. })
+        ));
+
+        for event in &mut events.0 {
+            if let ClassEvent::Module(module) = event {
+                module.events.0.retain(|event| !event.is_hashes());
+                module.events.0.push(ModuleEvent::Requires(
+                    (0..65536)
+                        .map(|_| ModuleRequireEvent {
+                            module: Cow::Borrowed(JavaStr::from_str("java.base")),
+                            access: ModuleRequireAccess::empty(),
+                            version: None,
+                        })
+                        .collect(),
+                ));
+            }
+        }
+        assert!(matches!(
+            ClassWriter::new(ClassWriterFlags::None).write(events),
+            Err(ClassFileError::TooMany {
+                what: "module requires",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_compute_frames() {
+        let mut class_writer = ClassWriter::new(ClassWriterFlags::ComputeFrames);
+        let mut class_hierarchy = SimpleClassHierarchy::new();
+        for (name, super_class) in [
+            ("java/lang/Throwable", "java/lang/Object"),
+            ("java/lang/Exception", "java/lang/Throwable"),
+            ("java/lang/RuntimeException", "java/lang/Exception"),
+            ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+            (
+                "java/lang/NullPointerException",
+                "java/lang/RuntimeException",
+            ),
+        ] {
+            class_hierarchy.insert(name, Some(super_class.into()), false);
+        }
+        class_writer.set_class_hierarchy(class_hierarchy);
+
+        let bytecode = include_class!("TestCode");
+        let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+        let written = class_writer.write(&reader).unwrap();
+
+        // javac chops locals which go out of scope, which the computed frames don't
+        let without_frames = |bytecode| {
+            method_events(bytecode)
+                .into_iter()
+                .map(|event| {
+                    if event.starts_with("Frame(") {
+                        "Frame".to_owned()
+                    } else {
+                        event
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(without_frames(bytecode), without_frames(&written));
+        assert!(method_events(&written).contains(
+            &r#"Frame(Same1 { stack_value: Class("java/lang/RuntimeException") })"#.to_owned()
+        ));
+    }
+
+    #[derive(Debug, Clone)]
+    struct ClassRefAttribute(JavaString);
+
+    impl Attribute for ClassRefAttribute {
+        fn name(&self) -> &JavaStr {
+            JavaStr::from_str("ClassRef")
+        }
+
+        fn copy(&self) -> Box<dyn Attribute> {
+            Box::new(self.clone())
+        }
+
+        fn write(&self, constant_pool: &mut ConstantPoolBuilder) -> ClassFileResult<Vec<u8>> {
+            Ok(constant_pool.class(&self.0)?.to_be_bytes().to_vec())
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct ClassRefAttributeReader;
+
+    impl AttributeReader for ClassRefAttributeReader {
+        fn read<'class>(
+            &self,
+            _name: &JavaStr,
+            reader: &ClassReader<'class>,
+            data: ClassBuffer<'class>,
+        ) -> ClassFileResult<Box<dyn Attribute>> {
+            let class = reader.constant_pool.get_class(data.read_u16(0)?)?;
+            Ok(Box::new(ClassRefAttribute(class.into_owned())))
+        }
+
+        fn copy(&self) -> Box<dyn AttributeReader> {
+            Box::new(self.clone())
+        }
+    }
+
+    fn class_ref_attributes(bytecode: &[u8]) -> Vec<String> {
+        let mut reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+        reader.add_attribute_reader("ClassRef", ClassRefAttributeReader);
+        let mut result = Vec::new();
+        for event in reader.events().unwrap() {
+            if let ClassEvent::Attributes(attributes) = event.unwrap() {
+                for attribute in attributes {
+                    let attribute: Box<dyn Any> = attribute.unwrap();
+                    result.push(
+                        attribute
+                            .downcast::<ClassRefAttribute>()
+                            .unwrap()
+                            .0
+                            .to_string(),
+                    );
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_write_custom_attributes() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let mut events = buffer_class_events(&reader).unwrap();
+        let attribute: Box<dyn Attribute> =
+            Box::new(ClassRefAttribute(JavaString::from("java/util/List")));
+        events
+            .0
+            .push(ClassEvent::Attributes(EventBuffer(vec![attribute])));
+        let class_writer = ClassWriter::new(ClassWriterFlags::None);
+        let written = class_writer.write(events.clone()).unwrap();
+        assert_eq!(vec!["java/util/List"], class_ref_attributes(&written));
+
+        // without an attribute reader, the constant pool indices are only valid if it's copied
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let mut copying_writer = ClassWriter::new(ClassWriterFlags::None);
+        copying_writer.copy_constant_pool(&reader).unwrap();
+        let rewritten = copying_writer.write(&reader).unwrap();
+        assert_eq!(vec!["java/util/List"], class_ref_attributes(&rewritten));
+
+        // sorting the constant pool writes the attributes again
+        let deterministic_writer = ClassWriter::new(ClassWriterFlags::Deterministic);
+        let sorted = deterministic_writer.write(events.clone()).unwrap();
+        assert_eq!(vec!["java/util/List"], class_ref_attributes(&sorted));
+        assert_eq!(
+            Err(ClassFileError::UnwritableAttribute(JavaString::from(
+                "ClassRef"
+            ))),
+            deterministic_writer.write(&reader)
+        );
+    }
+}
@@ -0,0 +1,696 @@
+use crate::analysis::method_desc_types;
+use crate::{
+    BufferedEventProviders, Label, LabelCreator, LdcConstant, MethodAccess, MethodEvent, Opcode,
+};
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+
+/// A comparison made by a conditional jump, see [`CodeGenerator::if_cmp`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Gt,
+    Le,
+}
+
+impl Comparison {
+    /// The offset of the comparison from the `eq` variant of the conditional jump opcodes.
+    fn opcode_offset(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Generates the code of a method at a higher level than individual instructions, choosing the
+/// shortest encoding for constants. Types are given as descriptors. Local variables are left to
+/// the [`ClassWriter`](crate::ClassWriter), which already writes the short forms such as
+/// `aload_0` where possible.
+///
+/// The generated instructions don't include the [`MethodEvent::Code`] event, nor frames or maxs.
+#[derive(Debug, Clone)]
+pub struct CodeGenerator<'class> {
+    access: MethodAccess,
+    desc: Cow<'class, JavaStr>,
+    label_creator: LabelCreator,
+    events: Vec<MethodEvent<'class, BufferedEventProviders>>,
+    next_local: u16,
+}
+
+impl<'class> CodeGenerator<'class> {
+    /// Creates a generator for a method with the given access and descriptor, creating labels with
+    /// the label creator of the method.
+    pub fn new(
+        access: MethodAccess,
+        desc: impl Into<Cow<'class, JavaStr>>,
+        label_creator: LabelCreator,
+    ) -> Self {
+        let desc = desc.into();
+        let next_local = method_desc_types(&desc)
+            .0
+            .iter()
+            .map(|ty| type_size(ty))
+            .sum::<u16>()
+            + !access.contains(MethodAccess::Static) as u16;
+        CodeGenerator {
+            access,
+            desc,
+            label_creator,
+            events: Vec::new(),
+            next_local,
+        }
+    }
+
+    pub fn events(&self) -> &[MethodEvent<'class, BufferedEventProviders>] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<MethodEvent<'class, BufferedEventProviders>> {
+        self.events
+    }
+
+    /// Removes the events generated so far, keeping the allocated locals.
+    pub(crate) fn take_events(&mut self) -> Vec<MethodEvent<'class, BufferedEventProviders>> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Adds an event which has no helper.
+    pub fn event(&mut self, event: MethodEvent<'class, BufferedEventProviders>) {
+        self.events.push(event);
+    }
+
+    pub fn insn(&mut self, opcode: Opcode) {
+        self.events.push(MethodEvent::Insn(opcode));
+    }
+
+    pub fn new_label(&self) -> Label {
+        self.label_creator.create_label()
+    }
+
+    /// Places a label at the current position.
+    pub fn mark(&mut self, label: Label) {
+        self.events.push(MethodEvent::Label(label));
+    }
+
+    pub fn push_int(&mut self, value: i32) {
+        let event = match value {
+            -1..=5 => MethodEvent::Insn(
+                Opcode::try_from(Opcode::IConstM1 as u8 + (value + 1) as u8).unwrap(),
+            ),
+            _ if i8::try_from(value).is_ok() => MethodEvent::BIPushInsn(value as i8),
+            _ if i16::try_from(value).is_ok() => MethodEvent::SIPushInsn(value as i16),
+            _ => MethodEvent::LdcInsn(LdcConstant::Integer(value)),
+        };
+        self.events.push(event);
+    }
+
+    pub fn push_long(&mut self, value: i64) {
+        self.events.push(match value {
+            0 => MethodEvent::Insn(Opcode::LConst0),
+            1 => MethodEvent::Insn(Opcode::LConst1),
+            _ => MethodEvent::LdcInsn(LdcConstant::Long(value)),
+        });
+    }
+
+    /// Pushes a float, using `fconst` for the exact bit patterns of `0.0`, `1.0` and `2.0`.
+    pub fn push_float(&mut self, value: f32) {
+        self.events.push(match value.to_bits() {
+            bits if bits == 0f32.to_bits() => MethodEvent::Insn(Opcode::FConst0),
+            bits if bits == 1f32.to_bits() => MethodEvent::Insn(Opcode::FConst1),
+            bits if bits == 2f32.to_bits() => MethodEvent::Insn(Opcode::FConst2),
+            _ => MethodEvent::LdcInsn(LdcConstant::Float(value)),
+        });
+    }
+
+    /// Pushes a double, using `dconst` for the exact bit patterns of `0.0` and `1.0`.
+    pub fn push_double(&mut self, value: f64) {
+        self.events.push(match value.to_bits() {
+            bits if bits == 0f64.to_bits() => MethodEvent::Insn(Opcode::DConst0),
+            bits if bits == 1f64.to_bits() => MethodEvent::Insn(Opcode::DConst1),
+            _ => MethodEvent::LdcInsn(LdcConstant::Double(value)),
+        });
+    }
+
+    pub fn push_bool(&mut self, value: bool) {
+        self.push_int(value as i32);
+    }
+
+    pub fn push_null(&mut self) {
+        self.insn(Opcode::AConstNull);
+    }
+
+    pub fn push_string(&mut self, value: impl Into<Cow<'class, JavaStr>>) {
+        self.events
+            .push(MethodEvent::LdcInsn(LdcConstant::String(value.into())));
+    }
+
+    /// Pushes the class object of a type. Primitive types are loaded from the `TYPE` field of
+    /// their wrapper class.
+    pub fn push_class(&mut self, desc: &JavaStr) {
+        if let Some(wrapper) = wrapper_class(desc) {
+            self.get_static(
+                wrapper,
+                JavaStr::from_str("TYPE"),
+                JavaStr::from_str("Ljava/lang/Class;"),
+            );
+            return;
+        }
+        self.events
+            .push(MethodEvent::LdcInsn(LdcConstant::Class(Cow::Owned(
+                internal_name(desc).to_owned(),
+            ))));
+    }
+
+    /// Allocates a new local variable of the given type after the arguments and the previously
+    /// allocated locals, returning its index.
+    pub fn new_local(&mut self, desc: &JavaStr) -> u16 {
+        let index = self.next_local;
+        self.next_local += type_size(desc);
+        index
+    }
+
+    /// Makes sure that new locals are allocated at or after the given index, so that they don't
+    /// clash with the locals of existing code.
+    pub(crate) fn reserve_locals(&mut self, max_locals: u16) {
+        self.next_local = self.next_local.max(max_locals);
+    }
+
+    pub fn load_local(&mut self, var_index: u16, desc: &JavaStr) {
+        self.events.push(MethodEvent::VarInsn {
+            opcode: typed_opcode(Opcode::ILoad, desc),
+            var_index,
+        });
+    }
+
+    pub fn store_local(&mut self, var_index: u16, desc: &JavaStr) {
+        self.events.push(MethodEvent::VarInsn {
+            opcode: typed_opcode(Opcode::IStore, desc),
+            var_index,
+        });
+    }
+
+    /// Loads `this`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method is static.
+    pub fn load_this(&mut self) {
+        assert!(
+            !self.access.contains(MethodAccess::Static),
+            "no this in a static method"
+        );
+        self.load_local(0, JavaStr::from_str("Ljava/lang/Object;"));
+    }
+
+    /// Loads the argument at the given index, not counting `this`.
+    pub fn load_arg(&mut self, index: usize) {
+        let (var_index, desc) = self.arg(index);
+        self.load_local(var_index, &desc);
+    }
+
+    /// Loads all the arguments, not including `this`.
+    pub fn load_args(&mut self) {
+        for index in 0..method_desc_types(&self.desc).0.len() {
+            self.load_arg(index);
+        }
+    }
+
+    pub fn store_arg(&mut self, index: usize) {
+        let (var_index, desc) = self.arg(index);
+        self.store_local(var_index, &desc);
+    }
+
+    /// The local variable index and descriptor of an argument.
+    fn arg(&self, index: usize) -> (u16, JavaString) {
+        let (arguments, _) = method_desc_types(&self.desc);
+        let var_index = arguments[..index]
+            .iter()
+            .map(|ty| type_size(ty))
+            .sum::<u16>()
+            + !self.access.contains(MethodAccess::Static) as u16;
+        (var_index, arguments[index].to_owned())
+    }
+
+    /// Pops a value of the given type.
+    pub fn pop(&mut self, desc: &JavaStr) {
+        match type_size(desc) {
+            0 => {}
+            1 => self.insn(Opcode::Pop),
+            _ => self.insn(Opcode::Pop2),
+        }
+    }
+
+    /// Duplicates a value of the given type.
+    pub fn dup(&mut self, desc: &JavaStr) {
+        match type_size(desc) {
+            0 => {}
+            1 => self.insn(Opcode::Dup),
+            _ => self.insn(Opcode::Dup2),
+        }
+    }
+
+    /// Swaps the top two values, of the given types with the top one last.
+    pub fn swap(&mut self, prev: &JavaStr, top: &JavaStr) {
+        match (type_size(prev), type_size(top)) {
+            (1, 1) => self.insn(Opcode::Swap),
+            (1, _) => {
+                self.insn(Opcode::Dup2X1);
+                self.insn(Opcode::Pop2);
+            }
+            (_, 1) => {
+                self.insn(Opcode::DupX2);
+                self.insn(Opcode::Pop);
+            }
+            _ => {
+                self.insn(Opcode::Dup2X2);
+                self.insn(Opcode::Pop2);
+            }
+        }
+    }
+
+    pub fn return_value(&mut self) {
+        let (_, return_type) = method_desc_types(&self.desc);
+        let opcode = match return_type.as_bytes().first() {
+            Some(b'V') => Opcode::Return,
+            _ => typed_opcode(Opcode::IReturn, return_type),
+        };
+        self.insn(opcode);
+    }
+
+    pub fn new_instance(&mut self, ty: impl Into<Cow<'class, JavaStr>>) {
+        self.type_insn(Opcode::New, ty);
+    }
+
+    /// Creates an instance with `new` and `dup`, then calls the constructor with the given
+    /// descriptor after the arguments pushed by `args`.
+    pub fn construct(
+        &mut self,
+        ty: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+        args: impl FnOnce(&mut Self),
+    ) {
+        let ty = ty.into();
+        self.new_instance(ty.clone());
+        self.insn(Opcode::Dup);
+        args(self);
+        self.invoke_special(ty, JavaStr::from_str("<init>"), desc);
+    }
+
+    pub fn check_cast(&mut self, ty: impl Into<Cow<'class, JavaStr>>) {
+        self.type_insn(Opcode::CheckCast, ty);
+    }
+
+    pub fn instance_of(&mut self, ty: impl Into<Cow<'class, JavaStr>>) {
+        self.type_insn(Opcode::Instanceof, ty);
+    }
+
+    fn type_insn(&mut self, opcode: Opcode, ty: impl Into<Cow<'class, JavaStr>>) {
+        self.events.push(MethodEvent::TypeInsn {
+            opcode,
+            ty: ty.into(),
+        });
+    }
+
+    pub fn get_field(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.field_insn(Opcode::GetField, owner, name, desc);
+    }
+
+    pub fn put_field(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.field_insn(Opcode::PutField, owner, name, desc);
+    }
+
+    pub fn get_static(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.field_insn(Opcode::GetStatic, owner, name, desc);
+    }
+
+    pub fn put_static(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.field_insn(Opcode::PutStatic, owner, name, desc);
+    }
+
+    fn field_insn(
+        &mut self,
+        opcode: Opcode,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.events.push(MethodEvent::FieldInsn {
+            opcode,
+            owner: owner.into(),
+            name: name.into(),
+            desc: desc.into(),
+        });
+    }
+
+    pub fn invoke_static(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.method_insn(Opcode::InvokeStatic, owner, name, desc, false);
+    }
+
+    pub fn invoke_virtual(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.method_insn(Opcode::InvokeVirtual, owner, name, desc, false);
+    }
+
+    pub fn invoke_special(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.method_insn(Opcode::InvokeSpecial, owner, name, desc, false);
+    }
+
+    pub fn invoke_interface(
+        &mut self,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.method_insn(Opcode::InvokeInterface, owner, name, desc, true);
+    }
+
+    fn method_insn(
+        &mut self,
+        opcode: Opcode,
+        owner: impl Into<Cow<'class, JavaStr>>,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+        is_interface: bool,
+    ) {
+        self.events.push(MethodEvent::MethodInsn {
+            opcode,
+            owner: owner.into(),
+            name: name.into(),
+            desc: desc.into(),
+            is_interface,
+        });
+    }
+
+    /// Converts a value of the given type on the stack to an object, boxing primitives with the
+    /// `valueOf` method of their wrapper class. Void pushes `null`.
+    pub fn box_value(&mut self, desc: &JavaStr) {
+        if desc == "V" {
+            self.push_null();
+            return;
+        }
+        let Some(wrapper) = wrapper_class(desc) else {
+            return;
+        };
+        let mut box_desc = JavaString::from("(");
+        box_desc.push_java_str(desc);
+        box_desc.push_str(")L");
+        box_desc.push_java_str(wrapper);
+        box_desc.push(';');
+        self.invoke_static(wrapper, JavaStr::from_str("valueOf"), box_desc);
+    }
+
+    /// Converts an object on the stack to a value of the given type, casting it to the type's
+    /// wrapper class and unboxing primitives. Numeric primitives are unboxed from any
+    /// `java/lang/Number`.
+    pub fn unbox(&mut self, desc: &JavaStr) {
+        let (owner, method) = match desc.as_bytes().first() {
+            Some(b'V') => {
+                self.insn(Opcode::Pop);
+                return;
+            }
+            Some(b'Z') => ("java/lang/Boolean", "booleanValue"),
+            Some(b'C') => ("java/lang/Character", "charValue"),
+            Some(b'B') => ("java/lang/Number", "byteValue"),
+            Some(b'S') => ("java/lang/Number", "shortValue"),
+            Some(b'I') => ("java/lang/Number", "intValue"),
+            Some(b'J') => ("java/lang/Number", "longValue"),
+            Some(b'F') => ("java/lang/Number", "floatValue"),
+            Some(b'D') => ("java/lang/Number", "doubleValue"),
+            _ => {
+                let ty = internal_name(desc);
+                if ty != "java/lang/Object" {
+                    self.check_cast(Cow::Owned(ty.to_owned()));
+                }
+                return;
+            }
+        };
+        let owner = JavaStr::from_str(owner);
+        self.check_cast(owner);
+        let mut unbox_desc = JavaString::from("()");
+        unbox_desc.push_java_str(desc);
+        self.invoke_virtual(owner, JavaStr::from_str(method), unbox_desc);
+    }
+
+    pub fn goto(&mut self, label: Label) {
+        self.jump(Opcode::Goto, label);
+    }
+
+    /// Jumps if the top two values of the given type compare as given, the top value being the
+    /// right-hand side. Comparisons with a NaN float or double are false, except for
+    /// [`Comparison::Ne`], as in Java.
+    ///
+    /// # Panics
+    ///
+    /// Panics if references are compared with anything other than [`Comparison::Eq`] or
+    /// [`Comparison::Ne`].
+    pub fn if_cmp(&mut self, desc: &JavaStr, comparison: Comparison, label: Label) {
+        let compare = match desc.as_bytes().first() {
+            Some(b'J') => Some(Opcode::LCmp),
+            Some(b'F') => Some(match comparison {
+                Comparison::Lt | Comparison::Le => Opcode::FCmpG,
+                _ => Opcode::FCmpL,
+            }),
+            Some(b'D') => Some(match comparison {
+                Comparison::Lt | Comparison::Le => Opcode::DCmpG,
+                _ => Opcode::DCmpL,
+            }),
+            Some(b'L' | b'[') => {
+                let opcode = match comparison {
+                    Comparison::Eq => Opcode::IfACmpEq,
+                    Comparison::Ne => Opcode::IfACmpNe,
+                    _ => panic!("references can only be compared for equality"),
+                };
+                self.jump(opcode, label);
+                return;
+            }
+            _ => None,
+        };
+        match compare {
+            Some(compare) => {
+                self.insn(compare);
+                self.if_zcmp(comparison, label);
+            }
+            None => {
+                let opcode = Opcode::IfICmpEq as u8 + comparison.opcode_offset();
+                self.jump(Opcode::try_from(opcode).unwrap(), label);
+            }
+        }
+    }
+
+    /// Jumps if the int on the top of the stack compares to zero as given.
+    pub fn if_zcmp(&mut self, comparison: Comparison, label: Label) {
+        let opcode = Opcode::IfEq as u8 + comparison.opcode_offset();
+        self.jump(Opcode::try_from(opcode).unwrap(), label);
+    }
+
+    pub fn if_null(&mut self, label: Label) {
+        self.jump(Opcode::IfNull, label);
+    }
+
+    pub fn if_non_null(&mut self, label: Label) {
+        self.jump(Opcode::IfNonNull, label);
+    }
+
+    fn jump(&mut self, opcode: Opcode, label: Label) {
+        self.events.push(MethodEvent::JumpInsn { opcode, label });
+    }
+
+    /// Throws a new exception of the given type, constructed with a message.
+    pub fn throw_exception(
+        &mut self,
+        ty: impl Into<Cow<'class, JavaStr>>,
+        message: impl Into<Cow<'class, JavaStr>>,
+    ) {
+        self.construct(
+            ty,
+            JavaStr::from_str("(Ljava/lang/String;)V"),
+            |generator| generator.push_string(message),
+        );
+        self.insn(Opcode::AThrow);
+    }
+}
+
+/// The number of local variable slots or stack entries taken by a value of the given type.
+fn type_size(desc: &JavaStr) -> u16 {
+    match desc.as_bytes().first() {
+        Some(b'V') => 0,
+        Some(b'J' | b'D') => 2,
+        _ => 1,
+    }
+}
+
+/// The variant of an `int` opcode for the given type, such as `lload` for `iload`.
+fn typed_opcode(int_opcode: Opcode, desc: &JavaStr) -> Opcode {
+    let offset = match desc.as_bytes().first() {
+        Some(b'J') => 1,
+        Some(b'F') => 2,
+        Some(b'D') => 3,
+        Some(b'L' | b'[') => 4,
+        _ => 0,
+    };
+    Opcode::try_from(int_opcode as u8 + offset).unwrap()
+}
+
+fn wrapper_class(desc: &JavaStr) -> Option<&'static JavaStr> {
+    Some(JavaStr::from_str(match desc.as_bytes().first()? {
+        b'Z' => "java/lang/Boolean",
+        b'B' => "java/lang/Byte",
+        b'C' => "java/lang/Character",
+        b'S' => "java/lang/Short",
+        b'I' => "java/lang/Integer",
+        b'J' => "java/lang/Long",
+        b'F' => "java/lang/Float",
+        b'D' => "java/lang/Double",
+        b'V' => "java/lang/Void",
+        _ => return None,
+    }))
+}
+
+/// The internal name of a class type, or the descriptor of an array type.
+fn internal_name(desc: &JavaStr) -> &JavaStr {
+    match desc
+        .strip_prefix('L')
+        .and_then(|desc| desc.strip_suffix(';'))
+    {
+        Some(name) => name,
+        None => desc,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{
+        buffer_class_events, ClassEvent, ClassMethodEvent, ClassReader, ClassReaderFlags,
+        ClassWriter, ClassWriterFlags, CodeGenerator, Comparison, EventBuffer, LabelCreator,
+        LdcConstant, MethodAccess, MethodEvent, Opcode,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_push_int() {
+        let mut generator = CodeGenerator::new(
+            MethodAccess::Static,
+            JavaStr::from_str("()V"),
+            LabelCreator::default(),
+        );
+        for value in [-1, 5, 100, 1000, 100000] {
+            generator.push_int(value);
+        }
+        let events = generator.into_events();
+        assert!(matches!(events[0], MethodEvent::Insn(Opcode::IConstM1)));
+        assert!(matches!(events[1], MethodEvent::Insn(Opcode::IConst5)));
+        assert!(matches!(events[2], MethodEvent::BIPushInsn(100)));
+        assert!(matches!(events[3], MethodEvent::SIPushInsn(1000)));
+        assert!(matches!(
+            events[4],
+            MethodEvent::LdcInsn(LdcConstant::Integer(100000))
+        ));
+    }
+
+    #[test]
+    fn test_generate_method() {
+        let desc = JavaStr::from_str("(JJ)Ljava/lang/Object;");
+        let label_creator = LabelCreator::default();
+        let mut generator = CodeGenerator::new(MethodAccess::Static, desc, label_creator.clone());
+        let otherwise = generator.new_label();
+        let end = generator.new_label();
+        generator.load_args();
+        generator.if_cmp(JavaStr::from_str("J"), Comparison::Ge, otherwise);
+        generator.load_arg(1);
+        generator.goto(end);
+        generator.mark(otherwise);
+        generator.load_arg(0);
+        generator.mark(end);
+        generator.box_value(JavaStr::from_str("J"));
+        generator.return_value();
+
+        let mut method_events = vec![MethodEvent::Code { label_creator }];
+        method_events.extend(generator.into_events());
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let mut events = buffer_class_events(&reader).unwrap();
+        events
+            .0
+            .push(ClassEvent::Methods(EventBuffer(vec![ClassMethodEvent {
+                access: MethodAccess::Static,
+                name: JavaStr::from_str("max").into(),
+                desc: desc.into(),
+                signature: None,
+                exceptions: Vec::new(),
+                events: EventBuffer(method_events),
+            }])));
+        let written = ClassWriter::new(ClassWriterFlags::ComputeFrames)
+            .write(events)
+            .unwrap();
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "max")
+            .unwrap();
+        let opcodes: Vec<_> = method
+            .instructions
+            .iter()
+            .filter_map(|insn| match insn {
+                InsnNode::Insn(opcode)
+                | InsnNode::VarInsn { opcode, .. }
+                | InsnNode::JumpInsn { opcode, .. }
+                | InsnNode::MethodInsn { opcode, .. } => Some(*opcode),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                Opcode::LLoad,
+                Opcode::LLoad,
+                Opcode::LCmp,
+                Opcode::IfGe,
+                Opcode::LLoad,
+                Opcode::Goto,
+                Opcode::LLoad,
+                Opcode::InvokeStatic,
+                Opcode::AReturn,
+            ],
+            opcodes
+        );
+    }
+}
@@ -2,8 +2,12 @@ use crate::{ClassBuffer, ClassFileError, ClassFileResult, Handle, HandleKind};
 use derive_more::{Debug, Display, TryFrom};
 use java_string::JavaStr;
 use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::iter::FusedIterator;
+use std::sync::Arc;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display, TryFrom)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 #[non_exhaustive]
 #[try_from(repr)]
@@ -34,6 +38,7 @@ impl ConstantPoolTag {
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum ConstantPoolEntry<'class> {
     Utf8(Cow<'class, JavaStr>),
@@ -56,12 +61,14 @@ pub enum ConstantPoolEntry<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NameAndType<'class> {
     pub name: Cow<'class, JavaStr>,
     pub desc: Cow<'class, JavaStr>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemberRef<'class> {
     pub owner: Cow<'class, JavaStr>,
     pub name: Cow<'class, JavaStr>,
@@ -69,16 +76,112 @@ pub struct MemberRef<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DynamicEntry<'class> {
     pub bootstrap_method_attr_index: u16,
     pub name: Cow<'class, JavaStr>,
     pub desc: Cow<'class, JavaStr>,
 }
 
+/// The bytes of a UTF-8 constant, which are only decoded from modified UTF-8 when needed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RawUtf8<'class> {
+    bytes: &'class [u8],
+}
+
+impl<'class> RawUtf8<'class> {
+    pub fn as_bytes(&self) -> &'class [u8] {
+        self.bytes
+    }
+
+    pub fn decode(&self) -> ClassFileResult<Cow<'class, JavaStr>> {
+        Ok(JavaStr::from_modified_utf8(self.bytes)?)
+    }
+}
+
+/// The number of entries with some tag, and the bytes they take up including their tags.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct TagStats {
+    pub count: u32,
+    pub size: usize,
+}
+
+/// Where the bytes of a constant pool go, see [`ConstantPool::stats`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConstantPoolStats {
+    pub tags: BTreeMap<ConstantPoolTag, TagStats>,
+    /// The indexes and sizes of the largest entries, largest first.
+    pub largest_entries: Vec<(u16, usize)>,
+    /// The indexes of the UTF-8 entries of each string which is stored more than once.
+    pub duplicate_utf8: Vec<Vec<u16>>,
+    /// The size of the constant pool, including the `constant_pool_count`.
+    pub total_size: usize,
+    /// The bytes taken up by tags, the lengths of UTF-8 entries and the `constant_pool_count`,
+    /// rather than by the constants themselves.
+    pub overhead: usize,
+}
+
+const LARGEST_ENTRY_COUNT: usize = 10;
+
+/// An instruction with a constant pool index operand.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InsnReference {
+    /// The index of the method in the class file.
+    pub method_index: u16,
+    /// The bytecode offset of the instruction.
+    pub offset: u16,
+}
+
+/// The entries and instructions referring to each constant pool entry, see
+/// [`ConstantPool::references`] and [`ClassReader::constant_pool_references`].
+///
+/// [`ClassReader::constant_pool_references`]: crate::ClassReader::constant_pool_references
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConstantPoolReferences {
+    entries: Vec<Vec<u16>>,
+    insns: Vec<Vec<InsnReference>>,
+}
+
+impl ConstantPoolReferences {
+    fn new(len: u16) -> Self {
+        ConstantPoolReferences {
+            entries: vec![Vec::new(); len as usize],
+            insns: vec![Vec::new(); len as usize],
+        }
+    }
+
+    pub(crate) fn add_insn(&mut self, index: u16, insn: InsnReference) -> ClassFileResult<()> {
+        let len = self.insns.len();
+        self.insns
+            .get_mut(index as usize)
+            .ok_or(ClassFileError::BadConstantPoolIndex { index, len })?
+            .push(insn);
+        Ok(())
+    }
+
+    /// The indexes of the entries referring to the entry at the given index, in ascending order.
+    pub fn entries_referencing(&self, index: u16) -> &[u16] {
+        self.entries.get(index as usize).map_or(&[], Vec::as_slice)
+    }
+
+    /// The instructions referring to the entry at the given index, in the order they appear in the
+    /// class file. Always empty if the references were computed by [`ConstantPool::references`].
+    pub fn insns_referencing(&self, index: u16) -> &[InsnReference] {
+        self.insns.get(index as usize).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether any entry or instruction refers to the entry at the given index. Other parts of the
+    /// class file, such as attributes, may still refer to an entry for which this returns `false`.
+    pub fn is_referenced(&self, index: u16) -> bool {
+        !self.entries_referencing(index).is_empty() || !self.insns_referencing(index).is_empty()
+    }
+}
+
 #[derive(Clone)]
 pub struct ConstantPool<'class> {
     buffer: ClassBuffer<'class>,
-    offset: Box<[usize]>,
+    offset: Arc<[usize]>,
+    end_offset: usize,
 }
 
 impl std::fmt::Debug for ConstantPool<'_> {
@@ -127,11 +230,30 @@ impl<'class> ConstantPool<'class> {
 
         let constant_pool = ConstantPool {
             buffer,
-            offset: cp_offset,
+            offset: cp_offset.into(),
+            end_offset: current_offset,
         };
         Ok((constant_pool, current_offset))
     }
 
+    /// The offsets of the entries and the end of the constant pool, for recreating it with
+    /// [`from_offsets`](Self::from_offsets) without reading it again.
+    pub(crate) fn offsets(&self) -> (Arc<[usize]>, usize) {
+        (self.offset.clone(), self.end_offset)
+    }
+
+    pub(crate) fn from_offsets(
+        buffer: ClassBuffer<'class>,
+        offset: Arc<[usize]>,
+        end_offset: usize,
+    ) -> ConstantPool<'class> {
+        ConstantPool {
+            buffer,
+            offset,
+            end_offset,
+        }
+    }
+
     fn index_to_offset(&self, index: u16) -> ClassFileResult<usize> {
         match self.offset.get(index as usize) {
             Some(&0) => Err(ClassFileError::BadConstantPoolIndexNoEntry(index)),
@@ -143,6 +265,37 @@ impl<'class> ConstantPool<'class> {
         }
     }
 
+    /// The `constant_pool_count` of the class file.
+    pub fn len(&self) -> u16 {
+        self.offset.len() as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset.len() <= 1
+    }
+
+    /// The encoded entries, not including the `constant_pool_count`.
+    pub(crate) fn as_bytes(&self) -> ClassFileResult<&'class [u8]> {
+        self.buffer.read_bytes(10, self.end_offset - 10)
+    }
+
+    /// The encoded entry at the given index, including its tag. Returns `None` for the unusable
+    /// index following a long or double entry.
+    pub(crate) fn raw_entry(&self, index: u16) -> ClassFileResult<Option<&'class [u8]>> {
+        if self.offset.get(index as usize) == Some(&0) {
+            return Ok(None);
+        }
+        let offset = self.index_to_offset(index)?;
+        let end = self
+            .offset
+            .iter()
+            .skip(index as usize + 1)
+            .find(|&&offset| offset != 0)
+            .copied()
+            .unwrap_or(self.end_offset);
+        self.buffer.read_bytes(offset, end - offset).map(Some)
+    }
+
     pub fn get_type(&self, index: u16) -> ClassFileResult<ConstantPoolTag> {
         let offset = self.index_to_offset(index)?;
         ConstantPoolTag::from_u8(self.buffer.read_u8(offset)?)
@@ -170,6 +323,125 @@ impl<'class> ConstantPool<'class> {
         let len = self.buffer.read_u16(offset + 1)?;
         self.buffer.read_bytes(offset + 3, len as usize)
     }
+
+    /// Counts the entries and bytes of each tag, and finds the 10 largest entries and duplicated
+    /// strings.
+    pub fn stats(&self) -> ClassFileResult<ConstantPoolStats> {
+        let mut stats = ConstantPoolStats {
+            total_size: self.end_offset - 8,
+            overhead: 2,
+            ..ConstantPoolStats::default()
+        };
+        let mut entries = Vec::new();
+        let mut utf8_indexes = HashMap::<&[u8], Vec<u16>>::new();
+        for index in 1..self.len() {
+            let Some(entry) = self.raw_entry(index)? else {
+                continue;
+            };
+            let tag = ConstantPoolTag::from_u8(entry[0])?;
+            let tag_stats = stats.tags.entry(tag).or_default();
+            tag_stats.count += 1;
+            tag_stats.size += entry.len();
+            stats.overhead += 1;
+            if tag == ConstantPoolTag::Utf8 {
+                stats.overhead += 2;
+                utf8_indexes.entry(&entry[3..]).or_default().push(index);
+            }
+            entries.push((index, entry.len()));
+        }
+
+        entries
+            .sort_by(|(index1, size1), (index2, size2)| size2.cmp(size1).then(index1.cmp(index2)));
+        entries.truncate(LARGEST_ENTRY_COUNT);
+        stats.largest_entries = entries;
+        stats.duplicate_utf8 = utf8_indexes
+            .into_values()
+            .filter(|indexes| indexes.len() > 1)
+            .collect();
+        stats.duplicate_utf8.sort();
+        Ok(stats)
+    }
+
+    /// Finds the entries referring to each entry. Bootstrap method indexes of dynamic entries aren't
+    /// constant pool indexes, and aren't included.
+    pub fn references(&self) -> ClassFileResult<ConstantPoolReferences> {
+        let mut references = ConstantPoolReferences::new(self.len());
+        for index in 1..self.len() {
+            let Some(entry) = self.raw_entry(index)? else {
+                continue;
+            };
+            for &offset in constant_references(ConstantPoolTag::from_u8(entry[0])?) {
+                let referenced = u16::from_be_bytes([entry[offset], entry[offset + 1]]);
+                references
+                    .entries
+                    .get_mut(referenced as usize)
+                    .ok_or(ClassFileError::BadConstantPoolIndex {
+                        index: referenced,
+                        len: self.offset.len(),
+                    })?
+                    .push(index);
+            }
+        }
+        Ok(references)
+    }
+
+    /// Iterates over the entries along with their indexes and tags. The unusable index following
+    /// each long or double entry is skipped.
+    pub fn iter_indexed(&self) -> ConstantPoolIndexedIter<'_, 'class> {
+        ConstantPoolIndexedIter {
+            constant_pool: self,
+            index: 0,
+        }
+    }
+
+    /// Like [`get_utf8`](Self::get_utf8), but without validating or decoding the string.
+    pub fn get_raw_utf8(&self, index: u16) -> ClassFileResult<RawUtf8<'class>> {
+        match self.get_raw(index)? {
+            (ConstantPoolTag::Utf8, bytes) => Ok(RawUtf8 { bytes }),
+            (tag, _) => Err(ClassFileError::BadConstantPoolType {
+                expected: ConstantPoolTag::Utf8,
+                actual: tag,
+            }),
+        }
+    }
+
+    /// The tag and undecoded contents of the entry at the given index. The contents don't include
+    /// the tag, nor the length of UTF-8 entries.
+    pub fn get_raw(&self, index: u16) -> ClassFileResult<(ConstantPoolTag, &'class [u8])> {
+        let Some(entry) = self.raw_entry(index)? else {
+            return Err(ClassFileError::BadConstantPoolIndexNoEntry(index));
+        };
+        let tag = ConstantPoolTag::from_u8(entry[0])?;
+        let contents = if tag == ConstantPoolTag::Utf8 {
+            &entry[3..]
+        } else {
+            &entry[1..]
+        };
+        Ok((tag, contents))
+    }
+}
+
+/// The offsets of the constant pool indices in an encoded entry with the given tag, not including
+/// the bootstrap method indices of dynamic entries.
+pub(crate) fn constant_references(tag: ConstantPoolTag) -> &'static [usize] {
+    match tag {
+        ConstantPoolTag::Class
+        | ConstantPoolTag::String
+        | ConstantPoolTag::MethodType
+        | ConstantPoolTag::Module
+        | ConstantPoolTag::Package => &[1],
+        ConstantPoolTag::FieldRef
+        | ConstantPoolTag::MethodRef
+        | ConstantPoolTag::InterfaceMethodRef
+        | ConstantPoolTag::NameAndType => &[1, 3],
+        ConstantPoolTag::MethodHandle => &[2],
+        ConstantPoolTag::Dynamic | ConstantPoolTag::InvokeDynamic => &[3],
+        ConstantPoolTag::Utf8
+        | ConstantPoolTag::Integer
+        | ConstantPoolTag::Float
+        | ConstantPoolTag::Long
+        | ConstantPoolTag::Double => &[],
+    }
 }
 
 macro_rules! generate_getters {
@@ -345,3 +617,139 @@ impl<'class> Iterator for ConstantPoolIntoIter<'_, 'class> {
         )
     }
 }
+
+#[derive(Debug, Copy, Clone)]
+pub struct ConstantPoolIndexedIter<'a, 'class> {
+    constant_pool: &'a ConstantPool<'class>,
+    index: u16,
+}
+
+impl<'class> Iterator for ConstantPoolIndexedIter<'_, 'class> {
+    type Item = (
+        u16,
+        ConstantPoolTag,
+        ClassFileResult<ConstantPoolEntry<'class>>,
+    );
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while (self.index as usize) + 1 < self.constant_pool.offset.len() {
+            self.index += 1;
+            if self.constant_pool.offset[self.index as usize] == 0 {
+                continue;
+            }
+            let tag = self
+                .constant_pool
+                .get_type(self.index)
+                .expect("constant pool tags were validated when reading the class");
+            return Some((self.index, tag, self.constant_pool.get(self.index)));
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .constant_pool
+            .offset
+            .len()
+            .saturating_sub(self.index as usize + 1);
+        (remaining.div_ceil(2), Some(remaining))
+    }
+}
+
+impl FusedIterator for ConstantPoolIndexedIter<'_, '_> {}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassReader, ClassReaderFlags, ConstantPoolEntry, ConstantPoolTag};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_get_raw() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let constant_pool = &reader.constant_pool;
+        for index in 1..constant_pool.len() {
+            let (tag, contents) = constant_pool.get_raw(index).unwrap();
+            assert_eq!(constant_pool.get_type(index).unwrap(), tag);
+            match constant_pool.get(index).unwrap() {
+                ConstantPoolEntry::Utf8(value) => {
+                    let raw = constant_pool.get_raw_utf8(index).unwrap();
+                    assert_eq!(contents, raw.as_bytes());
+                    assert_eq!(value, raw.decode().unwrap());
+                }
+                ConstantPoolEntry::Class(name) => {
+                    let name_index = u16::from_be_bytes([contents[0], contents[1]]);
+                    assert_eq!(name, constant_pool.get_utf8(name_index).unwrap());
+                    assert!(constant_pool.get_raw_utf8(index).is_err());
+                }
+                _ => assert_ne!(ConstantPoolTag::Utf8, tag),
+            }
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let stats = reader.constant_pool.stats().unwrap();
+        let count: u32 = stats.tags.values().map(|tag_stats| tag_stats.count).sum();
+        let size: usize = stats.tags.values().map(|tag_stats| tag_stats.size).sum();
+        assert_eq!(reader.constant_pool.len() as u32 - 1, count);
+        assert_eq!(stats.total_size, size + 2);
+        assert_eq!(10, stats.largest_entries.len());
+        assert!(stats
+            .largest_entries
+            .windows(2)
+            .all(|entries| entries[0].1 >= entries[1].1));
+        assert!(stats.duplicate_utf8.is_empty());
+    }
+
+    #[test]
+    fn test_references() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let constant_pool = &reader.constant_pool;
+        let references = reader.constant_pool_references().unwrap();
+        let println = (1..constant_pool.len())
+            .find(|&index| {
+                matches!(
+                    constant_pool.get(index).unwrap(),
+                    ConstantPoolEntry::MethodRef(method) if method.name.as_ref() == "println"
+                )
+            })
+            .unwrap();
+        assert_eq!(1, references.insns_referencing(println).len());
+        assert!(constant_pool
+            .references()
+            .unwrap()
+            .insns_referencing(println)
+            .is_empty());
+
+        let (_, contents) = constant_pool.get_raw(println).unwrap();
+        let name_and_type = u16::from_be_bytes([contents[2], contents[3]]);
+        assert_eq!(&[println], references.entries_referencing(name_and_type));
+        assert!(references.is_referenced(name_and_type));
+    }
+
+    #[test]
+    fn test_iter_indexed() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let constant_pool = &reader.constant_pool;
+        let entries: Vec<_> = constant_pool.iter_indexed().collect();
+        assert_eq!(constant_pool.into_iter().count(), entries.len());
+        let mut expected_index = 1;
+        for (index, tag, entry) in entries {
+            assert_eq!(expected_index, index);
+            assert_eq!(constant_pool.get_type(index).unwrap(), tag);
+            assert_eq!(constant_pool.get(index).unwrap(), entry.unwrap());
+            expected_index += match tag {
+                ConstantPoolTag::Long | ConstantPoolTag::Double => 2,
+                _ => 1,
+            };
+        }
+        assert_eq!(constant_pool.len(), expected_index);
+        assert!(constant_pool
+            .iter_indexed()
+            .any(|(_, tag, _)| tag == ConstantPoolTag::Long));
+    }
+}
@@ -0,0 +1,509 @@
+use crate::constant_pool::constant_references;
+use crate::{ClassFileError, ClassFileResult, ConstantPool, ConstantPoolTag, Handle, HandleKind};
+use java_string::{JavaStr, JavaString};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ConstantPoolKey {
+    Utf8(JavaString),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    FieldRef(u16, u16),
+    MethodRef(u16, u16),
+    InterfaceMethodRef(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+    InvokeDynamic(u16, u16),
+    Module(u16),
+    Package(u16),
+}
+
+impl ConstantPoolKey {
+    /// Parses an encoded entry, including its tag.
+    fn from_raw(entry: &[u8]) -> ClassFileResult<Self> {
+        let u16_at = |offset: usize| u16::from_be_bytes([entry[offset], entry[offset + 1]]);
+        let u32_at =
+            |offset: usize| u32::from_be_bytes(entry[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_be_bytes(entry[offset..offset + 8].try_into().unwrap());
+        Ok(match ConstantPoolTag::from_u8(entry[0])? {
+            ConstantPoolTag::Utf8 => {
+                ConstantPoolKey::Utf8(JavaStr::from_modified_utf8(&entry[3..])?.into_owned())
+            }
+            ConstantPoolTag::Integer => ConstantPoolKey::Integer(u32_at(1) as i32),
+            ConstantPoolTag::Float => ConstantPoolKey::Float(u32_at(1)),
+            ConstantPoolTag::Long => ConstantPoolKey::Long(u64_at(1) as i64),
+            ConstantPoolTag::Double => ConstantPoolKey::Double(u64_at(1)),
+            ConstantPoolTag::Class => ConstantPoolKey::Class(u16_at(1)),
+            ConstantPoolTag::String => ConstantPoolKey::String(u16_at(1)),
+            ConstantPoolTag::FieldRef => ConstantPoolKey::FieldRef(u16_at(1), u16_at(3)),
+            ConstantPoolTag::MethodRef => ConstantPoolKey::MethodRef(u16_at(1), u16_at(3)),
+            ConstantPoolTag::InterfaceMethodRef => {
+                ConstantPoolKey::InterfaceMethodRef(u16_at(1), u16_at(3))
+            }
+            ConstantPoolTag::NameAndType => ConstantPoolKey::NameAndType(u16_at(1), u16_at(3)),
+            ConstantPoolTag::MethodHandle => ConstantPoolKey::MethodHandle(entry[1], u16_at(2)),
+            ConstantPoolTag::MethodType => ConstantPoolKey::MethodType(u16_at(1)),
+            ConstantPoolTag::Dynamic => ConstantPoolKey::Dynamic(u16_at(1), u16_at(3)),
+            ConstantPoolTag::InvokeDynamic => ConstantPoolKey::InvokeDynamic(u16_at(1), u16_at(3)),
+            ConstantPoolTag::Module => ConstantPoolKey::Module(u16_at(1)),
+            ConstantPoolTag::Package => ConstantPoolKey::Package(u16_at(1)),
+        })
+    }
+
+    fn tag(&self) -> ConstantPoolTag {
+        match self {
+            ConstantPoolKey::Utf8(_) => ConstantPoolTag::Utf8,
+            ConstantPoolKey::Integer(_) => ConstantPoolTag::Integer,
+            ConstantPoolKey::Float(_) => ConstantPoolTag::Float,
+            ConstantPoolKey::Long(_) => ConstantPoolTag::Long,
+            ConstantPoolKey::Double(_) => ConstantPoolTag::Double,
+            ConstantPoolKey::Class(_) => ConstantPoolTag::Class,
+            ConstantPoolKey::String(_) => ConstantPoolTag::String,
+            ConstantPoolKey::FieldRef(..) => ConstantPoolTag::FieldRef,
+            ConstantPoolKey::MethodRef(..) => ConstantPoolTag::MethodRef,
+            ConstantPoolKey::InterfaceMethodRef(..) => ConstantPoolTag::InterfaceMethodRef,
+            ConstantPoolKey::NameAndType(..) => ConstantPoolTag::NameAndType,
+            ConstantPoolKey::MethodHandle(..) => ConstantPoolTag::MethodHandle,
+            ConstantPoolKey::MethodType(_) => ConstantPoolTag::MethodType,
+            ConstantPoolKey::Dynamic(..) => ConstantPoolTag::Dynamic,
+            ConstantPoolKey::InvokeDynamic(..) => ConstantPoolTag::InvokeDynamic,
+            ConstantPoolKey::Module(_) => ConstantPoolTag::Module,
+            ConstantPoolKey::Package(_) => ConstantPoolTag::Package,
+        }
+    }
+
+    fn slots(&self) -> u16 {
+        match self {
+            ConstantPoolKey::Long(_) | ConstantPoolKey::Double(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Where the entries of a constant pool ended up after merging it into a builder, see
+/// [`ConstantPoolBuilder::merge`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstantPoolIndexMap {
+    /// The new index of each entry, or 0 for indexes without an entry.
+    indexes: Vec<u16>,
+}
+
+impl ConstantPoolIndexMap {
+    /// The index in the builder of the entry at the given index in the source constant pool.
+    pub fn get(&self, index: u16) -> ClassFileResult<u16> {
+        match self.indexes.get(index as usize) {
+            Some(0) => Err(ClassFileError::BadConstantPoolIndexNoEntry(index)),
+            Some(&new_index) => Ok(new_index),
+            None => Err(ClassFileError::BadConstantPoolIndex {
+                index,
+                len: self.indexes.len(),
+            }),
+        }
+    }
+
+    /// Like [`get`](Self::get), but maps index 0, which stands for no entry in some places, to
+    /// itself.
+    pub fn get_optional(&self, index: u16) -> ClassFileResult<u16> {
+        if index == 0 {
+            return Ok(0);
+        }
+        self.get(index)
+    }
+}
+
+/// Builds the constant pool of a class file, handing out an index for each entry. Identical entries
+/// are only added once.
+#[derive(Debug, Clone)]
+pub struct ConstantPoolBuilder {
+    data: Vec<u8>,
+    count: u16,
+    entries: HashMap<ConstantPoolKey, u16>,
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> Self {
+        ConstantPoolBuilder {
+            data: Vec::new(),
+            count: 1,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder starting with the exact entries of an existing constant pool, in the same
+    /// order and at the same indices. Entries added later reuse the existing ones where possible.
+    pub fn from_constant_pool(constant_pool: &ConstantPool) -> ClassFileResult<Self> {
+        let mut builder = ConstantPoolBuilder {
+            data: constant_pool.as_bytes()?.to_vec(),
+            count: constant_pool.len(),
+            entries: HashMap::new(),
+        };
+        for index in 1..constant_pool.len() {
+            let Some(entry) = constant_pool.raw_entry(index)? else {
+                continue;
+            };
+            let key = ConstantPoolKey::from_raw(entry)?;
+            // where the original pool has duplicates, the first entry wins
+            builder.entries.entry(key).or_insert(index);
+        }
+        Ok(builder)
+    }
+
+    /// Adds every entry of a constant pool, reusing identical entries already in the builder.
+    /// Merging the constant pools of several classes one after the other gives a map for each of
+    /// them, with which the constant pool indexes in code copied from that class can be translated.
+    ///
+    /// The bootstrap method indexes of dynamic entries are copied unchanged, it's up to the caller
+    /// to merge the `BootstrapMethods` attributes such that they stay valid.
+    pub fn merge(&mut self, constant_pool: &ConstantPool) -> ClassFileResult<ConstantPoolIndexMap> {
+        let mut indexes = vec![0; constant_pool.len() as usize];
+        for index in 1..constant_pool.len() {
+            if constant_pool.raw_entry(index)?.is_some() {
+                self.merge_entry(constant_pool, index, &mut indexes, &mut Vec::new())?;
+            }
+        }
+        Ok(ConstantPoolIndexMap { indexes })
+    }
+
+    /// Adds an entry of another constant pool after the entries it refers to.
+    fn merge_entry(
+        &mut self,
+        constant_pool: &ConstantPool,
+        index: u16,
+        indexes: &mut [u16],
+        in_progress: &mut Vec<u16>,
+    ) -> ClassFileResult<u16> {
+        if let Some(&new_index) = indexes
+            .get(index as usize)
+            .filter(|&&new_index| new_index != 0)
+        {
+            return Ok(new_index);
+        }
+        if in_progress.contains(&index) {
+            return Err(ClassFileError::ConstantPoolCircularReference(index));
+        }
+        let mut entry = constant_pool
+            .raw_entry(index)?
+            .ok_or(ClassFileError::BadConstantPoolIndexNoEntry(index))?
+            .to_vec();
+        in_progress.push(index);
+        for &offset in constant_references(ConstantPoolTag::from_u8(entry[0])?) {
+            let reference = u16::from_be_bytes([entry[offset], entry[offset + 1]]);
+            let new_reference = self.merge_entry(constant_pool, reference, indexes, in_progress)?;
+            entry[offset..offset + 2].copy_from_slice(&new_reference.to_be_bytes());
+        }
+        in_progress.pop();
+        let new_index = self.add_raw(&entry)?;
+        indexes[index as usize] = new_index;
+        Ok(new_index)
+    }
+
+    /// The `constant_pool_count` of the class file, which is one more than the highest index in
+    /// use, as long and double entries take up two indices.
+    pub fn len(&self) -> u16 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 1
+    }
+
+    /// The encoded entries, not including the `constant_pool_count`.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Writes the `constant_pool_count` followed by the encoded entries.
+    pub fn write_to(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.count.to_be_bytes());
+        output.extend_from_slice(&self.data);
+    }
+
+    fn add(&mut self, key: ConstantPoolKey) -> ClassFileResult<u16> {
+        if let Some(&index) = self.entries.get(&key) {
+            return Ok(index);
+        }
+
+        let index = self.count;
+        let count = self
+            .count
+            .checked_add(key.slots())
+            .ok_or(ClassFileError::ConstantPoolOverflow)?;
+        if let ConstantPoolKey::Utf8(value) = &key {
+            let len = value.to_modified_utf8().len();
+            if len > u16::MAX as usize {
+                return Err(ClassFileError::Utf8TooLong(len));
+            }
+        }
+        self.count = count;
+
+        self.data.push(key.tag() as u8);
+        match &key {
+            ConstantPoolKey::Utf8(value) => {
+                let bytes = value.to_modified_utf8();
+                self.data
+                    .extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+                self.data.extend_from_slice(&bytes);
+            }
+            ConstantPoolKey::Integer(value) => self.data.extend_from_slice(&value.to_be_bytes()),
+            ConstantPoolKey::Float(bits) => self.data.extend_from_slice(&bits.to_be_bytes()),
+            ConstantPoolKey::Long(value) => self.data.extend_from_slice(&value.to_be_bytes()),
+            ConstantPoolKey::Double(bits) => self.data.extend_from_slice(&bits.to_be_bytes()),
+            ConstantPoolKey::Class(index)
+            | ConstantPoolKey::String(index)
+            | ConstantPoolKey::MethodType(index)
+            | ConstantPoolKey::Module(index)
+            | ConstantPoolKey::Package(index) => self.data.extend_from_slice(&index.to_be_bytes()),
+            ConstantPoolKey::FieldRef(a, b)
+            | ConstantPoolKey::MethodRef(a, b)
+            | ConstantPoolKey::InterfaceMethodRef(a, b)
+            | ConstantPoolKey::NameAndType(a, b)
+            | ConstantPoolKey::Dynamic(a, b)
+            | ConstantPoolKey::InvokeDynamic(a, b) => {
+                self.data.extend_from_slice(&a.to_be_bytes());
+                self.data.extend_from_slice(&b.to_be_bytes());
+            }
+            ConstantPoolKey::MethodHandle(kind, reference) => {
+                self.data.push(*kind);
+                self.data.extend_from_slice(&reference.to_be_bytes());
+            }
+        }
+
+        self.entries.insert(key, index);
+        Ok(index)
+    }
+
+    /// Adds an encoded entry, including its tag.
+    pub(crate) fn add_raw(&mut self, entry: &[u8]) -> ClassFileResult<u16> {
+        self.add(ConstantPoolKey::from_raw(entry)?)
+    }
+
+    pub fn utf8(&mut self, value: &JavaStr) -> ClassFileResult<u16> {
+        self.add(ConstantPoolKey::Utf8(value.to_owned()))
+    }
+
+    pub fn integer(&mut self, value: i32) -> ClassFileResult<u16> {
+        self.add(ConstantPoolKey::Integer(value))
+    }
+
+    /// Floats are compared by their bits, so different NaNs get different entries.
+    pub fn float(&mut self, value: f32) -> ClassFileResult<u16> {
+        self.add(ConstantPoolKey::Float(value.to_bits()))
+    }
+
+    pub fn long(&mut self, value: i64) -> ClassFileResult<u16> {
+        self.add(ConstantPoolKey::Long(value))
+    }
+
+    /// Doubles are compared by their bits, so different NaNs get different entries.
+    pub fn double(&mut self, value: f64) -> ClassFileResult<u16> {
+        self.add(ConstantPoolKey::Double(value.to_bits()))
+    }
+
+    pub fn class(&mut self, name: &JavaStr) -> ClassFileResult<u16> {
+        let name = self.utf8(name)?;
+        self.add(ConstantPoolKey::Class(name))
+    }
+
+    pub fn string(&mut self, value: &JavaStr) -> ClassFileResult<u16> {
+        let value = self.utf8(value)?;
+        self.add(ConstantPoolKey::String(value))
+    }
+
+    pub fn method_type(&mut self, desc: &JavaStr) -> ClassFileResult<u16> {
+        let desc = self.utf8(desc)?;
+        self.add(ConstantPoolKey::MethodType(desc))
+    }
+
+    pub fn module(&mut self, name: &JavaStr) -> ClassFileResult<u16> {
+        let name = self.utf8(name)?;
+        self.add(ConstantPoolKey::Module(name))
+    }
+
+    pub fn package(&mut self, name: &JavaStr) -> ClassFileResult<u16> {
+        let name = self.utf8(name)?;
+        self.add(ConstantPoolKey::Package(name))
+    }
+
+    pub fn name_and_type(&mut self, name: &JavaStr, desc: &JavaStr) -> ClassFileResult<u16> {
+        let name = self.utf8(name)?;
+        let desc = self.utf8(desc)?;
+        self.add(ConstantPoolKey::NameAndType(name, desc))
+    }
+
+    pub fn field_ref(
+        &mut self,
+        owner: &JavaStr,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> ClassFileResult<u16> {
+        let owner = self.class(owner)?;
+        let name_and_type = self.name_and_type(name, desc)?;
+        self.add(ConstantPoolKey::FieldRef(owner, name_and_type))
+    }
+
+    pub fn method_ref(
+        &mut self,
+        owner: &JavaStr,
+        name: &JavaStr,
+        desc: &JavaStr,
+        is_interface: bool,
+    ) -> ClassFileResult<u16> {
+        let owner = self.class(owner)?;
+        let name_and_type = self.name_and_type(name, desc)?;
+        if is_interface {
+            self.add(ConstantPoolKey::InterfaceMethodRef(owner, name_and_type))
+        } else {
+            self.add(ConstantPoolKey::MethodRef(owner, name_and_type))
+        }
+    }
+
+    pub fn method_handle(&mut self, handle: &Handle) -> ClassFileResult<u16> {
+        let reference = match handle.kind {
+            HandleKind::GetField
+            | HandleKind::GetStatic
+            | HandleKind::PutField
+            | HandleKind::PutStatic => self.field_ref(&handle.owner, &handle.name, &handle.desc)?,
+            _ => self.method_ref(
+                &handle.owner,
+                &handle.name,
+                &handle.desc,
+                handle.is_interface,
+            )?,
+        };
+        self.add(ConstantPoolKey::MethodHandle(handle.kind as u8, reference))
+    }
+
+    /// Adds a `CONSTANT_Dynamic` entry. The bootstrap method index refers to the `BootstrapMethods`
+    /// attribute, which is up to the caller to write.
+    pub fn dynamic(
+        &mut self,
+        bootstrap_method: u16,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> ClassFileResult<u16> {
+        let name_and_type = self.name_and_type(name, desc)?;
+        self.add(ConstantPoolKey::Dynamic(bootstrap_method, name_and_type))
+    }
+
+    /// Adds a `CONSTANT_InvokeDynamic` entry. The bootstrap method index refers to the
+    /// `BootstrapMethods` attribute, which is up to the caller to write.
+    pub fn invoke_dynamic(
+        &mut self,
+        bootstrap_method: u16,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> ClassFileResult<u16> {
+        let name_and_type = self.name_and_type(name, desc)?;
+        self.add(ConstantPoolKey::InvokeDynamic(
+            bootstrap_method,
+            name_and_type,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassFileError, ClassReader, ClassReaderFlags, ConstantPoolBuilder};
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_dedup() {
+        let mut builder = ConstantPoolBuilder::new();
+        let name = JavaStr::from_str("java/lang/Object");
+        let class = builder.class(name).unwrap();
+        assert_eq!(Ok(1), builder.utf8(name));
+        assert_eq!(2, class);
+        assert_eq!(Ok(class), builder.class(name));
+
+        let method = builder
+            .method_ref(
+                name,
+                JavaStr::from_str("<init>"),
+                JavaStr::from_str("()V"),
+                false,
+            )
+            .unwrap();
+        let interface_method = builder
+            .method_ref(
+                name,
+                JavaStr::from_str("<init>"),
+                JavaStr::from_str("()V"),
+                true,
+            )
+            .unwrap();
+        assert_ne!(method, interface_method);
+    }
+
+    #[test]
+    fn test_long_takes_two_slots() {
+        let mut builder = ConstantPoolBuilder::new();
+        assert_eq!(Ok(1), builder.long(42));
+        assert_eq!(Ok(3), builder.double(1.5));
+        assert_eq!(Ok(5), builder.integer(42));
+        assert_eq!(Ok(1), builder.long(42));
+        assert_eq!(6, builder.len());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut builder = ConstantPoolBuilder::new();
+        for i in 0..u16::MAX as i32 - 2 {
+            builder.integer(i).unwrap();
+        }
+        assert_eq!(u16::MAX - 1, builder.len());
+        assert_eq!(Err(ClassFileError::ConstantPoolOverflow), builder.long(0));
+        assert_eq!(Ok(u16::MAX - 1), builder.integer(-1));
+        assert_eq!(
+            Err(ClassFileError::ConstantPoolOverflow),
+            builder.integer(-2)
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let hello_world =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let test_code =
+            ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let mut builder = ConstantPoolBuilder::new();
+        let object = builder
+            .class(JavaStr::from_str("java/lang/Object"))
+            .unwrap();
+        let len = builder.len();
+        let maps =
+            [&hello_world, &test_code].map(|reader| builder.merge(&reader.constant_pool).unwrap());
+        assert!(builder.len() > len);
+
+        let mut output = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52];
+        builder.write_to(&mut output);
+        output.extend_from_slice(&[0; 8]);
+        let merged = ClassReader::new(&output, ClassReaderFlags::None).unwrap();
+        for (reader, map) in [&hello_world, &test_code].into_iter().zip(&maps) {
+            for (index, _, entry) in reader.constant_pool.iter_indexed() {
+                let new_index = map.get(index).unwrap();
+                assert_eq!(entry.unwrap(), merged.constant_pool.get(new_index).unwrap());
+                if reader.constant_pool.get_class(index).ok().as_deref()
+                    == Some(JavaStr::from_str("java/lang/Object"))
+                {
+                    assert_eq!(object, new_index);
+                }
+            }
+        }
+        assert!(maps[0].get(0).is_err());
+        assert_eq!(Ok(0), maps[0].get_optional(0));
+    }
+}
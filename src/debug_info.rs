@@ -0,0 +1,462 @@
+use crate::{
+    buffer_class_events, method_desc_slots, BufferedClassEvents, ClassEvent, ClassEventSource,
+    ClassFileResult, Label, MethodAccess, MethodEvent, MethodEventProviders,
+    MethodLocalVariableEvent, Opcode,
+};
+use bitflags::bitflags;
+use java_string::JavaStr;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Debug info which was lost or invalidated by a transformation.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum DebugInfoIssue<'class> {
+    #[error("line {line} now comes after line {previous_line} in {method_name}{method_desc}")]
+    LineNumberOutOfOrder {
+        method_name: Cow<'class, JavaStr>,
+        method_desc: Cow<'class, JavaStr>,
+        line: u16,
+        previous_line: u16,
+    },
+    #[error("line numbers of {method_name}{method_desc} were lost")]
+    LineNumbersLost {
+        method_name: Cow<'class, JavaStr>,
+        method_desc: Cow<'class, JavaStr>,
+    },
+    #[error("local variable {name} in slot {index} of {method_name}{method_desc} was lost")]
+    LocalVariableLost {
+        method_name: Cow<'class, JavaStr>,
+        method_desc: Cow<'class, JavaStr>,
+        name: Cow<'class, JavaStr>,
+        index: u16,
+    },
+    #[error("local variable {name} in slot {index} of {method_name}{method_desc} has a bad range")]
+    LocalVariableRangeInvalid {
+        method_name: Cow<'class, JavaStr>,
+        method_desc: Cow<'class, JavaStr>,
+        name: Cow<'class, JavaStr>,
+        index: u16,
+    },
+    #[error("source file was lost")]
+    SourceFileLost,
+}
+
+/// Compares a class before and after a transformation, and reports the line numbers and local
+/// variables which were lost or no longer make sense. Methods are matched by name and descriptor,
+/// and methods which were removed are ignored.
+///
+/// A local variable range is valid if it starts right after a store to the variable, or at the
+/// start of the method for arguments. Ranges which weren't valid before are not reported.
+pub fn check_debug_info<'class, B, A>(
+    before: B,
+    after: A,
+) -> ClassFileResult<Vec<DebugInfoIssue<'class>>>
+where
+    B: ClassEventSource<'class>,
+    A: ClassEventSource<'class>,
+{
+    let before = ClassDebugInfo::read(before)?;
+    let after = ClassDebugInfo::read(after)?;
+    let mut issues = Vec::new();
+
+    if before.has_source && !after.has_source {
+        issues.push(DebugInfoIssue::SourceFileLost);
+    }
+
+    let after_methods: HashMap<_, _> = after
+        .methods
+        .iter()
+        .map(|method| ((&method.name, &method.desc), method))
+        .collect();
+    for before_method in &before.methods {
+        if let Some(after_method) = after_methods.get(&(&before_method.name, &before_method.desc)) {
+            before_method.compare(after_method, &mut issues);
+        }
+    }
+
+    Ok(issues)
+}
+
+bitflags! {
+    /// The kinds of debug info removed by [`strip_debug_info`].
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    pub struct StripDebugFlags: u8 {
+        const None = 0;
+        const SourceFile = 1;
+        const SourceDebugExtension = 2;
+        const LineNumbers = 4;
+        /// The local variable table, which also removes the local variable types.
+        const LocalVariables = 8;
+        /// The generic signatures of local variables.
+        const LocalVariableTypes = 16;
+        const MethodParameters = 32;
+        const All = 63;
+    }
+}
+
+/// Reads a class, removing the given kinds of debug info. This has the same effect as
+/// [`ClassReaderFlags::SkipDebug`](crate::ClassReaderFlags::SkipDebug) with
+/// [`StripDebugFlags::All`], for events which don't come straight from a class reader.
+pub fn strip_debug_info<'class, S>(
+    source: S,
+    flags: StripDebugFlags,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    let mut events = buffer_class_events(source)?;
+    events.0.retain_mut(|event| match event {
+        ClassEvent::Source(source) => {
+            if flags.contains(StripDebugFlags::SourceFile) {
+                source.source = None;
+            }
+            if flags.contains(StripDebugFlags::SourceDebugExtension) {
+                source.debug = None;
+            }
+            source.source.is_some() || source.debug.is_some()
+        }
+        _ => true,
+    });
+
+    for event in &mut events.0 {
+        let ClassEvent::Methods(methods) = event else {
+            continue;
+        };
+        for method in &mut methods.0 {
+            method.events.0.retain_mut(|event| match event {
+                MethodEvent::Parameters(_) => !flags.contains(StripDebugFlags::MethodParameters),
+                MethodEvent::LineNumber { .. } => !flags.contains(StripDebugFlags::LineNumbers),
+                MethodEvent::LocalVariables(local_variables) => {
+                    if flags.contains(StripDebugFlags::LocalVariables) {
+                        return false;
+                    }
+                    if flags.contains(StripDebugFlags::LocalVariableTypes) {
+                        for local_variable in &mut local_variables.0 {
+                            local_variable.signature = None;
+                        }
+                    }
+                    true
+                }
+                _ => true,
+            });
+        }
+    }
+    Ok(events)
+}
+
+struct ClassDebugInfo<'class> {
+    has_source: bool,
+    methods: Vec<MethodDebugInfo<'class>>,
+}
+
+impl<'class> ClassDebugInfo<'class> {
+    fn read(source: impl ClassEventSource<'class>) -> ClassFileResult<Self> {
+        let mut info = ClassDebugInfo {
+            has_source: false,
+            methods: Vec::new(),
+        };
+        for event in source.events()? {
+            match event? {
+                ClassEvent::Source(source) => info.has_source = source.source.is_some(),
+                ClassEvent::Methods(methods) => {
+                    for method in methods {
+                        let method = method?;
+                        let (mut argument_slots, _) = method_desc_slots(&method.desc);
+                        if !method.access.contains(MethodAccess::Static) {
+                            argument_slots += 1;
+                        }
+                        let mut method_info = MethodDebugInfo {
+                            name: method.name,
+                            desc: method.desc,
+                            argument_slots,
+                            lines: Vec::new(),
+                            local_variables: Vec::new(),
+                            label_positions: HashMap::new(),
+                            stores: HashMap::new(),
+                        };
+                        method_info.read(method.events)?;
+                        info.methods.push(method_info);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
+
+struct MethodDebugInfo<'class> {
+    name: Cow<'class, JavaStr>,
+    desc: Cow<'class, JavaStr>,
+    argument_slots: u16,
+    lines: Vec<(u16, Label)>,
+    local_variables: Vec<MethodLocalVariableEvent<'class>>,
+    /// The number of instructions before each label.
+    label_positions: HashMap<Label, u32>,
+    /// The local variable stored to by each instruction that stores one, by the instruction index.
+    stores: HashMap<u32, u16>,
+}
+
+impl<'class> MethodDebugInfo<'class> {
+    fn read<E, P>(&mut self, events: E) -> ClassFileResult<()>
+    where
+        E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+        P: MethodEventProviders<'class>,
+    {
+        let mut insn_count = 0u32;
+        for event in events {
+            match event? {
+                MethodEvent::Label(label) => {
+                    self.label_positions.insert(label, insn_count);
+                    continue;
+                }
+                MethodEvent::LineNumber { line, start } => {
+                    self.lines.push((line, start));
+                    continue;
+                }
+                MethodEvent::LocalVariables(local_variables) => {
+                    for local_variable in local_variables {
+                        self.local_variables.push(local_variable?);
+                    }
+                    continue;
+                }
+                MethodEvent::VarInsn {
+                    opcode:
+                        Opcode::IStore
+                        | Opcode::LStore
+                        | Opcode::FStore
+                        | Opcode::DStore
+                        | Opcode::AStore,
+                    var_index,
+                }
+                | MethodEvent::IIncInsn { var_index, .. } => {
+                    self.stores.insert(insn_count, var_index);
+                }
+                MethodEvent::Insn(_)
+                | MethodEvent::BIPushInsn(_)
+                | MethodEvent::SIPushInsn(_)
+                | MethodEvent::NewArrayInsn(_)
+                | MethodEvent::VarInsn { .. }
+                | MethodEvent::TypeInsn { .. }
+                | MethodEvent::FieldInsn { .. }
+                | MethodEvent::MethodInsn { .. }
+                | MethodEvent::InvokeDynamicInsn { .. }
+                | MethodEvent::JumpInsn { .. }
+                | MethodEvent::LdcInsn(_)
+                | MethodEvent::TableSwitchInsn { .. }
+                | MethodEvent::LookupSwitchInsn { .. }
+                | MethodEvent::MultiANewArrayInsn { .. } => {}
+                _ => continue,
+            }
+            insn_count += 1;
+        }
+        Ok(())
+    }
+
+    fn compare(&self, after: &MethodDebugInfo<'class>, issues: &mut Vec<DebugInfoIssue<'class>>) {
+        if !self.lines.is_empty() && after.lines.is_empty() {
+            issues.push(DebugInfoIssue::LineNumbersLost {
+                method_name: self.name.clone(),
+                method_desc: self.desc.clone(),
+            });
+        }
+
+        let before_order: HashMap<u16, usize> = self
+            .line_order()
+            .into_iter()
+            .enumerate()
+            .map(|(order, line)| (line, order))
+            .collect();
+        for pair in after.line_order().windows(2) {
+            let [previous_line, line] = *pair else {
+                unreachable!()
+            };
+            if let (Some(previous_order), Some(order)) =
+                (before_order.get(&previous_line), before_order.get(&line))
+            {
+                if previous_order > order {
+                    issues.push(DebugInfoIssue::LineNumberOutOfOrder {
+                        method_name: self.name.clone(),
+                        method_desc: self.desc.clone(),
+                        line,
+                        previous_line,
+                    });
+                }
+            }
+        }
+
+        // variables with the same name and slot can't be told apart, so compare their counts
+        let mut local_variables: HashMap<_, (u32, u32, u32, u32)> = HashMap::new();
+        for local_variable in &self.local_variables {
+            let counts = local_variables
+                .entry((
+                    &local_variable.name,
+                    &local_variable.desc,
+                    local_variable.index,
+                ))
+                .or_default();
+            counts.0 += 1;
+            counts.1 += self.is_defined_at_start(local_variable) as u32;
+        }
+        for local_variable in &after.local_variables {
+            let counts = local_variables
+                .entry((
+                    &local_variable.name,
+                    &local_variable.desc,
+                    local_variable.index,
+                ))
+                .or_default();
+            counts.2 += 1;
+            counts.3 += after.is_defined_at_start(local_variable) as u32;
+        }
+        for local_variable in &self.local_variables {
+            let key = (
+                &local_variable.name,
+                &local_variable.desc,
+                local_variable.index,
+            );
+            let Some((count, valid_count, after_count, after_valid_count)) =
+                local_variables.remove(&key)
+            else {
+                continue;
+            };
+            if after_count < count {
+                issues.push(DebugInfoIssue::LocalVariableLost {
+                    method_name: self.name.clone(),
+                    method_desc: self.desc.clone(),
+                    name: local_variable.name.clone(),
+                    index: local_variable.index,
+                });
+            } else if after_valid_count < valid_count {
+                issues.push(DebugInfoIssue::LocalVariableRangeInvalid {
+                    method_name: self.name.clone(),
+                    method_desc: self.desc.clone(),
+                    name: local_variable.name.clone(),
+                    index: local_variable.index,
+                });
+            }
+        }
+    }
+
+    /// The distinct line numbers, in the order they first appear in the code.
+    fn line_order(&self) -> Vec<u16> {
+        let mut lines: Vec<_> = self
+            .lines
+            .iter()
+            .filter_map(|(line, start)| Some((*self.label_positions.get(start)?, *line)))
+            .collect();
+        lines.sort_by_key(|&(position, _)| position);
+        let mut order: Vec<u16> = Vec::new();
+        for (_, line) in lines {
+            if !order.contains(&line) {
+                order.push(line);
+            }
+        }
+        order
+    }
+
+    fn is_defined_at_start(&self, local_variable: &MethodLocalVariableEvent) -> bool {
+        let (Some(&start), Some(&end)) = (
+            self.label_positions.get(&local_variable.start),
+            self.label_positions.get(&local_variable.end),
+        ) else {
+            return false;
+        };
+        if start > end {
+            return false;
+        }
+        if start == 0 {
+            return local_variable.index < self.argument_slots;
+        }
+        self.stores.get(&(start - 1)) == Some(&local_variable.index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        buffer_class_events, check_debug_info, strip_debug_info, ClassEvent, ClassReader,
+        ClassReaderFlags, DebugInfoIssue, MethodEvent, StripDebugFlags,
+    };
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_check_debug_info() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        assert_eq!(
+            Vec::<DebugInfoIssue>::new(),
+            check_debug_info(&reader, &reader).unwrap()
+        );
+
+        let mut events = buffer_class_events(&reader).unwrap();
+        events
+            .0
+            .retain(|event| !matches!(event, ClassEvent::Source(_)));
+        for event in &mut events.0 {
+            let ClassEvent::Methods(methods) = event else {
+                continue;
+            };
+            for method in &mut methods.0 {
+                if method.name.as_ref() == "loops" {
+                    method
+                        .events
+                        .0
+                        .retain(|event| !matches!(event, MethodEvent::LineNumber { .. }));
+                } else if method.name.as_ref() == "tryCatch" {
+                    // swap the first two line numbers
+                    let mut lines: Vec<_> = method
+                        .events
+                        .0
+                        .iter_mut()
+                        .filter_map(|event| match event {
+                            MethodEvent::LineNumber { line, .. } => Some(line),
+                            _ => None,
+                        })
+                        .collect();
+                    let [first, second, ..] = &mut lines[..] else {
+                        panic!("expected at least two line numbers");
+                    };
+                    std::mem::swap(*first, *second);
+                }
+            }
+        }
+
+        let issues = check_debug_info(&reader, events).unwrap();
+        assert!(issues.contains(&DebugInfoIssue::SourceFileLost));
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            DebugInfoIssue::LineNumbersLost { method_name, .. } if method_name.as_ref() == "loops"
+        )));
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            DebugInfoIssue::LineNumberOutOfOrder { method_name, .. }
+                if method_name.as_ref() == "tryCatch"
+        )));
+    }
+
+    #[test]
+    fn test_strip_debug_info() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let events = strip_debug_info(&reader, StripDebugFlags::LineNumbers).unwrap();
+        let issues = check_debug_info(&reader, events).unwrap();
+        assert!(!issues.contains(&DebugInfoIssue::SourceFileLost));
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            DebugInfoIssue::LineNumbersLost { method_name, .. } if method_name.as_ref() == "loops"
+        )));
+
+        let events = strip_debug_info(&reader, StripDebugFlags::All).unwrap();
+        assert!(!events
+            .0
+            .iter()
+            .any(|event| matches!(event, ClassEvent::Source(_))));
+        let skipped =
+            ClassReader::new(include_class!("TestCode"), ClassReaderFlags::SkipDebug).unwrap();
+        assert_eq!(
+            check_debug_info(&reader, &skipped).unwrap(),
+            check_debug_info(&reader, events).unwrap()
+        );
+    }
+}
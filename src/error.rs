@@ -1,12 +1,29 @@
-use crate::{ConstantPoolTag, Opcode};
-use java_string::Utf8Error;
+use crate::analysis::BasicValue;
+use crate::{
+    ClassCheckError, ConstantPoolTag, FrameValue, Label, Opcode, SignatureError, VersionedConstruct,
+};
+use java_string::{JavaString, Utf8Error};
 use thiserror::Error;
 
 #[derive(Debug, Clone, PartialEq, Error)]
 #[non_exhaustive]
 pub enum ClassFileError {
+    #[error("analysis failed at instruction {index} with stack {stack}: {source}")]
+    AnalysisFailed {
+        index: usize,
+        stack: String,
+        source: Box<ClassFileError>,
+    },
+    #[error("analyzing {0} is not supported")]
+    AnalyzerUnsupported(&'static str),
+    #[error("bad access rules on line {line}: {reason}")]
+    BadAccessRules { line: usize, reason: &'static str },
     #[error("bad annotation tag: {0}")]
     BadAnnotationTag(u8),
+    #[error("bad assembly at line {line}: {reason}")]
+    BadAssembly { line: usize, reason: &'static str },
+    #[error("bad class name: {0}")]
+    BadClassName(JavaString),
     #[error("bad code size: {0}, must be between 1-65535 inclusive")]
     BadCodeSize(u32),
     #[error("bad constant pool index: {index}, len {len}")]
@@ -26,6 +43,8 @@ pub enum ClassFileError {
     BadConstantPoolTypeExpectedFieldConstantValue(ConstantPoolTag),
     #[error("bad constant pool tag: {0}, expected ldc operand")]
     BadConstantPoolTypeExpectedLdcOperand(ConstantPoolTag),
+    #[error("bad descriptor: {0}")]
+    BadDescriptor(JavaString),
     #[error("bad frame type: {0}")]
     BadFrameType(u8),
     #[error("bad frame value tag: {0}")]
@@ -34,10 +53,23 @@ pub enum ClassFileError {
     BadHandleKind(u8),
     #[error("bad magic number")]
     BadMagic,
+    #[error("bad mappings at line {line}: {reason}")]
+    BadMappings { line: usize, reason: &'static str },
     #[error("bad newarray type: {0}")]
     BadNewArrayType(u8),
     #[error("bad opcode: {0}")]
     BadOpcode(u8),
+    #[error("bad operand, expected {expected:?}, found {actual:?}")]
+    BadOperand {
+        expected: BasicValue,
+        actual: BasicValue,
+    },
+    #[error("bad operand size for {0}")]
+    BadOperandSize(Opcode),
+    #[error("bad signature: {0}")]
+    BadSignature(#[from] SignatureError),
+    #[error("bad source map at line {line}: {reason}")]
+    BadSourceMap { line: usize, reason: &'static str },
     #[error("bad type annotation target: {0}")]
     BadTypeAnnotationTarget(u8),
     #[error("bad wide opcode: {0}")]
@@ -46,18 +78,146 @@ pub enum ClassFileError {
     BootstrapMethodCircularDependency,
     #[error("bootstrap method out of bounds, index {index}, len {len}")]
     BootstrapMethodOutOfBounds { index: u16, len: u16 },
+    #[error("class {0} is its own superclass")]
+    CircularSuperClass(JavaString),
     #[error("code offset out of bounds, index {index}, len {len}")]
     CodeOffsetOutOfBounds { index: usize, len: usize },
+    #[error("constant pool entry {0} refers to itself")]
+    ConstantPoolCircularReference(u16),
+    #[error("too many constant pool entries")]
+    ConstantPoolOverflow,
+    #[error("constant value of type {actual} does not match field descriptor {desc}")]
+    ConstantValueMismatch {
+        desc: JavaString,
+        actual: ConstantPoolTag,
+    },
+    #[error("unchanged methods cannot be copied to another class")]
+    CopyUnchangedMethod,
+    #[error("duplicate attribute: {0}")]
+    DuplicateAttribute(JavaString),
+    #[error("duplicate class event")]
+    DuplicateClassEvent,
+    #[error("execution falls off the end of the code")]
+    FallOffEndOfCode,
+    #[error("formatting error")]
+    Fmt(#[from] std::fmt::Error),
+    #[error("invalid class: {0}")]
+    InvalidClass(#[from] ClassCheckError),
+    #[error("io error: {message}")]
+    Io {
+        kind: std::io::ErrorKind,
+        message: String,
+    },
+    #[error("jump offset too large: {0}")]
+    JumpOffsetTooLarge(i32),
+    #[error("attribute {0} is not allowed here")]
+    MisplacedAttribute(JavaString),
+    #[error("missing class event, must be the first event")]
+    MissingClassEvent,
+    #[error("{actual:?} is not assignable to {expected:?}")]
+    NotAssignable {
+        expected: FrameValue<'static>,
+        actual: FrameValue<'static>,
+    },
+    #[error("operand stack is empty")]
+    OperandStackEmpty,
+    #[error("operand stack heights don't match, expected {expected}, found {actual}")]
+    OperandStackHeightMismatch { expected: usize, actual: usize },
     #[error("read past the end of the class file, index {index}, len {len}")]
     OutOfBounds { index: usize, len: usize },
+    #[error("preview classes must have the latest major version, but the class is version {0}")]
+    PreviewNotLatestVersion(u16),
+    #[error("subroutine at {0} calls itself")]
+    RecursiveSubroutine(Label),
+    #[error("ret instruction outside of a subroutine")]
+    RetOutsideSubroutine,
+    #[error("stack heights don't match at code offset {0}")]
+    StackHeightMismatch(usize),
+    #[error("stack underflow at code offset {0}")]
+    StackUnderflow(usize),
     #[error("tableswitch bounds in wrong order, low: {low}, high: {high}, expected low <= high")]
     TableSwitchBoundsWrongOrder { low: i32, high: i32 },
     #[error("too deep annotation nesting")]
     TooDeepAnnotationNesting,
+    #[error("too many {what}: {len}, must be at most {max}")]
+    TooMany {
+        what: &'static str,
+        len: usize,
+        max: usize,
+    },
+    #[error("unchanged method event must be the only event of its method")]
+    UnchangedMethodNotAlone,
+    #[error("unknown access flags: {0:#06x}")]
+    UnknownAccessFlags(u16),
+    #[error("unknown class: {0}")]
+    UnknownClass(JavaString),
+    #[error("unknown label: {0}")]
+    UnknownLabel(Label),
+    #[error("unreachable code at code offset {0}, frames cannot be computed")]
+    UnreachableCode(usize),
     #[error("unsupported class file version: {0}")]
     UnsupportedVersion(u16),
+    #[error("attribute {0} cannot be written")]
+    UnwritableAttribute(JavaString),
     #[error("utf8 error: {0}")]
     Utf8(#[from] Utf8Error),
+    #[error("utf8 constant too long: {0} bytes, must be at most 65535")]
+    Utf8TooLong(usize),
+    #[error("{construct:?} is not supported by class version {major_version}")]
+    VersionTooOld {
+        construct: VersionedConstruct,
+        major_version: u16,
+    },
+    /// An error while reading part of a class, such as `method "foo()V" > Code > StackMapTable
+    /// entry 3`, at the given byte offset in the class file.
+    #[error("{path} at byte {offset}: {source}")]
+    WithContext {
+        path: String,
+        offset: usize,
+        source: Box<ClassFileError>,
+    },
+    #[error("writing {0} is not supported")]
+    WriterUnsupported(&'static str),
+}
+
+impl ClassFileError {
+    /// Prepends a step to the path of the part of the class the error happened in. The offset is
+    /// only used if the error has no context yet, so that the innermost offset is kept.
+    pub(crate) fn with_context(self, step: impl FnOnce() -> String, offset: usize) -> Self {
+        match self {
+            ClassFileError::WithContext {
+                path,
+                offset,
+                source,
+            } => ClassFileError::WithContext {
+                path: format!("{} > {path}", step()),
+                offset,
+                source,
+            },
+            err => ClassFileError::WithContext {
+                path: step(),
+                offset,
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// The error without the context of where it happened.
+    pub fn root(&self) -> &ClassFileError {
+        match self {
+            ClassFileError::WithContext { source, .. } => source.root(),
+            err => err,
+        }
+    }
+}
+
+impl From<std::io::Error> for ClassFileError {
+    fn from(err: std::io::Error) -> Self {
+        ClassFileError::Io {
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
 }
 
 pub type ClassFileResult<T> = Result<T, ClassFileError>;
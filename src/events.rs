@@ -1,15 +1,17 @@
+use crate::buffered_events::buffer_class_event;
 use crate::tree::{AnnotationNode, AnnotationValue, TypeAnnotationNode};
 use crate::{
-    Attribute, BootstrapMethodArgument, ClassAccess, ClassFileResult, FieldAccess, FieldValue,
-    Frame, FrameValue, Handle, InnerClassAccess, Label, LabelCreator, LdcConstant, MethodAccess,
-    ModuleAccess, ModuleRelationAccess, ModuleRequireAccess, NewArrayType, Opcode, ParameterAccess,
-    TypePath, TypeReference,
+    Attribute, BootstrapMethodArgument, BufferedEventProviders, ClassAccess, ClassFileResult,
+    FieldAccess, FieldValue, Frame, FrameValue, Handle, InnerClassAccess, Label, LabelCreator,
+    LdcConstant, MethodAccess, ModuleAccess, ModuleRelationAccess, ModuleRequireAccess,
+    ModuleResolution, NewArrayType, Opcode, ParameterAccess, TypePath, TypeReference,
+    PREVIEW_MINOR_VERSION,
 };
 use derive_more::{Debug, IsVariant, TryUnwrap, Unwrap};
 use java_string::JavaStr;
 use std::borrow::Cow;
 
-#[derive(Debug, IsVariant, TryUnwrap, Unwrap)]
+#[derive(Debug, Clone, IsVariant, TryUnwrap, Unwrap)]
 #[non_exhaustive]
 pub enum ClassEvent<'class, P>
 where
@@ -27,6 +29,10 @@ where
     Attributes(P::Attributes),
     NestMembers(P::NestMembers),
     PermittedSubclasses(P::PermittedSubclasses),
+    /// The descriptors of classes that the JVM may load early to lay out value objects, from the
+    /// Valhalla `LoadableDescriptors` attribute.
+    #[cfg(feature = "preview")]
+    LoadableDescriptors(Vec<Cow<'class, JavaStr>>),
     InnerClasses(P::InnerClasses),
     Record(P::RecordComponents),
     Fields(P::Fields),
@@ -34,6 +40,7 @@ where
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClassClassEvent<'class> {
     pub major_version: u16,
     pub minor_version: u16,
@@ -44,13 +51,21 @@ pub struct ClassClassEvent<'class> {
     pub interfaces: Vec<Cow<'class, JavaStr>>,
 }
 
+impl ClassClassEvent<'_> {
+    /// Whether the class depends on the preview features of its Java version.
+    pub fn is_preview(&self) -> bool {
+        self.minor_version == PREVIEW_MINOR_VERSION
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClassSourceEvent<'class> {
     pub source: Option<Cow<'class, JavaStr>>,
     pub debug: Option<Cow<'class, JavaStr>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ClassModuleEvent<'class, E> {
     pub name: Cow<'class, JavaStr>,
     pub access: ModuleAccess,
@@ -59,6 +74,7 @@ pub struct ClassModuleEvent<'class, E> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClassOuterClassEvent<'class> {
     pub owner: Cow<'class, JavaStr>,
     pub method_name: Option<Cow<'class, JavaStr>>,
@@ -66,6 +82,7 @@ pub struct ClassOuterClassEvent<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClassInnerClassEvent<'class> {
     pub name: Cow<'class, JavaStr>,
     pub outer_name: Option<Cow<'class, JavaStr>>,
@@ -73,7 +90,7 @@ pub struct ClassInnerClassEvent<'class> {
     pub access: InnerClassAccess,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ClassRecordComponentEvent<'class, E> {
     pub name: Cow<'class, JavaStr>,
     pub desc: Cow<'class, JavaStr>,
@@ -81,7 +98,7 @@ pub struct ClassRecordComponentEvent<'class, E> {
     pub events: E,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ClassFieldEvent<'class, E> {
     pub access: FieldAccess,
     pub name: Cow<'class, JavaStr>,
@@ -91,7 +108,7 @@ pub struct ClassFieldEvent<'class, E> {
     pub events: E,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ClassMethodEvent<'class, E> {
     pub access: MethodAccess,
     pub name: Cow<'class, JavaStr>,
@@ -160,9 +177,21 @@ pub trait ClassEventProviders<'class> {
         Item = ClassFileResult<MethodEvent<'class, Self::MethodSubProviders>>,
     >;
     type Methods: IntoIterator<Item = ClassFileResult<ClassMethodEvent<'class, Self::MethodEvents>>>;
+
+    /// Reads an event with these providers into memory, see
+    /// [`buffer_class_events`](crate::buffer_class_events). [`BufferedEventProviders`] overrides
+    /// this to return its events as they are.
+    fn buffer_event(
+        event: ClassEvent<'class, Self>,
+    ) -> ClassFileResult<ClassEvent<'class, BufferedEventProviders>>
+    where
+        Self: Sized,
+    {
+        buffer_class_event(event)
+    }
 }
 
-#[derive(Debug, IsVariant, TryUnwrap, Unwrap)]
+#[derive(Debug, Clone, IsVariant, TryUnwrap, Unwrap)]
 #[non_exhaustive]
 pub enum FieldEvent<'class, P>
 where
@@ -184,7 +213,7 @@ pub trait FieldEventProviders<'class> {
     type Attributes: IntoIterator<Item = ClassFileResult<Box<dyn Attribute>>>;
 }
 
-#[derive(Debug, IsVariant, TryUnwrap, Unwrap)]
+#[derive(Debug, Clone, IsVariant, TryUnwrap, Unwrap)]
 #[non_exhaustive]
 pub enum MethodEvent<'class, P>
 where
@@ -292,21 +321,30 @@ where
     TryCatchBlockAnnotations(P::TryCatchBlockAnnotations),
     CodeAttributes(P::CodeAttributes),
     Maxs(MethodMaxsEvent),
+    /// Signals that a method read by a [`ClassReader`](crate::ClassReader) is unchanged, and must
+    /// be the only event of the method. A [`ClassWriter`](crate::ClassWriter) which copied the
+    /// constant pool of the same class copies the method verbatim, and otherwise reads it again
+    /// from the original class file. Either way, the other fields of the [`ClassMethodEvent`] are
+    /// ignored.
+    Unchanged(MethodUnchangedEvent<'class>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodParameterEvent<'class> {
     pub name: Option<Cow<'class, JavaStr>>,
     pub access: ParameterAccess,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodAnnotableParameterCountEvent {
     pub count: u8,
     pub visible: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodParameterAnnotationEvent<'class> {
     pub parameter: u8,
     pub visible: bool,
@@ -314,6 +352,7 @@ pub struct MethodParameterAnnotationEvent<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodLocalVariableEvent<'class> {
     pub name: Cow<'class, JavaStr>,
     pub desc: Cow<'class, JavaStr>,
@@ -324,6 +363,7 @@ pub struct MethodLocalVariableEvent<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodLocalVariableAnnotationEvent<'class> {
     pub ranges: Vec<(Label, Label, u16)>,
     pub visible: bool,
@@ -331,6 +371,7 @@ pub struct MethodLocalVariableAnnotationEvent<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodTryCatchBlockEvent<'class> {
     pub start: Label,
     pub end: Label,
@@ -339,17 +380,37 @@ pub struct MethodTryCatchBlockEvent<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodTryCatchBlockAnnotationEvent<'class> {
     pub try_catch_block_index: u16,
+    pub visible: bool,
     pub annotation: TypeAnnotationNode<'class>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodMaxsEvent {
     pub max_stack: u16,
     pub max_locals: u16,
 }
 
+/// The location of a method in the class file it was read from, see [`MethodEvent::Unchanged`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MethodUnchangedEvent<'class> {
+    #[debug("{} bytes", class.len())]
+    pub(crate) class: &'class [u8],
+    pub(crate) index: u16,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl<'class> MethodUnchangedEvent<'class> {
+    /// The encoded `method_info` structure.
+    pub fn bytes(&self) -> &'class [u8] {
+        &self.class[self.start..self.end]
+    }
+}
+
 pub trait MethodEventProviders<'class> {
     type Parameters: IntoIterator<Item = ClassFileResult<MethodParameterEvent<'class>>>;
 
@@ -384,13 +445,14 @@ pub trait MethodEventProviders<'class> {
     type CodeAttributes: IntoIterator<Item = ClassFileResult<Box<dyn Attribute>>>;
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnnotationEvent<A> {
     pub visible: bool,
     pub annotation: A,
 }
 
-#[derive(Debug, IsVariant, TryUnwrap, Unwrap)]
+#[derive(Debug, Clone, IsVariant, TryUnwrap, Unwrap)]
 #[non_exhaustive]
 pub enum ModuleEvent<'class, P>
 where
@@ -398,6 +460,11 @@ where
 {
     MainClass(Cow<'class, JavaStr>),
     Packages(P::Packages),
+    /// The platform of a module with native code, such as `linux-amd64`, from the JDK's
+    /// `ModuleTarget` attribute.
+    Target(Cow<'class, JavaStr>),
+    Resolution(ModuleResolution),
+    Hashes(ModuleHashesEvent<'class>),
     Requires(P::Requires),
     Exports(P::Exports),
     Opens(P::Opens),
@@ -406,6 +473,7 @@ where
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleRequireEvent<'class> {
     pub module: Cow<'class, JavaStr>,
     pub access: ModuleRequireAccess,
@@ -413,6 +481,7 @@ pub struct ModuleRequireEvent<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleRelationEvent<'class> {
     pub package: Cow<'class, JavaStr>,
     pub access: ModuleRelationAccess,
@@ -420,11 +489,29 @@ pub struct ModuleRelationEvent<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleProvidesEvent<'class> {
     pub service: Cow<'class, JavaStr>,
     pub providers: Vec<Cow<'class, JavaStr>>,
 }
 
+/// The hashes of the modules which depend on a module, recorded by `jlink` so it can check that
+/// they were built together. From the JDK's `ModuleHashes` attribute.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleHashesEvent<'class> {
+    /// The name of the hash algorithm, such as `SHA-256`.
+    pub algorithm: Cow<'class, JavaStr>,
+    pub hashes: Vec<ModuleHashEvent<'class>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleHashEvent<'class> {
+    pub module: Cow<'class, JavaStr>,
+    pub hash: Cow<'class, [u8]>,
+}
+
 pub trait ModuleEventProviders<'class> {
     type Packages: IntoIterator<Item = ClassFileResult<Cow<'class, JavaStr>>>;
     type Requires: IntoIterator<Item = ClassFileResult<ModuleRequireEvent<'class>>>;
@@ -434,7 +521,7 @@ pub trait ModuleEventProviders<'class> {
     type Provides: IntoIterator<Item = ClassFileResult<ModuleProvidesEvent<'class>>>;
 }
 
-#[derive(Debug, IsVariant, TryUnwrap, Unwrap)]
+#[derive(Debug, Clone, IsVariant, TryUnwrap, Unwrap)]
 #[non_exhaustive]
 pub enum RecordComponentEvent<'class, P>
 where
@@ -1,7 +1,9 @@
+use crate::ConstantPoolTag;
 use java_string::JavaStr;
 use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FieldValue<'class> {
     Integer(i32),
     Float(f32),
@@ -9,3 +11,27 @@ pub enum FieldValue<'class> {
     Double(f64),
     String(Cow<'class, JavaStr>),
 }
+
+impl FieldValue<'_> {
+    pub(crate) fn tag(&self) -> ConstantPoolTag {
+        match self {
+            FieldValue::Integer(_) => ConstantPoolTag::Integer,
+            FieldValue::Float(_) => ConstantPoolTag::Float,
+            FieldValue::Long(_) => ConstantPoolTag::Long,
+            FieldValue::Double(_) => ConstantPoolTag::Double,
+            FieldValue::String(_) => ConstantPoolTag::String,
+        }
+    }
+
+    /// Returns whether this value can be the `ConstantValue` of a field with the given descriptor,
+    /// as checked by the JVM.
+    pub fn matches_desc(&self, desc: &JavaStr) -> bool {
+        match self {
+            FieldValue::Integer(_) => matches!(desc.as_bytes(), b"Z" | b"B" | b"C" | b"S" | b"I"),
+            FieldValue::Float(_) => desc == "F",
+            FieldValue::Long(_) => desc == "J",
+            FieldValue::Double(_) => desc == "D",
+            FieldValue::String(_) => desc == "Ljava/lang/String;",
+        }
+    }
+}
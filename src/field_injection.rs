@@ -0,0 +1,342 @@
+use crate::tree::AnnotationNode;
+use crate::{
+    buffer_class_events, AnnotationEvent, BufferedClassEvents, BufferedEventProviders, ClassEvent,
+    ClassEventSource, ClassFieldEvent, ClassFileError, ClassFileResult, ClassInnerClassEvent,
+    ClassMethodEvent, EventBuffer, FieldAccess, FieldEvent, LabelCreator, MethodAccess,
+    MethodEvent, MethodMaxsEvent, Opcode,
+};
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+
+/// A field to add to a class with [`inject_field`], optionally with a getter and setter.
+#[derive(Debug)]
+pub struct FieldInjection<'class> {
+    pub access: FieldAccess,
+    pub name: Cow<'class, JavaStr>,
+    pub desc: Cow<'class, JavaStr>,
+    pub signature: Option<Cow<'class, JavaStr>>,
+    pub annotations: Vec<AnnotationEvent<AnnotationNode<'class>>>,
+    pub getter: Option<AccessorInjection<'class>>,
+    pub setter: Option<AccessorInjection<'class>>,
+    /// Inner classes referenced by the field descriptor or signature, which are added to the
+    /// `InnerClasses` attribute of the class if they're not already there.
+    pub inner_classes: Vec<ClassInnerClassEvent<'class>>,
+}
+
+impl<'class> FieldInjection<'class> {
+    pub fn new(
+        access: FieldAccess,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) -> Self {
+        FieldInjection {
+            access,
+            name: name.into(),
+            desc: desc.into(),
+            signature: None,
+            annotations: Vec::new(),
+            getter: None,
+            setter: None,
+            inner_classes: Vec::new(),
+        }
+    }
+}
+
+/// A getter or setter generated for a [`FieldInjection`]. Accessors of static fields are made
+/// static.
+#[derive(Debug)]
+pub struct AccessorInjection<'class> {
+    pub access: MethodAccess,
+    pub name: Cow<'class, JavaStr>,
+    pub annotations: Vec<AnnotationEvent<AnnotationNode<'class>>>,
+}
+
+impl<'class> AccessorInjection<'class> {
+    pub fn new(access: MethodAccess, name: impl Into<Cow<'class, JavaStr>>) -> Self {
+        AccessorInjection {
+            access,
+            name: name.into(),
+            annotations: Vec::new(),
+        }
+    }
+}
+
+/// Adds a field to a class, along with its accessors and any inner classes it references.
+pub fn inject_field<'class, S>(
+    source: S,
+    field: FieldInjection<'class>,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    let mut events = buffer_class_events(source)?;
+    let owner = match events.0.first() {
+        Some(ClassEvent::Class(class)) => class.name.clone(),
+        _ => return Err(ClassFileError::MissingClassEvent),
+    };
+
+    let is_static = field.access.contains(FieldAccess::Static);
+    let (load, ret, size) = match field.desc.as_bytes().first() {
+        Some(b'B' | b'C' | b'I' | b'S' | b'Z') => (Opcode::ILoad, Opcode::IReturn, 1),
+        Some(b'J') => (Opcode::LLoad, Opcode::LReturn, 2),
+        Some(b'F') => (Opcode::FLoad, Opcode::FReturn, 1),
+        Some(b'D') => (Opcode::DLoad, Opcode::DReturn, 2),
+        _ => (Opcode::ALoad, Opcode::AReturn, 1),
+    };
+    let (get, put) = if is_static {
+        (Opcode::GetStatic, Opcode::PutStatic)
+    } else {
+        (Opcode::GetField, Opcode::PutField)
+    };
+    let this_slots = if is_static { 0 } else { 1 };
+
+    let mut methods = Vec::new();
+    if let Some(getter) = field.getter {
+        let mut code = Vec::new();
+        if !is_static {
+            code.push(MethodEvent::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 0,
+            });
+        }
+        code.push(MethodEvent::FieldInsn {
+            opcode: get,
+            owner: owner.clone(),
+            name: field.name.clone(),
+            desc: field.desc.clone(),
+        });
+        code.push(MethodEvent::Insn(ret));
+        methods.push(accessor(
+            getter,
+            is_static,
+            JavaString::from(format!("(){}", field.desc)),
+            field
+                .signature
+                .as_ref()
+                .map(|signature| JavaString::from(format!("(){signature}"))),
+            code,
+            MethodMaxsEvent {
+                max_stack: size,
+                max_locals: this_slots,
+            },
+        ));
+    }
+    if let Some(setter) = field.setter {
+        let mut code = Vec::new();
+        if !is_static {
+            code.push(MethodEvent::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 0,
+            });
+        }
+        code.push(MethodEvent::VarInsn {
+            opcode: load,
+            var_index: this_slots,
+        });
+        code.push(MethodEvent::FieldInsn {
+            opcode: put,
+            owner: owner.clone(),
+            name: field.name.clone(),
+            desc: field.desc.clone(),
+        });
+        code.push(MethodEvent::Insn(Opcode::Return));
+        methods.push(accessor(
+            setter,
+            is_static,
+            JavaString::from(format!("({})V", field.desc)),
+            field
+                .signature
+                .as_ref()
+                .map(|signature| JavaString::from(format!("({signature})V"))),
+            code,
+            MethodMaxsEvent {
+                max_stack: this_slots + size,
+                max_locals: this_slots + size,
+            },
+        ));
+    }
+
+    let mut field_events = Vec::new();
+    if !field.annotations.is_empty() {
+        field_events.push(FieldEvent::Annotations(EventBuffer(field.annotations)));
+    }
+    let field_event = ClassFieldEvent {
+        access: field.access,
+        name: field.name,
+        desc: field.desc,
+        signature: field.signature,
+        value: None,
+        events: EventBuffer(field_events),
+    };
+
+    let mut inner_classes = field.inner_classes;
+    for event in &events.0 {
+        if let ClassEvent::InnerClasses(existing) = event {
+            inner_classes.retain(|inner_class| {
+                !existing
+                    .0
+                    .iter()
+                    .any(|existing| existing.name == inner_class.name)
+            });
+        }
+    }
+    if !inner_classes.is_empty() {
+        let members_start = events
+            .0
+            .iter()
+            .position(|event| matches!(event, ClassEvent::Fields(_) | ClassEvent::Methods(_)))
+            .unwrap_or(events.0.len());
+        events.0.insert(
+            members_start,
+            ClassEvent::InnerClasses(EventBuffer(inner_classes)),
+        );
+    }
+
+    match events.0.iter_mut().find_map(|event| match event {
+        ClassEvent::Fields(fields) => Some(fields),
+        _ => None,
+    }) {
+        Some(fields) => fields.0.push(field_event),
+        None => events
+            .0
+            .push(ClassEvent::Fields(EventBuffer(vec![field_event]))),
+    }
+    if !methods.is_empty() {
+        match events.0.iter_mut().find_map(|event| match event {
+            ClassEvent::Methods(methods) => Some(methods),
+            _ => None,
+        }) {
+            Some(existing) => existing.0.extend(methods),
+            None => events.0.push(ClassEvent::Methods(EventBuffer(methods))),
+        }
+    }
+
+    Ok(events)
+}
+
+fn accessor<'class>(
+    accessor: AccessorInjection<'class>,
+    is_static: bool,
+    desc: JavaString,
+    signature: Option<JavaString>,
+    code: Vec<MethodEvent<'class, BufferedEventProviders>>,
+    maxs: MethodMaxsEvent,
+) -> ClassMethodEvent<'class, EventBuffer<MethodEvent<'class, BufferedEventProviders>>> {
+    let mut access = accessor.access;
+    if is_static {
+        access.insert(MethodAccess::Static);
+    }
+
+    let mut events = Vec::new();
+    if !accessor.annotations.is_empty() {
+        events.push(MethodEvent::Annotations(EventBuffer(accessor.annotations)));
+    }
+    events.push(MethodEvent::Code {
+        label_creator: LabelCreator::default(),
+    });
+    events.extend(code);
+    events.push(MethodEvent::Maxs(maxs));
+
+    ClassMethodEvent {
+        access,
+        name: accessor.name,
+        desc: Cow::Owned(desc),
+        signature: signature.map(Cow::Owned),
+        exceptions: Vec::new(),
+        events: EventBuffer(events),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        inject_field, AccessorInjection, ClassEvent, ClassEventSource, ClassInnerClassEvent,
+        ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags, FieldAccess, FieldInjection,
+        InnerClassAccess, MethodAccess,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    fn inner_class<'a>(
+        name: &'a str,
+        outer_name: &'a str,
+        inner_name: &'a str,
+    ) -> ClassInnerClassEvent<'a> {
+        ClassInnerClassEvent {
+            name: JavaStr::from_str(name).into(),
+            outer_name: Some(JavaStr::from_str(outer_name).into()),
+            inner_name: Some(JavaStr::from_str(inner_name).into()),
+            access: InnerClassAccess::Public | InnerClassAccess::Static,
+        }
+    }
+
+    #[test]
+    fn test_inject_field() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let mut field = FieldInjection::new(
+            FieldAccess::Private | FieldAccess::Static,
+            JavaStr::from_str("entry"),
+            JavaStr::from_str("Ljava/util/Map$Entry;"),
+        );
+        field.signature =
+            Some(JavaStr::from_str("Ljava/util/Map$Entry<Ljava/lang/String;*>;").into());
+        field.getter = Some(AccessorInjection::new(
+            MethodAccess::Public,
+            JavaStr::from_str("getEntry"),
+        ));
+        field.setter = Some(AccessorInjection::new(
+            MethodAccess::Public,
+            JavaStr::from_str("setEntry"),
+        ));
+        field.inner_classes = vec![
+            inner_class("java/util/Map$Entry", "java/util/Map", "Entry"),
+            inner_class(
+                "java/lang/invoke/MethodHandles$Lookup",
+                "java/lang/invoke/MethodHandles",
+                "Lookup",
+            ),
+        ];
+        let events = inject_field(&reader, field).unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(events)
+            .unwrap();
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let mut inner_classes = Vec::new();
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
+        for event in reader.events().unwrap() {
+            match event.unwrap() {
+                ClassEvent::InnerClasses(events) => {
+                    inner_classes.extend(events.into_iter().map(|event| event.unwrap().name))
+                }
+                ClassEvent::Fields(events) => {
+                    fields.extend(events.into_iter().map(|event| event.unwrap().name))
+                }
+                ClassEvent::Methods(events) => methods.extend(events.into_iter().map(|event| {
+                    let event = event.unwrap();
+                    (event.access, event.name, event.desc, event.signature)
+                })),
+                _ => {}
+            }
+        }
+
+        assert_eq!(2, inner_classes.len());
+        assert!(fields.iter().any(|name| name.as_ref() == "entry"));
+        let (access, _, desc, signature) = methods
+            .iter()
+            .find(|(_, name, _, _)| name.as_ref() == "getEntry")
+            .unwrap();
+        assert_eq!(MethodAccess::Public | MethodAccess::Static, *access);
+        assert_eq!("()Ljava/util/Map$Entry;", desc.as_ref());
+        assert_eq!(
+            "()Ljava/util/Map$Entry<Ljava/lang/String;*>;",
+            signature.as_deref().unwrap()
+        );
+        let (_, _, desc, _) = methods
+            .iter()
+            .find(|(_, name, _, _)| name.as_ref() == "setEntry")
+            .unwrap();
+        assert_eq!("(Ljava/util/Map$Entry;)V", desc.as_ref());
+    }
+}
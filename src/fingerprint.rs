@@ -0,0 +1,101 @@
+use crate::{ClassFileResult, ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags};
+use derive_more::Display;
+
+const FNV_OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// A stable hash of a class, which is the same for class files that only differ in the order of
+/// their constant pool, bootstrap methods and attributes. The reader flags choose what else is
+/// ignored, such as [`SkipDebug`](ClassReaderFlags::SkipDebug) for debug info and
+/// [`SkipFrames`](ClassReaderFlags::SkipFrames) for stack map frames. The order of fields and
+/// methods is significant.
+///
+/// The hash is computed from the class as written by a [`ClassWriter`] with
+/// [`Deterministic`](ClassWriterFlags::Deterministic), so doesn't change between runs or
+/// platforms. Custom attributes need an [`AttributeReader`](crate::AttributeReader) on the reader,
+/// as the constant pool indices in them can't be told apart from other data otherwise, and the
+/// hash fails with [`UnwritableAttribute`](crate::ClassFileError::UnwritableAttribute).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[display("{_0:032x}")]
+pub struct ClassFingerprint(pub u128);
+
+impl ClassFingerprint {
+    pub fn new(data: &[u8], reader_flags: ClassReaderFlags) -> ClassFileResult<Self> {
+        ClassFingerprint::of_reader(&ClassReader::new(data, reader_flags)?)
+    }
+
+    pub fn of_reader(reader: &ClassReader) -> ClassFileResult<Self> {
+        let bytes = ClassWriter::new(ClassWriterFlags::Deterministic).write(reader)?;
+        let hash = bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &b| {
+            (hash ^ b as u128).wrapping_mul(FNV_PRIME)
+        });
+        Ok(ClassFingerprint(hash))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        buffer_class_events, Attribute, ClassEvent, ClassFileError, ClassFingerprint, ClassReader,
+        ClassReaderFlags, ClassWriter, ClassWriterFlags, EventBuffer, UnknownAttribute,
+    };
+    use java_string::JavaString;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_fingerprint() {
+        let data = include_class!("HelloWorld");
+        let fingerprint = |data: &[u8], flags| ClassFingerprint::new(data, flags).unwrap();
+
+        let reader = ClassReader::new(data, ClassReaderFlags::None).unwrap();
+        let rewritten = ClassWriter::new(ClassWriterFlags::None)
+            .write(&reader)
+            .unwrap();
+        assert_ne!(data, rewritten.as_slice());
+        assert_eq!(
+            fingerprint(data, ClassReaderFlags::None),
+            fingerprint(&rewritten, ClassReaderFlags::None)
+        );
+
+        let reader = ClassReader::new(data, ClassReaderFlags::SkipDebug).unwrap();
+        let stripped = ClassWriter::new(ClassWriterFlags::None)
+            .write(&reader)
+            .unwrap();
+        assert_ne!(
+            fingerprint(data, ClassReaderFlags::None),
+            fingerprint(&stripped, ClassReaderFlags::None)
+        );
+        assert_eq!(
+            fingerprint(data, ClassReaderFlags::SkipDebug),
+            fingerprint(&stripped, ClassReaderFlags::SkipDebug)
+        );
+
+        assert_ne!(
+            fingerprint(data, ClassReaderFlags::None),
+            fingerprint(include_class!("TestCode"), ClassReaderFlags::None)
+        );
+        assert_eq!(
+            32,
+            fingerprint(data, ClassReaderFlags::None).to_string().len()
+        );
+
+        let mut events = buffer_class_events(&reader).unwrap();
+        let attribute: Box<dyn Attribute> = Box::new(UnknownAttribute {
+            name: JavaString::from("Unknown"),
+            data: vec![0, 1],
+        });
+        events
+            .0
+            .push(ClassEvent::Attributes(EventBuffer(vec![attribute])));
+        let unknown = ClassWriter::new(ClassWriterFlags::None)
+            .write(events)
+            .unwrap();
+        assert_eq!(
+            Err(ClassFileError::UnwritableAttribute(JavaString::from(
+                "Unknown"
+            ))),
+            ClassFingerprint::new(&unknown, ClassReaderFlags::None)
+        );
+    }
+}
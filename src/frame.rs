@@ -1,8 +1,9 @@
-use crate::Label;
-use java_string::JavaStr;
+use crate::{ClassFileError, ClassFileResult, ClassHierarchy, Label};
+use java_string::{JavaStr, JavaString};
 use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Frame<'class> {
     Full {
         locals: Vec<FrameValue<'class>>,
@@ -26,6 +27,7 @@ pub enum Frame<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FrameValue<'class> {
     Top,
     Integer,
@@ -37,3 +39,210 @@ pub enum FrameValue<'class> {
     Class(Cow<'class, JavaStr>),
     Uninitialized(Label),
 }
+
+/// Merges two verification types where control flow joins, using the class hierarchy to find the
+/// common superclass of reference types. Incompatible types merge to [`FrameValue::Top`].
+pub fn merge_frame_values<'class>(
+    class_hierarchy: &dyn ClassHierarchy,
+    a: &FrameValue<'class>,
+    b: &FrameValue<'class>,
+) -> ClassFileResult<FrameValue<'class>> {
+    Ok(match (a, b) {
+        _ if a == b => a.clone(),
+        (FrameValue::Null, FrameValue::Class(_)) => b.clone(),
+        (FrameValue::Class(_), FrameValue::Null) => a.clone(),
+        (FrameValue::Class(a), FrameValue::Class(b)) => {
+            FrameValue::Class(merge_classes(class_hierarchy, a, b)?)
+        }
+        _ => FrameValue::Top,
+    })
+}
+
+fn merge_classes<'class>(
+    class_hierarchy: &dyn ClassHierarchy,
+    a: &JavaStr,
+    b: &JavaStr,
+) -> ClassFileResult<Cow<'class, JavaStr>> {
+    let (a_dimensions, a_element) = array_element(a)?;
+    let (b_dimensions, b_element) = array_element(b)?;
+    if a_dimensions == 0 && b_dimensions == 0 {
+        return Ok(Cow::Owned(class_hierarchy.common_super_class(a, b)?));
+    }
+
+    if let (Some(a_element), Some(b_element)) = (a_element, b_element) {
+        if a_dimensions == b_dimensions {
+            let element = class_hierarchy.common_super_class(a_element, b_element)?;
+            let mut result = JavaString::from(&a[..a_dimensions]);
+            result.push('L');
+            result.push_java_str(&element);
+            result.push(';');
+            return Ok(Cow::Owned(result));
+        }
+    }
+
+    // like ASM, an array of primitives is treated as an array of objects with one dimension fewer
+    let dimensions = (a_dimensions - a_element.is_none() as usize)
+        .min(b_dimensions - b_element.is_none() as usize);
+    if dimensions == 0 {
+        return Ok(Cow::Borrowed(JavaStr::from_str("java/lang/Object")));
+    }
+    let mut result = JavaString::from("[".repeat(dimensions));
+    result.push_str("Ljava/lang/Object;");
+    Ok(Cow::Owned(result))
+}
+
+/// Splits a class name into its array dimensions and element class, which is `None` for arrays of
+/// primitives.
+fn array_element(name: &JavaStr) -> ClassFileResult<(usize, Option<&JavaStr>)> {
+    let dimensions = name.bytes().take_while(|&b| b == b'[').count();
+    if dimensions == 0 {
+        return Ok((0, Some(name)));
+    }
+    let element = &name[dimensions..];
+    match element.as_bytes() {
+        [b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z'] => Ok((dimensions, None)),
+        [b'L', .., b';'] if element.len() > 2 => {
+            Ok((dimensions, Some(&element[1..element.len() - 1])))
+        }
+        _ => Err(ClassFileError::BadDescriptor(name.to_owned())),
+    }
+}
+
+/// Merges the locals of two frames where control flow joins, one value per slot. Locals beyond the
+/// end of the shorter frame are dropped, as they're undefined in one of the frames.
+pub fn merge_frame_locals<'class>(
+    class_hierarchy: &dyn ClassHierarchy,
+    a: &[FrameValue<'class>],
+    b: &[FrameValue<'class>],
+) -> ClassFileResult<Vec<FrameValue<'class>>> {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| merge_frame_values(class_hierarchy, a, b))
+        .collect()
+}
+
+/// Merges the operand stacks of two frames where control flow joins, which must have the same
+/// height.
+pub fn merge_frame_stacks<'class>(
+    class_hierarchy: &dyn ClassHierarchy,
+    a: &[FrameValue<'class>],
+    b: &[FrameValue<'class>],
+) -> ClassFileResult<Vec<FrameValue<'class>>> {
+    if a.len() != b.len() {
+        return Err(ClassFileError::OperandStackHeightMismatch {
+            expected: a.len(),
+            actual: b.len(),
+        });
+    }
+    merge_frame_locals(class_hierarchy, a, b)
+}
+
+/// Applies a frame to the locals of the previous frame, returning it as a full frame.
+pub(crate) fn expand_frame<'class>(
+    locals: &mut Vec<FrameValue<'class>>,
+    frame: Frame<'class>,
+) -> Frame<'class> {
+    let stack = match frame {
+        Frame::Full {
+            locals: new_locals,
+            stack,
+        }
+        | Frame::New {
+            locals: new_locals,
+            stack,
+        } => {
+            *locals = new_locals;
+            stack
+        }
+        Frame::Append { locals: new_locals } => {
+            locals.extend(new_locals);
+            Vec::new()
+        }
+        Frame::Chop { num_locals } => {
+            locals.truncate(locals.len().saturating_sub(num_locals as usize));
+            Vec::new()
+        }
+        Frame::Same => Vec::new(),
+        Frame::Same1 { stack_value } => vec![stack_value],
+    };
+    Frame::Full {
+        locals: locals.clone(),
+        stack,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        merge_frame_locals, merge_frame_stacks, merge_frame_values, ClassFileError, FrameValue,
+        SimpleClassHierarchy,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+
+    fn class(name: &str) -> FrameValue<'_> {
+        FrameValue::Class(Cow::Borrowed(JavaStr::from_str(name)))
+    }
+
+    #[test]
+    fn test_merge_frame_values() {
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy.insert("A", Some("java/lang/Object".into()), false);
+        hierarchy.insert("B", Some("A".into()), false);
+        hierarchy.insert("C", Some("A".into()), false);
+
+        let merge = |a, b| merge_frame_values(&hierarchy, &a, &b).unwrap();
+        assert_eq!(
+            FrameValue::Integer,
+            merge(FrameValue::Integer, FrameValue::Integer)
+        );
+        assert_eq!(
+            FrameValue::Top,
+            merge(FrameValue::Integer, FrameValue::Float)
+        );
+        assert_eq!(class("B"), merge(FrameValue::Null, class("B")));
+        assert_eq!(class("A"), merge(class("B"), class("C")));
+        assert_eq!(class("[LA;"), merge(class("[LB;"), class("[LC;")));
+        assert_eq!(class("java/lang/Object"), merge(class("[I"), class("[LB;")));
+        assert_eq!(
+            class("[Ljava/lang/Object;"),
+            merge(class("[[I"), class("[[F"))
+        );
+        assert_eq!(
+            class("[Ljava/lang/Object;"),
+            merge(class("[[I"), class("[[LB;"))
+        );
+        assert_eq!(
+            class("[Ljava/lang/Object;"),
+            merge(class("[[Ljava/lang/String;"), class("[Ljava/lang/Object;"))
+        );
+        assert_eq!(
+            class("[[Ljava/lang/Object;"),
+            merge(class("[[[LB;"), class("[[LC;"))
+        );
+        assert_eq!(class("java/lang/Object"), merge(class("A"), class("[LA;")));
+        for malformed in ["[", "[L", "[L;", "[V", "[LA"] {
+            assert_eq!(
+                Err(ClassFileError::BadDescriptor(malformed.into())),
+                merge_frame_values(&hierarchy, &class(malformed), &class("[LA;"))
+            );
+        }
+
+        assert_eq!(
+            vec![class("A"), FrameValue::Top],
+            merge_frame_locals(
+                &hierarchy,
+                &[class("B"), FrameValue::Integer, FrameValue::Float],
+                &[class("C"), FrameValue::Long],
+            )
+            .unwrap()
+        );
+        assert!(matches!(
+            merge_frame_stacks(&hierarchy, &[FrameValue::Integer], &[]),
+            Err(ClassFileError::OperandStackHeightMismatch {
+                expected: 1,
+                actual: 0
+            })
+        ));
+    }
+}
@@ -0,0 +1,876 @@
+use crate::{
+    merge_frame_values, ClassFileError, ClassFileResult, ClassHierarchy, Frame, FrameValue, Label,
+    LdcConstant, MethodAccess, MethodEvent, MethodEventProviders, MethodTryCatchBlockEvent,
+    NewArrayType, Opcode,
+};
+use derive_more::Debug;
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// An instruction as far as the types in its frame are concerned, recorded by the class writer
+/// as it writes the code.
+#[derive(Debug)]
+pub(crate) enum FrameInsn<'class> {
+    Insn(Opcode),
+    Push(FrameValue<'class>),
+    NewArray(NewArrayType),
+    Var {
+        opcode: Opcode,
+        var_index: u16,
+    },
+    Type {
+        opcode: Opcode,
+        ty: Cow<'class, JavaStr>,
+    },
+    Field {
+        opcode: Opcode,
+        desc: Cow<'class, JavaStr>,
+    },
+    Method {
+        opcode: Opcode,
+        owner: Cow<'class, JavaStr>,
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+    },
+    InvokeDynamic {
+        desc: Cow<'class, JavaStr>,
+    },
+    Jump {
+        opcode: Opcode,
+        label: Label,
+    },
+    Switch {
+        labels: Vec<Label>,
+    },
+    MultiANewArray {
+        desc: Cow<'class, JavaStr>,
+        dimensions: u8,
+    },
+}
+
+impl<'class> FrameInsn<'class> {
+    /// Converts an instruction event, or returns `None` if the event isn't an instruction. The
+    /// value pushed by a `new` instruction is identified by `new_label`.
+    pub(crate) fn from_event<P>(event: &MethodEvent<'class, P>, new_label: Label) -> Option<Self>
+    where
+        P: MethodEventProviders<'class>,
+    {
+        Some(match event {
+            MethodEvent::Insn(opcode) => FrameInsn::Insn(*opcode),
+            MethodEvent::BIPushInsn(_) | MethodEvent::SIPushInsn(_) => {
+                FrameInsn::Push(FrameValue::Integer)
+            }
+            MethodEvent::NewArrayInsn(ty) => FrameInsn::NewArray(*ty),
+            MethodEvent::VarInsn { opcode, var_index } => FrameInsn::Var {
+                opcode: *opcode,
+                var_index: *var_index,
+            },
+            MethodEvent::TypeInsn {
+                opcode: Opcode::New,
+                ..
+            } => FrameInsn::Push(FrameValue::Uninitialized(new_label)),
+            MethodEvent::TypeInsn { opcode, ty } => FrameInsn::Type {
+                opcode: *opcode,
+                ty: ty.clone(),
+            },
+            MethodEvent::FieldInsn { opcode, desc, .. } => FrameInsn::Field {
+                opcode: *opcode,
+                desc: desc.clone(),
+            },
+            MethodEvent::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                ..
+            } => FrameInsn::Method {
+                opcode: *opcode,
+                owner: owner.clone(),
+                name: name.clone(),
+                desc: desc.clone(),
+            },
+            MethodEvent::InvokeDynamicInsn { desc, .. } => {
+                FrameInsn::InvokeDynamic { desc: desc.clone() }
+            }
+            MethodEvent::JumpInsn { opcode, label } => FrameInsn::Jump {
+                opcode: *opcode,
+                label: *label,
+            },
+            MethodEvent::LdcInsn(constant) => FrameInsn::Push(ldc_value(constant)),
+            MethodEvent::IIncInsn { .. } => FrameInsn::Insn(Opcode::IInc),
+            MethodEvent::TableSwitchInsn { dflt, labels, .. } => FrameInsn::Switch {
+                labels: labels
+                    .iter()
+                    .copied()
+                    .chain(std::iter::once(*dflt))
+                    .collect(),
+            },
+            MethodEvent::LookupSwitchInsn { dflt, values } => FrameInsn::Switch {
+                labels: values
+                    .iter()
+                    .map(|(_, label)| *label)
+                    .chain(std::iter::once(*dflt))
+                    .collect(),
+            },
+            MethodEvent::MultiANewArrayInsn { desc, dimensions } => FrameInsn::MultiANewArray {
+                desc: desc.clone(),
+                dimensions: *dimensions,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// The types in the locals and on the stack before an instruction. Long and double values take up
+/// two slots, the second of which is [`FrameValue::Top`].
+#[derive(Debug, Clone)]
+pub(crate) struct FrameState<'class> {
+    pub(crate) locals: Vec<FrameValue<'class>>,
+    pub(crate) stack: Vec<FrameValue<'class>>,
+}
+
+/// Computes the StackMapTable frames of a method by simulating the types of its instructions.
+#[derive(Debug)]
+pub(crate) struct FrameComputer<'class> {
+    #[debug(skip)]
+    class_hierarchy: Arc<dyn ClassHierarchy>,
+    this_class: Cow<'class, JavaStr>,
+    initial_locals: Vec<FrameValue<'class>>,
+    insns: Vec<(usize, FrameInsn<'class>)>,
+    /// Offsets of instructions which are jumped to by code not known to the frame computer.
+    extra_frame_targets: Vec<usize>,
+}
+
+impl<'class> FrameComputer<'class> {
+    pub(crate) fn new(
+        class_hierarchy: Arc<dyn ClassHierarchy>,
+        this_class: Cow<'class, JavaStr>,
+        access: MethodAccess,
+        name: &JavaStr,
+        desc: &Cow<'class, JavaStr>,
+    ) -> Self {
+        let mut initial_locals = Vec::new();
+        for value in initial_frame_locals(&this_class, access, name, desc) {
+            push_value(&mut initial_locals, value);
+        }
+
+        FrameComputer {
+            class_hierarchy,
+            this_class,
+            initial_locals,
+            insns: Vec::new(),
+            extra_frame_targets: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add_insn(&mut self, offset: usize, insn: FrameInsn<'class>) {
+        self.insns.push((offset, insn));
+    }
+
+    /// Moves the instructions to their new offsets after the code has been rewritten.
+    pub(crate) fn relocate(&mut self, new_offset: impl Fn(usize) -> usize) {
+        for (offset, _) in &mut self.insns {
+            *offset = new_offset(*offset);
+        }
+        for offset in &mut self.extra_frame_targets {
+            *offset = new_offset(*offset);
+        }
+    }
+
+    pub(crate) fn add_frame_target(&mut self, offset: usize) {
+        self.extra_frame_targets.push(offset);
+    }
+
+    /// Computes the frames at each jump target and exception handler.
+    pub(crate) fn compute(
+        &self,
+        label_offset: impl Fn(Label) -> ClassFileResult<usize>,
+        try_catch_blocks: &[MethodTryCatchBlockEvent<'class>],
+    ) -> ClassFileResult<Vec<(usize, Frame<'class>)>> {
+        let (states, frame_targets) = self.analyze(label_offset, try_catch_blocks)?;
+
+        let mut frames = Vec::with_capacity(frame_targets.len());
+        let mut last_locals = frame_values(&self.initial_locals, true);
+        for index in frame_targets {
+            let state = &states[index];
+            let locals = frame_values(&state.locals, true);
+            let stack = frame_values(&state.stack, false);
+            frames.push((
+                self.insns[index].0,
+                compress_frame(&last_locals, &locals, stack),
+            ));
+            last_locals = locals;
+        }
+        Ok(frames)
+    }
+
+    /// Computes the state before each instruction.
+    pub(crate) fn compute_states(
+        &self,
+        label_offset: impl Fn(Label) -> ClassFileResult<usize>,
+        try_catch_blocks: &[MethodTryCatchBlockEvent<'class>],
+    ) -> ClassFileResult<Vec<FrameState<'class>>> {
+        self.analyze(label_offset, try_catch_blocks)
+            .map(|(states, _)| states)
+    }
+
+    /// Returns the state before each instruction, and the indexes of the instructions which need a
+    /// frame.
+    fn analyze(
+        &self,
+        label_offset: impl Fn(Label) -> ClassFileResult<usize>,
+        try_catch_blocks: &[MethodTryCatchBlockEvent<'class>],
+    ) -> ClassFileResult<(Vec<FrameState<'class>>, BTreeSet<usize>)> {
+        let insn_indexes: HashMap<usize, usize> = self
+            .insns
+            .iter()
+            .enumerate()
+            .map(|(index, (offset, _))| (*offset, index))
+            .collect();
+        let label_index = |label: Label| -> ClassFileResult<usize> {
+            insn_indexes
+                .get(&label_offset(label)?)
+                .copied()
+                .ok_or(ClassFileError::UnknownLabel(label))
+        };
+
+        let mut handlers = Vec::with_capacity(try_catch_blocks.len());
+        for try_catch_block in try_catch_blocks {
+            let exception = try_catch_block
+                .ty
+                .clone()
+                .unwrap_or(Cow::Borrowed(JavaStr::from_str("java/lang/Throwable")));
+            handlers.push((
+                label_offset(try_catch_block.start)?..label_offset(try_catch_block.end)?,
+                label_index(try_catch_block.handler)?,
+                FrameValue::Class(exception),
+            ));
+        }
+
+        let mut frame_targets: BTreeSet<_> =
+            handlers.iter().map(|(_, handler, _)| *handler).collect();
+        frame_targets.extend(
+            self.extra_frame_targets
+                .iter()
+                .filter_map(|offset| insn_indexes.get(offset).copied()),
+        );
+        let mut states = vec![None; self.insns.len()];
+        let mut worklist = Vec::new();
+        if !self.insns.is_empty() {
+            states[0] = Some(FrameState {
+                locals: self.initial_locals.clone(),
+                stack: Vec::new(),
+            });
+            worklist.push(0);
+        }
+
+        while let Some(index) = worklist.pop() {
+            let (offset, insn) = &self.insns[index];
+            let input = states[index]
+                .clone()
+                .expect("instruction in worklist has no state");
+            let mut state = input.clone();
+            execute_frame_insn(&self.this_class, &mut state, insn, *offset)?;
+
+            for (range, handler, exception) in &handlers {
+                if range.contains(offset) {
+                    for locals in [&input.locals, &state.locals] {
+                        let handler_state = FrameState {
+                            locals: locals.clone(),
+                            stack: vec![exception.clone()],
+                        };
+                        self.merge_into(&mut states, &mut worklist, *handler, handler_state)?;
+                    }
+                }
+            }
+
+            let mut successors = Vec::new();
+            let mut falls_through = true;
+            match insn {
+                FrameInsn::Insn(
+                    Opcode::IReturn
+                    | Opcode::LReturn
+                    | Opcode::FReturn
+                    | Opcode::DReturn
+                    | Opcode::AReturn
+                    | Opcode::Return
+                    | Opcode::AThrow,
+                ) => falls_through = false,
+                FrameInsn::Jump { opcode, label } => {
+                    successors.push(label_index(*label)?);
+                    falls_through = *opcode != Opcode::Goto;
+                }
+                FrameInsn::Switch { labels } => {
+                    for label in labels {
+                        successors.push(label_index(*label)?);
+                    }
+                    falls_through = false;
+                }
+                _ => {}
+            }
+            frame_targets.extend(successors.iter().copied());
+            if falls_through && index + 1 < self.insns.len() {
+                successors.push(index + 1);
+            }
+            for successor in successors {
+                self.merge_into(&mut states, &mut worklist, successor, state.clone())?;
+            }
+        }
+
+        if let Some(index) = states.iter().position(Option::is_none) {
+            return Err(ClassFileError::UnreachableCode(self.insns[index].0));
+        }
+        let states = states
+            .into_iter()
+            .map(|state| state.expect("checked for unreachable code"))
+            .collect();
+        Ok((states, frame_targets))
+    }
+
+    fn merge_into(
+        &self,
+        states: &mut [Option<FrameState<'class>>],
+        worklist: &mut Vec<usize>,
+        index: usize,
+        state: FrameState<'class>,
+    ) -> ClassFileResult<()> {
+        let Some(existing) = &mut states[index] else {
+            states[index] = Some(state);
+            worklist.push(index);
+            return Ok(());
+        };
+
+        if existing.stack.len() != state.stack.len() {
+            return Err(ClassFileError::StackHeightMismatch(self.insns[index].0));
+        }
+        let mut changed = false;
+        if existing.locals.len() > state.locals.len() {
+            existing.locals.truncate(state.locals.len());
+            changed = true;
+        }
+        for (existing, new) in existing
+            .locals
+            .iter_mut()
+            .zip(&state.locals)
+            .chain(existing.stack.iter_mut().zip(&state.stack))
+        {
+            let merged = merge_frame_values(&*self.class_hierarchy, existing, new)?;
+            if merged != *existing {
+                *existing = merged;
+                changed = true;
+            }
+        }
+
+        if changed {
+            worklist.push(index);
+        }
+        Ok(())
+    }
+}
+
+/// Simulates an instruction on the state before it, leaving the state after it.
+pub(crate) fn execute_frame_insn<'class>(
+    this_class: &JavaStr,
+    state: &mut FrameState<'class>,
+    insn: &FrameInsn<'class>,
+    offset: usize,
+) -> ClassFileResult<()> {
+    let stack = &mut state.stack;
+    let pop = |stack: &mut Vec<FrameValue<'class>>| {
+        stack.pop().ok_or(ClassFileError::StackUnderflow(offset))
+    };
+
+    match insn {
+        FrameInsn::Insn(opcode) => match opcode {
+            Opcode::Nop | Opcode::IInc => {}
+            Opcode::AALoad => {
+                pop(stack)?;
+                let element = match pop(stack)? {
+                    FrameValue::Class(array) if array.starts_with('[') => {
+                        desc_value(&array, 1..array.len())
+                    }
+                    FrameValue::Null => FrameValue::Null,
+                    _ => FrameValue::Class(Cow::Borrowed(JavaStr::from_str("java/lang/Object"))),
+                };
+                push_value(stack, element);
+            }
+            Opcode::Dup => {
+                let value1 = pop(stack)?;
+                stack.extend([value1.clone(), value1]);
+            }
+            Opcode::DupX1 => {
+                let value1 = pop(stack)?;
+                let value2 = pop(stack)?;
+                stack.extend([value1.clone(), value2, value1]);
+            }
+            Opcode::DupX2 => {
+                let value1 = pop(stack)?;
+                let value2 = pop(stack)?;
+                let value3 = pop(stack)?;
+                stack.extend([value1.clone(), value3, value2, value1]);
+            }
+            Opcode::Dup2 => {
+                let value1 = pop(stack)?;
+                let value2 = pop(stack)?;
+                stack.extend([value2.clone(), value1.clone(), value2, value1]);
+            }
+            Opcode::Dup2X1 => {
+                let value1 = pop(stack)?;
+                let value2 = pop(stack)?;
+                let value3 = pop(stack)?;
+                stack.extend([value2.clone(), value1.clone(), value3, value2, value1]);
+            }
+            Opcode::Dup2X2 => {
+                let value1 = pop(stack)?;
+                let value2 = pop(stack)?;
+                let value3 = pop(stack)?;
+                let value4 = pop(stack)?;
+                stack.extend([
+                    value2.clone(),
+                    value1.clone(),
+                    value4,
+                    value3,
+                    value2,
+                    value1,
+                ]);
+            }
+            Opcode::Swap => {
+                let value1 = pop(stack)?;
+                let value2 = pop(stack)?;
+                stack.extend([value1, value2]);
+            }
+            _ => {
+                let result = insn_result(*opcode);
+                let pops = value_size(result.as_ref()) as i32 - opcode.stack_delta();
+                for _ in 0..pops {
+                    pop(stack)?;
+                }
+                if let Some(result) = result {
+                    push_value(stack, result);
+                }
+            }
+        },
+        FrameInsn::Push(value) => push_value(stack, value.clone()),
+        FrameInsn::NewArray(ty) => {
+            pop(stack)?;
+            let desc = match ty {
+                NewArrayType::Boolean => "[Z",
+                NewArrayType::Char => "[C",
+                NewArrayType::Float => "[F",
+                NewArrayType::Double => "[D",
+                NewArrayType::Byte => "[B",
+                NewArrayType::Short => "[S",
+                NewArrayType::Int => "[I",
+                NewArrayType::Long => "[J",
+            };
+            stack.push(FrameValue::Class(Cow::Borrowed(JavaStr::from_str(desc))));
+        }
+        FrameInsn::Var { opcode, var_index } => {
+            let var_index = *var_index as usize;
+            match opcode {
+                Opcode::ILoad => stack.push(FrameValue::Integer),
+                Opcode::FLoad => stack.push(FrameValue::Float),
+                Opcode::LLoad => push_value(stack, FrameValue::Long),
+                Opcode::DLoad => push_value(stack, FrameValue::Double),
+                Opcode::ALoad => stack.push(
+                    state
+                        .locals
+                        .get(var_index)
+                        .cloned()
+                        .unwrap_or(FrameValue::Top),
+                ),
+                Opcode::IStore | Opcode::FStore | Opcode::AStore => {
+                    let value = pop(stack)?;
+                    set_local(&mut state.locals, var_index, value);
+                }
+                Opcode::LStore | Opcode::DStore => {
+                    pop(stack)?;
+                    let value = pop(stack)?;
+                    set_local(&mut state.locals, var_index, value);
+                }
+                _ => return Err(ClassFileError::WriterUnsupported("frames for subroutines")),
+            }
+        }
+        FrameInsn::Type { opcode, ty } => {
+            pop(stack)?;
+            match opcode {
+                Opcode::ANewArray => {
+                    let mut array = JavaString::from("[");
+                    if ty.starts_with('[') {
+                        array.push_java_str(ty);
+                    } else {
+                        array.push('L');
+                        array.push_java_str(ty);
+                        array.push(';');
+                    }
+                    stack.push(FrameValue::Class(Cow::Owned(array)));
+                }
+                Opcode::Instanceof => stack.push(FrameValue::Integer),
+                _ => stack.push(FrameValue::Class(ty.clone())),
+            }
+        }
+        FrameInsn::Field { opcode, desc } => {
+            let value = desc_value(desc, 0..desc.len());
+            let size = value_size(Some(&value));
+            match opcode {
+                Opcode::GetStatic => push_value(stack, value),
+                Opcode::PutStatic => {
+                    for _ in 0..size {
+                        pop(stack)?;
+                    }
+                }
+                Opcode::GetField => {
+                    pop(stack)?;
+                    push_value(stack, value);
+                }
+                _ => {
+                    for _ in 0..size + 1 {
+                        pop(stack)?;
+                    }
+                }
+            }
+        }
+        FrameInsn::Method {
+            opcode,
+            owner,
+            name,
+            desc,
+        } => {
+            let (arguments, return_value) = parse_method_desc(desc);
+            for argument in &arguments {
+                for _ in 0..value_size(Some(argument)) {
+                    pop(stack)?;
+                }
+            }
+            if *opcode != Opcode::InvokeStatic {
+                let receiver = pop(stack)?;
+                if *opcode == Opcode::InvokeSpecial && name.as_ref() == "<init>" {
+                    let initialized = match receiver {
+                        FrameValue::UninitializedThis => Some(Cow::Owned(this_class.to_owned())),
+                        FrameValue::Uninitialized(_) => Some(owner.clone()),
+                        _ => None,
+                    };
+                    if let Some(initialized) = initialized {
+                        let initialized = FrameValue::Class(initialized);
+                        for value in state.locals.iter_mut().chain(stack.iter_mut()) {
+                            if *value == receiver {
+                                *value = initialized.clone();
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some(return_value) = return_value {
+                push_value(stack, return_value);
+            }
+        }
+        FrameInsn::InvokeDynamic { desc } => {
+            let (arguments, return_value) = parse_method_desc(desc);
+            for argument in &arguments {
+                for _ in 0..value_size(Some(argument)) {
+                    pop(stack)?;
+                }
+            }
+            if let Some(return_value) = return_value {
+                push_value(stack, return_value);
+            }
+        }
+        FrameInsn::Jump { opcode, .. } => match opcode {
+            Opcode::Goto => {}
+            Opcode::Jsr => return Err(ClassFileError::WriterUnsupported("frames for subroutines")),
+            Opcode::IfICmpEq
+            | Opcode::IfICmpNe
+            | Opcode::IfICmpLt
+            | Opcode::IfICmpGe
+            | Opcode::IfICmpGt
+            | Opcode::IfICmpLe
+            | Opcode::IfACmpEq
+            | Opcode::IfACmpNe => {
+                pop(stack)?;
+                pop(stack)?;
+            }
+            _ => {
+                pop(stack)?;
+            }
+        },
+        FrameInsn::Switch { .. } => {
+            pop(stack)?;
+        }
+        FrameInsn::MultiANewArray { desc, dimensions } => {
+            for _ in 0..*dimensions {
+                pop(stack)?;
+            }
+            stack.push(FrameValue::Class(desc.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+/// The type pushed by an `ldc` of the given constant.
+pub(crate) fn ldc_value<'class>(constant: &LdcConstant<'class>) -> FrameValue<'class> {
+    let class = |name| FrameValue::Class(Cow::Borrowed(JavaStr::from_str(name)));
+    match constant {
+        LdcConstant::Integer(_) => FrameValue::Integer,
+        LdcConstant::Float(_) => FrameValue::Float,
+        LdcConstant::Long(_) => FrameValue::Long,
+        LdcConstant::Double(_) => FrameValue::Double,
+        LdcConstant::String(_) => class("java/lang/String"),
+        LdcConstant::Class(_) => class("java/lang/Class"),
+        LdcConstant::MethodType(_) => class("java/lang/invoke/MethodType"),
+        LdcConstant::Handle(_) => class("java/lang/invoke/MethodHandle"),
+        LdcConstant::ConstantDynamic(constant) => {
+            desc_value(&constant.desc, 0..constant.desc.len())
+        }
+    }
+}
+
+fn insn_result<'class>(opcode: Opcode) -> Option<FrameValue<'class>> {
+    match opcode {
+        Opcode::AConstNull => Some(FrameValue::Null),
+        Opcode::IConstM1
+        | Opcode::IConst0
+        | Opcode::IConst1
+        | Opcode::IConst2
+        | Opcode::IConst3
+        | Opcode::IConst4
+        | Opcode::IConst5
+        | Opcode::IALoad
+        | Opcode::BALoad
+        | Opcode::CALoad
+        | Opcode::SALoad
+        | Opcode::IAdd
+        | Opcode::ISub
+        | Opcode::IMul
+        | Opcode::IDiv
+        | Opcode::IRem
+        | Opcode::INeg
+        | Opcode::IShl
+        | Opcode::IShr
+        | Opcode::IUShr
+        | Opcode::IAnd
+        | Opcode::IOr
+        | Opcode::IXor
+        | Opcode::L2i
+        | Opcode::F2i
+        | Opcode::D2i
+        | Opcode::I2b
+        | Opcode::I2c
+        | Opcode::I2s
+        | Opcode::LCmp
+        | Opcode::FCmpL
+        | Opcode::FCmpG
+        | Opcode::DCmpL
+        | Opcode::DCmpG
+        | Opcode::ArrayLength => Some(FrameValue::Integer),
+        Opcode::LConst0
+        | Opcode::LConst1
+        | Opcode::LALoad
+        | Opcode::LAdd
+        | Opcode::LSub
+        | Opcode::LMul
+        | Opcode::LDiv
+        | Opcode::LRem
+        | Opcode::LNeg
+        | Opcode::LShl
+        | Opcode::LShr
+        | Opcode::LUShr
+        | Opcode::LAnd
+        | Opcode::LOr
+        | Opcode::LXor
+        | Opcode::I2l
+        | Opcode::F2l
+        | Opcode::D2l => Some(FrameValue::Long),
+        Opcode::FConst0
+        | Opcode::FConst1
+        | Opcode::FConst2
+        | Opcode::FALoad
+        | Opcode::FAdd
+        | Opcode::FSub
+        | Opcode::FMul
+        | Opcode::FDiv
+        | Opcode::FRem
+        | Opcode::FNeg
+        | Opcode::I2f
+        | Opcode::L2f
+        | Opcode::D2f => Some(FrameValue::Float),
+        Opcode::DConst0
+        | Opcode::DConst1
+        | Opcode::DALoad
+        | Opcode::DAdd
+        | Opcode::DSub
+        | Opcode::DMul
+        | Opcode::DDiv
+        | Opcode::DRem
+        | Opcode::DNeg
+        | Opcode::I2d
+        | Opcode::L2d
+        | Opcode::F2d => Some(FrameValue::Double),
+        _ => None,
+    }
+}
+
+fn value_size(value: Option<&FrameValue>) -> usize {
+    match value {
+        Some(FrameValue::Long | FrameValue::Double) => 2,
+        Some(_) => 1,
+        None => 0,
+    }
+}
+
+/// The locals of the implicit frame at the start of a method, where long and double values take up
+/// a single entry.
+pub(crate) fn initial_frame_locals<'class>(
+    this_class: &Cow<'class, JavaStr>,
+    access: MethodAccess,
+    name: &JavaStr,
+    desc: &Cow<'class, JavaStr>,
+) -> Vec<FrameValue<'class>> {
+    let mut locals = Vec::new();
+    if !access.contains(MethodAccess::Static) {
+        if name == "<init>" && this_class.as_ref() != "java/lang/Object" {
+            locals.push(FrameValue::UninitializedThis);
+        } else {
+            locals.push(FrameValue::Class(this_class.clone()));
+        }
+    }
+    let (arguments, _) = parse_method_desc(desc);
+    locals.extend(arguments);
+    locals
+}
+
+pub(crate) fn push_value<'class>(values: &mut Vec<FrameValue<'class>>, value: FrameValue<'class>) {
+    let is_wide = matches!(value, FrameValue::Long | FrameValue::Double);
+    values.push(value);
+    if is_wide {
+        values.push(FrameValue::Top);
+    }
+}
+
+fn set_local<'class>(
+    locals: &mut Vec<FrameValue<'class>>,
+    index: usize,
+    value: FrameValue<'class>,
+) {
+    let size = value_size(Some(&value));
+    if locals.len() < index + size {
+        locals.resize(index + size, FrameValue::Top);
+    }
+    // overwriting the second half of a long or double invalidates it
+    if index > 0 && matches!(locals[index - 1], FrameValue::Long | FrameValue::Double) {
+        locals[index - 1] = FrameValue::Top;
+    }
+    locals[index] = value;
+    if size == 2 {
+        locals[index + 1] = FrameValue::Top;
+    }
+}
+
+/// Converts slots to the values written in a frame, where long and double values take up a single
+/// entry. Trailing tops are removed from the locals.
+fn frame_values<'class>(slots: &[FrameValue<'class>], is_locals: bool) -> Vec<FrameValue<'class>> {
+    let mut result = Vec::with_capacity(slots.len());
+    let mut index = 0;
+    while index < slots.len() {
+        result.push(slots[index].clone());
+        index += value_size(Some(&slots[index]));
+    }
+    if is_locals {
+        while result.last() == Some(&FrameValue::Top) {
+            result.pop();
+        }
+    }
+    result
+}
+
+fn compress_frame<'class>(
+    last_locals: &[FrameValue<'class>],
+    locals: &[FrameValue<'class>],
+    mut stack: Vec<FrameValue<'class>>,
+) -> Frame<'class> {
+    if locals == last_locals {
+        if stack.is_empty() {
+            return Frame::Same;
+        }
+        if stack.len() == 1 {
+            return Frame::Same1 {
+                stack_value: stack.pop().unwrap(),
+            };
+        }
+    }
+    if stack.is_empty() {
+        if locals.len() > last_locals.len()
+            && locals.len() - last_locals.len() <= 3
+            && locals.starts_with(last_locals)
+        {
+            return Frame::Append {
+                locals: locals[last_locals.len()..].to_vec(),
+            };
+        }
+        if last_locals.len() > locals.len()
+            && last_locals.len() - locals.len() <= 3
+            && last_locals.starts_with(locals)
+        {
+            return Frame::Chop {
+                num_locals: (last_locals.len() - locals.len()) as u8,
+            };
+        }
+    }
+    Frame::Full {
+        locals: locals.to_vec(),
+        stack,
+    }
+}
+
+fn parse_method_desc<'class>(
+    desc: &Cow<'class, JavaStr>,
+) -> (Vec<FrameValue<'class>>, Option<FrameValue<'class>>) {
+    let bytes = desc.as_bytes();
+    let mut arguments = Vec::new();
+    let mut index = 1;
+    while index < bytes.len() && bytes[index] != b')' {
+        let end = type_end(bytes, index);
+        arguments.push(desc_value(desc, index..end));
+        index = end;
+    }
+    let return_value = match bytes.get(index + 1) {
+        Some(b'V') | None => None,
+        Some(_) => Some(desc_value(desc, index + 1..bytes.len())),
+    };
+    (arguments, return_value)
+}
+
+pub(crate) fn type_end(bytes: &[u8], start: usize) -> usize {
+    let mut index = start;
+    while bytes.get(index) == Some(&b'[') {
+        index += 1;
+    }
+    if bytes.get(index) == Some(&b'L') {
+        while index < bytes.len() && bytes[index] != b';' {
+            index += 1;
+        }
+    }
+    (index + 1).min(bytes.len())
+}
+
+/// Returns the frame value of the field descriptor in the given range of a descriptor.
+fn desc_value<'class>(desc: &Cow<'class, JavaStr>, range: Range<usize>) -> FrameValue<'class> {
+    match desc.as_bytes().get(range.start) {
+        Some(b'Z' | b'B' | b'C' | b'S' | b'I') => FrameValue::Integer,
+        Some(b'F') => FrameValue::Float,
+        Some(b'J') => FrameValue::Long,
+        Some(b'D') => FrameValue::Double,
+        Some(b'L') => FrameValue::Class(sub_cow(desc, range.start + 1..range.end - 1)),
+        _ => FrameValue::Class(sub_cow(desc, range)),
+    }
+}
+
+fn sub_cow<'class>(s: &Cow<'class, JavaStr>, range: Range<usize>) -> Cow<'class, JavaStr> {
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[range]),
+        Cow::Owned(s) => Cow::Owned(s[range].to_owned()),
+    }
+}
@@ -0,0 +1,219 @@
+use crate::frame::expand_frame;
+use crate::frame_computer::{
+    execute_frame_insn, initial_frame_locals, push_value, FrameInsn, FrameState,
+};
+use crate::{
+    ClassFileResult, Frame, FrameValue, Label, MethodAccess, MethodEvent, MethodEventProviders,
+    Opcode,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+/// Follows the types in the locals and on the stack of a method as its events pass through, so
+/// that a transformation can see the types at the point of each instruction, such as what is on
+/// the top of the stack where code is injected.
+///
+/// Feed it each event of the method's code with [`visit`](FrameTracker::visit) after handling
+/// the event. The types are only known in code which is reachable by falling through from the
+/// start of the method or which follows a frame, so the events must include the frames of the
+/// method. Long and double values take up two slots, the second of which is [`FrameValue::Top`].
+/// The value created by a `new` instruction is identified by the label before it, or a synthetic
+/// label if there is none.
+#[derive(Debug, Clone)]
+pub struct FrameTracker<'class> {
+    this_class: Cow<'class, JavaStr>,
+    /// The locals of the last frame, where long and double values take up a single entry.
+    frame_locals: Vec<FrameValue<'class>>,
+    state: Option<FrameState<'class>>,
+    insn_count: u32,
+    /// The first label since the last instruction.
+    label: Option<Label>,
+}
+
+impl<'class> FrameTracker<'class> {
+    pub fn new(
+        this_class: impl Into<Cow<'class, JavaStr>>,
+        access: MethodAccess,
+        name: &JavaStr,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) -> Self {
+        let this_class = this_class.into();
+        let frame_locals = initial_frame_locals(&this_class, access, name, &desc.into());
+        let mut locals = Vec::new();
+        for value in frame_locals.iter().cloned() {
+            push_value(&mut locals, value);
+        }
+        FrameTracker {
+            this_class,
+            frame_locals,
+            state: Some(FrameState {
+                locals,
+                stack: Vec::new(),
+            }),
+            insn_count: 0,
+            label: None,
+        }
+    }
+
+    pub fn visit<P>(&mut self, event: &MethodEvent<'class, P>) -> ClassFileResult<()>
+    where
+        P: MethodEventProviders<'class>,
+    {
+        match event {
+            MethodEvent::Label(label) => {
+                self.label.get_or_insert(*label);
+            }
+            MethodEvent::Frame(frame) => {
+                let Frame::Full { locals, stack } =
+                    expand_frame(&mut self.frame_locals, frame.clone())
+                else {
+                    unreachable!("expanded frames are full frames");
+                };
+                let mut state = FrameState {
+                    locals: Vec::new(),
+                    stack: Vec::new(),
+                };
+                for value in locals {
+                    push_value(&mut state.locals, value);
+                }
+                for value in stack {
+                    push_value(&mut state.stack, value);
+                }
+                self.state = Some(state);
+            }
+            event => {
+                let new_label = self
+                    .label
+                    .unwrap_or_else(|| Label::synthetic(self.insn_count));
+                let Some(insn) = FrameInsn::from_event(event, new_label) else {
+                    return Ok(());
+                };
+                if let Some(state) = &mut self.state {
+                    execute_frame_insn(&self.this_class, state, &insn, self.insn_count as usize)?;
+                }
+                let falls_through = match insn {
+                    FrameInsn::Insn(opcode) => !matches!(
+                        opcode,
+                        Opcode::IReturn
+                            | Opcode::LReturn
+                            | Opcode::FReturn
+                            | Opcode::DReturn
+                            | Opcode::AReturn
+                            | Opcode::Return
+                            | Opcode::AThrow
+                    ),
+                    FrameInsn::Jump { opcode, .. } => opcode != Opcode::Goto,
+                    FrameInsn::Switch { .. } => false,
+                    _ => true,
+                };
+                if !falls_through {
+                    self.state = None;
+                }
+                self.insn_count += 1;
+                self.label = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// The types in the locals after the events visited so far, or `None` if the current code is
+    /// unreachable.
+    pub fn locals(&self) -> Option<&[FrameValue<'class>]> {
+        self.state.as_ref().map(|state| &state.locals[..])
+    }
+
+    /// The types on the stack after the events visited so far, with the top of the stack last, or
+    /// `None` if the current code is unreachable.
+    pub fn stack(&self) -> Option<&[FrameValue<'class>]> {
+        self.state.as_ref().map(|state| &state.stack[..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ClassNode;
+    use crate::{ClassReader, ClassReaderFlags, FrameTracker, FrameValue, MethodEvent, Opcode};
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    fn class_value(name: &str) -> FrameValue<'_> {
+        FrameValue::Class(Cow::Borrowed(JavaStr::from_str(name)))
+    }
+
+    #[test]
+    fn test_track_constructor() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let method = &class.methods[0];
+        assert_eq!("<init>", method.name.as_ref());
+        let mut tracker = FrameTracker::new(
+            class.name.clone(),
+            method.access,
+            &method.name,
+            method.desc.clone(),
+        );
+        for event in method.to_event().events {
+            let event = event.unwrap();
+            let is_return = matches!(event, MethodEvent::Insn(Opcode::Return));
+            tracker.visit(&event).unwrap();
+            if matches!(event, MethodEvent::VarInsn { .. }) {
+                assert_eq!(Some(&[FrameValue::UninitializedThis][..]), tracker.stack());
+            } else if matches!(event, MethodEvent::MethodInsn { .. }) {
+                assert_eq!(Some(&[class_value("HelloWorld")][..]), tracker.locals());
+                assert_eq!(Some(&[][..]), tracker.stack());
+            } else if is_return {
+                assert_eq!(None, tracker.stack());
+            }
+        }
+    }
+
+    #[test]
+    fn test_track_frames() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "tryCatch")
+            .unwrap();
+        let mut tracker = FrameTracker::new(
+            class.name.clone(),
+            method.access,
+            &method.name,
+            method.desc.clone(),
+        );
+        let mut checked_cast = false;
+        let mut handled = false;
+        for event in method.to_event().events {
+            let event = event.unwrap();
+            tracker.visit(&event).unwrap();
+            match event {
+                MethodEvent::TypeInsn {
+                    opcode: Opcode::CheckCast,
+                    ..
+                } if !checked_cast => {
+                    assert_eq!(
+                        Some(&[class_value("java/lang/String")][..]),
+                        tracker.stack()
+                    );
+                    checked_cast = true;
+                }
+                MethodEvent::Frame(_) if !handled => {
+                    // the first frame is the exception handler after the try block returns
+                    let stack = tracker.stack().unwrap();
+                    assert_eq!(1, stack.len());
+                    assert!(matches!(stack[0], FrameValue::Class(_)));
+                    assert_eq!(
+                        Some(&class_value("TestCode")),
+                        tracker.locals().unwrap().first()
+                    );
+                    handled = true;
+                }
+                _ => {}
+            }
+        }
+        assert!(checked_cast && handled);
+    }
+}
@@ -4,6 +4,7 @@ use java_string::JavaStr;
 use std::borrow::Cow;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display, TryFrom)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 #[non_exhaustive]
 #[try_from(repr)]
@@ -26,6 +27,7 @@ impl HandleKind {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)] // TODO: Display
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Handle<'class> {
     pub kind: HandleKind,
     pub owner: Cow<'class, JavaStr>,
@@ -35,6 +37,7 @@ pub struct Handle<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstantDynamic<'class> {
     pub name: Cow<'class, JavaStr>,
     pub desc: Cow<'class, JavaStr>,
@@ -43,6 +46,7 @@ pub struct ConstantDynamic<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BootstrapMethodArgument<'class> {
     Integer(i32),
     Float(f32),
@@ -0,0 +1,607 @@
+use crate::{ClassFileError, ClassFileResult, ClassReader, ClassReaderFlags};
+use derive_more::Debug;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+impl From<ZipError> for ClassFileError {
+    fn from(err: ZipError) -> Self {
+        match err {
+            ZipError::Io(err) => err.into(),
+            err => ClassFileError::Io {
+                kind: std::io::ErrorKind::InvalidData,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+/// A file in a jar or a classpath directory, named by its path relative to the root with `/` as
+/// the separator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JarEntry {
+    pub name: String,
+    #[debug("{} bytes", data.len())]
+    pub data: Vec<u8>,
+}
+
+impl JarEntry {
+    pub fn is_class(&self) -> bool {
+        self.name.ends_with(".class")
+    }
+
+    /// The internal name of the class in this entry, as implied by its name. Classes of
+    /// multi-release jars in `META-INF/versions/<version>/` have the same internal name as the
+    /// class they replace.
+    pub fn class_name(&self) -> Option<&str> {
+        let name = self.name.strip_suffix(".class")?;
+        match name.strip_prefix("META-INF/versions/") {
+            Some(versioned) => versioned.split_once('/').map(|(_, name)| name),
+            None => Some(name),
+        }
+    }
+
+    /// The release of the `META-INF/versions/<version>/` directory of a multi-release jar which
+    /// this entry is in. Releases before 9 aren't valid versions.
+    pub fn version(&self) -> Option<u16> {
+        let (version, _) = self
+            .name
+            .strip_prefix("META-INF/versions/")?
+            .split_once('/')?;
+        version
+            .parse()
+            .ok()
+            .filter(|&version| version >= FIRST_VERSIONED_RELEASE)
+    }
+
+    pub fn class_reader(&self, reader_flags: ClassReaderFlags) -> ClassFileResult<ClassReader<'_>> {
+        ClassReader::new(&self.data, reader_flags)
+    }
+}
+
+/// The first Java release which supports multi-release jars.
+const FIRST_VERSIONED_RELEASE: u16 = 9;
+
+/// A class as seen by a particular Java release, see [`JarReader::versioned_classes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionedClass {
+    /// The internal name of the class.
+    pub name: String,
+    /// The release of the `META-INF/versions/<version>/` directory the class was read from, or
+    /// `None` if it's the class in the root of the jar.
+    pub version: Option<u16>,
+    pub entry: JarEntry,
+}
+
+/// Reads the entries of a jar file, or any other zip archive.
+#[derive(Debug)]
+pub struct JarReader<R = BufReader<File>> {
+    #[debug(skip)]
+    archive: ZipArchive<R>,
+}
+
+impl JarReader {
+    pub fn open(path: impl AsRef<Path>) -> ClassFileResult<Self> {
+        JarReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R> JarReader<R>
+where
+    R: Read + Seek,
+{
+    pub fn new(reader: R) -> ClassFileResult<Self> {
+        Ok(JarReader {
+            archive: ZipArchive::new(reader)?,
+        })
+    }
+
+    /// The number of entries in the archive, including directories.
+    pub fn len(&self) -> usize {
+        self.archive.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.archive.is_empty()
+    }
+
+    /// Reads the entry with the given name, or returns `None` if there is no such file.
+    pub fn read(&mut self, name: &str) -> ClassFileResult<Option<JarEntry>> {
+        let mut file = match self.archive.by_name(name) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        if file.is_dir() {
+            return Ok(None);
+        }
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        Ok(Some(JarEntry {
+            name: name.to_owned(),
+            data,
+        }))
+    }
+
+    /// Reads the class with the given internal name.
+    pub fn read_class(&mut self, internal_name: &str) -> ClassFileResult<Option<JarEntry>> {
+        self.read(&format!("{internal_name}.class"))
+    }
+
+    /// Whether the main section of the manifest has `Multi-Release: true`. Otherwise, the
+    /// `META-INF/versions` directory is just a resource.
+    pub fn is_multi_release(&mut self) -> ClassFileResult<bool> {
+        let Some(manifest) = self.read("META-INF/MANIFEST.MF")? else {
+            return Ok(false);
+        };
+        Ok(String::from_utf8_lossy(&manifest.data)
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .any(|(key, value)| {
+                key.eq_ignore_ascii_case("Multi-Release")
+                    && value.trim().eq_ignore_ascii_case("true")
+            }))
+    }
+
+    /// Reads the class with the given internal name as seen by the given Java release, which is
+    /// the one in the latest `META-INF/versions/<version>/` directory not after the release if the
+    /// jar is multi-release.
+    pub fn read_versioned_class(
+        &mut self,
+        internal_name: &str,
+        release: u16,
+    ) -> ClassFileResult<Option<VersionedClass>> {
+        if self.is_multi_release()? {
+            for version in (FIRST_VERSIONED_RELEASE..=release).rev() {
+                let name = format!("META-INF/versions/{version}/{internal_name}.class");
+                if let Some(entry) = self.read(&name)? {
+                    return Ok(Some(VersionedClass {
+                        name: internal_name.to_owned(),
+                        version: Some(version),
+                        entry,
+                    }));
+                }
+            }
+        }
+        Ok(self.read_class(internal_name)?.map(|entry| VersionedClass {
+            name: internal_name.to_owned(),
+            version: None,
+            entry,
+        }))
+    }
+
+    /// Reads every class as seen by the given Java release, like [`read_versioned_class`]. The
+    /// classes are sorted by name.
+    ///
+    /// [`read_versioned_class`]: Self::read_versioned_class
+    pub fn versioned_classes(&mut self, release: u16) -> ClassFileResult<Vec<VersionedClass>> {
+        let multi_release = self.is_multi_release()?;
+        let mut classes = BTreeMap::new();
+        for entry in self.entries() {
+            let entry = entry?;
+            let version = entry.version();
+            if entry.name.starts_with("META-INF/versions/")
+                && !(multi_release && version.is_some_and(|version| version <= release))
+            {
+                continue;
+            }
+            let Some(name) = entry.class_name() else {
+                continue;
+            };
+            if classes
+                .get(name)
+                .is_some_and(|class: &VersionedClass| class.version > version)
+            {
+                continue;
+            }
+            classes.insert(
+                name.to_owned(),
+                VersionedClass {
+                    name: name.to_owned(),
+                    version,
+                    entry,
+                },
+            );
+        }
+        Ok(classes.into_values().collect())
+    }
+
+    /// Iterates over the files in the archive, class files and resources alike, in the order they
+    /// are stored. Directories are skipped.
+    pub fn entries(&mut self) -> JarEntries<'_, R> {
+        JarEntries {
+            archive: &mut self.archive,
+            index: 0,
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<R> JarReader<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the class files of the jar, to be processed on all cores. The entries are read one by
+    /// one first, as they all come from the same reader.
+    pub fn par_classes(&mut self) -> ClassFileResult<rayon::vec::IntoIter<JarEntry>> {
+        let mut classes = Vec::new();
+        for entry in self.entries() {
+            let entry = entry?;
+            if entry.is_class() {
+                classes.push(entry);
+            }
+        }
+        Ok(classes.into_par_iter())
+    }
+
+    /// Maps each class of the jar on all cores, and returns the results in the order of the
+    /// classes.
+    pub fn par_map_classes<T, F>(&mut self, map: F) -> ClassFileResult<Vec<T>>
+    where
+        T: Send,
+        F: Fn(JarEntry) -> ClassFileResult<T> + Sync + Send,
+    {
+        self.par_classes()?.map(map).collect()
+    }
+
+    /// Transforms each class of the jar on all cores, and returns all files of the jar in their
+    /// original order. Classes for which the transform returns `None` are removed, and resources
+    /// are kept as they are.
+    pub fn par_transform<F>(&mut self, transform: F) -> ClassFileResult<Vec<JarEntry>>
+    where
+        F: Fn(&JarEntry) -> ClassFileResult<Option<Vec<u8>>> + Sync + Send,
+    {
+        let entries = self.entries().collect::<ClassFileResult<Vec<_>>>()?;
+        let entries = entries
+            .into_par_iter()
+            .map(|entry| {
+                if !entry.is_class() {
+                    return Ok(Some(entry));
+                }
+                Ok(transform(&entry)?.map(|data| JarEntry {
+                    name: entry.name,
+                    data,
+                }))
+            })
+            .collect::<ClassFileResult<Vec<_>>>()?;
+        Ok(entries.into_iter().flatten().collect())
+    }
+}
+
+#[derive(Debug)]
+pub struct JarEntries<'a, R> {
+    #[debug(skip)]
+    archive: &'a mut ZipArchive<R>,
+    index: usize,
+}
+
+impl<R> Iterator for JarEntries<'_, R>
+where
+    R: Read + Seek,
+{
+    type Item = ClassFileResult<JarEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.archive.len() {
+            let index = self.index;
+            self.index += 1;
+            let mut file = match self.archive.by_index(index) {
+                Ok(file) => file,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if file.is_dir() {
+                continue;
+            }
+            let name = file.name().to_owned();
+            let mut data = Vec::with_capacity(file.size() as usize);
+            return Some(match file.read_to_end(&mut data) {
+                Ok(_) => Ok(JarEntry { name, data }),
+                Err(err) => Err(err.into()),
+            });
+        }
+        None
+    }
+}
+
+const JMOD_MAGIC: [u8; 4] = [b'J', b'M', 1, 0];
+
+/// Reads a `.jmod` file from the `jmods` directory of a JDK, which is a zip archive after a 4 byte
+/// header. Classes are in its `classes/` directory, next to directories for native libraries,
+/// commands, configuration and so on.
+#[derive(Debug)]
+pub struct JmodReader {
+    jar: JarReader<Cursor<Vec<u8>>>,
+}
+
+impl JmodReader {
+    pub fn open(path: impl AsRef<Path>) -> ClassFileResult<Self> {
+        JmodReader::new(std::fs::read(path)?)
+    }
+
+    pub fn new(mut data: Vec<u8>) -> ClassFileResult<Self> {
+        if !data.starts_with(&JMOD_MAGIC) {
+            return Err(ClassFileError::BadMagic);
+        }
+        data.drain(..JMOD_MAGIC.len());
+        Ok(JmodReader {
+            jar: JarReader::new(Cursor::new(data))?,
+        })
+    }
+
+    /// The archive after the header, for reading entries outside of `classes/`.
+    pub fn jar(&mut self) -> &mut JarReader<Cursor<Vec<u8>>> {
+        &mut self.jar
+    }
+
+    /// Reads the class with the given internal name.
+    pub fn read_class(&mut self, internal_name: &str) -> ClassFileResult<Option<JarEntry>> {
+        Ok(self
+            .jar
+            .read(&format!("classes/{internal_name}.class"))?
+            .map(strip_classes_dir))
+    }
+
+    /// Reads the files in the `classes/` directory, which are named relative to it.
+    pub fn classes(&mut self) -> ClassFileResult<Vec<JarEntry>> {
+        let mut classes = Vec::new();
+        for entry in self.jar.entries() {
+            let entry = entry?;
+            if entry.name.starts_with("classes/") {
+                classes.push(strip_classes_dir(entry));
+            }
+        }
+        Ok(classes)
+    }
+}
+
+fn strip_classes_dir(mut entry: JarEntry) -> JarEntry {
+    entry.name.drain(.."classes/".len());
+    entry
+}
+
+/// A root of the classpath, which is either a directory or a jar file.
+#[derive(Debug)]
+pub enum ClasspathRoot {
+    Directory(PathBuf),
+    Jar(JarReader),
+}
+
+impl ClasspathRoot {
+    /// Opens a directory, or otherwise a jar file.
+    pub fn open(path: impl AsRef<Path>) -> ClassFileResult<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            Ok(ClasspathRoot::Directory(path.to_owned()))
+        } else {
+            Ok(ClasspathRoot::Jar(JarReader::open(path)?))
+        }
+    }
+
+    /// Reads all files in the root. The files of a directory are sorted by name.
+    pub fn entries(&mut self) -> ClassFileResult<Vec<JarEntry>> {
+        match self {
+            ClasspathRoot::Directory(dir) => {
+                let mut entries = Vec::new();
+                read_dir_entries(dir, "", &mut entries)?;
+                Ok(entries)
+            }
+            ClasspathRoot::Jar(jar) => jar.entries().collect(),
+        }
+    }
+
+    /// Reads the class with the given internal name.
+    pub fn read_class(&mut self, internal_name: &str) -> ClassFileResult<Option<JarEntry>> {
+        match self {
+            ClasspathRoot::Directory(dir) => {
+                let name = format!("{internal_name}.class");
+                let path = dir.join(&name);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                Ok(Some(JarEntry {
+                    name,
+                    data: std::fs::read(path)?,
+                }))
+            }
+            ClasspathRoot::Jar(jar) => jar.read_class(internal_name),
+        }
+    }
+}
+
+fn read_dir_entries(dir: &Path, prefix: &str, entries: &mut Vec<JarEntry>) -> ClassFileResult<()> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    for path in paths {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let name = format!("{prefix}{file_name}");
+        if path.is_dir() {
+            read_dir_entries(&path, &format!("{name}/"), entries)?;
+        } else {
+            entries.push(JarEntry {
+                data: std::fs::read(&path)?,
+                name,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The class files in the jar at the given path.
+pub(crate) fn read_jar_classes(path: &Path) -> ClassFileResult<Vec<Vec<u8>>> {
+    let mut classes = Vec::new();
+    for entry in JarReader::open(path)?.entries() {
+        let entry = entry?;
+        if entry.is_class() {
+            classes.push(entry.data);
+        }
+    }
+    Ok(classes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::jar::{JarReader, JmodReader};
+    use crate::ClassReaderFlags;
+    use java_string::JavaStr;
+    use std::io::{Cursor, Write};
+    use test_helpers::include_class;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn test_read_jar() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        writer.add_directory("META-INF/", options).unwrap();
+        writer.start_file("META-INF/MANIFEST.MF", options).unwrap();
+        writer.write_all(b"Manifest-Version: 1.0\r\n").unwrap();
+        writer.start_file("HelloWorld.class", options).unwrap();
+        writer.write_all(include_class!("HelloWorld")).unwrap();
+        let jar = writer.finish().unwrap();
+
+        let mut reader = JarReader::new(jar).unwrap();
+        assert_eq!(3, reader.len());
+        let entries: Vec<_> = reader.entries().collect::<Result<_, _>>().unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!("META-INF/MANIFEST.MF", entries[0].name);
+        assert!(!entries[0].is_class());
+        assert_eq!(Some("HelloWorld"), entries[1].class_name());
+        assert_eq!(
+            JavaStr::from_str("HelloWorld"),
+            entries[1]
+                .class_reader(ClassReaderFlags::None)
+                .unwrap()
+                .name()
+                .unwrap()
+        );
+
+        assert_eq!(
+            Some(&entries[1]),
+            reader.read_class("HelloWorld").unwrap().as_ref()
+        );
+        assert_eq!(None, reader.read_class("Missing").unwrap());
+    }
+
+    #[test]
+    fn test_multi_release() {
+        let jar = |manifest: &[u8]| {
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            let options = SimpleFileOptions::default();
+            writer.start_file("META-INF/MANIFEST.MF", options).unwrap();
+            writer.write_all(manifest).unwrap();
+            for name in [
+                "HelloWorld.class",
+                "META-INF/versions/21/HelloWorld.class",
+                "META-INF/versions/11/HelloWorld.class",
+                "META-INF/versions/11/TestCode.class",
+            ] {
+                writer.start_file(name, options).unwrap();
+                writer.write_all(include_class!("HelloWorld")).unwrap();
+            }
+            JarReader::new(writer.finish().unwrap()).unwrap()
+        };
+
+        let mut reader = jar(b"Manifest-Version: 1.0\r\nMulti-Release: true\r\n");
+        assert!(reader.is_multi_release().unwrap());
+        let versions = |reader: &mut JarReader<_>, release| {
+            reader
+                .versioned_classes(release)
+                .unwrap()
+                .into_iter()
+                .map(|class| (class.name, class.version))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![("HelloWorld".to_owned(), None)],
+            versions(&mut reader, 8)
+        );
+        assert_eq!(
+            vec![
+                ("HelloWorld".to_owned(), Some(11)),
+                ("TestCode".to_owned(), Some(11))
+            ],
+            versions(&mut reader, 17)
+        );
+        assert_eq!(
+            Some(21),
+            reader
+                .read_versioned_class("HelloWorld", 21)
+                .unwrap()
+                .unwrap()
+                .version
+        );
+
+        let mut reader = jar(b"Manifest-Version: 1.0\r\n");
+        assert!(!reader.is_multi_release().unwrap());
+        assert_eq!(
+            vec![("HelloWorld".to_owned(), None)],
+            versions(&mut reader, 21)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_transform() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        for name in ["HelloWorld.class", "hello.txt", "TestCode.class"] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(include_class!("HelloWorld")).unwrap();
+        }
+        let mut reader = JarReader::new(writer.finish().unwrap()).unwrap();
+
+        let names = reader.par_map_classes(|entry| Ok(entry.name)).unwrap();
+        assert_eq!(vec!["HelloWorld.class", "TestCode.class"], names);
+
+        let entries = reader
+            .par_transform(|entry| {
+                Ok((entry.name != "TestCode.class").then(|| entry.data[..4].to_vec()))
+            })
+            .unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!("HelloWorld.class", entries[0].name);
+        assert_eq!(vec![0xca, 0xfe, 0xba, 0xbe], entries[0].data);
+        assert_eq!("hello.txt", entries[1].name);
+        assert_eq!(include_class!("HelloWorld"), entries[1].data.as_slice());
+    }
+
+    #[test]
+    fn test_read_jmod() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        writer
+            .start_file("classes/HelloWorld.class", options)
+            .unwrap();
+        writer.write_all(include_class!("HelloWorld")).unwrap();
+        writer.start_file("conf/hello.properties", options).unwrap();
+        let zip = writer.finish().unwrap().into_inner();
+        assert!(JmodReader::new(zip.clone()).is_err());
+
+        let mut jmod = b"JM\x01\x00".to_vec();
+        jmod.extend(zip);
+
+        let mut reader = JmodReader::new(jmod).unwrap();
+        let classes = reader.classes().unwrap();
+        assert_eq!(1, classes.len());
+        assert_eq!(Some("HelloWorld"), classes[0].class_name());
+        assert_eq!(
+            Some(&classes[0]),
+            reader.read_class("HelloWorld").unwrap().as_ref()
+        );
+        assert!(reader
+            .jar()
+            .read("conf/hello.properties")
+            .unwrap()
+            .is_some());
+    }
+}
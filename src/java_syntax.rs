@@ -0,0 +1,233 @@
+use crate::tree::{
+    ClassSignature, ClassTypeSignature, MethodSignature, TypeArgument, TypeParameter, TypeSignature,
+};
+use crate::{ClassFileResult, MethodType, Type, TypeArgumentKind};
+use java_string::JavaStr;
+use std::fmt;
+use std::fmt::Write;
+
+/// Renders a field or method descriptor as Java source, such as `void (int, String)` for
+/// `(ILjava/lang/String;)V`. Classes are written as their simple names, with nested classes
+/// qualified by their outer classes.
+pub fn desc_to_java(desc: &JavaStr) -> ClassFileResult<String> {
+    let mut out = String::new();
+    if desc.starts_with('(') {
+        let method_type = MethodType::from_desc(desc)?;
+        write_type(&mut out, &method_type.return_type())?;
+        out.push_str(" (");
+        for (index, argument_type) in method_type.argument_types().iter().enumerate() {
+            if index != 0 {
+                out.push_str(", ");
+            }
+            write_type(&mut out, argument_type)?;
+        }
+        out.push(')');
+    } else {
+        write_type(&mut out, &Type::from_desc(desc)?)?;
+    }
+    Ok(out)
+}
+
+/// Renders a class signature as the type parameters and supertypes of a Java class declaration,
+/// such as `<T extends Comparable<T>> extends AbstractList<T> implements RandomAccess`.
+pub fn class_signature_to_java(signature: &JavaStr) -> ClassFileResult<String> {
+    let signature = ClassSignature::parse(signature)?;
+    let mut out = String::new();
+    write_type_parameters(&mut out, &signature.type_parameters)?;
+    if !is_object(&signature.superclass) {
+        out.push_str("extends ");
+        write_class_type(&mut out, &signature.superclass)?;
+        out.push(' ');
+    }
+    for (index, interface) in signature.interfaces.iter().enumerate() {
+        out.push_str(if index == 0 { "implements " } else { ", " });
+        write_class_type(&mut out, interface)?;
+    }
+    out.truncate(out.trim_end().len());
+    Ok(out)
+}
+
+/// Renders a method signature like [`desc_to_java`], such as `<T> T (List<? extends T>)`.
+pub fn method_signature_to_java(signature: &JavaStr) -> ClassFileResult<String> {
+    let signature = MethodSignature::parse(signature)?;
+    let mut out = String::new();
+    write_type_parameters(&mut out, &signature.type_parameters)?;
+    write_type_signature(&mut out, &signature.return_type)?;
+    out.push_str(" (");
+    for (index, parameter_type) in signature.parameter_types.iter().enumerate() {
+        if index != 0 {
+            out.push_str(", ");
+        }
+        write_type_signature(&mut out, parameter_type)?;
+    }
+    out.push(')');
+    for (index, exception) in signature.exceptions.iter().enumerate() {
+        out.push_str(if index == 0 { " throws " } else { ", " });
+        write_type_signature(&mut out, exception)?;
+    }
+    Ok(out)
+}
+
+/// Renders a field signature as Java source, such as `Map<String, List<T>>`.
+pub fn field_signature_to_java(signature: &JavaStr) -> ClassFileResult<String> {
+    let mut out = String::new();
+    write_type_signature(&mut out, &TypeSignature::parse(signature)?)?;
+    Ok(out)
+}
+
+fn write_type<W: Write>(out: &mut W, ty: &Type) -> fmt::Result {
+    let element_type = ty.element_type();
+    match element_type.internal_name() {
+        Some(internal_name) => write_class_name(out, internal_name)?,
+        None => out.write_str(primitive_name(element_type.descriptor().as_bytes()[0]))?,
+    }
+    for _ in 0..ty.dimensions() {
+        out.write_str("[]")?;
+    }
+    Ok(())
+}
+
+fn primitive_name(descriptor: u8) -> &'static str {
+    match descriptor {
+        b'Z' => "boolean",
+        b'B' => "byte",
+        b'C' => "char",
+        b'S' => "short",
+        b'I' => "int",
+        b'J' => "long",
+        b'F' => "float",
+        b'D' => "double",
+        _ => "void",
+    }
+}
+
+fn write_class_name<W: Write>(out: &mut W, internal_name: &JavaStr) -> fmt::Result {
+    let name = internal_name
+        .rsplit_once('/')
+        .map_or(internal_name, |(_, name)| name);
+    write!(out, "{}", name.replace('$', "."))
+}
+
+fn is_object(class_type: &ClassTypeSignature) -> bool {
+    class_type.name.as_ref() == "java/lang/Object"
+        && class_type.type_arguments.is_empty()
+        && class_type.inner_classes.is_empty()
+}
+
+fn write_type_parameters<W: Write>(out: &mut W, type_parameters: &[TypeParameter]) -> fmt::Result {
+    if type_parameters.is_empty() {
+        return Ok(());
+    }
+    out.write_char('<')?;
+    for (index, type_parameter) in type_parameters.iter().enumerate() {
+        if index != 0 {
+            out.write_str(", ")?;
+        }
+        write!(out, "{}", type_parameter.name)?;
+        let class_bound = type_parameter.class_bound.iter().filter(
+            |bound| !matches!(bound, TypeSignature::Class(class_type) if is_object(class_type)),
+        );
+        for (index, bound) in class_bound
+            .chain(&type_parameter.interface_bounds)
+            .enumerate()
+        {
+            out.write_str(if index == 0 { " extends " } else { " & " })?;
+            write_type_signature(out, bound)?;
+        }
+    }
+    out.write_str("> ")
+}
+
+fn write_type_signature<W: Write>(out: &mut W, ty: &TypeSignature) -> fmt::Result {
+    match ty {
+        TypeSignature::Base(descriptor) => out.write_str(primitive_name(*descriptor as u8)),
+        TypeSignature::TypeVariable(name) => write!(out, "{name}"),
+        TypeSignature::Array(element_type) => {
+            write_type_signature(out, element_type)?;
+            out.write_str("[]")
+        }
+        TypeSignature::Class(class_type) => write_class_type(out, class_type),
+    }
+}
+
+fn write_class_type<W: Write>(out: &mut W, class_type: &ClassTypeSignature) -> fmt::Result {
+    write_class_name(out, &class_type.name)?;
+    write_type_arguments(out, &class_type.type_arguments)?;
+    for inner_class in &class_type.inner_classes {
+        write!(out, ".{}", inner_class.name)?;
+        write_type_arguments(out, &inner_class.type_arguments)?;
+    }
+    Ok(())
+}
+
+fn write_type_arguments<W: Write>(out: &mut W, type_arguments: &[TypeArgument]) -> fmt::Result {
+    if type_arguments.is_empty() {
+        return Ok(());
+    }
+    out.write_char('<')?;
+    for (index, type_argument) in type_arguments.iter().enumerate() {
+        if index != 0 {
+            out.write_str(", ")?;
+        }
+        match type_argument {
+            TypeArgument::Unbounded => out.write_char('?')?,
+            TypeArgument::Bounded(kind, ty) => {
+                match kind {
+                    TypeArgumentKind::Exact => {}
+                    TypeArgumentKind::Extends => out.write_str("? extends ")?,
+                    TypeArgumentKind::Super => out.write_str("? super ")?,
+                }
+                write_type_signature(out, ty)?;
+            }
+        }
+    }
+    out.write_char('>')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        class_signature_to_java, desc_to_java, field_signature_to_java, method_signature_to_java,
+    };
+    use java_string::JavaStr;
+
+    #[test]
+    fn test_to_java() {
+        let desc = |desc: &str| desc_to_java(JavaStr::from_str(desc)).unwrap();
+        assert_eq!("void (int, String)", desc("(ILjava/lang/String;)V"));
+        assert_eq!("Map.Entry[][]", desc("[[Ljava/util/Map$Entry;"));
+        assert_eq!("long", desc("J"));
+        assert!(desc_to_java(JavaStr::from_str("(I")).is_err());
+
+        assert_eq!(
+            "<T extends Comparable<T>> extends AbstractList<T> implements RandomAccess",
+            class_signature_to_java(JavaStr::from_str(
+                "<T::Ljava/lang/Comparable<TT;>;>Ljava/util/AbstractList<TT;>;\
+                 Ljava/util/RandomAccess;"
+            ))
+            .unwrap()
+        );
+        assert_eq!(
+            "<K, V> implements Map<K, V>",
+            class_signature_to_java(JavaStr::from_str(
+                "<K:Ljava/lang/Object;V:Ljava/lang/Object;>Ljava/lang/Object;\
+                 Ljava/util/Map<TK;TV;>;"
+            ))
+            .unwrap()
+        );
+        assert_eq!(
+            "<T, E extends Exception> T[] (List<? extends T>, int) throws E",
+            method_signature_to_java(JavaStr::from_str(
+                "<T:Ljava/lang/Object;E:Ljava/lang/Exception;>(Ljava/util/List<+TT;>;I)[TT;^TE;"
+            ))
+            .unwrap()
+        );
+        assert_eq!(
+            "Map<K, V>.Entry<?, ? super String>",
+            field_signature_to_java(JavaStr::from_str(
+                "Ljava/util/Map<TK;TV;>.Entry<*-Ljava/lang/String;>;"
+            ))
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{ClassFileError, ClassFileResult};
+use derive_more::Debug;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const JIMAGE_MAGIC: [u8; 4] = [0xca, 0xfe, 0xda, 0xda];
+const JIMAGE_MAJOR_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 28;
+const HASH_MULTIPLIER: u32 = 0x01000193;
+
+const ATTRIBUTE_END: u8 = 0;
+const ATTRIBUTE_MODULE: u8 = 1;
+const ATTRIBUTE_PARENT: u8 = 2;
+const ATTRIBUTE_BASE: u8 = 3;
+const ATTRIBUTE_EXTENSION: u8 = 4;
+const ATTRIBUTE_OFFSET: u8 = 5;
+const ATTRIBUTE_COMPRESSED: u8 = 6;
+const ATTRIBUTE_UNCOMPRESSED: u8 = 7;
+
+/// Reads a jimage file, the format of the `lib/modules` file of a JDK which holds the classes and
+/// resources of its modules. Only the index is kept in memory, resources are read on demand.
+/// Compressed resources, which only come from `jlink --compress`, aren't supported.
+#[derive(Debug)]
+pub struct JImageReader<R = BufReader<File>> {
+    #[debug(skip)]
+    reader: R,
+    #[debug("{} bytes", index.len())]
+    index: Vec<u8>,
+    big_endian: bool,
+    table_length: usize,
+    locations_start: usize,
+    strings_start: usize,
+}
+
+/// A resource in a [`JImageReader`], such as `java/lang/Object.class` in the `java.base` module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JImageResource {
+    pub module: String,
+    /// The path of the resource within its module, with `/` as the separator.
+    pub name: String,
+    offset: u64,
+    compressed_size: u64,
+    size: u64,
+}
+
+impl JImageResource {
+    pub fn is_class(&self) -> bool {
+        self.name.ends_with(".class")
+    }
+
+    /// The internal name of the class in this resource, as implied by its name.
+    pub fn class_name(&self) -> Option<&str> {
+        self.name.strip_suffix(".class")
+    }
+
+    /// The size of the resource once read.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl JImageReader {
+    pub fn open(path: impl AsRef<Path>) -> ClassFileResult<Self> {
+        JImageReader::new(BufReader::new(File::open(path)?))
+    }
+
+    /// Opens the `lib/modules` file of the JDK installed in the given directory.
+    pub fn open_jdk(java_home: impl AsRef<Path>) -> ClassFileResult<Self> {
+        JImageReader::open(java_home.as_ref().join("lib").join("modules"))
+    }
+}
+
+impl<R> JImageReader<R>
+where
+    R: Read + Seek,
+{
+    pub fn new(mut reader: R) -> ClassFileResult<Self> {
+        let mut index = vec![0; HEADER_SIZE];
+        reader.read_exact(&mut index)?;
+        let big_endian = if index[..4] == JIMAGE_MAGIC {
+            true
+        } else if index[..4].iter().eq(JIMAGE_MAGIC.iter().rev()) {
+            false
+        } else {
+            return Err(ClassFileError::BadMagic);
+        };
+        let mut jimage = JImageReader {
+            reader,
+            index,
+            big_endian,
+            table_length: 0,
+            locations_start: 0,
+            strings_start: 0,
+        };
+
+        let major_version = jimage.read_u32(4)? >> 16;
+        if major_version != JIMAGE_MAJOR_VERSION {
+            return Err(ClassFileError::Io {
+                kind: std::io::ErrorKind::Unsupported,
+                message: format!("unsupported jimage version: {major_version}"),
+            });
+        }
+        let too_large = || ClassFileError::Io {
+            kind: std::io::ErrorKind::InvalidData,
+            message: "jimage index too large".to_owned(),
+        };
+        jimage.table_length = jimage.read_u32(16)? as usize;
+        jimage.locations_start = jimage
+            .table_length
+            .checked_mul(8)
+            .and_then(|size| size.checked_add(HEADER_SIZE))
+            .ok_or_else(too_large)?;
+        jimage.strings_start = jimage
+            .locations_start
+            .checked_add(jimage.read_u32(20)? as usize)
+            .ok_or_else(too_large)?;
+        let index_size = jimage
+            .strings_start
+            .checked_add(jimage.read_u32(24)? as usize)
+            .ok_or_else(too_large)?;
+
+        let stream_len = jimage.reader.seek(SeekFrom::End(0))?;
+        if index_size as u64 > stream_len {
+            return Err(ClassFileError::Io {
+                kind: std::io::ErrorKind::UnexpectedEof,
+                message: format!("jimage index of {index_size} bytes in a {stream_len} byte file"),
+            });
+        }
+        jimage.reader.seek(SeekFrom::Start(HEADER_SIZE as u64))?;
+        jimage.index.resize(index_size, 0);
+        jimage.reader.read_exact(&mut jimage.index[HEADER_SIZE..])?;
+        Ok(jimage)
+    }
+
+    /// The number of resources in the image.
+    pub fn len(&self) -> usize {
+        self.table_length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table_length == 0
+    }
+
+    /// Lists all resources in the image, in the order of the index.
+    pub fn resources(&self) -> ClassFileResult<Vec<JImageResource>> {
+        (0..self.table_length)
+            .map(|index| self.resource(index))
+            .collect()
+    }
+
+    /// Finds the resource with the given name in the given module.
+    pub fn find(&self, module: &str, name: &str) -> ClassFileResult<Option<JImageResource>> {
+        if self.table_length == 0 {
+            return Ok(None);
+        }
+        let full_name = format!("/{module}/{name}");
+        let slot = hash(&full_name, HASH_MULTIPLIER) as usize % self.table_length;
+        let redirect = self.read_u32(HEADER_SIZE + slot * 4)? as i32;
+        let index = match redirect {
+            0 => return Ok(None),
+            redirect if redirect < 0 => (-1 - redirect) as usize,
+            seed => hash(&full_name, seed as u32) as usize % self.table_length,
+        };
+        let resource = self.resource(index)?;
+        if resource.module == module && resource.name == name {
+            Ok(Some(resource))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the contents of a resource.
+    pub fn read(&mut self, resource: &JImageResource) -> ClassFileResult<Vec<u8>> {
+        if resource.compressed_size != 0 {
+            return Err(ClassFileError::Io {
+                kind: std::io::ErrorKind::Unsupported,
+                message: format!(
+                    "compressed jimage resource: /{}/{}",
+                    resource.module, resource.name
+                ),
+            });
+        }
+        self.reader
+            .seek(SeekFrom::Start(self.index.len() as u64 + resource.offset))?;
+        let mut data = vec![0; resource.size as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Reads the class with the given internal name from the given module.
+    pub fn read_class(
+        &mut self,
+        module: &str,
+        internal_name: &str,
+    ) -> ClassFileResult<Option<Vec<u8>>> {
+        match self.find(module, &format!("{internal_name}.class"))? {
+            Some(resource) => self.read(&resource).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn resource(&self, index: usize) -> ClassFileResult<JImageResource> {
+        let mut attributes = [0u64; 8];
+        let mut offset = self.locations_start
+            + self.read_u32(HEADER_SIZE + (self.table_length + index) * 4)? as usize;
+        loop {
+            let byte = self.read_u8(offset)?;
+            let kind = byte >> 3;
+            if kind == ATTRIBUTE_END {
+                break;
+            }
+            let len = (byte & 7) as usize + 1;
+            let mut value = 0;
+            for i in 0..len {
+                value = (value << 8) | self.read_u8(offset + 1 + i)? as u64;
+            }
+            if let Some(attribute) = attributes.get_mut(kind as usize) {
+                *attribute = value;
+            }
+            offset += 1 + len;
+        }
+
+        let mut name = String::new();
+        let parent = self.string(attributes[ATTRIBUTE_PARENT as usize])?;
+        if !parent.is_empty() {
+            name.push_str(&parent);
+            name.push('/');
+        }
+        name.push_str(&self.string(attributes[ATTRIBUTE_BASE as usize])?);
+        let extension = self.string(attributes[ATTRIBUTE_EXTENSION as usize])?;
+        if !extension.is_empty() {
+            name.push('.');
+            name.push_str(&extension);
+        }
+        Ok(JImageResource {
+            module: self.string(attributes[ATTRIBUTE_MODULE as usize])?,
+            name,
+            offset: attributes[ATTRIBUTE_OFFSET as usize],
+            compressed_size: attributes[ATTRIBUTE_COMPRESSED as usize],
+            size: attributes[ATTRIBUTE_UNCOMPRESSED as usize],
+        })
+    }
+
+    fn string(&self, offset: u64) -> ClassFileResult<String> {
+        let start = self.strings_start + offset as usize;
+        let len = self
+            .index
+            .get(start..)
+            .and_then(|strings| strings.iter().position(|&b| b == 0))
+            .ok_or(ClassFileError::OutOfBounds {
+                index: start,
+                len: self.index.len(),
+            })?;
+        Ok(String::from_utf8_lossy(&self.index[start..start + len]).into_owned())
+    }
+
+    fn read_u8(&self, offset: usize) -> ClassFileResult<u8> {
+        self.index
+            .get(offset)
+            .copied()
+            .ok_or(ClassFileError::OutOfBounds {
+                index: offset,
+                len: self.index.len(),
+            })
+    }
+
+    fn read_u32(&self, offset: usize) -> ClassFileResult<u32> {
+        let bytes: [u8; 4] = self
+            .index
+            .get(offset..offset + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(ClassFileError::OutOfBounds {
+                index: offset,
+                len: self.index.len(),
+            })?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+}
+
+/// The hash of resource names in the index, from `jdk.internal.jimage.ImageStringsReader`.
+fn hash(name: &str, seed: u32) -> u32 {
+    name.bytes().fold(seed, |hash, b| {
+        hash.wrapping_mul(HASH_MULTIPLIER) ^ b as u32
+    }) & 0x7fffffff
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassFileError, ClassReader, ClassReaderFlags, JImageReader};
+    use java_string::JavaStr;
+    use std::io::{Cursor, ErrorKind};
+    use test_helpers::java_home;
+
+    #[test]
+    fn test_read_jimage() {
+        let mut jimage = JImageReader::open_jdk(java_home!()).unwrap();
+        let object = jimage
+            .find("java.base", "java/lang/Object.class")
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some("java/lang/Object"), object.class_name());
+        let data = jimage.read(&object).unwrap();
+        assert_eq!(object.size() as usize, data.len());
+        let reader = ClassReader::new(&data, ClassReaderFlags::None).unwrap();
+        assert_eq!(
+            JavaStr::from_str("java/lang/Object"),
+            reader.name().unwrap()
+        );
+
+        assert!(jimage
+            .read_class("java.base", "java/lang/String")
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            None,
+            jimage.find("java.base", "java/lang/Missing.class").unwrap()
+        );
+        assert_eq!(
+            None,
+            jimage.find("java.sql", "java/lang/Object.class").unwrap()
+        );
+
+        let resources = jimage.resources().unwrap();
+        assert!(resources.contains(&object));
+    }
+
+    #[test]
+    fn test_bad_index_size() {
+        let header = |table_length: u32| {
+            let mut header = vec![0xca, 0xfe, 0xda, 0xda, 0, 1, 0, 0];
+            header.extend([0; 8]);
+            header.extend(table_length.to_be_bytes());
+            header.extend([0xff; 8]);
+            header
+        };
+        let error = JImageReader::new(Cursor::new(header(u32::MAX))).unwrap_err();
+        assert!(matches!(error, ClassFileError::Io { .. }));
+        let error = JImageReader::new(Cursor::new(header(1))).unwrap_err();
+        assert!(matches!(
+            error,
+            ClassFileError::Io {
+                kind: ErrorKind::UnexpectedEof,
+                ..
+            }
+        ));
+    }
+}
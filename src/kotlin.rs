@@ -0,0 +1,301 @@
+use crate::scala::decode_7to8;
+use crate::tree::AnnotationNode;
+use crate::{ClassEvent, ClassEventSource, ClassFileError, ClassFileResult};
+use derive_more::{Debug, IsVariant};
+use java_string::{JavaStr, JavaString};
+
+const METADATA_DESC: &str = "Lkotlin/Metadata;";
+const UTF8_MODE_MARKER: u32 = 0;
+const LEGACY_MODE_MARKER: u32 = 0xffff;
+
+/// The `k` field of `@kotlin.Metadata`, which says what kind of class the metadata describes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, IsVariant)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KotlinClassKind {
+    Class,
+    /// The facade class of a single file, which holds its top-level declarations.
+    File,
+    /// A class generated by the compiler, such as for a lambda.
+    SyntheticClass,
+    /// The facade class of several files with the same `@JvmName`, which delegates to the parts.
+    MultiFileClassFacade,
+    MultiFileClassPart,
+    Unknown(i32),
+}
+
+impl KotlinClassKind {
+    pub fn from_id(id: i32) -> Self {
+        match id {
+            1 => KotlinClassKind::Class,
+            2 => KotlinClassKind::File,
+            3 => KotlinClassKind::SyntheticClass,
+            4 => KotlinClassKind::MultiFileClassFacade,
+            5 => KotlinClassKind::MultiFileClassPart,
+            id => KotlinClassKind::Unknown(id),
+        }
+    }
+
+    pub fn id(self) -> i32 {
+        match self {
+            KotlinClassKind::Class => 1,
+            KotlinClassKind::File => 2,
+            KotlinClassKind::SyntheticClass => 3,
+            KotlinClassKind::MultiFileClassFacade => 4,
+            KotlinClassKind::MultiFileClassPart => 5,
+            KotlinClassKind::Unknown(id) => id,
+        }
+    }
+
+    /// Whether the class only exists to hold top-level declarations, and has no Kotlin class of
+    /// its own.
+    pub fn is_facade(self) -> bool {
+        matches!(
+            self,
+            KotlinClassKind::File | KotlinClassKind::MultiFileClassFacade
+        )
+    }
+}
+
+/// The contents of a `@kotlin.Metadata` annotation, which the Kotlin compiler adds to every class
+/// it generates. Missing fields take the defaults of the annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KotlinMetadata {
+    /// The `k` field.
+    pub kind: KotlinClassKind,
+    /// The `mv` field, the version of the metadata format.
+    pub metadata_version: Vec<i32>,
+    /// The `d1` field, the protobuf messages encoded as strings. See [`KotlinMetadata::payload`].
+    #[debug("{} strings", data1.len())]
+    pub data1: Vec<JavaString>,
+    /// The `d2` field, the strings referenced by the protobuf messages.
+    pub data2: Vec<JavaString>,
+    /// The `xs` field, which is the name of the facade class of a multi-file class part.
+    pub extra_string: Option<JavaString>,
+    /// The `pn` field, the Kotlin package name if it differs from the JVM package name.
+    pub package_name: Option<JavaString>,
+    /// The `xi` field.
+    pub extra_int: i32,
+}
+
+/// The protobuf messages of a [`KotlinMetadata`], which can be parsed with the `.proto` files of
+/// the Kotlin compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KotlinPayload {
+    /// A `JvmProtoBuf.StringTableTypes`, which describes how to read [`KotlinMetadata::data2`].
+    #[debug("{} bytes", string_table.len())]
+    pub string_table: Vec<u8>,
+    /// The message for the kind of class, such as a `ProtoBuf.Class` for
+    /// [`KotlinClassKind::Class`] or a `ProtoBuf.Package` for [`KotlinClassKind::File`].
+    #[debug("{} bytes", message.len())]
+    pub message: Vec<u8>,
+}
+
+impl KotlinMetadata {
+    /// Reads the metadata from an annotation, or returns `None` if it isn't `@kotlin.Metadata`.
+    pub fn from_annotation(annotation: &AnnotationNode) -> Option<Self> {
+        if annotation.desc.as_ref() != JavaStr::from_str(METADATA_DESC) {
+            return None;
+        }
+        let strings = |name| {
+            annotation
+                .get_array_of::<&JavaStr>(JavaStr::from_str(name))
+                .unwrap_or_default()
+                .into_iter()
+                .map(JavaStr::to_owned)
+                .collect()
+        };
+        let string = |name| {
+            annotation
+                .get_string(JavaStr::from_str(name))
+                .filter(|value| !value.is_empty())
+                .map(JavaStr::to_owned)
+        };
+        Some(KotlinMetadata {
+            kind: KotlinClassKind::from_id(annotation.get_int(JavaStr::from_str("k")).unwrap_or(1)),
+            metadata_version: annotation
+                .get_array_of(JavaStr::from_str("mv"))
+                .unwrap_or_default(),
+            data1: strings("d1"),
+            data2: strings("d2"),
+            extra_string: string("xs"),
+            package_name: string("pn"),
+            extra_int: annotation.get_int(JavaStr::from_str("xi")).unwrap_or(0),
+        })
+    }
+
+    /// Reads the metadata from the annotations of a class, or returns `None` if it isn't a Kotlin
+    /// class.
+    pub fn read<'class, S>(source: S) -> ClassFileResult<Option<Self>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        for event in source.events()? {
+            if let ClassEvent::Annotations(annotations) = event? {
+                for annotation in annotations {
+                    let annotation = annotation?.annotation;
+                    if let Some(metadata) = KotlinMetadata::from_annotation(&annotation) {
+                        return Ok(Some(metadata));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Decodes [`data1`](KotlinMetadata::data1) into the bytes of its protobuf messages.
+    pub fn data1_bytes(&self) -> Vec<u8> {
+        let mut data = self
+            .data1
+            .iter()
+            .flat_map(|string| string.chars())
+            .map(|c| c.as_u32())
+            .peekable();
+        match data.peek() {
+            Some(&UTF8_MODE_MARKER) => data.skip(1).map(|c| c as u8).collect(),
+            Some(&LEGACY_MODE_MARKER) => decode_7to8(data.skip(1).map(|c| c as u8)),
+            _ => decode_7to8(data.map(|c| c as u8)),
+        }
+    }
+
+    /// Splits [`data1`](KotlinMetadata::data1) into its protobuf messages, or returns `None` if it
+    /// is empty, as for synthetic classes that aren't lambdas.
+    pub fn payload(&self) -> ClassFileResult<Option<KotlinPayload>> {
+        let bytes = self.data1_bytes();
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+        let (len, start) = read_varint(&bytes)?;
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= bytes.len())
+            .ok_or(ClassFileError::OutOfBounds {
+                index: start.saturating_add(len),
+                len: bytes.len(),
+            })?;
+        Ok(Some(KotlinPayload {
+            string_table: bytes[start..end].to_vec(),
+            message: bytes[end..].to_vec(),
+        }))
+    }
+}
+
+/// Reads a protobuf varint, returning its value and the index after it.
+fn read_varint(bytes: &[u8]) -> ClassFileResult<(usize, usize)> {
+    let mut value = 0usize;
+    for (index, &b) in bytes
+        .iter()
+        .enumerate()
+        .take(usize::BITS.div_ceil(7) as usize)
+    {
+        value |= ((b & 0x7f) as usize) << (index * 7);
+        if b & 0x80 == 0 {
+            return Ok((value, index + 1));
+        }
+    }
+    Err(ClassFileError::OutOfBounds {
+        index: bytes.len(),
+        len: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::kotlin::{KotlinClassKind, KotlinMetadata, KotlinPayload};
+    use crate::tree::{AnnotationBuilder, AnnotationNode, ClassNode};
+    use crate::{AnnotationEvent, ClassReader, ClassReaderFlags};
+    use java_string::{JavaCodePoint, JavaStr, JavaString};
+    use test_helpers::include_class;
+
+    fn str(value: &str) -> &JavaStr {
+        JavaStr::from_str(value)
+    }
+
+    fn metadata_annotation(kind: i32, data1: &[JavaString]) -> AnnotationNode<'_> {
+        AnnotationBuilder::new(str("Lkotlin/Metadata;"))
+            .value(str("k"), kind)
+            .array(str("mv"), [1, 9, 0])
+            .array(str("d1"), data1.iter().map(|s| s.as_java_str()))
+            .array(str("d2"), [str("Foo"), str("bar")])
+            .value(str("xi"), 48)
+            .build()
+    }
+
+    fn encode(bytes: impl IntoIterator<Item = u32>) -> JavaString {
+        bytes
+            .into_iter()
+            .map(|b| JavaCodePoint::from_u32(b).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_kotlin_metadata() {
+        // A string table of 2 bytes followed by the message, as in a UTF-8 mode `d1`.
+        let payload = [2, 0x08, 0x01, 0x10, 0x05, 0xff];
+        let data1 = [encode([0]), encode(payload.map(u32::from))];
+        let metadata = KotlinMetadata::from_annotation(&metadata_annotation(1, &data1)).unwrap();
+        assert_eq!(KotlinClassKind::Class, metadata.kind);
+        assert_eq!(vec![1, 9, 0], metadata.metadata_version);
+        assert_eq!(
+            vec![JavaString::from("Foo"), JavaString::from("bar")],
+            metadata.data2
+        );
+        assert_eq!(None, metadata.extra_string);
+        assert_eq!(48, metadata.extra_int);
+        assert_eq!(payload.to_vec(), metadata.data1_bytes());
+        assert_eq!(
+            Some(KotlinPayload {
+                string_table: vec![0x08, 0x01],
+                message: vec![0x10, 0x05, 0xff],
+            }),
+            metadata.payload().unwrap()
+        );
+
+        // The same payload packed 7 bits per character, plus one, as in a legacy mode `d1`.
+        let mut packed = vec![0xffff];
+        let (mut bits, mut bit_count) = (0u32, 0);
+        for b in payload {
+            bits |= (b as u32) << bit_count;
+            bit_count += 8;
+            while bit_count >= 7 {
+                packed.push(((bits & 0x7f) + 1) & 0x7f);
+                bits >>= 7;
+                bit_count -= 7;
+            }
+        }
+        packed.push((bits + 1) & 0x7f);
+        let data1 = [encode(packed)];
+        let metadata = KotlinMetadata::from_annotation(&metadata_annotation(2, &data1)).unwrap();
+        assert!(metadata.kind.is_facade());
+        assert_eq!(payload.to_vec(), metadata.data1_bytes());
+
+        let metadata = KotlinMetadata::from_annotation(&metadata_annotation(3, &[])).unwrap();
+        assert!(metadata.kind.is_synthetic_class());
+        assert!(!metadata.kind.is_facade());
+        assert_eq!(None, metadata.payload().unwrap());
+        assert_eq!(
+            KotlinClassKind::Unknown(42),
+            KotlinClassKind::from_id(KotlinClassKind::Unknown(42).id())
+        );
+
+        assert_eq!(
+            None,
+            KotlinMetadata::from_annotation(&AnnotationNode::new(str("LFoo;")))
+        );
+    }
+
+    #[test]
+    fn test_read_kotlin_metadata() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        assert_eq!(None, KotlinMetadata::read(&reader).unwrap());
+
+        let mut node = ClassNode::from_events(&reader).unwrap();
+        node.annotations.push(AnnotationEvent {
+            visible: true,
+            annotation: metadata_annotation(4, &[]),
+        });
+        let metadata = KotlinMetadata::read(&node).unwrap().unwrap();
+        assert_eq!(KotlinClassKind::MultiFileClassFacade, metadata.kind);
+    }
+}
@@ -3,9 +3,18 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display("L{_0}")]
 pub struct Label(u32);
 
+impl Label {
+    /// Creates a label for use within the library, counting down from the largest id so that it
+    /// doesn't clash with labels from a [`LabelCreator`] in practice.
+    pub(crate) fn synthetic(index: u32) -> Label {
+        Label(u32::MAX - index)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct LabelCreator {
     next_id: Arc<AtomicU32>,
@@ -16,3 +25,21 @@ impl LabelCreator {
         Label(self.next_id.fetch_add(1, Ordering::Relaxed))
     }
 }
+
+/// Serialized as the id of the next label. A deserialized creator no longer shares its ids with
+/// the creator it was serialized from.
+#[cfg(feature = "serde")]
+impl serde::Serialize for LabelCreator {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.next_id.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LabelCreator {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(LabelCreator {
+            next_id: Arc::new(AtomicU32::new(u32::deserialize(deserializer)?)),
+        })
+    }
+}
@@ -2,30 +2,109 @@
 #![warn(missing_debug_implementations)]
 
 mod access;
+mod access_transformer;
+pub mod analysis;
+mod assembler;
 mod attribute;
+mod buffered_events;
+mod class_hierarchy;
 mod class_reader;
+mod class_versions;
+mod class_writer;
+mod code_generator;
 mod constant_pool;
+mod constant_pool_builder;
 mod constants;
+mod debug_info;
 mod error;
 mod events;
 mod field;
+mod field_injection;
+mod fingerprint;
 mod frame;
+mod frame_computer;
+mod frame_tracker;
 mod handle;
+#[cfg(feature = "jar")]
+pub mod jar;
+mod java_syntax;
+mod jimage;
+#[cfg(feature = "kotlin")]
+pub mod kotlin;
 mod label;
+mod mappings;
+mod maxs_calculator;
+mod method_advice;
+mod method_splitter;
+mod metrics;
+pub mod names;
+mod nest;
 mod opcodes;
+mod package_relocation;
+mod pipeline;
+mod remapper;
+mod rustifier;
+mod scala;
+mod signature;
+mod smap;
+mod static_initializer;
+mod string_constants;
+mod switches;
+mod synthetic_members;
+mod textifier;
+mod transform_session;
 pub mod tree;
 mod type_annotation;
+mod types;
+mod usage_scanner;
+mod validation;
 
 pub use access::*;
+pub use access_transformer::*;
+pub use assembler::*;
 pub use attribute::*;
+pub use buffered_events::*;
+pub use class_hierarchy::*;
 pub use class_reader::*;
+pub use class_versions::*;
+pub use class_writer::*;
+pub use code_generator::*;
 pub use constant_pool::*;
+pub use constant_pool_builder::*;
 pub use constants::*;
+pub use debug_info::*;
 pub use error::*;
 pub use events::*;
 pub use field::*;
+pub use field_injection::*;
+pub use fingerprint::*;
 pub use frame::*;
+pub use frame_tracker::*;
 pub use handle::*;
+pub use java_syntax::*;
+pub use jimage::*;
 pub use label::*;
+pub use mappings::*;
+pub use maxs_calculator::*;
+pub use method_advice::*;
+pub use method_splitter::*;
+pub use metrics::*;
+pub use nest::*;
 pub use opcodes::*;
+pub use package_relocation::*;
+pub use pipeline::*;
+pub use remapper::*;
+pub use rustifier::*;
+pub use scala::*;
+pub use signature::*;
+pub use smap::*;
+pub use static_initializer::*;
+pub use string_constants::*;
+pub use switches::*;
+pub use synthetic_members::*;
+pub use textifier::*;
+pub use transform_session::*;
 pub use type_annotation::*;
+pub use types::*;
+pub use usage_scanner::*;
+pub use validation::*;
@@ -0,0 +1,501 @@
+use crate::{ClassFileError, ClassFileResult, Remapper, SimpleRemapper};
+use java_string::{JavaStr, JavaString};
+
+/// Reads a ProGuard `mapping.txt`, which maps from the original names to the obfuscated names.
+/// Use [`SimpleRemapper::reversed`] to deobfuscate. Line numbers and the members of inlined
+/// methods are ignored.
+pub fn read_proguard_mappings(input: &str) -> ClassFileResult<SimpleRemapper> {
+    let mut remapper = SimpleRemapper::new();
+    let mut class = None;
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let content = line.split('#').next().unwrap().trim_end();
+        if content.trim_start().is_empty() {
+            continue;
+        }
+        let (name, new_name) = content
+            .trim_start()
+            .split_once(" -> ")
+            .ok_or_else(|| bad_mappings(line_number, "expected ` -> `"))?;
+
+        if !content.starts_with(char::is_whitespace) {
+            let new_name = new_name
+                .strip_suffix(':')
+                .ok_or_else(|| bad_mappings(line_number, "expected `:` after class"))?;
+            let name = JavaString::from(name.replace('.', "/"));
+            remapper.add_class(name.clone(), new_name.replace('.', "/"));
+            class = Some(name);
+            continue;
+        }
+
+        let class = class
+            .as_ref()
+            .ok_or_else(|| bad_mappings(line_number, "member outside of a class"))?;
+        // method line numbers come before the return type
+        let member = name.trim_start_matches(|c: char| c.is_ascii_digit() || c == ':');
+        let (ty, member) = member
+            .split_once(' ')
+            .ok_or_else(|| bad_mappings(line_number, "expected member type"))?;
+        match member.split_once('(') {
+            Some((name, rest)) => {
+                // members of inlined methods are qualified by their class
+                if name.contains('.') {
+                    continue;
+                }
+                let (parameters, _) = rest
+                    .split_once(')')
+                    .ok_or_else(|| bad_mappings(line_number, "expected `)`"))?;
+                let mut desc = String::from("(");
+                for parameter in parameters.split(',').filter(|p| !p.is_empty()) {
+                    desc.push_str(&java_type_desc(parameter));
+                }
+                desc.push(')');
+                desc.push_str(&java_type_desc(ty));
+                remapper.add_method(
+                    class.clone(),
+                    JavaStr::from_str(name),
+                    JavaStr::from_str(&desc),
+                    new_name,
+                );
+            }
+            None => remapper.add_field(class.clone(), member, new_name),
+        }
+    }
+    Ok(remapper)
+}
+
+/// Converts a type as written in Java source, such as `java.lang.String[]`, to a descriptor.
+fn java_type_desc(ty: &str) -> String {
+    let element = ty.trim_end_matches("[]");
+    let mut desc = "[".repeat((ty.len() - element.len()) / 2);
+    match element {
+        "void" => desc.push('V'),
+        "boolean" => desc.push('Z'),
+        "byte" => desc.push('B'),
+        "char" => desc.push('C'),
+        "short" => desc.push('S'),
+        "int" => desc.push('I'),
+        "long" => desc.push('J'),
+        "float" => desc.push('F'),
+        "double" => desc.push('D'),
+        _ => {
+            desc.push('L');
+            desc.push_str(&element.replace('.', "/"));
+            desc.push(';');
+        }
+    }
+    desc
+}
+
+/// Reads an SRG file of `CL:`, `FD:` and `MD:` lines. Package lines are ignored.
+pub fn read_srg_mappings(input: &str) -> ClassFileResult<SimpleRemapper> {
+    let mut remapper = SimpleRemapper::new();
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let Some((kind, rest)) = line.trim().split_once(": ") else {
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Err(bad_mappings(line_number, "expected `:`"));
+        };
+        let parts: Vec<_> = rest.split_whitespace().collect();
+        match (kind, parts.as_slice()) {
+            ("PK", _) => {}
+            ("CL", [name, new_name]) => remapper.add_class(*name, *new_name),
+            // some SRG variants include the field descriptors
+            ("FD", [field, new_field] | [field, _, new_field, _]) => {
+                let (owner, name) = split_member(field, line_number)?;
+                let (_, new_name) = split_member(new_field, line_number)?;
+                remapper.add_field(owner, name, new_name);
+            }
+            ("MD", [method, desc, new_method, _]) => {
+                let (owner, name) = split_member(method, line_number)?;
+                let (_, new_name) = split_member(new_method, line_number)?;
+                remapper.add_method(
+                    owner,
+                    JavaStr::from_str(name),
+                    JavaStr::from_str(desc),
+                    new_name,
+                );
+            }
+            _ => return Err(bad_mappings(line_number, "bad SRG line")),
+        }
+    }
+    Ok(remapper)
+}
+
+fn split_member(member: &str, line_number: usize) -> ClassFileResult<(&str, &str)> {
+    member
+        .rsplit_once('/')
+        .ok_or_else(|| bad_mappings(line_number, "expected owner of member"))
+}
+
+/// Reads a TSRG file, where members are indented below their class. TSRG2 files, starting with a
+/// `tsrg2` header, are read from their first to their second namespace, ignoring parameters.
+pub fn read_tsrg_mappings(input: &str) -> ClassFileResult<SimpleRemapper> {
+    let mut lines = input.lines().enumerate().peekable();
+    let namespace_count = match lines.peek() {
+        Some((_, header)) if header.starts_with("tsrg2 ") => {
+            let count = header.split_whitespace().count() - 1;
+            lines.next();
+            count
+        }
+        _ => 2,
+    };
+    if namespace_count < 2 {
+        return Err(bad_mappings(1, "expected two namespaces"));
+    }
+
+    let mut mappings = NamespacedMappings::default();
+    for (index, line) in lines {
+        let line_number = index + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<_> = line.split_whitespace().collect();
+        if !line.starts_with(char::is_whitespace) {
+            if parts.len() != namespace_count {
+                return Err(bad_mappings(line_number, "bad TSRG class"));
+            }
+            mappings.add_class(&parts);
+        } else if line.starts_with("\t\t") || line.starts_with("        ") {
+            // parameters and the static marker of methods
+        } else if parts.len() == namespace_count {
+            mappings.add_field(line_number, &parts)?;
+        } else if parts.len() == namespace_count + 1 {
+            if parts[1].starts_with('(') {
+                mappings.add_method(line_number, parts[1], &[&parts[..1], &parts[2..]].concat())?;
+            } else {
+                mappings.add_field(line_number, &[&parts[..1], &parts[2..]].concat())?;
+            }
+        } else {
+            return Err(bad_mappings(line_number, "bad TSRG member"));
+        }
+    }
+    Ok(mappings.build(0, 1))
+}
+
+/// Reads a Tiny v1 or v2 file, mapping from the namespace `from` to the namespace `to`.
+/// Parameters, local variables and comments are ignored.
+pub fn read_tiny_mappings(input: &str, from: &str, to: &str) -> ClassFileResult<SimpleRemapper> {
+    let mut lines = input.lines().enumerate();
+    let header: Vec<_> = lines
+        .next()
+        .map(|(_, header)| header.split('\t').collect())
+        .unwrap_or_default();
+    let (is_v2, namespaces) = match header.as_slice() {
+        ["v1", namespaces @ ..] => (false, namespaces),
+        ["tiny", "2", _, namespaces @ ..] => (true, namespaces),
+        _ => return Err(bad_mappings(1, "expected Tiny header")),
+    };
+    let namespace = |name| {
+        namespaces
+            .iter()
+            .position(|namespace| *namespace == name)
+            .ok_or_else(|| bad_mappings(1, "unknown namespace"))
+    };
+    let (from, to) = (namespace(from)?, namespace(to)?);
+
+    let mut mappings = NamespacedMappings::default();
+    for (index, line) in lines {
+        let line_number = index + 1;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<_> = line.split('\t').collect();
+        let names = |start: usize| {
+            let names = parts.get(start..start + namespaces.len());
+            names.ok_or_else(|| bad_mappings(line_number, "missing names"))
+        };
+        if is_v2 {
+            match parts.as_slice() {
+                ["c", ..] => mappings.add_class(names(1)?),
+                ["", "f", _, ..] => mappings.add_field(line_number, names(3)?)?,
+                ["", "m", desc, ..] => mappings.add_method(line_number, desc, names(3)?)?,
+                // header properties, comments, parameters and local variables
+                ["", ..] => {}
+                _ => return Err(bad_mappings(line_number, "bad Tiny line")),
+            }
+        } else {
+            match parts.as_slice() {
+                ["CLASS", ..] => mappings.add_class(names(1)?),
+                ["FIELD", owner, ..] => {
+                    mappings.add_member_owner(owner);
+                    mappings.add_field(line_number, names(3)?)?;
+                }
+                ["METHOD", owner, desc, ..] => {
+                    mappings.add_member_owner(owner);
+                    mappings.add_method(line_number, desc, names(3)?)?;
+                }
+                _ => return Err(bad_mappings(line_number, "bad Tiny line")),
+            }
+        }
+    }
+    Ok(mappings.build(from, to))
+}
+
+/// Mappings between several namespaces, where the owners and descriptors of members are in the
+/// first namespace. Empty names are the same as in the first namespace.
+#[derive(Default)]
+struct NamespacedMappings {
+    classes: Vec<Vec<String>>,
+    fields: Vec<(String, Vec<String>)>,
+    methods: Vec<(String, String, Vec<String>)>,
+    /// The owner of the following members, in the first namespace.
+    owner: Option<String>,
+}
+
+impl NamespacedMappings {
+    fn add_class(&mut self, names: &[&str]) {
+        self.owner = Some(names[0].to_owned());
+        self.classes
+            .push(names.iter().map(|&name| name.to_owned()).collect());
+    }
+
+    fn add_member_owner(&mut self, owner: &str) {
+        self.owner = Some(owner.to_owned());
+    }
+
+    fn current_owner(&self, line_number: usize) -> ClassFileResult<String> {
+        self.owner
+            .clone()
+            .ok_or_else(|| bad_mappings(line_number, "member outside of a class"))
+    }
+
+    fn add_field(&mut self, line_number: usize, names: &[&str]) -> ClassFileResult<()> {
+        let owner = self.current_owner(line_number)?;
+        self.fields
+            .push((owner, names.iter().map(|&name| name.to_owned()).collect()));
+        Ok(())
+    }
+
+    fn add_method(
+        &mut self,
+        line_number: usize,
+        desc: &str,
+        names: &[&str],
+    ) -> ClassFileResult<()> {
+        let owner = self.current_owner(line_number)?;
+        self.methods.push((
+            owner,
+            desc.to_owned(),
+            names.iter().map(|&name| name.to_owned()).collect(),
+        ));
+        Ok(())
+    }
+
+    fn build(self, from: usize, to: usize) -> SimpleRemapper {
+        let name = |names: &[String], namespace: usize| {
+            let name = &names[namespace];
+            JavaString::from(if name.is_empty() { &names[0] } else { name }.as_str())
+        };
+
+        // renames the owners and descriptors of members to the source namespace
+        let mut to_source = SimpleRemapper::new();
+        let mut remapper = SimpleRemapper::new();
+        for names in &self.classes {
+            to_source.add_class(names[0].as_str(), name(names, from));
+            remapper.add_class(name(names, from), name(names, to));
+        }
+        let source_owner = |owner: &str| {
+            let owner = JavaStr::from_str(owner);
+            to_source
+                .map_class(owner)
+                .unwrap_or_else(|| owner.to_owned())
+        };
+        for (owner, names) in &self.fields {
+            remapper.add_field(source_owner(owner), name(names, from), name(names, to));
+        }
+        for (owner, desc, names) in &self.methods {
+            let desc = JavaStr::from_str(desc);
+            let source_desc = to_source.map_desc(desc).unwrap_or_else(|| desc.to_owned());
+            remapper.add_method(
+                source_owner(owner),
+                &name(names, from),
+                &source_desc,
+                name(names, to),
+            );
+        }
+        remapper
+    }
+}
+
+fn bad_mappings(line: usize, reason: &'static str) -> ClassFileError {
+    ClassFileError::BadMappings { line, reason }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        read_proguard_mappings, read_srg_mappings, read_tiny_mappings, read_tsrg_mappings,
+        ClassFileError, Remapper, SimpleClassHierarchy, SimpleRemapper,
+    };
+    use java_string::{JavaStr, JavaString};
+
+    fn map_class(remapper: &SimpleRemapper, name: &str) -> Option<JavaString> {
+        remapper.map_class(JavaStr::from_str(name))
+    }
+
+    fn map_field(remapper: &SimpleRemapper, owner: &str, name: &str) -> Option<JavaString> {
+        remapper.map_field(
+            JavaStr::from_str(owner),
+            JavaStr::from_str(name),
+            JavaStr::from_str("I"),
+        )
+    }
+
+    fn map_method(
+        remapper: &SimpleRemapper,
+        owner: &str,
+        name: &str,
+        desc: &str,
+    ) -> Option<JavaString> {
+        remapper.map_method(
+            JavaStr::from_str(owner),
+            JavaStr::from_str(name),
+            JavaStr::from_str(desc),
+        )
+    }
+
+    #[test]
+    fn test_proguard() {
+        let remapper = read_proguard_mappings(
+            "# compiler: R8\n\
+             com.example.Foo -> a:\n\
+             \x20   int count -> a\n\
+             \x20   1:4:void add(int,java.lang.String[]) -> b\n\
+             \x20   5:5:void com.example.Bar.inlined():10:10 -> b\n\
+             \x20   com.example.Foo self() -> c\n",
+        )
+        .unwrap();
+        assert_eq!(Some("a".into()), map_class(&remapper, "com/example/Foo"));
+        assert_eq!(
+            Some("a".into()),
+            map_field(&remapper, "com/example/Foo", "count")
+        );
+        assert_eq!(
+            Some("b".into()),
+            map_method(
+                &remapper,
+                "com/example/Foo",
+                "add",
+                "(I[Ljava/lang/String;)V"
+            )
+        );
+
+        let reversed = remapper.reversed();
+        assert_eq!(Some("com/example/Foo".into()), map_class(&reversed, "a"));
+        assert_eq!(
+            Some("self".into()),
+            map_method(&reversed, "a", "c", "()La;")
+        );
+
+        assert_eq!(
+            Err(ClassFileError::BadMappings {
+                line: 1,
+                reason: "member outside of a class"
+            }),
+            read_proguard_mappings("    int count -> a").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_srg() {
+        let remapper = read_srg_mappings(
+            "PK: . net/minecraft/src\n\
+             CL: a net/minecraft/src/Block\n\
+             FD: a/b net/minecraft/src/Block/blockID\n\
+             MD: a/c (La;)V net/minecraft/src/Block/copy (Lnet/minecraft/src/Block;)V\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Some("net/minecraft/src/Block".into()),
+            map_class(&remapper, "a")
+        );
+        assert_eq!(Some("blockID".into()), map_field(&remapper, "a", "b"));
+        assert_eq!(
+            Some("copy".into()),
+            map_method(&remapper, "a", "c", "(La;)V")
+        );
+    }
+
+    #[test]
+    fn test_tsrg() {
+        let remapper = read_tsrg_mappings(
+            "a net/minecraft/Block\n\
+             \tb blockID\n\
+             \tc (La;)V copy\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Some("net/minecraft/Block".into()),
+            map_class(&remapper, "a")
+        );
+        assert_eq!(Some("blockID".into()), map_field(&remapper, "a", "b"));
+        assert_eq!(
+            Some("copy".into()),
+            map_method(&remapper, "a", "c", "(La;)V")
+        );
+
+        let remapper = read_tsrg_mappings(
+            "tsrg2 obf srg\n\
+             a net/minecraft/Block\n\
+             \tb I f_1\n\
+             \tc (La;)V m_1\n\
+             \t\tstatic\n\
+             \t\t0 o p_1\n",
+        )
+        .unwrap();
+        assert_eq!(Some("f_1".into()), map_field(&remapper, "a", "b"));
+        assert_eq!(
+            Some("m_1".into()),
+            map_method(&remapper, "a", "c", "(La;)V")
+        );
+    }
+
+    #[test]
+    fn test_tiny() {
+        let v1 = "v1\tofficial\tintermediary\tnamed\n\
+                  CLASS\ta\tclass_1\tpkg/Block\n\
+                  FIELD\ta\tI\tb\tfield_1\tcount\n\
+                  METHOD\ta\t(La;)V\tc\tmethod_1\tcopy\n";
+        let v2 = "tiny\t2\t0\tofficial\tintermediary\tnamed\n\
+                  c\ta\tclass_1\tpkg/Block\n\
+                  \tf\tI\tb\tfield_1\tcount\n\
+                  \tm\t(La;)V\tc\tmethod_1\tcopy\n\
+                  \t\tp\t1\t\t\tother\n\
+                  \tc\tA block.\n";
+        for input in [v1, v2] {
+            let remapper = read_tiny_mappings(input, "intermediary", "named").unwrap();
+            assert_eq!(Some("pkg/Block".into()), map_class(&remapper, "class_1"));
+            assert_eq!(
+                Some("count".into()),
+                map_field(&remapper, "class_1", "field_1")
+            );
+            assert_eq!(
+                Some("copy".into()),
+                map_method(&remapper, "class_1", "method_1", "(Lclass_1;)V")
+            );
+        }
+        assert_eq!(
+            Err(ClassFileError::BadMappings {
+                line: 1,
+                reason: "unknown namespace"
+            }),
+            read_tiny_mappings(v1, "official", "mojang").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_inherited_members() {
+        let mut remapper = read_srg_mappings("FD: a/b a/count\n").unwrap();
+        assert_eq!(None, map_field(&remapper, "c", "b"));
+
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy.insert("a", Some("java/lang/Object".into()), false);
+        hierarchy.insert("c", Some("a".into()), false);
+        remapper.set_class_hierarchy(hierarchy);
+        assert_eq!(Some("count".into()), map_field(&remapper, "c", "b"));
+        assert_eq!(None, map_field(&remapper, "d", "b"));
+    }
+}
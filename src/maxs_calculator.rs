@@ -0,0 +1,317 @@
+use crate::class_writer::{field_desc_slots, method_desc_slots};
+use crate::{
+    ClassFileError, ClassFileResult, Label, LdcConstant, MethodAccess, MethodEvent,
+    MethodEventProviders, MethodMaxsEvent, Opcode,
+};
+use java_string::JavaStr;
+use std::collections::HashMap;
+
+/// Computes the `max_stack` and `max_locals` of a method from its events, the same way as
+/// [`ClassWriterFlags::ComputeMaxs`](crate::ClassWriterFlags::ComputeMaxs) does, for consumers of
+/// events other than the class writer.
+///
+/// Feed it the events of the method's code with [`visit`](MaxsCalculator::visit), then call
+/// [`finish`](MaxsCalculator::finish). Events which don't affect the maxs are ignored.
+#[derive(Debug, Clone)]
+pub struct MaxsCalculator {
+    max_locals: u16,
+    insn_flows: Vec<InsnFlow>,
+    /// The index of the instruction following each label.
+    labels: HashMap<Label, usize>,
+    handlers: Vec<Label>,
+}
+
+impl MaxsCalculator {
+    /// Creates a calculator for a method with the given access and descriptor, whose parameters
+    /// count towards the max locals.
+    pub fn new(access: MethodAccess, desc: &JavaStr) -> Self {
+        let (argument_slots, _) = method_desc_slots(desc);
+        let this_slots = !access.contains(MethodAccess::Static) as u16;
+        MaxsCalculator {
+            max_locals: argument_slots + this_slots,
+            insn_flows: Vec::new(),
+            labels: HashMap::new(),
+            handlers: Vec::new(),
+        }
+    }
+
+    pub fn visit<'class, P>(&mut self, event: MethodEvent<'class, P>) -> ClassFileResult<()>
+    where
+        P: MethodEventProviders<'class>,
+    {
+        if let Some((var_index, size)) = local_use(&event) {
+            self.max_locals = self.max_locals.max(var_index.saturating_add(size));
+        }
+        if let Some((stack_delta, kind)) = insn_flow(&event) {
+            self.insn_flows.push(InsnFlow {
+                offset: self.insn_flows.len(),
+                stack_delta,
+                kind,
+            });
+        }
+        match event {
+            MethodEvent::Label(label) => {
+                self.labels.insert(label, self.insn_flows.len());
+            }
+            MethodEvent::TryCatchBlocks(events) => {
+                for try_catch_block in events {
+                    self.handlers.push(try_catch_block?.handler);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> ClassFileResult<MethodMaxsEvent> {
+        let label_index = |label: Label| match self.labels.get(&label) {
+            Some(index) => Ok(Some(*index)),
+            None => Err(ClassFileError::UnknownLabel(label)),
+        };
+        Ok(MethodMaxsEvent {
+            max_stack: compute_max_stack(&self.insn_flows, &self.handlers, label_index)?,
+            max_locals: self.max_locals,
+        })
+    }
+}
+
+/// Computes the maxs of a method from its events, see [`MaxsCalculator`].
+pub fn compute_maxs<'class, P, I>(
+    access: MethodAccess,
+    desc: &JavaStr,
+    events: I,
+) -> ClassFileResult<MethodMaxsEvent>
+where
+    P: MethodEventProviders<'class>,
+    I: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+{
+    let mut calculator = MaxsCalculator::new(access, desc);
+    for event in events {
+        calculator.visit(event?)?;
+    }
+    calculator.finish()
+}
+
+/// The effect of an instruction on the operand stack and on control flow, recorded to compute the
+/// max stack.
+#[derive(Debug, Clone)]
+pub(crate) struct InsnFlow {
+    /// The position of the instruction, which labels are resolved to.
+    pub(crate) offset: usize,
+    pub(crate) stack_delta: i32,
+    pub(crate) kind: InsnFlowKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum InsnFlowKind {
+    Next,
+    Branch(Label),
+    Goto(Label),
+    Jsr(Label),
+    Switch(Vec<Label>),
+    End,
+}
+
+/// The local used by an instruction event and its size.
+pub(crate) fn local_use<'class, P>(event: &MethodEvent<'class, P>) -> Option<(u16, u16)>
+where
+    P: MethodEventProviders<'class>,
+{
+    match event {
+        MethodEvent::VarInsn {
+            opcode: Opcode::LLoad | Opcode::DLoad | Opcode::LStore | Opcode::DStore,
+            var_index,
+        } => Some((*var_index, 2)),
+        MethodEvent::VarInsn { var_index, .. } | MethodEvent::IIncInsn { var_index, .. } => {
+            Some((*var_index, 1))
+        }
+        _ => None,
+    }
+}
+
+/// The stack delta and control flow of an instruction event, or `None` if it isn't an
+/// instruction.
+pub(crate) fn insn_flow<'class, P>(event: &MethodEvent<'class, P>) -> Option<(i32, InsnFlowKind)>
+where
+    P: MethodEventProviders<'class>,
+{
+    Some(match event {
+        MethodEvent::Insn(opcode) => {
+            let kind = match opcode {
+                Opcode::IReturn
+                | Opcode::LReturn
+                | Opcode::FReturn
+                | Opcode::DReturn
+                | Opcode::AReturn
+                | Opcode::Return
+                | Opcode::AThrow => InsnFlowKind::End,
+                _ => InsnFlowKind::Next,
+            };
+            (opcode.stack_delta(), kind)
+        }
+        MethodEvent::BIPushInsn(_) | MethodEvent::SIPushInsn(_) => (1, InsnFlowKind::Next),
+        MethodEvent::NewArrayInsn(_) | MethodEvent::IIncInsn { .. } => (0, InsnFlowKind::Next),
+        MethodEvent::VarInsn { opcode, .. } => match opcode {
+            Opcode::LLoad | Opcode::DLoad => (2, InsnFlowKind::Next),
+            Opcode::LStore | Opcode::DStore => (-2, InsnFlowKind::Next),
+            Opcode::IStore | Opcode::FStore | Opcode::AStore => (-1, InsnFlowKind::Next),
+            Opcode::Ret => (0, InsnFlowKind::End),
+            _ => (1, InsnFlowKind::Next),
+        },
+        MethodEvent::TypeInsn { opcode, .. } => {
+            let stack_delta = if *opcode == Opcode::New { 1 } else { 0 };
+            (stack_delta, InsnFlowKind::Next)
+        }
+        MethodEvent::FieldInsn { opcode, desc, .. } => {
+            let size = field_desc_slots(desc) as i32;
+            let stack_delta = match opcode {
+                Opcode::GetStatic => size,
+                Opcode::PutStatic => -size,
+                Opcode::GetField => size - 1,
+                _ => -size - 1,
+            };
+            (stack_delta, InsnFlowKind::Next)
+        }
+        MethodEvent::MethodInsn { opcode, desc, .. } => {
+            let (argument_slots, return_slots) = method_desc_slots(desc);
+            let this_slots = (*opcode != Opcode::InvokeStatic) as i32;
+            (
+                return_slots as i32 - argument_slots as i32 - this_slots,
+                InsnFlowKind::Next,
+            )
+        }
+        MethodEvent::InvokeDynamicInsn { desc, .. } => {
+            let (argument_slots, return_slots) = method_desc_slots(desc);
+            (
+                return_slots as i32 - argument_slots as i32,
+                InsnFlowKind::Next,
+            )
+        }
+        MethodEvent::JumpInsn { opcode, label } => match opcode {
+            Opcode::Goto => (0, InsnFlowKind::Goto(*label)),
+            Opcode::Jsr => (0, InsnFlowKind::Jsr(*label)),
+            Opcode::IfICmpEq
+            | Opcode::IfICmpNe
+            | Opcode::IfICmpLt
+            | Opcode::IfICmpGe
+            | Opcode::IfICmpGt
+            | Opcode::IfICmpLe
+            | Opcode::IfACmpEq
+            | Opcode::IfACmpNe => (-2, InsnFlowKind::Branch(*label)),
+            _ => (-1, InsnFlowKind::Branch(*label)),
+        },
+        MethodEvent::LdcInsn(constant) => {
+            let stack_delta = match constant {
+                LdcConstant::Long(_) | LdcConstant::Double(_) => 2,
+                LdcConstant::ConstantDynamic(constant) => field_desc_slots(&constant.desc) as i32,
+                _ => 1,
+            };
+            (stack_delta, InsnFlowKind::Next)
+        }
+        MethodEvent::TableSwitchInsn { dflt, labels, .. } => {
+            let mut targets = labels.clone();
+            targets.push(*dflt);
+            (-1, InsnFlowKind::Switch(targets))
+        }
+        MethodEvent::LookupSwitchInsn { dflt, values } => {
+            let mut targets: Vec<_> = values.iter().map(|&(_, label)| label).collect();
+            targets.push(*dflt);
+            (-1, InsnFlowKind::Switch(targets))
+        }
+        MethodEvent::MultiANewArrayInsn { dimensions, .. } => {
+            (1 - *dimensions as i32, InsnFlowKind::Next)
+        }
+        _ => return None,
+    })
+}
+
+/// Computes the max stack by following the control flow from the first instruction and from each
+/// exception handler. `label_index` gives the index in `insn_flows` of the instruction at a label,
+/// or `None` if there is none.
+pub(crate) fn compute_max_stack(
+    insn_flows: &[InsnFlow],
+    handlers: &[Label],
+    label_index: impl Fn(Label) -> ClassFileResult<Option<usize>>,
+) -> ClassFileResult<u16> {
+    let mut visited = vec![false; insn_flows.len()];
+    let mut worklist = Vec::new();
+    let mut max_stack = 0;
+
+    let mut reach = |index: Option<usize>, height: i32, worklist: &mut Vec<(usize, i32)>| {
+        if let Some(index) = index {
+            if let Some(visited @ false) = visited.get_mut(index) {
+                *visited = true;
+                worklist.push((index, height));
+            }
+        }
+    };
+
+    reach(Some(0), 0, &mut worklist);
+    for &handler in handlers {
+        reach(label_index(handler)?, 1, &mut worklist);
+    }
+
+    while let Some((index, height)) = worklist.pop() {
+        let insn = &insn_flows[index];
+        let height = height + insn.stack_delta;
+        max_stack = max_stack.max(height);
+        let next = Some(index + 1);
+        match &insn.kind {
+            InsnFlowKind::Next => reach(next, height, &mut worklist),
+            InsnFlowKind::Branch(label) => {
+                reach(label_index(*label)?, height, &mut worklist);
+                reach(next, height, &mut worklist);
+            }
+            InsnFlowKind::Goto(label) => reach(label_index(*label)?, height, &mut worklist),
+            InsnFlowKind::Jsr(label) => {
+                // the return address is only on the stack in the subroutine
+                max_stack = max_stack.max(height + 1);
+                reach(label_index(*label)?, height + 1, &mut worklist);
+                reach(next, height, &mut worklist);
+            }
+            InsnFlowKind::Switch(labels) => {
+                for label in labels {
+                    reach(label_index(*label)?, height, &mut worklist);
+                }
+            }
+            InsnFlowKind::End => {}
+        }
+    }
+
+    Ok(max_stack.clamp(0, u16::MAX as i32) as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ClassNode;
+    use crate::{
+        compute_maxs, BufferedEventProviders, ClassReader, ClassReaderFlags, MethodAccess,
+        MethodEvent, Opcode,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_compute_maxs() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        for method in &class.methods {
+            let Some(expected) = method.maxs else {
+                continue;
+            };
+            let events = method.to_event().events;
+            let maxs = compute_maxs(method.access, &method.desc, events).unwrap();
+            assert_eq!(expected, maxs, "{}", method.name);
+        }
+
+        // the parameters count towards the max locals even if they're unused
+        let maxs = compute_maxs::<BufferedEventProviders, _>(
+            MethodAccess::Static,
+            JavaStr::from_str("(JI)V"),
+            [Ok(MethodEvent::Insn(Opcode::Return))],
+        )
+        .unwrap();
+        assert_eq!(0, maxs.max_stack);
+        assert_eq!(3, maxs.max_locals);
+    }
+}
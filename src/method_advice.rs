@@ -0,0 +1,332 @@
+use crate::class_writer::method_desc_slots;
+use crate::maxs_calculator::{insn_flow, local_use, InsnFlowKind};
+use crate::{
+    buffer_class_events, BufferedClassEvents, BufferedEventProviders, ClassEvent, ClassEventSource,
+    ClassFileResult, CodeGenerator, Label, MethodAccess, MethodEvent, Opcode,
+};
+use java_string::JavaStr;
+use std::collections::HashMap;
+
+/// The method being advised, see [`MethodAdvice`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AdvisedMethod<'a> {
+    pub owner: &'a JavaStr,
+    pub access: MethodAccess,
+    pub name: &'a JavaStr,
+    pub desc: &'a JavaStr,
+}
+
+/// Code to insert at the entry and exits of methods, such as for profiling or tracing. See
+/// [`add_method_advice`].
+///
+/// The same [`CodeGenerator`] is used for the entry and the exits of a method, so a local allocated
+/// with [`CodeGenerator::new_local`] on entry can be used on exit. Locals are allocated after those
+/// of the existing code.
+pub trait MethodAdvice<'class> {
+    /// Generates the code to run on entry to a method. In constructors, this runs after the
+    /// `super()` or `this()` call, once `this` is initialized.
+    fn on_method_enter(
+        &mut self,
+        method: &AdvisedMethod<'_>,
+        generator: &mut CodeGenerator<'class>,
+    ) {
+        let _ = (method, generator);
+    }
+
+    /// Generates the code to run before each return and `athrow` instruction, with the given
+    /// opcode. The return value or the exception is on the top of the stack, and must be left
+    /// there.
+    fn on_method_exit(
+        &mut self,
+        method: &AdvisedMethod<'_>,
+        opcode: Opcode,
+        generator: &mut CodeGenerator<'class>,
+    ) {
+        let _ = (method, opcode, generator);
+    }
+}
+
+/// Reads a class, inserting the code generated by `advice` at the entry and exits of each method
+/// with code. Constructors in which the `super()` or `this()` call can't be found, such as that of
+/// `java/lang/Object`, aren't advised.
+///
+/// The frames and maxs of advised methods are removed, so the result should be written with
+/// [`ClassWriterFlags::ComputeFrames`](crate::ClassWriterFlags::ComputeFrames), or
+/// [`ClassWriterFlags::ComputeMaxs`](crate::ClassWriterFlags::ComputeMaxs) for classes which don't
+/// use frames.
+pub fn add_method_advice<'class, S, A>(
+    source: S,
+    advice: &mut A,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+    A: MethodAdvice<'class> + ?Sized,
+{
+    let mut events = buffer_class_events(source)?;
+    let Some(owner) = events.0.iter().find_map(|event| match event {
+        ClassEvent::Class(class) => Some(class.name.clone()),
+        _ => None,
+    }) else {
+        return Ok(events);
+    };
+
+    for event in &mut events.0 {
+        let ClassEvent::Methods(methods) = event else {
+            continue;
+        };
+        for method in &mut methods.0 {
+            let method_info = AdvisedMethod {
+                owner: &owner,
+                access: method.access,
+                name: &method.name,
+                desc: &method.desc,
+            };
+            advise_method(&method_info, &mut method.events.0, advice);
+        }
+    }
+    Ok(events)
+}
+
+fn advise_method<'class, A>(
+    method: &AdvisedMethod<'_>,
+    events: &mut Vec<MethodEvent<'class, BufferedEventProviders>>,
+    advice: &mut A,
+) where
+    A: MethodAdvice<'class> + ?Sized,
+{
+    let Some((code_start, label_creator)) =
+        events
+            .iter()
+            .enumerate()
+            .find_map(|(index, event)| match event {
+                MethodEvent::Code { label_creator } => Some((index + 1, label_creator.clone())),
+                _ => None,
+            })
+    else {
+        return;
+    };
+    let entry = if method.name == "<init>" {
+        match find_initialization(&events[code_start..]) {
+            Some(index) => code_start + index + 1,
+            None => return,
+        }
+    } else {
+        code_start
+    };
+
+    let mut generator = CodeGenerator::new(method.access, method.desc.to_owned(), label_creator);
+    let max_locals = events
+        .iter()
+        .filter_map(local_use)
+        .map(|(var_index, size)| var_index.saturating_add(size))
+        .max()
+        .unwrap_or(0);
+    generator.reserve_locals(max_locals);
+
+    let old_events = std::mem::take(events);
+    events.reserve(old_events.len());
+    for (index, event) in old_events.into_iter().enumerate() {
+        if index == entry {
+            advice.on_method_enter(method, &mut generator);
+            events.extend(generator.take_events());
+        }
+        match event {
+            MethodEvent::Frame(_) | MethodEvent::Maxs(_) => continue,
+            MethodEvent::Insn(
+                opcode @ (Opcode::IReturn
+                | Opcode::LReturn
+                | Opcode::FReturn
+                | Opcode::DReturn
+                | Opcode::AReturn
+                | Opcode::Return
+                | Opcode::AThrow),
+            ) if index >= entry => {
+                advice.on_method_exit(method, opcode, &mut generator);
+                events.extend(generator.take_events());
+            }
+            _ => {}
+        }
+        events.push(event);
+    }
+}
+
+/// Finds the index of the `invokespecial` instruction which initializes `this` in a constructor,
+/// by following which values on the stack are the uninitialized `this`. The `this` is assumed to
+/// only be loaded from local 0.
+fn find_initialization<'class>(
+    events: &[MethodEvent<'class, BufferedEventProviders>],
+) -> Option<usize> {
+    // whether each stack slot holds the uninitialized this
+    let mut stack = Vec::new();
+    let mut branch_stacks: HashMap<Label, Vec<bool>> = HashMap::new();
+    let mut reachable = true;
+
+    for (index, event) in events.iter().enumerate() {
+        match event {
+            MethodEvent::Label(label) => {
+                if !reachable {
+                    if let Some(branch_stack) = branch_stacks.get(label) {
+                        stack.clone_from(branch_stack);
+                        reachable = true;
+                    }
+                }
+                continue;
+            }
+            _ if !reachable => continue,
+            MethodEvent::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 0,
+            } => stack.push(true),
+            MethodEvent::MethodInsn {
+                opcode, name, desc, ..
+            } => {
+                let (argument_slots, return_slots) = method_desc_slots(desc);
+                stack.truncate(stack.len().checked_sub(argument_slots as usize)?);
+                if *opcode != Opcode::InvokeStatic {
+                    let is_this = stack.pop()?;
+                    if is_this && *opcode == Opcode::InvokeSpecial && name.as_ref() == "<init>" {
+                        return Some(index);
+                    }
+                }
+                stack.resize(stack.len() + return_slots as usize, false);
+            }
+            MethodEvent::Insn(Opcode::Dup) => dup(&mut stack, 1, 0)?,
+            MethodEvent::Insn(Opcode::DupX1) => dup(&mut stack, 1, 1)?,
+            MethodEvent::Insn(Opcode::DupX2) => dup(&mut stack, 1, 2)?,
+            MethodEvent::Insn(Opcode::Dup2) => dup(&mut stack, 2, 0)?,
+            MethodEvent::Insn(Opcode::Dup2X1) => dup(&mut stack, 2, 1)?,
+            MethodEvent::Insn(Opcode::Dup2X2) => dup(&mut stack, 2, 2)?,
+            MethodEvent::Insn(Opcode::Swap) => {
+                let len = stack.len();
+                stack.swap(len.checked_sub(1)?, len.checked_sub(2)?);
+            }
+            event => {
+                let Some((stack_delta, kind)) = insn_flow(event) else {
+                    continue;
+                };
+                // values which are consumed before this is initialized can't be the uninitialized
+                // this, other than by the instructions handled above and by putfield
+                if stack_delta < 0 {
+                    stack.truncate(stack.len().checked_sub(-stack_delta as usize)?);
+                } else {
+                    stack.resize(stack.len() + stack_delta as usize, false);
+                }
+                match kind {
+                    InsnFlowKind::Next | InsnFlowKind::Jsr(_) => {}
+                    InsnFlowKind::Branch(label) => {
+                        branch_stacks.entry(label).or_insert_with(|| stack.clone());
+                    }
+                    InsnFlowKind::Goto(label) => {
+                        branch_stacks.entry(label).or_insert_with(|| stack.clone());
+                        reachable = false;
+                    }
+                    InsnFlowKind::Switch(labels) => {
+                        for label in labels {
+                            branch_stacks.entry(label).or_insert_with(|| stack.clone());
+                        }
+                        reachable = false;
+                    }
+                    InsnFlowKind::End => reachable = false,
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Duplicates the top `count` stack slots, inserting them `depth` slots below.
+fn dup(stack: &mut Vec<bool>, count: usize, depth: usize) -> Option<()> {
+    let top = stack.len().checked_sub(count)?;
+    let insert_at = top.checked_sub(depth)?;
+    let values = stack[top..].to_vec();
+    stack.splice(insert_at..insert_at, values);
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{
+        add_method_advice, AdvisedMethod, ClassReader, ClassReaderFlags, ClassWriter,
+        ClassWriterFlags, CodeGenerator, MethodAdvice, Opcode,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    struct Tracer;
+
+    impl<'class> MethodAdvice<'class> for Tracer {
+        fn on_method_enter(
+            &mut self,
+            _method: &AdvisedMethod<'_>,
+            generator: &mut CodeGenerator<'class>,
+        ) {
+            generator.invoke_static(
+                JavaStr::from_str("Tracer"),
+                JavaStr::from_str("enter"),
+                JavaStr::from_str("()V"),
+            );
+        }
+
+        fn on_method_exit(
+            &mut self,
+            _method: &AdvisedMethod<'_>,
+            opcode: Opcode,
+            generator: &mut CodeGenerator<'class>,
+        ) {
+            generator.push_int(opcode as i32);
+            generator.invoke_static(
+                JavaStr::from_str("Tracer"),
+                JavaStr::from_str("exit"),
+                JavaStr::from_str("(I)V"),
+            );
+        }
+    }
+
+    fn advised_calls(class: &[u8], method_name: &str) -> Vec<String> {
+        let reader = ClassReader::new(class, ClassReaderFlags::None).unwrap();
+        let events = add_method_advice(&reader, &mut Tracer).unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::ComputeMaxs)
+            .write(events)
+            .unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == method_name)
+            .unwrap();
+        method
+            .instructions
+            .iter()
+            .filter_map(|insn| match insn {
+                InsnNode::MethodInsn { name, .. } => Some(name.to_string()),
+                InsnNode::Insn(opcode @ (Opcode::Return | Opcode::IReturn | Opcode::AThrow)) => {
+                    Some(format!("{opcode:?}"))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_constructor_advice() {
+        assert_eq!(
+            vec!["<init>", "enter", "exit", "Return"],
+            advised_calls(include_class!("HelloWorld"), "<init>")
+        );
+    }
+
+    #[test]
+    fn test_exit_advice() {
+        let calls = advised_calls(include_class!("TestCode"), "tryCatch");
+        assert_eq!("enter", calls[0]);
+        let exits = calls.iter().filter(|call| *call == "exit").count();
+        let returns = calls
+            .iter()
+            .filter(|call| matches!(call.as_str(), "IReturn" | "AThrow"))
+            .count();
+        assert!(returns >= 3);
+        assert_eq!(returns, exits);
+    }
+}
@@ -0,0 +1,534 @@
+use crate::frame_computer::{FrameComputer, FrameInsn};
+use crate::{
+    buffer_class_events, BufferedClassEvents, BufferedEventProviders, ClassAccess, ClassEvent,
+    ClassEventSource, ClassFileError, ClassFileResult, ClassHierarchy, ClassMethodEvent,
+    EventBuffer, FrameValue, Label, MethodAccess, MethodEvent, MethodTryCatchBlockEvent, Opcode,
+    SimpleClassHierarchy,
+};
+use derive_more::Debug;
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The maximum size of the code of a method, in bytes.
+pub const MAX_CODE_SIZE: usize = 65535;
+
+type BufferedMethod<'class> =
+    ClassMethodEvent<'class, EventBuffer<MethodEvent<'class, BufferedEventProviders>>>;
+
+/// Splits methods whose code is too large into the original method and private static
+/// continuation methods. This is experimental.
+///
+/// A method is split at an instruction where the operand stack is empty, and which no jumps or
+/// exception handlers cross. The original method ends by passing its locals to the continuation
+/// as arguments and returning its result. The size of the code is estimated conservatively, so
+/// methods slightly below the limit may be split too, and methods without a suitable instruction
+/// to split at are left unchanged, as are constructors and static initializers.
+///
+/// The frames, maxs and local variable tables of split methods are removed, so the result should
+/// be written with [`ClassWriterFlags::ComputeFrames`](crate::ClassWriterFlags::ComputeFrames).
+#[derive(Debug, Clone)]
+pub struct MethodSplitter {
+    max_code_size: usize,
+    #[debug(skip)]
+    class_hierarchy: Option<Arc<dyn ClassHierarchy>>,
+}
+
+impl Default for MethodSplitter {
+    fn default() -> Self {
+        MethodSplitter {
+            max_code_size: MAX_CODE_SIZE,
+            class_hierarchy: None,
+        }
+    }
+}
+
+impl MethodSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the code size above which methods are split, which defaults to [`MAX_CODE_SIZE`].
+    pub fn set_max_code_size(&mut self, max_code_size: usize) {
+        self.max_code_size = max_code_size;
+    }
+
+    /// Sets the class hierarchy used to find the types of the locals passed to continuations.
+    /// Without one, only `java/lang/Object` is known.
+    pub fn set_class_hierarchy<H>(&mut self, class_hierarchy: H)
+    where
+        H: ClassHierarchy + 'static,
+    {
+        self.class_hierarchy = Some(Arc::new(class_hierarchy));
+    }
+
+    pub fn split<'class, S>(&self, source: S) -> ClassFileResult<BufferedClassEvents<'class>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut events = buffer_class_events(source)?;
+        let (this_class, is_interface) = match events.0.first() {
+            Some(ClassEvent::Class(class)) => (
+                class.name.clone(),
+                class.access.contains(ClassAccess::Interface),
+            ),
+            _ => return Err(ClassFileError::MissingClassEvent),
+        };
+        let class_hierarchy = self
+            .class_hierarchy
+            .clone()
+            .unwrap_or_else(|| Arc::new(SimpleClassHierarchy::new()));
+
+        let mut continuation_count = 0;
+        for event in &mut events.0 {
+            let ClassEvent::Methods(methods) = event else {
+                continue;
+            };
+            let mut result = Vec::with_capacity(methods.0.len());
+            for method in std::mem::take(&mut methods.0) {
+                // final fields can only be assigned in constructors and static initializers
+                if method.name.starts_with('<') {
+                    result.push(method);
+                    continue;
+                }
+                let base_name = method.name.to_string();
+                let mut pending = Some(method);
+                while let Some(method) = pending.take() {
+                    let split = SplitContext {
+                        max_code_size: self.max_code_size,
+                        class_hierarchy: &class_hierarchy,
+                        this_class: &this_class,
+                        is_interface,
+                    };
+                    let (method, continuation) =
+                        split.split_method(method, &base_name, continuation_count)?;
+                    result.push(method);
+                    if continuation.is_some() {
+                        continuation_count += 1;
+                        pending = continuation;
+                    }
+                }
+            }
+            methods.0 = result;
+        }
+        Ok(events)
+    }
+}
+
+struct SplitContext<'a, 'class> {
+    max_code_size: usize,
+    class_hierarchy: &'a Arc<dyn ClassHierarchy>,
+    this_class: &'a Cow<'class, JavaStr>,
+    is_interface: bool,
+}
+
+impl<'class> SplitContext<'_, 'class> {
+    fn split_method(
+        &self,
+        method: BufferedMethod<'class>,
+        base_name: &str,
+        continuation_index: u32,
+    ) -> ClassFileResult<(BufferedMethod<'class>, Option<BufferedMethod<'class>>)> {
+        let events = &method.events.0;
+        let Some(code_start) = events
+            .iter()
+            .position(|event| matches!(event, MethodEvent::Code { .. }))
+        else {
+            return Ok((method, None));
+        };
+
+        let insn_events: Vec<usize> = (code_start + 1..events.len())
+            .filter(|&index| is_insn(&events[index]))
+            .collect();
+        let mut prefix_sizes = Vec::with_capacity(insn_events.len() + 1);
+        prefix_sizes.push(0);
+        for &index in &insn_events {
+            prefix_sizes.push(prefix_sizes.last().unwrap() + max_insn_size(&events[index]));
+        }
+        if *prefix_sizes.last().unwrap() <= self.max_code_size {
+            return Ok((method, None));
+        }
+
+        let mut label_insns = HashMap::new();
+        let mut insn_count = 0;
+        for event in &events[code_start + 1..] {
+            match event {
+                MethodEvent::Label(label) => {
+                    label_insns.insert(*label, insn_count);
+                }
+                event if is_insn(event) => insn_count += 1,
+                _ => {}
+            }
+        }
+        let label_insn = |label: Label| {
+            label_insns
+                .get(&label)
+                .copied()
+                .ok_or(ClassFileError::UnknownLabel(label))
+        };
+
+        let try_catch_blocks: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                MethodEvent::TryCatchBlocks(blocks) => Some(blocks.0.iter().cloned()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        let mut frame_computer = FrameComputer::new(
+            self.class_hierarchy.clone(),
+            self.this_class.clone(),
+            method.access,
+            &method.name,
+            &method.desc,
+        );
+        let mut targets = Vec::with_capacity(insn_events.len());
+        for (insn, &index) in insn_events.iter().enumerate() {
+            let frame_insn = FrameInsn::from_event(&events[index], Label::synthetic(insn as u32))
+                .expect("not an instruction");
+            frame_computer.add_insn(insn, frame_insn);
+            targets.push(match &events[index] {
+                MethodEvent::JumpInsn { opcode, label } => {
+                    if *opcode == Opcode::Jsr {
+                        return Ok((method, None));
+                    }
+                    vec![label_insn(*label)?]
+                }
+                MethodEvent::TableSwitchInsn { dflt, labels, .. } => labels
+                    .iter()
+                    .chain(std::iter::once(dflt))
+                    .map(|label| label_insn(*label))
+                    .collect::<ClassFileResult<_>>()?,
+                MethodEvent::LookupSwitchInsn { dflt, values } => values
+                    .iter()
+                    .map(|(_, label)| label)
+                    .chain(std::iter::once(dflt))
+                    .map(|label| label_insn(*label))
+                    .collect::<ClassFileResult<_>>()?,
+                _ => Vec::new(),
+            });
+        }
+        let states = match frame_computer.compute_states(label_insn, &try_catch_blocks) {
+            Ok(states) => states,
+            Err(ClassFileError::UnreachableCode(_) | ClassFileError::WriterUnsupported(_)) => {
+                return Ok((method, None))
+            }
+            Err(err) => return Err(err),
+        };
+        let try_ranges = try_catch_blocks
+            .iter()
+            .map(|block| {
+                Ok((
+                    label_insn(block.start)?,
+                    label_insn(block.end)?,
+                    label_insn(block.handler)?,
+                ))
+            })
+            .collect::<ClassFileResult<Vec<_>>>()?;
+
+        // the furthest target jumped to from before each instruction, and the nearest target
+        // jumped to from each instruction onwards
+        let mut max_prefix_target = vec![0; insn_count + 1];
+        for insn in 0..insn_count {
+            let max_target = targets[insn].iter().copied().max().unwrap_or(0);
+            max_prefix_target[insn + 1] = max_prefix_target[insn].max(max_target);
+        }
+        let mut min_suffix_target = vec![usize::MAX; insn_count + 1];
+        for insn in (0..insn_count).rev() {
+            let min_target = targets[insn].iter().copied().min().unwrap_or(usize::MAX);
+            min_suffix_target[insn] = min_suffix_target[insn + 1].min(min_target);
+        }
+
+        let split_point = (1..insn_count).rev().find(|&insn| {
+            let state = &states[insn];
+            let argument_count = state
+                .locals
+                .iter()
+                .filter(|value| **value != FrameValue::Top)
+                .count();
+            state.stack.is_empty()
+                && !state.locals.iter().any(|value| {
+                    matches!(
+                        value,
+                        FrameValue::Uninitialized(_) | FrameValue::UninitializedThis
+                    )
+                })
+                && max_prefix_target[insn] <= insn
+                && min_suffix_target[insn] >= insn
+                && try_ranges.iter().all(|&(start, end, handler)| {
+                    (end <= insn && handler < insn) || (start >= insn && handler >= insn)
+                })
+                && prefix_sizes[insn] + argument_count * 4 + 4 <= self.max_code_size
+        });
+        let Some(split_point) = split_point else {
+            return Ok((method, None));
+        };
+
+        let mut arguments = Vec::new();
+        let mut continuation_desc = String::from("(");
+        let mut var_mapping = HashMap::new();
+        let mut argument_slots = 0u16;
+        for (var_index, value) in states[split_point].locals.iter().enumerate() {
+            let (load, desc, size) = match value {
+                FrameValue::Top => continue,
+                FrameValue::Integer => (Opcode::ILoad, Cow::Borrowed("I"), 1),
+                FrameValue::Float => (Opcode::FLoad, Cow::Borrowed("F"), 1),
+                FrameValue::Long => (Opcode::LLoad, Cow::Borrowed("J"), 2),
+                FrameValue::Double => (Opcode::DLoad, Cow::Borrowed("D"), 2),
+                FrameValue::Null => (Opcode::ALoad, Cow::Borrowed("Ljava/lang/Object;"), 1),
+                FrameValue::Class(name) if name.starts_with('[') => {
+                    (Opcode::ALoad, Cow::Owned(name.to_string()), 1)
+                }
+                FrameValue::Class(name) => (Opcode::ALoad, Cow::Owned(format!("L{name};")), 1),
+                FrameValue::Uninitialized(_) | FrameValue::UninitializedThis => {
+                    unreachable!("split points don't have uninitialized locals")
+                }
+            };
+            arguments.push(MethodEvent::VarInsn {
+                opcode: load,
+                var_index: var_index as u16,
+            });
+            continuation_desc.push_str(&desc);
+            var_mapping.insert(var_index as u16, argument_slots);
+            argument_slots += size;
+        }
+        let return_desc = &method.desc[method.desc.find(')').map_or(0, |index| index + 1)..];
+        continuation_desc.push(')');
+        continuation_desc.push_str(&return_desc.to_string());
+        let return_opcode = match return_desc.as_bytes().first() {
+            Some(b'V') => Opcode::Return,
+            Some(b'B' | b'C' | b'I' | b'S' | b'Z') => Opcode::IReturn,
+            Some(b'J') => Opcode::LReturn,
+            Some(b'F') => Opcode::FReturn,
+            Some(b'D') => Opcode::DReturn,
+            _ => Opcode::AReturn,
+        };
+        let continuation_name = JavaString::from(format!("{base_name}$split${continuation_index}"));
+        let continuation_desc = JavaString::from(continuation_desc);
+
+        let split_event = insn_events[split_point];
+        let boundary_start = insn_events[split_point - 1] + 1;
+        let map_var = |var_index: u16| {
+            var_mapping
+                .get(&var_index)
+                .copied()
+                .unwrap_or(argument_slots + var_index)
+        };
+
+        let ClassMethodEvent {
+            access,
+            name,
+            desc,
+            signature,
+            exceptions,
+            events,
+        } = method;
+        let mut method_events = Vec::new();
+        let mut continuation_events = Vec::new();
+        let mut prefix_try_catch_blocks = Vec::new();
+        let mut suffix_try_catch_blocks = Vec::new();
+        for (index, event) in events.0.into_iter().enumerate() {
+            match event {
+                MethodEvent::Code { label_creator } => {
+                    continuation_events.push(MethodEvent::Code {
+                        label_creator: label_creator.clone(),
+                    });
+                    method_events.push(MethodEvent::Code { label_creator });
+                }
+                MethodEvent::Frame(_)
+                | MethodEvent::Maxs(_)
+                | MethodEvent::LocalVariables(_)
+                | MethodEvent::LocalVariableAnnotations(_)
+                | MethodEvent::TryCatchBlockAnnotations(_) => {}
+                MethodEvent::TryCatchBlocks(blocks) => {
+                    for block in blocks.0 {
+                        if label_insn(block.end)? <= split_point {
+                            prefix_try_catch_blocks.push(block);
+                        } else {
+                            suffix_try_catch_blocks.push(block);
+                        }
+                    }
+                }
+                MethodEvent::Label(label) if (boundary_start..split_event).contains(&index) => {
+                    method_events.push(MethodEvent::Label(label));
+                    continuation_events.push(MethodEvent::Label(label));
+                }
+                MethodEvent::LineNumber { line, start }
+                    if (boundary_start..split_event).contains(&index) =>
+                {
+                    continuation_events.push(MethodEvent::LineNumber { line, start });
+                }
+                event if index == split_event => {
+                    method_events.append(&mut arguments);
+                    method_events.push(MethodEvent::MethodInsn {
+                        opcode: Opcode::InvokeStatic,
+                        owner: self.this_class.clone(),
+                        name: Cow::Owned(continuation_name.clone()),
+                        desc: Cow::Owned(continuation_desc.clone()),
+                        is_interface: self.is_interface,
+                    });
+                    method_events.push(MethodEvent::Insn(return_opcode));
+                    continuation_events.push(remap_vars(event, map_var));
+                }
+                MethodEvent::CodeAttributes(attributes) => {
+                    method_events.push(MethodEvent::CodeAttributes(attributes));
+                }
+                event if index > split_event && index > code_start => {
+                    continuation_events.push(remap_vars(event, map_var));
+                }
+                event => method_events.push(event),
+            }
+        }
+        if !prefix_try_catch_blocks.is_empty() {
+            method_events.push(MethodEvent::TryCatchBlocks(EventBuffer(
+                prefix_try_catch_blocks,
+            )));
+        }
+        if !suffix_try_catch_blocks.is_empty() {
+            continuation_events.push(MethodEvent::TryCatchBlocks(EventBuffer(
+                suffix_try_catch_blocks,
+            )));
+        }
+
+        let continuation = ClassMethodEvent {
+            access: MethodAccess::Private | MethodAccess::Static | MethodAccess::Synthetic,
+            name: Cow::Owned(continuation_name),
+            desc: Cow::Owned(continuation_desc),
+            signature: None,
+            exceptions: exceptions.clone(),
+            events: EventBuffer(continuation_events),
+        };
+        let method = ClassMethodEvent {
+            access,
+            name,
+            desc,
+            signature,
+            exceptions,
+            events: EventBuffer(method_events),
+        };
+        Ok((method, Some(continuation)))
+    }
+}
+
+fn is_insn(event: &MethodEvent<'_, BufferedEventProviders>) -> bool {
+    matches!(
+        event,
+        MethodEvent::Insn(_)
+            | MethodEvent::BIPushInsn(_)
+            | MethodEvent::SIPushInsn(_)
+            | MethodEvent::NewArrayInsn(_)
+            | MethodEvent::VarInsn { .. }
+            | MethodEvent::TypeInsn { .. }
+            | MethodEvent::FieldInsn { .. }
+            | MethodEvent::MethodInsn { .. }
+            | MethodEvent::InvokeDynamicInsn { .. }
+            | MethodEvent::JumpInsn { .. }
+            | MethodEvent::LdcInsn(_)
+            | MethodEvent::IIncInsn { .. }
+            | MethodEvent::TableSwitchInsn { .. }
+            | MethodEvent::LookupSwitchInsn { .. }
+            | MethodEvent::MultiANewArrayInsn { .. }
+    )
+}
+
+/// An upper bound of the size of an instruction, assuming the widest encoding and the most
+/// padding.
+fn max_insn_size(event: &MethodEvent<'_, BufferedEventProviders>) -> usize {
+    match event {
+        MethodEvent::Insn(_) => 1,
+        MethodEvent::BIPushInsn(_) | MethodEvent::NewArrayInsn(_) => 2,
+        MethodEvent::SIPushInsn(_)
+        | MethodEvent::TypeInsn { .. }
+        | MethodEvent::FieldInsn { .. }
+        | MethodEvent::LdcInsn(_) => 3,
+        MethodEvent::VarInsn { .. } | MethodEvent::MultiANewArrayInsn { .. } => 4,
+        MethodEvent::MethodInsn { .. } | MethodEvent::InvokeDynamicInsn { .. } => 5,
+        MethodEvent::IIncInsn { .. } => 6,
+        // a conditional jump may be replaced with the opposite jump over a goto_w
+        MethodEvent::JumpInsn { .. } => 8,
+        MethodEvent::TableSwitchInsn { labels, .. } => 16 + labels.len() * 4,
+        MethodEvent::LookupSwitchInsn { values, .. } => 12 + values.len() * 8,
+        _ => 0,
+    }
+}
+
+fn remap_vars<'class>(
+    event: MethodEvent<'class, BufferedEventProviders>,
+    map_var: impl Fn(u16) -> u16,
+) -> MethodEvent<'class, BufferedEventProviders> {
+    match event {
+        MethodEvent::VarInsn { opcode, var_index } => MethodEvent::VarInsn {
+            opcode,
+            var_index: map_var(var_index),
+        },
+        MethodEvent::IIncInsn {
+            var_index,
+            increment,
+        } => MethodEvent::IIncInsn {
+            var_index: map_var(var_index),
+            increment,
+        },
+        event => event,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassEvent, ClassEventSource, ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags,
+        MethodAccess, MethodSplitter, SimpleClassHierarchy,
+    };
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_split_methods() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let mut class_hierarchy = SimpleClassHierarchy::new();
+        for (name, super_class) in [
+            ("java/lang/Throwable", "java/lang/Object"),
+            ("java/lang/Exception", "java/lang/Throwable"),
+            ("java/lang/RuntimeException", "java/lang/Exception"),
+            ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+            (
+                "java/lang/NullPointerException",
+                "java/lang/RuntimeException",
+            ),
+        ] {
+            class_hierarchy.insert(name, Some(super_class.into()), false);
+        }
+        let mut splitter = MethodSplitter::new();
+        splitter.set_max_code_size(30);
+        splitter.set_class_hierarchy(class_hierarchy.clone());
+        let events = splitter.split(&reader).unwrap();
+
+        let mut class_writer = ClassWriter::new(ClassWriterFlags::ComputeFrames);
+        class_writer.set_class_hierarchy(class_hierarchy);
+        let written = class_writer.write(events).unwrap();
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let mut methods = Vec::new();
+        for event in reader.events().unwrap() {
+            if let ClassEvent::Methods(events) = event.unwrap() {
+                methods.extend(events.into_iter().map(|event| {
+                    let event = event.unwrap();
+                    (event.access, event.name.to_string(), event.desc.to_string())
+                }));
+            }
+        }
+        let (access, _, desc) = methods
+            .iter()
+            .find(|(_, name, _)| name.starts_with("loops$split$"))
+            .unwrap();
+        assert_eq!(
+            MethodAccess::Private | MethodAccess::Static | MethodAccess::Synthetic,
+            *access
+        );
+        assert!(desc.starts_with("(LTestCode;[I"));
+        assert!(desc.ends_with(")D"));
+        assert!(methods
+            .iter()
+            .all(|(_, name, _)| !name.starts_with("<init>$")));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    ClassEvent, ClassEventSource, ClassFileResult, Label, MethodEvent, MethodEventProviders, Opcode,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Size and complexity metrics of a method's code, for finding huge or complex methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodMetrics<'class> {
+    pub name: Cow<'class, JavaStr>,
+    pub desc: Cow<'class, JavaStr>,
+    pub insn_count: u32,
+    /// The number of linearly independent paths through the control flow graph: one more than
+    /// the number of extra successors of each branch instruction, plus one for each exception
+    /// handler.
+    pub cyclomatic_complexity: u32,
+    pub max_stack: u16,
+    pub max_locals: u16,
+    /// The maximum number of distinct try ranges covering a single instruction.
+    pub max_try_depth: u32,
+    pub invoke_counts: BTreeMap<Opcode, u32>,
+}
+
+impl<'class> MethodMetrics<'class> {
+    pub fn compute<E, P>(
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+        events: E,
+    ) -> ClassFileResult<MethodMetrics<'class>>
+    where
+        E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+        P: MethodEventProviders<'class>,
+    {
+        let mut metrics = MethodMetrics {
+            name,
+            desc,
+            insn_count: 0,
+            cyclomatic_complexity: 1,
+            max_stack: 0,
+            max_locals: 0,
+            max_try_depth: 0,
+            invoke_counts: BTreeMap::new(),
+        };
+        let mut label_positions = HashMap::new();
+        let mut try_ranges = BTreeSet::new();
+        let mut handlers = BTreeSet::new();
+
+        for event in events {
+            let extra_successors = match event? {
+                MethodEvent::Label(label) => {
+                    label_positions.insert(label, metrics.insn_count);
+                    continue;
+                }
+                MethodEvent::Maxs(maxs) => {
+                    metrics.max_stack = maxs.max_stack;
+                    metrics.max_locals = maxs.max_locals;
+                    continue;
+                }
+                MethodEvent::TryCatchBlocks(try_catch_blocks) => {
+                    for try_catch_block in try_catch_blocks {
+                        let try_catch_block = try_catch_block?;
+                        try_ranges.insert((try_catch_block.start, try_catch_block.end));
+                        handlers.insert(try_catch_block.handler);
+                    }
+                    continue;
+                }
+                MethodEvent::Insn(_)
+                | MethodEvent::BIPushInsn(_)
+                | MethodEvent::SIPushInsn(_)
+                | MethodEvent::NewArrayInsn(_)
+                | MethodEvent::VarInsn { .. }
+                | MethodEvent::TypeInsn { .. }
+                | MethodEvent::FieldInsn { .. }
+                | MethodEvent::LdcInsn(_)
+                | MethodEvent::IIncInsn { .. }
+                | MethodEvent::MultiANewArrayInsn { .. } => 0,
+                MethodEvent::MethodInsn { opcode, .. } => {
+                    *metrics.invoke_counts.entry(opcode).or_default() += 1;
+                    0
+                }
+                MethodEvent::InvokeDynamicInsn { .. } => {
+                    *metrics
+                        .invoke_counts
+                        .entry(Opcode::InvokeDynamic)
+                        .or_default() += 1;
+                    0
+                }
+                MethodEvent::JumpInsn { opcode, .. } => match opcode {
+                    Opcode::Goto | Opcode::Jsr => 0,
+                    _ => 1,
+                },
+                MethodEvent::TableSwitchInsn { dflt, labels, .. } => {
+                    distinct_targets(dflt, labels.into_iter())
+                }
+                MethodEvent::LookupSwitchInsn { dflt, values } => {
+                    distinct_targets(dflt, values.into_iter().map(|(_, label)| label))
+                }
+                _ => continue,
+            };
+            metrics.insn_count += 1;
+            metrics.cyclomatic_complexity += extra_successors;
+        }
+
+        metrics.cyclomatic_complexity += handlers.len() as u32;
+
+        let mut depth_changes = Vec::new();
+        for (start, end) in try_ranges {
+            if let (Some(&start), Some(&end)) =
+                (label_positions.get(&start), label_positions.get(&end))
+            {
+                if start < end {
+                    depth_changes.push((start, 1));
+                    depth_changes.push((end, -1));
+                }
+            }
+        }
+        // ends sort before starts at the same position, as try ranges are exclusive at the end
+        depth_changes.sort();
+        let mut depth = 0i32;
+        for (_, change) in depth_changes {
+            depth += change;
+            metrics.max_try_depth = metrics.max_try_depth.max(depth as u32);
+        }
+
+        Ok(metrics)
+    }
+
+    /// Computes the metrics of each method with code in the given class.
+    pub fn compute_all<S>(source: S) -> ClassFileResult<Vec<MethodMetrics<'class>>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut result = Vec::new();
+        for event in source.events()? {
+            if let ClassEvent::Methods(methods) = event? {
+                for method in methods {
+                    let method = method?;
+                    let metrics = MethodMetrics::compute(method.name, method.desc, method.events)?;
+                    if metrics.insn_count != 0 {
+                        result.push(metrics);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn invoke_count(&self) -> u32 {
+        self.invoke_counts.values().sum()
+    }
+}
+
+fn distinct_targets(dflt: Label, labels: impl Iterator<Item = Label>) -> u32 {
+    let mut targets: BTreeSet<_> = labels.collect();
+    targets.insert(dflt);
+    targets.len() as u32 - 1
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassReader, ClassReaderFlags, MethodMetrics, Opcode};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_method_metrics() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let metrics = MethodMetrics::compute_all(&reader).unwrap();
+        let get = |name: &str| {
+            metrics
+                .iter()
+                .find(|metrics| metrics.name.as_ref() == name)
+                .unwrap()
+        };
+
+        let switches = get("switches");
+        assert_eq!(6, switches.cyclomatic_complexity);
+        assert_eq!(0, switches.max_try_depth);
+        assert_eq!(Some(&1), switches.invoke_counts.get(&Opcode::InvokeVirtual));
+
+        let loops = get("loops");
+        assert_eq!(2, loops.cyclomatic_complexity);
+        assert_eq!(0, loops.invoke_count());
+
+        let try_catch = get("tryCatch");
+        assert_eq!(1, try_catch.max_try_depth);
+        assert_eq!(5, try_catch.max_stack);
+
+        let concat = get("concat");
+        assert_eq!(1, concat.cyclomatic_complexity);
+        assert_eq!(Some(&1), concat.invoke_counts.get(&Opcode::InvokeDynamic));
+    }
+}
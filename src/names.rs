@@ -0,0 +1,204 @@
+use crate::validation::field_desc_len;
+use crate::{ClassFileError, ClassFileResult};
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+
+/// Whether a name is a valid internal name of a class other than an array class, per JVMS 4.2.1.
+pub fn is_valid_internal_name(name: &JavaStr) -> bool {
+    crate::validation::is_valid_internal_name(name.as_bytes())
+}
+
+/// Whether a name is a valid binary name of a class other than an array class.
+pub fn is_valid_binary_name(name: &JavaStr) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|segment| {
+            !segment.is_empty()
+                && !segment
+                    .as_bytes()
+                    .iter()
+                    .any(|&b| matches!(b, b'/' | b';' | b'['))
+        })
+}
+
+fn is_valid_array_desc(desc: &JavaStr) -> bool {
+    desc.starts_with('[') && field_desc_len(desc.as_bytes()) == Some(desc.len())
+}
+
+fn bad_class_name(name: &JavaStr) -> ClassFileError {
+    ClassFileError::BadClassName(name.to_owned())
+}
+
+/// Converts an internal name such as `java/lang/String` to a binary name such as
+/// `java.lang.String`. The names of array classes are their descriptors, such as
+/// `[Ljava/lang/String;` and `[Ljava.lang.String;`, like `Class.getName()`.
+pub fn internal_to_binary_name(name: &JavaStr) -> ClassFileResult<JavaString> {
+    if !is_valid_internal_name(name) && !is_valid_array_desc(name) {
+        return Err(bad_class_name(name));
+    }
+    Ok(name.replace('/', "."))
+}
+
+/// The inverse of [`internal_to_binary_name`].
+pub fn binary_to_internal_name(name: &JavaStr) -> ClassFileResult<JavaString> {
+    if name.starts_with('[') {
+        let internal_name = name.replace('.', "/");
+        return if is_valid_array_desc(&internal_name) {
+            Ok(internal_name)
+        } else {
+            Err(bad_class_name(name))
+        };
+    }
+    if !is_valid_binary_name(name) {
+        return Err(bad_class_name(name));
+    }
+    Ok(name.replace('.', "/"))
+}
+
+/// Converts the internal name of a class to its descriptor. Array classes are already descriptors.
+pub fn internal_name_to_desc(name: &JavaStr) -> ClassFileResult<Cow<'_, JavaStr>> {
+    if is_valid_array_desc(name) {
+        return Ok(Cow::Borrowed(name));
+    }
+    if !is_valid_internal_name(name) {
+        return Err(bad_class_name(name));
+    }
+    let mut desc = JavaString::with_capacity(name.len() + 2);
+    desc.push('L');
+    desc.push_java_str(name);
+    desc.push(';');
+    Ok(Cow::Owned(desc))
+}
+
+/// Converts the descriptor of a class or array type to its internal name.
+pub fn desc_to_internal_name(desc: &JavaStr) -> ClassFileResult<&JavaStr> {
+    if field_desc_len(desc.as_bytes()) != Some(desc.len()) {
+        return Err(ClassFileError::BadDescriptor(desc.to_owned()));
+    }
+    match desc.as_bytes()[0] {
+        b'[' => Ok(desc),
+        b'L' => Ok(&desc[1..desc.len() - 1]),
+        _ => Err(ClassFileError::BadDescriptor(desc.to_owned())),
+    }
+}
+
+/// The internal name of the package of a class, which is empty for the default package.
+pub fn package_name(internal_name: &JavaStr) -> &JavaStr {
+    internal_name
+        .rsplit_once('/')
+        .map_or(JavaStr::from_str(""), |(package, _)| package)
+}
+
+/// The simple name of a class as written in Java source, such as `Entry` for `java/util/Map$Entry`
+/// or `int[]` for `[I`. Anonymous classes have an empty simple name.
+///
+/// Without the `InnerClasses` attribute, a `$` is assumed to separate nested classes, and the
+/// digits after it to number local and anonymous classes.
+pub fn simple_name(internal_name: &JavaStr) -> Cow<'_, JavaStr> {
+    let element = internal_name.trim_start_matches('[');
+    let dimensions = internal_name.len() - element.len();
+    if dimensions == 0 {
+        return Cow::Borrowed(class_simple_name(internal_name));
+    }
+    let mut simple_name = JavaString::from(match element.as_bytes().first() {
+        Some(b'Z') => "boolean",
+        Some(b'B') => "byte",
+        Some(b'C') => "char",
+        Some(b'S') => "short",
+        Some(b'I') => "int",
+        Some(b'J') => "long",
+        Some(b'F') => "float",
+        Some(b'D') => "double",
+        _ => "",
+    });
+    if let Some(class_name) = element
+        .strip_prefix('L')
+        .and_then(|element| element.strip_suffix(';'))
+    {
+        simple_name.push_java_str(class_simple_name(class_name));
+    }
+    for _ in 0..dimensions {
+        simple_name.push_str("[]");
+    }
+    Cow::Owned(simple_name)
+}
+
+fn class_simple_name(internal_name: &JavaStr) -> &JavaStr {
+    let name = internal_name
+        .rsplit_once('/')
+        .map_or(internal_name, |(_, name)| name);
+    let Some((_, nested_name)) = name.rsplit_once('$') else {
+        return name;
+    };
+    let start = nested_name
+        .bytes()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(nested_name.len());
+    &nested_name[start..]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::names::{
+        binary_to_internal_name, desc_to_internal_name, internal_name_to_desc,
+        internal_to_binary_name, package_name, simple_name,
+    };
+    use java_string::JavaStr;
+
+    #[test]
+    fn test_convert_names() {
+        assert_eq!(
+            internal_to_binary_name(JavaStr::from_str("java/util/Map$Entry")).unwrap(),
+            "java.util.Map$Entry"
+        );
+        assert_eq!(
+            binary_to_internal_name(JavaStr::from_str("java.lang.String")).unwrap(),
+            "java/lang/String"
+        );
+        assert_eq!(
+            binary_to_internal_name(JavaStr::from_str("[Ljava.lang.String;")).unwrap(),
+            "[Ljava/lang/String;"
+        );
+        assert_eq!(
+            internal_name_to_desc(JavaStr::from_str("java/lang/String"))
+                .unwrap()
+                .as_ref(),
+            "Ljava/lang/String;"
+        );
+        assert_eq!(
+            internal_name_to_desc(JavaStr::from_str("[I"))
+                .unwrap()
+                .as_ref(),
+            "[I"
+        );
+        assert_eq!(
+            desc_to_internal_name(JavaStr::from_str("Ljava/lang/String;")).unwrap(),
+            "java/lang/String"
+        );
+        assert_eq!(
+            package_name(JavaStr::from_str("java/util/Map$Entry")),
+            "java/util"
+        );
+        assert_eq!(package_name(JavaStr::from_str("Foo")), "");
+        assert_eq!(
+            simple_name(JavaStr::from_str("java/util/Map$Entry")).as_ref(),
+            "Entry"
+        );
+        assert_eq!(
+            simple_name(JavaStr::from_str("Foo$1Local")).as_ref(),
+            "Local"
+        );
+        assert_eq!(simple_name(JavaStr::from_str("Foo$1")).as_ref(), "");
+        assert_eq!(
+            simple_name(JavaStr::from_str("[[Ljava/lang/String;")).as_ref(),
+            "String[][]"
+        );
+        assert_eq!(simple_name(JavaStr::from_str("[I")).as_ref(), "int[]");
+
+        assert!(internal_to_binary_name(JavaStr::from_str("java.lang.String")).is_err());
+        assert!(internal_to_binary_name(JavaStr::from_str("java//String")).is_err());
+        assert!(binary_to_internal_name(JavaStr::from_str("java/lang/String")).is_err());
+        assert!(binary_to_internal_name(JavaStr::from_str("[Ljava.lang.String")).is_err());
+        assert!(internal_name_to_desc(JavaStr::from_str("Ljava/lang/String;")).is_err());
+        assert!(desc_to_internal_name(JavaStr::from_str("I")).is_err());
+    }
+}
@@ -0,0 +1,498 @@
+use crate::tree::ClassNode;
+use crate::{
+    BufferedClassEvents, ClassEvent, ClassEventSource, ClassFileError, ClassFileResult,
+    ClassHierarchy, Remapper,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum NestValidationError<'class> {
+    #[error("class {0} has both a nest host and nest members")]
+    HostAndMembers(Cow<'class, JavaStr>),
+    #[error("nest host {host} does not list its member {member}")]
+    HostDoesNotListMember {
+        host: Cow<'class, JavaStr>,
+        member: Cow<'class, JavaStr>,
+    },
+    #[error("nest host {host} lists {member}, but its nest host is {actual_host:?}")]
+    MemberHasDifferentHost {
+        host: Cow<'class, JavaStr>,
+        member: Cow<'class, JavaStr>,
+        actual_host: Option<Cow<'class, JavaStr>>,
+    },
+    #[error("nest member {member} is not in the same package as its nest host {host}")]
+    MemberInDifferentPackage {
+        host: Cow<'class, JavaStr>,
+        member: Cow<'class, JavaStr>,
+    },
+    #[error("permitted subclass {subclass} of {sealed} does not directly extend it")]
+    PermittedSubclassNotDirectSubtype {
+        sealed: Cow<'class, JavaStr>,
+        subclass: Cow<'class, JavaStr>,
+    },
+    #[error("{subclass} extends sealed class {sealed}, which does not permit it")]
+    SubclassNotPermitted {
+        sealed: Cow<'class, JavaStr>,
+        subclass: Cow<'class, JavaStr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NestMembership<'class> {
+    pub super_name: Option<Cow<'class, JavaStr>>,
+    pub interfaces: Vec<Cow<'class, JavaStr>>,
+    pub nest_host: Option<Cow<'class, JavaStr>>,
+    pub nest_members: Vec<Cow<'class, JavaStr>>,
+    pub permitted_subclasses: Vec<Cow<'class, JavaStr>>,
+}
+
+impl NestMembership<'_> {
+    fn is_sealed(&self) -> bool {
+        !self.permitted_subclasses.is_empty()
+    }
+
+    fn direct_supertypes(&self) -> impl Iterator<Item = &JavaStr> {
+        self.super_name
+            .as_deref()
+            .into_iter()
+            .chain(self.interfaces.iter().map(|itf| &**itf))
+    }
+}
+
+/// Nest membership and sealed class information for a set of classes, used to check that
+/// `NestHost`, `NestMembers` and `PermittedSubclasses` attributes agree with each other, and to
+/// recompute them after classes have been added, removed or renamed. Classes which aren't in the
+/// index are assumed to be correct.
+#[derive(Debug, Clone, Default)]
+pub struct NestIndex<'class> {
+    classes: HashMap<Cow<'class, JavaStr>, NestMembership<'class>>,
+}
+
+impl<'class> NestIndex<'class> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_class<S>(&mut self, source: S) -> ClassFileResult<()>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut name = None;
+        let mut membership = NestMembership::default();
+
+        for event in source.events()? {
+            match event? {
+                ClassEvent::Class(class) => {
+                    name = Some(class.name);
+                    membership.super_name = class.super_name;
+                    membership.interfaces = class.interfaces;
+                }
+                ClassEvent::NestHost(nest_host) => membership.nest_host = Some(nest_host),
+                ClassEvent::NestMembers(members) => {
+                    for member in members {
+                        membership.nest_members.push(member?);
+                    }
+                }
+                ClassEvent::PermittedSubclasses(subclasses) => {
+                    for subclass in subclasses {
+                        membership.permitted_subclasses.push(subclass?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(name) = name {
+            self.insert(name, membership);
+        }
+        Ok(())
+    }
+
+    pub fn insert(
+        &mut self,
+        name: impl Into<Cow<'class, JavaStr>>,
+        membership: NestMembership<'class>,
+    ) -> Option<NestMembership<'class>> {
+        self.classes.insert(name.into(), membership)
+    }
+
+    pub fn remove(&mut self, name: &JavaStr) -> Option<NestMembership<'class>> {
+        self.classes.remove(name)
+    }
+
+    pub fn get(&self, name: &JavaStr) -> Option<&NestMembership<'class>> {
+        self.classes.get(name)
+    }
+
+    pub fn validate(&self) -> Vec<NestValidationError<'class>> {
+        self.validate_impl(None)
+            .expect("validating without a class hierarchy can't fail")
+    }
+
+    /// Like [`validate`](NestIndex::validate), but also checks permitted subclasses which aren't in
+    /// the index against the class hierarchy. Classes which aren't in the hierarchy either are
+    /// assumed to be correct.
+    pub fn validate_with_hierarchy(
+        &self,
+        class_hierarchy: &dyn ClassHierarchy,
+    ) -> ClassFileResult<Vec<NestValidationError<'class>>> {
+        self.validate_impl(Some(class_hierarchy))
+    }
+
+    fn validate_impl(
+        &self,
+        class_hierarchy: Option<&dyn ClassHierarchy>,
+    ) -> ClassFileResult<Vec<NestValidationError<'class>>> {
+        let mut errors = Vec::new();
+
+        for (name, membership) in self.sorted_classes() {
+            if membership.nest_host.is_some() && !membership.nest_members.is_empty() {
+                errors.push(NestValidationError::HostAndMembers(name.clone()));
+            }
+
+            if let Some(host) = &membership.nest_host {
+                if !same_package(name, host) {
+                    errors.push(NestValidationError::MemberInDifferentPackage {
+                        host: host.clone(),
+                        member: name.clone(),
+                    });
+                }
+                if let Some(host_membership) = self.classes.get(host) {
+                    if !host_membership.nest_members.contains(name) {
+                        errors.push(NestValidationError::HostDoesNotListMember {
+                            host: host.clone(),
+                            member: name.clone(),
+                        });
+                    }
+                }
+            }
+
+            for member in &membership.nest_members {
+                if let Some(member_membership) = self.classes.get(member) {
+                    if member_membership.nest_host.as_ref() != Some(name) {
+                        errors.push(NestValidationError::MemberHasDifferentHost {
+                            host: name.clone(),
+                            member: member.clone(),
+                            actual_host: member_membership.nest_host.clone(),
+                        });
+                    }
+                }
+            }
+
+            for subclass in &membership.permitted_subclasses {
+                let is_direct_subtype = match (self.classes.get(subclass), class_hierarchy) {
+                    (Some(subclass_membership), _) => Some(
+                        subclass_membership
+                            .direct_supertypes()
+                            .any(|supertype| supertype == &**name),
+                    ),
+                    (None, Some(class_hierarchy)) => {
+                        is_direct_subtype_in_hierarchy(class_hierarchy, subclass, name)?
+                    }
+                    (None, None) => None,
+                };
+                if is_direct_subtype == Some(false) {
+                    errors.push(NestValidationError::PermittedSubclassNotDirectSubtype {
+                        sealed: name.clone(),
+                        subclass: subclass.clone(),
+                    });
+                }
+            }
+
+            for supertype in membership.direct_supertypes() {
+                if let Some((sealed, sealed_membership)) = self.classes.get_key_value(supertype) {
+                    if sealed_membership.is_sealed()
+                        && !sealed_membership.permitted_subclasses.contains(name)
+                    {
+                        errors.push(NestValidationError::SubclassNotPermitted {
+                            sealed: sealed.clone(),
+                            subclass: name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Renames the classes in the index, for example to match classes renamed by
+    /// [`remap_class`](crate::remap_class).
+    pub fn remap<R: Remapper + ?Sized>(&mut self, remapper: &R) {
+        let remap = |name: &mut Cow<'class, JavaStr>| {
+            if let Some(new_name) = remapper.map_class(name) {
+                *name = Cow::Owned(new_name);
+            }
+        };
+        self.classes = mem::take(&mut self.classes)
+            .into_iter()
+            .map(|(mut name, mut membership)| {
+                remap(&mut name);
+                membership
+                    .super_name
+                    .iter_mut()
+                    .chain(&mut membership.interfaces)
+                    .chain(&mut membership.nest_host)
+                    .chain(&mut membership.nest_members)
+                    .chain(&mut membership.permitted_subclasses)
+                    .for_each(remap);
+                (name, membership)
+            })
+            .collect();
+    }
+
+    /// Rewrites the `NestHost`, `NestMembers` and `PermittedSubclasses` of a class to agree with
+    /// the index. Nest members and permitted subclasses which aren't in the index are kept.
+    pub fn fix_class<S>(&self, source: S) -> ClassFileResult<BufferedClassEvents<'class>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut node = ClassNode::from_events(source)?;
+        self.fix_class_node(&mut node);
+        Ok(node.to_events())
+    }
+
+    /// Like [`fix_class`](NestIndex::fix_class), but for a [`ClassNode`].
+    pub fn fix_class_node(&self, node: &mut ClassNode<'class>) {
+        let Some(membership) = self.classes.get(&node.name) else {
+            return;
+        };
+        node.nest_host = membership.nest_host.clone();
+
+        node.nest_members
+            .retain(|member| !self.classes.contains_key(member));
+        for (name, membership) in self.sorted_classes() {
+            if membership.nest_host.as_ref() == Some(&node.name) {
+                node.nest_members.push(name.clone());
+            }
+        }
+
+        if membership.is_sealed() {
+            node.permitted_subclasses
+                .retain(|subclass| !self.classes.contains_key(subclass));
+            for (name, membership) in self.sorted_classes() {
+                if membership
+                    .direct_supertypes()
+                    .any(|supertype| supertype == &*node.name)
+                {
+                    node.permitted_subclasses.push(name.clone());
+                }
+            }
+        }
+    }
+
+    /// Recomputes the `NestMembers` of each nest host from the `NestHost` attributes of the
+    /// classes in the index.
+    pub fn compute_nest_members(
+        &self,
+    ) -> BTreeMap<Cow<'class, JavaStr>, Vec<Cow<'class, JavaStr>>> {
+        let mut result: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (name, membership) in self.sorted_classes() {
+            if let Some(host) = &membership.nest_host {
+                result.entry(host.clone()).or_default().push(name.clone());
+            }
+        }
+        result
+    }
+
+    /// Recomputes the `PermittedSubclasses` of each sealed class in the index from the direct
+    /// subtypes of that class in the index.
+    pub fn compute_permitted_subclasses(
+        &self,
+    ) -> BTreeMap<Cow<'class, JavaStr>, Vec<Cow<'class, JavaStr>>> {
+        let mut result: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (name, membership) in self.sorted_classes() {
+            if membership.is_sealed() {
+                result.entry(name.clone()).or_default();
+            }
+        }
+        for (name, membership) in self.sorted_classes() {
+            for supertype in membership.direct_supertypes() {
+                if let Some(subclasses) = result.get_mut(supertype) {
+                    subclasses.push(name.clone());
+                }
+            }
+        }
+        result
+    }
+
+    fn sorted_classes(&self) -> Vec<(&Cow<'class, JavaStr>, &NestMembership<'class>)> {
+        let mut classes: Vec<_> = self.classes.iter().collect();
+        classes.sort_by_key(|(name, _)| *name);
+        classes
+    }
+}
+
+/// Whether `subclass` directly extends or implements `sealed`, or `None` if `subclass` isn't in the
+/// class hierarchy.
+fn is_direct_subtype_in_hierarchy(
+    class_hierarchy: &dyn ClassHierarchy,
+    subclass: &JavaStr,
+    sealed: &JavaStr,
+) -> ClassFileResult<Option<bool>> {
+    let super_class = match class_hierarchy.super_class(subclass) {
+        Ok(super_class) => super_class,
+        Err(ClassFileError::UnknownClass(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    Ok(Some(
+        super_class.as_deref() == Some(sealed)
+            || class_hierarchy
+                .interfaces(subclass)?
+                .iter()
+                .any(|interface| interface == sealed),
+    ))
+}
+
+fn same_package(a: &JavaStr, b: &JavaStr) -> bool {
+    fn package(name: &JavaStr) -> &JavaStr {
+        name.rsplit_once('/')
+            .map_or(JavaStr::from_str(""), |(package, _)| package)
+    }
+    package(a) == package(b)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ClassNode;
+    use crate::{
+        remap_class, ClassReader, ClassReaderFlags, NestIndex, NestMembership, NestValidationError,
+        SimpleClassHierarchy, SimpleRemapper,
+    };
+    use java_string::{JavaStr, JavaString};
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    fn str(s: &str) -> Cow<'_, JavaStr> {
+        JavaStr::from_str(s).into()
+    }
+
+    #[test]
+    fn test_nest_index() {
+        const HOST: &[u8] = include_class!("TestInnerClass");
+        const MEMBER: &[u8] = include_class!("TestInnerClass$Inner");
+        const SEALED: &[u8] = include_class!("TestSealedClass");
+        const FOO: &[u8] = include_class!("TestSealedClass$Foo");
+        const BAR: &[u8] = include_class!("TestSealedClass$Bar");
+
+        let mut index = NestIndex::new();
+        for bytecode in [HOST, MEMBER, SEALED, FOO, BAR] {
+            let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+            index.add_class(&reader).unwrap();
+        }
+
+        assert_eq!(Vec::<NestValidationError>::new(), index.validate());
+        assert_eq!(
+            Some(&vec![str("TestInnerClass$Inner")]),
+            index
+                .compute_nest_members()
+                .get(JavaStr::from_str("TestInnerClass"))
+        );
+        assert_eq!(
+            Some(&vec![
+                str("TestSealedClass$Bar"),
+                str("TestSealedClass$Foo")
+            ]),
+            index
+                .compute_permitted_subclasses()
+                .get(JavaStr::from_str("TestSealedClass"))
+        );
+
+        let mut member = index
+            .remove(JavaStr::from_str("TestInnerClass$Inner"))
+            .unwrap();
+        member.nest_host = Some(str("pkg/Other"));
+        index.insert(str("TestInnerClass$Inner"), member);
+        assert_eq!(
+            vec![
+                NestValidationError::MemberHasDifferentHost {
+                    host: str("TestInnerClass"),
+                    member: str("TestInnerClass$Inner"),
+                    actual_host: Some(str("pkg/Other")),
+                },
+                NestValidationError::MemberInDifferentPackage {
+                    host: str("pkg/Other"),
+                    member: str("TestInnerClass$Inner"),
+                },
+            ],
+            index.validate()
+        );
+    }
+
+    #[test]
+    fn test_fix_renamed_classes() {
+        const HOST: &[u8] = include_class!("TestInnerClass");
+        const MEMBER: &[u8] = include_class!("TestInnerClass$Inner");
+        const SEALED: &[u8] = include_class!("TestSealedClass");
+        const FOO: &[u8] = include_class!("TestSealedClass$Foo");
+        const BAR: &[u8] = include_class!("TestSealedClass$Bar");
+
+        let mut index = NestIndex::new();
+        for bytecode in [HOST, MEMBER, SEALED, FOO, BAR] {
+            let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+            index.add_class(&reader).unwrap();
+        }
+        let mut remapper = SimpleRemapper::new();
+        remapper.add_class("TestInnerClass$Inner", "TestInnerClass$Renamed");
+        remapper.add_class("TestSealedClass$Foo", "TestSealedClass$Baz");
+        index.remap(&remapper);
+        assert_eq!(Vec::<NestValidationError>::new(), index.validate());
+
+        // a class joins the nest, and another stops extending the sealed class
+        index.insert(
+            str("TestInnerClass$New"),
+            NestMembership {
+                nest_host: Some(str("TestInnerClass")),
+                ..NestMembership::default()
+            },
+        );
+        let mut bar = index
+            .remove(JavaStr::from_str("TestSealedClass$Bar"))
+            .unwrap();
+        bar.super_name = Some(str("java/lang/Object"));
+        index.insert(str("TestSealedClass$Bar"), bar);
+
+        let fix = |bytecode| {
+            let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+            let events = remap_class(&reader, &remapper).unwrap();
+            ClassNode::from_events(index.fix_class(events).unwrap()).unwrap()
+        };
+        assert_eq!(
+            vec![str("TestInnerClass$New"), str("TestInnerClass$Renamed")],
+            fix(HOST).nest_members
+        );
+        assert_eq!(Some(str("TestInnerClass")), fix(MEMBER).nest_host);
+        assert_eq!(
+            vec![str("TestSealedClass$Baz")],
+            fix(SEALED).permitted_subclasses
+        );
+    }
+
+    #[test]
+    fn test_validate_with_hierarchy() {
+        let reader =
+            ClassReader::new(include_class!("TestSealedClass"), ClassReaderFlags::None).unwrap();
+        let mut index = NestIndex::new();
+        index.add_class(&reader).unwrap();
+
+        let mut class_hierarchy = SimpleClassHierarchy::new();
+        class_hierarchy.insert(
+            "TestSealedClass$Foo",
+            Some(JavaString::from("java/lang/Object")),
+            false,
+        );
+        assert_eq!(Vec::<NestValidationError>::new(), index.validate());
+        assert_eq!(
+            vec![NestValidationError::PermittedSubclassNotDirectSubtype {
+                sealed: str("TestSealedClass"),
+                subclass: str("TestSealedClass$Foo"),
+            }],
+            index.validate_with_hierarchy(&class_hierarchy).unwrap()
+        );
+    }
+}
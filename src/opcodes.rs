@@ -4,6 +4,7 @@ use java_string::JavaStr;
 use std::borrow::Cow;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display, TryFrom)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 #[non_exhaustive]
 #[try_from(repr)]
@@ -195,6 +196,105 @@ pub enum Opcode {
     IfNonNull = 199,
 }
 
+impl Opcode {
+    /// The change in operand stack size, in slots, caused by this opcode when it has no operands.
+    /// Opcodes whose stack effect depends on their operands return 0.
+    pub(crate) fn stack_delta(self) -> i32 {
+        match self {
+            Opcode::AConstNull
+            | Opcode::IConstM1
+            | Opcode::IConst0
+            | Opcode::IConst1
+            | Opcode::IConst2
+            | Opcode::IConst3
+            | Opcode::IConst4
+            | Opcode::IConst5
+            | Opcode::FConst0
+            | Opcode::FConst1
+            | Opcode::FConst2
+            | Opcode::Dup
+            | Opcode::DupX1
+            | Opcode::DupX2
+            | Opcode::I2l
+            | Opcode::I2d
+            | Opcode::F2l
+            | Opcode::F2d => 1,
+            Opcode::LConst0
+            | Opcode::LConst1
+            | Opcode::DConst0
+            | Opcode::DConst1
+            | Opcode::Dup2
+            | Opcode::Dup2X1
+            | Opcode::Dup2X2 => 2,
+            Opcode::IALoad
+            | Opcode::FALoad
+            | Opcode::AALoad
+            | Opcode::BALoad
+            | Opcode::CALoad
+            | Opcode::SALoad
+            | Opcode::Pop
+            | Opcode::IAdd
+            | Opcode::FAdd
+            | Opcode::ISub
+            | Opcode::FSub
+            | Opcode::IMul
+            | Opcode::FMul
+            | Opcode::IDiv
+            | Opcode::FDiv
+            | Opcode::IRem
+            | Opcode::FRem
+            | Opcode::IShl
+            | Opcode::LShl
+            | Opcode::IShr
+            | Opcode::LShr
+            | Opcode::IUShr
+            | Opcode::LUShr
+            | Opcode::IAnd
+            | Opcode::IOr
+            | Opcode::IXor
+            | Opcode::L2i
+            | Opcode::L2f
+            | Opcode::D2i
+            | Opcode::D2f
+            | Opcode::FCmpL
+            | Opcode::FCmpG
+            | Opcode::IReturn
+            | Opcode::FReturn
+            | Opcode::AReturn
+            | Opcode::AThrow
+            | Opcode::MonitorEnter
+            | Opcode::MonitorExit => -1,
+            Opcode::Pop2
+            | Opcode::LAdd
+            | Opcode::DAdd
+            | Opcode::LSub
+            | Opcode::DSub
+            | Opcode::LMul
+            | Opcode::DMul
+            | Opcode::LDiv
+            | Opcode::DDiv
+            | Opcode::LRem
+            | Opcode::DRem
+            | Opcode::LAnd
+            | Opcode::LOr
+            | Opcode::LXor
+            | Opcode::LReturn
+            | Opcode::DReturn => -2,
+            Opcode::IAStore
+            | Opcode::FAStore
+            | Opcode::AAStore
+            | Opcode::BAStore
+            | Opcode::CAStore
+            | Opcode::SAStore
+            | Opcode::LCmp
+            | Opcode::DCmpL
+            | Opcode::DCmpG => -3,
+            Opcode::LAStore | Opcode::DAStore => -4,
+            _ => 0,
+        }
+    }
+}
+
 pub(crate) struct InternalOpcodes;
 
 impl InternalOpcodes {
@@ -246,6 +346,7 @@ impl InternalOpcodes {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display, TryFrom)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 #[display(rename_all = "lowercase")]
 #[try_from(repr)]
@@ -261,6 +362,7 @@ pub enum NewArrayType {
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LdcConstant<'class> {
     Integer(i32),
     Float(f32),
@@ -0,0 +1,176 @@
+use crate::{
+    remap_class, rewrite_string_constants, BufferedClassEvents, ClassEventSource, ClassFileResult,
+    Remapper,
+};
+use java_string::{JavaStr, JavaString};
+
+/// Moves classes from one package prefix to another, as done when shading a library into a jar.
+/// See [`relocate_packages`].
+#[derive(Debug, Clone, Default)]
+pub struct PackageRelocator {
+    relocations: Vec<(JavaString, JavaString)>,
+    relocate_strings: bool,
+}
+
+impl PackageRelocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Relocates the classes whose internal names start with `from` to start with `to` instead,
+    /// such as `com/google/` to `shaded/com/google/`. Prefixes should end with a `/` so that only
+    /// whole packages match. The longest matching prefix wins.
+    pub fn add_relocation(&mut self, from: impl Into<JavaString>, to: impl Into<JavaString>) {
+        self.relocations.push((from.into(), to.into()));
+    }
+
+    /// Also relocates string constants which look like class names, in either internal or binary
+    /// form, such as `com/google/Foo` or `com.google.Foo`. Off by default, as it may rewrite
+    /// strings which only happen to look like class names.
+    pub fn set_relocate_strings(&mut self, relocate_strings: bool) {
+        self.relocate_strings = relocate_strings;
+    }
+
+    /// Relocates an internal class name, or returns `None` if it isn't in a relocated package.
+    pub fn relocate(&self, name: &JavaStr) -> Option<JavaString> {
+        let (from, to) = self
+            .relocations
+            .iter()
+            .filter(|(from, _)| name.starts_with(from.as_java_str()))
+            .max_by_key(|(from, _)| from.len())?;
+        let mut result = to.clone();
+        result.push_java_str(&name[from.len()..]);
+        Some(result)
+    }
+
+    /// Relocates a string constant if it looks like a class name in a relocated package.
+    fn relocate_string(&self, value: &JavaStr) -> Option<JavaString> {
+        if value.is_empty()
+            || value
+                .chars()
+                .any(|ch| ch.as_char().is_none_or(char::is_whitespace))
+        {
+            return None;
+        }
+        if let Some(relocated) = self.relocate(value) {
+            return Some(relocated);
+        }
+        if !value.contains('.') || value.contains('/') {
+            return None;
+        }
+        let relocated = self.relocate(&value.replace('.', "/"))?;
+        Some(relocated.replace('/', "."))
+    }
+}
+
+impl Remapper for PackageRelocator {
+    fn map_class(&self, name: &JavaStr) -> Option<JavaString> {
+        self.relocate(name)
+    }
+
+    fn map_package(&self, name: &JavaStr) -> Option<JavaString> {
+        let mut package = name.to_owned();
+        package.push('/');
+        let mut relocated = self.relocate(&package)?;
+        relocated.pop();
+        Some(relocated)
+    }
+}
+
+/// Reads a class, relocating every reference to a class in a relocated package, including the
+/// packages of modules. The returned events can be passed to a
+/// [`ClassWriter`](crate::ClassWriter).
+pub fn relocate_packages<'class, S>(
+    source: S,
+    relocator: &PackageRelocator,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    let events = remap_class(source, relocator)?;
+    if !relocator.relocate_strings {
+        return Ok(events);
+    }
+    rewrite_string_constants(events, |_, _, value| relocator.relocate_string(value))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{
+        relocate_packages, ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags,
+        LdcConstant, PackageRelocator,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_relocate_module() {
+        let reader =
+            ClassReader::new(include_class!("module-info"), ClassReaderFlags::None).unwrap();
+        let mut relocator = PackageRelocator::new();
+        relocator.add_relocation("pkg/", "shaded/pkg/");
+        let events = relocate_packages(&reader, &relocator).unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(events)
+            .unwrap();
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let module = ClassNode::from_events(&reader).unwrap().module.unwrap();
+        let exports: Vec<_> = module
+            .exports
+            .iter()
+            .map(|export| export.package.as_ref())
+            .collect();
+        assert_eq!(
+            vec![JavaStr::from_str("shaded/pkg"), JavaStr::from_str("pkg2")],
+            exports
+        );
+        assert_eq!(
+            "shaded/pkg/ClassInPackage",
+            module.provides[0].providers[0].as_ref()
+        );
+    }
+
+    #[test]
+    fn test_relocate_strings() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let mut relocator = PackageRelocator::new();
+        relocator.add_relocation("java/", "shaded/java/");
+        relocator.add_relocation("java/lang/", "java/lang/");
+        let class =
+            ClassNode::from_events(relocate_packages(&reader, &relocator).unwrap()).unwrap();
+        let main = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "main")
+            .unwrap();
+        // the longest prefix keeps java/lang in place
+        assert!(main.instructions.iter().any(|insn| matches!(
+            insn,
+            InsnNode::FieldInsn { owner, desc, .. }
+                if owner.as_ref() == "java/lang/System" && desc.as_ref() == "Lshaded/java/io/PrintStream;"
+        )));
+        assert!(main.instructions.iter().any(
+            |insn| matches!(insn, InsnNode::LdcInsn(LdcConstant::String(value)) if value.as_ref() == "Hello, World!")
+        ));
+
+        relocator.set_relocate_strings(true);
+        for (value, expected) in [
+            ("java/io/File", Some("shaded/java/io/File")),
+            ("java.util.List", Some("shaded.java.util.List")),
+            ("java.lang.String", Some("java.lang.String")),
+            ("java.io is a package", None),
+            ("javax.swing.JFrame", None),
+        ] {
+            assert_eq!(
+                expected.map(JavaStr::from_str),
+                relocator
+                    .relocate_string(JavaStr::from_str(value))
+                    .as_deref(),
+                "{value}"
+            );
+        }
+    }
+}
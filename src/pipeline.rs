@@ -0,0 +1,240 @@
+use crate::tree::{AnnotationNode, BufferedFieldEvent, BufferedMethodEvent};
+use crate::{
+    buffer_class_events, AnnotationEvent, BufferedClassEvents, ClassEvent, ClassEventSource,
+    ClassFileResult, ClassWriter, EventBuffer, FieldEvent, MethodEvent, RecordComponentEvent,
+};
+
+/// Combinators for building transformation pipelines out of class event sources, such as
+/// `reader.pipe(adapter)?.filter_methods(|method| ...)?.write_with(&writer)`.
+///
+/// Each combinator buffers the events of the source, so the result of one combinator can be
+/// passed to the next without being copied again. This trait is implemented for all class event
+/// sources.
+pub trait ClassEventPipeline<'class>: ClassEventSource<'class> + Sized {
+    /// Passes this source to an adapter, such as
+    /// [`strip_debug_info`](crate::strip_debug_info) or any other function taking a source.
+    fn pipe<T, F>(self, adapter: F) -> ClassFileResult<T>
+    where
+        F: FnOnce(Self) -> ClassFileResult<T>,
+    {
+        adapter(self)
+    }
+
+    /// Removes the fields for which `predicate` returns `false`.
+    fn filter_fields<F>(self, mut predicate: F) -> ClassFileResult<BufferedClassEvents<'class>>
+    where
+        F: FnMut(&BufferedFieldEvent<'class>) -> bool,
+    {
+        let mut events = buffer_class_events(self)?;
+        for event in &mut events.0 {
+            if let ClassEvent::Fields(fields) = event {
+                fields.0.retain(&mut predicate);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Removes the methods for which `predicate` returns `false`.
+    fn filter_methods<F>(self, mut predicate: F) -> ClassFileResult<BufferedClassEvents<'class>>
+    where
+        F: FnMut(&BufferedMethodEvent<'class>) -> bool,
+    {
+        let mut events = buffer_class_events(self)?;
+        for event in &mut events.0 {
+            if let ClassEvent::Methods(methods) = event {
+                methods.0.retain(&mut predicate);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Replaces each field with the result of `mapper`.
+    fn map_fields<F>(self, mut mapper: F) -> ClassFileResult<BufferedClassEvents<'class>>
+    where
+        F: FnMut(BufferedFieldEvent<'class>) -> ClassFileResult<BufferedFieldEvent<'class>>,
+    {
+        let mut events = buffer_class_events(self)?;
+        for event in &mut events.0 {
+            if let ClassEvent::Fields(fields) = event {
+                fields.0 = std::mem::take(&mut fields.0)
+                    .into_iter()
+                    .map(&mut mapper)
+                    .collect::<ClassFileResult<_>>()?;
+            }
+        }
+        Ok(events)
+    }
+
+    /// Replaces each method with the result of `mapper`.
+    fn map_methods<F>(self, mut mapper: F) -> ClassFileResult<BufferedClassEvents<'class>>
+    where
+        F: FnMut(BufferedMethodEvent<'class>) -> ClassFileResult<BufferedMethodEvent<'class>>,
+    {
+        let mut events = buffer_class_events(self)?;
+        for event in &mut events.0 {
+            if let ClassEvent::Methods(methods) = event {
+                methods.0 = std::mem::take(&mut methods.0)
+                    .into_iter()
+                    .map(&mut mapper)
+                    .collect::<ClassFileResult<_>>()?;
+            }
+        }
+        Ok(events)
+    }
+
+    /// Replaces each annotation on the class, its fields, methods, method parameters and record
+    /// components with the result of `mapper`, which is also passed whether the annotation is
+    /// visible at runtime. Annotations for which `mapper` returns `None` are removed. Type
+    /// annotations are left as they are.
+    fn map_annotations<F>(self, mut mapper: F) -> ClassFileResult<BufferedClassEvents<'class>>
+    where
+        F: FnMut(AnnotationNode<'class>, bool) -> Option<AnnotationNode<'class>>,
+    {
+        let mut events = buffer_class_events(self)?;
+        for event in &mut events.0 {
+            match event {
+                ClassEvent::Annotations(annotations) => {
+                    map_annotation_events(annotations, &mut mapper)
+                }
+                ClassEvent::Fields(fields) => {
+                    for field in &mut fields.0 {
+                        for event in &mut field.events.0 {
+                            if let FieldEvent::Annotations(annotations) = event {
+                                map_annotation_events(annotations, &mut mapper);
+                            }
+                        }
+                    }
+                }
+                ClassEvent::Methods(methods) => {
+                    for method in &mut methods.0 {
+                        for event in &mut method.events.0 {
+                            match event {
+                                MethodEvent::Annotations(annotations) => {
+                                    map_annotation_events(annotations, &mut mapper)
+                                }
+                                MethodEvent::ParameterAnnotations(annotations) => {
+                                    annotations.0 = std::mem::take(&mut annotations.0)
+                                        .into_iter()
+                                        .filter_map(|mut event| {
+                                            event.annotation =
+                                                mapper(event.annotation, event.visible)?;
+                                            Some(event)
+                                        })
+                                        .collect();
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                ClassEvent::Record(components) => {
+                    for component in &mut components.0 {
+                        for event in &mut component.events.0 {
+                            if let RecordComponentEvent::Annotations(annotations) = event {
+                                map_annotation_events(annotations, &mut mapper);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(events)
+    }
+
+    /// Writes the class with the given writer, see [`ClassWriter::write`].
+    fn write_with(self, writer: &ClassWriter) -> ClassFileResult<Vec<u8>> {
+        writer.write(self)
+    }
+}
+
+impl<'class, S> ClassEventPipeline<'class> for S where S: ClassEventSource<'class> {}
+
+fn map_annotation_events<'class, F>(
+    annotations: &mut EventBuffer<AnnotationEvent<AnnotationNode<'class>>>,
+    mapper: &mut F,
+) where
+    F: FnMut(AnnotationNode<'class>, bool) -> Option<AnnotationNode<'class>>,
+{
+    annotations.0 = std::mem::take(&mut annotations.0)
+        .into_iter()
+        .filter_map(|mut event| {
+            event.annotation = mapper(event.annotation, event.visible)?;
+            Some(event)
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ClassNode;
+    use crate::{
+        buffer_class_events, strip_debug_info, BufferedClassEvents, ClassEvent, ClassEventPipeline,
+        ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags, StripDebugFlags,
+    };
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_pipeline() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let written = reader
+            .pipe(|events| strip_debug_info(events, StripDebugFlags::All))
+            .unwrap()
+            .filter_methods(|method| method.name.as_ref() != "tryCatch")
+            .unwrap()
+            .write_with(&ClassWriter::new(ClassWriterFlags::None))
+            .unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        assert!(class.source.is_none());
+        assert!(!class.methods.is_empty());
+        assert!(class
+            .methods
+            .iter()
+            .all(|method| method.name.as_ref() != "tryCatch"));
+    }
+
+    #[test]
+    fn test_combinators_reuse_buffered_events() {
+        fn first_method_events(events: &BufferedClassEvents) -> *const () {
+            events
+                .0
+                .iter()
+                .find_map(|event| match event {
+                    ClassEvent::Methods(methods) => Some(methods.0[0].events.0.as_ptr().cast()),
+                    _ => None,
+                })
+                .unwrap()
+        }
+
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let events = buffer_class_events(&reader).unwrap();
+        let before = first_method_events(&events);
+        let events = events.filter_fields(|_| true).unwrap();
+        assert_eq!(before, first_method_events(&events));
+    }
+
+    #[test]
+    fn test_map_annotations() {
+        let reader =
+            ClassReader::new(include_class!("TestAnnotations"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        assert!(!class.annotations.is_empty());
+        let class = ClassNode::from_events(
+            reader
+                .map_annotations(|annotation, visible| visible.then_some(annotation))
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(!class.annotations.is_empty());
+        assert!(class
+            .annotations
+            .iter()
+            .all(|annotation| annotation.visible));
+        assert!(class
+            .fields
+            .iter()
+            .flat_map(|field| &field.annotations)
+            .all(|annotation| annotation.visible));
+    }
+}
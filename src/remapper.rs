@@ -0,0 +1,873 @@
+use crate::tree::{AnnotationNode, AnnotationValue};
+use crate::{
+    buffer_class_events, BootstrapMethodArgument, BufferedClassEvents, BufferedEventProviders,
+    ClassEvent, ClassEventSource, ClassFileResult, ClassHierarchy, ConstantDynamic, FieldEvent,
+    Frame, FrameValue, Handle, HandleKind, LdcConstant, MethodEvent, ModuleEvent,
+    RecordComponentEvent, SignatureReader, SignatureVisitor, SignatureWriter, TypeArgumentKind,
+};
+use derive_more::Debug;
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Renames classes and members, see [`remap_class`]. Each method returns `None` to leave the name
+/// unchanged. Owners, names and descriptors passed to the methods are the ones before remapping.
+pub trait Remapper {
+    /// Maps an internal class name.
+    fn map_class(&self, name: &JavaStr) -> Option<JavaString>;
+
+    fn map_field(&self, _owner: &JavaStr, _name: &JavaStr, _desc: &JavaStr) -> Option<JavaString> {
+        None
+    }
+
+    /// Maps the name of a method. Not called for constructors and static initializers.
+    fn map_method(&self, _owner: &JavaStr, _name: &JavaStr, _desc: &JavaStr) -> Option<JavaString> {
+        None
+    }
+
+    /// Maps the name of the method implemented by an `invokedynamic` call site, such as the
+    /// interface method of a lambda.
+    fn map_invoke_dynamic_method(
+        &self,
+        _name: &JavaStr,
+        _desc: &JavaStr,
+        _bootstrap_method: &Handle,
+    ) -> Option<JavaString> {
+        None
+    }
+
+    /// Maps the name of a record component, the same way as its field by default.
+    fn map_record_component(
+        &self,
+        owner: &JavaStr,
+        name: &JavaStr,
+        desc: &JavaStr,
+    ) -> Option<JavaString> {
+        self.map_field(owner, name, desc)
+    }
+
+    /// Maps an internal package name, as used by modules.
+    fn map_package(&self, _name: &JavaStr) -> Option<JavaString> {
+        None
+    }
+
+    /// Maps an internal class name or an array descriptor.
+    fn map_type(&self, name: &JavaStr) -> Option<JavaString> {
+        if name.starts_with('[') {
+            self.map_desc(name)
+        } else {
+            self.map_class(name)
+        }
+    }
+
+    /// Maps the classes in a field or method descriptor.
+    fn map_desc(&self, desc: &JavaStr) -> Option<JavaString> {
+        remap_signature_classes(self, desc)
+    }
+
+    /// Maps the classes in a class, field or method signature.
+    fn map_signature(&self, signature: &JavaStr) -> Option<JavaString> {
+        remap_signature_classes(self, signature)
+    }
+}
+
+/// A [`Remapper`] backed by fixed mappings. Fields are identified by their owner and name, and
+/// methods by their owner, name and descriptor.
+///
+/// With a class hierarchy, members which aren't mapped in their owner are looked up in its
+/// superclasses, so that references to inherited members through a subclass are remapped too.
+/// Members inherited from interfaces aren't found this way.
+#[derive(Debug, Clone, Default)]
+pub struct SimpleRemapper {
+    classes: HashMap<JavaString, JavaString>,
+    packages: HashMap<JavaString, JavaString>,
+    /// The new names of fields by owner and name.
+    fields: HashMap<JavaString, HashMap<JavaString, JavaString>>,
+    /// The new names of methods by owner, and name followed by descriptor.
+    methods: HashMap<JavaString, HashMap<JavaString, JavaString>>,
+    #[debug(skip)]
+    class_hierarchy: Option<Arc<dyn ClassHierarchy>>,
+}
+
+impl SimpleRemapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_class(&mut self, name: impl Into<JavaString>, new_name: impl Into<JavaString>) {
+        self.classes.insert(name.into(), new_name.into());
+    }
+
+    pub fn add_package(&mut self, name: impl Into<JavaString>, new_name: impl Into<JavaString>) {
+        self.packages.insert(name.into(), new_name.into());
+    }
+
+    pub fn add_field(
+        &mut self,
+        owner: impl Into<JavaString>,
+        name: impl Into<JavaString>,
+        new_name: impl Into<JavaString>,
+    ) {
+        self.fields
+            .entry(owner.into())
+            .or_default()
+            .insert(name.into(), new_name.into());
+    }
+
+    pub fn add_method(
+        &mut self,
+        owner: impl Into<JavaString>,
+        name: &JavaStr,
+        desc: &JavaStr,
+        new_name: impl Into<JavaString>,
+    ) {
+        self.methods
+            .entry(owner.into())
+            .or_default()
+            .insert(method_key(name, desc), new_name.into());
+    }
+
+    /// Sets the class hierarchy used to find inherited members, in the namespace of the names
+    /// before remapping.
+    pub fn set_class_hierarchy<H>(&mut self, class_hierarchy: H)
+    where
+        H: ClassHierarchy + 'static,
+    {
+        self.class_hierarchy = Some(Arc::new(class_hierarchy));
+    }
+
+    /// The mappings in the opposite direction, without the class hierarchy.
+    pub fn reversed(&self) -> SimpleRemapper {
+        let mut result = SimpleRemapper::new();
+        for (name, new_name) in &self.classes {
+            result.add_class(new_name.clone(), name.clone());
+        }
+        for (name, new_name) in &self.packages {
+            result.add_package(new_name.clone(), name.clone());
+        }
+        for (owner, fields) in &self.fields {
+            let new_owner = self.map_class(owner).unwrap_or_else(|| owner.clone());
+            for (name, new_name) in fields {
+                result.add_field(new_owner.clone(), new_name.clone(), name.clone());
+            }
+        }
+        for (owner, methods) in &self.methods {
+            let new_owner = self.map_class(owner).unwrap_or_else(|| owner.clone());
+            for (key, new_name) in methods {
+                let desc_start = key.find('(').unwrap_or(key.len());
+                let (name, desc) = key.split_at(desc_start);
+                let new_desc = self.map_desc(desc).unwrap_or_else(|| desc.to_owned());
+                result.add_method(new_owner.clone(), new_name, &new_desc, name);
+            }
+        }
+        result
+    }
+
+    /// Finds a member in the given owner or, with a class hierarchy, in its superclasses.
+    fn find_member(
+        &self,
+        members: &HashMap<JavaString, HashMap<JavaString, JavaString>>,
+        owner: &JavaStr,
+        key: &JavaStr,
+    ) -> Option<JavaString> {
+        let mut owner = owner.to_owned();
+        loop {
+            if let Some(new_name) = members.get(&owner).and_then(|members| members.get(key)) {
+                return Some(new_name.clone());
+            }
+            owner = self.class_hierarchy.as_ref()?.super_class(&owner).ok()??;
+        }
+    }
+}
+
+impl Remapper for SimpleRemapper {
+    fn map_class(&self, name: &JavaStr) -> Option<JavaString> {
+        self.classes.get(name).cloned()
+    }
+
+    fn map_field(&self, owner: &JavaStr, name: &JavaStr, _desc: &JavaStr) -> Option<JavaString> {
+        self.find_member(&self.fields, owner, name)
+    }
+
+    fn map_method(&self, owner: &JavaStr, name: &JavaStr, desc: &JavaStr) -> Option<JavaString> {
+        self.find_member(&self.methods, owner, &method_key(name, desc))
+    }
+
+    fn map_package(&self, name: &JavaStr) -> Option<JavaString> {
+        self.packages.get(name).cloned()
+    }
+}
+
+fn method_key(name: &JavaStr, desc: &JavaStr) -> JavaString {
+    let mut key = JavaString::with_capacity(name.len() + desc.len());
+    key.push_java_str(name);
+    key.push_java_str(desc);
+    key
+}
+
+/// Reads a class, renaming every reference to a class or member with the given remapper: the
+/// class header, members, instructions, handles, frames, annotations, inner classes and modules.
+/// The returned events can be passed to a [`ClassWriter`](crate::ClassWriter).
+///
+/// Custom attributes are passed through unchanged.
+pub fn remap_class<'class, S, R>(
+    source: S,
+    remapper: &R,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+    R: Remapper + ?Sized,
+{
+    let mut events = buffer_class_events(source)?;
+    let remapper = EventRemapper(remapper);
+    let mut class_name = JavaString::new();
+    for event in &mut events.0 {
+        match event {
+            ClassEvent::Class(class) => {
+                class_name = class.name.clone().into_owned();
+                remapper.class(&mut class.name);
+                remapper.optional_signature(&mut class.signature);
+                if let Some(super_name) = &mut class.super_name {
+                    remapper.class(super_name);
+                }
+                for interface in &mut class.interfaces {
+                    remapper.class(interface);
+                }
+            }
+            ClassEvent::Module(module) => {
+                for event in &mut module.events.0 {
+                    match event {
+                        ModuleEvent::MainClass(name) => remapper.class(name),
+                        ModuleEvent::Packages(packages) => {
+                            for package in &mut packages.0 {
+                                remapper.package(package);
+                            }
+                        }
+                        ModuleEvent::Exports(relations) | ModuleEvent::Opens(relations) => {
+                            for relation in &mut relations.0 {
+                                remapper.package(&mut relation.package);
+                            }
+                        }
+                        ModuleEvent::Uses(services) => {
+                            for service in &mut services.0 {
+                                remapper.class(service);
+                            }
+                        }
+                        ModuleEvent::Provides(provides) => {
+                            for provide in &mut provides.0 {
+                                remapper.class(&mut provide.service);
+                                for provider in &mut provide.providers {
+                                    remapper.class(provider);
+                                }
+                            }
+                        }
+                        ModuleEvent::Target(_)
+                        | ModuleEvent::Resolution(_)
+                        | ModuleEvent::Hashes(_)
+                        | ModuleEvent::Requires(_) => {}
+                    }
+                }
+            }
+            ClassEvent::NestHost(name) => remapper.class(name),
+            ClassEvent::OuterClass(outer_class) => {
+                if let (Some(name), Some(desc)) =
+                    (&mut outer_class.method_name, &outer_class.method_desc)
+                {
+                    remapper.method_name(&outer_class.owner, name, desc);
+                }
+                remapper.class(&mut outer_class.owner);
+                if let Some(desc) = &mut outer_class.method_desc {
+                    remapper.desc(desc);
+                }
+            }
+            ClassEvent::Annotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    remapper.annotation(&mut annotation.annotation);
+                }
+            }
+            ClassEvent::TypeAnnotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    remapper.desc(&mut annotation.annotation.desc);
+                    remapper.annotation_values(&mut annotation.annotation.values);
+                }
+            }
+            ClassEvent::NestMembers(names) | ClassEvent::PermittedSubclasses(names) => {
+                for name in &mut names.0 {
+                    remapper.class(name);
+                }
+            }
+            #[cfg(feature = "preview")]
+            ClassEvent::LoadableDescriptors(descs) => {
+                for desc in descs {
+                    remapper.desc(desc);
+                }
+            }
+            ClassEvent::InnerClasses(inner_classes) => {
+                for inner_class in &mut inner_classes.0 {
+                    if let Some(new_name) = remapper.0.map_class(&inner_class.name) {
+                        if let Some(inner_name) = &mut inner_class.inner_name {
+                            remap_inner_name(inner_name, &new_name);
+                        }
+                        inner_class.name = Cow::Owned(new_name);
+                    }
+                    if let Some(outer_name) = &mut inner_class.outer_name {
+                        remapper.class(outer_name);
+                    }
+                }
+            }
+            ClassEvent::Record(components) => {
+                for component in &mut components.0 {
+                    if let Some(new_name) = remapper.0.map_record_component(
+                        &class_name,
+                        &component.name,
+                        &component.desc,
+                    ) {
+                        component.name = Cow::Owned(new_name);
+                    }
+                    remapper.desc(&mut component.desc);
+                    remapper.optional_signature(&mut component.signature);
+                    for event in &mut component.events.0 {
+                        match event {
+                            RecordComponentEvent::Annotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    remapper.annotation(&mut annotation.annotation);
+                                }
+                            }
+                            RecordComponentEvent::TypeAnnotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    remapper.desc(&mut annotation.annotation.desc);
+                                    remapper.annotation_values(&mut annotation.annotation.values);
+                                }
+                            }
+                            RecordComponentEvent::Attributes(_) => {}
+                        }
+                    }
+                }
+            }
+            ClassEvent::Fields(fields) => {
+                for field in &mut fields.0 {
+                    remapper.field_name(&class_name, &mut field.name, &field.desc);
+                    remapper.desc(&mut field.desc);
+                    remapper.optional_signature(&mut field.signature);
+                    for event in &mut field.events.0 {
+                        match event {
+                            FieldEvent::Annotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    remapper.annotation(&mut annotation.annotation);
+                                }
+                            }
+                            FieldEvent::TypeAnnotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    remapper.desc(&mut annotation.annotation.desc);
+                                    remapper.annotation_values(&mut annotation.annotation.values);
+                                }
+                            }
+                            FieldEvent::Deprecated | FieldEvent::Attributes(_) => {}
+                        }
+                    }
+                }
+            }
+            ClassEvent::Methods(methods) => {
+                for method in &mut methods.0 {
+                    remapper.method_name(&class_name, &mut method.name, &method.desc);
+                    remapper.desc(&mut method.desc);
+                    remapper.optional_signature(&mut method.signature);
+                    for exception in &mut method.exceptions {
+                        remapper.class(exception);
+                    }
+                    for event in &mut method.events.0 {
+                        remapper.method_event(event);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+struct EventRemapper<'a, R: ?Sized>(&'a R);
+
+impl<R: Remapper + ?Sized> EventRemapper<'_, R> {
+    fn method_event<'class>(&self, event: &mut MethodEvent<'class, BufferedEventProviders>) {
+        match event {
+            MethodEvent::AnnotationDefault(value) => self.annotation_value(value),
+            MethodEvent::Annotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    self.annotation(&mut annotation.annotation);
+                }
+            }
+            MethodEvent::TypeAnnotations(annotations)
+            | MethodEvent::InsnAnnotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    self.desc(&mut annotation.annotation.desc);
+                    self.annotation_values(&mut annotation.annotation.values);
+                }
+            }
+            MethodEvent::ParameterAnnotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    self.annotation(&mut annotation.annotation);
+                }
+            }
+            MethodEvent::Frame(frame) => match frame {
+                Frame::Full { locals, stack } | Frame::New { locals, stack } => {
+                    for value in locals.iter_mut().chain(stack) {
+                        self.frame_value(value);
+                    }
+                }
+                Frame::Append { locals } => {
+                    for value in locals {
+                        self.frame_value(value);
+                    }
+                }
+                Frame::Same1 { stack_value } => self.frame_value(stack_value),
+                Frame::Chop { .. } | Frame::Same => {}
+            },
+            MethodEvent::TypeInsn { ty, .. } => self.ty(ty),
+            MethodEvent::FieldInsn {
+                owner, name, desc, ..
+            } => {
+                self.field_name(owner, name, desc);
+                self.ty(owner);
+                self.desc(desc);
+            }
+            MethodEvent::MethodInsn {
+                owner, name, desc, ..
+            } => {
+                self.method_name(owner, name, desc);
+                self.ty(owner);
+                self.desc(desc);
+            }
+            MethodEvent::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            } => {
+                if let Some(new_name) =
+                    self.0
+                        .map_invoke_dynamic_method(name, desc, bootstrap_method_handle)
+                {
+                    *name = Cow::Owned(new_name);
+                }
+                self.desc(desc);
+                self.handle(bootstrap_method_handle);
+                self.bootstrap_method_arguments(bootstrap_method_arguments);
+            }
+            MethodEvent::LdcInsn(constant) => match constant {
+                LdcConstant::Class(name) => self.ty(name),
+                LdcConstant::MethodType(desc) => self.desc(desc),
+                LdcConstant::Handle(handle) => self.handle(handle),
+                LdcConstant::ConstantDynamic(constant) => self.constant_dynamic(constant),
+                _ => {}
+            },
+            MethodEvent::MultiANewArrayInsn { desc, .. } => self.desc(desc),
+            MethodEvent::LocalVariables(local_variables) => {
+                for local_variable in &mut local_variables.0 {
+                    self.desc(&mut local_variable.desc);
+                    self.optional_signature(&mut local_variable.signature);
+                }
+            }
+            MethodEvent::LocalVariableAnnotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    self.desc(&mut annotation.annotation.desc);
+                    self.annotation_values(&mut annotation.annotation.values);
+                }
+            }
+            MethodEvent::TryCatchBlocks(try_catch_blocks) => {
+                for try_catch_block in &mut try_catch_blocks.0 {
+                    if let Some(ty) = &mut try_catch_block.ty {
+                        self.class(ty);
+                    }
+                }
+            }
+            MethodEvent::TryCatchBlockAnnotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    self.desc(&mut annotation.annotation.desc);
+                    self.annotation_values(&mut annotation.annotation.values);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn frame_value(&self, value: &mut FrameValue) {
+        if let FrameValue::Class(name) = value {
+            self.ty(name);
+        }
+    }
+
+    fn handle(&self, handle: &mut Handle) {
+        match handle.kind {
+            HandleKind::GetField
+            | HandleKind::GetStatic
+            | HandleKind::PutField
+            | HandleKind::PutStatic => {
+                self.field_name(&handle.owner, &mut handle.name, &handle.desc);
+            }
+            _ => self.method_name(&handle.owner, &mut handle.name, &handle.desc),
+        }
+        self.ty(&mut handle.owner);
+        self.desc(&mut handle.desc);
+    }
+
+    fn constant_dynamic(&self, constant: &mut ConstantDynamic) {
+        self.desc(&mut constant.desc);
+        self.handle(&mut constant.bootstrap_method);
+        self.bootstrap_method_arguments(&mut constant.bootstrap_method_arguments);
+    }
+
+    fn bootstrap_method_arguments(&self, arguments: &mut [BootstrapMethodArgument]) {
+        for argument in arguments {
+            match argument {
+                BootstrapMethodArgument::Class(name) => self.ty(name),
+                BootstrapMethodArgument::Handle(handle) => self.handle(handle),
+                BootstrapMethodArgument::ConstantDynamic(constant) => {
+                    self.constant_dynamic(constant)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn annotation(&self, annotation: &mut AnnotationNode) {
+        self.desc(&mut annotation.desc);
+        self.annotation_values(&mut annotation.values);
+    }
+
+    fn annotation_values(&self, values: &mut [(Cow<JavaStr>, AnnotationValue)]) {
+        for (_, value) in values {
+            self.annotation_value(value);
+        }
+    }
+
+    fn annotation_value(&self, value: &mut AnnotationValue) {
+        match value {
+            AnnotationValue::Enum { desc, name } => {
+                if let Some(owner) = desc
+                    .strip_prefix('L')
+                    .and_then(|desc| desc.strip_suffix(';'))
+                {
+                    self.field_name(owner, name, desc);
+                }
+                self.desc(desc);
+            }
+            AnnotationValue::Class(desc) => self.desc(desc),
+            AnnotationValue::Annotation(annotation) => self.annotation(annotation),
+            AnnotationValue::Array(values) => {
+                for value in values {
+                    self.annotation_value(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn field_name(&self, owner: &JavaStr, name: &mut Cow<JavaStr>, desc: &JavaStr) {
+        if let Some(new_name) = self.0.map_field(owner, name, desc) {
+            *name = Cow::Owned(new_name);
+        }
+    }
+
+    fn method_name(&self, owner: &JavaStr, name: &mut Cow<JavaStr>, desc: &JavaStr) {
+        if name.starts_with('<') {
+            return;
+        }
+        if let Some(new_name) = self.0.map_method(owner, name, desc) {
+            *name = Cow::Owned(new_name);
+        }
+    }
+
+    fn class(&self, name: &mut Cow<JavaStr>) {
+        if let Some(new_name) = self.0.map_class(name) {
+            *name = Cow::Owned(new_name);
+        }
+    }
+
+    fn ty(&self, name: &mut Cow<JavaStr>) {
+        if let Some(new_name) = self.0.map_type(name) {
+            *name = Cow::Owned(new_name);
+        }
+    }
+
+    fn package(&self, name: &mut Cow<JavaStr>) {
+        if let Some(new_name) = self.0.map_package(name) {
+            *name = Cow::Owned(new_name);
+        }
+    }
+
+    fn desc(&self, desc: &mut Cow<JavaStr>) {
+        if let Some(new_desc) = self.0.map_desc(desc) {
+            *desc = Cow::Owned(new_desc);
+        }
+    }
+
+    fn optional_signature(&self, signature: &mut Option<Cow<JavaStr>>) {
+        if let Some(signature) = signature {
+            if let Some(new_signature) = self.0.map_signature(signature) {
+                *signature = Cow::Owned(new_signature);
+            }
+        }
+    }
+}
+
+/// Replaces the simple name of an inner class with the part of its new name after the last `$`,
+/// without the digits of local classes. Names without a `$` keep their simple name.
+fn remap_inner_name(inner_name: &mut Cow<JavaStr>, new_name: &JavaStr) {
+    let Some(index) = new_name.rfind('$') else {
+        return;
+    };
+    let simple_name = &new_name[index + 1..];
+    let start = simple_name
+        .bytes()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(simple_name.len());
+    if start < simple_name.len() {
+        *inner_name = Cow::Owned(simple_name[start..].to_owned());
+    }
+}
+
+/// Maps the classes in a descriptor or signature, returning `None` if none of them changed.
+/// Malformed signatures are left unchanged.
+fn remap_signature_classes<R: Remapper + ?Sized>(
+    remapper: &R,
+    signature: &JavaStr,
+) -> Option<JavaString> {
+    type Read<R> =
+        fn(SignatureReader, &mut SignatureRemapper<R, SignatureWriter>) -> ClassFileResult<()>;
+    let remap = |read: Read<R>| {
+        let mut signature_remapper = SignatureRemapper::new(remapper, SignatureWriter::new());
+        read(SignatureReader::new(signature), &mut signature_remapper).ok()?;
+        Some(signature_remapper.into_inner().into_signature())
+    };
+    let output = match signature.as_bytes().first()? {
+        b'(' => remap(|reader, visitor| reader.read_method(visitor)),
+        // type parameters start both class and method signatures
+        b'<' => remap(|reader, visitor| reader.read_class(visitor))
+            .or_else(|| remap(|reader, visitor| reader.read_method(visitor))),
+        b'L' => remap(|reader, visitor| reader.read_class(visitor)),
+        b'[' => remap(|reader, visitor| reader.read_field(visitor)),
+        _ => None,
+    }?;
+    (output != *signature).then_some(output)
+}
+
+/// A [`SignatureVisitor`] which renames the classes of a signature with a [`Remapper`] before
+/// passing them on to another visitor, usually a [`SignatureWriter`].
+#[derive(Debug)]
+pub struct SignatureRemapper<'r, R: ?Sized, V> {
+    #[debug(skip)]
+    remapper: &'r R,
+    delegate: V,
+    /// The old and new names of the class types being visited, innermost last.
+    class_names: Vec<(JavaString, JavaString)>,
+}
+
+impl<'r, R: Remapper + ?Sized, V> SignatureRemapper<'r, R, V> {
+    pub fn new(remapper: &'r R, delegate: V) -> Self {
+        SignatureRemapper {
+            remapper,
+            delegate,
+            class_names: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> V {
+        self.delegate
+    }
+}
+
+impl<'a, R, V> SignatureVisitor<'a> for SignatureRemapper<'_, R, V>
+where
+    R: Remapper + ?Sized,
+    V: for<'b> SignatureVisitor<'b>,
+{
+    fn visit_formal_type_parameter(&mut self, name: &'a JavaStr) {
+        self.delegate.visit_formal_type_parameter(name);
+    }
+
+    fn visit_class_bound(&mut self) {
+        self.delegate.visit_class_bound();
+    }
+
+    fn visit_interface_bound(&mut self) {
+        self.delegate.visit_interface_bound();
+    }
+
+    fn visit_superclass(&mut self) {
+        self.delegate.visit_superclass();
+    }
+
+    fn visit_interface(&mut self) {
+        self.delegate.visit_interface();
+    }
+
+    fn visit_parameter_type(&mut self) {
+        self.delegate.visit_parameter_type();
+    }
+
+    fn visit_return_type(&mut self) {
+        self.delegate.visit_return_type();
+    }
+
+    fn visit_exception_type(&mut self) {
+        self.delegate.visit_exception_type();
+    }
+
+    fn visit_base_type(&mut self, descriptor: char) {
+        self.delegate.visit_base_type(descriptor);
+    }
+
+    fn visit_type_variable(&mut self, name: &'a JavaStr) {
+        self.delegate.visit_type_variable(name);
+    }
+
+    fn visit_array_type(&mut self) {
+        self.delegate.visit_array_type();
+    }
+
+    fn visit_class_type(&mut self, name: &'a JavaStr) {
+        let new_name = self
+            .remapper
+            .map_class(name)
+            .unwrap_or_else(|| name.to_owned());
+        self.delegate.visit_class_type(&new_name);
+        self.class_names.push((name.to_owned(), new_name));
+    }
+
+    fn visit_inner_class_type(&mut self, name: &'a JavaStr) {
+        let Some((class_name, new_name)) = self.class_names.last_mut() else {
+            return;
+        };
+
+        // inner classes are written as their simple name, after the outer class
+        class_name.push('$');
+        class_name.push_java_str(name);
+        let mut outer_prefix = std::mem::take(new_name);
+        outer_prefix.push('$');
+        *new_name = match self.remapper.map_class(class_name) {
+            Some(new_name) => new_name,
+            None => {
+                let mut new_name = outer_prefix.clone();
+                new_name.push_java_str(name);
+                new_name
+            }
+        };
+        let simple_name_start = if new_name.starts_with(outer_prefix.as_java_str()) {
+            outer_prefix.len()
+        } else {
+            new_name
+                .rfind('$')
+                .or_else(|| new_name.rfind('/'))
+                .map_or(0, |index| index + 1)
+        };
+        self.delegate
+            .visit_inner_class_type(&new_name[simple_name_start..]);
+    }
+
+    fn visit_unbounded_type_argument(&mut self) {
+        self.delegate.visit_unbounded_type_argument();
+    }
+
+    fn visit_type_argument(&mut self, kind: TypeArgumentKind) {
+        self.delegate.visit_type_argument(kind);
+    }
+
+    fn visit_end(&mut self) {
+        self.class_names.pop();
+        self.delegate.visit_end();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{ClassNode, InsnNode};
+    use crate::{
+        remap_class, ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags, Remapper,
+        SimpleRemapper,
+    };
+    use java_string::{JavaStr, JavaString};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_map_signature() {
+        let mut remapper = SimpleRemapper::new();
+        remapper.add_class("a", "pkg/Outer");
+        remapper.add_class("a$b", "pkg/Outer$Inner");
+        remapper.add_class("c", "pkg/Other");
+
+        let map = |signature: &str| {
+            remapper
+                .map_signature(JavaStr::from_str(signature))
+                .map(JavaString::into_string)
+                .map(Result::unwrap)
+        };
+        assert_eq!(
+            Some("(I[Lpkg/Other;J)Lpkg/Outer;".to_owned()),
+            map("(I[Lc;J)La;")
+        );
+        assert_eq!(
+            Some("<T:Lpkg/Other;U::Ljava/lang/Comparable<-TT;>;>Lpkg/Outer<TT;>.Inner<*+Lpkg/Other;>;".to_owned()),
+            map("<T:Lc;U::Ljava/lang/Comparable<-TT;>;>La<TT;>.b<*+Lc;>;")
+        );
+        assert_eq!(
+            Some("Lpkg/Outer<Lpkg/Other;>.d;".to_owned()),
+            map("La<Lc;>.d;")
+        );
+        assert_eq!(
+            Some("<T:Lpkg/Other;>(TT;)Lpkg/Outer$Inner;".to_owned()),
+            map("<T:Lc;>(TT;)La$b;")
+        );
+        assert_eq!(None, map("(TLc;Ljava/lang/String;)V"));
+        assert_eq!(None, map("La<Lc;"));
+    }
+
+    #[test]
+    fn test_remap_class() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let mut remapper = SimpleRemapper::new();
+        remapper.add_class("TestCode", "pkg/Renamed");
+        remapper.add_class("java/lang/ClassCastException", "pkg/CastError");
+        remapper.add_field(
+            JavaStr::from_str("TestCode"),
+            JavaStr::from_str("total"),
+            "sum",
+        );
+        remapper.add_method(
+            JavaStr::from_str("TestCode"),
+            JavaStr::from_str("loops"),
+            JavaStr::from_str("([I)D"),
+            "iterate",
+        );
+        let events = remap_class(&reader, &remapper).unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(events)
+            .unwrap();
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(&reader).unwrap();
+        assert_eq!("pkg/Renamed", class.name.as_ref());
+        assert!(class
+            .fields
+            .iter()
+            .any(|field| field.name.as_ref() == "sum"));
+        assert!(class
+            .methods
+            .iter()
+            .any(|method| method.name.as_ref() == "iterate"));
+        let try_catch = class
+            .methods
+            .iter()
+            .find(|method| method.name.as_ref() == "tryCatch")
+            .unwrap();
+        assert!(try_catch.try_catch_blocks.iter().any(|try_catch_block| {
+            try_catch_block.ty.as_deref() == Some(JavaStr::from_str("pkg/CastError"))
+        }));
+        assert!(try_catch.instructions.iter().any(|insn| matches!(
+            insn,
+            InsnNode::FieldInsn { owner, name, .. }
+                if owner.as_ref() == "pkg/Renamed" && name.as_ref() == "sum"
+        )));
+    }
+}
@@ -0,0 +1,923 @@
+use crate::tree::{AnnotationNode, AnnotationValue};
+use crate::{
+    buffer_class_events, AnnotationEvent, BootstrapMethodArgument, BufferedEventProviders,
+    ClassEvent, ClassEventSource, ClassFileResult, ConstantDynamic, FieldEvent, FieldValue, Frame,
+    FrameValue, Handle, Label, LdcConstant, MethodEvent, ModuleEvent, ModuleRelationEvent,
+    RecordComponentEvent,
+};
+use bitflags::Flags;
+use java_string::JavaStr;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+/// Generates Rust source code which builds the events of a class and writes it with a
+/// [`ClassWriter`](crate::ClassWriter), like ASM's `ASMifier`. This shows how to emit any construct
+/// which javac can compile: compile the Java, rustify the class file and copy the parts of the
+/// generated code which are needed.
+///
+/// The generated function returns the bytes of the class. Type annotations and custom attributes
+/// can't be generated, and are replaced by comments.
+#[derive(Debug, Clone)]
+pub struct Rustifier {
+    function_name: String,
+}
+
+impl Default for Rustifier {
+    fn default() -> Self {
+        Rustifier {
+            function_name: "generate".to_owned(),
+        }
+    }
+}
+
+impl Rustifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the generated function, which defaults to `generate`.
+    pub fn set_function_name(&mut self, function_name: impl Into<String>) {
+        self.function_name = function_name.into();
+    }
+
+    pub fn rustify<'class, S>(&self, source: S) -> ClassFileResult<String>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut output = String::new();
+        self.write_to(source, &mut output)?;
+        Ok(output)
+    }
+
+    pub fn write_to<'class, S, W>(&self, source: S, output: &mut W) -> ClassFileResult<()>
+    where
+        S: ClassEventSource<'class>,
+        W: Write,
+    {
+        let events = buffer_class_events(source)?;
+        let mut code = Code::default();
+        code.line("use classfile::tree::{AnnotationNode, AnnotationValue};");
+        code.line("use classfile::*;");
+        code.line("use java_string::JavaStr;");
+        code.line("use std::borrow::Cow;");
+        code.line("");
+        code.open(format!(
+            "pub fn {}() -> ClassFileResult<Vec<u8>> {{",
+            self.function_name
+        ));
+        code.line("let s = |s: &'static str| Cow::Borrowed(JavaStr::from_str(s));");
+        code.open("let events: BufferedClassEvents = EventBuffer(vec![");
+        for event in events.0 {
+            write_class_event(&mut code, event);
+        }
+        code.close("]);");
+        code.line("ClassWriter::new(ClassWriterFlags::None).write(events)");
+        code.close("}");
+        output.write_str(&code.text)?;
+        Ok(())
+    }
+}
+
+/// Generated code, written a line at a time.
+#[derive(Default)]
+struct Code {
+    text: String,
+    indent: usize,
+}
+
+impl Code {
+    fn line(&mut self, line: impl fmt::Display) {
+        let line = line.to_string();
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.text.push_str("    ");
+            }
+        }
+        self.text.push_str(&line);
+        self.text.push('\n');
+    }
+
+    /// Writes a line which opens a bracket, indenting the following lines.
+    fn open(&mut self, line: impl fmt::Display) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    /// Writes a line which closes a bracket opened by [`open`](Code::open).
+    fn close(&mut self, line: impl fmt::Display) {
+        self.indent -= 1;
+        self.line(line);
+    }
+}
+
+fn write_class_event(code: &mut Code, event: ClassEvent<'_, BufferedEventProviders>) {
+    match event {
+        ClassEvent::Class(class) => {
+            code.open("ClassEvent::Class(ClassClassEvent {");
+            code.line(format!("major_version: {},", class.major_version));
+            code.line(format!("minor_version: {},", class.minor_version));
+            code.line(format!(
+                "access: {},",
+                access_expr("ClassAccess", class.access)
+            ));
+            code.line(format!("name: {},", str_expr(&class.name)));
+            code.line(format!(
+                "signature: {},",
+                opt_str_expr(class.signature.as_deref())
+            ));
+            code.line(format!(
+                "super_name: {},",
+                opt_str_expr(class.super_name.as_deref())
+            ));
+            code.line(format!("interfaces: {},", str_vec_expr(&class.interfaces)));
+            code.close("}),");
+        }
+        ClassEvent::Synthetic => code.line("ClassEvent::Synthetic,"),
+        ClassEvent::Deprecated => code.line("ClassEvent::Deprecated,"),
+        ClassEvent::Source(source) => code.line(format!(
+            "ClassEvent::Source(ClassSourceEvent {{ source: {}, debug: {} }}),",
+            opt_str_expr(source.source.as_deref()),
+            opt_str_expr(source.debug.as_deref())
+        )),
+        ClassEvent::Module(module) => {
+            code.open("ClassEvent::Module(ClassModuleEvent {");
+            code.line(format!("name: {},", str_expr(&module.name)));
+            code.line(format!(
+                "access: {},",
+                access_expr("ModuleAccess", module.access)
+            ));
+            code.line(format!(
+                "version: {},",
+                opt_str_expr(module.version.as_deref())
+            ));
+            code.open("events: EventBuffer(vec![");
+            for event in module.events.0 {
+                write_module_event(code, event);
+            }
+            code.close("]),");
+            code.close("}),");
+        }
+        ClassEvent::NestHost(host) => {
+            code.line(format!("ClassEvent::NestHost({}),", str_expr(&host)))
+        }
+        ClassEvent::OuterClass(outer_class) => code.line(format!(
+            "ClassEvent::OuterClass(ClassOuterClassEvent {{ owner: {}, method_name: {}, \
+             method_desc: {} }}),",
+            str_expr(&outer_class.owner),
+            opt_str_expr(outer_class.method_name.as_deref()),
+            opt_str_expr(outer_class.method_desc.as_deref())
+        )),
+        ClassEvent::Annotations(annotations) => code.line(format!(
+            "ClassEvent::Annotations({}),",
+            annotations_expr(&annotations.0)
+        )),
+        ClassEvent::TypeAnnotations(_) => type_annotations_comment(code),
+        ClassEvent::Attributes(attributes) => {
+            for attribute in attributes.0 {
+                attribute_comment(code, attribute.name());
+            }
+        }
+        ClassEvent::NestMembers(members) => code.line(format!(
+            "ClassEvent::NestMembers(EventBuffer({})),",
+            str_vec_expr(&members.0)
+        )),
+        ClassEvent::PermittedSubclasses(subclasses) => code.line(format!(
+            "ClassEvent::PermittedSubclasses(EventBuffer({})),",
+            str_vec_expr(&subclasses.0)
+        )),
+        #[cfg(feature = "preview")]
+        ClassEvent::LoadableDescriptors(descs) => code.line(format!(
+            "ClassEvent::LoadableDescriptors({}),",
+            str_vec_expr(&descs)
+        )),
+        ClassEvent::InnerClasses(inner_classes) => {
+            code.open("ClassEvent::InnerClasses(EventBuffer(vec![");
+            for inner_class in inner_classes.0 {
+                code.line(format!(
+                    "ClassInnerClassEvent {{ name: {}, outer_name: {}, inner_name: {}, \
+                     access: {} }},",
+                    str_expr(&inner_class.name),
+                    opt_str_expr(inner_class.outer_name.as_deref()),
+                    opt_str_expr(inner_class.inner_name.as_deref()),
+                    access_expr("InnerClassAccess", inner_class.access)
+                ));
+            }
+            code.close("])),");
+        }
+        ClassEvent::Record(components) => {
+            code.open("ClassEvent::Record(EventBuffer(vec![");
+            for component in components.0 {
+                code.open("ClassRecordComponentEvent {");
+                code.line(format!("name: {},", str_expr(&component.name)));
+                code.line(format!("desc: {},", str_expr(&component.desc)));
+                code.line(format!(
+                    "signature: {},",
+                    opt_str_expr(component.signature.as_deref())
+                ));
+                code.open("events: EventBuffer(vec![");
+                for event in component.events.0 {
+                    match event {
+                        RecordComponentEvent::Annotations(annotations) => code.line(format!(
+                            "RecordComponentEvent::Annotations({}),",
+                            annotations_expr(&annotations.0)
+                        )),
+                        RecordComponentEvent::TypeAnnotations(_) => type_annotations_comment(code),
+                        RecordComponentEvent::Attributes(attributes) => {
+                            for attribute in attributes.0 {
+                                attribute_comment(code, attribute.name());
+                            }
+                        }
+                    }
+                }
+                code.close("]),");
+                code.close("},");
+            }
+            code.close("])),");
+        }
+        ClassEvent::Fields(fields) => {
+            code.open("ClassEvent::Fields(EventBuffer(vec![");
+            for field in fields.0 {
+                code.open("ClassFieldEvent {");
+                code.line(format!(
+                    "access: {},",
+                    access_expr("FieldAccess", field.access)
+                ));
+                code.line(format!("name: {},", str_expr(&field.name)));
+                code.line(format!("desc: {},", str_expr(&field.desc)));
+                code.line(format!(
+                    "signature: {},",
+                    opt_str_expr(field.signature.as_deref())
+                ));
+                let value = match &field.value {
+                    Some(FieldValue::Integer(value)) => {
+                        format!("Some(FieldValue::Integer({value}))")
+                    }
+                    Some(FieldValue::Float(value)) => {
+                        format!("Some(FieldValue::Float({}))", f32_expr(*value))
+                    }
+                    Some(FieldValue::Long(value)) => format!("Some(FieldValue::Long({value}))"),
+                    Some(FieldValue::Double(value)) => {
+                        format!("Some(FieldValue::Double({}))", f64_expr(*value))
+                    }
+                    Some(FieldValue::String(value)) => {
+                        format!("Some(FieldValue::String({}))", str_expr(value))
+                    }
+                    None => "None".to_owned(),
+                };
+                code.line(format!("value: {value},"));
+                code.open("events: EventBuffer(vec![");
+                for event in field.events.0 {
+                    match event {
+                        FieldEvent::Deprecated => code.line("FieldEvent::Deprecated,"),
+                        FieldEvent::Annotations(annotations) => code.line(format!(
+                            "FieldEvent::Annotations({}),",
+                            annotations_expr(&annotations.0)
+                        )),
+                        FieldEvent::TypeAnnotations(_) => type_annotations_comment(code),
+                        FieldEvent::Attributes(attributes) => {
+                            for attribute in attributes.0 {
+                                attribute_comment(code, attribute.name());
+                            }
+                        }
+                    }
+                }
+                code.close("]),");
+                code.close("},");
+            }
+            code.close("])),");
+        }
+        ClassEvent::Methods(methods) => {
+            code.open("ClassEvent::Methods(EventBuffer(vec![");
+            for method in methods.0 {
+                code.open("ClassMethodEvent {");
+                code.line(format!(
+                    "access: {},",
+                    access_expr("MethodAccess", method.access)
+                ));
+                code.line(format!("name: {},", str_expr(&method.name)));
+                code.line(format!("desc: {},", str_expr(&method.desc)));
+                code.line(format!(
+                    "signature: {},",
+                    opt_str_expr(method.signature.as_deref())
+                ));
+                code.line(format!("exceptions: {},", str_vec_expr(&method.exceptions)));
+                write_method_events(code, method.events.0);
+                code.close("},");
+            }
+            code.close("])),");
+        }
+    }
+}
+
+fn write_module_event(code: &mut Code, event: ModuleEvent<'_, BufferedEventProviders>) {
+    match event {
+        ModuleEvent::MainClass(main_class) => code.line(format!(
+            "ModuleEvent::MainClass({}),",
+            str_expr(&main_class)
+        )),
+        ModuleEvent::Packages(packages) => code.line(format!(
+            "ModuleEvent::Packages(EventBuffer({})),",
+            str_vec_expr(&packages.0)
+        )),
+        ModuleEvent::Target(target) => {
+            code.line(format!("ModuleEvent::Target({}),", str_expr(&target)))
+        }
+        ModuleEvent::Resolution(resolution) => code.line(format!(
+            "ModuleEvent::Resolution({}),",
+            access_expr("ModuleResolution", resolution)
+        )),
+        ModuleEvent::Hashes(hashes) => {
+            code.open("ModuleEvent::Hashes(ModuleHashesEvent {");
+            code.line(format!("algorithm: {},", str_expr(&hashes.algorithm)));
+            code.open("hashes: vec![");
+            for hash in hashes.hashes {
+                code.line(format!(
+                    "ModuleHashEvent {{ module: {}, hash: Cow::Borrowed(&{:?}) }},",
+                    str_expr(&hash.module),
+                    hash.hash
+                ));
+            }
+            code.close("],");
+            code.close("}),");
+        }
+        ModuleEvent::Requires(requires) => {
+            code.open("ModuleEvent::Requires(EventBuffer(vec![");
+            for require in requires.0 {
+                code.line(format!(
+                    "ModuleRequireEvent {{ module: {}, access: {}, version: {} }},",
+                    str_expr(&require.module),
+                    access_expr("ModuleRequireAccess", require.access),
+                    opt_str_expr(require.version.as_deref())
+                ));
+            }
+            code.close("])),");
+        }
+        ModuleEvent::Exports(relations) => {
+            code.open("ModuleEvent::Exports(EventBuffer(vec![");
+            write_module_relations(code, relations.0);
+            code.close("])),");
+        }
+        ModuleEvent::Opens(relations) => {
+            code.open("ModuleEvent::Opens(EventBuffer(vec![");
+            write_module_relations(code, relations.0);
+            code.close("])),");
+        }
+        ModuleEvent::Uses(uses) => code.line(format!(
+            "ModuleEvent::Uses(EventBuffer({})),",
+            str_vec_expr(&uses.0)
+        )),
+        ModuleEvent::Provides(provides) => {
+            code.open("ModuleEvent::Provides(EventBuffer(vec![");
+            for provide in provides.0 {
+                code.line(format!(
+                    "ModuleProvidesEvent {{ service: {}, providers: {} }},",
+                    str_expr(&provide.service),
+                    str_vec_expr(&provide.providers)
+                ));
+            }
+            code.close("])),");
+        }
+    }
+}
+
+fn write_module_relations(code: &mut Code, relations: Vec<ModuleRelationEvent<'_>>) {
+    for relation in relations {
+        code.line(format!(
+            "ModuleRelationEvent {{ package: {}, access: {}, modules: {} }},",
+            str_expr(&relation.package),
+            access_expr("ModuleRelationAccess", relation.access),
+            str_vec_expr(&relation.modules)
+        ));
+    }
+}
+
+fn write_method_events<'class>(
+    code: &mut Code,
+    events: Vec<MethodEvent<'class, BufferedEventProviders>>,
+) {
+    // the events are generated first, so that the labels they use can be declared before them
+    let has_code = events
+        .iter()
+        .any(|event| matches!(event, MethodEvent::Code { .. }));
+    let mut labels = Labels::default();
+    let mut event_code = Code {
+        text: String::new(),
+        indent: code.indent + 2,
+    };
+    for event in events {
+        write_method_event(&mut event_code, &mut labels, event);
+    }
+
+    if !has_code {
+        code.open("events: EventBuffer(vec![");
+        code.text.push_str(&event_code.text);
+        code.close("]),");
+        return;
+    }
+    code.open("events: {");
+    code.line("let label_creator = LabelCreator::default();");
+    for index in 0..labels.0.len() {
+        code.line(format!("let l{index} = label_creator.create_label();"));
+    }
+    code.open("EventBuffer(vec![");
+    code.text.push_str(&event_code.text);
+    code.close("])");
+    code.close("},");
+}
+
+/// The names of the labels of a method, in the order they are first used.
+#[derive(Default)]
+struct Labels(HashMap<Label, usize>);
+
+impl Labels {
+    fn name(&mut self, label: Label) -> String {
+        let next_index = self.0.len();
+        format!("l{}", self.0.entry(label).or_insert(next_index))
+    }
+}
+
+fn write_method_event<'class>(
+    code: &mut Code,
+    labels: &mut Labels,
+    event: MethodEvent<'class, BufferedEventProviders>,
+) {
+    match event {
+        MethodEvent::Deprecated => code.line("MethodEvent::Deprecated,"),
+        MethodEvent::Parameters(parameters) => {
+            code.open("MethodEvent::Parameters(EventBuffer(vec![");
+            for parameter in parameters.0 {
+                code.line(format!(
+                    "MethodParameterEvent {{ name: {}, access: {} }},",
+                    opt_str_expr(parameter.name.as_deref()),
+                    access_expr("ParameterAccess", parameter.access)
+                ));
+            }
+            code.close("])),");
+        }
+        MethodEvent::AnnotationDefault(value) => code.line(format!(
+            "MethodEvent::AnnotationDefault({}),",
+            annotation_value_expr(&value)
+        )),
+        MethodEvent::Annotations(annotations) => code.line(format!(
+            "MethodEvent::Annotations({}),",
+            annotations_expr(&annotations.0)
+        )),
+        MethodEvent::TypeAnnotations(_)
+        | MethodEvent::InsnAnnotations(_)
+        | MethodEvent::LocalVariableAnnotations(_)
+        | MethodEvent::TryCatchBlockAnnotations(_) => type_annotations_comment(code),
+        MethodEvent::AnnotableParameterCount(count) => code.line(format!(
+            "MethodEvent::AnnotableParameterCount(MethodAnnotableParameterCountEvent {{ \
+             count: {}, visible: {} }}),",
+            count.count, count.visible
+        )),
+        MethodEvent::ParameterAnnotations(annotations) => {
+            code.open("MethodEvent::ParameterAnnotations(EventBuffer(vec![");
+            for annotation in annotations.0 {
+                code.line(format!(
+                    "MethodParameterAnnotationEvent {{ parameter: {}, visible: {}, \
+                     annotation: {} }},",
+                    annotation.parameter,
+                    annotation.visible,
+                    annotation_expr(&annotation.annotation)
+                ));
+            }
+            code.close("])),");
+        }
+        MethodEvent::Attributes(attributes) | MethodEvent::CodeAttributes(attributes) => {
+            for attribute in attributes.0 {
+                attribute_comment(code, attribute.name());
+            }
+        }
+        MethodEvent::Code { .. } => {
+            code.line("MethodEvent::Code { label_creator: label_creator.clone() },")
+        }
+        MethodEvent::Frame(frame) => {
+            let frame = match frame {
+                Frame::Full { locals, stack } => format!(
+                    "Frame::Full {{ locals: {}, stack: {} }}",
+                    frame_values_expr(labels, &locals),
+                    frame_values_expr(labels, &stack)
+                ),
+                Frame::New { locals, stack } => format!(
+                    "Frame::New {{ locals: {}, stack: {} }}",
+                    frame_values_expr(labels, &locals),
+                    frame_values_expr(labels, &stack)
+                ),
+                Frame::Append { locals } => format!(
+                    "Frame::Append {{ locals: {} }}",
+                    frame_values_expr(labels, &locals)
+                ),
+                Frame::Chop { num_locals } => format!("Frame::Chop {{ num_locals: {num_locals} }}"),
+                Frame::Same => "Frame::Same".to_owned(),
+                Frame::Same1 { stack_value } => format!(
+                    "Frame::Same1 {{ stack_value: {} }}",
+                    frame_value_expr(labels, &stack_value)
+                ),
+            };
+            code.line(format!("MethodEvent::Frame({frame}),"));
+        }
+        MethodEvent::Insn(opcode) => code.line(format!("MethodEvent::Insn(Opcode::{opcode:?}),")),
+        MethodEvent::BIPushInsn(value) => code.line(format!("MethodEvent::BIPushInsn({value}),")),
+        MethodEvent::SIPushInsn(value) => code.line(format!("MethodEvent::SIPushInsn({value}),")),
+        MethodEvent::NewArrayInsn(ty) => {
+            code.line(format!("MethodEvent::NewArrayInsn(NewArrayType::{ty:?}),"))
+        }
+        MethodEvent::VarInsn { opcode, var_index } => code.line(format!(
+            "MethodEvent::VarInsn {{ opcode: Opcode::{opcode:?}, var_index: {var_index} }},"
+        )),
+        MethodEvent::TypeInsn { opcode, ty } => code.line(format!(
+            "MethodEvent::TypeInsn {{ opcode: Opcode::{opcode:?}, ty: {} }},",
+            str_expr(&ty)
+        )),
+        MethodEvent::FieldInsn {
+            opcode,
+            owner,
+            name,
+            desc,
+        } => code.line(format!(
+            "MethodEvent::FieldInsn {{ opcode: Opcode::{opcode:?}, owner: {}, name: {}, \
+             desc: {} }},",
+            str_expr(&owner),
+            str_expr(&name),
+            str_expr(&desc)
+        )),
+        MethodEvent::MethodInsn {
+            opcode,
+            owner,
+            name,
+            desc,
+            is_interface,
+        } => code.line(format!(
+            "MethodEvent::MethodInsn {{ opcode: Opcode::{opcode:?}, owner: {}, name: {}, \
+             desc: {}, is_interface: {is_interface} }},",
+            str_expr(&owner),
+            str_expr(&name),
+            str_expr(&desc)
+        )),
+        MethodEvent::InvokeDynamicInsn {
+            name,
+            desc,
+            bootstrap_method_handle,
+            bootstrap_method_arguments,
+        } => {
+            code.open("MethodEvent::InvokeDynamicInsn {");
+            code.line(format!("name: {},", str_expr(&name)));
+            code.line(format!("desc: {},", str_expr(&desc)));
+            code.line(format!(
+                "bootstrap_method_handle: {},",
+                handle_expr(&bootstrap_method_handle)
+            ));
+            code.line(format!(
+                "bootstrap_method_arguments: {},",
+                bootstrap_arguments_expr(&bootstrap_method_arguments)
+            ));
+            code.close("},");
+        }
+        MethodEvent::JumpInsn { opcode, label } => code.line(format!(
+            "MethodEvent::JumpInsn {{ opcode: Opcode::{opcode:?}, label: {} }},",
+            labels.name(label)
+        )),
+        MethodEvent::Label(label) => {
+            code.line(format!("MethodEvent::Label({}),", labels.name(label)))
+        }
+        MethodEvent::LdcInsn(constant) => code.line(format!(
+            "MethodEvent::LdcInsn({}),",
+            ldc_constant_expr(&constant)
+        )),
+        MethodEvent::IIncInsn {
+            var_index,
+            increment,
+        } => code.line(format!(
+            "MethodEvent::IIncInsn {{ var_index: {var_index}, increment: {increment} }},"
+        )),
+        MethodEvent::TableSwitchInsn {
+            low,
+            high,
+            dflt,
+            labels: targets,
+        } => {
+            let dflt = labels.name(dflt);
+            let targets = targets
+                .into_iter()
+                .map(|label| labels.name(label))
+                .collect::<Vec<_>>()
+                .join(", ");
+            code.line(format!(
+                "MethodEvent::TableSwitchInsn {{ low: {low}, high: {high}, dflt: {dflt}, \
+                 labels: vec![{targets}] }},"
+            ));
+        }
+        MethodEvent::LookupSwitchInsn { dflt, values } => {
+            let dflt = labels.name(dflt);
+            let values = values
+                .into_iter()
+                .map(|(value, label)| format!("({value}, {})", labels.name(label)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            code.line(format!(
+                "MethodEvent::LookupSwitchInsn {{ dflt: {dflt}, values: vec![{values}] }},"
+            ));
+        }
+        MethodEvent::MultiANewArrayInsn { desc, dimensions } => code.line(format!(
+            "MethodEvent::MultiANewArrayInsn {{ desc: {}, dimensions: {dimensions} }},",
+            str_expr(&desc)
+        )),
+        MethodEvent::LineNumber { line, start } => code.line(format!(
+            "MethodEvent::LineNumber {{ line: {line}, start: {} }},",
+            labels.name(start)
+        )),
+        MethodEvent::LocalVariables(locals) => {
+            code.open("MethodEvent::LocalVariables(EventBuffer(vec![");
+            for local in locals.0 {
+                code.line(format!(
+                    "MethodLocalVariableEvent {{ name: {}, desc: {}, signature: {}, start: {}, \
+                     end: {}, index: {} }},",
+                    str_expr(&local.name),
+                    str_expr(&local.desc),
+                    opt_str_expr(local.signature.as_deref()),
+                    labels.name(local.start),
+                    labels.name(local.end),
+                    local.index
+                ));
+            }
+            code.close("])),");
+        }
+        MethodEvent::TryCatchBlocks(blocks) => {
+            code.open("MethodEvent::TryCatchBlocks(EventBuffer(vec![");
+            for block in blocks.0 {
+                code.line(format!(
+                    "MethodTryCatchBlockEvent {{ start: {}, end: {}, handler: {}, ty: {} }},",
+                    labels.name(block.start),
+                    labels.name(block.end),
+                    labels.name(block.handler),
+                    opt_str_expr(block.ty.as_deref())
+                ));
+            }
+            code.close("])),");
+        }
+        MethodEvent::Maxs(maxs) => code.line(format!(
+            "MethodEvent::Maxs(MethodMaxsEvent {{ max_stack: {}, max_locals: {} }}),",
+            maxs.max_stack, maxs.max_locals
+        )),
+        MethodEvent::Unchanged(_) => code.line("// unchanged methods can't be generated"),
+    }
+}
+
+fn type_annotations_comment(code: &mut Code) {
+    code.line("// type annotations can't be generated");
+}
+
+fn attribute_comment(code: &mut Code, name: &JavaStr) {
+    code.line(format!("// attribute {name} can't be generated"));
+}
+
+fn str_expr(value: &JavaStr) -> String {
+    match value.as_str() {
+        Ok(value) => format!("s({value:?})"),
+        Err(_) => {
+            let bytes: String = value
+                .as_bytes()
+                .iter()
+                .map(|byte| format!("\\x{byte:02x}"))
+                .collect();
+            format!("Cow::Borrowed(JavaStr::from_semi_utf8(b\"{bytes}\").unwrap())")
+        }
+    }
+}
+
+fn opt_str_expr(value: Option<&JavaStr>) -> String {
+    match value {
+        Some(value) => format!("Some({})", str_expr(value)),
+        None => "None".to_owned(),
+    }
+}
+
+fn str_vec_expr(values: &[Cow<'_, JavaStr>]) -> String {
+    let values = values
+        .iter()
+        .map(|value| str_expr(value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("vec![{values}]")
+}
+
+fn access_expr<F>(type_name: &str, access: F) -> String
+where
+    F: Flags<Bits = u16>,
+{
+    let mut named_bits = 0;
+    let mut terms = Vec::new();
+    for (name, flag) in access.iter_names() {
+        named_bits |= flag.bits();
+        terms.push(format!("{type_name}::{name}"));
+    }
+    let unnamed_bits = access.bits() & !named_bits;
+    if unnamed_bits != 0 {
+        terms.push(format!("{type_name}::from_bits_retain({unnamed_bits:#x})"));
+    }
+    if terms.is_empty() {
+        format!("{type_name}::empty()")
+    } else {
+        terms.join(" | ")
+    }
+}
+
+fn f32_expr(value: f32) -> String {
+    if value.is_finite() {
+        format!("{value:?}")
+    } else {
+        format!("f32::from_bits({:#x})", value.to_bits())
+    }
+}
+
+fn f64_expr(value: f64) -> String {
+    if value.is_finite() {
+        format!("{value:?}")
+    } else {
+        format!("f64::from_bits({:#x})", value.to_bits())
+    }
+}
+
+fn annotations_expr(annotations: &[AnnotationEvent<AnnotationNode<'_>>]) -> String {
+    let annotations = annotations
+        .iter()
+        .map(|annotation| {
+            format!(
+                "AnnotationEvent {{ visible: {}, annotation: {} }}",
+                annotation.visible,
+                annotation_expr(&annotation.annotation)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("EventBuffer(vec![{annotations}])")
+}
+
+fn annotation_expr(annotation: &AnnotationNode<'_>) -> String {
+    let values = annotation
+        .values
+        .iter()
+        .map(|(name, value)| format!("({}, {})", str_expr(name), annotation_value_expr(value)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "AnnotationNode {{ desc: {}, values: vec![{values}] }}",
+        str_expr(&annotation.desc)
+    )
+}
+
+fn annotation_value_expr(value: &AnnotationValue<'_>) -> String {
+    match value {
+        AnnotationValue::Byte(value) => format!("AnnotationValue::Byte({value})"),
+        AnnotationValue::Char(value) => format!("AnnotationValue::Char({value})"),
+        AnnotationValue::Double(value) => format!("AnnotationValue::Double({})", f64_expr(*value)),
+        AnnotationValue::Float(value) => format!("AnnotationValue::Float({})", f32_expr(*value)),
+        AnnotationValue::Int(value) => format!("AnnotationValue::Int({value})"),
+        AnnotationValue::Long(value) => format!("AnnotationValue::Long({value})"),
+        AnnotationValue::Short(value) => format!("AnnotationValue::Short({value})"),
+        AnnotationValue::Boolean(value) => format!("AnnotationValue::Boolean({value})"),
+        AnnotationValue::String(value) => format!("AnnotationValue::String({})", str_expr(value)),
+        AnnotationValue::Enum { desc, name } => format!(
+            "AnnotationValue::Enum {{ desc: {}, name: {} }}",
+            str_expr(desc),
+            str_expr(name)
+        ),
+        AnnotationValue::Class(desc) => format!("AnnotationValue::Class({})", str_expr(desc)),
+        AnnotationValue::Annotation(annotation) => {
+            format!(
+                "AnnotationValue::Annotation({})",
+                annotation_expr(annotation)
+            )
+        }
+        AnnotationValue::Array(values) => {
+            let values = values
+                .iter()
+                .map(annotation_value_expr)
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("AnnotationValue::Array(vec![{values}])")
+        }
+    }
+}
+
+fn frame_values_expr(labels: &mut Labels, values: &[FrameValue<'_>]) -> String {
+    let values = values
+        .iter()
+        .map(|value| frame_value_expr(labels, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("vec![{values}]")
+}
+
+fn frame_value_expr(labels: &mut Labels, value: &FrameValue<'_>) -> String {
+    match value {
+        FrameValue::Top => "FrameValue::Top".to_owned(),
+        FrameValue::Integer => "FrameValue::Integer".to_owned(),
+        FrameValue::Float => "FrameValue::Float".to_owned(),
+        FrameValue::Long => "FrameValue::Long".to_owned(),
+        FrameValue::Double => "FrameValue::Double".to_owned(),
+        FrameValue::Null => "FrameValue::Null".to_owned(),
+        FrameValue::UninitializedThis => "FrameValue::UninitializedThis".to_owned(),
+        FrameValue::Class(name) => format!("FrameValue::Class({})", str_expr(name)),
+        FrameValue::Uninitialized(label) => {
+            format!("FrameValue::Uninitialized({})", labels.name(*label))
+        }
+    }
+}
+
+fn ldc_constant_expr(constant: &LdcConstant<'_>) -> String {
+    match constant {
+        LdcConstant::Integer(value) => format!("LdcConstant::Integer({value})"),
+        LdcConstant::Float(value) => format!("LdcConstant::Float({})", f32_expr(*value)),
+        LdcConstant::Long(value) => format!("LdcConstant::Long({value})"),
+        LdcConstant::Double(value) => format!("LdcConstant::Double({})", f64_expr(*value)),
+        LdcConstant::String(value) => format!("LdcConstant::String({})", str_expr(value)),
+        LdcConstant::Class(name) => format!("LdcConstant::Class({})", str_expr(name)),
+        LdcConstant::MethodType(desc) => format!("LdcConstant::MethodType({})", str_expr(desc)),
+        LdcConstant::Handle(handle) => format!("LdcConstant::Handle({})", handle_expr(handle)),
+        LdcConstant::ConstantDynamic(constant) => format!(
+            "LdcConstant::ConstantDynamic({})",
+            constant_dynamic_expr(constant)
+        ),
+    }
+}
+
+fn handle_expr(handle: &Handle<'_>) -> String {
+    format!(
+        "Handle {{ kind: HandleKind::{:?}, owner: {}, name: {}, desc: {}, is_interface: {} }}",
+        handle.kind,
+        str_expr(&handle.owner),
+        str_expr(&handle.name),
+        str_expr(&handle.desc),
+        handle.is_interface
+    )
+}
+
+fn constant_dynamic_expr(constant: &ConstantDynamic<'_>) -> String {
+    format!(
+        "ConstantDynamic {{ name: {}, desc: {}, bootstrap_method: {}, \
+         bootstrap_method_arguments: {} }}",
+        str_expr(&constant.name),
+        str_expr(&constant.desc),
+        handle_expr(&constant.bootstrap_method),
+        bootstrap_arguments_expr(&constant.bootstrap_method_arguments)
+    )
+}
+
+fn bootstrap_arguments_expr(arguments: &[BootstrapMethodArgument<'_>]) -> String {
+    let arguments = arguments
+        .iter()
+        .map(|argument| match argument {
+            BootstrapMethodArgument::Integer(value) => {
+                format!("BootstrapMethodArgument::Integer({value})")
+            }
+            BootstrapMethodArgument::Float(value) => {
+                format!("BootstrapMethodArgument::Float({})", f32_expr(*value))
+            }
+            BootstrapMethodArgument::Long(value) => {
+                format!("BootstrapMethodArgument::Long({value})")
+            }
+            BootstrapMethodArgument::Double(value) => {
+                format!("BootstrapMethodArgument::Double({})", f64_expr(*value))
+            }
+            BootstrapMethodArgument::String(value) => {
+                format!("BootstrapMethodArgument::String({})", str_expr(value))
+            }
+            BootstrapMethodArgument::Class(name) => {
+                format!("BootstrapMethodArgument::Class({})", str_expr(name))
+            }
+            BootstrapMethodArgument::Handle(handle) => {
+                format!("BootstrapMethodArgument::Handle({})", handle_expr(handle))
+            }
+            BootstrapMethodArgument::ConstantDynamic(constant) => format!(
+                "BootstrapMethodArgument::ConstantDynamic({})",
+                constant_dynamic_expr(constant)
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("vec![{arguments}]")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassReader, ClassReaderFlags, Rustifier};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_rustify_hello_world() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let code = Rustifier::new().rustify(&reader).unwrap();
+        let lines: Vec<_> = code.lines().map(str::trim).collect();
+        assert!(lines.contains(&"pub fn generate() -> ClassFileResult<Vec<u8>> {"));
+        assert!(lines.contains(&"access: ClassAccess::Public | ClassAccess::Super,"));
+        assert!(lines.contains(&"super_name: Some(s(\"java/lang/Object\")),"));
+        assert!(lines.contains(&"let l0 = label_creator.create_label();"));
+        let ldc = "MethodEvent::LdcInsn(LdcConstant::String(s(\"Hello, World!\"))),";
+        assert!(lines.contains(&ldc));
+        assert!(lines.contains(&"MethodEvent::VarInsn { opcode: Opcode::ALoad, var_index: 0 },"));
+    }
+}
@@ -0,0 +1,421 @@
+use crate::tree::AnnotationNode;
+use crate::{
+    Attribute, AttributeReader, ClassBuffer, ClassEvent, ClassEventSource, ClassFileError,
+    ClassFileResult, ClassReader, ConstantPoolBuilder, UnknownAttribute,
+};
+use derive_more::{Debug, IsVariant};
+use java_string::JavaStr;
+use std::any::Any;
+
+const SCALA_ATTRIBUTE: &str = "Scala";
+const SCALA_SIG_ATTRIBUTE: &str = "ScalaSig";
+const SCALA_SIGNATURE_DESC: &str = "Lscala/reflect/ScalaSignature;";
+const SCALA_LONG_SIGNATURE_DESC: &str = "Lscala/reflect/ScalaLongSignature;";
+
+/// The signatures of a Scala 2 class and its companion, as pickled by the Scala compiler. The
+/// entries refer to each other by index and are decoded by `scala.reflect.internal.pickling`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScalaPickle {
+    #[debug("{} bytes", data.len())]
+    pub data: Vec<u8>,
+}
+
+/// An entry of a [`ScalaPickle`], such as a name, symbol or type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScalaPickleEntry<'a> {
+    pub tag: u8,
+    #[debug("{} bytes", data.len())]
+    pub data: &'a [u8],
+}
+
+impl ScalaPickle {
+    /// Decodes the `bytes` of a `@ScalaSignature` annotation or the concatenated `bytes` of a
+    /// `@ScalaLongSignature` annotation, or returns `None` if it's neither.
+    pub fn from_annotation(annotation: &AnnotationNode) -> Option<Self> {
+        let bytes = JavaStr::from_str("bytes");
+        let chars: Vec<u8> = if annotation.desc.as_ref() == JavaStr::from_str(SCALA_SIGNATURE_DESC)
+        {
+            annotation
+                .get_string(bytes)?
+                .chars()
+                .map(|c| c.as_u32() as u8)
+                .collect()
+        } else if annotation.desc.as_ref() == JavaStr::from_str(SCALA_LONG_SIGNATURE_DESC) {
+            annotation
+                .get_array_of::<&JavaStr>(bytes)?
+                .into_iter()
+                .flat_map(|string| string.chars())
+                .map(|c| c.as_u32() as u8)
+                .collect()
+        } else {
+            return None;
+        };
+        Some(ScalaPickle {
+            data: decode_7to8(chars.into_iter()),
+        })
+    }
+
+    /// The major and minor version of the pickle format.
+    pub fn version(&self) -> ClassFileResult<(u32, u32)> {
+        let mut index = 0;
+        let major = self.read_nat(&mut index)?;
+        let minor = self.read_nat(&mut index)?;
+        Ok((major, minor))
+    }
+
+    pub fn entries(&self) -> ClassFileResult<Vec<ScalaPickleEntry<'_>>> {
+        let mut index = 0;
+        self.read_nat(&mut index)?;
+        self.read_nat(&mut index)?;
+        let count = self.read_nat(&mut index)?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let tag = self.read_u8(&mut index)?;
+            let len = self.read_nat(&mut index)? as usize;
+            let data = self.data.get(index..index.saturating_add(len)).ok_or(
+                ClassFileError::OutOfBounds {
+                    index: index.saturating_add(len),
+                    len: self.data.len(),
+                },
+            )?;
+            index += len;
+            entries.push(ScalaPickleEntry { tag, data });
+        }
+        Ok(entries)
+    }
+
+    fn read_u8(&self, index: &mut usize) -> ClassFileResult<u8> {
+        let b = self
+            .data
+            .get(*index)
+            .copied()
+            .ok_or(ClassFileError::OutOfBounds {
+                index: *index,
+                len: self.data.len(),
+            })?;
+        *index += 1;
+        Ok(b)
+    }
+
+    /// Reads a natural number, which is big endian with 7 bits per byte.
+    fn read_nat(&self, index: &mut usize) -> ClassFileResult<u32> {
+        let mut value = 0u32;
+        loop {
+            let b = self.read_u8(index)?;
+            value = (value << 7) | (b & 0x7f) as u32;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+/// What the Scala 2 compiler recorded about a class, from its `Scala` and `ScalaSig` attributes
+/// and its `@ScalaSignature` annotation.
+#[derive(Debug, Clone, PartialEq, Eq, IsVariant)]
+pub enum ScalaSignature {
+    /// The class was compiled by Scala, but its signature is pickled in another class. This is
+    /// the case for the module class `Foo$` of an object `Foo`, and for nested classes.
+    Auxiliary,
+    Pickled(ScalaPickle),
+}
+
+impl ScalaSignature {
+    /// Finds the Scala signature of a class, or returns `None` if it wasn't compiled by Scala 2.
+    /// The `Scala` and `ScalaSig` attributes are found whether or not their readers were added.
+    pub fn read<'class, S>(source: S) -> ClassFileResult<Option<Self>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut is_scala = false;
+        let mut attribute_pickle = None;
+        let mut annotation_pickle = None;
+        for event in source.events()? {
+            match event? {
+                ClassEvent::Attributes(attributes) => {
+                    for attribute in attributes {
+                        let attribute: Box<dyn Any> = attribute?;
+                        if let Some(attribute) = attribute.downcast_ref::<UnknownAttribute>() {
+                            if attribute.name == SCALA_ATTRIBUTE {
+                                is_scala = true;
+                            } else if attribute.name == SCALA_SIG_ATTRIBUTE {
+                                attribute_pickle = Some(ScalaPickle {
+                                    data: attribute.data.clone(),
+                                });
+                            }
+                        } else if let Some(attribute) =
+                            attribute.downcast_ref::<ScalaSigAttribute>()
+                        {
+                            attribute_pickle = Some(attribute.pickle.clone());
+                        } else if attribute.is::<ScalaAttribute>() {
+                            is_scala = true;
+                        }
+                    }
+                }
+                ClassEvent::Annotations(annotations) => {
+                    for annotation in annotations {
+                        if let Some(pickle) = ScalaPickle::from_annotation(&annotation?.annotation)
+                        {
+                            annotation_pickle = Some(pickle);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Since Scala 2.8, the `ScalaSig` attribute only has the version of the pickle in the
+        // annotation.
+        Ok(match annotation_pickle.or(attribute_pickle) {
+            Some(pickle) => Some(ScalaSignature::Pickled(pickle)),
+            None if is_scala => Some(ScalaSignature::Auxiliary),
+            None => None,
+        })
+    }
+}
+
+/// The `Scala` attribute, which marks a class compiled by Scala 2 without a pickled signature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScalaAttribute;
+
+impl Attribute for ScalaAttribute {
+    fn name(&self) -> &JavaStr {
+        JavaStr::from_str(SCALA_ATTRIBUTE)
+    }
+
+    fn copy(&self) -> Box<dyn Attribute> {
+        Box::new(*self)
+    }
+
+    fn write(&self, _constant_pool: &mut ConstantPoolBuilder) -> ClassFileResult<Vec<u8>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Reads the `Scala` attribute as a [`ScalaAttribute`].
+#[derive(Debug, Copy, Clone)]
+pub struct ScalaAttributeReader;
+
+impl AttributeReader for ScalaAttributeReader {
+    fn read<'class>(
+        &self,
+        _name: &JavaStr,
+        _reader: &ClassReader<'class>,
+        _data: ClassBuffer<'class>,
+    ) -> ClassFileResult<Box<dyn Attribute>> {
+        Ok(Box::new(ScalaAttribute))
+    }
+
+    fn copy(&self) -> Box<dyn AttributeReader> {
+        Box::new(*self)
+    }
+}
+
+/// The `ScalaSig` attribute. Since Scala 2.8 it only holds the version of the pickle format, and
+/// the pickle is in the `@ScalaSignature` annotation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScalaSigAttribute {
+    pub pickle: ScalaPickle,
+}
+
+impl Attribute for ScalaSigAttribute {
+    fn name(&self) -> &JavaStr {
+        JavaStr::from_str(SCALA_SIG_ATTRIBUTE)
+    }
+
+    fn copy(&self) -> Box<dyn Attribute> {
+        Box::new(self.clone())
+    }
+
+    fn write(&self, _constant_pool: &mut ConstantPoolBuilder) -> ClassFileResult<Vec<u8>> {
+        Ok(self.pickle.data.clone())
+    }
+}
+
+/// Reads the `ScalaSig` attribute as a [`ScalaSigAttribute`].
+#[derive(Debug, Copy, Clone)]
+pub struct ScalaSigAttributeReader;
+
+impl AttributeReader for ScalaSigAttributeReader {
+    fn read<'class>(
+        &self,
+        _name: &JavaStr,
+        _reader: &ClassReader<'class>,
+        data: ClassBuffer<'class>,
+    ) -> ClassFileResult<Box<dyn Attribute>> {
+        let pickle = ScalaPickle {
+            data: data.read_bytes(0, data.len())?.to_vec(),
+        };
+        pickle.version()?;
+        Ok(Box::new(ScalaSigAttribute { pickle }))
+    }
+
+    fn copy(&self) -> Box<dyn AttributeReader> {
+        Box::new(*self)
+    }
+}
+
+/// Decodes strings that were encoded 7 bits per character, so that they never contain a zero
+/// byte. Each character was also incremented by one, modulo 128. This is the encoding of the
+/// pickles of Scala, and of the metadata of Kotlin which copied it.
+pub(crate) fn decode_7to8(data: impl Iterator<Item = u8>) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    for b in data {
+        bits |= ((b.wrapping_add(0x7f) & 0x7f) as u32) << bit_count;
+        bit_count += 7;
+        if bit_count >= 8 {
+            result.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::AnnotationBuilder;
+    use crate::{
+        buffer_class_events, AnnotationEvent, Attribute, ClassEvent, ClassReader, ClassReaderFlags,
+        ClassWriter, ClassWriterFlags, EventBuffer, ScalaAttribute, ScalaAttributeReader,
+        ScalaPickle, ScalaPickleEntry, ScalaSigAttribute, ScalaSigAttributeReader, ScalaSignature,
+    };
+    use java_string::{JavaCodePoint, JavaStr, JavaString};
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    const PICKLE: [u8; 13] = [5, 0, 2, 1, 3, b'F', b'o', b'o', 2, 3, b'b', b'a', b'r'];
+
+    fn str(value: &str) -> &JavaStr {
+        JavaStr::from_str(value)
+    }
+
+    fn encode(data: &[u8]) -> JavaString {
+        let mut result = Vec::new();
+        let (mut bits, mut bit_count) = (0u32, 0);
+        for &b in data {
+            bits |= (b as u32) << bit_count;
+            bit_count += 8;
+            while bit_count >= 7 {
+                result.push((bits & 0x7f) + 1);
+                bits >>= 7;
+                bit_count -= 7;
+            }
+        }
+        result.push(bits + 1);
+        result
+            .into_iter()
+            .map(|c| JavaCodePoint::from_u32(c & 0x7f).unwrap())
+            .collect()
+    }
+
+    fn scala_class(attributes: Vec<Box<dyn Attribute>>, pickle_in_annotation: bool) -> Vec<u8> {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let mut events = buffer_class_events(&reader).unwrap();
+        events
+            .0
+            .push(ClassEvent::Attributes(EventBuffer(attributes)));
+        if pickle_in_annotation {
+            let annotation = AnnotationBuilder::new(str("Lscala/reflect/ScalaSignature;"))
+                .value(str("bytes"), Cow::<JavaStr>::Owned(encode(&PICKLE)))
+                .build();
+            events.0.push(ClassEvent::Annotations(EventBuffer(vec![
+                AnnotationEvent {
+                    visible: true,
+                    annotation,
+                },
+            ])));
+        }
+        ClassWriter::new(ClassWriterFlags::None)
+            .write(events)
+            .unwrap()
+    }
+
+    fn read_signature(data: &[u8], add_readers: bool) -> Option<ScalaSignature> {
+        let mut reader = ClassReader::new(data, ClassReaderFlags::None).unwrap();
+        if add_readers {
+            reader.add_attribute_reader("Scala", ScalaAttributeReader);
+            reader.add_attribute_reader("ScalaSig", ScalaSigAttributeReader);
+        }
+        ScalaSignature::read(&reader).unwrap()
+    }
+
+    #[test]
+    fn test_scala_pickle() {
+        let pickle = ScalaPickle {
+            data: PICKLE.to_vec(),
+        };
+        assert_eq!((5, 0), pickle.version().unwrap());
+        assert_eq!(
+            vec![
+                ScalaPickleEntry {
+                    tag: 1,
+                    data: b"Foo",
+                },
+                ScalaPickleEntry {
+                    tag: 2,
+                    data: b"bar",
+                },
+            ],
+            pickle.entries().unwrap()
+        );
+
+        let encoded = encode(&PICKLE);
+        let (first, second) = encoded.split_at(7);
+        let annotation = AnnotationBuilder::new(str("Lscala/reflect/ScalaLongSignature;"))
+            .array(str("bytes"), [first, second])
+            .build();
+        assert_eq!(Some(pickle), ScalaPickle::from_annotation(&annotation));
+
+        let truncated = ScalaPickle {
+            data: PICKLE[..10].to_vec(),
+        };
+        assert!(truncated.entries().is_err());
+    }
+
+    #[test]
+    fn test_scala_signature() {
+        let marker = ScalaPickle {
+            data: vec![5, 0, 0],
+        };
+        let scala_sig = || -> Vec<Box<dyn Attribute>> {
+            vec![Box::new(ScalaSigAttribute {
+                pickle: marker.clone(),
+            })]
+        };
+        let class = scala_class(scala_sig(), true);
+        let expected = ScalaSignature::Pickled(ScalaPickle {
+            data: PICKLE.to_vec(),
+        });
+        assert_eq!(Some(&expected), read_signature(&class, true).as_ref());
+        assert_eq!(Some(&expected), read_signature(&class, false).as_ref());
+
+        let mut reader = ClassReader::new(&class, ClassReaderFlags::None).unwrap();
+        reader.add_attribute_reader("ScalaSig", ScalaSigAttributeReader);
+        let rewritten = ClassWriter::new(ClassWriterFlags::None)
+            .write(&reader)
+            .unwrap();
+        assert_eq!(Some(expected), read_signature(&rewritten, false));
+
+        // without the annotation, only the pickle in the attribute is left
+        let class = scala_class(scala_sig(), false);
+        assert_eq!(
+            Some(ScalaSignature::Pickled(marker.clone())),
+            read_signature(&class, true)
+        );
+
+        let class = scala_class(vec![Box::new(ScalaAttribute)], false);
+        assert_eq!(
+            Some(ScalaSignature::Auxiliary),
+            read_signature(&class, true)
+        );
+        assert_eq!(
+            Some(ScalaSignature::Auxiliary),
+            read_signature(&class, false)
+        );
+
+        assert_eq!(None, read_signature(include_class!("HelloWorld"), true));
+    }
+}
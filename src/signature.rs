@@ -0,0 +1,660 @@
+use crate::{ClassFileError, ClassFileResult};
+use java_string::{JavaStr, JavaString};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("expected {expected} at index {index} of signature {signature}")]
+pub struct SignatureError {
+    pub signature: JavaString,
+    /// The byte index of the offending character, or the length of the signature if it ended too
+    /// early.
+    pub index: usize,
+    pub expected: &'static str,
+}
+
+/// The kind of a bounded type argument, see [`SignatureVisitor::visit_type_argument`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeArgumentKind {
+    /// An exact type argument, such as `String` in `List<String>`.
+    Exact,
+    /// A type argument with an upper bound, such as `? extends Number`, written as `+`.
+    Extends,
+    /// A type argument with a lower bound, such as `? super Integer`, written as `-`.
+    Super,
+}
+
+/// Receives the parts of a generic signature from a [`SignatureReader`], in the order they appear
+/// in the signature.
+///
+/// Calls which introduce a type, such as [`visit_superclass`](SignatureVisitor::visit_superclass)
+/// or [`visit_parameter_type`](SignatureVisitor::visit_parameter_type), are followed by the calls
+/// for that type. A type is either a single [`visit_base_type`](SignatureVisitor::visit_base_type)
+/// or [`visit_type_variable`](SignatureVisitor::visit_type_variable), a
+/// [`visit_array_type`](SignatureVisitor::visit_array_type) followed by the element type, or a
+/// [`visit_class_type`](SignatureVisitor::visit_class_type) followed by its type arguments and
+/// inner classes and ended by [`visit_end`](SignatureVisitor::visit_end).
+pub trait SignatureVisitor<'a> {
+    /// A type parameter of a generic class or method, followed by its bounds.
+    fn visit_formal_type_parameter(&mut self, _name: &'a JavaStr) {}
+
+    /// The class bound of the last type parameter, followed by its type. Type parameters bounded
+    /// only by interfaces have no class bound.
+    fn visit_class_bound(&mut self) {}
+
+    /// An interface bound of the last type parameter, followed by its type.
+    fn visit_interface_bound(&mut self) {}
+
+    /// The superclass of a class signature, followed by its type.
+    fn visit_superclass(&mut self) {}
+
+    /// An interface of a class signature, followed by its type.
+    fn visit_interface(&mut self) {}
+
+    /// A parameter of a method signature, followed by its type.
+    fn visit_parameter_type(&mut self) {}
+
+    /// The return type of a method signature, followed by its type, which may be the base type
+    /// `V`.
+    fn visit_return_type(&mut self) {}
+
+    /// A thrown exception of a method signature, followed by its type.
+    fn visit_exception_type(&mut self) {}
+
+    /// A primitive type, given as its descriptor character such as `I`.
+    fn visit_base_type(&mut self, _descriptor: char) {}
+
+    fn visit_type_variable(&mut self, _name: &'a JavaStr) {}
+
+    /// An array type, followed by its element type.
+    fn visit_array_type(&mut self) {}
+
+    /// A class type, given as its internal name. Followed by its type arguments, then any inner
+    /// classes it is qualified with, and then [`visit_end`](SignatureVisitor::visit_end).
+    fn visit_class_type(&mut self, _name: &'a JavaStr) {}
+
+    /// An inner class of the last class type, given as its simple name, followed by its own type
+    /// arguments.
+    fn visit_inner_class_type(&mut self, _name: &'a JavaStr) {}
+
+    /// An unbounded wildcard type argument, `?` in Java and `*` in signatures.
+    fn visit_unbounded_type_argument(&mut self) {}
+
+    /// A type argument of the last class type, followed by its type.
+    fn visit_type_argument(&mut self, _kind: TypeArgumentKind) {}
+
+    /// The end of the last class type.
+    fn visit_end(&mut self) {}
+}
+
+/// Reads a class, method or field signature, validating it and passing its parts to a
+/// [`SignatureVisitor`]. The visitor is called while the signature is read, so it may have received
+/// part of an invalid signature by the time the error is returned.
+#[derive(Debug, Clone)]
+pub struct SignatureReader<'a> {
+    signature: &'a JavaStr,
+    index: usize,
+}
+
+impl<'a> SignatureReader<'a> {
+    pub fn new(signature: &'a JavaStr) -> Self {
+        SignatureReader {
+            signature,
+            index: 0,
+        }
+    }
+
+    /// Reads a class signature, such as `<T:Ljava/lang/Object;>Ljava/lang/Object;`.
+    pub fn read_class<V>(mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        self.type_parameters(visitor)?;
+        visitor.visit_superclass();
+        self.class_type(visitor)?;
+        while self.peek().is_some() {
+            visitor.visit_interface();
+            self.class_type(visitor)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a method signature, such as `<T:Ljava/lang/Object;>(TT;)V^Ljava/io/IOException;`.
+    pub fn read_method<V>(mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        self.type_parameters(visitor)?;
+        self.expect(b'(', "`(`")?;
+        while self.peek() != Some(b')') {
+            visitor.visit_parameter_type();
+            self.java_type(visitor, "a parameter type or `)`")?;
+        }
+        self.index += 1;
+        visitor.visit_return_type();
+        if self.peek() == Some(b'V') {
+            self.index += 1;
+            visitor.visit_base_type('V');
+        } else {
+            self.java_type(visitor, "a return type")?;
+        }
+        while self.peek().is_some() {
+            self.expect(b'^', "`^`")?;
+            visitor.visit_exception_type();
+            if self.peek() == Some(b'T') {
+                self.type_variable(visitor)?;
+            } else {
+                self.class_type(visitor)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a field signature, such as `Ljava/util/List<Ljava/lang/String;>;`, which is also the
+    /// format of the signatures of record components and local variables.
+    pub fn read_field<V>(mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        self.reference_type(visitor)?;
+        if self.peek().is_some() {
+            return Err(self.error("the end of the signature"));
+        }
+        Ok(())
+    }
+
+    fn type_parameters<V>(&mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        if self.peek() != Some(b'<') {
+            return Ok(());
+        }
+        self.index += 1;
+        if self.peek() == Some(b'>') {
+            return Err(self.error("a type parameter"));
+        }
+        while self.peek() != Some(b'>') {
+            let name = self.identifier(b".;[/<>:", "a type parameter name")?;
+            visitor.visit_formal_type_parameter(name);
+            self.expect(b':', "`:`")?;
+            if matches!(self.peek(), Some(b'L' | b'T' | b'[')) {
+                visitor.visit_class_bound();
+                self.reference_type(visitor)?;
+            }
+            while self.peek() == Some(b':') {
+                self.index += 1;
+                visitor.visit_interface_bound();
+                self.reference_type(visitor)?;
+            }
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn java_type<V>(&mut self, visitor: &mut V, expected: &'static str) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        match self.peek() {
+            Some(b @ (b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z')) => {
+                self.index += 1;
+                visitor.visit_base_type(char::from(b));
+                Ok(())
+            }
+            Some(b'L' | b'T' | b'[') => self.reference_type(visitor),
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn reference_type<V>(&mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        match self.peek() {
+            Some(b'L') => self.class_type(visitor),
+            Some(b'T') => self.type_variable(visitor),
+            Some(b'[') => {
+                self.index += 1;
+                visitor.visit_array_type();
+                self.java_type(visitor, "an array element type")
+            }
+            _ => Err(self.error("a reference type")),
+        }
+    }
+
+    fn type_variable<V>(&mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        self.expect(b'T', "`T`")?;
+        let name = self.identifier(b".;[/<>:", "a type variable name")?;
+        self.expect(b';', "`;`")?;
+        visitor.visit_type_variable(name);
+        Ok(())
+    }
+
+    fn class_type<V>(&mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        self.expect(b'L', "a class type")?;
+        let name = self.identifier(b".;[<>:", "a class name")?;
+        visitor.visit_class_type(name);
+        loop {
+            if self.peek() == Some(b'<') {
+                self.type_arguments(visitor)?;
+            }
+            if self.peek() != Some(b'.') {
+                break;
+            }
+            self.index += 1;
+            let name = self.identifier(b".;[/<>:", "an inner class name")?;
+            visitor.visit_inner_class_type(name);
+        }
+        self.expect(b';', "`;`")?;
+        visitor.visit_end();
+        Ok(())
+    }
+
+    fn type_arguments<V>(&mut self, visitor: &mut V) -> ClassFileResult<()>
+    where
+        V: SignatureVisitor<'a>,
+    {
+        self.index += 1;
+        if self.peek() == Some(b'>') {
+            return Err(self.error("a type argument"));
+        }
+        while self.peek() != Some(b'>') {
+            let kind = match self.peek() {
+                Some(b'*') => {
+                    self.index += 1;
+                    visitor.visit_unbounded_type_argument();
+                    continue;
+                }
+                Some(b'+') => TypeArgumentKind::Extends,
+                Some(b'-') => TypeArgumentKind::Super,
+                _ => TypeArgumentKind::Exact,
+            };
+            if kind != TypeArgumentKind::Exact {
+                self.index += 1;
+            }
+            visitor.visit_type_argument(kind);
+            self.reference_type(visitor)?;
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.signature.as_bytes().get(self.index).copied()
+    }
+
+    fn expect(&mut self, b: u8, expected: &'static str) -> ClassFileResult<()> {
+        if self.peek() != Some(b) {
+            return Err(self.error(expected));
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Reads a non-empty identifier up to the next of the given delimiters, or the end of the
+    /// signature.
+    fn identifier(
+        &mut self,
+        delimiters: &[u8],
+        expected: &'static str,
+    ) -> ClassFileResult<&'a JavaStr> {
+        let start = self.index;
+        while self.peek().is_some_and(|b| !delimiters.contains(&b)) {
+            self.index += 1;
+        }
+        if self.index == start {
+            return Err(self.error(expected));
+        }
+        Ok(&self.signature[start..self.index])
+    }
+
+    fn error(&self, expected: &'static str) -> ClassFileError {
+        SignatureError {
+            signature: self.signature.to_owned(),
+            index: self.index,
+            expected,
+        }
+        .into()
+    }
+}
+
+/// Builds a signature from the calls of a [`SignatureVisitor`], such as those of a
+/// [`SignatureReader`] or a transform which changes the generic types of a class. The calls must be
+/// made in the order described by [`SignatureVisitor`], which is not checked.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureWriter {
+    output: JavaString,
+    /// Whether type parameters have been written without the closing `>`.
+    in_type_parameters: bool,
+    has_parameters: bool,
+    /// For each class type being written, whether it has type arguments without the closing `>`.
+    type_arguments: Vec<bool>,
+}
+
+impl SignatureWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn signature(&self) -> &JavaStr {
+        &self.output
+    }
+
+    pub fn into_signature(self) -> JavaString {
+        self.output
+    }
+
+    fn end_type_parameters(&mut self) {
+        if self.in_type_parameters {
+            self.in_type_parameters = false;
+            self.output.push('>');
+        }
+    }
+
+    fn start_type_argument(&mut self) {
+        if let Some(has_arguments) = self.type_arguments.last_mut() {
+            if !*has_arguments {
+                *has_arguments = true;
+                self.output.push('<');
+            }
+        }
+    }
+
+    fn end_type_arguments(&mut self) {
+        if let Some(has_arguments) = self.type_arguments.last_mut() {
+            if *has_arguments {
+                *has_arguments = false;
+                self.output.push('>');
+            }
+        }
+    }
+}
+
+impl<'a> SignatureVisitor<'a> for SignatureWriter {
+    fn visit_formal_type_parameter(&mut self, name: &'a JavaStr) {
+        if !self.in_type_parameters {
+            self.in_type_parameters = true;
+            self.output.push('<');
+        }
+        self.output.push_java_str(name);
+        self.output.push(':');
+    }
+
+    fn visit_interface_bound(&mut self) {
+        self.output.push(':');
+    }
+
+    fn visit_superclass(&mut self) {
+        self.end_type_parameters();
+    }
+
+    fn visit_parameter_type(&mut self) {
+        self.end_type_parameters();
+        if !self.has_parameters {
+            self.has_parameters = true;
+            self.output.push('(');
+        }
+    }
+
+    fn visit_return_type(&mut self) {
+        self.end_type_parameters();
+        if !self.has_parameters {
+            self.has_parameters = true;
+            self.output.push('(');
+        }
+        self.output.push(')');
+    }
+
+    fn visit_exception_type(&mut self) {
+        self.output.push('^');
+    }
+
+    fn visit_base_type(&mut self, descriptor: char) {
+        self.output.push(descriptor);
+    }
+
+    fn visit_type_variable(&mut self, name: &'a JavaStr) {
+        self.output.push('T');
+        self.output.push_java_str(name);
+        self.output.push(';');
+    }
+
+    fn visit_array_type(&mut self) {
+        self.output.push('[');
+    }
+
+    fn visit_class_type(&mut self, name: &'a JavaStr) {
+        self.output.push('L');
+        self.output.push_java_str(name);
+        self.type_arguments.push(false);
+    }
+
+    fn visit_inner_class_type(&mut self, name: &'a JavaStr) {
+        self.end_type_arguments();
+        self.output.push('.');
+        self.output.push_java_str(name);
+    }
+
+    fn visit_unbounded_type_argument(&mut self) {
+        self.start_type_argument();
+        self.output.push('*');
+    }
+
+    fn visit_type_argument(&mut self, kind: TypeArgumentKind) {
+        self.start_type_argument();
+        match kind {
+            TypeArgumentKind::Exact => {}
+            TypeArgumentKind::Extends => self.output.push('+'),
+            TypeArgumentKind::Super => self.output.push('-'),
+        }
+    }
+
+    fn visit_end(&mut self) {
+        self.end_type_arguments();
+        self.type_arguments.pop();
+        self.output.push(';');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassFileError, SignatureError, SignatureReader, SignatureVisitor, SignatureWriter,
+        TypeArgumentKind,
+    };
+    use java_string::JavaStr;
+
+    #[derive(Default)]
+    struct RecordingVisitor(Vec<String>);
+
+    impl<'a> SignatureVisitor<'a> for RecordingVisitor {
+        fn visit_formal_type_parameter(&mut self, name: &'a JavaStr) {
+            self.0.push(format!("<{name}"));
+        }
+
+        fn visit_class_bound(&mut self) {
+            self.0.push(":".to_owned());
+        }
+
+        fn visit_interface_bound(&mut self) {
+            self.0.push("::".to_owned());
+        }
+
+        fn visit_superclass(&mut self) {
+            self.0.push("extends".to_owned());
+        }
+
+        fn visit_interface(&mut self) {
+            self.0.push("implements".to_owned());
+        }
+
+        fn visit_parameter_type(&mut self) {
+            self.0.push("param".to_owned());
+        }
+
+        fn visit_return_type(&mut self) {
+            self.0.push("return".to_owned());
+        }
+
+        fn visit_exception_type(&mut self) {
+            self.0.push("throws".to_owned());
+        }
+
+        fn visit_base_type(&mut self, descriptor: char) {
+            self.0.push(descriptor.to_string());
+        }
+
+        fn visit_type_variable(&mut self, name: &'a JavaStr) {
+            self.0.push(format!("T{name}"));
+        }
+
+        fn visit_array_type(&mut self) {
+            self.0.push("[".to_owned());
+        }
+
+        fn visit_class_type(&mut self, name: &'a JavaStr) {
+            self.0.push(format!("L{name}"));
+        }
+
+        fn visit_inner_class_type(&mut self, name: &'a JavaStr) {
+            self.0.push(format!(".{name}"));
+        }
+
+        fn visit_unbounded_type_argument(&mut self) {
+            self.0.push("*".to_owned());
+        }
+
+        fn visit_type_argument(&mut self, kind: TypeArgumentKind) {
+            self.0.push(format!("{kind:?}"));
+        }
+
+        fn visit_end(&mut self) {
+            self.0.push(";".to_owned());
+        }
+    }
+
+    #[test]
+    fn test_read_signature() {
+        let mut visitor = RecordingVisitor::default();
+        SignatureReader::new(JavaStr::from_str(
+            "<T:Ljava/lang/Object;U::Ljava/lang/Comparable<-TT;>;>La<TT;>.b<*+[I>;Lc;",
+        ))
+        .read_class(&mut visitor)
+        .unwrap();
+        assert_eq!(
+            vec![
+                "<T",
+                ":",
+                "Ljava/lang/Object",
+                ";",
+                "<U",
+                "::",
+                "Ljava/lang/Comparable",
+                "Super",
+                "TT",
+                ";",
+                "extends",
+                "La",
+                "Exact",
+                "TT",
+                ".b",
+                "*",
+                "Extends",
+                "[",
+                "I",
+                ";",
+                "implements",
+                "Lc",
+                ";",
+            ],
+            visitor.0
+        );
+
+        let mut visitor = RecordingVisitor::default();
+        SignatureReader::new(JavaStr::from_str("<E:Ljava/lang/Exception;>([TE;J)V^TE;"))
+            .read_method(&mut visitor)
+            .unwrap();
+        assert_eq!(
+            vec![
+                "<E",
+                ":",
+                "Ljava/lang/Exception",
+                ";",
+                "param",
+                "[",
+                "TE",
+                "param",
+                "J",
+                "return",
+                "V",
+                "throws",
+                "TE",
+            ],
+            visitor.0
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_signature() {
+        let error = |kind, signature| {
+            let reader = SignatureReader::new(JavaStr::from_str(signature));
+            let visitor = &mut RecordingVisitor::default();
+            let result = match kind {
+                "class" => reader.read_class(visitor),
+                "method" => reader.read_method(visitor),
+                _ => reader.read_field(visitor),
+            };
+            match result {
+                Err(ClassFileError::BadSignature(SignatureError {
+                    index, expected, ..
+                })) => (index, expected),
+                result => panic!("expected an error, got {result:?}"),
+            }
+        };
+        assert_eq!(
+            (1, "a type parameter"),
+            error("class", "<>Ljava/lang/Object;")
+        );
+        assert_eq!((2, "`:`"), error("class", "<T;>Ljava/lang/Object;"));
+        assert_eq!((17, "`;`"), error("field", "Ljava/lang/Object"));
+        assert_eq!((1, "a parameter type or `)`"), error("method", "(V)V"));
+        assert_eq!((3, "`^`"), error("method", "()VI"));
+        assert_eq!((6, "a type argument"), error("field", "Ljava<>;"));
+        assert_eq!((3, "the end of the signature"), error("field", "TT;I"));
+    }
+
+    #[test]
+    fn test_write_signature() {
+        let signatures = [
+            (
+                "class",
+                "<T:Ljava/lang/Object;U::Ljava/lang/Comparable<-TT;>;>La<TT;>.b<*+[I>;Lc;",
+            ),
+            ("class", "Ljava/lang/Enum<LColor;>;"),
+            (
+                "method",
+                "<E:Ljava/lang/Exception;>([TE;J)V^TE;^Ljava/io/IOException;",
+            ),
+            ("method", "()La<Ljava/lang/String;>.b.c<TT;>;"),
+            ("field", "[[Ljava/util/Map<*Ljava/util/List<-TT;>;>;"),
+        ];
+        for (kind, signature) in signatures {
+            let reader = SignatureReader::new(JavaStr::from_str(signature));
+            let mut writer = SignatureWriter::new();
+            match kind {
+                "class" => reader.read_class(&mut writer),
+                "method" => reader.read_method(&mut writer),
+                _ => reader.read_field(&mut writer),
+            }
+            .unwrap();
+            assert_eq!(signature, writer.signature());
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{ClassFileError, ClassFileResult};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A source map in the SMAP format of JSR-045, as found in the `SourceDebugExtension` attribute of
+/// classes generated from other languages, such as Kotlin inline functions and JSPs. It maps the
+/// line numbers of the class back to the lines of the original sources.
+///
+/// Parse one with [`str::parse`] and write one with [`ToString::to_string`]. Embedded source maps
+/// aren't supported, and vendor sections are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceMap {
+    /// The name of the generated source file, usually the same as in the `SourceFile` attribute.
+    pub output_file: String,
+    pub default_stratum: String,
+    pub strata: Vec<SourceMapStratum>,
+}
+
+/// One view of the original sources, such as `Kotlin` or `JSP`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceMapStratum {
+    pub name: String,
+    pub files: Vec<SourceMapFile>,
+    pub lines: Vec<SourceMapLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceMapFile {
+    pub id: u32,
+    pub name: String,
+    /// The path of the file relative to a source root, if known.
+    pub path: Option<String>,
+}
+
+/// Maps `repeat_count` lines from `input_start_line` in a file to output lines. Each input line
+/// maps to `output_line_increment` output lines, starting from `output_start_line`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceMapLine {
+    pub input_start_line: u32,
+    pub file_id: u32,
+    pub repeat_count: u32,
+    pub output_start_line: u32,
+    pub output_line_increment: u32,
+}
+
+impl SourceMap {
+    pub fn stratum(&self, name: &str) -> Option<&SourceMapStratum> {
+        self.strata.iter().find(|stratum| stratum.name == name)
+    }
+
+    /// Maps a line number of the class to a file and line number in the default stratum.
+    pub fn map_line(&self, output_line: u32) -> Option<(&SourceMapFile, u32)> {
+        self.stratum(&self.default_stratum)?.map_line(output_line)
+    }
+}
+
+impl SourceMapStratum {
+    pub fn file(&self, id: u32) -> Option<&SourceMapFile> {
+        self.files.iter().find(|file| file.id == id)
+    }
+
+    /// Maps a line number of the class to a file and line number in this stratum. If several
+    /// input lines map to the output line, the first is returned.
+    pub fn map_line(&self, output_line: u32) -> Option<(&SourceMapFile, u32)> {
+        self.lines.iter().find_map(|line| {
+            let offset = output_line.checked_sub(line.output_start_line)?;
+            let input_offset = match offset.checked_div(line.output_line_increment) {
+                Some(input_offset) => input_offset,
+                // every input line maps to the same output line
+                None if offset == 0 => 0,
+                None => return None,
+            };
+            if input_offset >= line.repeat_count {
+                return None;
+            }
+            Some((
+                self.file(line.file_id)?,
+                line.input_start_line + input_offset,
+            ))
+        })
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Section {
+    None,
+    Files,
+    Lines,
+    Ignored,
+}
+
+impl FromStr for SourceMap {
+    type Err = ClassFileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(index, line)| (index + 1, line));
+        let mut header = |reason| {
+            lines
+                .next()
+                .map(|(_, line)| line.trim().to_owned())
+                .ok_or_else(|| bad_source_map(s.lines().count() + 1, reason))
+        };
+        if header("expected `SMAP`")? != "SMAP" {
+            return Err(bad_source_map(1, "expected `SMAP`"));
+        }
+        let mut source_map = SourceMap {
+            output_file: header("expected output file name")?,
+            default_stratum: header("expected default stratum")?,
+            strata: Vec::new(),
+        };
+
+        let mut section = Section::None;
+        let mut file_id = 0;
+        let mut pending_file: Option<SourceMapFile> = None;
+        for (line_number, line) in lines {
+            if let Some(file) = pending_file.take() {
+                let stratum = source_map.strata.last_mut().unwrap();
+                stratum.files.push(SourceMapFile {
+                    path: Some(line.trim().to_owned()),
+                    ..file
+                });
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('*') {
+                let (kind, rest) = header.split_at(header.chars().next().map_or(0, char::len_utf8));
+                section = match kind {
+                    "S" => {
+                        let name = rest.trim();
+                        if name.is_empty() {
+                            return Err(bad_source_map(line_number, "expected stratum name"));
+                        }
+                        source_map.strata.push(SourceMapStratum {
+                            name: name.to_owned(),
+                            files: Vec::new(),
+                            lines: Vec::new(),
+                        });
+                        file_id = 0;
+                        Section::None
+                    }
+                    "F" | "L" if source_map.strata.is_empty() => {
+                        return Err(bad_source_map(line_number, "section outside of a stratum"));
+                    }
+                    "F" => Section::Files,
+                    "L" => Section::Lines,
+                    "E" => Section::None,
+                    "O" | "C" => {
+                        return Err(bad_source_map(
+                            line_number,
+                            "embedded source maps aren't supported",
+                        ));
+                    }
+                    _ => Section::Ignored,
+                };
+                continue;
+            }
+
+            match section {
+                Section::Files => {
+                    let (has_path, file) = match line.trim_start().strip_prefix('+') {
+                        Some(file) => (true, file),
+                        None => (false, line),
+                    };
+                    let (id, name) = file
+                        .trim()
+                        .split_once(' ')
+                        .ok_or_else(|| bad_source_map(line_number, "expected file id and name"))?;
+                    let file = SourceMapFile {
+                        id: parse_number(id, line_number)?,
+                        name: name.trim().to_owned(),
+                        path: None,
+                    };
+                    if has_path {
+                        pending_file = Some(file);
+                    } else {
+                        source_map.strata.last_mut().unwrap().files.push(file);
+                    }
+                }
+                Section::Lines => {
+                    let line_info = parse_line_info(line.trim(), &mut file_id, line_number)?;
+                    source_map.strata.last_mut().unwrap().lines.push(line_info);
+                }
+                Section::Ignored => {}
+                Section::None if line.trim().is_empty() => {}
+                Section::None => {
+                    return Err(bad_source_map(line_number, "expected section header"));
+                }
+            }
+        }
+        if pending_file.is_some() {
+            return Err(bad_source_map(s.lines().count() + 1, "expected file path"));
+        }
+        Ok(source_map)
+    }
+}
+
+/// Parses `InputStartLine[#LineFileID][,RepeatCount]:OutputStartLine[,OutputLineIncrement]`. The
+/// file id defaults to the one of the previous line.
+fn parse_line_info(
+    line: &str,
+    file_id: &mut u32,
+    line_number: usize,
+) -> ClassFileResult<SourceMapLine> {
+    let (input, output) = line
+        .split_once(':')
+        .ok_or_else(|| bad_source_map(line_number, "expected `:`"))?;
+    let (input, repeat_count) = match input.split_once(',') {
+        Some((input, repeat_count)) => (input, parse_number(repeat_count, line_number)?),
+        None => (input, 1),
+    };
+    let input_start_line = match input.split_once('#') {
+        Some((input, id)) => {
+            *file_id = parse_number(id, line_number)?;
+            input
+        }
+        None => input,
+    };
+    let (output_start_line, output_line_increment) = match output.split_once(',') {
+        Some((output, increment)) => (output, parse_number(increment, line_number)?),
+        None => (output, 1),
+    };
+    Ok(SourceMapLine {
+        input_start_line: parse_number(input_start_line, line_number)?,
+        file_id: *file_id,
+        repeat_count,
+        output_start_line: parse_number(output_start_line, line_number)?,
+        output_line_increment,
+    })
+}
+
+fn parse_number(number: &str, line_number: usize) -> ClassFileResult<u32> {
+    number
+        .trim()
+        .parse()
+        .map_err(|_| bad_source_map(line_number, "expected number"))
+}
+
+fn bad_source_map(line: usize, reason: &'static str) -> ClassFileError {
+    ClassFileError::BadSourceMap { line, reason }
+}
+
+impl Display for SourceMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "SMAP")?;
+        writeln!(f, "{}", self.output_file)?;
+        writeln!(f, "{}", self.default_stratum)?;
+        for stratum in &self.strata {
+            writeln!(f, "*S {}", stratum.name)?;
+            writeln!(f, "*F")?;
+            for file in &stratum.files {
+                match &file.path {
+                    Some(path) => writeln!(f, "+ {} {}\n{path}", file.id, file.name)?,
+                    None => writeln!(f, "{} {}", file.id, file.name)?,
+                }
+            }
+            writeln!(f, "*L")?;
+            let mut file_id = 0;
+            for line in &stratum.lines {
+                write!(f, "{}", line.input_start_line)?;
+                if line.file_id != file_id {
+                    write!(f, "#{}", line.file_id)?;
+                    file_id = line.file_id;
+                }
+                if line.repeat_count != 1 {
+                    write!(f, ",{}", line.repeat_count)?;
+                }
+                write!(f, ":{}", line.output_start_line)?;
+                if line.output_line_increment != 1 {
+                    write!(f, ",{}", line.output_line_increment)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        writeln!(f, "*E")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassFileError, SourceMap, SourceMapLine};
+
+    const KOTLIN_SMAP: &str = "SMAP
+Main.kt
+Kotlin
+*S Kotlin
+*F
++ 1 Main.kt
+com/example/MainKt
++ 2 Util.kt
+com/example/UtilKt
+*L
+1#1,10:1
+5#2,3:11
+20#1:14,2
+*E
+*S KotlinDebug
+*F
++ 1 Main.kt
+com/example/MainKt
+*L
+4#1:11,3
+*E
+";
+
+    fn lines(
+        source_map: &SourceMap,
+        stratum: &str,
+        output_lines: &[u32],
+    ) -> Vec<Option<(String, u32)>> {
+        let stratum = source_map.stratum(stratum).unwrap();
+        output_lines
+            .iter()
+            .map(|&line| {
+                stratum
+                    .map_line(line)
+                    .map(|(file, line)| (file.name.clone(), line))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_source_map() {
+        let source_map: SourceMap = KOTLIN_SMAP.parse().unwrap();
+        assert_eq!("Main.kt", source_map.output_file);
+        assert_eq!("Kotlin", source_map.default_stratum);
+        let kotlin = source_map.stratum("Kotlin").unwrap();
+        assert_eq!(Some("com/example/UtilKt"), kotlin.files[1].path.as_deref());
+        assert_eq!(
+            SourceMapLine {
+                input_start_line: 20,
+                file_id: 1,
+                repeat_count: 1,
+                output_start_line: 14,
+                output_line_increment: 2,
+            },
+            kotlin.lines[2]
+        );
+
+        let main = |line| Some((String::from("Main.kt"), line));
+        let util = |line| Some((String::from("Util.kt"), line));
+        assert_eq!(
+            vec![
+                main(1),
+                main(10),
+                util(5),
+                util(7),
+                main(20),
+                main(20),
+                None
+            ],
+            lines(&source_map, "Kotlin", &[1, 10, 11, 13, 14, 15, 16])
+        );
+        assert_eq!(
+            vec![main(4), main(4), None],
+            lines(&source_map, "KotlinDebug", &[11, 13, 14])
+        );
+        assert_eq!(
+            Some("Util.kt"),
+            source_map.map_line(12).map(|(file, _)| file.name.as_str())
+        );
+
+        let written = source_map.to_string();
+        assert!(written.contains("\n5#2,3:11\n20#1:14,2\n"));
+        assert_eq!(source_map, written.parse().unwrap());
+    }
+
+    #[test]
+    fn test_bad_source_map() {
+        let error = |input: &str| match input.parse::<SourceMap>() {
+            Err(ClassFileError::BadSourceMap { line, .. }) => line,
+            result => panic!("expected bad source map, got {result:?}"),
+        };
+        assert_eq!(1, error("SMP\nA.kt\nKotlin\n"));
+        assert_eq!(3, error("SMAP\nA.kt\n"));
+        assert_eq!(4, error("SMAP\nA.kt\nKotlin\n*F\n"));
+        assert_eq!(7, error("SMAP\nA.kt\nKotlin\n*S Kotlin\n*L\n1:1\n1#x:2\n"));
+        assert_eq!(7, error("SMAP\nA.kt\nKotlin\n*S Kotlin\n*F\n+ 1 A.kt\n"));
+        assert_eq!(4, error("SMAP\nA.jsp\nJSP\n*O A\n"));
+        assert_eq!(5, error("SMAP\nA.kt\nKotlin\n*\u{e9}\n*L\n"));
+    }
+}
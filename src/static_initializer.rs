@@ -0,0 +1,218 @@
+use crate::{
+    buffer_class_events, BufferedClassEvents, BufferedEventProviders, ClassEvent, ClassEventSource,
+    ClassFileResult, ClassMethodEvent, EventBuffer, LabelCreator, MethodAccess, MethodEvent,
+    Opcode,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+/// Appends instructions to the static initializer of a class, creating one if it doesn't exist.
+///
+/// The instructions are created by `insns` with the label creator of the static initializer, and
+/// must not return. They run after the existing static initializer code: each of its `return`
+/// instructions is replaced with a jump to the appended code, which is followed by a single
+/// `return`.
+///
+/// The frames and maxs of the static initializer are removed, so the result should be written with
+/// [`ClassWriterFlags::ComputeFrames`](crate::ClassWriterFlags::ComputeFrames), or
+/// [`ClassWriterFlags::ComputeMaxs`](crate::ClassWriterFlags::ComputeMaxs) for classes which don't
+/// use frames.
+pub fn append_to_static_initializer<'class, S, F>(
+    source: S,
+    insns: F,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+    F: FnOnce(&LabelCreator) -> Vec<MethodEvent<'class, BufferedEventProviders>>,
+{
+    let mut events = buffer_class_events(source)?;
+
+    let static_initializer = events.0.iter_mut().find_map(|event| match event {
+        ClassEvent::Methods(methods) => methods
+            .0
+            .iter_mut()
+            .find(|method| method.name.as_ref() == "<clinit>" && method.desc.as_ref() == "()V"),
+        _ => None,
+    });
+    if let Some(static_initializer) = static_initializer {
+        merge_static_initializer(&mut static_initializer.events.0, insns);
+        return Ok(events);
+    }
+
+    let label_creator = LabelCreator::default();
+    let mut method_events = vec![MethodEvent::Code {
+        label_creator: label_creator.clone(),
+    }];
+    method_events.extend(insns(&label_creator));
+    method_events.push(MethodEvent::Insn(Opcode::Return));
+    let static_initializer = ClassMethodEvent {
+        access: MethodAccess::Static,
+        name: Cow::Borrowed(JavaStr::from_str("<clinit>")),
+        desc: Cow::Borrowed(JavaStr::from_str("()V")),
+        signature: None,
+        exceptions: Vec::new(),
+        events: EventBuffer(method_events),
+    };
+
+    let methods = events.0.iter_mut().find_map(|event| match event {
+        ClassEvent::Methods(methods) => Some(methods),
+        _ => None,
+    });
+    match methods {
+        Some(methods) => methods.0.push(static_initializer),
+        None => events
+            .0
+            .push(ClassEvent::Methods(EventBuffer(vec![static_initializer]))),
+    }
+    Ok(events)
+}
+
+fn merge_static_initializer<'class, F>(
+    events: &mut Vec<MethodEvent<'class, BufferedEventProviders>>,
+    insns: F,
+) where
+    F: FnOnce(&LabelCreator) -> Vec<MethodEvent<'class, BufferedEventProviders>>,
+{
+    events.retain(|event| !matches!(event, MethodEvent::Frame(_) | MethodEvent::Maxs(_)));
+
+    let label_creator = match events.iter().find_map(|event| match event {
+        MethodEvent::Code { label_creator } => Some(label_creator.clone()),
+        _ => None,
+    }) {
+        Some(label_creator) => label_creator,
+        None => {
+            let label_creator = LabelCreator::default();
+            events.push(MethodEvent::Code {
+                label_creator: label_creator.clone(),
+            });
+            label_creator
+        }
+    };
+
+    // the appended code goes after the last instruction, before the tables of the code attribute
+    let mut code_end = events
+        .iter()
+        .rposition(|event| {
+            !matches!(
+                event,
+                MethodEvent::InsnAnnotations(_)
+                    | MethodEvent::LocalVariables(_)
+                    | MethodEvent::LocalVariableAnnotations(_)
+                    | MethodEvent::TryCatchBlocks(_)
+                    | MethodEvent::TryCatchBlockAnnotations(_)
+                    | MethodEvent::CodeAttributes(_)
+            )
+        })
+        .map_or(0, |index| index + 1);
+
+    // a trailing return can fall through into the appended code instead of jumping to it
+    let last_insn = events[..code_end].iter().rposition(|event| {
+        !matches!(
+            event,
+            MethodEvent::Label(_) | MethodEvent::LineNumber { .. }
+        )
+    });
+    if let Some(last_insn) = last_insn {
+        if matches!(events[last_insn], MethodEvent::Insn(Opcode::Return)) {
+            events.remove(last_insn);
+            code_end -= 1;
+        }
+    }
+
+    let appended_code = label_creator.create_label();
+    for event in &mut events[..code_end] {
+        if matches!(event, MethodEvent::Insn(Opcode::Return)) {
+            *event = MethodEvent::JumpInsn {
+                opcode: Opcode::Goto,
+                label: appended_code,
+            };
+        }
+    }
+
+    let mut appended = vec![MethodEvent::Label(appended_code)];
+    appended.extend(insns(&label_creator));
+    appended.push(MethodEvent::Insn(Opcode::Return));
+    events.splice(code_end..code_end, appended);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        append_to_static_initializer, BufferedEventProviders, ClassEvent, ClassEventSource,
+        ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags, LdcConstant, MethodEvent,
+        Opcode,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    fn print(message: &str) -> Vec<MethodEvent<'_, BufferedEventProviders>> {
+        vec![
+            MethodEvent::FieldInsn {
+                opcode: Opcode::GetStatic,
+                owner: Cow::Borrowed(JavaStr::from_str("java/lang/System")),
+                name: Cow::Borrowed(JavaStr::from_str("out")),
+                desc: Cow::Borrowed(JavaStr::from_str("Ljava/io/PrintStream;")),
+            },
+            MethodEvent::LdcInsn(LdcConstant::String(Cow::Borrowed(JavaStr::from_str(
+                message,
+            )))),
+            MethodEvent::MethodInsn {
+                opcode: Opcode::InvokeVirtual,
+                owner: Cow::Borrowed(JavaStr::from_str("java/io/PrintStream")),
+                name: Cow::Borrowed(JavaStr::from_str("println")),
+                desc: Cow::Borrowed(JavaStr::from_str("(Ljava/lang/String;)V")),
+                is_interface: false,
+            },
+        ]
+    }
+
+    fn static_initializer_insns(bytecode: &[u8]) -> Vec<String> {
+        let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+        let mut result = Vec::new();
+        for event in reader.events().unwrap() {
+            if let ClassEvent::Methods(methods) = event.unwrap() {
+                for method in methods {
+                    let method = method.unwrap();
+                    if method.name.as_ref() != "<clinit>" {
+                        continue;
+                    }
+                    for event in method.events {
+                        match event.unwrap() {
+                            MethodEvent::Insn(opcode) => result.push(format!("{opcode:?}")),
+                            MethodEvent::LdcInsn(LdcConstant::String(value)) => {
+                                result.push(value.to_string())
+                            }
+                            MethodEvent::JumpInsn { opcode, .. } => {
+                                result.push(format!("{opcode:?}"))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_append_to_static_initializer() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let events = append_to_static_initializer(&reader, |_| print("first")).unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::ComputeFrames)
+            .write(events)
+            .unwrap();
+        assert_eq!(vec!["first", "Return"], static_initializer_insns(&written));
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let events = append_to_static_initializer(&reader, |_| print("second")).unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::ComputeFrames)
+            .write(events)
+            .unwrap();
+        assert_eq!(
+            vec!["first", "second", "Return"],
+            static_initializer_insns(&written)
+        );
+    }
+}
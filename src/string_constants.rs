@@ -0,0 +1,306 @@
+use crate::tree::{AnnotationNode, AnnotationValue};
+use crate::{
+    buffer_class_events, BufferedClassEvents, BufferedEventProviders, ClassEvent, ClassEventSource,
+    ClassFileResult, FieldEvent, FieldValue, LdcConstant, MethodEvent, RecordComponentEvent,
+};
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StringConstantLocation<'class> {
+    Class,
+    Field {
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+    },
+    Method {
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+    },
+    RecordComponent {
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StringConstantKind {
+    /// A string loaded by an `ldc` instruction.
+    Ldc,
+    /// The `ConstantValue` of a field.
+    ConstantValue,
+    /// A string element value of an annotation, including annotation defaults.
+    Annotation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StringConstant<'class> {
+    pub location: StringConstantLocation<'class>,
+    pub kind: StringConstantKind,
+    pub value: Cow<'class, JavaStr>,
+}
+
+/// Finds all string constants in a class, in the order they appear in the events.
+pub fn find_string_constants<'class, S>(source: S) -> ClassFileResult<Vec<StringConstant<'class>>>
+where
+    S: ClassEventSource<'class>,
+{
+    let mut events = buffer_class_events(source)?;
+    let mut result = Vec::new();
+    visit_string_constants(&mut events, &mut |location, kind, value| {
+        result.push(StringConstant {
+            location: location.clone(),
+            kind,
+            value: value.clone(),
+        });
+    });
+    Ok(result)
+}
+
+/// Reads a class, replacing each string constant for which `rewriter` returns `Some`. The returned
+/// events can be passed to a [`ClassWriter`](crate::ClassWriter).
+pub fn rewrite_string_constants<'class, S, F>(
+    source: S,
+    mut rewriter: F,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+    F: FnMut(&StringConstantLocation<'class>, StringConstantKind, &JavaStr) -> Option<JavaString>,
+{
+    let mut events = buffer_class_events(source)?;
+    visit_string_constants(&mut events, &mut |location, kind, value| {
+        if let Some(new_value) = rewriter(location, kind, value) {
+            *value = Cow::Owned(new_value);
+        }
+    });
+    Ok(events)
+}
+
+type Visitor<'a, 'class> =
+    dyn FnMut(&StringConstantLocation<'class>, StringConstantKind, &mut Cow<'class, JavaStr>) + 'a;
+
+fn visit_string_constants<'class>(
+    events: &mut BufferedClassEvents<'class>,
+    visitor: &mut Visitor<'_, 'class>,
+) {
+    let class_location = StringConstantLocation::Class;
+    for event in &mut events.0 {
+        match event {
+            ClassEvent::Annotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    visit_annotation(&class_location, &mut annotation.annotation, visitor);
+                }
+            }
+            ClassEvent::TypeAnnotations(annotations) => {
+                for annotation in &mut annotations.0 {
+                    visit_values(&class_location, &mut annotation.annotation.values, visitor);
+                }
+            }
+            ClassEvent::Record(components) => {
+                for component in &mut components.0 {
+                    let location = StringConstantLocation::RecordComponent {
+                        name: component.name.clone(),
+                        desc: component.desc.clone(),
+                    };
+                    for event in &mut component.events.0 {
+                        match event {
+                            RecordComponentEvent::Annotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    visit_annotation(
+                                        &location,
+                                        &mut annotation.annotation,
+                                        visitor,
+                                    );
+                                }
+                            }
+                            RecordComponentEvent::TypeAnnotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    visit_values(
+                                        &location,
+                                        &mut annotation.annotation.values,
+                                        visitor,
+                                    );
+                                }
+                            }
+                            RecordComponentEvent::Attributes(_) => {}
+                        }
+                    }
+                }
+            }
+            ClassEvent::Fields(fields) => {
+                for field in &mut fields.0 {
+                    let location = StringConstantLocation::Field {
+                        name: field.name.clone(),
+                        desc: field.desc.clone(),
+                    };
+                    if let Some(FieldValue::String(value)) = &mut field.value {
+                        visitor(&location, StringConstantKind::ConstantValue, value);
+                    }
+                    for event in &mut field.events.0 {
+                        match event {
+                            FieldEvent::Annotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    visit_annotation(
+                                        &location,
+                                        &mut annotation.annotation,
+                                        visitor,
+                                    );
+                                }
+                            }
+                            FieldEvent::TypeAnnotations(annotations) => {
+                                for annotation in &mut annotations.0 {
+                                    visit_values(
+                                        &location,
+                                        &mut annotation.annotation.values,
+                                        visitor,
+                                    );
+                                }
+                            }
+                            FieldEvent::Deprecated | FieldEvent::Attributes(_) => {}
+                        }
+                    }
+                }
+            }
+            ClassEvent::Methods(methods) => {
+                for method in &mut methods.0 {
+                    let location = StringConstantLocation::Method {
+                        name: method.name.clone(),
+                        desc: method.desc.clone(),
+                    };
+                    for event in &mut method.events.0 {
+                        visit_method_event(&location, event, visitor);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn visit_method_event<'class>(
+    location: &StringConstantLocation<'class>,
+    event: &mut MethodEvent<'class, BufferedEventProviders>,
+    visitor: &mut Visitor<'_, 'class>,
+) {
+    match event {
+        MethodEvent::LdcInsn(LdcConstant::String(value)) => {
+            visitor(location, StringConstantKind::Ldc, value);
+        }
+        MethodEvent::AnnotationDefault(value) => visit_value(location, value, visitor),
+        MethodEvent::Annotations(annotations) => {
+            for annotation in &mut annotations.0 {
+                visit_annotation(location, &mut annotation.annotation, visitor);
+            }
+        }
+        MethodEvent::TypeAnnotations(annotations) | MethodEvent::InsnAnnotations(annotations) => {
+            for annotation in &mut annotations.0 {
+                visit_values(location, &mut annotation.annotation.values, visitor);
+            }
+        }
+        MethodEvent::ParameterAnnotations(annotations) => {
+            for annotation in &mut annotations.0 {
+                visit_annotation(location, &mut annotation.annotation, visitor);
+            }
+        }
+        MethodEvent::LocalVariableAnnotations(annotations) => {
+            for annotation in &mut annotations.0 {
+                visit_values(location, &mut annotation.annotation.values, visitor);
+            }
+        }
+        MethodEvent::TryCatchBlockAnnotations(annotations) => {
+            for annotation in &mut annotations.0 {
+                visit_values(location, &mut annotation.annotation.values, visitor);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn visit_annotation<'class>(
+    location: &StringConstantLocation<'class>,
+    annotation: &mut AnnotationNode<'class>,
+    visitor: &mut Visitor<'_, 'class>,
+) {
+    visit_values(location, &mut annotation.values, visitor);
+}
+
+fn visit_values<'class>(
+    location: &StringConstantLocation<'class>,
+    values: &mut [(Cow<'class, JavaStr>, AnnotationValue<'class>)],
+    visitor: &mut Visitor<'_, 'class>,
+) {
+    for (_, value) in values {
+        visit_value(location, value, visitor);
+    }
+}
+
+fn visit_value<'class>(
+    location: &StringConstantLocation<'class>,
+    value: &mut AnnotationValue<'class>,
+    visitor: &mut Visitor<'_, 'class>,
+) {
+    match value {
+        AnnotationValue::String(value) => {
+            visitor(location, StringConstantKind::Annotation, value);
+        }
+        AnnotationValue::Annotation(annotation) => visit_annotation(location, annotation, visitor),
+        AnnotationValue::Array(values) => {
+            for value in values {
+                visit_value(location, value, visitor);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        find_string_constants, rewrite_string_constants, ClassReader, ClassReaderFlags,
+        ClassWriter, ClassWriterFlags, StringConstantKind, StringConstantLocation,
+    };
+    use java_string::JavaString;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_find_string_constants() {
+        let reader =
+            ClassReader::new(include_class!("TestAnnotations"), ClassReaderFlags::None).unwrap();
+        let constants = find_string_constants(&reader).unwrap();
+        let class_annotation_strings: Vec<_> = constants
+            .iter()
+            .filter(|constant| constant.location == StringConstantLocation::Class)
+            .map(|constant| (constant.kind, constant.value.to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (StringConstantKind::Annotation, "Hello World".to_owned()),
+                (StringConstantKind::Annotation, "Hello".to_owned()),
+                (StringConstantKind::Annotation, "World".to_owned()),
+            ],
+            class_annotation_strings
+        );
+    }
+
+    #[test]
+    fn test_rewrite_string_constants() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let events = rewrite_string_constants(&reader, |location, kind, value| {
+            assert_eq!(StringConstantKind::Ldc, kind);
+            assert!(
+                matches!(location, StringConstantLocation::Method { name, .. } if name.as_ref() == "main")
+            );
+            Some(JavaString::from(value.to_string().to_uppercase()))
+        })
+        .unwrap();
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(events)
+            .unwrap();
+
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        let constants = find_string_constants(&reader).unwrap();
+        assert_eq!(1, constants.len());
+        assert_eq!("HELLO, WORLD!", constants[0].value.as_ref());
+    }
+}
@@ -0,0 +1,417 @@
+use crate::{
+    ClassEvent, ClassEventSource, ClassFileResult, Label, LdcConstant, MethodEvent,
+    MethodEventProviders, Opcode,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+/// A `$SwitchMap$` array generated by javac to switch over an enum, mapping each enum constant's
+/// ordinal to the key used in the lowered switch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchMap<'class> {
+    pub owner: Cow<'class, JavaStr>,
+    pub name: Cow<'class, JavaStr>,
+    pub enum_name: Cow<'class, JavaStr>,
+    pub constants: BTreeMap<i32, Cow<'class, JavaStr>>,
+}
+
+impl<'class> SwitchMap<'class> {
+    /// Finds the switch maps initialized in the static initializer of the given holder class,
+    /// such as `Foo$1`.
+    pub fn find_all<S>(source: S) -> ClassFileResult<Vec<SwitchMap<'class>>>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut class_name = None;
+        let mut switch_maps: Vec<SwitchMap> = Vec::new();
+
+        for event in source.events()? {
+            match event? {
+                ClassEvent::Class(class) => class_name = Some(class.name),
+                ClassEvent::Methods(methods) => {
+                    let Some(class_name) = &class_name else {
+                        continue;
+                    };
+                    for method in methods {
+                        let method = method?;
+                        if method.name.as_ref() != "<clinit>" {
+                            continue;
+                        }
+                        let insns = collect_insns(method.events)?;
+                        for window in insns.windows(5) {
+                            let [MethodEvent::FieldInsn {
+                                opcode: Opcode::GetStatic,
+                                owner: map_owner,
+                                name: map_name,
+                                desc: map_desc,
+                            }, MethodEvent::FieldInsn {
+                                opcode: Opcode::GetStatic,
+                                owner: enum_name,
+                                name: constant,
+                                ..
+                            }, ordinal, key, MethodEvent::Insn(Opcode::IAStore)] = window
+                            else {
+                                continue;
+                            };
+                            if map_owner != class_name
+                                || !is_switch_map_field(map_name, map_desc)
+                                || !is_ordinal_call(ordinal)
+                            {
+                                continue;
+                            }
+                            let Some(key) = int_constant(key) else {
+                                continue;
+                            };
+
+                            let index = match switch_maps
+                                .iter()
+                                .position(|switch_map| switch_map.name == *map_name)
+                            {
+                                Some(index) => index,
+                                None => {
+                                    switch_maps.push(SwitchMap {
+                                        owner: map_owner.clone(),
+                                        name: map_name.clone(),
+                                        enum_name: enum_name.clone(),
+                                        constants: BTreeMap::new(),
+                                    });
+                                    switch_maps.len() - 1
+                                }
+                            };
+                            switch_maps[index].constants.insert(key, constant.clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(switch_maps)
+    }
+
+    /// Maps the cases of a lowered enum switch back to the names of the enum constants. Returns
+    /// `None` if the switch doesn't use this switch map.
+    pub fn resolve(&self, switch: &EnumSwitch) -> Option<Vec<(Cow<'class, JavaStr>, Label)>> {
+        if switch.switch_map_owner != self.owner || switch.switch_map_name != self.name {
+            return None;
+        }
+
+        Some(
+            switch
+                .cases
+                .iter()
+                .filter_map(|&(key, label)| {
+                    self.constants
+                        .get(&key)
+                        .map(|constant| (constant.clone(), label))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A switch over an enum which javac has lowered to a switch over the enum's `$SwitchMap$`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumSwitch<'class> {
+    pub switch_map_owner: Cow<'class, JavaStr>,
+    pub switch_map_name: Cow<'class, JavaStr>,
+    pub enum_name: Cow<'class, JavaStr>,
+    pub dflt: Label,
+    pub cases: Vec<(i32, Label)>,
+}
+
+impl<'class> EnumSwitch<'class> {
+    pub fn find_all<E, P>(events: E) -> ClassFileResult<Vec<EnumSwitch<'class>>>
+    where
+        E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+        P: MethodEventProviders<'class>,
+    {
+        let insns = collect_insns(events)?;
+        let mut result = Vec::new();
+        let mut switch_map = None;
+
+        for (index, insn) in insns.iter().enumerate() {
+            if let MethodEvent::FieldInsn {
+                opcode: Opcode::GetStatic,
+                owner,
+                name,
+                desc,
+            } = insn
+            {
+                if is_switch_map_field(name, desc) {
+                    switch_map = Some((owner, name));
+                }
+                continue;
+            }
+
+            let [ordinal @ MethodEvent::MethodInsn {
+                owner: enum_name, ..
+            }, MethodEvent::Insn(Opcode::IALoad), switch, ..] = &insns[index..]
+            else {
+                continue;
+            };
+            if !is_ordinal_call(ordinal) {
+                continue;
+            }
+            let Some((dflt, cases)) = switch_cases(switch) else {
+                continue;
+            };
+            if let Some((switch_map_owner, switch_map_name)) = switch_map.take() {
+                result.push(EnumSwitch {
+                    switch_map_owner: switch_map_owner.clone(),
+                    switch_map_name: switch_map_name.clone(),
+                    enum_name: enum_name.clone(),
+                    dflt,
+                    cases,
+                });
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// A switch over a string which javac has lowered to a switch over its hash code, followed by a
+/// switch over the index of the matching case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringSwitch<'class> {
+    /// The local variable holding the index of the matching case.
+    pub index_var: u16,
+    pub dflt: Label,
+    pub cases: Vec<(Cow<'class, JavaStr>, Label)>,
+}
+
+impl<'class> StringSwitch<'class> {
+    pub fn find_all<E, P>(events: E) -> ClassFileResult<Vec<StringSwitch<'class>>>
+    where
+        E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+        P: MethodEventProviders<'class>,
+    {
+        let insns = collect_insns(events)?;
+        let mut result = Vec::new();
+        let mut in_hash_switch = false;
+        let mut case_strings: HashMap<u16, BTreeMap<i32, Cow<'class, JavaStr>>> = HashMap::new();
+
+        for index in 0..insns.len() {
+            match &insns[index..] {
+                [MethodEvent::MethodInsn {
+                    opcode: Opcode::InvokeVirtual,
+                    owner,
+                    name,
+                    desc,
+                    ..
+                }, switch, ..]
+                    if owner.as_ref() == "java/lang/String"
+                        && name.as_ref() == "hashCode"
+                        && desc.as_ref() == "()I"
+                        && switch_cases(switch).is_some() =>
+                {
+                    in_hash_switch = true;
+                }
+                [MethodEvent::LdcInsn(LdcConstant::String(string)), MethodEvent::MethodInsn {
+                    opcode: Opcode::InvokeVirtual,
+                    owner,
+                    name,
+                    desc,
+                    ..
+                }, MethodEvent::JumpInsn {
+                    opcode: Opcode::IfEq,
+                    ..
+                }, key, MethodEvent::VarInsn {
+                    opcode: Opcode::IStore,
+                    var_index,
+                }, ..]
+                    if in_hash_switch
+                        && owner.as_ref() == "java/lang/String"
+                        && name.as_ref() == "equals"
+                        && desc.as_ref() == "(Ljava/lang/Object;)Z" =>
+                {
+                    if let Some(key) = int_constant(key) {
+                        case_strings
+                            .entry(*var_index)
+                            .or_default()
+                            .insert(key, string.clone());
+                    }
+                }
+                [MethodEvent::VarInsn {
+                    opcode: Opcode::ILoad,
+                    var_index,
+                }, switch, ..] => {
+                    let Some((dflt, cases)) = switch_cases(switch) else {
+                        continue;
+                    };
+                    let Some(strings) = case_strings.remove(var_index) else {
+                        continue;
+                    };
+                    in_hash_switch = false;
+                    result.push(StringSwitch {
+                        index_var: *var_index,
+                        dflt,
+                        cases: cases
+                            .into_iter()
+                            .filter_map(|(key, label)| {
+                                strings.get(&key).map(|string| (string.clone(), label))
+                            })
+                            .collect(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Collects the instructions of a method, skipping labels, frames and other non-instruction events.
+fn collect_insns<'class, E, P>(events: E) -> ClassFileResult<Vec<MethodEvent<'class, P>>>
+where
+    E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+    P: MethodEventProviders<'class>,
+{
+    let mut insns = Vec::new();
+    for event in events {
+        let event = event?;
+        if matches!(
+            event,
+            MethodEvent::Insn(_)
+                | MethodEvent::BIPushInsn(_)
+                | MethodEvent::SIPushInsn(_)
+                | MethodEvent::NewArrayInsn(_)
+                | MethodEvent::VarInsn { .. }
+                | MethodEvent::TypeInsn { .. }
+                | MethodEvent::FieldInsn { .. }
+                | MethodEvent::MethodInsn { .. }
+                | MethodEvent::InvokeDynamicInsn { .. }
+                | MethodEvent::JumpInsn { .. }
+                | MethodEvent::LdcInsn(_)
+                | MethodEvent::IIncInsn { .. }
+                | MethodEvent::TableSwitchInsn { .. }
+                | MethodEvent::LookupSwitchInsn { .. }
+                | MethodEvent::MultiANewArrayInsn { .. }
+        ) {
+            insns.push(event);
+        }
+    }
+    Ok(insns)
+}
+
+fn is_switch_map_field(name: &JavaStr, desc: &JavaStr) -> bool {
+    name.starts_with("$SwitchMap$") && desc == "[I"
+}
+
+fn is_ordinal_call<'class, P>(insn: &MethodEvent<'class, P>) -> bool
+where
+    P: MethodEventProviders<'class>,
+{
+    matches!(
+        insn,
+        MethodEvent::MethodInsn {
+            opcode: Opcode::InvokeVirtual,
+            name,
+            desc,
+            ..
+        } if name.as_ref() == "ordinal" && desc.as_ref() == "()I"
+    )
+}
+
+fn int_constant<'class, P>(insn: &MethodEvent<'class, P>) -> Option<i32>
+where
+    P: MethodEventProviders<'class>,
+{
+    match insn {
+        MethodEvent::Insn(opcode) => match opcode {
+            Opcode::IConstM1 => Some(-1),
+            Opcode::IConst0 => Some(0),
+            Opcode::IConst1 => Some(1),
+            Opcode::IConst2 => Some(2),
+            Opcode::IConst3 => Some(3),
+            Opcode::IConst4 => Some(4),
+            Opcode::IConst5 => Some(5),
+            _ => None,
+        },
+        MethodEvent::BIPushInsn(value) => Some(*value as i32),
+        MethodEvent::SIPushInsn(value) => Some(*value as i32),
+        MethodEvent::LdcInsn(LdcConstant::Integer(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Returns the default label and the explicit cases of a switch instruction.
+fn switch_cases<'class, P>(insn: &MethodEvent<'class, P>) -> Option<(Label, Vec<(i32, Label)>)>
+where
+    P: MethodEventProviders<'class>,
+{
+    match insn {
+        MethodEvent::TableSwitchInsn {
+            low, dflt, labels, ..
+        } => Some((
+            *dflt,
+            labels
+                .iter()
+                .zip(*low..)
+                .filter(|(label, _)| *label != dflt)
+                .map(|(label, key)| (key, *label))
+                .collect(),
+        )),
+        MethodEvent::LookupSwitchInsn { dflt, values } => Some((*dflt, values.clone())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassEvent, ClassEventSource, ClassReader, ClassReaderFlags, EnumSwitch, Label,
+        StringSwitch, SwitchMap,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_switches() {
+        let holder =
+            ClassReader::new(include_class!("TestSwitches$1"), ClassReaderFlags::None).unwrap();
+        let switch_maps = SwitchMap::find_all(&holder).unwrap();
+        assert_eq!(1, switch_maps.len());
+        assert_eq!("TestSwitches$Color", switch_maps[0].enum_name.as_ref());
+
+        let reader =
+            ClassReader::new(include_class!("TestSwitches"), ClassReaderFlags::None).unwrap();
+        let mut enum_cases = None;
+        let mut string_cases = None;
+        for event in reader.events().unwrap() {
+            let ClassEvent::Methods(methods) = event.unwrap() else {
+                continue;
+            };
+            for method in methods {
+                let method = method.unwrap();
+                match method.name.as_ref().as_str().unwrap() {
+                    "enumSwitch" => {
+                        let switches = EnumSwitch::find_all(method.events).unwrap();
+                        assert_eq!(1, switches.len());
+                        enum_cases = switch_maps[0].resolve(&switches[0]);
+                    }
+                    "stringSwitch" => {
+                        let switches = StringSwitch::find_all(method.events).unwrap();
+                        assert_eq!(1, switches.len());
+                        string_cases = Some(switches[0].cases.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        fn names(cases: Option<Vec<(Cow<JavaStr>, Label)>>) -> Vec<String> {
+            cases
+                .unwrap()
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect()
+        }
+        assert_eq!(vec!["BLUE", "RED"], names(enum_cases));
+        assert_eq!(vec!["foo", "bar", "Aa", "BB"], names(string_cases));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    buffer_class_events, BufferedClassEvents, ClassAccess, ClassEvent, ClassEventSource,
+    ClassFileResult, FieldAccess, MethodAccess,
+};
+use bitflags::bitflags;
+use java_string::JavaStr;
+
+bitflags! {
+    /// Kinds of members generated by the compiler, see [`drop_synthetic_members`]. A member can be
+    /// of several kinds, such as a bridge method which is also synthetic.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    pub struct SyntheticMembers: u8 {
+        const None = 0;
+        const SyntheticFields = 1;
+        const SyntheticMethods = 2;
+        const BridgeMethods = 4;
+        /// The `$VALUES` field and `$values()` method of enums.
+        const EnumValues = 8;
+        /// The methods holding the bodies of lambdas, named `lambda$...`.
+        const Lambdas = 16;
+        /// The `$SwitchMap$...` fields of javac and the `$SWITCH_TABLE$...` fields and methods of
+        /// ecj, which map enum constants to switch cases.
+        const SwitchMaps = 32;
+        const All = 63;
+    }
+}
+
+impl SyntheticMembers {
+    /// The kinds of compiler-generated member a field is.
+    pub fn of_field(access: FieldAccess, name: &JavaStr) -> SyntheticMembers {
+        let mut kinds = SyntheticMembers::None;
+        kinds.set(
+            SyntheticMembers::SyntheticFields,
+            access.contains(FieldAccess::Synthetic),
+        );
+        kinds.set(SyntheticMembers::EnumValues, name == "$VALUES");
+        kinds.set(
+            SyntheticMembers::SwitchMaps,
+            name.starts_with("$SwitchMap$") || name.starts_with("$SWITCH_TABLE$"),
+        );
+        kinds
+    }
+
+    /// The kinds of compiler-generated member a method is.
+    pub fn of_method(access: MethodAccess, name: &JavaStr) -> SyntheticMembers {
+        let mut kinds = SyntheticMembers::None;
+        kinds.set(
+            SyntheticMembers::SyntheticMethods,
+            access.contains(MethodAccess::Synthetic),
+        );
+        kinds.set(
+            SyntheticMembers::BridgeMethods,
+            access.contains(MethodAccess::Bridge),
+        );
+        kinds.set(
+            SyntheticMembers::EnumValues,
+            name == "$values" && access.contains(MethodAccess::Synthetic),
+        );
+        kinds.set(SyntheticMembers::Lambdas, name.starts_with("lambda$"));
+        kinds.set(
+            SyntheticMembers::SwitchMaps,
+            name.starts_with("$SWITCH_TABLE$"),
+        );
+        kinds
+    }
+}
+
+/// Reads a class, removing the fields and methods which are of any of the given kinds.
+pub fn drop_synthetic_members<'class, S>(
+    source: S,
+    kinds: SyntheticMembers,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    filter_members(source, |member_kinds| !member_kinds.intersects(kinds))
+}
+
+/// Reads a class, removing the fields and methods which aren't of any of the given kinds.
+pub fn keep_synthetic_members<'class, S>(
+    source: S,
+    kinds: SyntheticMembers,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    filter_members(source, |member_kinds| member_kinds.intersects(kinds))
+}
+
+fn filter_members<'class, S>(
+    source: S,
+    keep: impl Fn(SyntheticMembers) -> bool,
+) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    let mut events = buffer_class_events(source)?;
+    for event in &mut events.0 {
+        match event {
+            ClassEvent::Fields(fields) => fields
+                .0
+                .retain(|field| keep(SyntheticMembers::of_field(field.access, &field.name))),
+            ClassEvent::Methods(methods) => methods
+                .0
+                .retain(|method| keep(SyntheticMembers::of_method(method.access, &method.name))),
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// Whether a class only exists to hold the switch maps of another class, such as the `Foo$1`
+/// classes javac generates for switches over enums. Such classes are synthetic and all of their
+/// fields are switch maps.
+pub fn is_switch_map_class<'class, S>(source: S) -> ClassFileResult<bool>
+where
+    S: ClassEventSource<'class>,
+{
+    let mut is_synthetic = false;
+    let mut has_switch_map = false;
+    for event in source.events()? {
+        match event? {
+            ClassEvent::Class(class) => {
+                is_synthetic = class.access.contains(ClassAccess::Synthetic)
+            }
+            ClassEvent::Fields(fields) => {
+                for field in fields {
+                    let field = field?;
+                    if !SyntheticMembers::of_field(field.access, &field.name)
+                        .contains(SyntheticMembers::SwitchMaps)
+                    {
+                        return Ok(false);
+                    }
+                    has_switch_map = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(is_synthetic && has_switch_map)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ClassNode;
+    use crate::{
+        drop_synthetic_members, is_switch_map_class, keep_synthetic_members, ClassReader,
+        ClassReaderFlags, SyntheticMembers,
+    };
+    use test_helpers::include_class;
+
+    fn member_names(class: &ClassNode) -> Vec<String> {
+        class
+            .fields
+            .iter()
+            .map(|field| field.name.to_string())
+            .chain(class.methods.iter().map(|method| method.name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_enum_values() {
+        let reader =
+            ClassReader::new(include_class!("TestSwitches$Color"), ClassReaderFlags::None).unwrap();
+        let class = ClassNode::from_events(
+            drop_synthetic_members(&reader, SyntheticMembers::EnumValues).unwrap(),
+        )
+        .unwrap();
+        let names = member_names(&class);
+        assert!(names.contains(&"values".to_owned()));
+        assert!(!names.contains(&"$VALUES".to_owned()));
+        assert!(!names.contains(&"$values".to_owned()));
+
+        let class = ClassNode::from_events(
+            keep_synthetic_members(&reader, SyntheticMembers::EnumValues).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vec!["$VALUES", "$values"], member_names(&class));
+    }
+
+    #[test]
+    fn test_switch_map_class() {
+        let reader = ClassReader::new(
+            include_class!("TestSyntheticClass$1"),
+            ClassReaderFlags::None,
+        )
+        .unwrap();
+        assert!(is_switch_map_class(&reader).unwrap());
+        let class = ClassNode::from_events(
+            drop_synthetic_members(&reader, SyntheticMembers::SwitchMaps).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vec!["<clinit>"], member_names(&class));
+
+        let reader =
+            ClassReader::new(include_class!("TestSyntheticClass"), ClassReaderFlags::None).unwrap();
+        assert!(!is_switch_map_class(&reader).unwrap());
+    }
+}
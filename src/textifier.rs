@@ -0,0 +1,857 @@
+use crate::tree::{AnnotationNode, AnnotationValue, TypeAnnotationNode};
+use crate::{
+    buffer_class_events, class_signature_to_java, field_signature_to_java,
+    method_signature_to_java, AnnotationEvent, BootstrapMethodArgument, BufferedEventProviders,
+    ClassAccess, ClassEvent, ClassEventSource, ClassFileResult, ConstantDynamic, FieldEvent,
+    FieldValue, Frame, FrameValue, Handle, Label, LdcConstant, MethodEvent, ModuleEvent,
+    RecordComponentEvent,
+};
+use bitflags::Flags;
+use java_string::JavaStr;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+const INDENT: &str = "  ";
+const INSN_INDENT: &str = "      ";
+const LABEL_INDENT: &str = "     ";
+
+/// Renders classes as human-readable text, similar to `javap -c`, for debugging transformations.
+///
+/// Each member is printed in its own block, preceded by a comment with its access flags, and its
+/// generic signature both as written and as a Java declaration.
+/// Instructions use the mnemonics of [`Opcode`](crate::Opcode), labels are numbered `L0`, `L1`,
+/// ... in the order they appear in each method, and frames, line numbers, local variables and
+/// exception handlers are printed inline with the code.
+#[derive(Debug, Clone)]
+pub struct Textifier {
+    show_frames: bool,
+}
+
+impl Default for Textifier {
+    fn default() -> Self {
+        Textifier { show_frames: true }
+    }
+}
+
+impl Textifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether stack map frames are printed, which defaults to `true`.
+    pub fn set_show_frames(&mut self, show_frames: bool) {
+        self.show_frames = show_frames;
+    }
+
+    pub fn textify<'class, S>(&self, source: S) -> ClassFileResult<String>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut output = String::new();
+        self.write_to(source, &mut output)?;
+        Ok(output)
+    }
+
+    pub fn write_to<'class, S, W>(&self, source: S, output: &mut W) -> ClassFileResult<()>
+    where
+        S: ClassEventSource<'class>,
+        W: Write,
+    {
+        let events = buffer_class_events(source)?;
+        for event in events.0 {
+            self.write_class_event(event, output)?;
+        }
+        Ok(())
+    }
+
+    fn write_class_event<W: Write>(
+        &self,
+        event: ClassEvent<'_, BufferedEventProviders>,
+        out: &mut W,
+    ) -> fmt::Result {
+        match event {
+            ClassEvent::Class(class) => {
+                writeln!(
+                    out,
+                    "// class version {}.{}",
+                    class.major_version, class.minor_version
+                )?;
+                writeln!(out, "// access flags {:#x}", class.access.bits())?;
+                if let Some(signature) = &class.signature {
+                    writeln!(out, "// signature {signature}")?;
+                    if let Ok(declaration) = class_signature_to_java(signature) {
+                        writeln!(out, "// declaration: {declaration}")?;
+                    }
+                }
+                let kind_flags = ClassAccess::Interface
+                    | ClassAccess::Annotation
+                    | ClassAccess::Enum
+                    | ClassAccess::Module;
+                write_access(out, class.access - kind_flags)?;
+                let kind = if class.access.contains(ClassAccess::Module) {
+                    "module"
+                } else if class.access.contains(ClassAccess::Annotation) {
+                    "@interface"
+                } else if class.access.contains(ClassAccess::Interface) {
+                    "interface"
+                } else if class.access.contains(ClassAccess::Enum) {
+                    "enum"
+                } else {
+                    "class"
+                };
+                writeln!(out, "{kind} {}", class.name)?;
+                if let Some(super_name) = &class.super_name {
+                    writeln!(out, "{INDENT}extends {super_name}")?;
+                }
+                if !class.interfaces.is_empty() {
+                    writeln!(out, "{INDENT}implements {}", join(&class.interfaces))?;
+                }
+                writeln!(out)?;
+            }
+            ClassEvent::Synthetic => writeln!(out, "{INDENT}// synthetic")?,
+            ClassEvent::Deprecated => writeln!(out, "{INDENT}// deprecated")?,
+            ClassEvent::Source(source) => {
+                if let Some(file) = &source.source {
+                    writeln!(out, "{INDENT}// compiled from: {file}")?;
+                }
+                if let Some(debug) = &source.debug {
+                    writeln!(out, "{INDENT}// debug info: {debug}")?;
+                }
+            }
+            ClassEvent::Module(module) => {
+                write!(out, "{INDENT}module {}", module.name)?;
+                if let Some(version) = &module.version {
+                    write!(out, "@{version}")?;
+                }
+                writeln!(out, " // access flags {:#x}", module.access.bits())?;
+                for event in module.events.0 {
+                    match event {
+                        ModuleEvent::MainClass(main_class) => {
+                            writeln!(out, "{INDENT}{INDENT}mainclass {main_class}")?
+                        }
+                        ModuleEvent::Packages(packages) => {
+                            for package in packages.0 {
+                                writeln!(out, "{INDENT}{INDENT}package {package}")?;
+                            }
+                        }
+                        ModuleEvent::Target(target) => {
+                            writeln!(out, "{INDENT}{INDENT}target {target}")?
+                        }
+                        ModuleEvent::Resolution(resolution) => {
+                            write!(out, "{INDENT}{INDENT}resolution ")?;
+                            write_access(out, resolution)?;
+                            writeln!(out, "// flags {:#x}", resolution.bits())?;
+                        }
+                        ModuleEvent::Hashes(hashes) => {
+                            for hash in hashes.hashes {
+                                write!(
+                                    out,
+                                    "{INDENT}{INDENT}hash {} {} ",
+                                    hash.module, hashes.algorithm
+                                )?;
+                                for b in hash.hash.iter() {
+                                    write!(out, "{b:02x}")?;
+                                }
+                                writeln!(out)?;
+                            }
+                        }
+                        ModuleEvent::Requires(requires) => {
+                            for require in requires.0 {
+                                write!(out, "{INDENT}{INDENT}requires ")?;
+                                write_access(out, require.access)?;
+                                write!(out, "{}", require.module)?;
+                                if let Some(version) = &require.version {
+                                    write!(out, "@{version}")?;
+                                }
+                                writeln!(out)?;
+                            }
+                        }
+                        ModuleEvent::Exports(exports) => {
+                            for export in exports.0 {
+                                write!(out, "{INDENT}{INDENT}exports {}", export.package)?;
+                                if !export.modules.is_empty() {
+                                    write!(out, " to {}", join(&export.modules))?;
+                                }
+                                writeln!(out)?;
+                            }
+                        }
+                        ModuleEvent::Opens(opens) => {
+                            for open in opens.0 {
+                                write!(out, "{INDENT}{INDENT}opens {}", open.package)?;
+                                if !open.modules.is_empty() {
+                                    write!(out, " to {}", join(&open.modules))?;
+                                }
+                                writeln!(out)?;
+                            }
+                        }
+                        ModuleEvent::Uses(uses) => {
+                            for service in uses.0 {
+                                writeln!(out, "{INDENT}{INDENT}uses {service}")?;
+                            }
+                        }
+                        ModuleEvent::Provides(provides) => {
+                            for provide in provides.0 {
+                                writeln!(
+                                    out,
+                                    "{INDENT}{INDENT}provides {} with {}",
+                                    provide.service,
+                                    join(&provide.providers)
+                                )?;
+                            }
+                        }
+                    }
+                }
+                writeln!(out)?;
+            }
+            ClassEvent::NestHost(host) => writeln!(out, "{INDENT}nesthost {host}")?,
+            ClassEvent::OuterClass(outer_class) => {
+                write!(out, "{INDENT}outerclass {}", outer_class.owner)?;
+                if let Some(name) = &outer_class.method_name {
+                    write!(out, " {name}")?;
+                }
+                if let Some(desc) = &outer_class.method_desc {
+                    write!(out, " {desc}")?;
+                }
+                writeln!(out)?;
+            }
+            ClassEvent::Annotations(annotations) => {
+                write_annotations(out, INDENT, annotations.0)?;
+            }
+            ClassEvent::TypeAnnotations(annotations) => {
+                write_type_annotations(out, INDENT, annotations.0)?;
+            }
+            ClassEvent::Attributes(attributes) => {
+                for attribute in attributes.0 {
+                    writeln!(out, "{INDENT}attribute {}", attribute.name())?;
+                }
+            }
+            ClassEvent::NestMembers(members) => {
+                for member in members.0 {
+                    writeln!(out, "{INDENT}nestmember {member}")?;
+                }
+            }
+            ClassEvent::PermittedSubclasses(subclasses) => {
+                for subclass in subclasses.0 {
+                    writeln!(out, "{INDENT}permittedsubclass {subclass}")?;
+                }
+            }
+            #[cfg(feature = "preview")]
+            ClassEvent::LoadableDescriptors(descs) => {
+                for desc in descs {
+                    writeln!(out, "{INDENT}loadabledescriptor {desc}")?;
+                }
+            }
+            ClassEvent::InnerClasses(inner_classes) => {
+                for inner_class in inner_classes.0 {
+                    writeln!(
+                        out,
+                        "{INDENT}// access flags {:#x}",
+                        inner_class.access.bits()
+                    )?;
+                    write!(out, "{INDENT}")?;
+                    write_access(out, inner_class.access)?;
+                    write!(out, "innerclass {}", inner_class.name)?;
+                    write!(out, " {}", OrNull(inner_class.outer_name.as_deref()))?;
+                    writeln!(out, " {}", OrNull(inner_class.inner_name.as_deref()))?;
+                }
+            }
+            ClassEvent::Record(components) => {
+                writeln!(out)?;
+                for component in components.0 {
+                    writeln!(
+                        out,
+                        "{INDENT}// record component {} : {}",
+                        component.name, component.desc
+                    )?;
+                    if let Some(signature) = &component.signature {
+                        writeln!(out, "{INDENT}// signature {signature}")?;
+                        if let Ok(declaration) = field_signature_to_java(signature) {
+                            writeln!(out, "{INDENT}// declaration: {declaration}")?;
+                        }
+                    }
+                    for event in component.events.0 {
+                        match event {
+                            RecordComponentEvent::Annotations(annotations) => {
+                                write_annotations(out, INDENT, annotations.0)?
+                            }
+                            RecordComponentEvent::TypeAnnotations(annotations) => {
+                                write_type_annotations(out, INDENT, annotations.0)?
+                            }
+                            RecordComponentEvent::Attributes(attributes) => {
+                                for attribute in attributes.0 {
+                                    writeln!(out, "{INDENT}attribute {}", attribute.name())?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ClassEvent::Fields(fields) => {
+                for field in fields.0 {
+                    writeln!(out)?;
+                    writeln!(out, "{INDENT}// access flags {:#x}", field.access.bits())?;
+                    if let Some(signature) = &field.signature {
+                        writeln!(out, "{INDENT}// signature {signature}")?;
+                        if let Ok(declaration) = field_signature_to_java(signature) {
+                            writeln!(out, "{INDENT}// declaration: {declaration}")?;
+                        }
+                    }
+                    write!(out, "{INDENT}")?;
+                    write_access(out, field.access)?;
+                    write!(out, "{} : {}", field.name, field.desc)?;
+                    match &field.value {
+                        Some(FieldValue::Integer(value)) => write!(out, " = {value}")?,
+                        Some(FieldValue::Float(value)) => write!(out, " = {value:?}F")?,
+                        Some(FieldValue::Long(value)) => write!(out, " = {value}L")?,
+                        Some(FieldValue::Double(value)) => write!(out, " = {value:?}D")?,
+                        Some(FieldValue::String(value)) => write!(out, " = {value:?}")?,
+                        None => {}
+                    }
+                    writeln!(out)?;
+                    for event in field.events.0 {
+                        match event {
+                            FieldEvent::Deprecated => writeln!(out, "{INDENT}// deprecated")?,
+                            FieldEvent::Annotations(annotations) => {
+                                write_annotations(out, INDENT, annotations.0)?
+                            }
+                            FieldEvent::TypeAnnotations(annotations) => {
+                                write_type_annotations(out, INDENT, annotations.0)?
+                            }
+                            FieldEvent::Attributes(attributes) => {
+                                for attribute in attributes.0 {
+                                    writeln!(out, "{INDENT}attribute {}", attribute.name())?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ClassEvent::Methods(methods) => {
+                for method in methods.0 {
+                    writeln!(out)?;
+                    writeln!(out, "{INDENT}// access flags {:#x}", method.access.bits())?;
+                    if let Some(signature) = &method.signature {
+                        writeln!(out, "{INDENT}// signature {signature}")?;
+                        if let Ok(declaration) = method_signature_to_java(signature) {
+                            writeln!(out, "{INDENT}// declaration: {declaration}")?;
+                        }
+                    }
+                    write!(out, "{INDENT}")?;
+                    write_access(out, method.access)?;
+                    write!(out, "{}{}", method.name, method.desc)?;
+                    if !method.exceptions.is_empty() {
+                        write!(out, " throws {}", join(&method.exceptions))?;
+                    }
+                    writeln!(out)?;
+                    self.write_method_events(out, method.events.0)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_method_events<'class, W: Write>(
+        &self,
+        out: &mut W,
+        events: Vec<MethodEvent<'class, BufferedEventProviders>>,
+    ) -> fmt::Result {
+        let mut labels = HashMap::new();
+        for event in &events {
+            if let MethodEvent::Label(label) = event {
+                let next_index = labels.len();
+                labels.entry(*label).or_insert(next_index);
+            }
+        }
+        let label = |label: &Label| match labels.get(label) {
+            Some(index) => format!("L{index}"),
+            // a label which is never visited
+            None => format!("?{label}"),
+        };
+
+        for event in events {
+            match event {
+                MethodEvent::Deprecated => writeln!(out, "{INDENT}// deprecated")?,
+                MethodEvent::Parameters(parameters) => {
+                    for parameter in parameters.0 {
+                        write!(out, "{INDENT}// parameter ")?;
+                        write_access(out, parameter.access)?;
+                        writeln!(out, "{}", OrNull(parameter.name.as_deref()))?;
+                    }
+                }
+                MethodEvent::AnnotationDefault(value) => {
+                    write!(out, "{INDENT}default=")?;
+                    write_annotation_value(out, &value)?;
+                    writeln!(out)?;
+                }
+                MethodEvent::Annotations(annotations) => {
+                    write_annotations(out, INDENT, annotations.0)?
+                }
+                MethodEvent::TypeAnnotations(annotations) => {
+                    write_type_annotations(out, INDENT, annotations.0)?
+                }
+                MethodEvent::AnnotableParameterCount(count) => writeln!(
+                    out,
+                    "{INDENT}// annotable parameter count: {} ({})",
+                    count.count,
+                    visibility(count.visible)
+                )?,
+                MethodEvent::ParameterAnnotations(annotations) => {
+                    for annotation in annotations.0 {
+                        write!(out, "{INDENT}")?;
+                        write_annotation(out, &annotation.annotation)?;
+                        writeln!(
+                            out,
+                            " // parameter {}, {}",
+                            annotation.parameter,
+                            visibility(annotation.visible)
+                        )?;
+                    }
+                }
+                MethodEvent::Attributes(attributes) | MethodEvent::CodeAttributes(attributes) => {
+                    for attribute in attributes.0 {
+                        writeln!(out, "{INDENT}attribute {}", attribute.name())?;
+                    }
+                }
+                MethodEvent::Code { .. } => {}
+                MethodEvent::Frame(frame) => {
+                    if self.show_frames {
+                        write!(out, "{INSN_INDENT}frame ")?;
+                        write_frame(out, &frame, &label)?;
+                        writeln!(out)?;
+                    }
+                }
+                MethodEvent::Insn(opcode) => writeln!(out, "{INSN_INDENT}{opcode}")?,
+                MethodEvent::BIPushInsn(value) => writeln!(out, "{INSN_INDENT}bipush {value}")?,
+                MethodEvent::SIPushInsn(value) => writeln!(out, "{INSN_INDENT}sipush {value}")?,
+                MethodEvent::NewArrayInsn(ty) => writeln!(out, "{INSN_INDENT}newarray {ty}")?,
+                MethodEvent::VarInsn { opcode, var_index } => {
+                    writeln!(out, "{INSN_INDENT}{opcode} {var_index}")?
+                }
+                MethodEvent::TypeInsn { opcode, ty } => {
+                    writeln!(out, "{INSN_INDENT}{opcode} {ty}")?
+                }
+                MethodEvent::FieldInsn {
+                    opcode,
+                    owner,
+                    name,
+                    desc,
+                } => writeln!(out, "{INSN_INDENT}{opcode} {owner}.{name} : {desc}")?,
+                MethodEvent::MethodInsn {
+                    opcode,
+                    owner,
+                    name,
+                    desc,
+                    is_interface,
+                } => {
+                    write!(out, "{INSN_INDENT}{opcode} {owner}.{name}{desc}")?;
+                    if is_interface {
+                        write!(out, " (itf)")?;
+                    }
+                    writeln!(out)?;
+                }
+                MethodEvent::InvokeDynamicInsn {
+                    name,
+                    desc,
+                    bootstrap_method_handle,
+                    bootstrap_method_arguments,
+                } => {
+                    writeln!(out, "{INSN_INDENT}invokedynamic {name}{desc}")?;
+                    write!(out, "{INSN_INDENT}{INDENT}// bootstrap method ")?;
+                    write_handle(out, &bootstrap_method_handle)?;
+                    writeln!(out)?;
+                    if !bootstrap_method_arguments.is_empty() {
+                        write!(out, "{INSN_INDENT}{INDENT}// arguments ")?;
+                        write_bootstrap_arguments(out, &bootstrap_method_arguments)?;
+                        writeln!(out)?;
+                    }
+                }
+                MethodEvent::JumpInsn {
+                    opcode,
+                    label: target,
+                } => writeln!(out, "{INSN_INDENT}{opcode} {}", label(&target))?,
+                MethodEvent::Label(target) => writeln!(out, "{LABEL_INDENT}{}", label(&target))?,
+                MethodEvent::LdcInsn(constant) => {
+                    write!(out, "{INSN_INDENT}ldc ")?;
+                    write_ldc_constant(out, &constant)?;
+                    writeln!(out)?;
+                }
+                MethodEvent::IIncInsn {
+                    var_index,
+                    increment,
+                } => writeln!(out, "{INSN_INDENT}iinc {var_index} {increment}")?,
+                MethodEvent::TableSwitchInsn {
+                    low,
+                    high,
+                    dflt,
+                    labels,
+                } => {
+                    writeln!(out, "{INSN_INDENT}tableswitch // {low} to {high}")?;
+                    for (value, target) in (i64::from(low)..).zip(&labels) {
+                        writeln!(out, "{INSN_INDENT}{INDENT}{value}: {}", label(target))?;
+                    }
+                    writeln!(out, "{INSN_INDENT}{INDENT}default: {}", label(&dflt))?;
+                }
+                MethodEvent::LookupSwitchInsn { dflt, values } => {
+                    writeln!(out, "{INSN_INDENT}lookupswitch")?;
+                    for (value, target) in &values {
+                        writeln!(out, "{INSN_INDENT}{INDENT}{value}: {}", label(target))?;
+                    }
+                    writeln!(out, "{INSN_INDENT}{INDENT}default: {}", label(&dflt))?;
+                }
+                MethodEvent::MultiANewArrayInsn { desc, dimensions } => {
+                    writeln!(out, "{INSN_INDENT}multianewarray {desc} {dimensions}")?
+                }
+                MethodEvent::InsnAnnotations(annotations) => {
+                    write_type_annotations(out, INSN_INDENT, annotations.0)?
+                }
+                MethodEvent::LineNumber { line, start } => {
+                    writeln!(out, "{INSN_INDENT}line {line} {}", label(&start))?
+                }
+                MethodEvent::LocalVariables(locals) => {
+                    for local in locals.0 {
+                        write!(
+                            out,
+                            "{INSN_INDENT}localvariable {} {} {} {} {}",
+                            local.name,
+                            local.desc,
+                            label(&local.start),
+                            label(&local.end),
+                            local.index
+                        )?;
+                        if let Some(signature) = &local.signature {
+                            write!(out, " // signature {signature}")?;
+                        }
+                        writeln!(out)?;
+                    }
+                }
+                MethodEvent::LocalVariableAnnotations(annotations) => {
+                    for annotation in annotations.0 {
+                        write!(out, "{INSN_INDENT}")?;
+                        write_type_annotation(out, &annotation.annotation)?;
+                        for (start, end, index) in &annotation.ranges {
+                            write!(out, " [{} {} {index}]", label(start), label(end))?;
+                        }
+                        writeln!(out, " // {}", visibility(annotation.visible))?;
+                    }
+                }
+                MethodEvent::TryCatchBlocks(blocks) => {
+                    for block in blocks.0 {
+                        writeln!(
+                            out,
+                            "{INSN_INDENT}trycatch {} {} {} {}",
+                            label(&block.start),
+                            label(&block.end),
+                            label(&block.handler),
+                            OrNull(block.ty.as_deref())
+                        )?;
+                    }
+                }
+                MethodEvent::TryCatchBlockAnnotations(annotations) => {
+                    for annotation in annotations.0 {
+                        write!(out, "{INSN_INDENT}")?;
+                        write_type_annotation(out, &annotation.annotation)?;
+                        writeln!(
+                            out,
+                            " // try catch block {}, {}",
+                            annotation.try_catch_block_index,
+                            visibility(annotation.visible)
+                        )?;
+                    }
+                }
+                MethodEvent::Maxs(maxs) => {
+                    writeln!(out, "{INSN_INDENT}maxstack = {}", maxs.max_stack)?;
+                    writeln!(out, "{INSN_INDENT}maxlocals = {}", maxs.max_locals)?;
+                }
+                MethodEvent::Unchanged(unchanged) => writeln!(
+                    out,
+                    "{INDENT}// unchanged, {} bytes",
+                    unchanged.bytes().len()
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the names of the given flags in lowercase, each followed by a space.
+fn write_access<W: Write, F: Flags>(out: &mut W, access: F) -> fmt::Result {
+    for (name, _) in access.iter_names() {
+        write!(out, "{} ", name.to_lowercase())?;
+    }
+    Ok(())
+}
+
+fn join(names: &[impl AsRef<JavaStr>]) -> String {
+    names
+        .iter()
+        .map(|name| name.as_ref().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn visibility(visible: bool) -> &'static str {
+    if visible {
+        "visible"
+    } else {
+        "invisible"
+    }
+}
+
+/// Displays an optional name, or `null` if there is none.
+struct OrNull<'a>(Option<&'a JavaStr>);
+
+impl fmt::Display for OrNull<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "null"),
+        }
+    }
+}
+
+fn write_annotations<W: Write>(
+    out: &mut W,
+    indent: &str,
+    annotations: Vec<AnnotationEvent<AnnotationNode<'_>>>,
+) -> fmt::Result {
+    for annotation in annotations {
+        write!(out, "{indent}")?;
+        write_annotation(out, &annotation.annotation)?;
+        writeln!(out, " // {}", visibility(annotation.visible))?;
+    }
+    Ok(())
+}
+
+fn write_type_annotations<W: Write>(
+    out: &mut W,
+    indent: &str,
+    annotations: Vec<AnnotationEvent<TypeAnnotationNode<'_>>>,
+) -> fmt::Result {
+    for annotation in annotations {
+        write!(out, "{indent}")?;
+        write_type_annotation(out, &annotation.annotation)?;
+        writeln!(out, " // {}", visibility(annotation.visible))?;
+    }
+    Ok(())
+}
+
+fn write_annotation<W: Write>(out: &mut W, annotation: &AnnotationNode<'_>) -> fmt::Result {
+    write!(out, "@{}", annotation.desc)?;
+    write_annotation_values(out, &annotation.values)
+}
+
+fn write_type_annotation<W: Write>(
+    out: &mut W,
+    annotation: &TypeAnnotationNode<'_>,
+) -> fmt::Result {
+    write!(out, "@{}", annotation.desc)?;
+    write_annotation_values(out, &annotation.values)?;
+    write!(out, " : {:?}", annotation.type_ref)?;
+    if !annotation.type_path.is_empty() {
+        write!(out, ", {}", annotation.type_path)?;
+    }
+    Ok(())
+}
+
+fn write_annotation_values<W: Write>(
+    out: &mut W,
+    values: &[(impl AsRef<JavaStr>, AnnotationValue<'_>)],
+) -> fmt::Result {
+    write!(out, "(")?;
+    for (index, (name, value)) in values.iter().enumerate() {
+        if index != 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "{}=", name.as_ref())?;
+        write_annotation_value(out, value)?;
+    }
+    write!(out, ")")
+}
+
+fn write_annotation_value<W: Write>(out: &mut W, value: &AnnotationValue<'_>) -> fmt::Result {
+    match value {
+        AnnotationValue::Byte(value) => write!(out, "(byte) {value}"),
+        AnnotationValue::Char(value) => match char::from_u32(u32::from(*value)) {
+            Some(value) => write!(out, "{value:?}"),
+            None => write!(out, "(char) {value}"),
+        },
+        AnnotationValue::Double(value) => write!(out, "{value:?}D"),
+        AnnotationValue::Float(value) => write!(out, "{value:?}F"),
+        AnnotationValue::Int(value) => write!(out, "{value}"),
+        AnnotationValue::Long(value) => write!(out, "{value}L"),
+        AnnotationValue::Short(value) => write!(out, "(short) {value}"),
+        AnnotationValue::Boolean(value) => write!(out, "{value}"),
+        AnnotationValue::String(value) => write!(out, "{value:?}"),
+        AnnotationValue::Enum { desc, name } => write!(out, "{desc}.{name}"),
+        AnnotationValue::Class(desc) => write!(out, "{desc}.class"),
+        AnnotationValue::Annotation(annotation) => write_annotation(out, annotation),
+        AnnotationValue::Array(values) => {
+            write!(out, "{{")?;
+            for (index, value) in values.iter().enumerate() {
+                if index != 0 {
+                    write!(out, ", ")?;
+                }
+                write_annotation_value(out, value)?;
+            }
+            write!(out, "}}")
+        }
+    }
+}
+
+fn write_frame<W: Write>(
+    out: &mut W,
+    frame: &Frame<'_>,
+    label: &impl Fn(&Label) -> String,
+) -> fmt::Result {
+    match frame {
+        Frame::Full { locals, stack } => {
+            write!(out, "full ")?;
+            write_frame_values(out, locals, label)?;
+            write!(out, " ")?;
+            write_frame_values(out, stack, label)
+        }
+        Frame::New { locals, stack } => {
+            write!(out, "new ")?;
+            write_frame_values(out, locals, label)?;
+            write!(out, " ")?;
+            write_frame_values(out, stack, label)
+        }
+        Frame::Append { locals } => {
+            write!(out, "append ")?;
+            write_frame_values(out, locals, label)
+        }
+        Frame::Chop { num_locals } => write!(out, "chop {num_locals}"),
+        Frame::Same => write!(out, "same"),
+        Frame::Same1 { stack_value } => {
+            write!(out, "same1 ")?;
+            write_frame_values(out, std::slice::from_ref(stack_value), label)
+        }
+    }
+}
+
+fn write_frame_values<W: Write>(
+    out: &mut W,
+    values: &[FrameValue<'_>],
+    label: &impl Fn(&Label) -> String,
+) -> fmt::Result {
+    write!(out, "[")?;
+    for (index, value) in values.iter().enumerate() {
+        if index != 0 {
+            write!(out, ", ")?;
+        }
+        match value {
+            FrameValue::Top => write!(out, "top")?,
+            FrameValue::Integer => write!(out, "int")?,
+            FrameValue::Float => write!(out, "float")?,
+            FrameValue::Long => write!(out, "long")?,
+            FrameValue::Double => write!(out, "double")?,
+            FrameValue::Null => write!(out, "null")?,
+            FrameValue::UninitializedThis => write!(out, "uninitialized_this")?,
+            FrameValue::Class(name) => write!(out, "{name}")?,
+            FrameValue::Uninitialized(target) => write!(out, "uninitialized {}", label(target))?,
+        }
+    }
+    write!(out, "]")
+}
+
+fn write_ldc_constant<W: Write>(out: &mut W, constant: &LdcConstant<'_>) -> fmt::Result {
+    match constant {
+        LdcConstant::Integer(value) => write!(out, "{value}"),
+        LdcConstant::Float(value) => write!(out, "{value:?}F"),
+        LdcConstant::Long(value) => write!(out, "{value}L"),
+        LdcConstant::Double(value) => write!(out, "{value:?}D"),
+        LdcConstant::String(value) => write!(out, "{value:?}"),
+        LdcConstant::Class(name) => write!(out, "{name}.class"),
+        LdcConstant::MethodType(desc) => write!(out, "methodtype {desc}"),
+        LdcConstant::Handle(handle) => write_handle(out, handle),
+        LdcConstant::ConstantDynamic(constant) => write_constant_dynamic(out, constant),
+    }
+}
+
+fn write_handle<W: Write>(out: &mut W, handle: &Handle<'_>) -> fmt::Result {
+    write!(
+        out,
+        "{} {}.{}{}",
+        handle.kind, handle.owner, handle.name, handle.desc
+    )?;
+    if handle.is_interface {
+        write!(out, " (itf)")?;
+    }
+    Ok(())
+}
+
+fn write_constant_dynamic<W: Write>(out: &mut W, constant: &ConstantDynamic<'_>) -> fmt::Result {
+    write!(out, "condy {} : {} [", constant.name, constant.desc)?;
+    write_handle(out, &constant.bootstrap_method)?;
+    if !constant.bootstrap_method_arguments.is_empty() {
+        write!(out, ", ")?;
+        write_bootstrap_arguments(out, &constant.bootstrap_method_arguments)?;
+    }
+    write!(out, "]")
+}
+
+fn write_bootstrap_arguments<W: Write>(
+    out: &mut W,
+    arguments: &[BootstrapMethodArgument<'_>],
+) -> fmt::Result {
+    for (index, argument) in arguments.iter().enumerate() {
+        if index != 0 {
+            write!(out, ", ")?;
+        }
+        match argument {
+            BootstrapMethodArgument::Integer(value) => write!(out, "{value}")?,
+            BootstrapMethodArgument::Float(value) => write!(out, "{value:?}F")?,
+            BootstrapMethodArgument::Long(value) => write!(out, "{value}L")?,
+            BootstrapMethodArgument::Double(value) => write!(out, "{value:?}D")?,
+            BootstrapMethodArgument::String(value) => write!(out, "{value:?}")?,
+            BootstrapMethodArgument::Class(name) => write!(out, "{name}.class")?,
+            BootstrapMethodArgument::Handle(handle) => write_handle(out, handle)?,
+            BootstrapMethodArgument::ConstantDynamic(constant) => {
+                write_constant_dynamic(out, constant)?
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassReader, ClassReaderFlags, Textifier};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_textify_hello_world() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let text = Textifier::new().textify(&reader).unwrap();
+        assert!(text.contains("public super class HelloWorld\n  extends java/lang/Object\n"));
+        assert!(text.contains("  public <init>()V\n     L0\n      line 2 L0\n      aload 0\n"));
+        assert!(text.contains("      invokespecial java/lang/Object.<init>()V\n      return\n"));
+        assert!(text.contains("      ldc \"Hello, World!\"\n"));
+        assert!(text.contains("     L1\n      line 5 L1\n"));
+        assert!(text.contains("      maxstack = 2\n      maxlocals = 1\n"));
+    }
+
+    #[test]
+    fn test_textify_code() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let mut textifier = Textifier::new();
+        let text = textifier.textify(&reader).unwrap();
+        assert!(text.contains("      frame "));
+        assert!(text.contains("      trycatch "));
+        assert!(!text.contains('?'));
+
+        textifier.set_show_frames(false);
+        let text = textifier.textify(&reader).unwrap();
+        assert!(!text.contains("      frame "));
+    }
+
+    #[test]
+    fn test_textify_signature() {
+        let reader =
+            ClassReader::new(include_class!("TestSignature"), ClassReaderFlags::None).unwrap();
+        let text = Textifier::new().textify(&reader).unwrap();
+        assert!(text.contains("// signature <T:Ljava/lang/Object;>Ljava/lang/Object;\n"));
+        assert!(text.contains("// declaration: <T>\n"));
+    }
+}
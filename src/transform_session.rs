@@ -0,0 +1,366 @@
+use crate::{
+    buffer_class_events, BootstrapMethodArgument, BufferedClassEvents, BufferedEventProviders,
+    ClassEvent, ClassFileError, ClassFileResult, ClassReader, ClassWriter, ConstantDynamic, Handle,
+    HandleKind, LdcConstant, MethodEvent,
+};
+use derive_more::Debug;
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+type Rewrite<'class> =
+    Box<dyn FnOnce(&mut BufferedClassEvents<'class>) -> ClassFileResult<()> + 'class>;
+
+/// Collects rewrites of several classes which depend on each other, such as renaming a method
+/// along with its call sites, and applies them together.
+///
+/// Nothing is rewritten until [`apply`](Self::apply), which either produces every modified class
+/// or fails without producing any. References between classes are resolved against the classes as
+/// they were added, before any rewrites.
+#[derive(Debug, Default)]
+pub struct TransformSession<'class> {
+    classes: BTreeMap<JavaString, ClassReader<'class>>,
+    method_renames: Vec<MethodRename>,
+    #[debug(skip)]
+    rewrites: Vec<(JavaString, Rewrite<'class>)>,
+}
+
+#[derive(Debug)]
+struct MethodRename {
+    owner: JavaString,
+    name: JavaString,
+    desc: JavaString,
+    new_name: JavaString,
+}
+
+impl<'class> TransformSession<'class> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a class which can be rewritten, and whose references to other classes are updated.
+    pub fn add_class(&mut self, reader: ClassReader<'class>) -> ClassFileResult<()> {
+        let name = reader.name()?.into_owned();
+        self.classes.insert(name, reader);
+        Ok(())
+    }
+
+    /// Renames a method, along with the method instructions and method handles in all classes of
+    /// the session which resolve to it. Methods overriding it are not renamed.
+    pub fn rename_method(
+        &mut self,
+        owner: impl Into<JavaString>,
+        name: impl Into<JavaString>,
+        desc: impl Into<JavaString>,
+        new_name: impl Into<JavaString>,
+    ) {
+        self.method_renames.push(MethodRename {
+            owner: owner.into(),
+            name: name.into(),
+            desc: desc.into(),
+            new_name: new_name.into(),
+        });
+    }
+
+    /// Adds an arbitrary rewrite of a class, which runs after the renames.
+    pub fn rewrite_class<F>(&mut self, class_name: impl Into<JavaString>, rewrite: F)
+    where
+        F: FnOnce(&mut BufferedClassEvents<'class>) -> ClassFileResult<()> + 'class,
+    {
+        self.rewrites.push((class_name.into(), Box::new(rewrite)));
+    }
+
+    /// Applies all rewrites, and returns the bytes of the modified classes by name.
+    pub fn apply(
+        self,
+        class_writer: &ClassWriter,
+    ) -> ClassFileResult<BTreeMap<JavaString, Vec<u8>>> {
+        for owner in self
+            .method_renames
+            .iter()
+            .map(|rename| &rename.owner)
+            .chain(self.rewrites.iter().map(|(class_name, _)| class_name))
+        {
+            if !self.classes.contains_key(owner) {
+                return Err(ClassFileError::UnknownClass(owner.clone()));
+            }
+        }
+
+        let mut class_events = BTreeMap::new();
+        let mut hierarchy = HashMap::new();
+        for (name, reader) in &self.classes {
+            let events = buffer_class_events(reader)?;
+            hierarchy.insert(name.clone(), ClassInfo::new(&events));
+            class_events.insert(name.clone(), events);
+        }
+        let renamer = MethodRenamer {
+            hierarchy: &hierarchy,
+            renames: &self.method_renames,
+        };
+
+        let mut modified = HashSet::new();
+        for (name, events) in &mut class_events {
+            if renamer.rename(name, events) {
+                modified.insert(name.clone());
+            }
+        }
+        for (class_name, rewrite) in self.rewrites {
+            let events = class_events
+                .get_mut(&class_name)
+                .expect("rewritten classes were checked to exist");
+            rewrite(events)?;
+            modified.insert(class_name);
+        }
+
+        class_events
+            .into_iter()
+            .filter(|(name, _)| modified.contains(name))
+            .map(|(name, events)| Ok((name, class_writer.write(events)?)))
+            .collect()
+    }
+}
+
+/// What's needed to resolve method references to a class.
+struct ClassInfo {
+    super_name: Option<JavaString>,
+    interfaces: Vec<JavaString>,
+    /// The descriptors of the declared methods, by name.
+    methods: HashMap<JavaString, HashSet<JavaString>>,
+}
+
+impl ClassInfo {
+    fn new(events: &BufferedClassEvents) -> Self {
+        let mut info = ClassInfo {
+            super_name: None,
+            interfaces: Vec::new(),
+            methods: HashMap::new(),
+        };
+        for event in &events.0 {
+            match event {
+                ClassEvent::Class(class) => {
+                    info.super_name = class.super_name.as_deref().map(JavaStr::to_owned);
+                    info.interfaces = class
+                        .interfaces
+                        .iter()
+                        .map(|interface| interface.as_ref().to_owned())
+                        .collect();
+                }
+                ClassEvent::Methods(methods) => {
+                    for method in &methods.0 {
+                        info.methods
+                            .entry(method.name.as_ref().to_owned())
+                            .or_default()
+                            .insert(method.desc.as_ref().to_owned());
+                    }
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+
+    fn declares(&self, name: &JavaStr, desc: &JavaStr) -> bool {
+        self.methods
+            .get(name)
+            .is_some_and(|descs| descs.contains(desc))
+    }
+}
+
+struct MethodRenamer<'a> {
+    hierarchy: &'a HashMap<JavaString, ClassInfo>,
+    renames: &'a [MethodRename],
+}
+
+impl MethodRenamer<'_> {
+    /// Renames the declarations and references in a class, returning whether anything changed.
+    fn rename(&self, class_name: &JavaStr, events: &mut BufferedClassEvents) -> bool {
+        if self.renames.is_empty() {
+            return false;
+        }
+        let mut changed = false;
+        for event in &mut events.0 {
+            let ClassEvent::Methods(methods) = event else {
+                continue;
+            };
+            for method in &mut methods.0 {
+                if let Some(new_name) = self.new_name(class_name, &method.name, &method.desc) {
+                    method.name = Cow::Owned(new_name.to_owned());
+                    changed = true;
+                }
+                for event in &mut method.events.0 {
+                    changed |= self.rename_insn(event);
+                }
+            }
+        }
+        changed
+    }
+
+    fn rename_insn(&self, event: &mut MethodEvent<'_, BufferedEventProviders>) -> bool {
+        match event {
+            MethodEvent::MethodInsn {
+                owner, name, desc, ..
+            } => self.rename_reference(owner, name, desc),
+            MethodEvent::InvokeDynamicInsn {
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+                ..
+            } => {
+                self.rename_handle(bootstrap_method_handle)
+                    | self.rename_arguments(bootstrap_method_arguments)
+            }
+            MethodEvent::LdcInsn(LdcConstant::Handle(handle)) => self.rename_handle(handle),
+            MethodEvent::LdcInsn(LdcConstant::ConstantDynamic(constant)) => {
+                self.rename_constant_dynamic(constant)
+            }
+            _ => false,
+        }
+    }
+
+    fn rename_handle(&self, handle: &mut Handle) -> bool {
+        match handle.kind {
+            HandleKind::GetField
+            | HandleKind::GetStatic
+            | HandleKind::PutField
+            | HandleKind::PutStatic => false,
+            HandleKind::InvokeVirtual
+            | HandleKind::InvokeStatic
+            | HandleKind::InvokeSpecial
+            | HandleKind::NewInvokeSpecial
+            | HandleKind::InvokeInterface => {
+                self.rename_reference(&handle.owner, &mut handle.name, &handle.desc)
+            }
+        }
+    }
+
+    fn rename_arguments(&self, arguments: &mut [BootstrapMethodArgument]) -> bool {
+        let mut changed = false;
+        for argument in arguments {
+            changed |= match argument {
+                BootstrapMethodArgument::Handle(handle) => self.rename_handle(handle),
+                BootstrapMethodArgument::ConstantDynamic(constant) => {
+                    self.rename_constant_dynamic(constant)
+                }
+                _ => false,
+            };
+        }
+        changed
+    }
+
+    fn rename_constant_dynamic(&self, constant: &mut ConstantDynamic) -> bool {
+        self.rename_handle(&mut constant.bootstrap_method)
+            | self.rename_arguments(&mut constant.bootstrap_method_arguments)
+    }
+
+    fn rename_reference(&self, owner: &JavaStr, name: &mut Cow<JavaStr>, desc: &JavaStr) -> bool {
+        match self.new_name(owner, name, desc) {
+            Some(new_name) => {
+                *name = Cow::Owned(new_name.to_owned());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The new name of the method that a reference resolves to, if it's renamed.
+    fn new_name(&self, owner: &JavaStr, name: &JavaStr, desc: &JavaStr) -> Option<&JavaStr> {
+        let rename = self
+            .renames
+            .iter()
+            .find(|rename| rename.name == name && rename.desc == desc)?;
+        (self.resolve(owner, name, desc)? == rename.owner).then_some(&rename.new_name)
+    }
+
+    /// Finds the class declaring the method that a reference resolves to, by searching the
+    /// superclasses and then the superinterfaces. Returns `None` if the search leaves the session.
+    fn resolve(&self, owner: &JavaStr, name: &JavaStr, desc: &JavaStr) -> Option<&JavaStr> {
+        let mut interfaces: Vec<&JavaStr> = Vec::new();
+        let mut class = Some(owner);
+        while let Some(class_name) = class {
+            let (key, info) = self.hierarchy.get_key_value(class_name)?;
+            if info.declares(name, desc) {
+                return Some(key);
+            }
+            interfaces.extend(info.interfaces.iter().map(|interface| &**interface));
+            class = info.super_name.as_deref();
+        }
+
+        let mut visited = HashSet::new();
+        while let Some(interface) = interfaces.pop() {
+            if !visited.insert(interface) {
+                continue;
+            }
+            let (key, info) = self.hierarchy.get_key_value(interface)?;
+            if info.declares(name, desc) {
+                return Some(key);
+            }
+            interfaces.extend(info.interfaces.iter().map(|interface| &**interface));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassEvent, ClassEventSource, ClassReader, ClassReaderFlags, ClassWriter, ClassWriterFlags,
+        MethodEvent, TransformSession,
+    };
+    use java_string::JavaStr;
+    use test_helpers::include_class;
+
+    fn method_refs(bytecode: &[u8]) -> Vec<String> {
+        let reader = ClassReader::new(bytecode, ClassReaderFlags::None).unwrap();
+        let mut result = Vec::new();
+        for event in reader.events().unwrap() {
+            let ClassEvent::Methods(methods) = event.unwrap() else {
+                continue;
+            };
+            for method in methods {
+                let method = method.unwrap();
+                result.push(method.name.to_string());
+                for event in method.events {
+                    if let MethodEvent::MethodInsn { owner, name, .. } = event.unwrap() {
+                        result.push(format!("{owner}.{name}"));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_rename_method() {
+        let mut session = TransformSession::new();
+        let bytecodes: [&[u8]; 3] = [
+            include_class!("TestCallee"),
+            include_class!("TestCaller"),
+            include_class!("HelloWorld"),
+        ];
+        for bytecode in bytecodes {
+            session
+                .add_class(ClassReader::new(bytecode, ClassReaderFlags::None).unwrap())
+                .unwrap();
+        }
+        session.rename_method("TestCallee", "value", "()I", "renamedValue");
+        session.rename_method("TestCallee", "inst", "()I", "renamedInst");
+        let classes = session
+            .apply(&ClassWriter::new(ClassWriterFlags::None))
+            .unwrap();
+
+        assert_eq!(
+            vec!["TestCallee", "TestCaller"],
+            classes
+                .keys()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        );
+        let callee = method_refs(&classes[JavaStr::from_str("TestCallee")]);
+        assert!(callee.contains(&"renamedValue".to_owned()));
+        assert!(callee.contains(&"renamedInst".to_owned()));
+        let caller = method_refs(&classes[JavaStr::from_str("TestCaller")]);
+        assert!(caller.contains(&"TestCallee.renamedValue".to_owned()));
+        assert!(caller.contains(&"TestCallee.renamedInst".to_owned()));
+        assert!(caller.contains(&"TestCaller.value".to_owned()));
+        assert!(caller.contains(&"value".to_owned()));
+    }
+}
@@ -3,12 +3,14 @@ use java_string::JavaStr;
 use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnnotationNode<'class> {
     pub desc: Cow<'class, JavaStr>,
     pub values: Vec<(Cow<'class, JavaStr>, AnnotationValue<'class>)>,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeAnnotationNode<'class> {
     pub type_ref: TypeReference,
     pub type_path: TypePath<'class>,
@@ -17,6 +19,7 @@ pub struct TypeAnnotationNode<'class> {
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AnnotationValue<'class> {
     Byte(i8),
     Char(u16),
@@ -35,3 +38,290 @@ pub enum AnnotationValue<'class> {
     Annotation(AnnotationNode<'class>),
     Array(Vec<AnnotationValue<'class>>),
 }
+
+impl<'class> AnnotationNode<'class> {
+    pub fn new(desc: impl Into<Cow<'class, JavaStr>>) -> Self {
+        AnnotationNode {
+            desc: desc.into(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, name: &JavaStr) -> Option<&AnnotationValue<'class>> {
+        self.values
+            .iter()
+            .find(|(value_name, _)| value_name.as_ref() == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Gets a value, returning `None` if it's missing or of a different kind.
+    pub fn get_as<'a, T>(&'a self, name: &JavaStr) -> Option<T>
+    where
+        T: FromAnnotationValue<'a, 'class>,
+    {
+        T::from_annotation_value(self.get(name)?)
+    }
+
+    pub fn get_int(&self, name: &JavaStr) -> Option<i32> {
+        self.get_as(name)
+    }
+
+    pub fn get_long(&self, name: &JavaStr) -> Option<i64> {
+        self.get_as(name)
+    }
+
+    pub fn get_boolean(&self, name: &JavaStr) -> Option<bool> {
+        self.get_as(name)
+    }
+
+    pub fn get_string(&self, name: &JavaStr) -> Option<&JavaStr> {
+        self.get_as(name)
+    }
+
+    /// Gets the descriptor of a class value.
+    pub fn get_class(&self, name: &JavaStr) -> Option<&JavaStr> {
+        match self.get(name)? {
+            AnnotationValue::Class(desc) => Some(desc),
+            _ => None,
+        }
+    }
+
+    /// Gets the descriptor and constant name of an enum value.
+    pub fn get_enum(&self, name: &JavaStr) -> Option<(&JavaStr, &JavaStr)> {
+        self.get_as(name)
+    }
+
+    pub fn get_annotation(&self, name: &JavaStr) -> Option<&AnnotationNode<'class>> {
+        self.get_as(name)
+    }
+
+    pub fn get_array(&self, name: &JavaStr) -> Option<&[AnnotationValue<'class>]> {
+        match self.get(name)? {
+            AnnotationValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Gets an array whose elements are all of the given kind.
+    pub fn get_array_of<'a, T>(&'a self, name: &JavaStr) -> Option<Vec<T>>
+    where
+        T: FromAnnotationValue<'a, 'class>,
+    {
+        self.get_array(name)?
+            .iter()
+            .map(T::from_annotation_value)
+            .collect()
+    }
+}
+
+/// A kind of annotation value, which can be read by [`AnnotationNode::get_as`].
+pub trait FromAnnotationValue<'a, 'class>: Sized {
+    fn from_annotation_value(value: &'a AnnotationValue<'class>) -> Option<Self>;
+}
+
+macro_rules! from_annotation_value {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl FromAnnotationValue<'_, '_> for $ty {
+                fn from_annotation_value(value: &AnnotationValue<'_>) -> Option<Self> {
+                    match value {
+                        AnnotationValue::$variant(value) => Some(*value),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl From<$ty> for AnnotationValue<'_> {
+                fn from(value: $ty) -> Self {
+                    AnnotationValue::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+from_annotation_value! {
+    i8 => Byte,
+    u16 => Char,
+    f64 => Double,
+    f32 => Float,
+    i32 => Int,
+    i64 => Long,
+    i16 => Short,
+    bool => Boolean,
+}
+
+impl<'a> FromAnnotationValue<'a, '_> for &'a JavaStr {
+    fn from_annotation_value(value: &'a AnnotationValue<'_>) -> Option<Self> {
+        match value {
+            AnnotationValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// An enum value, as its descriptor and constant name.
+impl<'a> FromAnnotationValue<'a, '_> for (&'a JavaStr, &'a JavaStr) {
+    fn from_annotation_value(value: &'a AnnotationValue<'_>) -> Option<Self> {
+        match value {
+            AnnotationValue::Enum { desc, name } => Some((desc, name)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, 'class> FromAnnotationValue<'a, 'class> for &'a AnnotationNode<'class> {
+    fn from_annotation_value(value: &'a AnnotationValue<'class>) -> Option<Self> {
+        match value {
+            AnnotationValue::Annotation(annotation) => Some(annotation),
+            _ => None,
+        }
+    }
+}
+
+impl<'class> From<Cow<'class, JavaStr>> for AnnotationValue<'class> {
+    fn from(value: Cow<'class, JavaStr>) -> Self {
+        AnnotationValue::String(value)
+    }
+}
+
+impl<'class> From<&'class JavaStr> for AnnotationValue<'class> {
+    fn from(value: &'class JavaStr) -> Self {
+        AnnotationValue::String(Cow::Borrowed(value))
+    }
+}
+
+impl<'class> From<AnnotationNode<'class>> for AnnotationValue<'class> {
+    fn from(value: AnnotationNode<'class>) -> Self {
+        AnnotationValue::Annotation(value)
+    }
+}
+
+/// Builds an [`AnnotationNode`]. The elements of an array all have the same kind, as they are
+/// converted from the same type.
+#[derive(Debug, Clone)]
+pub struct AnnotationBuilder<'class> {
+    annotation: AnnotationNode<'class>,
+}
+
+impl<'class> AnnotationBuilder<'class> {
+    pub fn new(desc: impl Into<Cow<'class, JavaStr>>) -> Self {
+        AnnotationBuilder {
+            annotation: AnnotationNode::new(desc),
+        }
+    }
+
+    /// Adds a primitive, string or nested annotation value.
+    pub fn value(
+        mut self,
+        name: impl Into<Cow<'class, JavaStr>>,
+        value: impl Into<AnnotationValue<'class>>,
+    ) -> Self {
+        self.annotation.values.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn enum_value(
+        self,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+        constant: impl Into<Cow<'class, JavaStr>>,
+    ) -> Self {
+        self.value(
+            name,
+            AnnotationValue::Enum {
+                desc: desc.into(),
+                name: constant.into(),
+            },
+        )
+    }
+
+    pub fn class_value(
+        self,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) -> Self {
+        self.value(name, AnnotationValue::Class(desc.into()))
+    }
+
+    pub fn array<T>(
+        self,
+        name: impl Into<Cow<'class, JavaStr>>,
+        values: impl IntoIterator<Item = T>,
+    ) -> Self
+    where
+        T: Into<AnnotationValue<'class>>,
+    {
+        self.value(
+            name,
+            AnnotationValue::Array(values.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    pub fn build(self) -> AnnotationNode<'class> {
+        self.annotation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{AnnotationBuilder, AnnotationNode, AnnotationValue};
+    use java_string::JavaStr;
+
+    fn str(value: &str) -> &JavaStr {
+        JavaStr::from_str(value)
+    }
+
+    #[test]
+    fn test_annotation_builder() {
+        let inner = AnnotationBuilder::new(str("LInner;"))
+            .value(str("value"), true)
+            .build();
+        let annotation = AnnotationBuilder::new(str("LTest;"))
+            .value(str("count"), 3)
+            .value(str("name"), str("test"))
+            .enum_value(
+                str("type"),
+                str("Ljava/lang/annotation/ElementType;"),
+                str("FIELD"),
+            )
+            .class_value(str("type2"), str("Ljava/lang/String;"))
+            .array(str("ids"), [1i64, 2, 3])
+            .array(str("names"), [str("a"), str("b")])
+            .value(str("inner"), inner.clone())
+            .build();
+
+        assert_eq!(Some(3), annotation.get_int(str("count")));
+        assert_eq!(None, annotation.get_long(str("count")));
+        assert_eq!(None, annotation.get_int(str("missing")));
+        assert_eq!(Some(str("test")), annotation.get_string(str("name")));
+        assert_eq!(
+            Some((str("Ljava/lang/annotation/ElementType;"), str("FIELD"))),
+            annotation.get_enum(str("type"))
+        );
+        assert_eq!(
+            Some(str("Ljava/lang/String;")),
+            annotation.get_class(str("type2"))
+        );
+        assert_eq!(
+            Some(vec![1, 2, 3]),
+            annotation.get_array_of::<i64>(str("ids"))
+        );
+        assert_eq!(None, annotation.get_array_of::<i32>(str("ids")));
+        assert_eq!(
+            Some(vec![str("a"), str("b")]),
+            annotation.get_array_of::<&JavaStr>(str("names"))
+        );
+        assert_eq!(Some(&inner), annotation.get_annotation(str("inner")));
+        assert_eq!(
+            Some(&AnnotationValue::Boolean(true)),
+            annotation
+                .get_annotation(str("inner"))
+                .and_then(|inner| inner.get(str("value")))
+        );
+        assert_eq!(
+            AnnotationNode::new(str("LEmpty;")),
+            AnnotationBuilder::new(str("LEmpty;")).build()
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use crate::tree::{
+    AnnotationNode, FieldNode, MethodNode, ModuleNode, RecordComponentNode, TypeAnnotationNode,
+};
+use crate::{
+    AnnotationEvent, Attribute, BufferedClassEvents, BufferedEventProviders, ClassAccess,
+    ClassClassEvent, ClassEvent, ClassEventSource, ClassFileError, ClassFileResult,
+    ClassInnerClassEvent, ClassOuterClassEvent, ClassSourceEvent, EventBuffer,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+/// A class held in memory, which can be modified freely and then passed on as events, in the same
+/// way as a [`ClassReader`](crate::ClassReader).
+///
+/// With the `serde` feature, custom attributes and unchanged methods are skipped when serializing.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassNode<'class> {
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub access: ClassAccess,
+    pub name: Cow<'class, JavaStr>,
+    pub signature: Option<Cow<'class, JavaStr>>,
+    pub super_name: Option<Cow<'class, JavaStr>>,
+    pub interfaces: Vec<Cow<'class, JavaStr>>,
+    /// Whether the class is marked synthetic by an attribute, rather than the access flags.
+    pub synthetic: bool,
+    pub deprecated: bool,
+    pub source: Option<ClassSourceEvent<'class>>,
+    pub module: Option<ModuleNode<'class>>,
+    pub nest_host: Option<Cow<'class, JavaStr>>,
+    pub outer_class: Option<ClassOuterClassEvent<'class>>,
+    pub annotations: Vec<AnnotationEvent<AnnotationNode<'class>>>,
+    pub type_annotations: Vec<AnnotationEvent<TypeAnnotationNode<'class>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub attributes: Vec<Box<dyn Attribute>>,
+    pub nest_members: Vec<Cow<'class, JavaStr>>,
+    pub permitted_subclasses: Vec<Cow<'class, JavaStr>>,
+    #[cfg(feature = "preview")]
+    pub loadable_descriptors: Vec<Cow<'class, JavaStr>>,
+    pub inner_classes: Vec<ClassInnerClassEvent<'class>>,
+    /// The record components, if the class is a record.
+    pub record_components: Option<Vec<RecordComponentNode<'class>>>,
+    pub fields: Vec<FieldNode<'class>>,
+    pub methods: Vec<MethodNode<'class>>,
+}
+
+impl<'class> ClassNode<'class> {
+    pub fn new(class: ClassClassEvent<'class>) -> Self {
+        ClassNode {
+            major_version: class.major_version,
+            minor_version: class.minor_version,
+            access: class.access,
+            name: class.name,
+            signature: class.signature,
+            super_name: class.super_name,
+            interfaces: class.interfaces,
+            synthetic: false,
+            deprecated: false,
+            source: None,
+            module: None,
+            nest_host: None,
+            outer_class: None,
+            annotations: Vec::new(),
+            type_annotations: Vec::new(),
+            attributes: Vec::new(),
+            nest_members: Vec::new(),
+            permitted_subclasses: Vec::new(),
+            #[cfg(feature = "preview")]
+            loadable_descriptors: Vec::new(),
+            inner_classes: Vec::new(),
+            record_components: None,
+            fields: Vec::new(),
+            methods: Vec::new(),
+        }
+    }
+
+    /// Reads all the events from a source into a new node, returning the first error.
+    pub fn from_events<S>(source: S) -> ClassFileResult<Self>
+    where
+        S: ClassEventSource<'class>,
+    {
+        let mut events = source.events()?;
+        let mut node = match events.next().transpose()? {
+            Some(ClassEvent::Class(class)) => ClassNode::new(class),
+            _ => return Err(ClassFileError::MissingClassEvent),
+        };
+        for event in events {
+            match event? {
+                ClassEvent::Class(_) => return Err(ClassFileError::DuplicateClassEvent),
+                ClassEvent::Synthetic => node.synthetic = true,
+                ClassEvent::Deprecated => node.deprecated = true,
+                ClassEvent::Source(source) => node.source = Some(source),
+                ClassEvent::Module(module) => node.module = Some(ModuleNode::from_event(module)?),
+                ClassEvent::NestHost(nest_host) => node.nest_host = Some(nest_host),
+                ClassEvent::OuterClass(outer_class) => node.outer_class = Some(outer_class),
+                ClassEvent::Annotations(events) => extend(&mut node.annotations, events)?,
+                ClassEvent::TypeAnnotations(events) => extend(&mut node.type_annotations, events)?,
+                ClassEvent::Attributes(events) => extend(&mut node.attributes, events)?,
+                ClassEvent::NestMembers(events) => extend(&mut node.nest_members, events)?,
+                ClassEvent::PermittedSubclasses(events) => {
+                    extend(&mut node.permitted_subclasses, events)?
+                }
+                #[cfg(feature = "preview")]
+                ClassEvent::LoadableDescriptors(descs) => node.loadable_descriptors.extend(descs),
+                ClassEvent::InnerClasses(events) => extend(&mut node.inner_classes, events)?,
+                ClassEvent::Record(components) => {
+                    let record_components = node.record_components.get_or_insert_with(Vec::new);
+                    for component in components {
+                        record_components.push(RecordComponentNode::from_event(component?)?);
+                    }
+                }
+                ClassEvent::Fields(fields) => {
+                    for field in fields {
+                        node.fields.push(FieldNode::from_event(field?)?);
+                    }
+                }
+                ClassEvent::Methods(methods) => {
+                    for method in methods {
+                        node.methods.push(MethodNode::from_event(method?)?);
+                    }
+                }
+            }
+        }
+        Ok(node)
+    }
+
+    pub fn class_event(&self) -> ClassClassEvent<'class> {
+        ClassClassEvent {
+            major_version: self.major_version,
+            minor_version: self.minor_version,
+            access: self.access,
+            name: self.name.clone(),
+            signature: self.signature.clone(),
+            super_name: self.super_name.clone(),
+            interfaces: self.interfaces.clone(),
+        }
+    }
+
+    /// Copies the class into events, in the order a [`ClassReader`](crate::ClassReader) would
+    /// produce them.
+    pub fn to_events(&self) -> BufferedClassEvents<'class> {
+        let mut events = vec![ClassEvent::Class(self.class_event())];
+        if self.synthetic {
+            events.push(ClassEvent::Synthetic);
+        }
+        if self.deprecated {
+            events.push(ClassEvent::Deprecated);
+        }
+        if let Some(source) = &self.source {
+            events.push(ClassEvent::Source(source.clone()));
+        }
+        if let Some(module) = &self.module {
+            events.push(ClassEvent::Module(module.to_event()));
+        }
+        if let Some(nest_host) = &self.nest_host {
+            events.push(ClassEvent::NestHost(nest_host.clone()));
+        }
+        if let Some(outer_class) = &self.outer_class {
+            events.push(ClassEvent::OuterClass(outer_class.clone()));
+        }
+        if !self.annotations.is_empty() {
+            events.push(ClassEvent::Annotations(EventBuffer(
+                self.annotations.clone(),
+            )));
+        }
+        if !self.type_annotations.is_empty() {
+            events.push(ClassEvent::TypeAnnotations(EventBuffer(
+                self.type_annotations.clone(),
+            )));
+        }
+        if !self.attributes.is_empty() {
+            events.push(ClassEvent::Attributes(EventBuffer(self.attributes.clone())));
+        }
+        if !self.nest_members.is_empty() {
+            events.push(ClassEvent::NestMembers(EventBuffer(
+                self.nest_members.clone(),
+            )));
+        }
+        if !self.permitted_subclasses.is_empty() {
+            events.push(ClassEvent::PermittedSubclasses(EventBuffer(
+                self.permitted_subclasses.clone(),
+            )));
+        }
+        #[cfg(feature = "preview")]
+        if !self.loadable_descriptors.is_empty() {
+            events.push(ClassEvent::LoadableDescriptors(
+                self.loadable_descriptors.clone(),
+            ));
+        }
+        if !self.inner_classes.is_empty() {
+            events.push(ClassEvent::InnerClasses(EventBuffer(
+                self.inner_classes.clone(),
+            )));
+        }
+        if let Some(record_components) = &self.record_components {
+            events.push(ClassEvent::Record(
+                record_components
+                    .iter()
+                    .map(RecordComponentNode::to_event)
+                    .collect(),
+            ));
+        }
+        if !self.fields.is_empty() {
+            events.push(ClassEvent::Fields(
+                self.fields.iter().map(FieldNode::to_event).collect(),
+            ));
+        }
+        if !self.methods.is_empty() {
+            events.push(ClassEvent::Methods(
+                self.methods.iter().map(MethodNode::to_event).collect(),
+            ));
+        }
+        EventBuffer(events)
+    }
+}
+
+fn extend<T>(
+    vec: &mut Vec<T>,
+    events: impl IntoIterator<Item = ClassFileResult<T>>,
+) -> ClassFileResult<()> {
+    for event in events {
+        vec.push(event?);
+    }
+    Ok(())
+}
+
+impl<'class> ClassEventSource<'class> for &ClassNode<'class> {
+    type Providers = BufferedEventProviders;
+    type Iterator = <BufferedClassEvents<'class> as IntoIterator>::IntoIter;
+
+    fn events(self) -> ClassFileResult<Self::Iterator> {
+        Ok(self.to_events().into_iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{ClassNode, FieldNode, InsnNode, MethodNode};
+    use crate::{
+        buffer_class_events, BufferedClassEvents, ClassAccess, ClassClassEvent, ClassFileError,
+        ClassReader, ClassReaderFlags, ClassSourceEvent, ClassWriter, ClassWriterFlags,
+        EventBuffer, FieldAccess, LabelCreator, MethodAccess, MethodMaxsEvent, Opcode,
+        JAVA_17_VERSION,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    fn str(value: &str) -> Cow<'_, JavaStr> {
+        Cow::Borrowed(JavaStr::from_str(value))
+    }
+
+    #[test]
+    fn test_class_node_events() {
+        let mut node = ClassNode::new(ClassClassEvent {
+            major_version: JAVA_17_VERSION,
+            minor_version: 0,
+            access: ClassAccess::Public | ClassAccess::Super,
+            name: str("Node"),
+            signature: None,
+            super_name: Some(str("java/lang/Object")),
+            interfaces: vec![str("java/lang/Runnable")],
+        });
+        node.deprecated = true;
+        node.source = Some(ClassSourceEvent {
+            source: Some(str("Node.java")),
+            debug: None,
+        });
+        node.fields
+            .push(FieldNode::new(FieldAccess::Private, str("count"), str("I")));
+        let mut method = MethodNode::new(MethodAccess::Public, str("run"), str("()V"));
+        method.label_creator = Some(LabelCreator::default());
+        method
+            .instructions
+            .push_back(InsnNode::Insn(Opcode::Return));
+        method.maxs = Some(MethodMaxsEvent {
+            max_stack: 0,
+            max_locals: 1,
+        });
+        node.methods.push(method);
+
+        let written = ClassWriter::new(ClassWriterFlags::None)
+            .write(&node)
+            .unwrap();
+        let reader = ClassReader::new(&written, ClassReaderFlags::None).unwrap();
+        assert_eq!(
+            format!("{:?}", node.to_events()),
+            format!("{:?}", buffer_class_events(&reader).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_from_events() {
+        let classes: [&[u8]; 4] = [
+            include_class!("TestCode"),
+            include_class!("TestRecord"),
+            include_class!("TestAnnotations"),
+            include_class!("module-info"),
+        ];
+        for class in classes {
+            let reader = ClassReader::new(class, ClassReaderFlags::None).unwrap();
+            let node = ClassNode::from_events(&reader).unwrap();
+            assert_eq!(
+                format!("{:?}", buffer_class_events(&reader).unwrap()),
+                format!("{:?}", node.to_events())
+            );
+        }
+
+        let no_events: BufferedClassEvents = EventBuffer(Vec::new());
+        assert!(matches!(
+            ClassNode::from_events(no_events),
+            Err(ClassFileError::MissingClassEvent)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let node = ClassNode::from_events(&reader).unwrap();
+        let json = serde_json::to_string(&node).unwrap();
+        let deserialized: ClassNode = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{node:?}"), format!("{deserialized:?}"));
+
+        let method = node
+            .methods
+            .iter()
+            .find(|method| !method.instructions.is_empty())
+            .unwrap();
+        let deserialized_method = deserialized
+            .methods
+            .iter()
+            .find(|m| m.name == method.name && m.desc == method.desc)
+            .unwrap();
+        assert_eq!(
+            method.instructions.iter().collect::<Vec<_>>(),
+            deserialized_method.instructions.iter().collect::<Vec<_>>()
+        );
+        // the deserialized creator continues from the same id, but doesn't share it
+        let label_creator = method.label_creator.as_ref().unwrap();
+        let deserialized_label_creator = deserialized_method.label_creator.as_ref().unwrap();
+        assert_eq!(
+            label_creator.create_label(),
+            deserialized_label_creator.create_label()
+        );
+
+        let reader =
+            ClassReader::new(include_class!("TestAnnotations"), ClassReaderFlags::None).unwrap();
+        let node = ClassNode::from_events(&reader).unwrap();
+        assert!(node
+            .type_annotations
+            .iter()
+            .any(|annotation| !annotation.annotation.type_path.is_empty()));
+        let json = serde_json::to_string(&node).unwrap();
+        let deserialized: ClassNode = serde_json::from_str(&json).unwrap();
+        assert_eq!(node.type_annotations, deserialized.type_annotations);
+        assert_eq!(format!("{node:?}"), format!("{deserialized:?}"));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::tree::{AnnotationNode, TypeAnnotationNode};
+use crate::{
+    buffer_field_events, AnnotationEvent, Attribute, BufferedEventProviders, ClassFieldEvent,
+    ClassFileResult, EventBuffer, FieldAccess, FieldEvent, FieldEventProviders, FieldValue,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+pub type BufferedFieldEvent<'class> =
+    ClassFieldEvent<'class, EventBuffer<FieldEvent<'class, BufferedEventProviders>>>;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldNode<'class> {
+    pub access: FieldAccess,
+    pub name: Cow<'class, JavaStr>,
+    pub desc: Cow<'class, JavaStr>,
+    pub signature: Option<Cow<'class, JavaStr>>,
+    /// The constant value of the field, only used by static fields.
+    pub value: Option<FieldValue<'class>>,
+    pub deprecated: bool,
+    pub annotations: Vec<AnnotationEvent<AnnotationNode<'class>>>,
+    pub type_annotations: Vec<AnnotationEvent<TypeAnnotationNode<'class>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub attributes: Vec<Box<dyn Attribute>>,
+}
+
+impl<'class> FieldNode<'class> {
+    pub fn new(
+        access: FieldAccess,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) -> Self {
+        FieldNode {
+            access,
+            name: name.into(),
+            desc: desc.into(),
+            signature: None,
+            value: None,
+            deprecated: false,
+            annotations: Vec::new(),
+            type_annotations: Vec::new(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn from_event<E, P>(field: ClassFieldEvent<'class, E>) -> ClassFileResult<Self>
+    where
+        E: IntoIterator<Item = ClassFileResult<FieldEvent<'class, P>>>,
+        P: FieldEventProviders<'class>,
+    {
+        let mut node = FieldNode::new(field.access, field.name, field.desc);
+        node.signature = field.signature;
+        node.value = field.value;
+        for event in buffer_field_events(field.events)?.0 {
+            match event {
+                FieldEvent::Deprecated => node.deprecated = true,
+                FieldEvent::Annotations(events) => node.annotations.extend(events.0),
+                FieldEvent::TypeAnnotations(events) => node.type_annotations.extend(events.0),
+                FieldEvent::Attributes(events) => node.attributes.extend(events.0),
+            }
+        }
+        Ok(node)
+    }
+
+    pub fn to_event(&self) -> BufferedFieldEvent<'class> {
+        let mut events = Vec::new();
+        if self.deprecated {
+            events.push(FieldEvent::Deprecated);
+        }
+        if !self.annotations.is_empty() {
+            events.push(FieldEvent::Annotations(EventBuffer(
+                self.annotations.clone(),
+            )));
+        }
+        if !self.type_annotations.is_empty() {
+            events.push(FieldEvent::TypeAnnotations(EventBuffer(
+                self.type_annotations.clone(),
+            )));
+        }
+        if !self.attributes.is_empty() {
+            events.push(FieldEvent::Attributes(EventBuffer(self.attributes.clone())));
+        }
+        ClassFieldEvent {
+            access: self.access,
+            name: self.name.clone(),
+            desc: self.desc.clone(),
+            signature: self.signature.clone(),
+            value: self.value.clone(),
+            events: EventBuffer(events),
+        }
+    }
+}
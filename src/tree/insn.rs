@@ -0,0 +1,473 @@
+use crate::tree::{InsnHandle, InsnList, TypeAnnotationNode};
+use crate::{
+    AnnotationEvent, BootstrapMethodArgument, BufferedEventProviders, EventBuffer, Frame, Handle,
+    Label, LdcConstant, MethodEvent, NewArrayType, Opcode,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+/// An element of the instruction list of a [`MethodNode`](crate::tree::MethodNode), which is
+/// either an instruction or a pseudo-instruction: a label, a line number, a frame or the
+/// annotations of the previous instruction. Each node is identified by its
+/// [`InsnHandle`](crate::tree::InsnHandle) in the list.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum InsnNode<'class> {
+    Insn(Opcode),
+    BIPushInsn(i8),
+    SIPushInsn(i16),
+    NewArrayInsn(NewArrayType),
+    VarInsn {
+        opcode: Opcode,
+        var_index: u16,
+    },
+    TypeInsn {
+        opcode: Opcode,
+        ty: Cow<'class, JavaStr>,
+    },
+    FieldInsn {
+        opcode: Opcode,
+        owner: Cow<'class, JavaStr>,
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+    },
+    MethodInsn {
+        opcode: Opcode,
+        owner: Cow<'class, JavaStr>,
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+        is_interface: bool,
+    },
+    InvokeDynamicInsn {
+        name: Cow<'class, JavaStr>,
+        desc: Cow<'class, JavaStr>,
+        bootstrap_method_handle: Handle<'class>,
+        bootstrap_method_arguments: Vec<BootstrapMethodArgument<'class>>,
+    },
+    JumpInsn {
+        opcode: Opcode,
+        label: Label,
+    },
+    LdcInsn(LdcConstant<'class>),
+    IIncInsn {
+        var_index: u16,
+        increment: i16,
+    },
+    TableSwitchInsn {
+        low: i32,
+        high: i32,
+        dflt: Label,
+        labels: Vec<Label>,
+    },
+    LookupSwitchInsn {
+        dflt: Label,
+        values: Vec<(i32, Label)>,
+    },
+    MultiANewArrayInsn {
+        desc: Cow<'class, JavaStr>,
+        dimensions: u8,
+    },
+    Label(Label),
+    LineNumber {
+        line: u16,
+        start: Label,
+    },
+    Frame(Frame<'class>),
+    InsnAnnotations(Vec<AnnotationEvent<TypeAnnotationNode<'class>>>),
+}
+
+impl<'class> InsnNode<'class> {
+    /// The opcode of the instruction, or `None` for pseudo-instructions. The `ldc_w`, `ldc2_w`,
+    /// `goto_w`, `jsr_w` and `wide` variants are chosen when writing, and aren't reported here.
+    pub fn opcode(&self) -> Option<Opcode> {
+        Some(match self {
+            InsnNode::Insn(opcode)
+            | InsnNode::VarInsn { opcode, .. }
+            | InsnNode::TypeInsn { opcode, .. }
+            | InsnNode::FieldInsn { opcode, .. }
+            | InsnNode::MethodInsn { opcode, .. }
+            | InsnNode::JumpInsn { opcode, .. } => *opcode,
+            InsnNode::BIPushInsn(_) => Opcode::BIPush,
+            InsnNode::SIPushInsn(_) => Opcode::SIPush,
+            InsnNode::NewArrayInsn(_) => Opcode::NewArray,
+            InsnNode::InvokeDynamicInsn { .. } => Opcode::InvokeDynamic,
+            InsnNode::LdcInsn(_) => Opcode::Ldc,
+            InsnNode::IIncInsn { .. } => Opcode::IInc,
+            InsnNode::TableSwitchInsn { .. } => Opcode::TableSwitch,
+            InsnNode::LookupSwitchInsn { .. } => Opcode::LookupSwitch,
+            InsnNode::MultiANewArrayInsn { .. } => Opcode::MultiANewArray,
+            InsnNode::Label(_)
+            | InsnNode::LineNumber { .. }
+            | InsnNode::Frame(_)
+            | InsnNode::InsnAnnotations(_) => return None,
+        })
+    }
+
+    pub fn is_pseudo_insn(&self) -> bool {
+        self.opcode().is_none()
+    }
+
+    /// The size in bytes of the instruction when written at the given code offset, which only
+    /// matters for the padding of switches. Assumes that the constant of an `ldc` instruction has
+    /// a constant pool index below 256, and that jumps don't need to be widened. Pseudo-instructions
+    /// have size 0.
+    pub fn size(&self, offset: usize) -> usize {
+        let switch_padding = 3 - offset % 4;
+        match self {
+            InsnNode::Insn(_) => 1,
+            InsnNode::BIPushInsn(_) | InsnNode::NewArrayInsn(_) => 2,
+            InsnNode::SIPushInsn(_)
+            | InsnNode::TypeInsn { .. }
+            | InsnNode::FieldInsn { .. }
+            | InsnNode::JumpInsn { .. } => 3,
+            InsnNode::VarInsn { opcode, var_index } => {
+                if *var_index > u8::MAX as u16 {
+                    4
+                } else if *var_index < 4 && *opcode != Opcode::Ret {
+                    1
+                } else {
+                    2
+                }
+            }
+            InsnNode::MethodInsn {
+                opcode: Opcode::InvokeInterface,
+                ..
+            }
+            | InsnNode::InvokeDynamicInsn { .. } => 5,
+            InsnNode::MethodInsn { .. } => 3,
+            InsnNode::LdcInsn(LdcConstant::Long(_) | LdcConstant::Double(_)) => 3,
+            InsnNode::LdcInsn(_) => 2,
+            InsnNode::IIncInsn {
+                var_index,
+                increment,
+            } => {
+                if *var_index > u8::MAX as u16 || i8::try_from(*increment).is_err() {
+                    6
+                } else {
+                    3
+                }
+            }
+            InsnNode::TableSwitchInsn { labels, .. } => 1 + switch_padding + 12 + labels.len() * 4,
+            InsnNode::LookupSwitchInsn { values, .. } => 1 + switch_padding + 8 + values.len() * 8,
+            InsnNode::MultiANewArrayInsn { .. } => 4,
+            InsnNode::Label(_)
+            | InsnNode::LineNumber { .. }
+            | InsnNode::Frame(_)
+            | InsnNode::InsnAnnotations(_) => 0,
+        }
+    }
+
+    pub fn to_event(&self) -> MethodEvent<'class, BufferedEventProviders> {
+        match self.clone() {
+            InsnNode::Insn(opcode) => MethodEvent::Insn(opcode),
+            InsnNode::BIPushInsn(value) => MethodEvent::BIPushInsn(value),
+            InsnNode::SIPushInsn(value) => MethodEvent::SIPushInsn(value),
+            InsnNode::NewArrayInsn(ty) => MethodEvent::NewArrayInsn(ty),
+            InsnNode::VarInsn { opcode, var_index } => MethodEvent::VarInsn { opcode, var_index },
+            InsnNode::TypeInsn { opcode, ty } => MethodEvent::TypeInsn { opcode, ty },
+            InsnNode::FieldInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+            } => MethodEvent::FieldInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+            },
+            InsnNode::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                is_interface,
+            } => MethodEvent::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                is_interface,
+            },
+            InsnNode::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            } => MethodEvent::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            },
+            InsnNode::JumpInsn { opcode, label } => MethodEvent::JumpInsn { opcode, label },
+            InsnNode::LdcInsn(constant) => MethodEvent::LdcInsn(constant),
+            InsnNode::IIncInsn {
+                var_index,
+                increment,
+            } => MethodEvent::IIncInsn {
+                var_index,
+                increment,
+            },
+            InsnNode::TableSwitchInsn {
+                low,
+                high,
+                dflt,
+                labels,
+            } => MethodEvent::TableSwitchInsn {
+                low,
+                high,
+                dflt,
+                labels,
+            },
+            InsnNode::LookupSwitchInsn { dflt, values } => {
+                MethodEvent::LookupSwitchInsn { dflt, values }
+            }
+            InsnNode::MultiANewArrayInsn { desc, dimensions } => {
+                MethodEvent::MultiANewArrayInsn { desc, dimensions }
+            }
+            InsnNode::Label(label) => MethodEvent::Label(label),
+            InsnNode::LineNumber { line, start } => MethodEvent::LineNumber { line, start },
+            InsnNode::Frame(frame) => MethodEvent::Frame(frame),
+            InsnNode::InsnAnnotations(annotations) => {
+                MethodEvent::InsnAnnotations(EventBuffer(annotations))
+            }
+        }
+    }
+}
+
+impl InsnList<InsnNode<'_>> {
+    /// The handle of the label pseudo-instruction of the given label.
+    pub fn find_label(&self, label: Label) -> Option<InsnHandle> {
+        self.handles()
+            .find(|&handle| self[handle] == InsnNode::Label(label))
+    }
+
+    /// The first real instruction at or after the given label, which is where a jump to it goes.
+    pub fn label_target(&self, label: Label) -> Option<InsnHandle> {
+        let handle = self.find_label(label)?;
+        self.next_real_insn_from(handle)
+    }
+
+    /// The size in bytes of the code, see [`InsnNode::size`].
+    pub fn code_size(&self) -> usize {
+        self.iter()
+            .fold(0, |offset, insn| offset + insn.size(offset))
+    }
+
+    /// The handles of the real instructions, skipping pseudo-instructions.
+    pub fn real_insns(&self) -> impl Iterator<Item = InsnHandle> + '_ {
+        self.handles()
+            .filter(|&handle| !self[handle].is_pseudo_insn())
+    }
+
+    pub fn first_real_insn(&self) -> Option<InsnHandle> {
+        self.next_real_insn_from(self.first()?)
+    }
+
+    pub fn last_real_insn(&self) -> Option<InsnHandle> {
+        self.prev_real_insn_from(self.last()?)
+    }
+
+    /// The first real instruction after the given element.
+    pub fn next_real_insn(&self, handle: InsnHandle) -> Option<InsnHandle> {
+        self.next_real_insn_from(self.next(handle)?)
+    }
+
+    /// The last real instruction before the given element.
+    pub fn prev_real_insn(&self, handle: InsnHandle) -> Option<InsnHandle> {
+        self.prev_real_insn_from(self.prev(handle)?)
+    }
+
+    fn next_real_insn_from(&self, mut handle: InsnHandle) -> Option<InsnHandle> {
+        while self.get(handle)?.is_pseudo_insn() {
+            handle = self.next(handle)?;
+        }
+        Some(handle)
+    }
+
+    fn prev_real_insn_from(&self, mut handle: InsnHandle) -> Option<InsnHandle> {
+        while self.get(handle)?.is_pseudo_insn() {
+            handle = self.prev(handle)?;
+        }
+        Some(handle)
+    }
+}
+
+/// Converts an instruction or pseudo-instruction event, giving back any other event.
+impl<'class> TryFrom<MethodEvent<'class, BufferedEventProviders>> for InsnNode<'class> {
+    type Error = MethodEvent<'class, BufferedEventProviders>;
+
+    fn try_from(event: MethodEvent<'class, BufferedEventProviders>) -> Result<Self, Self::Error> {
+        Ok(match event {
+            MethodEvent::Insn(opcode) => InsnNode::Insn(opcode),
+            MethodEvent::BIPushInsn(value) => InsnNode::BIPushInsn(value),
+            MethodEvent::SIPushInsn(value) => InsnNode::SIPushInsn(value),
+            MethodEvent::NewArrayInsn(ty) => InsnNode::NewArrayInsn(ty),
+            MethodEvent::VarInsn { opcode, var_index } => InsnNode::VarInsn { opcode, var_index },
+            MethodEvent::TypeInsn { opcode, ty } => InsnNode::TypeInsn { opcode, ty },
+            MethodEvent::FieldInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+            } => InsnNode::FieldInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+            },
+            MethodEvent::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                is_interface,
+            } => InsnNode::MethodInsn {
+                opcode,
+                owner,
+                name,
+                desc,
+                is_interface,
+            },
+            MethodEvent::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            } => InsnNode::InvokeDynamicInsn {
+                name,
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+            },
+            MethodEvent::JumpInsn { opcode, label } => InsnNode::JumpInsn { opcode, label },
+            MethodEvent::LdcInsn(constant) => InsnNode::LdcInsn(constant),
+            MethodEvent::IIncInsn {
+                var_index,
+                increment,
+            } => InsnNode::IIncInsn {
+                var_index,
+                increment,
+            },
+            MethodEvent::TableSwitchInsn {
+                low,
+                high,
+                dflt,
+                labels,
+            } => InsnNode::TableSwitchInsn {
+                low,
+                high,
+                dflt,
+                labels,
+            },
+            MethodEvent::LookupSwitchInsn { dflt, values } => {
+                InsnNode::LookupSwitchInsn { dflt, values }
+            }
+            MethodEvent::MultiANewArrayInsn { desc, dimensions } => {
+                InsnNode::MultiANewArrayInsn { desc, dimensions }
+            }
+            MethodEvent::Label(label) => InsnNode::Label(label),
+            MethodEvent::LineNumber { line, start } => InsnNode::LineNumber { line, start },
+            MethodEvent::Frame(frame) => InsnNode::Frame(frame),
+            MethodEvent::InsnAnnotations(annotations) => InsnNode::InsnAnnotations(annotations.0),
+            event => return Err(event),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{InsnList, InsnNode};
+    use crate::{LabelCreator, LdcConstant, Opcode};
+
+    #[test]
+    fn test_insn_sizes() {
+        let label = LabelCreator::default().create_label();
+        assert_eq!(
+            Some(Opcode::ALoad),
+            InsnNode::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 0,
+            }
+            .opcode()
+        );
+        assert_eq!(None, InsnNode::Label(label).opcode());
+        for (expected, insn) in [
+            (
+                1,
+                InsnNode::VarInsn {
+                    opcode: Opcode::ALoad,
+                    var_index: 3,
+                },
+            ),
+            (
+                2,
+                InsnNode::VarInsn {
+                    opcode: Opcode::Ret,
+                    var_index: 3,
+                },
+            ),
+            (
+                4,
+                InsnNode::VarInsn {
+                    opcode: Opcode::ILoad,
+                    var_index: 300,
+                },
+            ),
+            (
+                6,
+                InsnNode::IIncInsn {
+                    var_index: 1,
+                    increment: 1000,
+                },
+            ),
+            (3, InsnNode::LdcInsn(LdcConstant::Long(1))),
+            (0, InsnNode::Label(label)),
+        ] {
+            assert_eq!(expected, insn.size(0));
+        }
+        let switch = InsnNode::TableSwitchInsn {
+            low: 0,
+            high: 1,
+            dflt: label,
+            labels: vec![label, label],
+        };
+        assert_eq!(24, switch.size(0));
+        assert_eq!(21, switch.size(3));
+    }
+
+    #[test]
+    fn test_real_insns() {
+        let label_creator = LabelCreator::default();
+        let (start, end) = (label_creator.create_label(), label_creator.create_label());
+        let mut insns = InsnList::new();
+        let start_handle = insns.push_back(InsnNode::Label(start));
+        insns.push_back(InsnNode::LineNumber { line: 1, start });
+        let load = insns.push_back(InsnNode::VarInsn {
+            opcode: Opcode::ILoad,
+            var_index: 0,
+        });
+        let jump = insns.push_back(InsnNode::JumpInsn {
+            opcode: Opcode::IfEq,
+            label: end,
+        });
+        insns.push_back(InsnNode::Label(end));
+        let ret = insns.push_back(InsnNode::Insn(Opcode::Return));
+        insns.push_back(InsnNode::Label(label_creator.create_label()));
+
+        assert_eq!(Some(start_handle), insns.find_label(start));
+        assert_eq!(Some(ret), insns.label_target(end));
+        assert_eq!(Some(load), insns.first_real_insn());
+        assert_eq!(Some(ret), insns.last_real_insn());
+        assert_eq!(Some(jump), insns.prev_real_insn(ret));
+        assert_eq!(Some(ret), insns.next_real_insn(jump));
+        assert_eq!(None, insns.next_real_insn(ret));
+        assert_eq!(
+            vec![load, jump, ret],
+            insns.real_insns().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(2), insns.index_of(load));
+    }
+}
@@ -0,0 +1,527 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::{Index, IndexMut};
+
+/// A reference to an element of an [`InsnList`]. It stays valid while the element remains in the
+/// list, no matter what else is inserted or removed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InsnHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// A doubly linked list of instructions, which supports insertion and removal in constant time
+/// given an [`InsnHandle`].
+#[derive(Clone)]
+pub struct InsnList<T> {
+    entries: Vec<InsnEntry<T>>,
+    free: Vec<u32>,
+    first: Option<u32>,
+    last: Option<u32>,
+    len: usize,
+}
+
+#[derive(Clone)]
+struct InsnEntry<T> {
+    generation: u32,
+    value: Option<T>,
+    prev: Option<u32>,
+    next: Option<u32>,
+}
+
+impl<T> InsnList<T> {
+    pub fn new() -> Self {
+        InsnList {
+            entries: Vec::new(),
+            free: Vec::new(),
+            first: None,
+            last: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn first(&self) -> Option<InsnHandle> {
+        self.first.map(|index| self.handle(index))
+    }
+
+    pub fn last(&self) -> Option<InsnHandle> {
+        self.last.map(|index| self.handle(index))
+    }
+
+    pub fn next(&self, handle: InsnHandle) -> Option<InsnHandle> {
+        let index = self.entries[self.checked_index(handle)?].next?;
+        Some(self.handle(index))
+    }
+
+    pub fn prev(&self, handle: InsnHandle) -> Option<InsnHandle> {
+        let index = self.entries[self.checked_index(handle)?].prev?;
+        Some(self.handle(index))
+    }
+
+    /// The position of an element in the list, which takes time linear in the position.
+    pub fn index_of(&self, handle: InsnHandle) -> Option<usize> {
+        self.checked_index(handle)?;
+        self.handles().position(|other| other == handle)
+    }
+
+    /// Whether the handle refers to an element which is still in the list.
+    pub fn contains(&self, handle: InsnHandle) -> bool {
+        self.checked_index(handle).is_some()
+    }
+
+    pub fn get(&self, handle: InsnHandle) -> Option<&T> {
+        self.entries[self.checked_index(handle)?].value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: InsnHandle) -> Option<&mut T> {
+        let index = self.checked_index(handle)?;
+        self.entries[index].value.as_mut()
+    }
+
+    pub fn push_back(&mut self, value: T) -> InsnHandle {
+        let index = self.alloc(value);
+        self.link(index, self.last, None);
+        self.handle(index)
+    }
+
+    pub fn push_front(&mut self, value: T) -> InsnHandle {
+        let index = self.alloc(value);
+        self.link(index, None, self.first);
+        self.handle(index)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.last().and_then(|handle| self.remove(handle))
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.first().and_then(|handle| self.remove(handle))
+    }
+
+    /// # Panics
+    /// Panics if the handle is not in the list.
+    pub fn insert_before(&mut self, handle: InsnHandle, value: T) -> InsnHandle {
+        let next = self.expect_index(handle);
+        let index = self.alloc(value);
+        self.link(index, self.entries[next as usize].prev, Some(next));
+        self.handle(index)
+    }
+
+    /// # Panics
+    /// Panics if the handle is not in the list.
+    pub fn insert_after(&mut self, handle: InsnHandle, value: T) -> InsnHandle {
+        let prev = self.expect_index(handle);
+        let index = self.alloc(value);
+        self.link(index, Some(prev), self.entries[prev as usize].next);
+        self.handle(index)
+    }
+
+    /// Removes an element from the list, returning `None` if it was already removed.
+    pub fn remove(&mut self, handle: InsnHandle) -> Option<T> {
+        let index = self.checked_index(handle)?;
+        let entry = &mut self.entries[index];
+        let (prev, next) = (entry.prev.take(), entry.next.take());
+        let value = entry.value.take();
+        entry.generation = entry.generation.wrapping_add(1);
+        match prev {
+            Some(prev) => self.entries[prev as usize].next = next,
+            None => self.first = next,
+        }
+        match next {
+            Some(next) => self.entries[next as usize].prev = prev,
+            None => self.last = prev,
+        }
+        self.free.push(index as u32);
+        self.len -= 1;
+        value
+    }
+
+    /// Moves all the elements of `other` into this list before the given element. Handles into
+    /// `other` are invalidated.
+    ///
+    /// # Panics
+    /// Panics if the handle is not in the list.
+    pub fn splice_before(&mut self, handle: InsnHandle, other: InsnList<T>) {
+        self.expect_index(handle);
+        for value in other {
+            self.insert_before(handle, value);
+        }
+    }
+
+    /// Moves all the elements of `other` into this list after the given element. Handles into
+    /// `other` are invalidated.
+    ///
+    /// # Panics
+    /// Panics if the handle is not in the list.
+    pub fn splice_after(&mut self, handle: InsnHandle, other: InsnList<T>) {
+        let mut prev = handle;
+        self.expect_index(handle);
+        for value in other {
+            prev = self.insert_after(prev, value);
+        }
+    }
+
+    /// Moves all the elements of `other` to the end of this list. Handles into `other` are
+    /// invalidated.
+    pub fn append(&mut self, other: InsnList<T>) {
+        for value in other {
+            self.push_back(value);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = InsnList::new();
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            handles: self.handles(),
+        }
+    }
+
+    /// The handles of the elements, in order.
+    pub fn handles(&self) -> Handles<'_, T> {
+        Handles {
+            list: self,
+            next: self.first,
+        }
+    }
+
+    /// A cursor at the first element, or at the end if the list is empty.
+    pub fn cursor_front(&mut self) -> InsnCursor<'_, T> {
+        InsnCursor {
+            current: self.first,
+            list: self,
+        }
+    }
+
+    /// A cursor at the last element, or at the end if the list is empty.
+    pub fn cursor_back(&mut self) -> InsnCursor<'_, T> {
+        InsnCursor {
+            current: self.last,
+            list: self,
+        }
+    }
+
+    /// # Panics
+    /// Panics if the handle is not in the list.
+    pub fn cursor_at(&mut self, handle: InsnHandle) -> InsnCursor<'_, T> {
+        let index = self.expect_index(handle);
+        InsnCursor {
+            current: Some(index),
+            list: self,
+        }
+    }
+
+    fn handle(&self, index: u32) -> InsnHandle {
+        InsnHandle {
+            index,
+            generation: self.entries[index as usize].generation,
+        }
+    }
+
+    fn checked_index(&self, handle: InsnHandle) -> Option<usize> {
+        let entry = self.entries.get(handle.index as usize)?;
+        (entry.generation == handle.generation && entry.value.is_some())
+            .then_some(handle.index as usize)
+    }
+
+    fn expect_index(&self, handle: InsnHandle) -> u32 {
+        match self.checked_index(handle) {
+            Some(index) => index as u32,
+            None => panic!("instruction handle {handle:?} is not in the list"),
+        }
+    }
+
+    fn alloc(&mut self, value: T) -> u32 {
+        if let Some(index) = self.free.pop() {
+            self.entries[index as usize].value = Some(value);
+            index
+        } else {
+            let index = u32::try_from(self.entries.len()).expect("too many instructions");
+            self.entries.push(InsnEntry {
+                generation: 0,
+                value: Some(value),
+                prev: None,
+                next: None,
+            });
+            index
+        }
+    }
+
+    fn link(&mut self, index: u32, prev: Option<u32>, next: Option<u32>) {
+        let entry = &mut self.entries[index as usize];
+        entry.prev = prev;
+        entry.next = next;
+        match prev {
+            Some(prev) => self.entries[prev as usize].next = Some(index),
+            None => self.first = Some(index),
+        }
+        match next {
+            Some(next) => self.entries[next as usize].prev = Some(index),
+            None => self.last = Some(index),
+        }
+        self.len += 1;
+    }
+}
+
+impl<T> Default for InsnList<T> {
+    fn default() -> Self {
+        InsnList::new()
+    }
+}
+
+impl<T: Debug> Debug for InsnList<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Serialized as a sequence of its elements. Handles into a deserialized list are unrelated to
+/// handles into the list it was serialized from.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for InsnList<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for InsnList<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<T>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl<T> Index<InsnHandle> for InsnList<T> {
+    type Output = T;
+
+    fn index(&self, handle: InsnHandle) -> &T {
+        let index = self.expect_index(handle);
+        self.entries[index as usize]
+            .value
+            .as_ref()
+            .expect("checked index should have a value")
+    }
+}
+
+impl<T> IndexMut<InsnHandle> for InsnList<T> {
+    fn index_mut(&mut self, handle: InsnHandle) -> &mut T {
+        let index = self.expect_index(handle);
+        self.entries[index as usize]
+            .value
+            .as_mut()
+            .expect("checked index should have a value")
+    }
+}
+
+impl<T> FromIterator<T> for InsnList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = InsnList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for InsnList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+impl<T> IntoIterator for InsnList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a InsnList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    list: InsnList<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+#[derive(Debug)]
+pub struct Handles<'a, T> {
+    list: &'a InsnList<T>,
+    next: Option<u32>,
+}
+
+impl<T> Iterator for Handles<'_, T> {
+    type Item = InsnHandle;
+
+    fn next(&mut self) -> Option<InsnHandle> {
+        let index = self.next?;
+        self.next = self.list.entries[index as usize].next;
+        Some(self.list.handle(index))
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    handles: Handles<'a, T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let handle = self.handles.next()?;
+        self.handles.list.entries[handle.index as usize]
+            .value
+            .as_ref()
+    }
+}
+
+/// A position in an [`InsnList`] which can move back and forth and edit the list around it. The
+/// cursor is either at an element, or at the end of the list, which sits between the last and the
+/// first element.
+#[derive(Debug)]
+pub struct InsnCursor<'a, T> {
+    list: &'a mut InsnList<T>,
+    current: Option<u32>,
+}
+
+impl<T> InsnCursor<'_, T> {
+    /// The handle of the current element, or `None` at the end of the list.
+    pub fn handle(&self) -> Option<InsnHandle> {
+        self.current.map(|index| self.list.handle(index))
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.list.entries[self.current? as usize].value.as_ref()
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        self.list.entries[self.current? as usize].value.as_mut()
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = match self.current {
+            Some(index) => self.list.entries[index as usize].next,
+            None => self.list.first,
+        };
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = match self.current {
+            Some(index) => self.list.entries[index as usize].prev,
+            None => self.list.last,
+        };
+    }
+
+    /// Inserts an element before the cursor, or at the back of the list if the cursor is at the
+    /// end.
+    pub fn insert_before(&mut self, value: T) -> InsnHandle {
+        match self.handle() {
+            Some(handle) => self.list.insert_before(handle, value),
+            None => self.list.push_back(value),
+        }
+    }
+
+    /// Inserts an element after the cursor, or at the front of the list if the cursor is at the
+    /// end.
+    pub fn insert_after(&mut self, value: T) -> InsnHandle {
+        match self.handle() {
+            Some(handle) => self.list.insert_after(handle, value),
+            None => self.list.push_front(value),
+        }
+    }
+
+    /// Removes the current element and moves the cursor to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let handle = self.handle()?;
+        self.move_next();
+        self.list.remove(handle)
+    }
+
+    pub fn list(&self) -> &InsnList<T> {
+        self.list
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::InsnList;
+
+    #[test]
+    fn test_insn_list() {
+        let mut list: InsnList<i32> = (1..=3).collect();
+        let handles: Vec<_> = list.handles().collect();
+        list.insert_before(handles[1], 10);
+        list.insert_after(handles[2], 20);
+        assert_eq!(Some(2), list.remove(handles[1]));
+        assert_eq!(None, list.remove(handles[1]));
+        assert!(!list.contains(handles[1]));
+        assert_eq!(vec![1, 10, 3, 20], list.iter().copied().collect::<Vec<_>>());
+
+        // the freed slot is reused without reviving the old handle
+        let reused = list.push_front(0);
+        assert_eq!(None, list.get(handles[1]));
+        assert_eq!(0, list[reused]);
+
+        list.splice_after(handles[0], [4, 5].into_iter().collect());
+        assert_eq!(
+            vec![0, 1, 4, 5, 10, 3, 20],
+            list.iter().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(7, list.len());
+    }
+
+    #[test]
+    fn test_insn_cursor() {
+        let mut list: InsnList<i32> = (1..=5).collect();
+        let mut cursor = list.cursor_front();
+        while let Some(&value) = cursor.current() {
+            if value % 2 == 0 {
+                cursor.remove_current();
+            } else {
+                cursor.insert_before(-value);
+                cursor.move_next();
+            }
+        }
+        cursor.insert_before(6);
+        assert_eq!(
+            vec![-1, 1, -3, 3, -5, 5, 6],
+            list.iter().copied().collect::<Vec<_>>()
+        );
+
+        let mut cursor = list.cursor_back();
+        cursor.move_next();
+        assert_eq!(None, cursor.current());
+        cursor.move_next();
+        assert_eq!(Some(&-1), cursor.current());
+    }
+}
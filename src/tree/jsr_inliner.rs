@@ -0,0 +1,584 @@
+use crate::tree::{InsnList, InsnNode, MethodNode};
+use crate::{
+    ClassFileError, ClassFileResult, Label, LabelCreator, MethodLocalVariableEvent,
+    MethodTryCatchBlockAnnotationEvent, MethodTryCatchBlockEvent, Opcode,
+};
+use std::collections::HashMap;
+use std::ops::Range;
+
+impl MethodNode<'_> {
+    /// Replaces the subroutines of the method (`jsr` and `ret`, as used by classes older than Java
+    /// 6) with copies of the subroutine bodies at each call site, so that the method can be
+    /// verified with stack map frames. Each `jsr` becomes an `aconst_null` standing in for the
+    /// return address, followed by a `goto` to the copy of the subroutine, and each `ret` becomes
+    /// a `goto` back to the instruction after the `jsr`.
+    ///
+    /// Try-catch blocks, local variables and line numbers are copied along with the code they
+    /// cover, and frames are removed. Code which can't be reached is removed too. Does nothing if
+    /// the method has no `jsr` instructions.
+    pub fn inline_subroutines(&mut self) -> ClassFileResult<()> {
+        let Some(label_creator) = &self.label_creator else {
+            return Ok(());
+        };
+        let insns: Vec<_> = self.instructions.iter().collect();
+        if !insns.iter().any(|insn| {
+            matches!(
+                insn,
+                InsnNode::JumpInsn {
+                    opcode: Opcode::Jsr,
+                    ..
+                }
+            )
+        }) {
+            return Ok(());
+        }
+
+        let label_indexes: HashMap<Label, usize> = insns
+            .iter()
+            .enumerate()
+            .filter_map(|(index, insn)| match insn {
+                InsnNode::Label(label) => Some((*label, index)),
+                _ => None,
+            })
+            .collect();
+        let label_index = |label: Label| {
+            label_indexes
+                .get(&label)
+                .copied()
+                .ok_or(ClassFileError::UnknownLabel(label))
+        };
+        let mut try_catch_blocks = Vec::with_capacity(self.try_catch_blocks.len());
+        for try_catch_block in &self.try_catch_blocks {
+            try_catch_blocks.push((
+                label_index(try_catch_block.start)?..label_index(try_catch_block.end)?,
+                label_index(try_catch_block.handler)?,
+            ));
+        }
+        for local_variable in &self.local_variables {
+            label_index(local_variable.start)?;
+            label_index(local_variable.end)?;
+        }
+        for annotation in &self.local_variable_annotations {
+            for &(start, end, _) in &annotation.ranges {
+                label_index(start)?;
+                label_index(end)?;
+            }
+        }
+        for insn in &insns {
+            if let InsnNode::LineNumber { start, .. } = insn {
+                label_index(*start)?;
+            }
+        }
+
+        let mut inliner = Inliner {
+            insns: &insns,
+            label_index: &label_index,
+            try_catch_blocks: &try_catch_blocks,
+            label_creator,
+            subroutines: HashMap::new(),
+            instantiations: Vec::new(),
+        };
+        let main = inliner.subroutine(None)?;
+        inliner.instantiate(None, main, None);
+
+        let mut instructions = InsnList::new();
+        let mut new_try_catch_blocks = Vec::new();
+        let mut new_try_catch_block_annotations = Vec::new();
+        let mut local_variables = Vec::new();
+        let mut local_variable_annotations = self.local_variable_annotations.clone();
+        for annotation in &mut local_variable_annotations {
+            annotation.ranges.clear();
+        }
+        let mut instantiation = 0;
+        while instantiation < inliner.instantiations.len() {
+            inliner.emit(instantiation, &mut instructions)?;
+
+            let range_labels = &inliner.instantiations[instantiation].range_labels;
+            for (index, try_catch_block) in self.try_catch_blocks.iter().enumerate() {
+                let start = range_labels[&try_catch_block.start];
+                let end = range_labels[&try_catch_block.end];
+                if start == end {
+                    continue;
+                }
+                for annotation in &self.try_catch_block_annotations {
+                    if annotation.try_catch_block_index as usize == index {
+                        new_try_catch_block_annotations.push(MethodTryCatchBlockAnnotationEvent {
+                            try_catch_block_index: new_try_catch_blocks.len() as u16,
+                            visible: annotation.visible,
+                            annotation: annotation.annotation.clone(),
+                        });
+                    }
+                }
+                new_try_catch_blocks.push(MethodTryCatchBlockEvent {
+                    start,
+                    end,
+                    handler: inliner.jump_label(instantiation, try_catch_block.handler)?,
+                    ty: try_catch_block.ty.clone(),
+                });
+            }
+            for local_variable in &self.local_variables {
+                let start = range_labels[&local_variable.start];
+                let end = range_labels[&local_variable.end];
+                if start != end {
+                    local_variables.push(MethodLocalVariableEvent {
+                        start,
+                        end,
+                        ..local_variable.clone()
+                    });
+                }
+            }
+            for (annotation, new_annotation) in self
+                .local_variable_annotations
+                .iter()
+                .zip(&mut local_variable_annotations)
+            {
+                for &(start, end, index) in &annotation.ranges {
+                    let (start, end) = (range_labels[&start], range_labels[&end]);
+                    if start != end {
+                        new_annotation.ranges.push((start, end, index));
+                    }
+                }
+            }
+
+            instantiation += 1;
+        }
+        local_variable_annotations.retain(|annotation| !annotation.ranges.is_empty());
+
+        self.instructions = instructions;
+        self.try_catch_blocks = new_try_catch_blocks;
+        self.try_catch_block_annotations = new_try_catch_block_annotations;
+        self.local_variables = local_variables;
+        self.local_variable_annotations = local_variable_annotations;
+        Ok(())
+    }
+}
+
+struct Inliner<'a, 'class, F> {
+    insns: &'a [&'a InsnNode<'class>],
+    label_index: &'a F,
+    /// The range of instructions covered by each try-catch block, and the index of its handler.
+    try_catch_blocks: &'a [(Range<usize>, usize)],
+    label_creator: &'a LabelCreator,
+    /// The instructions belonging to the subroutine starting at each label, or to the main code of
+    /// the method for `None`.
+    subroutines: HashMap<Option<Label>, Vec<bool>>,
+    instantiations: Vec<Instantiation>,
+}
+
+/// A copy of a subroutine, or of the main code, in the inlined code.
+struct Instantiation {
+    parent: Option<usize>,
+    subroutine: Option<Label>,
+    insns: Vec<bool>,
+    /// Where a `ret` in the subroutine returns to.
+    return_label: Option<Label>,
+    /// The label in this copy standing for each label of the original code, for ranges. Each run
+    /// of labels without instructions of this copy in between maps to the same label.
+    range_labels: HashMap<Label, Label>,
+}
+
+impl<'class, F> Inliner<'_, 'class, F>
+where
+    F: Fn(Label) -> ClassFileResult<usize>,
+{
+    /// Finds the instructions of a subroutine by following the control flow from its start,
+    /// without entering the subroutines it calls, and including the exception handlers covering
+    /// any of its instructions.
+    fn subroutine(&mut self, start: Option<Label>) -> ClassFileResult<Vec<bool>> {
+        if let Some(insns) = self.subroutines.get(&start) {
+            return Ok(insns.clone());
+        }
+
+        let mut insns = vec![false; self.insns.len()];
+        let mut worklist = vec![match start {
+            Some(start) => (self.label_index)(start)?,
+            None => 0,
+        }];
+        loop {
+            while let Some(index) = worklist.pop() {
+                if index >= self.insns.len() || insns[index] {
+                    continue;
+                }
+                insns[index] = true;
+                let falls_through = match self.insns[index] {
+                    InsnNode::Insn(
+                        Opcode::IReturn
+                        | Opcode::LReturn
+                        | Opcode::FReturn
+                        | Opcode::DReturn
+                        | Opcode::AReturn
+                        | Opcode::Return
+                        | Opcode::AThrow,
+                    )
+                    | InsnNode::VarInsn {
+                        opcode: Opcode::Ret,
+                        ..
+                    } => false,
+                    InsnNode::JumpInsn {
+                        opcode: Opcode::Jsr,
+                        ..
+                    } => true,
+                    InsnNode::JumpInsn { opcode, label } => {
+                        worklist.push((self.label_index)(*label)?);
+                        *opcode != Opcode::Goto
+                    }
+                    InsnNode::TableSwitchInsn { dflt, labels, .. } => {
+                        worklist.push((self.label_index)(*dflt)?);
+                        for label in labels {
+                            worklist.push((self.label_index)(*label)?);
+                        }
+                        false
+                    }
+                    InsnNode::LookupSwitchInsn { dflt, values } => {
+                        worklist.push((self.label_index)(*dflt)?);
+                        for (_, label) in values {
+                            worklist.push((self.label_index)(*label)?);
+                        }
+                        false
+                    }
+                    _ => true,
+                };
+                if falls_through {
+                    worklist.push(index + 1);
+                }
+            }
+
+            for (range, handler) in self.try_catch_blocks {
+                if !insns[*handler] && insns[range.clone()].iter().any(|&insn| insn) {
+                    worklist.push(*handler);
+                }
+            }
+            if worklist.is_empty() {
+                break;
+            }
+        }
+
+        self.subroutines.insert(start, insns.clone());
+        Ok(insns)
+    }
+
+    fn instantiate(
+        &mut self,
+        parent: Option<usize>,
+        insns: Vec<bool>,
+        subroutine: Option<Label>,
+    ) -> usize {
+        let id = self.instantiations.len();
+        let return_label = parent.map(|_| self.label_creator.create_label());
+
+        let mut range_labels = HashMap::new();
+        let mut current_label = None;
+        for (index, insn) in self.insns.iter().enumerate() {
+            if let InsnNode::Label(label) = insn {
+                let range_label = match parent {
+                    // the main code keeps its labels
+                    None => *label,
+                    Some(_) => {
+                        *current_label.get_or_insert_with(|| self.label_creator.create_label())
+                    }
+                };
+                range_labels.insert(*label, range_label);
+            } else if insns[index] {
+                current_label = None;
+            }
+        }
+
+        self.instantiations.push(Instantiation {
+            parent,
+            subroutine,
+            insns,
+            return_label,
+            range_labels,
+        });
+        id
+    }
+
+    /// The copy which owns the instruction at the given index, as seen from the given copy.
+    fn owner(&self, mut instantiation: usize, index: usize) -> Option<usize> {
+        loop {
+            let current = &self.instantiations[instantiation];
+            if current.insns[index] {
+                return Some(instantiation);
+            }
+            instantiation = current.parent?;
+        }
+    }
+
+    /// The label to jump to for the given label of the original code, from the given copy.
+    fn jump_label(&self, instantiation: usize, label: Label) -> ClassFileResult<Label> {
+        let index = (self.label_index)(label)?;
+        let owner = self.owner(instantiation, index).unwrap_or(instantiation);
+        Ok(self.instantiations[owner].range_labels[&label])
+    }
+
+    fn emit(
+        &mut self,
+        instantiation: usize,
+        out: &mut InsnList<InsnNode<'class>>,
+    ) -> ClassFileResult<()> {
+        let mut previous_label = None;
+        for index in 0..self.insns.len() {
+            let insn = self.insns[index];
+            if let InsnNode::Label(label) = insn {
+                let range_label = self.instantiations[instantiation].range_labels[label];
+                if previous_label != Some(range_label) {
+                    out.push_back(InsnNode::Label(range_label));
+                    previous_label = Some(range_label);
+                }
+                continue;
+            }
+            if self.owner(instantiation, index) != Some(instantiation) {
+                continue;
+            }
+
+            let jump_label = |label: Label| self.jump_label(instantiation, label);
+            let insn = match insn {
+                InsnNode::VarInsn {
+                    opcode: Opcode::Ret,
+                    ..
+                } => InsnNode::JumpInsn {
+                    opcode: Opcode::Goto,
+                    label: self.instantiations[instantiation]
+                        .return_label
+                        .ok_or(ClassFileError::RetOutsideSubroutine)?,
+                },
+                InsnNode::JumpInsn {
+                    opcode: Opcode::Jsr,
+                    label,
+                } => {
+                    let mut ancestor = Some(instantiation);
+                    while let Some(current) = ancestor {
+                        if self.instantiations[current].subroutine == Some(*label) {
+                            return Err(ClassFileError::RecursiveSubroutine(*label));
+                        }
+                        ancestor = self.instantiations[current].parent;
+                    }
+                    let insns = self.subroutine(Some(*label))?;
+                    let callee = self.instantiate(Some(instantiation), insns, Some(*label));
+                    out.push_back(InsnNode::Insn(Opcode::AConstNull));
+                    out.push_back(InsnNode::JumpInsn {
+                        opcode: Opcode::Goto,
+                        label: self.jump_label(callee, *label)?,
+                    });
+                    let return_label = self.instantiations[callee].return_label.unwrap();
+                    out.push_back(InsnNode::Label(return_label));
+                    previous_label = Some(return_label);
+                    continue;
+                }
+                InsnNode::JumpInsn { opcode, label } => InsnNode::JumpInsn {
+                    opcode: *opcode,
+                    label: jump_label(*label)?,
+                },
+                InsnNode::TableSwitchInsn {
+                    low,
+                    high,
+                    dflt,
+                    labels,
+                } => InsnNode::TableSwitchInsn {
+                    low: *low,
+                    high: *high,
+                    dflt: jump_label(*dflt)?,
+                    labels: labels
+                        .iter()
+                        .map(|&label| jump_label(label))
+                        .collect::<ClassFileResult<_>>()?,
+                },
+                InsnNode::LookupSwitchInsn { dflt, values } => InsnNode::LookupSwitchInsn {
+                    dflt: jump_label(*dflt)?,
+                    values: values
+                        .iter()
+                        .map(|&(value, label)| Ok((value, jump_label(label)?)))
+                        .collect::<ClassFileResult<_>>()?,
+                },
+                InsnNode::LineNumber { line, start } => InsnNode::LineNumber {
+                    line: *line,
+                    start: self.instantiations[instantiation].range_labels[start],
+                },
+                // the frames are no longer valid
+                InsnNode::Frame(_) => continue,
+                insn => insn.clone(),
+            };
+            out.push_back(insn);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::{Analyzer, BasicInterpreter};
+    use crate::tree::{InsnNode, MethodNode};
+    use crate::{
+        ClassFileError, LabelCreator, MethodAccess, MethodLocalVariableEvent,
+        MethodTryCatchBlockEvent, Opcode,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_inline_subroutines() {
+        // try { x = 1; } finally { x = 2; }, as compiled by old compilers
+        let label_creator = LabelCreator::default();
+        let [start, end, handler, finally, after] = [(); 5].map(|()| label_creator.create_label());
+        let mut method = MethodNode::new(
+            MethodAccess::Static,
+            JavaStr::from_str("test"),
+            JavaStr::from_str("()V"),
+        );
+        method.label_creator = Some(label_creator);
+        method.instructions.extend([
+            InsnNode::Label(start),
+            InsnNode::Insn(Opcode::IConst1),
+            InsnNode::VarInsn {
+                opcode: Opcode::IStore,
+                var_index: 0,
+            },
+            InsnNode::JumpInsn {
+                opcode: Opcode::Jsr,
+                label: finally,
+            },
+            InsnNode::Label(end),
+            InsnNode::JumpInsn {
+                opcode: Opcode::Goto,
+                label: after,
+            },
+            InsnNode::Label(handler),
+            InsnNode::VarInsn {
+                opcode: Opcode::AStore,
+                var_index: 1,
+            },
+            InsnNode::JumpInsn {
+                opcode: Opcode::Jsr,
+                label: finally,
+            },
+            InsnNode::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 1,
+            },
+            InsnNode::Insn(Opcode::AThrow),
+            InsnNode::Label(finally),
+            InsnNode::VarInsn {
+                opcode: Opcode::AStore,
+                var_index: 2,
+            },
+            InsnNode::Insn(Opcode::IConst2),
+            InsnNode::VarInsn {
+                opcode: Opcode::IStore,
+                var_index: 0,
+            },
+            InsnNode::VarInsn {
+                opcode: Opcode::Ret,
+                var_index: 2,
+            },
+            InsnNode::Label(after),
+            InsnNode::Insn(Opcode::Return),
+        ]);
+        method.try_catch_blocks.push(MethodTryCatchBlockEvent {
+            start,
+            end,
+            handler,
+            ty: None,
+        });
+        method.inline_subroutines().unwrap();
+
+        let insns: Vec<_> = method.instructions.iter().collect();
+        assert!(!insns.iter().any(|insn| matches!(
+            insn,
+            InsnNode::JumpInsn {
+                opcode: Opcode::Jsr,
+                ..
+            } | InsnNode::VarInsn {
+                opcode: Opcode::Ret,
+                ..
+            }
+        )));
+        let copies = insns
+            .iter()
+            .filter(|insn| ***insn == InsnNode::Insn(Opcode::IConst2))
+            .count();
+        assert_eq!(2, copies);
+        assert_eq!(1, method.try_catch_blocks.len());
+
+        let mut analyzer = Analyzer::new(BasicInterpreter::new());
+        analyzer
+            .analyze(JavaStr::from_str("Test"), &method)
+            .unwrap();
+        for handle in method.instructions.real_insns() {
+            assert!(analyzer.frame(handle).is_some());
+        }
+
+        // a subroutine calling itself can't be inlined
+        let label_creator = LabelCreator::default();
+        let recursive = label_creator.create_label();
+        let mut method = MethodNode::new(
+            MethodAccess::Static,
+            JavaStr::from_str("test"),
+            JavaStr::from_str("()V"),
+        );
+        method.label_creator = Some(label_creator);
+        method.instructions.extend([
+            InsnNode::JumpInsn {
+                opcode: Opcode::Jsr,
+                label: recursive,
+            },
+            InsnNode::Insn(Opcode::Return),
+            InsnNode::Label(recursive),
+            InsnNode::JumpInsn {
+                opcode: Opcode::Jsr,
+                label: recursive,
+            },
+            InsnNode::Insn(Opcode::Return),
+        ]);
+        assert_eq!(
+            Err(ClassFileError::RecursiveSubroutine(recursive)),
+            method.inline_subroutines()
+        );
+
+        // labels outside the code are rejected rather than panicking
+        let label_creator = LabelCreator::default();
+        let [subroutine, missing] = [(); 2].map(|()| label_creator.create_label());
+        let mut method = MethodNode::new(
+            MethodAccess::Static,
+            JavaStr::from_str("test"),
+            JavaStr::from_str("()V"),
+        );
+        method.label_creator = Some(label_creator);
+        method.instructions.extend([
+            InsnNode::JumpInsn {
+                opcode: Opcode::Jsr,
+                label: subroutine,
+            },
+            InsnNode::Insn(Opcode::Return),
+            InsnNode::Label(subroutine),
+            InsnNode::VarInsn {
+                opcode: Opcode::AStore,
+                var_index: 0,
+            },
+            InsnNode::VarInsn {
+                opcode: Opcode::Ret,
+                var_index: 0,
+            },
+        ]);
+        let mut with_local = method.clone();
+        with_local.local_variables.push(MethodLocalVariableEvent {
+            name: Cow::Borrowed(JavaStr::from_str("x")),
+            desc: Cow::Borrowed(JavaStr::from_str("I")),
+            signature: None,
+            start: subroutine,
+            end: missing,
+            index: 1,
+        });
+        assert_eq!(
+            Err(ClassFileError::UnknownLabel(missing)),
+            with_local.inline_subroutines()
+        );
+        method.instructions.push_front(InsnNode::LineNumber {
+            line: 1,
+            start: missing,
+        });
+        assert_eq!(
+            Err(ClassFileError::UnknownLabel(missing)),
+            method.inline_subroutines()
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use crate::tree::{AnnotationNode, AnnotationValue, InsnList, InsnNode, TypeAnnotationNode};
+use crate::{
+    buffer_method_events, AnnotationEvent, Attribute, BufferedEventProviders, ClassFileResult,
+    ClassMethodEvent, EventBuffer, LabelCreator, MethodAccess, MethodAnnotableParameterCountEvent,
+    MethodEvent, MethodEventProviders, MethodLocalVariableAnnotationEvent,
+    MethodLocalVariableEvent, MethodMaxsEvent, MethodParameterAnnotationEvent,
+    MethodParameterEvent, MethodTryCatchBlockAnnotationEvent, MethodTryCatchBlockEvent,
+    MethodUnchangedEvent,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+pub type BufferedMethodEvent<'class> =
+    ClassMethodEvent<'class, EventBuffer<MethodEvent<'class, BufferedEventProviders>>>;
+
+/// A method held in memory, whose instructions can be edited in any order.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodNode<'class> {
+    pub access: MethodAccess,
+    pub name: Cow<'class, JavaStr>,
+    pub desc: Cow<'class, JavaStr>,
+    pub signature: Option<Cow<'class, JavaStr>>,
+    pub exceptions: Vec<Cow<'class, JavaStr>>,
+    pub deprecated: bool,
+    pub parameters: Vec<MethodParameterEvent<'class>>,
+    pub annotation_default: Option<AnnotationValue<'class>>,
+    pub annotations: Vec<AnnotationEvent<AnnotationNode<'class>>>,
+    pub type_annotations: Vec<AnnotationEvent<TypeAnnotationNode<'class>>>,
+    pub annotable_parameter_counts: Vec<MethodAnnotableParameterCountEvent>,
+    pub parameter_annotations: Vec<MethodParameterAnnotationEvent<'class>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub attributes: Vec<Box<dyn Attribute>>,
+    /// The label creator of the code, or `None` if the method has no code.
+    ///
+    /// With the `serde` feature, a deserialized creator starts from the same id as the one it was
+    /// serialized from, but no longer shares it. Labels created in one copy of the method can
+    /// therefore collide with labels created in another.
+    pub label_creator: Option<LabelCreator>,
+    pub instructions: InsnList<InsnNode<'class>>,
+    pub local_variables: Vec<MethodLocalVariableEvent<'class>>,
+    pub local_variable_annotations: Vec<MethodLocalVariableAnnotationEvent<'class>>,
+    pub try_catch_blocks: Vec<MethodTryCatchBlockEvent<'class>>,
+    pub try_catch_block_annotations: Vec<MethodTryCatchBlockAnnotationEvent<'class>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub code_attributes: Vec<Box<dyn Attribute>>,
+    pub maxs: Option<MethodMaxsEvent>,
+    /// If set, the method is copied from the class it was read from, and the rest of the node is
+    /// ignored. See [`MethodEvent::Unchanged`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub unchanged: Option<MethodUnchangedEvent<'class>>,
+}
+
+impl<'class> MethodNode<'class> {
+    pub fn new(
+        access: MethodAccess,
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) -> Self {
+        MethodNode {
+            access,
+            name: name.into(),
+            desc: desc.into(),
+            signature: None,
+            exceptions: Vec::new(),
+            deprecated: false,
+            parameters: Vec::new(),
+            annotation_default: None,
+            annotations: Vec::new(),
+            type_annotations: Vec::new(),
+            annotable_parameter_counts: Vec::new(),
+            parameter_annotations: Vec::new(),
+            attributes: Vec::new(),
+            label_creator: None,
+            instructions: InsnList::new(),
+            local_variables: Vec::new(),
+            local_variable_annotations: Vec::new(),
+            try_catch_blocks: Vec::new(),
+            try_catch_block_annotations: Vec::new(),
+            code_attributes: Vec::new(),
+            maxs: None,
+            unchanged: None,
+        }
+    }
+
+    pub fn from_event<E, P>(method: ClassMethodEvent<'class, E>) -> ClassFileResult<Self>
+    where
+        E: IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+        P: MethodEventProviders<'class>,
+    {
+        let mut node = MethodNode::new(method.access, method.name, method.desc);
+        node.signature = method.signature;
+        node.exceptions = method.exceptions;
+        for event in buffer_method_events(method.events)?.0 {
+            let event = match InsnNode::try_from(event) {
+                Ok(insn) => {
+                    node.instructions.push_back(insn);
+                    continue;
+                }
+                Err(event) => event,
+            };
+            match event {
+                MethodEvent::Deprecated => node.deprecated = true,
+                MethodEvent::Parameters(events) => node.parameters.extend(events.0),
+                MethodEvent::AnnotationDefault(value) => node.annotation_default = Some(value),
+                MethodEvent::Annotations(events) => node.annotations.extend(events.0),
+                MethodEvent::TypeAnnotations(events) => node.type_annotations.extend(events.0),
+                MethodEvent::AnnotableParameterCount(event) => {
+                    node.annotable_parameter_counts.push(event)
+                }
+                MethodEvent::ParameterAnnotations(events) => {
+                    node.parameter_annotations.extend(events.0)
+                }
+                MethodEvent::Attributes(events) => node.attributes.extend(events.0),
+                MethodEvent::Code { label_creator } => node.label_creator = Some(label_creator),
+                MethodEvent::LocalVariables(events) => node.local_variables.extend(events.0),
+                MethodEvent::LocalVariableAnnotations(events) => {
+                    node.local_variable_annotations.extend(events.0)
+                }
+                MethodEvent::TryCatchBlocks(events) => node.try_catch_blocks.extend(events.0),
+                MethodEvent::TryCatchBlockAnnotations(events) => {
+                    node.try_catch_block_annotations.extend(events.0)
+                }
+                MethodEvent::CodeAttributes(events) => node.code_attributes.extend(events.0),
+                MethodEvent::Maxs(maxs) => node.maxs = Some(maxs),
+                MethodEvent::Unchanged(event) => node.unchanged = Some(event),
+                // instructions are handled above
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    /// Copies the method into events, in the order a [`ClassReader`](crate::ClassReader) would
+    /// produce them.
+    pub fn to_event(&self) -> BufferedMethodEvent<'class> {
+        ClassMethodEvent {
+            access: self.access,
+            name: self.name.clone(),
+            desc: self.desc.clone(),
+            signature: self.signature.clone(),
+            exceptions: self.exceptions.clone(),
+            events: self.to_method_events(),
+        }
+    }
+
+    fn to_method_events(&self) -> EventBuffer<MethodEvent<'class, BufferedEventProviders>> {
+        if let Some(unchanged) = self.unchanged {
+            return EventBuffer(vec![MethodEvent::Unchanged(unchanged)]);
+        }
+
+        let mut events = Vec::new();
+        if self.deprecated {
+            events.push(MethodEvent::Deprecated);
+        }
+        if !self.parameters.is_empty() {
+            events.push(MethodEvent::Parameters(EventBuffer(
+                self.parameters.clone(),
+            )));
+        }
+        if let Some(annotation_default) = &self.annotation_default {
+            events.push(MethodEvent::AnnotationDefault(annotation_default.clone()));
+        }
+        if !self.annotations.is_empty() {
+            events.push(MethodEvent::Annotations(EventBuffer(
+                self.annotations.clone(),
+            )));
+        }
+        if !self.type_annotations.is_empty() {
+            events.push(MethodEvent::TypeAnnotations(EventBuffer(
+                self.type_annotations.clone(),
+            )));
+        }
+        events.extend(
+            self.annotable_parameter_counts
+                .iter()
+                .copied()
+                .map(MethodEvent::AnnotableParameterCount),
+        );
+        if !self.parameter_annotations.is_empty() {
+            events.push(MethodEvent::ParameterAnnotations(EventBuffer(
+                self.parameter_annotations.clone(),
+            )));
+        }
+        if !self.attributes.is_empty() {
+            events.push(MethodEvent::Attributes(EventBuffer(
+                self.attributes.clone(),
+            )));
+        }
+
+        let Some(label_creator) = &self.label_creator else {
+            return EventBuffer(events);
+        };
+        events.push(MethodEvent::Code {
+            label_creator: label_creator.clone(),
+        });
+        events.extend(self.instructions.iter().map(InsnNode::to_event));
+        if !self.local_variables.is_empty() {
+            events.push(MethodEvent::LocalVariables(EventBuffer(
+                self.local_variables.clone(),
+            )));
+        }
+        if !self.local_variable_annotations.is_empty() {
+            events.push(MethodEvent::LocalVariableAnnotations(EventBuffer(
+                self.local_variable_annotations.clone(),
+            )));
+        }
+        if !self.try_catch_blocks.is_empty() {
+            events.push(MethodEvent::TryCatchBlocks(EventBuffer(
+                self.try_catch_blocks.clone(),
+            )));
+        }
+        if !self.try_catch_block_annotations.is_empty() {
+            events.push(MethodEvent::TryCatchBlockAnnotations(EventBuffer(
+                self.try_catch_block_annotations.clone(),
+            )));
+        }
+        if !self.code_attributes.is_empty() {
+            events.push(MethodEvent::CodeAttributes(EventBuffer(
+                self.code_attributes.clone(),
+            )));
+        }
+        if let Some(maxs) = self.maxs {
+            events.push(MethodEvent::Maxs(maxs));
+        }
+        EventBuffer(events)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::MethodNode;
+    use crate::{buffer_class_events, ClassReader, ClassReaderFlags};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_method_node_round_trip() {
+        let reader = ClassReader::new(include_class!("TestCode"), ClassReaderFlags::None).unwrap();
+        let events = buffer_class_events(&reader).unwrap();
+        let methods = events
+            .0
+            .into_iter()
+            .find_map(|event| event.try_unwrap_methods().ok())
+            .unwrap();
+        for method in methods.0 {
+            let node = MethodNode::from_event(method.clone()).unwrap();
+            assert!(node.label_creator.is_some());
+            assert!(!node.instructions.is_empty());
+            assert_eq!(format!("{:?}", method), format!("{:?}", node.to_event()));
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use crate::tree::{ClassNode, InsnNode, MethodNode};
+use crate::{
+    BootstrapMethodArgument, ClassFileError, ClassFileResult, ConstantDynamic, Frame, FrameValue,
+    Handle, Label, LabelCreator, LdcConstant,
+};
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+impl<'class> ClassNode<'class> {
+    /// Copies a method of another class into this class, see [`MethodNode::copy_to_class`].
+    pub fn copy_method(
+        &mut self,
+        source: &ClassNode<'class>,
+        method: &MethodNode<'class>,
+    ) -> ClassFileResult<&mut MethodNode<'class>> {
+        let method = method.copy_to_class(&source.name, &self.name)?;
+        self.methods.push(method);
+        Ok(self.methods.last_mut().unwrap())
+    }
+}
+
+impl<'class> MethodNode<'class> {
+    /// Copies the method from the class `from` into the class `to`. References to `from` in the
+    /// descriptor, signature, exceptions, instructions, frames, local variables and try-catch
+    /// blocks are replaced with `to`, and the labels are replaced with labels from a new
+    /// [`LabelCreator`], so that the copy can be edited independently of the original.
+    ///
+    /// Unchanged methods can't be copied, as their bytecode can't be rewritten.
+    pub fn copy_to_class(&self, from: &JavaStr, to: &JavaStr) -> ClassFileResult<Self> {
+        if self.unchanged.is_some() {
+            return Err(ClassFileError::CopyUnchangedMethod);
+        }
+
+        let mut remapper = SelfRemapper {
+            from,
+            to,
+            label_creator: LabelCreator::default(),
+            labels: HashMap::new(),
+        };
+        let mut method = self.clone();
+        remapper.remap_desc(&mut method.desc);
+        if let Some(signature) = &mut method.signature {
+            remapper.remap_desc(signature);
+        }
+        for exception in &mut method.exceptions {
+            remapper.remap_name(exception);
+        }
+
+        if method.label_creator.is_none() {
+            return Ok(method);
+        }
+        let handles: Vec<_> = method.instructions.handles().collect();
+        for handle in handles {
+            remapper.remap_insn(&mut method.instructions[handle]);
+        }
+        for local_variable in &mut method.local_variables {
+            remapper.remap_desc(&mut local_variable.desc);
+            if let Some(signature) = &mut local_variable.signature {
+                remapper.remap_desc(signature);
+            }
+            remapper.remap_label(&mut local_variable.start);
+            remapper.remap_label(&mut local_variable.end);
+        }
+        for annotation in &mut method.local_variable_annotations {
+            for (start, end, _) in &mut annotation.ranges {
+                remapper.remap_label(start);
+                remapper.remap_label(end);
+            }
+        }
+        for try_catch_block in &mut method.try_catch_blocks {
+            remapper.remap_label(&mut try_catch_block.start);
+            remapper.remap_label(&mut try_catch_block.end);
+            remapper.remap_label(&mut try_catch_block.handler);
+            if let Some(ty) = &mut try_catch_block.ty {
+                remapper.remap_name(ty);
+            }
+        }
+        method.label_creator = Some(remapper.label_creator);
+        Ok(method)
+    }
+}
+
+struct SelfRemapper<'a> {
+    from: &'a JavaStr,
+    to: &'a JavaStr,
+    label_creator: LabelCreator,
+    labels: HashMap<Label, Label>,
+}
+
+impl SelfRemapper<'_> {
+    fn remap_insn(&mut self, insn: &mut InsnNode) {
+        match insn {
+            InsnNode::TypeInsn { ty, .. } => self.remap_name(ty),
+            InsnNode::FieldInsn { owner, desc, .. } => {
+                self.remap_name(owner);
+                self.remap_desc(desc);
+            }
+            InsnNode::MethodInsn { owner, desc, .. } => {
+                self.remap_name(owner);
+                self.remap_desc(desc);
+            }
+            InsnNode::InvokeDynamicInsn {
+                desc,
+                bootstrap_method_handle,
+                bootstrap_method_arguments,
+                ..
+            } => {
+                self.remap_desc(desc);
+                self.remap_handle(bootstrap_method_handle);
+                self.remap_arguments(bootstrap_method_arguments);
+            }
+            InsnNode::JumpInsn { label, .. } => self.remap_label(label),
+            InsnNode::LdcInsn(constant) => match constant {
+                LdcConstant::Class(name) => self.remap_name(name),
+                LdcConstant::MethodType(desc) => self.remap_desc(desc),
+                LdcConstant::Handle(handle) => self.remap_handle(handle),
+                LdcConstant::ConstantDynamic(constant) => self.remap_constant_dynamic(constant),
+                _ => {}
+            },
+            InsnNode::TableSwitchInsn { dflt, labels, .. } => {
+                self.remap_label(dflt);
+                for label in labels {
+                    self.remap_label(label);
+                }
+            }
+            InsnNode::LookupSwitchInsn { dflt, values } => {
+                self.remap_label(dflt);
+                for (_, label) in values {
+                    self.remap_label(label);
+                }
+            }
+            InsnNode::MultiANewArrayInsn { desc, .. } => self.remap_desc(desc),
+            InsnNode::Label(label) => self.remap_label(label),
+            InsnNode::LineNumber { start, .. } => self.remap_label(start),
+            InsnNode::Frame(frame) => match frame {
+                Frame::Full { locals, stack } | Frame::New { locals, stack } => {
+                    for value in locals.iter_mut().chain(stack) {
+                        self.remap_frame_value(value);
+                    }
+                }
+                Frame::Append { locals } => {
+                    for value in locals {
+                        self.remap_frame_value(value);
+                    }
+                }
+                Frame::Same1 { stack_value } => self.remap_frame_value(stack_value),
+                Frame::Chop { .. } | Frame::Same => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn remap_frame_value(&mut self, value: &mut FrameValue) {
+        match value {
+            FrameValue::Class(name) => self.remap_name(name),
+            FrameValue::Uninitialized(label) => self.remap_label(label),
+            _ => {}
+        }
+    }
+
+    fn remap_handle(&self, handle: &mut Handle) {
+        self.remap_name(&mut handle.owner);
+        self.remap_desc(&mut handle.desc);
+    }
+
+    fn remap_constant_dynamic(&self, constant: &mut ConstantDynamic) {
+        self.remap_desc(&mut constant.desc);
+        self.remap_handle(&mut constant.bootstrap_method);
+        self.remap_arguments(&mut constant.bootstrap_method_arguments);
+    }
+
+    fn remap_arguments(&self, arguments: &mut [BootstrapMethodArgument]) {
+        for argument in arguments {
+            match argument {
+                BootstrapMethodArgument::Class(name) => self.remap_name(name),
+                BootstrapMethodArgument::Handle(handle) => self.remap_handle(handle),
+                BootstrapMethodArgument::ConstantDynamic(constant) => {
+                    self.remap_constant_dynamic(constant)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn remap_label(&mut self, label: &mut Label) {
+        *label = *self
+            .labels
+            .entry(*label)
+            .or_insert_with(|| self.label_creator.create_label());
+    }
+
+    /// Remaps an internal name, or an array descriptor.
+    fn remap_name(&self, name: &mut Cow<JavaStr>) {
+        if name.starts_with('[') {
+            self.remap_desc(name);
+        } else if **name == *self.from {
+            *name = Cow::Owned(self.to.to_owned());
+        }
+    }
+
+    /// Remaps the classes in a descriptor or signature.
+    fn remap_desc(&self, desc: &mut Cow<JavaStr>) {
+        if let Some(remapped) = remap_desc(desc, self.from, self.to) {
+            *desc = Cow::Owned(remapped);
+        }
+    }
+}
+
+/// Replaces the class `from` with `to` in a descriptor or signature, or returns `None` if it
+/// doesn't occur. Type variables, type parameters and inner class suffixes in signatures are
+/// skipped, as they are terminated in the same way as class names.
+fn remap_desc(desc: &JavaStr, from: &JavaStr, to: &JavaStr) -> Option<JavaString> {
+    let bytes = desc.as_bytes();
+    let mut remapped: Option<JavaString> = None;
+    let mut copied = 0;
+    let mut index = 0;
+    while index < bytes.len() {
+        if !matches!(bytes[index], b'L' | b'T' | b'.') {
+            index += 1;
+            continue;
+        }
+        let start = index + 1;
+        let Some(len) = bytes[start..]
+            .iter()
+            .position(|b| matches!(b, b';' | b'<' | b'.' | b':'))
+        else {
+            break;
+        };
+        let end = start + len;
+        if bytes[index] == b'L' && bytes[end] != b':' && desc[start..end] == *from {
+            let remapped = remapped.get_or_insert_with(|| JavaString::with_capacity(desc.len()));
+            remapped.push_java_str(&desc[copied..start]);
+            remapped.push_java_str(to);
+            copied = end;
+        }
+        index = end;
+    }
+    let mut remapped = remapped?;
+    remapped.push_java_str(&desc[copied..]);
+    Some(remapped)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::method_copy::remap_desc;
+    use crate::tree::{ClassNode, InsnNode, MethodNode};
+    use crate::{
+        ClassAccess, ClassClassEvent, Frame, FrameValue, LabelCreator, MethodAccess,
+        MethodTryCatchBlockEvent, Opcode, JAVA_17_VERSION,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+
+    fn str(value: &str) -> Cow<'_, JavaStr> {
+        Cow::Borrowed(JavaStr::from_str(value))
+    }
+
+    fn class(name: &str) -> ClassNode<'_> {
+        ClassNode::new(ClassClassEvent {
+            major_version: JAVA_17_VERSION,
+            minor_version: 0,
+            access: ClassAccess::Public,
+            name: str(name),
+            signature: None,
+            super_name: Some(str("java/lang/Object")),
+            interfaces: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_remap_desc() {
+        let remap = |desc: &str| {
+            remap_desc(
+                JavaStr::from_str(desc),
+                JavaStr::from_str("Source"),
+                JavaStr::from_str("Target"),
+            )
+            .map(|desc| desc.into_string().unwrap())
+        };
+        assert_eq!(
+            Some("(ILTarget;)[LTarget;".to_owned()),
+            remap("(ILSource;)[LSource;")
+        );
+        assert_eq!(None, remap("(Lpkg/Source;LSourceX;)V"));
+        assert_eq!(
+            Some("<LSource:LTarget;>(LTarget<TLSource;>.LSource;)V".to_owned()),
+            remap("<LSource:LSource;>(LSource<TLSource;>.LSource;)V")
+        );
+    }
+
+    #[test]
+    fn test_copy_method() {
+        let mut source = class("Source");
+        let mut method = MethodNode::new(MethodAccess::Public, str("run"), str("(LSource;)V"));
+        let label_creator = LabelCreator::default();
+        let start = label_creator.create_label();
+        let end = label_creator.create_label();
+        method.label_creator = Some(label_creator);
+        method.instructions.extend([
+            InsnNode::Label(start),
+            InsnNode::VarInsn {
+                opcode: Opcode::ALoad,
+                var_index: 1,
+            },
+            InsnNode::MethodInsn {
+                opcode: Opcode::InvokeVirtual,
+                owner: str("Source"),
+                name: str("run"),
+                desc: str("(LSource;)V"),
+                is_interface: false,
+            },
+            InsnNode::FieldInsn {
+                opcode: Opcode::GetStatic,
+                owner: str("java/lang/System"),
+                name: str("out"),
+                desc: str("Ljava/io/PrintStream;"),
+            },
+            InsnNode::Label(end),
+            InsnNode::Frame(Frame::Same1 {
+                stack_value: FrameValue::Class(str("Source")),
+            }),
+            InsnNode::Insn(Opcode::Return),
+        ]);
+        method.try_catch_blocks.push(MethodTryCatchBlockEvent {
+            start,
+            end,
+            handler: end,
+            ty: None,
+        });
+        source.methods.push(method);
+
+        let mut target = class("Target");
+        let copy = target
+            .copy_method(&source, &source.methods[0])
+            .unwrap()
+            .clone();
+        assert_eq!("(LTarget;)V", copy.desc.as_ref());
+        let insns: Vec<_> = copy.instructions.iter().cloned().collect();
+        let (InsnNode::Label(new_start), InsnNode::Label(new_end)) = (&insns[0], &insns[4]) else {
+            panic!("expected labels, got {:?}", insns);
+        };
+        let new_label = copy.label_creator.as_ref().unwrap().create_label();
+        assert!(![*new_start, *new_end].contains(&new_label));
+        assert_eq!(
+            new_label,
+            source.methods[0]
+                .label_creator
+                .as_ref()
+                .unwrap()
+                .create_label()
+        );
+        assert_eq!(
+            InsnNode::MethodInsn {
+                opcode: Opcode::InvokeVirtual,
+                owner: str("Target"),
+                name: str("run"),
+                desc: str("(LTarget;)V"),
+                is_interface: false,
+            },
+            insns[2]
+        );
+        assert_eq!(
+            InsnNode::FieldInsn {
+                opcode: Opcode::GetStatic,
+                owner: str("java/lang/System"),
+                name: str("out"),
+                desc: str("Ljava/io/PrintStream;"),
+            },
+            insns[3]
+        );
+        assert_eq!(
+            InsnNode::Frame(Frame::Same1 {
+                stack_value: FrameValue::Class(str("Target")),
+            }),
+            insns[5]
+        );
+        assert_eq!(
+            (*new_start, *new_end, *new_end),
+            (
+                copy.try_catch_blocks[0].start,
+                copy.try_catch_blocks[0].end,
+                copy.try_catch_blocks[0].handler
+            )
+        );
+        assert_eq!(1, target.methods.len());
+        assert_eq!("(LSource;)V", source.methods[0].desc.as_ref());
+    }
+}
@@ -1,3 +1,23 @@
 pub mod annotation;
+pub mod class;
+pub mod field;
+pub mod insn;
+pub mod insn_list;
+pub mod jsr_inliner;
+pub mod method;
+pub mod method_copy;
+pub mod module;
+pub mod record_component;
+pub mod signature;
 
 pub use annotation::*;
+pub use class::*;
+pub use field::*;
+pub use insn::*;
+pub use insn_list::*;
+pub use jsr_inliner::*;
+pub use method::*;
+pub use method_copy::*;
+pub use module::*;
+pub use record_component::*;
+pub use signature::*;
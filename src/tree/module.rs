@@ -0,0 +1,154 @@
+use crate::{
+    buffer_module_events, BufferedEventProviders, ClassFileResult, ClassModuleEvent, EventBuffer,
+    ModuleAccess, ModuleEvent, ModuleEventProviders, ModuleHashesEvent, ModuleProvidesEvent,
+    ModuleRelationEvent, ModuleRequireEvent, ModuleResolution,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+pub type BufferedModuleEvent<'class> =
+    ClassModuleEvent<'class, EventBuffer<ModuleEvent<'class, BufferedEventProviders>>>;
+
+/// The module descriptor of a `module-info` class.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleNode<'class> {
+    pub name: Cow<'class, JavaStr>,
+    pub access: ModuleAccess,
+    pub version: Option<Cow<'class, JavaStr>>,
+    pub main_class: Option<Cow<'class, JavaStr>>,
+    pub packages: Vec<Cow<'class, JavaStr>>,
+    pub target: Option<Cow<'class, JavaStr>>,
+    pub resolution: Option<ModuleResolution>,
+    pub hashes: Option<ModuleHashesEvent<'class>>,
+    pub requires: Vec<ModuleRequireEvent<'class>>,
+    pub exports: Vec<ModuleRelationEvent<'class>>,
+    pub opens: Vec<ModuleRelationEvent<'class>>,
+    pub uses: Vec<Cow<'class, JavaStr>>,
+    pub provides: Vec<ModuleProvidesEvent<'class>>,
+}
+
+impl<'class> ModuleNode<'class> {
+    pub fn new(name: impl Into<Cow<'class, JavaStr>>, access: ModuleAccess) -> Self {
+        ModuleNode {
+            name: name.into(),
+            access,
+            version: None,
+            main_class: None,
+            packages: Vec::new(),
+            target: None,
+            resolution: None,
+            hashes: None,
+            requires: Vec::new(),
+            exports: Vec::new(),
+            opens: Vec::new(),
+            uses: Vec::new(),
+            provides: Vec::new(),
+        }
+    }
+
+    pub fn from_event<E, P>(module: ClassModuleEvent<'class, E>) -> ClassFileResult<Self>
+    where
+        E: IntoIterator<Item = ClassFileResult<ModuleEvent<'class, P>>>,
+        P: ModuleEventProviders<'class>,
+    {
+        let mut node = ModuleNode::new(module.name, module.access);
+        node.version = module.version;
+        for event in buffer_module_events(module.events)?.0 {
+            match event {
+                ModuleEvent::MainClass(main_class) => node.main_class = Some(main_class),
+                ModuleEvent::Packages(events) => node.packages.extend(events.0),
+                ModuleEvent::Target(target) => node.target = Some(target),
+                ModuleEvent::Resolution(resolution) => node.resolution = Some(resolution),
+                ModuleEvent::Hashes(hashes) => node.hashes = Some(hashes),
+                ModuleEvent::Requires(events) => node.requires.extend(events.0),
+                ModuleEvent::Exports(events) => node.exports.extend(events.0),
+                ModuleEvent::Opens(events) => node.opens.extend(events.0),
+                ModuleEvent::Uses(events) => node.uses.extend(events.0),
+                ModuleEvent::Provides(events) => node.provides.extend(events.0),
+            }
+        }
+        Ok(node)
+    }
+
+    pub fn to_event(&self) -> BufferedModuleEvent<'class> {
+        let mut events = Vec::new();
+        if let Some(main_class) = &self.main_class {
+            events.push(ModuleEvent::MainClass(main_class.clone()));
+        }
+        if !self.packages.is_empty() {
+            events.push(ModuleEvent::Packages(EventBuffer(self.packages.clone())));
+        }
+        if let Some(target) = &self.target {
+            events.push(ModuleEvent::Target(target.clone()));
+        }
+        if let Some(resolution) = self.resolution {
+            events.push(ModuleEvent::Resolution(resolution));
+        }
+        if let Some(hashes) = &self.hashes {
+            events.push(ModuleEvent::Hashes(hashes.clone()));
+        }
+        if !self.requires.is_empty() {
+            events.push(ModuleEvent::Requires(EventBuffer(self.requires.clone())));
+        }
+        if !self.exports.is_empty() {
+            events.push(ModuleEvent::Exports(EventBuffer(self.exports.clone())));
+        }
+        if !self.opens.is_empty() {
+            events.push(ModuleEvent::Opens(EventBuffer(self.opens.clone())));
+        }
+        if !self.uses.is_empty() {
+            events.push(ModuleEvent::Uses(EventBuffer(self.uses.clone())));
+        }
+        if !self.provides.is_empty() {
+            events.push(ModuleEvent::Provides(EventBuffer(self.provides.clone())));
+        }
+        ClassModuleEvent {
+            name: self.name.clone(),
+            access: self.access,
+            version: self.version.clone(),
+            events: EventBuffer(events),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::ModuleNode;
+    use crate::{
+        buffer_class_events, ClassReader, ClassReaderFlags, ModuleRelationAccess,
+        ModuleRelationEvent,
+    };
+    use java_string::JavaStr;
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_module_node() {
+        let reader =
+            ClassReader::new(include_class!("module-info"), ClassReaderFlags::None).unwrap();
+        let module = buffer_class_events(&reader)
+            .unwrap()
+            .0
+            .into_iter()
+            .find_map(|event| event.try_unwrap_module().ok())
+            .unwrap();
+        let mut node = ModuleNode::from_event(module.clone()).unwrap();
+        assert_eq!(format!("{module:?}"), format!("{:?}", node.to_event()));
+        assert_eq!("test", node.name.as_ref());
+        assert_eq!(3, node.requires.len());
+        assert_eq!("java/lang/Runnable", node.uses[0].as_ref());
+
+        node.requires
+            .retain(|require| require.module.as_ref() != "java.logging");
+        node.exports.push(ModuleRelationEvent {
+            package: Cow::Borrowed(JavaStr::from_str("pkg3")),
+            access: ModuleRelationAccess::empty(),
+            modules: Vec::new(),
+        });
+        let module = node.to_event();
+        let node = ModuleNode::from_event(module).unwrap();
+        assert_eq!(2, node.requires.len());
+        assert_eq!(3, node.exports.len());
+    }
+}
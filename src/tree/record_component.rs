@@ -0,0 +1,124 @@
+use crate::tree::{AnnotationNode, TypeAnnotationNode};
+use crate::{
+    buffer_record_component_events, AnnotationEvent, Attribute, BufferedEventProviders,
+    ClassFileResult, ClassRecordComponentEvent, EventBuffer, RecordComponentEvent,
+    RecordComponentEventProviders,
+};
+use java_string::JavaStr;
+use std::borrow::Cow;
+
+pub type BufferedRecordComponentEvent<'class> = ClassRecordComponentEvent<
+    'class,
+    EventBuffer<RecordComponentEvent<'class, BufferedEventProviders>>,
+>;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordComponentNode<'class> {
+    pub name: Cow<'class, JavaStr>,
+    pub desc: Cow<'class, JavaStr>,
+    pub signature: Option<Cow<'class, JavaStr>>,
+    pub annotations: Vec<AnnotationEvent<AnnotationNode<'class>>>,
+    pub type_annotations: Vec<AnnotationEvent<TypeAnnotationNode<'class>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub attributes: Vec<Box<dyn Attribute>>,
+}
+
+impl<'class> RecordComponentNode<'class> {
+    pub fn new(
+        name: impl Into<Cow<'class, JavaStr>>,
+        desc: impl Into<Cow<'class, JavaStr>>,
+    ) -> Self {
+        RecordComponentNode {
+            name: name.into(),
+            desc: desc.into(),
+            signature: None,
+            annotations: Vec::new(),
+            type_annotations: Vec::new(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn from_event<E, P>(
+        component: ClassRecordComponentEvent<'class, E>,
+    ) -> ClassFileResult<Self>
+    where
+        E: IntoIterator<Item = ClassFileResult<RecordComponentEvent<'class, P>>>,
+        P: RecordComponentEventProviders<'class>,
+    {
+        let mut node = RecordComponentNode::new(component.name, component.desc);
+        node.signature = component.signature;
+        for event in buffer_record_component_events(component.events)?.0 {
+            match event {
+                RecordComponentEvent::Annotations(events) => node.annotations.extend(events.0),
+                RecordComponentEvent::TypeAnnotations(events) => {
+                    node.type_annotations.extend(events.0)
+                }
+                RecordComponentEvent::Attributes(events) => node.attributes.extend(events.0),
+            }
+        }
+        Ok(node)
+    }
+
+    pub fn to_event(&self) -> BufferedRecordComponentEvent<'class> {
+        let mut events = Vec::new();
+        if !self.annotations.is_empty() {
+            events.push(RecordComponentEvent::Annotations(EventBuffer(
+                self.annotations.clone(),
+            )));
+        }
+        if !self.type_annotations.is_empty() {
+            events.push(RecordComponentEvent::TypeAnnotations(EventBuffer(
+                self.type_annotations.clone(),
+            )));
+        }
+        if !self.attributes.is_empty() {
+            events.push(RecordComponentEvent::Attributes(EventBuffer(
+                self.attributes.clone(),
+            )));
+        }
+        ClassRecordComponentEvent {
+            name: self.name.clone(),
+            desc: self.desc.clone(),
+            signature: self.signature.clone(),
+            events: EventBuffer(events),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{FieldNode, RecordComponentNode};
+    use crate::{buffer_class_events, ClassEvent, ClassReader, ClassReaderFlags};
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_record_component_and_field_nodes() {
+        let reader =
+            ClassReader::new(include_class!("TestRecord"), ClassReaderFlags::None).unwrap();
+        let mut component_count = 0;
+        let mut field_count = 0;
+        for event in buffer_class_events(&reader).unwrap().0 {
+            match event {
+                ClassEvent::Record(components) => {
+                    for component in components.0 {
+                        let node = RecordComponentNode::from_event(component.clone()).unwrap();
+                        assert_eq!(format!("{component:?}"), format!("{:?}", node.to_event()));
+                        component_count += 1;
+                    }
+                }
+                ClassEvent::Fields(fields) => {
+                    for field in fields.0 {
+                        let node = FieldNode::from_event(field.clone()).unwrap();
+                        assert_eq!(format!("{field:?}"), format!("{:?}", node.to_event()));
+                        field_count += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(2, component_count);
+        // the component fields and the static field
+        assert_eq!(3, field_count);
+    }
+}
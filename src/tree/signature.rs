@@ -0,0 +1,497 @@
+use crate::{
+    ClassFileResult, SignatureReader, SignatureVisitor, SignatureWriter, TypeArgumentKind,
+};
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+
+/// A parsed class signature, such as `<T:Ljava/lang/Object;>Ljava/util/AbstractList<TT;>;`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassSignature<'class> {
+    pub type_parameters: Vec<TypeParameter<'class>>,
+    pub superclass: ClassTypeSignature<'class>,
+    pub interfaces: Vec<ClassTypeSignature<'class>>,
+}
+
+/// A parsed method signature, such as `<T:Ljava/lang/Object;>(TT;)V^Ljava/io/IOException;`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodSignature<'class> {
+    pub type_parameters: Vec<TypeParameter<'class>>,
+    pub parameter_types: Vec<TypeSignature<'class>>,
+    /// The return type, which is [`TypeSignature::Base('V')`](TypeSignature::Base) for `void`.
+    pub return_type: TypeSignature<'class>,
+    /// The thrown exceptions, each of which is a class type or a type variable.
+    pub exceptions: Vec<TypeSignature<'class>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeParameter<'class> {
+    pub name: Cow<'class, JavaStr>,
+    /// The class bound, which is missing for type parameters bounded only by interfaces.
+    pub class_bound: Option<TypeSignature<'class>>,
+    pub interface_bounds: Vec<TypeSignature<'class>>,
+}
+
+/// A parsed type, which is also the format of field signatures.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeSignature<'class> {
+    /// A primitive type, given as its descriptor character such as `I`.
+    Base(char),
+    TypeVariable(Cow<'class, JavaStr>),
+    Array(Box<TypeSignature<'class>>),
+    Class(ClassTypeSignature<'class>),
+}
+
+/// A class type, such as `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassTypeSignature<'class> {
+    /// The internal name of the outermost class.
+    pub name: Cow<'class, JavaStr>,
+    pub type_arguments: Vec<TypeArgument<'class>>,
+    /// The inner classes qualifying the class, from outermost to innermost.
+    pub inner_classes: Vec<InnerClassTypeSignature<'class>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InnerClassTypeSignature<'class> {
+    /// The simple name of the inner class.
+    pub name: Cow<'class, JavaStr>,
+    pub type_arguments: Vec<TypeArgument<'class>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeArgument<'class> {
+    /// The unbounded wildcard `?`, written as `*`.
+    Unbounded,
+    Bounded(TypeArgumentKind, TypeSignature<'class>),
+}
+
+impl<'class> ClassSignature<'class> {
+    pub fn parse(signature: &'class JavaStr) -> ClassFileResult<Self> {
+        let mut builder = SignatureBuilder::default();
+        SignatureReader::new(signature).read_class(&mut builder)?;
+        let mut superclass = None;
+        let mut interfaces = Vec::new();
+        for (slot, ty) in builder.types {
+            if let TypeSignature::Class(ty) = ty {
+                match slot {
+                    Slot::Superclass => superclass = Some(ty),
+                    _ => interfaces.push(ty),
+                }
+            }
+        }
+        Ok(ClassSignature {
+            type_parameters: builder.type_parameters,
+            superclass: superclass.expect("class signatures have a superclass"),
+            interfaces,
+        })
+    }
+
+    /// Passes the parts of this signature to a visitor, the same way as
+    /// [`SignatureReader::read_class`].
+    pub fn accept<'a, V>(&'a self, visitor: &mut V)
+    where
+        V: SignatureVisitor<'a>,
+    {
+        accept_type_parameters(&self.type_parameters, visitor);
+        visitor.visit_superclass();
+        self.superclass.accept(visitor);
+        for interface in &self.interfaces {
+            visitor.visit_interface();
+            interface.accept(visitor);
+        }
+    }
+
+    pub fn to_signature(&self) -> JavaString {
+        let mut writer = SignatureWriter::new();
+        self.accept(&mut writer);
+        writer.into_signature()
+    }
+}
+
+impl<'class> MethodSignature<'class> {
+    pub fn parse(signature: &'class JavaStr) -> ClassFileResult<Self> {
+        let mut builder = SignatureBuilder::default();
+        SignatureReader::new(signature).read_method(&mut builder)?;
+        let mut parameter_types = Vec::new();
+        let mut return_type = None;
+        let mut exceptions = Vec::new();
+        for (slot, ty) in builder.types {
+            match slot {
+                Slot::Parameter => parameter_types.push(ty),
+                Slot::Return => return_type = Some(ty),
+                _ => exceptions.push(ty),
+            }
+        }
+        Ok(MethodSignature {
+            type_parameters: builder.type_parameters,
+            parameter_types,
+            return_type: return_type.expect("method signatures have a return type"),
+            exceptions,
+        })
+    }
+
+    /// Passes the parts of this signature to a visitor, the same way as
+    /// [`SignatureReader::read_method`].
+    pub fn accept<'a, V>(&'a self, visitor: &mut V)
+    where
+        V: SignatureVisitor<'a>,
+    {
+        accept_type_parameters(&self.type_parameters, visitor);
+        for parameter_type in &self.parameter_types {
+            visitor.visit_parameter_type();
+            parameter_type.accept(visitor);
+        }
+        visitor.visit_return_type();
+        self.return_type.accept(visitor);
+        for exception in &self.exceptions {
+            visitor.visit_exception_type();
+            exception.accept(visitor);
+        }
+    }
+
+    pub fn to_signature(&self) -> JavaString {
+        let mut writer = SignatureWriter::new();
+        self.accept(&mut writer);
+        writer.into_signature()
+    }
+}
+
+impl<'class> TypeSignature<'class> {
+    /// Parses a field signature, which is also the format of the signatures of record components
+    /// and local variables.
+    pub fn parse(signature: &'class JavaStr) -> ClassFileResult<Self> {
+        let mut builder = SignatureBuilder::default();
+        SignatureReader::new(signature).read_field(&mut builder)?;
+        let (_, ty) = builder.types.pop().expect("field signatures have a type");
+        Ok(ty)
+    }
+
+    /// Passes the parts of this type to a visitor, the same way as
+    /// [`SignatureReader::read_field`].
+    pub fn accept<'a, V>(&'a self, visitor: &mut V)
+    where
+        V: SignatureVisitor<'a>,
+    {
+        match self {
+            TypeSignature::Base(descriptor) => visitor.visit_base_type(*descriptor),
+            TypeSignature::TypeVariable(name) => visitor.visit_type_variable(name),
+            TypeSignature::Array(element_type) => {
+                visitor.visit_array_type();
+                element_type.accept(visitor);
+            }
+            TypeSignature::Class(class_type) => class_type.accept(visitor),
+        }
+    }
+
+    pub fn to_signature(&self) -> JavaString {
+        let mut writer = SignatureWriter::new();
+        self.accept(&mut writer);
+        writer.into_signature()
+    }
+}
+
+impl<'class> ClassTypeSignature<'class> {
+    pub fn new(name: impl Into<Cow<'class, JavaStr>>) -> Self {
+        ClassTypeSignature {
+            name: name.into(),
+            type_arguments: Vec::new(),
+            inner_classes: Vec::new(),
+        }
+    }
+
+    /// The type arguments of the innermost class.
+    pub fn last_type_arguments_mut(&mut self) -> &mut Vec<TypeArgument<'class>> {
+        match self.inner_classes.last_mut() {
+            Some(inner_class) => &mut inner_class.type_arguments,
+            None => &mut self.type_arguments,
+        }
+    }
+
+    pub fn accept<'a, V>(&'a self, visitor: &mut V)
+    where
+        V: SignatureVisitor<'a>,
+    {
+        visitor.visit_class_type(&self.name);
+        accept_type_arguments(&self.type_arguments, visitor);
+        for inner_class in &self.inner_classes {
+            visitor.visit_inner_class_type(&inner_class.name);
+            accept_type_arguments(&inner_class.type_arguments, visitor);
+        }
+        visitor.visit_end();
+    }
+}
+
+fn accept_type_parameters<'a, 'class, V>(
+    type_parameters: &'a [TypeParameter<'class>],
+    visitor: &mut V,
+) where
+    V: SignatureVisitor<'a>,
+{
+    for type_parameter in type_parameters {
+        visitor.visit_formal_type_parameter(&type_parameter.name);
+        if let Some(class_bound) = &type_parameter.class_bound {
+            visitor.visit_class_bound();
+            class_bound.accept(visitor);
+        }
+        for interface_bound in &type_parameter.interface_bounds {
+            visitor.visit_interface_bound();
+            interface_bound.accept(visitor);
+        }
+    }
+}
+
+fn accept_type_arguments<'a, 'class, V>(type_arguments: &'a [TypeArgument<'class>], visitor: &mut V)
+where
+    V: SignatureVisitor<'a>,
+{
+    for type_argument in type_arguments {
+        match type_argument {
+            TypeArgument::Unbounded => visitor.visit_unbounded_type_argument(),
+            TypeArgument::Bounded(kind, ty) => {
+                visitor.visit_type_argument(*kind);
+                ty.accept(visitor);
+            }
+        }
+    }
+}
+
+/// Which part of a signature the next complete top-level type belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+enum Slot {
+    ClassBound,
+    InterfaceBound,
+    Superclass,
+    Interface,
+    Parameter,
+    Return,
+    Exception,
+    #[default]
+    Field,
+}
+
+/// A type which is still being visited.
+enum PartialType<'class> {
+    Array,
+    Class(ClassTypeSignature<'class>),
+    TypeArgument(TypeArgumentKind),
+}
+
+/// Builds the tree of a signature from the calls of a [`SignatureReader`].
+#[derive(Default)]
+struct SignatureBuilder<'class> {
+    type_parameters: Vec<TypeParameter<'class>>,
+    types: Vec<(Slot, TypeSignature<'class>)>,
+    slot: Slot,
+    stack: Vec<PartialType<'class>>,
+}
+
+impl<'class> SignatureBuilder<'class> {
+    fn complete(&mut self, mut ty: TypeSignature<'class>) {
+        loop {
+            match self.stack.last() {
+                Some(PartialType::Array) => {
+                    self.stack.pop();
+                    ty = TypeSignature::Array(Box::new(ty));
+                }
+                Some(PartialType::TypeArgument(kind)) => {
+                    let kind = *kind;
+                    self.stack.pop();
+                    if let Some(PartialType::Class(class_type)) = self.stack.last_mut() {
+                        class_type
+                            .last_type_arguments_mut()
+                            .push(TypeArgument::Bounded(kind, ty));
+                    }
+                    return;
+                }
+                Some(PartialType::Class(_)) => return,
+                None => break,
+            }
+        }
+        match self.slot {
+            Slot::ClassBound => {
+                if let Some(type_parameter) = self.type_parameters.last_mut() {
+                    type_parameter.class_bound = Some(ty);
+                }
+            }
+            Slot::InterfaceBound => {
+                if let Some(type_parameter) = self.type_parameters.last_mut() {
+                    type_parameter.interface_bounds.push(ty);
+                }
+            }
+            slot => self.types.push((slot, ty)),
+        }
+    }
+}
+
+impl<'class> SignatureVisitor<'class> for SignatureBuilder<'class> {
+    fn visit_formal_type_parameter(&mut self, name: &'class JavaStr) {
+        self.type_parameters.push(TypeParameter {
+            name: Cow::Borrowed(name),
+            class_bound: None,
+            interface_bounds: Vec::new(),
+        });
+    }
+
+    fn visit_class_bound(&mut self) {
+        self.slot = Slot::ClassBound;
+    }
+
+    fn visit_interface_bound(&mut self) {
+        self.slot = Slot::InterfaceBound;
+    }
+
+    fn visit_superclass(&mut self) {
+        self.slot = Slot::Superclass;
+    }
+
+    fn visit_interface(&mut self) {
+        self.slot = Slot::Interface;
+    }
+
+    fn visit_parameter_type(&mut self) {
+        self.slot = Slot::Parameter;
+    }
+
+    fn visit_return_type(&mut self) {
+        self.slot = Slot::Return;
+    }
+
+    fn visit_exception_type(&mut self) {
+        self.slot = Slot::Exception;
+    }
+
+    fn visit_base_type(&mut self, descriptor: char) {
+        self.complete(TypeSignature::Base(descriptor));
+    }
+
+    fn visit_type_variable(&mut self, name: &'class JavaStr) {
+        self.complete(TypeSignature::TypeVariable(Cow::Borrowed(name)));
+    }
+
+    fn visit_array_type(&mut self) {
+        self.stack.push(PartialType::Array);
+    }
+
+    fn visit_class_type(&mut self, name: &'class JavaStr) {
+        self.stack
+            .push(PartialType::Class(ClassTypeSignature::new(name)));
+    }
+
+    fn visit_inner_class_type(&mut self, name: &'class JavaStr) {
+        if let Some(PartialType::Class(class_type)) = self.stack.last_mut() {
+            class_type.inner_classes.push(InnerClassTypeSignature {
+                name: Cow::Borrowed(name),
+                type_arguments: Vec::new(),
+            });
+        }
+    }
+
+    fn visit_unbounded_type_argument(&mut self) {
+        if let Some(PartialType::Class(class_type)) = self.stack.last_mut() {
+            class_type
+                .last_type_arguments_mut()
+                .push(TypeArgument::Unbounded);
+        }
+    }
+
+    fn visit_type_argument(&mut self, kind: TypeArgumentKind) {
+        self.stack.push(PartialType::TypeArgument(kind));
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(PartialType::Class(class_type)) = self.stack.pop() {
+            self.complete(TypeSignature::Class(class_type));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{
+        ClassSignature, ClassTypeSignature, MethodSignature, TypeArgument, TypeSignature,
+    };
+    use crate::TypeArgumentKind;
+    use java_string::JavaStr;
+
+    #[test]
+    fn test_parse_signature() {
+        let signature = ClassSignature::parse(JavaStr::from_str(
+            "<K:Ljava/lang/Object;V::Ljava/lang/Comparable<-TV;>;>\
+             Ljava/util/Map<TK;TV;>.Entry<*[I>;",
+        ))
+        .unwrap();
+        assert_eq!(2, signature.type_parameters.len());
+        assert_eq!("V", signature.type_parameters[1].name.as_ref());
+        assert_eq!(None, signature.type_parameters[1].class_bound);
+        let mut comparable = ClassTypeSignature::new(JavaStr::from_str("java/lang/Comparable"));
+        comparable.type_arguments.push(TypeArgument::Bounded(
+            TypeArgumentKind::Super,
+            TypeSignature::TypeVariable(JavaStr::from_str("V").into()),
+        ));
+        assert_eq!(
+            vec![TypeSignature::Class(comparable)],
+            signature.type_parameters[1].interface_bounds
+        );
+        let superclass = &signature.superclass;
+        assert_eq!("java/util/Map", superclass.name.as_ref());
+        assert_eq!(2, superclass.type_arguments.len());
+        assert_eq!("Entry", superclass.inner_classes[0].name.as_ref());
+        assert_eq!(
+            vec![
+                TypeArgument::Unbounded,
+                TypeArgument::Bounded(
+                    TypeArgumentKind::Exact,
+                    TypeSignature::Array(Box::new(TypeSignature::Base('I')))
+                ),
+            ],
+            superclass.inner_classes[0].type_arguments
+        );
+        assert!(signature.interfaces.is_empty());
+
+        let signature =
+            MethodSignature::parse(JavaStr::from_str("<E:Ljava/lang/Exception;>(TE;J)V^TE;"))
+                .unwrap();
+        assert_eq!(2, signature.parameter_types.len());
+        assert_eq!(TypeSignature::Base('V'), signature.return_type);
+        assert_eq!(
+            vec![TypeSignature::TypeVariable(JavaStr::from_str("E").into())],
+            signature.exceptions
+        );
+    }
+
+    #[test]
+    fn test_signature_round_trip() {
+        let class_signature = JavaStr::from_str(
+            "<T:Ljava/lang/Object;U::Ljava/lang/Comparable<-TT;>;>La<TT;>.b<*+[I>;Lc;",
+        );
+        assert_eq!(
+            class_signature,
+            ClassSignature::parse(class_signature)
+                .unwrap()
+                .to_signature()
+        );
+        let method_signature = JavaStr::from_str(
+            "<E:Ljava/lang/Exception;>([TE;J)La<Ljava/lang/String;>.b.c<TT;>;^TE;",
+        );
+        assert_eq!(
+            method_signature,
+            MethodSignature::parse(method_signature)
+                .unwrap()
+                .to_signature()
+        );
+        let field_signature = JavaStr::from_str("[[Ljava/util/Map<*Ljava/util/List<-TT;>;>;");
+        assert_eq!(
+            field_signature,
+            TypeSignature::parse(field_signature)
+                .unwrap()
+                .to_signature()
+        );
+    }
+}
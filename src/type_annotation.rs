@@ -39,6 +39,7 @@ pub(crate) enum TypeReferenceTargetType {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 #[non_exhaustive]
 pub enum TypeReference {
@@ -66,6 +67,51 @@ pub enum TypeReference {
     MethodReferenceTypeArgument { arg_index: u8 } = 0x4B,
 }
 
+impl TypeReference {
+    pub(crate) fn target_type(&self) -> TypeReferenceTargetType {
+        match self {
+            TypeReference::ClassTypeParameter { .. } => TypeReferenceTargetType::ClassTypeParameter,
+            TypeReference::MethodTypeParameter { .. } => {
+                TypeReferenceTargetType::MethodTypeParameter
+            }
+            TypeReference::ClassExtends { .. } => TypeReferenceTargetType::ClassExtends,
+            TypeReference::ClassTypeParameterBound { .. } => {
+                TypeReferenceTargetType::ClassTypeParameterBound
+            }
+            TypeReference::MethodTypeParameterBound { .. } => {
+                TypeReferenceTargetType::MethodTypeParameterBound
+            }
+            TypeReference::Field => TypeReferenceTargetType::Field,
+            TypeReference::MethodReturn => TypeReferenceTargetType::MethodReturn,
+            TypeReference::MethodReceiver => TypeReferenceTargetType::MethodReceiver,
+            TypeReference::MethodFormalParameter { .. } => {
+                TypeReferenceTargetType::MethodFormalParameter
+            }
+            TypeReference::Throws { .. } => TypeReferenceTargetType::Throws,
+            TypeReference::LocalVariable => TypeReferenceTargetType::LocalVariable,
+            TypeReference::ResourceVariable => TypeReferenceTargetType::ResourceVariable,
+            TypeReference::ExceptionParameter => TypeReferenceTargetType::ExceptionParameter,
+            TypeReference::Instanceof => TypeReferenceTargetType::Instanceof,
+            TypeReference::New => TypeReferenceTargetType::New,
+            TypeReference::ConstructorReference => TypeReferenceTargetType::ConstructorReference,
+            TypeReference::MethodReference => TypeReferenceTargetType::MethodReference,
+            TypeReference::Cast { .. } => TypeReferenceTargetType::Cast,
+            TypeReference::ConstructorInvocationTypeArgument { .. } => {
+                TypeReferenceTargetType::ConstructorInvocationTypeArgument
+            }
+            TypeReference::MethodInvocationTypeArgument { .. } => {
+                TypeReferenceTargetType::MethodInvocationTypeArgument
+            }
+            TypeReference::ConstructorReferenceTypeArgument { .. } => {
+                TypeReferenceTargetType::ConstructorReferenceTypeArgument
+            }
+            TypeReference::MethodReferenceTypeArgument { .. } => {
+                TypeReferenceTargetType::MethodReferenceTypeArgument
+            }
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialOrd, Default)]
 pub struct TypePath<'class> {
     // Invariant: path len must always be a multiple of 2
@@ -87,6 +133,10 @@ impl<'class> TypePath<'class> {
         TypePath { path: bytes.into() }
     }
 
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.path
+    }
+
     pub fn len(&self) -> usize {
         self.path.len() / 2
     }
@@ -342,6 +392,23 @@ pub enum ParseTypePathErrorKind {
     ExpectedNumberTerminator,
 }
 
+/// Serialized in its string form, see [`FromStr`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for TypePath<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TypePath<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 impl<'path, 'class> IntoIterator for &'path TypePath<'class> {
     type Item = Result<TypePathElement, TypePathError>;
     type IntoIter = TypePathIterator<'path, 'class>;
@@ -0,0 +1,350 @@
+use crate::validation::{field_desc_len, is_valid_internal_name, is_valid_method_desc};
+use crate::{ClassFileError, ClassFileResult};
+use derive_more::Display;
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeSort {
+    Void,
+    Boolean,
+    Char,
+    Byte,
+    Short,
+    Int,
+    Float,
+    Long,
+    Double,
+    Array,
+    Object,
+}
+
+/// A Java type, backed by its field descriptor, or `V` for `void`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[display("{desc}")]
+pub struct Type<'class> {
+    desc: Cow<'class, JavaStr>,
+}
+
+impl<'class> Type<'class> {
+    pub const VOID: Type<'static> = Type::primitive("V");
+    pub const BOOLEAN: Type<'static> = Type::primitive("Z");
+    pub const CHAR: Type<'static> = Type::primitive("C");
+    pub const BYTE: Type<'static> = Type::primitive("B");
+    pub const SHORT: Type<'static> = Type::primitive("S");
+    pub const INT: Type<'static> = Type::primitive("I");
+    pub const FLOAT: Type<'static> = Type::primitive("F");
+    pub const LONG: Type<'static> = Type::primitive("J");
+    pub const DOUBLE: Type<'static> = Type::primitive("D");
+
+    const fn primitive(desc: &'static str) -> Type<'static> {
+        Type {
+            desc: Cow::Borrowed(JavaStr::from_str(desc)),
+        }
+    }
+
+    /// Parses a field descriptor, or `V`.
+    pub fn from_desc(desc: impl Into<Cow<'class, JavaStr>>) -> ClassFileResult<Self> {
+        let desc = desc.into();
+        if desc.as_ref() != "V" && field_desc_len(desc.as_bytes()) != Some(desc.len()) {
+            return Err(ClassFileError::BadDescriptor(desc.into_owned()));
+        }
+        Ok(Type { desc })
+    }
+
+    /// Parses an internal name, which is an array descriptor for array types.
+    pub fn from_internal_name(name: impl Into<Cow<'class, JavaStr>>) -> ClassFileResult<Self> {
+        let name = name.into();
+        if name.starts_with('[') {
+            return Type::from_desc(name);
+        }
+        if !is_valid_internal_name(name.as_bytes()) {
+            return Err(ClassFileError::BadDescriptor(name.into_owned()));
+        }
+        Ok(Type::object(name))
+    }
+
+    /// The type of instances of the class with the given internal name, which isn't validated.
+    pub fn object(internal_name: impl AsRef<JavaStr>) -> Type<'static> {
+        let internal_name = internal_name.as_ref();
+        let mut desc = JavaString::with_capacity(internal_name.len() + 2);
+        desc.push('L');
+        desc.push_java_str(internal_name);
+        desc.push(';');
+        Type {
+            desc: Cow::Owned(desc),
+        }
+    }
+
+    /// The array type with the given number of dimensions added to the given type.
+    pub fn array_of(element_type: &Type, dimensions: usize) -> Type<'static> {
+        let mut desc = JavaString::with_capacity(dimensions + element_type.desc.len());
+        for _ in 0..dimensions {
+            desc.push('[');
+        }
+        desc.push_java_str(&element_type.desc);
+        Type {
+            desc: Cow::Owned(desc),
+        }
+    }
+
+    /// Builds the descriptor of a method with the given return and argument types.
+    pub fn method_descriptor(return_type: &Type, argument_types: &[Type]) -> JavaString {
+        let mut desc = JavaString::with_capacity(
+            argument_types.iter().map(|ty| ty.desc.len()).sum::<usize>()
+                + return_type.desc.len()
+                + 2,
+        );
+        desc.push('(');
+        for argument_type in argument_types {
+            desc.push_java_str(&argument_type.desc);
+        }
+        desc.push(')');
+        desc.push_java_str(&return_type.desc);
+        desc
+    }
+
+    pub fn sort(&self) -> TypeSort {
+        match self.desc.as_bytes()[0] {
+            b'V' => TypeSort::Void,
+            b'Z' => TypeSort::Boolean,
+            b'C' => TypeSort::Char,
+            b'B' => TypeSort::Byte,
+            b'S' => TypeSort::Short,
+            b'I' => TypeSort::Int,
+            b'F' => TypeSort::Float,
+            b'J' => TypeSort::Long,
+            b'D' => TypeSort::Double,
+            b'[' => TypeSort::Array,
+            _ => TypeSort::Object,
+        }
+    }
+
+    pub fn is_primitive(&self) -> bool {
+        !matches!(self.sort(), TypeSort::Array | TypeSort::Object)
+    }
+
+    /// The number of array dimensions, which is 0 for types other than arrays.
+    pub fn dimensions(&self) -> usize {
+        self.desc.bytes().take_while(|&b| b == b'[').count()
+    }
+
+    /// The type with all array dimensions removed, which is this type for types other than arrays.
+    pub fn element_type(&self) -> Type<'_> {
+        Type {
+            desc: Cow::Borrowed(&self.desc[self.dimensions()..]),
+        }
+    }
+
+    /// The internal name of object types, or the descriptor of array types. Primitive types have no
+    /// internal name.
+    pub fn internal_name(&self) -> Option<&JavaStr> {
+        match self.sort() {
+            TypeSort::Object => Some(&self.desc[1..self.desc.len() - 1]),
+            TypeSort::Array => Some(&self.desc),
+            _ => None,
+        }
+    }
+
+    pub fn descriptor(&self) -> &JavaStr {
+        &self.desc
+    }
+
+    /// The number of local variable or operand stack slots taken up by a value of this type.
+    pub fn size(&self) -> usize {
+        match self.sort() {
+            TypeSort::Void => 0,
+            TypeSort::Long | TypeSort::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn into_owned(self) -> Type<'static> {
+        Type {
+            desc: Cow::Owned(self.desc.into_owned()),
+        }
+    }
+}
+
+impl<'class> From<Type<'class>> for Cow<'class, JavaStr> {
+    fn from(ty: Type<'class>) -> Self {
+        ty.desc
+    }
+}
+
+/// A method descriptor, such as `(ILjava/lang/String;)V`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[display("{desc}")]
+pub struct MethodType<'class> {
+    desc: Cow<'class, JavaStr>,
+}
+
+impl<'class> MethodType<'class> {
+    pub fn new(return_type: &Type, argument_types: &[Type]) -> MethodType<'static> {
+        MethodType {
+            desc: Cow::Owned(Type::method_descriptor(return_type, argument_types)),
+        }
+    }
+
+    pub fn from_desc(desc: impl Into<Cow<'class, JavaStr>>) -> ClassFileResult<Self> {
+        let desc = desc.into();
+        if !is_valid_method_desc(desc.as_bytes()) {
+            return Err(ClassFileError::BadDescriptor(desc.into_owned()));
+        }
+        Ok(MethodType { desc })
+    }
+
+    pub fn argument_types(&self) -> Vec<Type<'_>> {
+        let mut argument_types = Vec::new();
+        let mut start = 1;
+        while let Some(len) = field_desc_len(&self.desc.as_bytes()[start..]) {
+            argument_types.push(Type {
+                desc: Cow::Borrowed(&self.desc[start..start + len]),
+            });
+            start += len;
+        }
+        argument_types
+    }
+
+    pub fn argument_count(&self) -> usize {
+        self.argument_types().len()
+    }
+
+    /// The number of local variable slots taken up by the arguments, not including `this`.
+    pub fn argument_slots(&self) -> usize {
+        self.argument_types().iter().map(Type::size).sum()
+    }
+
+    pub fn return_type(&self) -> Type<'_> {
+        let start = self.desc.rfind(')').map_or(0, |index| index + 1);
+        Type {
+            desc: Cow::Borrowed(&self.desc[start..]),
+        }
+    }
+
+    pub fn descriptor(&self) -> &JavaStr {
+        &self.desc
+    }
+
+    pub fn into_owned(self) -> MethodType<'static> {
+        MethodType {
+            desc: Cow::Owned(self.desc.into_owned()),
+        }
+    }
+}
+
+impl<'class> From<MethodType<'class>> for Cow<'class, JavaStr> {
+    fn from(method_type: MethodType<'class>) -> Self {
+        method_type.desc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{MethodType, Type, TypeSort};
+    use java_string::JavaStr;
+
+    #[test]
+    fn test_type() {
+        let string = Type::object("java/lang/String");
+        assert_eq!("Ljava/lang/String;", string.descriptor());
+        assert_eq!(TypeSort::Object, string.sort());
+        assert_eq!(
+            Some(JavaStr::from_str("java/lang/String")),
+            string.internal_name()
+        );
+        assert_eq!(1, string.size());
+
+        let array = Type::array_of(&string, 2);
+        assert_eq!("[[Ljava/lang/String;", array.descriptor());
+        assert_eq!(TypeSort::Array, array.sort());
+        assert_eq!(2, array.dimensions());
+        assert_eq!(string, array.element_type());
+        assert_eq!(
+            Some(JavaStr::from_str("[[Ljava/lang/String;")),
+            array.internal_name()
+        );
+
+        let long = Type::from_desc(JavaStr::from_str("J")).unwrap();
+        assert_eq!(Type::LONG, long);
+        assert!(long.is_primitive());
+        assert_eq!(None, long.internal_name());
+        assert_eq!(2, long.size());
+        assert_eq!(0, Type::VOID.size());
+
+        assert_eq!(
+            string,
+            Type::from_internal_name(JavaStr::from_str("java/lang/String")).unwrap()
+        );
+        assert_eq!(
+            Type::array_of(&Type::INT, 1),
+            Type::from_internal_name(JavaStr::from_str("[I")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_type() {
+        for desc in ["", "Ljava/lang/String", "[V", "II", "(I)V", "Q"] {
+            assert!(Type::from_desc(JavaStr::from_str(desc)).is_err(), "{desc}");
+        }
+        assert!(Type::from_internal_name(JavaStr::from_str("java/lang/String;")).is_err());
+    }
+
+    #[test]
+    fn test_method_type() {
+        let method_type = MethodType::from_desc(JavaStr::from_str(
+            "(IJ[Ljava/lang/String;D)Ljava/lang/Object;",
+        ))
+        .unwrap();
+        assert_eq!(
+            vec![
+                Type::INT,
+                Type::LONG,
+                Type::array_of(&Type::object("java/lang/String"), 1),
+                Type::DOUBLE,
+            ],
+            method_type.argument_types()
+        );
+        assert_eq!(4, method_type.argument_count());
+        assert_eq!(6, method_type.argument_slots());
+        assert_eq!(Type::object("java/lang/Object"), method_type.return_type());
+
+        let method_type = MethodType::from_desc(JavaStr::from_str("()V")).unwrap();
+        assert!(method_type.argument_types().is_empty());
+        assert_eq!(Type::VOID, method_type.return_type());
+
+        for desc in [
+            "",
+            "I",
+            "(I",
+            "(V)V",
+            "(I)",
+            "(I)VV",
+            "(Ljava/lang/String)V",
+        ] {
+            assert!(
+                MethodType::from_desc(JavaStr::from_str(desc)).is_err(),
+                "{desc}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_method_type() {
+        let string = Type::object("java/lang/String");
+        let method_type = MethodType::new(&Type::VOID, &[Type::INT, Type::array_of(&string, 1)]);
+        assert_eq!("(I[Ljava/lang/String;)V", method_type.descriptor());
+        assert_eq!("(I[Ljava/lang/String;)V", method_type.to_string());
+        assert_eq!(
+            Type::method_descriptor(&string, &[]),
+            "()Ljava/lang/String;"
+        );
+        assert_eq!(
+            method_type,
+            MethodType::from_desc(method_type.descriptor()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,606 @@
+use crate::tree::{AnnotationNode, AnnotationValue};
+use crate::{
+    BootstrapMethodArgument, ClassEvent, ClassEventSource, ClassFileError, ClassFileResult,
+    ClassReader, ClassReaderFlags, ConstantDynamic, FieldEvent, Handle, LdcConstant, MethodEvent,
+    MethodEventProviders,
+};
+use java_string::{JavaStr, JavaString};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// Finds the classes, members and instructions which use any of a set of types, such as
+/// annotations, across many classes at once.
+///
+/// Classes are read without debug info or stack map frames, and are scanned on several threads.
+#[derive(Debug, Clone)]
+pub struct UsageScanner {
+    types: HashSet<JavaString>,
+    threads: usize,
+}
+
+impl Default for UsageScanner {
+    fn default() -> Self {
+        UsageScanner {
+            types: HashSet::new(),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+}
+
+/// A use of one of the scanned types.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Usage {
+    /// The internal name of the class containing the use.
+    pub class: JavaString,
+    pub location: UsageLocation,
+    pub kind: UsageKind,
+    /// The internal name of the type that is used.
+    pub ty: JavaString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UsageLocation {
+    Class,
+    Field {
+        name: JavaString,
+        desc: JavaString,
+    },
+    Method {
+        name: JavaString,
+        desc: JavaString,
+    },
+    /// An instruction of a method, by its index among the instructions of the method.
+    Insn {
+        method_name: JavaString,
+        method_desc: JavaString,
+        index: u32,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UsageKind {
+    /// The superclass or a superinterface of the class.
+    Supertype,
+    Annotation,
+    TypeAnnotation,
+    /// The descriptor of the field or method.
+    Descriptor,
+    /// An instruction operand, or the exception type of a try-catch block.
+    Reference,
+}
+
+impl UsageScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a type to scan for, either as an internal name such as `java/lang/Deprecated`, or as
+    /// a descriptor such as `Ljava/lang/Deprecated;`.
+    pub fn add_type(&mut self, ty: impl Into<JavaString>) {
+        let ty = ty.into();
+        let ty = match ty.strip_prefix('L').and_then(|ty| ty.strip_suffix(';')) {
+            Some(internal_name) => internal_name.to_owned(),
+            None => ty,
+        };
+        self.types.insert(ty);
+    }
+
+    /// Sets the maximum number of threads used by [`scan_classes`](Self::scan_classes). Defaults
+    /// to the available parallelism.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn scan_class(&self, bytecode: &[u8]) -> ClassFileResult<Vec<Usage>> {
+        let reader = ClassReader::new(
+            bytecode,
+            ClassReaderFlags::SkipDebug | ClassReaderFlags::SkipFrames,
+        )?;
+        let mut collector = UsageCollector {
+            types: &self.types,
+            class: reader.name()?.into_owned(),
+            usages: Vec::new(),
+        };
+        collector.scan(&reader)?;
+        Ok(collector.usages)
+    }
+
+    /// Scans many classes in parallel. The usages are returned in the order of the classes.
+    pub fn scan_classes<'a>(
+        &self,
+        classes: impl IntoIterator<Item = &'a [u8]>,
+    ) -> ClassFileResult<Vec<Usage>> {
+        let usages = scan_in_parallel(classes, self.threads, |bytecode| self.scan_class(bytecode))?;
+        Ok(usages.into_iter().flatten().collect())
+    }
+
+    /// Scans the class files in the given classpath entries, which are either class files,
+    /// directories searched recursively, or jar files with the `jar` feature.
+    pub fn scan_classpath<P>(
+        &self,
+        classpath: impl IntoIterator<Item = P>,
+    ) -> ClassFileResult<Vec<Usage>>
+    where
+        P: AsRef<Path>,
+    {
+        let classes = read_classpath(classpath)?;
+        self.scan_classes(classes.iter().map(Vec::as_slice))
+    }
+}
+
+/// Scans classes on up to the given number of threads, and returns the results in the order of the
+/// classes.
+pub(crate) fn scan_in_parallel<'a, T, F>(
+    classes: impl IntoIterator<Item = &'a [u8]>,
+    threads: usize,
+    scan: F,
+) -> ClassFileResult<Vec<T>>
+where
+    T: Send,
+    F: Fn(&[u8]) -> ClassFileResult<T> + Sync,
+{
+    let classes: Vec<&[u8]> = classes.into_iter().collect();
+    if classes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let chunk_size = classes.len().div_ceil(threads.max(1));
+    let scan = &scan;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = classes
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|bytecode| scan(bytecode)).collect()))
+            .collect();
+        let mut results = Vec::with_capacity(classes.len());
+        for handle in handles {
+            let chunk_results: ClassFileResult<Vec<T>> = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            results.extend(chunk_results?);
+        }
+        Ok(results)
+    })
+}
+
+/// Reads the class files in the given classpath entries, which are either class files,
+/// directories searched recursively, or jar files with the `jar` feature.
+pub(crate) fn read_classpath<P>(
+    classpath: impl IntoIterator<Item = P>,
+) -> ClassFileResult<Vec<Vec<u8>>>
+where
+    P: AsRef<Path>,
+{
+    let mut classes = Vec::new();
+    for entry in classpath {
+        let entry = entry.as_ref();
+        if entry.is_dir() {
+            let mut files = Vec::new();
+            find_class_files(entry, &mut files)?;
+            for file in files {
+                classes.push(std::fs::read(file)?);
+            }
+        } else if is_class_file(entry) {
+            classes.push(std::fs::read(entry)?);
+        } else {
+            #[cfg(feature = "jar")]
+            if entry
+                .extension()
+                .is_some_and(|extension| extension == "jar")
+            {
+                classes.extend(crate::jar::read_jar_classes(entry)?);
+                continue;
+            }
+            return Err(ClassFileError::Io {
+                kind: std::io::ErrorKind::Unsupported,
+                message: format!("unsupported classpath entry: {}", entry.display()),
+            });
+        }
+    }
+    Ok(classes)
+}
+
+fn is_class_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "class")
+}
+
+fn find_class_files(dir: &Path, files: &mut Vec<PathBuf>) -> ClassFileResult<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_class_files(&entry, files)?;
+        } else if is_class_file(&entry) {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+struct UsageCollector<'a> {
+    types: &'a HashSet<JavaString>,
+    class: JavaString,
+    usages: Vec<Usage>,
+}
+
+impl UsageCollector<'_> {
+    fn scan<'class>(&mut self, source: impl ClassEventSource<'class>) -> ClassFileResult<()> {
+        for event in source.events()? {
+            match event? {
+                ClassEvent::Class(class) => {
+                    for super_name in class.super_name.iter().chain(&class.interfaces) {
+                        self.check_name(super_name, &UsageLocation::Class, UsageKind::Supertype);
+                    }
+                }
+                ClassEvent::Annotations(annotations) => {
+                    for annotation in annotations {
+                        self.check_annotation(
+                            &annotation?.annotation,
+                            &UsageLocation::Class,
+                            UsageKind::Annotation,
+                        );
+                    }
+                }
+                ClassEvent::TypeAnnotations(annotations) => {
+                    for annotation in annotations {
+                        let annotation = annotation?.annotation;
+                        self.check_desc(
+                            &annotation.desc,
+                            &UsageLocation::Class,
+                            UsageKind::TypeAnnotation,
+                        );
+                    }
+                }
+                ClassEvent::Fields(fields) => {
+                    for field in fields {
+                        let field = field?;
+                        let location = UsageLocation::Field {
+                            name: field.name.into_owned(),
+                            desc: field.desc.clone().into_owned(),
+                        };
+                        self.check_desc(&field.desc, &location, UsageKind::Descriptor);
+                        for event in field.events {
+                            match event? {
+                                FieldEvent::Annotations(annotations) => {
+                                    for annotation in annotations {
+                                        self.check_annotation(
+                                            &annotation?.annotation,
+                                            &location,
+                                            UsageKind::Annotation,
+                                        );
+                                    }
+                                }
+                                FieldEvent::TypeAnnotations(annotations) => {
+                                    for annotation in annotations {
+                                        self.check_desc(
+                                            &annotation?.annotation.desc,
+                                            &location,
+                                            UsageKind::TypeAnnotation,
+                                        );
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                ClassEvent::Methods(methods) => {
+                    for method in methods {
+                        let method = method?;
+                        self.scan_method(
+                            method.name.into_owned(),
+                            method.desc.into_owned(),
+                            method.events,
+                        )?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_method<'class, P>(
+        &mut self,
+        name: JavaString,
+        desc: JavaString,
+        events: impl IntoIterator<Item = ClassFileResult<MethodEvent<'class, P>>>,
+    ) -> ClassFileResult<()>
+    where
+        P: MethodEventProviders<'class>,
+    {
+        let location = UsageLocation::Method {
+            name: name.clone(),
+            desc: desc.clone(),
+        };
+        self.check_desc(&desc, &location, UsageKind::Descriptor);
+        let mut insn_count = 0u32;
+        let insn_location = |index| UsageLocation::Insn {
+            method_name: name.clone(),
+            method_desc: desc.clone(),
+            index,
+        };
+
+        for event in events {
+            let event = event?;
+            let is_insn = matches!(
+                event,
+                MethodEvent::Insn(_)
+                    | MethodEvent::BIPushInsn(_)
+                    | MethodEvent::SIPushInsn(_)
+                    | MethodEvent::NewArrayInsn(_)
+                    | MethodEvent::VarInsn { .. }
+                    | MethodEvent::TypeInsn { .. }
+                    | MethodEvent::FieldInsn { .. }
+                    | MethodEvent::MethodInsn { .. }
+                    | MethodEvent::InvokeDynamicInsn { .. }
+                    | MethodEvent::JumpInsn { .. }
+                    | MethodEvent::LdcInsn(_)
+                    | MethodEvent::IIncInsn { .. }
+                    | MethodEvent::TableSwitchInsn { .. }
+                    | MethodEvent::LookupSwitchInsn { .. }
+                    | MethodEvent::MultiANewArrayInsn { .. }
+            );
+            if is_insn {
+                insn_count += 1;
+            }
+            let kind = UsageKind::Reference;
+            match event {
+                MethodEvent::Annotations(annotations) => {
+                    for annotation in annotations {
+                        self.check_annotation(
+                            &annotation?.annotation,
+                            &location,
+                            UsageKind::Annotation,
+                        );
+                    }
+                }
+                MethodEvent::ParameterAnnotations(annotations) => {
+                    for annotation in annotations {
+                        self.check_annotation(
+                            &annotation?.annotation,
+                            &location,
+                            UsageKind::Annotation,
+                        );
+                    }
+                }
+                MethodEvent::TypeAnnotations(annotations) => {
+                    for annotation in annotations {
+                        self.check_desc(
+                            &annotation?.annotation.desc,
+                            &location,
+                            UsageKind::TypeAnnotation,
+                        );
+                    }
+                }
+                MethodEvent::LocalVariableAnnotations(annotations) => {
+                    for annotation in annotations {
+                        self.check_desc(
+                            &annotation?.annotation.desc,
+                            &location,
+                            UsageKind::TypeAnnotation,
+                        );
+                    }
+                }
+                MethodEvent::TryCatchBlockAnnotations(annotations) => {
+                    for annotation in annotations {
+                        self.check_desc(
+                            &annotation?.annotation.desc,
+                            &location,
+                            UsageKind::TypeAnnotation,
+                        );
+                    }
+                }
+                MethodEvent::InsnAnnotations(annotations) => {
+                    // instruction annotations belong to the previous instruction
+                    let location = insn_location(insn_count.saturating_sub(1));
+                    for annotation in annotations {
+                        self.check_desc(
+                            &annotation?.annotation.desc,
+                            &location,
+                            UsageKind::TypeAnnotation,
+                        );
+                    }
+                }
+                MethodEvent::TryCatchBlocks(try_catch_blocks) => {
+                    for try_catch_block in try_catch_blocks {
+                        if let Some(ty) = try_catch_block?.ty {
+                            self.check_name(&ty, &location, kind);
+                        }
+                    }
+                }
+                MethodEvent::TypeInsn { ty, .. } => {
+                    self.check_name(&ty, &insn_location(insn_count - 1), kind);
+                }
+                MethodEvent::FieldInsn { owner, desc, .. }
+                | MethodEvent::MethodInsn { owner, desc, .. } => {
+                    let location = insn_location(insn_count - 1);
+                    self.check_name(&owner, &location, kind);
+                    self.check_desc(&desc, &location, kind);
+                }
+                MethodEvent::MultiANewArrayInsn { desc, .. } => {
+                    self.check_desc(&desc, &insn_location(insn_count - 1), kind);
+                }
+                MethodEvent::InvokeDynamicInsn {
+                    desc,
+                    bootstrap_method_handle,
+                    bootstrap_method_arguments,
+                    ..
+                } => {
+                    let location = insn_location(insn_count - 1);
+                    self.check_desc(&desc, &location, kind);
+                    self.check_handle(&bootstrap_method_handle, &location);
+                    self.check_arguments(&bootstrap_method_arguments, &location);
+                }
+                MethodEvent::LdcInsn(constant) => {
+                    let location = insn_location(insn_count - 1);
+                    match constant {
+                        LdcConstant::Class(ty) => self.check_name(&ty, &location, kind),
+                        LdcConstant::MethodType(desc) => self.check_desc(&desc, &location, kind),
+                        LdcConstant::Handle(handle) => self.check_handle(&handle, &location),
+                        LdcConstant::ConstantDynamic(constant) => {
+                            self.check_constant_dynamic(&constant, &location)
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn check_handle(&mut self, handle: &Handle, location: &UsageLocation) {
+        self.check_name(&handle.owner, location, UsageKind::Reference);
+        self.check_desc(&handle.desc, location, UsageKind::Reference);
+    }
+
+    fn check_arguments(&mut self, arguments: &[BootstrapMethodArgument], location: &UsageLocation) {
+        for argument in arguments {
+            match argument {
+                BootstrapMethodArgument::Class(ty) => {
+                    self.check_name(ty, location, UsageKind::Reference)
+                }
+                BootstrapMethodArgument::Handle(handle) => self.check_handle(handle, location),
+                BootstrapMethodArgument::ConstantDynamic(constant) => {
+                    self.check_constant_dynamic(constant, location)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_constant_dynamic(&mut self, constant: &ConstantDynamic, location: &UsageLocation) {
+        self.check_desc(&constant.desc, location, UsageKind::Reference);
+        self.check_handle(&constant.bootstrap_method, location);
+        self.check_arguments(&constant.bootstrap_method_arguments, location);
+    }
+
+    /// Checks an annotation, and the classes, enums and annotations in its values.
+    fn check_annotation(
+        &mut self,
+        annotation: &AnnotationNode,
+        location: &UsageLocation,
+        kind: UsageKind,
+    ) {
+        self.check_desc(&annotation.desc, location, kind);
+        for (_, value) in &annotation.values {
+            self.check_annotation_value(value, location, kind);
+        }
+    }
+
+    fn check_annotation_value(
+        &mut self,
+        value: &AnnotationValue,
+        location: &UsageLocation,
+        kind: UsageKind,
+    ) {
+        match value {
+            AnnotationValue::Class(desc) | AnnotationValue::Enum { desc, .. } => {
+                self.check_desc(desc, location, kind)
+            }
+            AnnotationValue::Annotation(annotation) => {
+                self.check_annotation(annotation, location, kind)
+            }
+            AnnotationValue::Array(values) => {
+                for value in values {
+                    self.check_annotation_value(value, location, kind);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks an internal name, or an array descriptor.
+    fn check_name(&mut self, name: &JavaStr, location: &UsageLocation, kind: UsageKind) {
+        if name.starts_with('[') {
+            self.check_desc(name, location, kind);
+        } else if self.types.contains(name) {
+            self.add_usage(name, location, kind);
+        }
+    }
+
+    /// Checks every class in a field or method descriptor.
+    fn check_desc(&mut self, desc: &JavaStr, location: &UsageLocation, kind: UsageKind) {
+        let mut rest = desc;
+        while let Some((before, after)) = rest.split_once(';') {
+            if let Some(start) = before.find('L') {
+                let name = &before[start + 1..];
+                if self.types.contains(name) {
+                    self.add_usage(name, location, kind);
+                }
+            }
+            rest = after;
+        }
+    }
+
+    fn add_usage(&mut self, ty: &JavaStr, location: &UsageLocation, kind: UsageKind) {
+        self.usages.push(Usage {
+            class: self.class.clone(),
+            location: location.clone(),
+            kind,
+            ty: ty.to_owned(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Usage, UsageKind, UsageLocation, UsageScanner};
+    use java_string::JavaString;
+    use test_helpers::include_class;
+
+    #[test]
+    fn test_scan_usages() {
+        let mut scanner = UsageScanner::new();
+        scanner.add_type("LVisibleAnnotation;");
+        scanner.add_type("java/lang/Deprecated");
+        scanner.add_type("java/lang/Runnable");
+        scanner.add_type("java/io/PrintStream");
+        scanner.set_threads(2);
+        let classes: [&[u8]; 3] = [
+            include_class!("TestAnnotations"),
+            include_class!("HelloWorld"),
+            include_class!("TestCode"),
+        ];
+        let usages = scanner.scan_classes(classes).unwrap();
+
+        let class_usage = |class: &str, kind, ty: &str| Usage {
+            class: JavaString::from(class),
+            location: UsageLocation::Class,
+            kind,
+            ty: JavaString::from(ty),
+        };
+        assert!(usages.contains(&class_usage(
+            "TestAnnotations",
+            UsageKind::Annotation,
+            "VisibleAnnotation"
+        )));
+        assert!(usages.contains(&class_usage(
+            "TestAnnotations",
+            UsageKind::Annotation,
+            "java/lang/Deprecated"
+        )));
+        assert!(usages.contains(&class_usage(
+            "TestAnnotations",
+            UsageKind::Supertype,
+            "java/lang/Runnable"
+        )));
+
+        let print_usages: Vec<_> = usages
+            .iter()
+            .filter(|usage| usage.ty == "java/io/PrintStream")
+            .collect();
+        assert!(!print_usages.is_empty());
+        for usage in print_usages {
+            assert_eq!(UsageKind::Reference, usage.kind);
+            assert!(matches!(usage.location, UsageLocation::Insn { .. }));
+        }
+        assert!(usages.iter().any(|usage| usage.class == "TestCode"
+            && usage.ty == "java/lang/Runnable"
+            && matches!(usage.location, UsageLocation::Insn { .. })));
+    }
+}
@@ -0,0 +1,1340 @@
+use crate::class_versions::find_constructs;
+use crate::maxs_calculator::{insn_flow, local_use, InsnFlowKind};
+use crate::{
+    buffer_class_events, BufferedClassEvents, BufferedEventProviders, ClassAccess, ClassEvent,
+    ClassEventSource, ClassFileError, ClassFileResult, ClassModuleEvent, ClassVersionInfo,
+    FieldAccess, Label, LdcConstant, MethodAccess, MethodEvent, ModuleAccess, ModuleEvent,
+    ModuleEventProviders, ModuleProvidesEvent, ModuleRelationEvent, Opcode, JAVA_8_VERSION,
+};
+use java_string::{JavaStr, JavaString};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashSet};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ModuleValidationError<'class> {
+    #[error("duplicate exports of package {0}")]
+    DuplicateExports(Cow<'class, JavaStr>),
+    #[error("package {package} is exported to module {module} more than once")]
+    DuplicateExportsTarget {
+        package: Cow<'class, JavaStr>,
+        module: Cow<'class, JavaStr>,
+    },
+    #[error("duplicate opens of package {0}")]
+    DuplicateOpens(Cow<'class, JavaStr>),
+    #[error("package {package} is opened to module {module} more than once")]
+    DuplicateOpensTarget {
+        package: Cow<'class, JavaStr>,
+        module: Cow<'class, JavaStr>,
+    },
+    #[error("duplicate provides of service {0}")]
+    DuplicateProvides(Cow<'class, JavaStr>),
+    #[error("service {service} lists provider {provider} more than once")]
+    DuplicateProvider {
+        service: Cow<'class, JavaStr>,
+        provider: Cow<'class, JavaStr>,
+    },
+    #[error("duplicate requires of module {0}")]
+    DuplicateRequires(Cow<'class, JavaStr>),
+    #[error("duplicate uses of service {0}")]
+    DuplicateUses(Cow<'class, JavaStr>),
+    #[error("service {0} is provided with no providers")]
+    EmptyProviders(Cow<'class, JavaStr>),
+    #[error("invalid package name: {0}")]
+    InvalidPackageName(Cow<'class, JavaStr>),
+    #[error("open module cannot open package {0}")]
+    OpensInOpenModule(Cow<'class, JavaStr>),
+    #[error("provider {provider} of service {service} is not in the module")]
+    ProviderNotInModule {
+        service: Cow<'class, JavaStr>,
+        provider: Cow<'class, JavaStr>,
+    },
+}
+
+/// Checks the module descriptor for the semantic errors which the JVM would otherwise only report
+/// as a `ClassFormatError` when the module is loaded. Provider classes can only be checked against
+/// the module's packages if the `ModulePackages` attribute is present.
+pub fn validate_module<'class, E, P>(
+    module: ClassModuleEvent<'class, E>,
+) -> ClassFileResult<Vec<ModuleValidationError<'class>>>
+where
+    E: IntoIterator<Item = ClassFileResult<ModuleEvent<'class, P>>>,
+    P: ModuleEventProviders<'class>,
+{
+    let mut errors = Vec::new();
+
+    let mut packages = None;
+    let mut requires = HashSet::new();
+    let mut exports = Vec::new();
+    let mut opens = Vec::new();
+    let mut uses = HashSet::new();
+    let mut provides = Vec::new();
+
+    for event in module.events {
+        match event? {
+            ModuleEvent::Packages(events) => {
+                let mut package_set = HashSet::new();
+                for package in events {
+                    let package = package?;
+                    if !is_valid_package_name(&package) {
+                        errors.push(ModuleValidationError::InvalidPackageName(package.clone()));
+                    }
+                    package_set.insert(package);
+                }
+                packages = Some(package_set);
+            }
+            ModuleEvent::Requires(events) => {
+                for require in events {
+                    let require = require?;
+                    if !requires.insert(require.module.clone()) {
+                        errors.push(ModuleValidationError::DuplicateRequires(require.module));
+                    }
+                }
+            }
+            ModuleEvent::Exports(events) => {
+                for export in events {
+                    exports.push(export?);
+                }
+            }
+            ModuleEvent::Opens(events) => {
+                for open in events {
+                    opens.push(open?);
+                }
+            }
+            ModuleEvent::Uses(events) => {
+                for service in events {
+                    let service = service?;
+                    if !uses.insert(service.clone()) {
+                        errors.push(ModuleValidationError::DuplicateUses(service));
+                    }
+                }
+            }
+            ModuleEvent::Provides(events) => {
+                for provide in events {
+                    provides.push(provide?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    validate_relations(
+        exports,
+        &mut errors,
+        ModuleValidationError::DuplicateExports,
+        |package, module| ModuleValidationError::DuplicateExportsTarget { package, module },
+    );
+
+    if module.access.contains(ModuleAccess::Open) {
+        for open in &opens {
+            errors.push(ModuleValidationError::OpensInOpenModule(
+                open.package.clone(),
+            ));
+        }
+    }
+    validate_relations(
+        opens,
+        &mut errors,
+        ModuleValidationError::DuplicateOpens,
+        |package, module| ModuleValidationError::DuplicateOpensTarget { package, module },
+    );
+
+    validate_provides(provides, packages.as_ref(), &mut errors);
+
+    Ok(errors)
+}
+
+fn validate_relations<'class>(
+    relations: Vec<ModuleRelationEvent<'class>>,
+    errors: &mut Vec<ModuleValidationError<'class>>,
+    duplicate_package: impl Fn(Cow<'class, JavaStr>) -> ModuleValidationError<'class>,
+    duplicate_target: impl Fn(
+        Cow<'class, JavaStr>,
+        Cow<'class, JavaStr>,
+    ) -> ModuleValidationError<'class>,
+) {
+    let mut seen_packages = HashSet::new();
+    for relation in relations {
+        if !is_valid_package_name(&relation.package) {
+            errors.push(ModuleValidationError::InvalidPackageName(
+                relation.package.clone(),
+            ));
+        }
+        if !seen_packages.insert(relation.package.clone()) {
+            errors.push(duplicate_package(relation.package.clone()));
+        }
+
+        let mut seen_modules = HashSet::new();
+        for module in relation.modules {
+            if !seen_modules.insert(module.clone()) {
+                errors.push(duplicate_target(relation.package.clone(), module));
+            }
+        }
+    }
+}
+
+fn validate_provides<'class>(
+    provides: Vec<ModuleProvidesEvent<'class>>,
+    packages: Option<&HashSet<Cow<'class, JavaStr>>>,
+    errors: &mut Vec<ModuleValidationError<'class>>,
+) {
+    let mut seen_services = HashSet::new();
+    for provide in provides {
+        if !seen_services.insert(provide.service.clone()) {
+            errors.push(ModuleValidationError::DuplicateProvides(
+                provide.service.clone(),
+            ));
+        }
+        if provide.providers.is_empty() {
+            errors.push(ModuleValidationError::EmptyProviders(
+                provide.service.clone(),
+            ));
+        }
+
+        let mut seen_providers = HashSet::new();
+        for provider in provide.providers {
+            if !seen_providers.insert(provider.clone()) {
+                errors.push(ModuleValidationError::DuplicateProvider {
+                    service: provide.service.clone(),
+                    provider: provider.clone(),
+                });
+            }
+            if let Some(packages) = packages {
+                let in_module = match provider.rsplit_once('/') {
+                    Some((package, _)) => packages.contains(package),
+                    None => false,
+                };
+                if !in_module {
+                    errors.push(ModuleValidationError::ProviderNotInModule {
+                        service: provide.service.clone(),
+                        provider,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn is_valid_package_name(name: &JavaStr) -> bool {
+    is_valid_internal_name(name.as_bytes())
+}
+
+pub(crate) fn is_valid_internal_name(name: &[u8]) -> bool {
+    !name.is_empty()
+        && name.split(|&b| b == b'/').all(|segment| {
+            !segment.is_empty() && !segment.iter().any(|&b| matches!(b, b'.' | b';' | b'['))
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum RecordValidationError<'class> {
+    #[error("accessor {name} has descriptor {actual}, expected {expected}")]
+    AccessorDescriptorMismatch {
+        name: Cow<'class, JavaStr>,
+        expected: JavaString,
+        actual: Cow<'class, JavaStr>,
+    },
+    #[error("accessor {name} has signature {actual:?}, expected {expected:?}")]
+    AccessorSignatureMismatch {
+        name: Cow<'class, JavaStr>,
+        expected: Option<JavaString>,
+        actual: Option<Cow<'class, JavaStr>>,
+    },
+    #[error("accessor {name} must be public and non-static, found {access:?}")]
+    BadAccessorAccess {
+        name: Cow<'class, JavaStr>,
+        access: MethodAccess,
+    },
+    #[error("field {name} must be private, final and non-static, found {access:?}")]
+    BadFieldAccess {
+        name: Cow<'class, JavaStr>,
+        access: FieldAccess,
+    },
+    #[error("field {name} has descriptor {actual}, expected {expected}")]
+    FieldDescriptorMismatch {
+        name: Cow<'class, JavaStr>,
+        expected: Cow<'class, JavaStr>,
+        actual: Cow<'class, JavaStr>,
+    },
+    #[error("field {name} has signature {actual:?}, expected {expected:?}")]
+    FieldSignatureMismatch {
+        name: Cow<'class, JavaStr>,
+        expected: Option<Cow<'class, JavaStr>>,
+        actual: Option<Cow<'class, JavaStr>>,
+    },
+    #[error("missing accessor for record component {0}")]
+    MissingAccessor(Cow<'class, JavaStr>),
+    #[error("missing field for record component {0}")]
+    MissingField(Cow<'class, JavaStr>),
+    #[error("instance field {0} does not correspond to a record component")]
+    UnexpectedInstanceField(Cow<'class, JavaStr>),
+}
+
+struct RecordMember<'class, A> {
+    access: A,
+    name: Cow<'class, JavaStr>,
+    desc: Cow<'class, JavaStr>,
+    signature: Option<Cow<'class, JavaStr>>,
+}
+
+/// Checks that each record component has a matching field and accessor method, as required by
+/// `java.lang.Record` and the reflection APIs. Classes without a `Record` attribute are not
+/// checked.
+pub fn validate_record<'class, S>(source: S) -> ClassFileResult<Vec<RecordValidationError<'class>>>
+where
+    S: ClassEventSource<'class>,
+{
+    let mut components = None;
+    let mut fields = Vec::new();
+    let mut methods = Vec::new();
+
+    for event in source.events()? {
+        match event? {
+            ClassEvent::Record(events) => {
+                let mut component_list = Vec::new();
+                for component in events {
+                    let component = component?;
+                    component_list.push(RecordMember {
+                        access: (),
+                        name: component.name,
+                        desc: component.desc,
+                        signature: component.signature,
+                    });
+                }
+                components = Some(component_list);
+            }
+            ClassEvent::Fields(events) => {
+                for field in events {
+                    let field = field?;
+                    fields.push(RecordMember {
+                        access: field.access,
+                        name: field.name,
+                        desc: field.desc,
+                        signature: field.signature,
+                    });
+                }
+            }
+            ClassEvent::Methods(events) => {
+                for method in events {
+                    let method = method?;
+                    methods.push(RecordMember {
+                        access: method.access,
+                        name: method.name,
+                        desc: method.desc,
+                        signature: method.signature,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(components) = components else {
+        return Ok(Vec::new());
+    };
+
+    let mut errors = Vec::new();
+
+    for component in &components {
+        let field = fields
+            .iter()
+            .filter(|field| field.name == component.name)
+            .find(|field| {
+                field.desc == component.desc || !field.access.contains(FieldAccess::Static)
+            });
+        match field {
+            Some(field) => {
+                if field.access & (FieldAccess::Private | FieldAccess::Final | FieldAccess::Static)
+                    != FieldAccess::Private | FieldAccess::Final
+                {
+                    errors.push(RecordValidationError::BadFieldAccess {
+                        name: field.name.clone(),
+                        access: field.access,
+                    });
+                }
+                if field.desc != component.desc {
+                    errors.push(RecordValidationError::FieldDescriptorMismatch {
+                        name: field.name.clone(),
+                        expected: component.desc.clone(),
+                        actual: field.desc.clone(),
+                    });
+                }
+                if field.signature != component.signature {
+                    errors.push(RecordValidationError::FieldSignatureMismatch {
+                        name: field.name.clone(),
+                        expected: component.signature.clone(),
+                        actual: field.signature.clone(),
+                    });
+                }
+            }
+            None => errors.push(RecordValidationError::MissingField(component.name.clone())),
+        }
+
+        let expected_desc = accessor_type(&component.desc);
+        let accessor = methods
+            .iter()
+            .filter(|method| method.name == component.name && method.desc.starts_with("()"))
+            .find(|method| {
+                method.desc == expected_desc || !method.access.contains(MethodAccess::Static)
+            });
+        match accessor {
+            Some(accessor) => {
+                if accessor.access & (MethodAccess::Public | MethodAccess::Static)
+                    != MethodAccess::Public
+                {
+                    errors.push(RecordValidationError::BadAccessorAccess {
+                        name: accessor.name.clone(),
+                        access: accessor.access,
+                    });
+                }
+                if accessor.desc != expected_desc {
+                    errors.push(RecordValidationError::AccessorDescriptorMismatch {
+                        name: accessor.name.clone(),
+                        expected: expected_desc,
+                        actual: accessor.desc.clone(),
+                    });
+                }
+                let expected_signature = component.signature.as_deref().map(accessor_type);
+                if accessor.signature.as_deref() != expected_signature.as_deref() {
+                    errors.push(RecordValidationError::AccessorSignatureMismatch {
+                        name: accessor.name.clone(),
+                        expected: expected_signature,
+                        actual: accessor.signature.clone(),
+                    });
+                }
+            }
+            None => errors.push(RecordValidationError::MissingAccessor(
+                component.name.clone(),
+            )),
+        }
+    }
+
+    for field in &fields {
+        if !field.access.contains(FieldAccess::Static)
+            && !components
+                .iter()
+                .any(|component| component.name == field.name)
+        {
+            errors.push(RecordValidationError::UnexpectedInstanceField(
+                field.name.clone(),
+            ));
+        }
+    }
+
+    Ok(errors)
+}
+
+fn accessor_type(component_type: &JavaStr) -> JavaString {
+    let mut result = JavaString::from("()");
+    result.push_java_str(component_type);
+    result
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ClassCheckError {
+    #[error("invalid class access flags: {0:?}")]
+    BadClassAccess(ClassAccess),
+    #[error("invalid code in method {name}{desc} at instruction {index}: {error}")]
+    BadCode {
+        name: JavaString,
+        desc: JavaString,
+        index: usize,
+        error: CodeCheckError,
+    },
+    #[error("invalid access flags of field {name}: {access:?}")]
+    BadFieldAccess {
+        name: JavaString,
+        access: FieldAccess,
+    },
+    #[error("invalid descriptor of field {name}: {desc}")]
+    BadFieldDescriptor { name: JavaString, desc: JavaString },
+    #[error("invalid field name: {0}")]
+    BadFieldName(JavaString),
+    #[error("invalid internal name: {0}")]
+    BadInternalName(JavaString),
+    #[error("invalid access flags of method {name}{desc}: {access:?}")]
+    BadMethodAccess {
+        name: JavaString,
+        desc: JavaString,
+        access: MethodAccess,
+    },
+    #[error("invalid descriptor of method {name}: {desc}")]
+    BadMethodDescriptor { name: JavaString, desc: JavaString },
+    #[error("invalid method name: {0}")]
+    BadMethodName(JavaString),
+    #[error("duplicate field {name} {desc}")]
+    DuplicateField { name: JavaString, desc: JavaString },
+    #[error("duplicate method {name}{desc}")]
+    DuplicateMethod { name: JavaString, desc: JavaString },
+    #[error("class {0} has no superclass")]
+    MissingSuperclass(JavaString),
+}
+
+/// An error in the code of a method, see [`ClassCheckError::BadCode`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum CodeCheckError {
+    #[error("invalid descriptor: {0}")]
+    BadDescriptor(JavaString),
+    #[error("invalid number of dimensions: {0}")]
+    BadDimensions(u8),
+    #[error("invalid internal name: {0}")]
+    BadInternalName(JavaString),
+    #[error("invalid member name: {0}")]
+    BadMemberName(JavaString),
+    #[error("opcode {0} is not valid for this instruction")]
+    BadOpcode(Opcode),
+    #[error("tableswitch has {actual} labels, expected {expected}")]
+    BadSwitchLabelCount { expected: i64, actual: usize },
+    #[error("label {0} is visited more than once")]
+    DuplicateLabel(Label),
+    #[error("local {var_index} of size {size} is out of bounds, max locals is {max_locals}")]
+    LocalOutOfBounds {
+        var_index: u16,
+        size: u16,
+        max_locals: u16,
+    },
+    #[error("frames must be followed by an instruction, with at most one frame per instruction")]
+    MisplacedFrame,
+    #[error("label {0} is used but never visited")]
+    UnvisitedLabel(Label),
+}
+
+/// Reads a class, checking that it is structurally valid, so that mistakes in a transformation are
+/// reported with a descriptive error rather than by the JVM when the class is loaded. This checks
+/// the combinations of access flags, the names and descriptors of the class and its members, that
+/// there is a single class event, that the attributes and instructions used are supported by the
+/// class version, and that there are no duplicate members.
+///
+/// The code of each method is also checked, for instructions with opcodes and operands which are
+/// inconsistent, labels which are used but never visited, locals beyond the max locals, and frames
+/// which aren't followed by an instruction. The types of values aren't checked, which needs an
+/// [`Analyzer`](crate::analysis::Analyzer).
+pub fn check_class<'class, S>(source: S) -> ClassFileResult<BufferedClassEvents<'class>>
+where
+    S: ClassEventSource<'class>,
+{
+    let events = buffer_class_events(source)?;
+    let Some(ClassEvent::Class(class)) = events.0.first() else {
+        return Err(ClassFileError::MissingClassEvent);
+    };
+
+    let is_module = class.access.contains(ClassAccess::Module);
+    let is_interface = class.access.contains(ClassAccess::Interface);
+    if !is_valid_class_access(class.access) {
+        return Err(ClassCheckError::BadClassAccess(class.access).into());
+    }
+    for name in std::iter::once(&class.name)
+        .chain(&class.super_name)
+        .chain(&class.interfaces)
+    {
+        if !is_valid_internal_name(name.as_bytes()) {
+            return Err(ClassCheckError::BadInternalName(name.clone().into_owned()).into());
+        }
+    }
+    if class.super_name.is_none() && !is_module && class.name.as_ref() != "java/lang/Object" {
+        return Err(ClassCheckError::MissingSuperclass(class.name.clone().into_owned()).into());
+    }
+
+    let mut fields = HashSet::new();
+    let mut methods = HashSet::new();
+    for event in &events.0[1..] {
+        match event {
+            ClassEvent::Class(_) => return Err(ClassFileError::DuplicateClassEvent),
+            ClassEvent::Fields(events) => {
+                for field in &events.0 {
+                    if !is_valid_unqualified_name(field.name.as_bytes()) {
+                        return Err(
+                            ClassCheckError::BadFieldName(field.name.clone().into_owned()).into(),
+                        );
+                    }
+                    if field_desc_len(field.desc.as_bytes()) != Some(field.desc.len()) {
+                        return Err(ClassCheckError::BadFieldDescriptor {
+                            name: field.name.clone().into_owned(),
+                            desc: field.desc.clone().into_owned(),
+                        }
+                        .into());
+                    }
+                    if !is_valid_field_access(field.access, is_interface) {
+                        return Err(ClassCheckError::BadFieldAccess {
+                            name: field.name.clone().into_owned(),
+                            access: field.access,
+                        }
+                        .into());
+                    }
+                    if !fields.insert((&field.name, &field.desc)) {
+                        return Err(ClassCheckError::DuplicateField {
+                            name: field.name.clone().into_owned(),
+                            desc: field.desc.clone().into_owned(),
+                        }
+                        .into());
+                    }
+                }
+            }
+            ClassEvent::Methods(events) => {
+                for method in &events.0 {
+                    if !is_valid_method_name(&method.name) {
+                        return Err(ClassCheckError::BadMethodName(
+                            method.name.clone().into_owned(),
+                        )
+                        .into());
+                    }
+                    if !is_valid_method_desc(method.desc.as_bytes()) {
+                        return Err(ClassCheckError::BadMethodDescriptor {
+                            name: method.name.clone().into_owned(),
+                            desc: method.desc.clone().into_owned(),
+                        }
+                        .into());
+                    }
+                    if !is_valid_method_access(
+                        method.access,
+                        &method.name,
+                        is_interface,
+                        class.major_version,
+                    ) {
+                        return Err(ClassCheckError::BadMethodAccess {
+                            name: method.name.clone().into_owned(),
+                            desc: method.desc.clone().into_owned(),
+                            access: method.access,
+                        }
+                        .into());
+                    }
+                    if !methods.insert((&method.name, &method.desc)) {
+                        return Err(ClassCheckError::DuplicateMethod {
+                            name: method.name.clone().into_owned(),
+                            desc: method.desc.clone().into_owned(),
+                        }
+                        .into());
+                    }
+                    if let Err((index, error)) = check_code(&method.events.0) {
+                        return Err(ClassCheckError::BadCode {
+                            name: method.name.clone().into_owned(),
+                            desc: method.desc.clone().into_owned(),
+                            index,
+                            error,
+                        }
+                        .into());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut info = ClassVersionInfo {
+        name: class.name.clone().into_owned(),
+        major_version: 0,
+        minor_version: 0,
+        constructs: BTreeSet::new(),
+    };
+    find_constructs(events.clone(), &mut info)?;
+    if let Some(&construct) = info
+        .constructs
+        .iter()
+        .find(|construct| construct.required_version() > info.major_version)
+    {
+        return Err(ClassFileError::VersionTooOld {
+            construct,
+            major_version: info.major_version,
+        });
+    }
+
+    Ok(events)
+}
+
+/// Checks the code of a method, returning the index of the offending instruction with the error.
+fn check_code<'class>(
+    events: &[MethodEvent<'class, BufferedEventProviders>],
+) -> Result<(), (usize, CodeCheckError)> {
+    if !events
+        .iter()
+        .any(|event| matches!(event, MethodEvent::Code { .. }))
+    {
+        return Ok(());
+    }
+    let max_locals = events.iter().find_map(|event| match event {
+        MethodEvent::Maxs(maxs) => Some(maxs.max_locals),
+        _ => None,
+    });
+
+    let mut insn_index = 0;
+    let mut visited_labels = HashSet::new();
+    let mut used_labels = Vec::new();
+    let mut frame_pending = false;
+    for event in events {
+        match event {
+            MethodEvent::Label(label) => {
+                if !visited_labels.insert(*label) {
+                    return Err((insn_index, CodeCheckError::DuplicateLabel(*label)));
+                }
+            }
+            MethodEvent::Frame(_) => {
+                if frame_pending {
+                    return Err((insn_index, CodeCheckError::MisplacedFrame));
+                }
+                frame_pending = true;
+            }
+            MethodEvent::LineNumber { start, .. } => used_labels.push((insn_index, *start)),
+            MethodEvent::LocalVariables(locals) => {
+                for local in &locals.0 {
+                    used_labels.push((insn_index, local.start));
+                    used_labels.push((insn_index, local.end));
+                }
+            }
+            MethodEvent::TryCatchBlocks(blocks) => {
+                for block in &blocks.0 {
+                    used_labels.push((insn_index, block.start));
+                    used_labels.push((insn_index, block.end));
+                    used_labels.push((insn_index, block.handler));
+                }
+            }
+            event => {
+                let Some((_, flow)) = insn_flow(event) else {
+                    continue;
+                };
+                check_insn(event).map_err(|error| (insn_index, error))?;
+                if let (Some(max_locals), Some((var_index, size))) = (max_locals, local_use(event))
+                {
+                    if u32::from(var_index) + u32::from(size) > u32::from(max_locals) {
+                        return Err((
+                            insn_index,
+                            CodeCheckError::LocalOutOfBounds {
+                                var_index,
+                                size,
+                                max_locals,
+                            },
+                        ));
+                    }
+                }
+                match flow {
+                    InsnFlowKind::Next | InsnFlowKind::End => {}
+                    InsnFlowKind::Branch(label)
+                    | InsnFlowKind::Goto(label)
+                    | InsnFlowKind::Jsr(label) => used_labels.push((insn_index, label)),
+                    InsnFlowKind::Switch(labels) => {
+                        used_labels.extend(labels.into_iter().map(|label| (insn_index, label)))
+                    }
+                }
+                frame_pending = false;
+                insn_index += 1;
+            }
+        }
+    }
+
+    if frame_pending {
+        return Err((insn_index, CodeCheckError::MisplacedFrame));
+    }
+    for (index, label) in used_labels {
+        if !visited_labels.contains(&label) {
+            return Err((index, CodeCheckError::UnvisitedLabel(label)));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the opcode and operands of an instruction are consistent with each other.
+fn check_insn<'class>(
+    event: &MethodEvent<'class, BufferedEventProviders>,
+) -> Result<(), CodeCheckError> {
+    let (opcode, valid_opcode) = match event {
+        MethodEvent::Insn(opcode) => (*opcode, has_no_operands(*opcode)),
+        MethodEvent::VarInsn { opcode, .. } => (
+            *opcode,
+            matches!(*opcode as u8, 21..=25 | 54..=58) || *opcode == Opcode::Ret,
+        ),
+        MethodEvent::TypeInsn { opcode, .. } => (
+            *opcode,
+            matches!(
+                opcode,
+                Opcode::New | Opcode::ANewArray | Opcode::CheckCast | Opcode::Instanceof
+            ),
+        ),
+        MethodEvent::FieldInsn { opcode, .. } => (
+            *opcode,
+            matches!(
+                opcode,
+                Opcode::GetStatic | Opcode::PutStatic | Opcode::GetField | Opcode::PutField
+            ),
+        ),
+        MethodEvent::MethodInsn { opcode, .. } => (
+            *opcode,
+            matches!(
+                opcode,
+                Opcode::InvokeVirtual
+                    | Opcode::InvokeSpecial
+                    | Opcode::InvokeStatic
+                    | Opcode::InvokeInterface
+            ),
+        ),
+        MethodEvent::JumpInsn { opcode, .. } => (
+            *opcode,
+            matches!(*opcode as u8, 153..=168)
+                || matches!(opcode, Opcode::IfNull | Opcode::IfNonNull),
+        ),
+        _ => (Opcode::Nop, true),
+    };
+    if !valid_opcode {
+        return Err(CodeCheckError::BadOpcode(opcode));
+    }
+
+    match event {
+        MethodEvent::TypeInsn { opcode, ty } => {
+            let valid = if *opcode == Opcode::New {
+                is_valid_internal_name(ty.as_bytes())
+            } else {
+                is_valid_class_operand(ty.as_bytes())
+            };
+            if !valid {
+                return Err(CodeCheckError::BadInternalName(ty.clone().into_owned()));
+            }
+        }
+        MethodEvent::FieldInsn {
+            owner, name, desc, ..
+        } => {
+            check_owner(owner, is_valid_internal_name(owner.as_bytes()))?;
+            if !is_valid_unqualified_name(name.as_bytes()) {
+                return Err(CodeCheckError::BadMemberName(name.clone().into_owned()));
+            }
+            check_field_desc(desc)?;
+        }
+        MethodEvent::MethodInsn {
+            opcode,
+            owner,
+            name,
+            desc,
+            ..
+        } => {
+            check_owner(owner, is_valid_class_operand(owner.as_bytes()))?;
+            if !is_valid_method_name(name)
+                || name.as_ref() == "<clinit>"
+                || (name.as_ref() == "<init>" && *opcode != Opcode::InvokeSpecial)
+            {
+                return Err(CodeCheckError::BadMemberName(name.clone().into_owned()));
+            }
+            check_method_desc(desc)?;
+        }
+        MethodEvent::InvokeDynamicInsn { name, desc, .. } => {
+            if !is_valid_unqualified_name(name.as_bytes()) {
+                return Err(CodeCheckError::BadMemberName(name.clone().into_owned()));
+            }
+            check_method_desc(desc)?;
+        }
+        MethodEvent::LdcInsn(constant) => match constant {
+            LdcConstant::Class(class) => {
+                check_owner(class, is_valid_class_operand(class.as_bytes()))?
+            }
+            LdcConstant::MethodType(desc) => check_method_desc(desc)?,
+            LdcConstant::ConstantDynamic(constant) => check_field_desc(&constant.desc)?,
+            _ => {}
+        },
+        MethodEvent::TableSwitchInsn {
+            low, high, labels, ..
+        } => {
+            let expected = i64::from(*high) - i64::from(*low) + 1;
+            if expected != labels.len() as i64 {
+                return Err(CodeCheckError::BadSwitchLabelCount {
+                    expected,
+                    actual: labels.len(),
+                });
+            }
+        }
+        MethodEvent::MultiANewArrayInsn { desc, dimensions } => {
+            check_field_desc(desc)?;
+            let array_dimensions = desc.as_bytes().iter().take_while(|&&b| b == b'[').count();
+            if *dimensions == 0 || usize::from(*dimensions) > array_dimensions {
+                return Err(CodeCheckError::BadDimensions(*dimensions));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub(crate) fn has_no_operands(opcode: Opcode) -> bool {
+    matches!(
+        opcode as u8,
+        0..=15 | 46..=53 | 79..=131 | 133..=152 | 172..=177 | 190 | 191 | 194 | 195
+    )
+}
+
+/// Whether a name is a valid operand of an instruction which takes a class, which may be an array
+/// type.
+fn is_valid_class_operand(name: &[u8]) -> bool {
+    if name.first() == Some(&b'[') {
+        field_desc_len(name) == Some(name.len())
+    } else {
+        is_valid_internal_name(name)
+    }
+}
+
+fn check_owner(owner: &JavaStr, valid: bool) -> Result<(), CodeCheckError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(CodeCheckError::BadInternalName(owner.to_owned()))
+    }
+}
+
+fn check_field_desc(desc: &JavaStr) -> Result<(), CodeCheckError> {
+    if field_desc_len(desc.as_bytes()) == Some(desc.len()) {
+        Ok(())
+    } else {
+        Err(CodeCheckError::BadDescriptor(desc.to_owned()))
+    }
+}
+
+fn check_method_desc(desc: &JavaStr) -> Result<(), CodeCheckError> {
+    if is_valid_method_desc(desc.as_bytes()) {
+        Ok(())
+    } else {
+        Err(CodeCheckError::BadDescriptor(desc.to_owned()))
+    }
+}
+
+fn is_valid_class_access(access: ClassAccess) -> bool {
+    if access.contains(ClassAccess::Module) {
+        access == ClassAccess::Module
+    } else if access.contains(ClassAccess::Interface) {
+        access.contains(ClassAccess::Abstract)
+            && !access.intersects(ClassAccess::Final | ClassAccess::Super | ClassAccess::Enum)
+    } else {
+        !access.contains(ClassAccess::Annotation)
+            && !access.contains(ClassAccess::Final | ClassAccess::Abstract)
+    }
+}
+
+fn is_valid_field_access(access: FieldAccess, is_interface: bool) -> bool {
+    let visibility = access & (FieldAccess::Public | FieldAccess::Private | FieldAccess::Protected);
+    if visibility.bits().count_ones() > 1
+        || access.contains(FieldAccess::Final | FieldAccess::Volatile)
+    {
+        return false;
+    }
+    !is_interface
+        || access - FieldAccess::Synthetic
+            == FieldAccess::Public | FieldAccess::Static | FieldAccess::Final
+}
+
+fn is_valid_method_access(
+    access: MethodAccess,
+    name: &JavaStr,
+    is_interface: bool,
+    major_version: u16,
+) -> bool {
+    let visibility =
+        access & (MethodAccess::Public | MethodAccess::Private | MethodAccess::Protected);
+    if visibility.bits().count_ones() > 1 {
+        return false;
+    }
+    // the JVM ignores the other flags of static initializers
+    if name == "<clinit>" {
+        return true;
+    }
+    if access.contains(MethodAccess::Abstract)
+        && access.intersects(
+            MethodAccess::Private
+                | MethodAccess::Static
+                | MethodAccess::Final
+                | MethodAccess::Synchronized
+                | MethodAccess::Native,
+        )
+    {
+        return false;
+    }
+    if name == "<init>"
+        && access.intersects(
+            MethodAccess::Static
+                | MethodAccess::Final
+                | MethodAccess::Synchronized
+                | MethodAccess::Bridge
+                | MethodAccess::Native
+                | MethodAccess::Abstract,
+        )
+    {
+        return false;
+    }
+    if !is_interface {
+        return true;
+    }
+    if major_version < JAVA_8_VERSION {
+        access.contains(MethodAccess::Public | MethodAccess::Abstract)
+    } else {
+        !visibility.is_empty()
+            && !access.intersects(
+                MethodAccess::Protected
+                    | MethodAccess::Final
+                    | MethodAccess::Synchronized
+                    | MethodAccess::Native,
+            )
+    }
+}
+
+fn is_valid_unqualified_name(name: &[u8]) -> bool {
+    !name.is_empty() && !name.iter().any(|&b| matches!(b, b'.' | b';' | b'[' | b'/'))
+}
+
+fn is_valid_method_name(name: &JavaStr) -> bool {
+    name == "<init>"
+        || name == "<clinit>"
+        || (is_valid_unqualified_name(name.as_bytes())
+            && !name.as_bytes().iter().any(|&b| matches!(b, b'<' | b'>')))
+}
+
+/// The length of the field descriptor at the start of `desc`, or `None` if it doesn't start with
+/// a valid field descriptor.
+pub(crate) fn field_desc_len(desc: &[u8]) -> Option<usize> {
+    let dimensions = desc.iter().take_while(|&&b| b == b'[').count();
+    if dimensions > 255 {
+        return None;
+    }
+    match desc.get(dimensions)? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => Some(dimensions + 1),
+        b'L' => {
+            let name_start = dimensions + 1;
+            let name_len = desc[name_start..].iter().position(|&b| b == b';')?;
+            is_valid_internal_name(&desc[name_start..name_start + name_len])
+                .then_some(name_start + name_len + 1)
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn is_valid_method_desc(desc: &[u8]) -> bool {
+    let Some(mut desc) = desc.strip_prefix(b"(") else {
+        return false;
+    };
+    while desc.first() != Some(&b')') {
+        let Some(len) = field_desc_len(desc) else {
+            return false;
+        };
+        desc = &desc[len..];
+    }
+    let return_type = &desc[1..];
+    return_type == b"V" || field_desc_len(return_type) == Some(return_type.len())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{ClassNode, FieldNode, RecordComponentNode};
+    use crate::{
+        buffer_class_events, check_class, validate_module, validate_record, ClassAccess,
+        ClassCheckError, ClassEvent, ClassEventSource, ClassFileError, ClassFileResult,
+        ClassModuleEvent, ClassReader, ClassReaderFlags, CodeCheckError, FieldAccess, Label,
+        MethodAccess, MethodEvent, ModuleAccess, ModuleEvent, ModuleEventProviders,
+        ModuleProvidesEvent, ModuleRelationAccess, ModuleRelationEvent, ModuleRequireAccess,
+        ModuleRequireEvent, ModuleValidationError, Opcode, RecordValidationError,
+    };
+    use java_string::{JavaStr, JavaString};
+    use std::borrow::Cow;
+    use test_helpers::include_class;
+
+    struct VecModuleEventProviders;
+
+    impl<'class> ModuleEventProviders<'class> for VecModuleEventProviders {
+        type Packages = Vec<ClassFileResult<Cow<'class, JavaStr>>>;
+        type Requires = Vec<ClassFileResult<ModuleRequireEvent<'class>>>;
+        type Exports = Vec<ClassFileResult<ModuleRelationEvent<'class>>>;
+        type Opens = Vec<ClassFileResult<ModuleRelationEvent<'class>>>;
+        type Uses = Vec<ClassFileResult<Cow<'class, JavaStr>>>;
+        type Provides = Vec<ClassFileResult<ModuleProvidesEvent<'class>>>;
+    }
+
+    fn str(s: &str) -> Cow<'_, JavaStr> {
+        JavaStr::from_str(s).into()
+    }
+
+    fn relation(
+        package: &'static str,
+        modules: &[&'static str],
+    ) -> ClassFileResult<ModuleRelationEvent<'static>> {
+        Ok(ModuleRelationEvent {
+            package: str(package),
+            access: ModuleRelationAccess::empty(),
+            modules: modules.iter().map(|module| str(module)).collect(),
+        })
+    }
+
+    #[test]
+    fn test_valid_module() {
+        const BYTECODE: &[u8] = include_class!("module-info");
+        let reader = ClassReader::new(BYTECODE, ClassReaderFlags::None).unwrap();
+        let module = reader
+            .events()
+            .unwrap()
+            .find_map(|event| match event.unwrap() {
+                ClassEvent::Module(module) => Some(module),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            Vec::<ModuleValidationError>::new(),
+            validate_module(module).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_module() {
+        let events: Vec<ClassFileResult<ModuleEvent<VecModuleEventProviders>>> = vec![
+            Ok(ModuleEvent::Packages(vec![
+                Ok(str("pkg")),
+                Ok(str("bad.pkg")),
+            ])),
+            Ok(ModuleEvent::Requires(vec![
+                Ok(ModuleRequireEvent {
+                    module: str("java.base"),
+                    access: ModuleRequireAccess::empty(),
+                    version: None,
+                }),
+                Ok(ModuleRequireEvent {
+                    module: str("java.base"),
+                    access: ModuleRequireAccess::empty(),
+                    version: None,
+                }),
+            ])),
+            Ok(ModuleEvent::Exports(vec![
+                relation("pkg", &["a", "a"]),
+                relation("pkg", &[]),
+            ])),
+            Ok(ModuleEvent::Opens(vec![relation("pkg", &[])])),
+            Ok(ModuleEvent::Provides(vec![Ok(ModuleProvidesEvent {
+                service: str("java/lang/Runnable"),
+                providers: vec![str("other/Provider")],
+            })])),
+        ];
+        let module = ClassModuleEvent {
+            name: str("test"),
+            access: ModuleAccess::Open,
+            version: None,
+            events,
+        };
+
+        assert_eq!(
+            vec![
+                ModuleValidationError::InvalidPackageName(str("bad.pkg")),
+                ModuleValidationError::DuplicateRequires(str("java.base")),
+                ModuleValidationError::DuplicateExportsTarget {
+                    package: str("pkg"),
+                    module: str("a"),
+                },
+                ModuleValidationError::DuplicateExports(str("pkg")),
+                ModuleValidationError::OpensInOpenModule(str("pkg")),
+                ModuleValidationError::ProviderNotInModule {
+                    service: str("java/lang/Runnable"),
+                    provider: str("other/Provider"),
+                },
+            ],
+            validate_module(module).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_valid_record() {
+        const BYTECODE: &[u8] = include_class!("TestRecord");
+        let reader = ClassReader::new(BYTECODE, ClassReaderFlags::None).unwrap();
+        assert_eq!(
+            Vec::<RecordValidationError>::new(),
+            validate_record(&reader).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_record() {
+        const BYTECODE: &[u8] = include_class!("TestRecord");
+        let reader = ClassReader::new(BYTECODE, ClassReaderFlags::None).unwrap();
+        let mut node = ClassNode::from_events(&reader).unwrap();
+        let field = |node: &ClassNode, name| {
+            let name = JavaStr::from_str(name);
+            node.fields
+                .iter()
+                .position(|field| field.name == name)
+                .unwrap()
+        };
+        let method = |node: &ClassNode, name| {
+            let name = JavaStr::from_str(name);
+            node.methods
+                .iter()
+                .position(|method| method.name == name)
+                .unwrap()
+        };
+
+        let x = field(&node, "x");
+        node.fields[x].access.remove(FieldAccess::Final);
+        let x = method(&node, "x");
+        node.methods.remove(x);
+
+        let names = field(&node, "names");
+        node.fields[names].desc = str("Ljava/util/Collection;");
+        node.fields[names].signature = None;
+        let names = method(&node, "names");
+        node.methods[names].access.remove(MethodAccess::Public);
+        node.methods[names].desc = str("()Ljava/util/Collection;");
+        node.methods[names].signature = None;
+
+        node.record_components
+            .as_mut()
+            .unwrap()
+            .push(RecordComponentNode::new(str("y"), str("J")));
+        node.fields.push(FieldNode::new(
+            FieldAccess::Private | FieldAccess::Final,
+            str("extra"),
+            str("I"),
+        ));
+
+        assert_eq!(
+            vec![
+                RecordValidationError::BadFieldAccess {
+                    name: str("x"),
+                    access: FieldAccess::Private,
+                },
+                RecordValidationError::MissingAccessor(str("x")),
+                RecordValidationError::FieldDescriptorMismatch {
+                    name: str("names"),
+                    expected: str("Ljava/util/List;"),
+                    actual: str("Ljava/util/Collection;"),
+                },
+                RecordValidationError::FieldSignatureMismatch {
+                    name: str("names"),
+                    expected: Some(str("Ljava/util/List<Ljava/lang/String;>;")),
+                    actual: None,
+                },
+                RecordValidationError::BadAccessorAccess {
+                    name: str("names"),
+                    access: MethodAccess::empty(),
+                },
+                RecordValidationError::AccessorDescriptorMismatch {
+                    name: str("names"),
+                    expected: JavaString::from("()Ljava/util/List;"),
+                    actual: str("()Ljava/util/Collection;"),
+                },
+                RecordValidationError::AccessorSignatureMismatch {
+                    name: str("names"),
+                    expected: Some(JavaString::from("()Ljava/util/List<Ljava/lang/String;>;")),
+                    actual: None,
+                },
+                RecordValidationError::MissingField(str("y")),
+                RecordValidationError::MissingAccessor(str("y")),
+                RecordValidationError::UnexpectedInstanceField(str("extra")),
+            ],
+            validate_record(&node).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_check_valid_class() {
+        let classes: [&[u8]; 5] = [
+            include_class!("TestCode"),
+            include_class!("TestRecord"),
+            include_class!("TestAnnotations"),
+            include_class!("TestInterfaces"),
+            include_class!("module-info"),
+        ];
+        for class in classes {
+            let reader = ClassReader::new(class, ClassReaderFlags::None).unwrap();
+            check_class(&reader).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_check_invalid_class() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+
+        let mut events = buffer_class_events(&reader).unwrap();
+        let ClassEvent::Class(class) = &mut events.0[0] else {
+            panic!("expected class event");
+        };
+        class.access |= ClassAccess::Interface;
+        assert_eq!(
+            Err(ClassFileError::InvalidClass(
+                ClassCheckError::BadClassAccess(class.access)
+            )),
+            check_class(events).map(|_| ())
+        );
+
+        let mut events = buffer_class_events(&reader).unwrap();
+        for event in &mut events.0 {
+            if let ClassEvent::Methods(methods) = event {
+                methods.0[0].desc = str("(L;)V");
+            }
+        }
+        assert!(matches!(
+            check_class(events),
+            Err(ClassFileError::InvalidClass(
+                ClassCheckError::BadMethodDescriptor { .. }
+            ))
+        ));
+
+        let mut events = buffer_class_events(&reader).unwrap();
+        for event in &mut events.0 {
+            if let ClassEvent::Methods(methods) = event {
+                methods.0.push(methods.0[0].clone());
+            }
+        }
+        assert!(matches!(
+            check_class(events),
+            Err(ClassFileError::InvalidClass(
+                ClassCheckError::DuplicateMethod { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_check_invalid_code() {
+        let reader =
+            ClassReader::new(include_class!("HelloWorld"), ClassReaderFlags::None).unwrap();
+        let check_constructor = |insn| {
+            let mut events = buffer_class_events(&reader).unwrap();
+            let constructor = events
+                .0
+                .iter_mut()
+                .find_map(|event| match event {
+                    ClassEvent::Methods(methods) => Some(&mut methods.0[0].events.0),
+                    _ => None,
+                })
+                .unwrap();
+            let ret = constructor
+                .iter()
+                .position(|event| matches!(event, MethodEvent::Insn(_)))
+                .unwrap();
+            constructor.insert(ret, insn);
+            match check_class(events) {
+                Err(ClassFileError::InvalidClass(ClassCheckError::BadCode {
+                    index,
+                    error,
+                    ..
+                })) => {
+                    assert_eq!(2, index);
+                    error
+                }
+                result => panic!("expected invalid code, found {result:?}"),
+            }
+        };
+
+        let label = Label::synthetic(100);
+        assert_eq!(
+            CodeCheckError::UnvisitedLabel(label),
+            check_constructor(MethodEvent::JumpInsn {
+                opcode: Opcode::Goto,
+                label,
+            })
+        );
+        assert_eq!(
+            CodeCheckError::BadOpcode(Opcode::ALoad),
+            check_constructor(MethodEvent::Insn(Opcode::ALoad))
+        );
+        assert_eq!(
+            CodeCheckError::LocalOutOfBounds {
+                var_index: 1,
+                size: 2,
+                max_locals: 1,
+            },
+            check_constructor(MethodEvent::VarInsn {
+                opcode: Opcode::LLoad,
+                var_index: 1,
+            })
+        );
+    }
+}
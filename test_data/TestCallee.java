@@ -0,0 +1,9 @@
+public class TestCallee {
+    public static int value() {
+        return 42;
+    }
+
+    public int inst() {
+        return 1;
+    }
+}
@@ -0,0 +1,9 @@
+public class TestCaller {
+    public int call() {
+        return TestCallee.value() + new TestCallee().inst() + TestCaller.value();
+    }
+
+    public static int value() {
+        return 0;
+    }
+}
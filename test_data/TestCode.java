@@ -0,0 +1,56 @@
+public class TestCode {
+    private static final long BIG = 1L << 40;
+    private double total;
+
+    public int switches(int x, String s) {
+        int result;
+        switch (x) {
+            case 1:
+                result = 10;
+                break;
+            case 2:
+                result = 20;
+                break;
+            case 3:
+                result = 30;
+                break;
+            default:
+                result = 0;
+        }
+        switch (x) {
+            case -100:
+                result += 1;
+                break;
+            case 1000:
+                result += 2;
+                break;
+        }
+        return result + s.length();
+    }
+
+    public double loops(int[] values) {
+        double sum = 0;
+        for (int i = 0; i < values.length; i += 300) {
+            sum += values[i];
+        }
+        return sum * BIG;
+    }
+
+    public int tryCatch(Object o) {
+        try {
+            return ((String) o).length();
+        } catch (ClassCastException | NullPointerException e) {
+            return -1;
+        } finally {
+            total++;
+        }
+    }
+
+    public String concat(int x, String s) {
+        return s + ": " + x;
+    }
+
+    public Object[][] arrays(Object o) {
+        return o instanceof Runnable ? new Object[2][3] : new Object[0][];
+    }
+}
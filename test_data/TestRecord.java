@@ -0,0 +1,5 @@
+import java.util.List;
+
+public record TestRecord(@VisibleAnnotation(intValue = 1) int x, List<@VisibleTypeAnnotation String> names) {
+    static int counter;
+}
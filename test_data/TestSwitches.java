@@ -0,0 +1,32 @@
+public class TestSwitches {
+    enum Color {
+        RED, GREEN, BLUE
+    }
+
+    public int enumSwitch(Color color) {
+        switch (color) {
+            case BLUE:
+                return 1;
+            case RED:
+                return 2;
+            default:
+                return 0;
+        }
+    }
+
+    public int stringSwitch(String s) {
+        switch (s) {
+            case "foo":
+                return 1;
+            case "bar":
+                return 2;
+            // "Aa" and "BB" have the same hash code
+            case "Aa":
+                return 3;
+            case "BB":
+                return 4;
+            default:
+                return 0;
+        }
+    }
+}
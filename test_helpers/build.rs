@@ -34,6 +34,15 @@ fn main() {
         String::from_utf8_lossy(version)
     );
 
+    let java_home = javac
+        .canonicalize()
+        .expect("Could not resolve the path to javac");
+    let java_home = java_home
+        .parent()
+        .and_then(|bin| bin.parent())
+        .expect("javac should be in the bin directory of a JDK");
+    println!("cargo:rustc-env=JAVA_HOME_DIR={}", java_home.display());
+
     let input_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
         .parent()
         .unwrap()
@@ -19,3 +19,12 @@ pub fn java_version(_: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+#[proc_macro]
+pub fn java_home(_: TokenStream) -> TokenStream {
+    let java_home = env!("JAVA_HOME_DIR");
+    quote! {
+        #java_home
+    }
+    .into()
+}